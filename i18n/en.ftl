@@ -0,0 +1,702 @@
+# English messages of the UI. The Japanese ones in ja.ftl must have the same ids.
+
+## Main window
+
+choose-image-folder = Choose Image Folder
+add-folder = Add Folder
+add-folder-hint = Queue another folder to sort in the same session.
+open-archive = Open Archive
+open-archive-hint = Sort the images of a .zip or .cbz file. The archive itself is not changed.
+scanning = Scanning...
+already-scanning = Already reading the folders.
+images-gone = { $file } was deleted or moved outside the app. Dropped { $count } images that are gone.
+cancel = Cancel
+indexing = Indexing { $indexed }/{ $total }
+reading-dates = Reading dates...
+reading-sizes = Reading file sizes...
+reading-colors = Reading colors...
+check-images = Check Images
+check-images-hint = Decode every image to find damaged files.
+showing-only = Showing only: { $name } ({ $count ->
+        [one] { $count } image
+       *[other] { $count } images
+    })
+show-all = Show all
+filter-by-color = Only:
+dominant-color = Mostly { $color }
+hue-red = Red
+hue-orange = Orange
+hue-yellow = Yellow
+hue-green = Green
+hue-cyan = Cyan
+hue-blue = Blue
+hue-purple = Purple
+hue-pink = Pink
+hue-gray = Gray
+hidden-kept = { $count ->
+        [one] { $count } image kept in place before is hidden.
+       *[other] { $count } images kept in place before are hidden.
+    }
+show-them = Show them
+deferred = { $count ->
+        [one] { $count } image skipped for later. It comes after the last image.
+       *[other] { $count } images skipped for later. They come after the last image.
+    }
+selected-folder = Selected Folder:
+no-folder-selected = No folder selected.
+folders = Folders:
+remove-from-queue = Remove from the queue.
+remove-source = Remove { $folder } from the queue
+status = Status:
+settings = Settings
+tools = Tools
+merge-folders-menu = Merge folders...
+flatten-folders-menu = Flatten folders...
+organize-by-date-menu = Organize by date...
+locations-menu = Locations...
+find-blurry-menu = Find blurry images...
+find-screenshots-menu = Find screenshots...
+quarantine-menu = Quarantine problem files...
+discard-menu = Review discard folder...
+faces-menu = Group by faces...
+storage-menu = Storage usage...
+contact-sheet-menu = Contact sheet...
+sync-menu = Sync with mirror...
+move-log-menu = Export or replay the move log...
+audit-menu = Audit trail...
+log-menu = Log...
+wizard = Getting started
+wizard-welcome = Welcome
+wizard-welcome-text = This app sorts the images of a folder into other folders, one key press each. The next steps set up a folder to sort and a few destinations. The settings and tools are there to come back to later.
+wizard-source = The folder to sort
+wizard-source-text = Choose a folder of images to sort, e.g. your downloads or a phone export. Or try it first on a demo folder of generated images; your own files are not touched.
+wizard-demo = Try the demo folder
+wizard-demo-hint = Creates a folder of red, green and blue images in the temp directory, with a destination for each color.
+wizard-destinations = Destinations
+wizard-destinations-text = Each destination folder gets a key. Pressing it moves the image there. More can be added later under the image.
+wizard-keys = The keys
+wizard-keys-text = Look at an image, press the key of where it goes, and the next one is shown. Nothing is lost: Ctrl + Z puts the last image back.
+wizard-back = Back
+wizard-next = Next
+wizard-finish = Start sorting
+wizard-skip = Skip
+wizard-done = Ready. Press the key of a destination to move the image there.
+wizard-menu = Getting started...
+practice = Practice
+practice-menu = Practice with sample images
+practice-text = These are generated images in the temp directory. Nothing else is moved until you leave.
+practice-task-move = Move an image with R, G or B
+practice-task-undo = Undo the move with Ctrl + Z
+practice-task-filter = Give an image a color label with Ctrl + 1, then show only those with Ctrl + P
+practice-done = Well done. You know the basics.
+practice-reset = Start over
+practice-leave = Leave practice
+practice-outside = Only the practice folders can be used while practicing.
+exit-question = Quit while work is pending?
+exit-nothing-pending = Everything has finished since.
+exit-uploads = { $count } uploads that have not finished
+exit-retries = { $count } moves waiting to be retried. The images stay where they are.
+exit-batch = { $title }: { $done } of { $total } files done. The rest are left as they are.
+exit-revert = Reverting the session: { $done } of { $total } moves undone
+exit-confirmation = A move waiting for your answer
+exit-plan = { $count } planned moves that were not exported
+exit-keep-open = Keep open
+exit-quit = Quit anyway
+log-to-file = Keep a log file
+log-to-file-hint = Writes what the app does, e.g. where each image was moved, to a file in the config folder. The oldest part is removed as it grows.
+revert-session-menu = Revert all moves from this session...
+labels = Labels
+grid = Grid
+timeline = Timeline
+timeline-hint = The images grouped by the day or the event they were taken on
+timeline-days = { $days ->
+        [one] { $days } day
+       *[other] { $days } days
+    }
+timeline-jump = Jump to date
+timeline-unknown-date = Date unknown
+timeline-images = { $count ->
+        [one] { $count } image
+       *[other] { $count } images
+    }
+timeline-show-day = Show only these
+timeline-move-day = Move to...
+timeline-by-day = By day
+timeline-by-day-hint = One group for each day
+timeline-by-event = By event
+timeline-by-event-hint = A new group starts where more time than this passes between images
+timeline-event-gap = Gap:
+timeline-events = { $events ->
+        [one] { $events } event
+       *[other] { $events } events
+    }
+timeline-events-folder = New folders in:
+timeline-events-folder-unset = Choose a folder for the events first
+timeline-choose-folder = Choose...
+timeline-move-event = Move to "{ $folder }"
+info = Info
+histogram = Histogram
+library = Library
+history = History
+status-history = Status history
+read-only = Read-only
+read-only-hint = Change nothing on disk. Destination keys only plan moves, which can be exported.
+lock = Lock
+lock-hint = Ignore the keys of the destinations, e.g. when stepping away. Escape turns it on and off.
+current-image = Current Image:
+page-of = Page { $page }/{ $pages }
+previous-page = Previous page (Page Up)
+burst-stack = Burst of { $count }
+burst-stack-hint = The shots of one burst are shown and moved as one. Click to go through them one by one.
+burst-shot = Shot { $shot }/{ $count }
+burst-shot-hint = Click to stack the burst again.
+next-page = Next page (Page Down)
+from-folder = from { $folder }
+full-resolution-hint = Show the image at full resolution.
+clipping = Clipping
+clipping-hint = Mark blown highlights red and crushed shadows blue.
+detach-image = Detach
+detach-image-hint = Show the image in a window of its own, e.g. full screen on another monitor.
+image-detached = The image is shown in its own window.
+attach-image = Bring it back
+freed-so-far = { $size } freed so far
+properties = Properties
+properties-hint = Show the size, format, dates, and full path of the image.
+property-created = Created { $time }
+property-modified = Modified { $time }
+failed-to-read-properties = Failed to read the properties: { $error }
+end-of-folder = End of the folder. Every image has been gone through.
+start-over = Start over
+pass-summary = { $moved ->
+        [one] { $moved } image moved
+       *[other] { $moved } images moved
+    } this session, { $remaining } left in the folder.
+no-images-found = No images found in the folder.
+all-sorted = Every image has been sorted.
+goal = Goal
+goal-empty-folder = Empty the folder
+goal-sort-before = Sort
+goal-sort-after = images
+goal-start = Start
+goal-clear = Stop following the goal
+goal-progress = { $done } / { $target }
+goal-progress-eta = { $done } / { $target }, about { $eta } left
+goal-eta-minutes = { $minutes } min
+goal-eta-hours = { $hours } h { $minutes } min
+session-stats = { $moved ->
+        [one] { $moved } image moved
+       *[other] { $moved } images moved
+    } and { $deleted } deleted in { $minutes ->
+        [one] { $minutes } minute.
+       *[other] { $minutes } minutes.
+    }
+open-next-folder = Open the next folder: { $folder }
+
+## Destinations
+
+destinations-heading = Folder & Letter Entries:
+folder = Folder:
+choose-destination-folder = Choose Destination Folder
+date-template-hint = {"{"}year{"}"}, {"{"}month{"}"} and {"{"}day{"}"} are replaced with the date the photo was taken, e.g. /photos/{"{"}year{"}"}/{"{"}month{"}"}.
+letter = Letter:
+not-a-key = '{ $letter }' cannot be used as a key.
+destination-entry = Folder: { $folder }, Letter: { $letter }
+add-destination = Add destination
+remove-destination = Remove the destination { $folder }
+ask-first = Ask first
+ask-first-hint = Confirm before moving images here, e.g. to a folder of images to delete.
+hard-link = Link
+hard-link-hint = Add a hard link there instead of moving, so that the image is in several folders without taking space twice. Copies across drives.
+copy-destination = Copy
+copy-destination-hint = Copy the image here when its letter is pressed. It is moved to the destination with the same letter that does not copy.
+double-press = Press twice
+double-press-hint = Move only when the letter is pressed twice within a second, e.g. for a folder of images to delete.
+press-again = Press { $letter } again to move to { $folder }
+destination-icon = Icon
+destination-icon-hint = An emoji or a few letters shown next to the destination, e.g. 🐈.
+destination-color = Color
+destination-color-hint = Show the destination in a color, and flash it around the window when an image is moved there.
+album-tree = Folder tree
+album-tree-hint = Show the folders of an albums folder instead of this list. Click a folder to move the image there, or right-click it to give it a key.
+albums-folder = Albums folder:
+choose-albums-folder = Choose Albums Folder
+albums-folder-unset = Choose the folder that has the albums.
+refresh = Refresh
+album-move-hint = Move the image here. Right-click to give the folder a key.
+assign-key = Assign
+remove-key = Remove the key
+new-subfolder = New folder in this folder
+create-folder = Create
+new-album = New album
+palette-hint = Type a command, or a folder to move the image to…
+palette-find-duplicates = Find duplicates in the library
+palette-filter-label = Show only images labeled { $label }
+palette-filter-rating = Show only images rated { $stars } or more
+
+## Image menu and broken images
+
+move-to = Move to
+keep-here = Keep here
+keep-here-hint = Leave the image where it is and skip it when sorting again (Enter).
+copy-path = Copy path
+open-externally = Open externally
+show-in-file-manager = Show in file manager
+rename-menu = Rename...
+delete = Delete
+delete-permanently = Delete permanently
+cannot-display-image = Cannot display this image
+file-size = { $bytes } bytes
+failed-to-read-file-info = Failed to read file info: { $error }
+skip = Skip
+move-to-quarantine = Move to quarantine folder
+
+## Dialogs
+
+failed-to-move-heading = Failed to move file
+source = Source:
+destination = Destination:
+retry = Retry
+source-unreachable = { $folder } cannot be read: { $error }
+confirm-move-file = Move { $file }?
+confirm-move-images = { $count ->
+        [one] Move { $count } image?
+       *[other] Move { $count } images?
+    }
+confirm-destination = To { $folder }
+identical-exists = { $file } is already there
+identical-existing = Same contents as { $path }
+skip-and-delete = Skip and delete source
+move-anyway = Move anyway
+move = Move
+rename = Rename
+waiting-to-retry = Waiting to retry ({ $count })
+retry-attempt = attempt { $attempt }, next in { $seconds }s: { $error }
+retry-now = Retry now
+
+## Keyboard shortcuts
+
+keyboard-shortcuts = Keyboard shortcuts
+key-space = Space
+key-enter = Enter
+key-ctrl-digits = Ctrl + 1 to 5
+key-alt-digits = Alt + 1 to 5
+key-hold-shift = Hold Shift
+key-tab = Tab
+key-page-up-down = Page Up / Page Down
+shortcut-navigate = Next / previous image
+shortcut-page = Next / previous page of a TIFF file
+shortcut-skip = Skip for later
+shortcut-keep = Keep in place
+shortcut-undo = Undo
+shortcut-label = Set a color label, Ctrl + 0 clears it
+shortcut-rating = Set a star rating, Alt + 0 clears it
+shortcut-loupe = Magnify the pixels under the pointer and show their color
+shortcut-help = Show or hide this list
+shortcut-rename = Rename the image
+shortcut-rescan = Read the folders again for images added or deleted outside the app
+shortcut-fullscreen = Full screen in the detached image window
+shortcut-open-folder = Choose an image folder
+shortcut-new-album = Make a new album in the albums folder and move the image there
+shortcut-palette = Run any command, or move the image to a folder found by typing part of its name
+shortcut-lock = Lock or unlock the keys of the destinations
+shortcut-focus = Move between buttons. Enter presses one, Esc leaves them.
+shortcut-move = Move to { $folder }
+shortcut-move-asks-first = Move to { $folder } (asks first)
+shortcut-plan-move = Plan to move to { $folder }
+shortcuts-no-destinations = Add destination folders below to move images with a single key.
+
+## Info window
+
+no-image = No image.
+info-file = File:
+info-folder = Folder:
+info-link = Link to:
+broken-link = The file it points to is missing.
+info-taken = Taken:
+info-description = Description:
+info-location = Location:
+info-note = Note:
+unknown = Unknown
+note-hint = e.g. send this one to mom
+notes-need-index = Notes need the library index. Turn it on in the settings.
+
+## Settings
+
+images-in-memory = Images in memory: about { $megabytes } MB
+settings-language = Language:
+settings-max-texture-size = Max texture size:
+settings-max-texture-size-hint = Larger images are downscaled. "100%" shows the full size.
+settings-color-management = Color management:
+settings-color-management-hint = Show images with a color profile, like the Display P3 photos of phones, in the right colors. Thumbnails made before keep their colors.
+settings-preload = Preload images:
+settings-preload-hint = Images before and after the current one to keep loaded.
+settings-memory-budget = Memory budget:
+settings-thumbnail-cache-size = Thumbnail cache size:
+settings-library-index = Library index:
+settings-library-index-hint = Remember the images of opened folders so that large folders reopen instantly and can be searched.
+enabled = Enabled
+settings-raw-pairs = RAW+JPEG pairs:
+settings-raw-pairs-hint = Takes effect the next time a folder is opened.
+settings-bursts = Bursts:
+settings-bursts-hint = Shots with sequential names taken within a second of each other, or named as a burst by the phone.
+settings-stack-bursts = Stack them and move them together
+settings-treat-as-one = Treat as one image
+settings-sort-by = Sort by:
+settings-file-name = File name
+settings-date-taken = Date taken
+settings-date-taken-hint = Newest first.
+settings-largest-first = Largest first
+settings-largest-first-hint = Biggest files first, to free disk space quickly. Shows how much was freed.
+settings-color = Color
+settings-color-hint = Around the color wheel by the color that covers most of each image, grays last. Good for wallpapers and design references.
+settings-ignore = Leave out:
+settings-ignore-hint = One pattern per line, like *.tmp.jpg or 2024/raw/*. A .imageorganizerignore file in a folder adds its own. Applies to folders opened afterwards.
+settings-include-hidden = Show hidden files
+settings-symlinks = Symbolic links:
+settings-symlinks-hint = Links to images in the folders being sorted. Leaving them out applies to folders opened afterwards.
+settings-symlinks-skip = Leave out
+settings-symlinks-follow = Move the file they point to
+settings-symlinks-move-link = Move the link
+settings-collisions = Name taken:
+settings-collisions-hint = When the destination already has a different file of the same name. Names that differ only in case count as the same where the drive ignores case, as on Windows and macOS.
+settings-collisions-keep-both = Move with a new name
+settings-collisions-skip = Leave in the source folder
+settings-download-flag = Downloaded files:
+settings-download-flag-keep = Keep the mark of downloaded files
+settings-download-flag-hint = macOS marks downloaded files so that it asks before opening them. Copies to another drive keep the mark unless this is off. Finder tags and other extended attributes are always kept.
+settings-network-timeout = Network timeout:
+settings-network-timeout-hint = How long to wait for a folder on a network share before saying that it is unreachable.
+settings-maps = Maps:
+settings-maps-hint = Shows where photos with a GPS location were taken. The map is downloaded from OpenStreetMap.
+settings-show-in-info = Show in the info window
+settings-tags = Tags:
+settings-tags-hint = Tags need the library index.
+settings-suggest-destinations = Suggest destinations from tags
+settings-kept-images = Kept images:
+settings-kept-images-hint = Enter keeps an image where it is. The library index remembers it.
+settings-hide-kept = Hide when sorting again
+settings-navigation = Navigation:
+settings-wrap-around = Go from the last image back to the first
+settings-wrap-around-hint = Turned off, the pass stops at the last image so that it is clear when it is over.
+settings-pass-summary = Sum up the pass at the end
+settings-after-move = After a move show:
+settings-after-move-advance = The next image
+settings-after-move-stay = The image in its place
+settings-after-move-stay-hint = The next one, except after the last image, where it is the one before.
+settings-after-move-back = The previous image
+settings-recent-moves = Recently moved:
+settings-recent-moves-after = images
+settings-recent-moves-hint = Thumbnails of the last moves with the keys that were pressed. Click one to undo that move. 0 hides them.
+settings-confirm = Confirm:
+settings-confirm-ask-first = Moves to destinations marked "Ask first"
+settings-confirm-batch-before = Moves of more than
+settings-confirm-batch-after = images at once
+settings-confirm-batch-hint = 0 never asks.
+settings-confirm-identical = Moves to a destination that already has the same file
+settings-confirm-identical-hint = Compares the contents of the files in the destination and the folders under it.
+settings-theme = Theme:
+settings-theme-system = System
+settings-theme-dark = Dark
+settings-theme-light = Light
+settings-accent-color = Accent color:
+settings-custom = Custom
+settings-ui-scale = UI scale:
+settings-font-size = Font size:
+settings-image-background = Image background:
+settings-image-background-hint = Shown through transparent images.
+background-none = None
+background-checkerboard = Checkerboard
+background-black = Black
+background-gray = Gray
+background-white = White
+
+## Tray icon
+
+tray-icon = Tray icon for
+tray-icon-hint = Show the number of images waiting in the watched folder next to the clock, e.g. for screenshots.
+tray-unsupported = This build has no tray icon. It needs Linux and the "tray" feature.
+choose-watched-folder = Choose Watched Folder
+tray-summary = { $folder }: { $count } to sort
+tray-sort = { $count ->
+        [one] Sort { $count } image
+       *[other] Sort { $count } images
+    }
+tray-show = Show the window
+tray-quit = Quit
+
+## Tools
+
+choose = Choose
+not-selected = Not selected.
+read-only-off = Turned off in read-only mode.
+show = Show
+move-to-folder = Move to...
+image-count = { $count ->
+        [one] { $count } image
+       *[other] { $count } images
+    }
+
+## Merge folders
+
+merge-folders = Merge folders
+merge-intro = Moves the images of the source folder into the target folder.
+merge-source = Source:
+merge-target = Target:
+compare = Compare
+comparing = Comparing...
+compare-stopped = The comparison stopped.
+compare-failed = Failed to compare the folders: { $error }
+only-in-source = Only in source
+only-in-target = Only in target
+identical = Identical
+conflicts = Same name, different contents
+conflicts-label = Same name, different contents:
+leave-in-source = Leave in the source
+move-with-new-name = Move with a new name
+delete-identical = Delete identical copies from the source
+merge = Merge
+merging = Merging { $source } into { $target }
+
+## Flatten folders
+
+flatten-folders = Flatten folders
+flatten-intro = Moves the images in all subfolders into one folder.
+flatten-target = Move to:
+find-images = Find images
+searching = Searching...
+search-stopped = The search stopped.
+list-images-failed = Failed to list the images: { $error }
+images-found = { $count ->
+        [one] { $count } image found.
+       *[other] { $count } images found.
+    }
+move-all = Move all
+flattening = Moving { $count } images into { $folder }
+
+## Locations
+
+locations = Locations
+locations-intro = Groups the images by where they were taken.
+read-locations = Read locations
+within = Within:
+locations-read = { $checked }/{ $total } images read, { $found } with a location.
+open-in-openstreetmap = Open in OpenStreetMap.
+near = Near { $place }
+
+## Blurry images
+
+blurry-images = Blurry images
+blur-intro = Finds images that are likely out of focus or shaken.
+measure-sharpness = Measure sharpness
+blurry-below = Blurry below:
+blurry-below-hint = Variance of the Laplacian. Raise it to flag more images, lower it to flag fewer.
+blurry-count = { $count ->
+        [one] { $count } blurry image
+       *[other] { $count } blurry images
+    }
+review = Review
+review-blurry-hint = Go through only the blurry images.
+blurry-filter = Blurry
+
+## Tags
+
+tags = Tags:
+tags-hint = Comma separated, Enter to save
+save = Save
+show-only = Show only
+no-tags = No tags yet.
+suggested = Suggested:
+suggested-hint = Folders that hold other images with these tags. Click to move the image.
+
+## Revert session
+
+revert-session = Revert session
+revert-session-intro = Moves every image moved since the app started back where it was, newest first. Links and copies that were made are removed.
+moves-to-undo = { $count ->
+        [one] { $count } move to undo.
+       *[other] { $count } moves to undo.
+    }
+revert-all = Revert all
+wait-for-moves = Wait for other moves to finish first.
+moved-back = { $count ->
+        [one] Moved { $count } image back.
+       *[other] Moved { $count } images back.
+    }
+revert-cancelled = Cancelled. { $count ->
+        [one] { $count } move was left as it is.
+       *[other] { $count } moves were left as they are.
+    }
+revert-failed = { $count } could not be moved back:
+
+## Interrupted file operations
+
+interrupted-operations = Interrupted file operations
+interrupted-intro = The app stopped while copying these files to another drive.
+interrupted-not-copied = The copy did not finish. The original is untouched.
+interrupted-not-removed = Copied, but the original was not removed.
+interrupted-conflict = Both files exist and differ. Check them by hand.
+interrupted-done = Finished.
+interrupted-missing = Neither file exists anymore.
+remove-partial-copy = Remove the partial copy
+remove-original = Remove the original
+dismiss = Dismiss
+repair-all = Repair all
+
+## Log
+
+log = Log
+search-log-hint = Search, e.g. a file name
+warnings-only = Warnings and errors only
+reload = Reload
+open-folder = Open folder
+nothing-logged = Nothing logged.
+
+## Discard folder
+
+discard-folder = Discard folder
+discard-intro = Images moved into the discard folder wait there until they are restored or purged. Where each came from is kept in a log in the folder.
+discard-folder-label = Discard folder:
+read-folder-failed = Failed to read { $path }: { $error }
+purge-confirm = { $count ->
+        [one] Delete { $count } file for good?
+       *[other] Delete { $count } files for good?
+    }
+restore-selected = Restore selected
+purge-selected = Purge selected
+purge-older-than = Purge files older than
+days = days
+files-selected = { $count ->
+        [one] { $count } file, { $selected } selected
+       *[other] { $count } files, { $selected } selected
+    }
+not-in-log = Not in the log
+days-ago = { $count ->
+        [one] { $count } day ago, { $time }
+       *[other] { $count } days ago, { $time }
+    }
+
+## Sync with mirror
+
+sync-with-mirror = Sync with mirror
+sync-intro = Brings a mirror of the library, e.g. on a NAS or an external drive, in line with it. Images moved in the library are moved on the mirror instead of being copied again, and new images are copied both ways. Nothing changes until "Sync" is clicked.
+sync-library = Library:
+sync-mirror = Mirror:
+sync-summary = { $moves } to move on the mirror, { $to_mirror } to copy to the mirror, { $to_library } to copy to the library, { $conflicts } conflicts. { $unchanged } are the same.
+move-on-mirror = Move on the mirror: { $from } → { $to }
+copy-to-mirror = Copy to the mirror: { $path }
+copy-to-library = Copy to the library: { $path }
+sync-conflict = Conflict: { $path }
+sync-moved-both = Moved to { $path } in the library, but the mirror has both
+deleted-on-mirror = Deleted on the mirror since the last sync
+deleted-in-library = Deleted in the library since the last sync
+keep-library = Keep library's
+keep-mirror = Keep mirror's
+sync = Sync
+syncing = Syncing { $library } with { $mirror }
+
+## Move log
+
+move-log = Move log
+session-moves = { $count ->
+        [one] { $count } move in this session.
+       *[other] { $count } moves in this session.
+    }
+export = Export...
+export-move-log-hint = Saves where each image came from and went, with its hash.
+replay-intro = Makes the same moves in another copy of the library, e.g. on a mirror drive. Images are found by name, and by contents where names are not enough.
+move-log-label = Move log:
+move-log-file = { $path } ({ $count ->
+        [one] { $count } move
+       *[other] { $count } moves
+    })
+library-copy = Library copy:
+read-move-log-failed = Failed to read { $path }: { $error }
+not-a-move-log = Not a move log: the first line is not "{ $header }".
+line-not-a-move = Line { $line } is not a move.
+preview = Preview
+finding-images = Finding the images...
+replay-summary = { $count } images to move. { $in_place } are already in place, { $missing } are not in the copy, and { $ambiguous } match more than one image.
+replay-renamed = { $count ->
+        [one] { $count } move changed the name of the image and is left out.
+       *[other] { $count } moves changed the name of the image and are left out.
+    }
+replay = Replay
+replaying = Replaying { $count } moves in { $folder }
+
+## Quarantine
+
+quarantine = Quarantine
+quarantine-intro = Finds empty files, files that cannot be read, and files whose contents are not what their names say. Quarantined files are listed with the reason in a report in the folder.
+quarantine-folder = Quarantine folder:
+find-problem-files = Find problem files
+problem-files = { $count ->
+        [one] { $count } problem file
+       *[other] { $count } problem files
+    }
+quarantine-all = Quarantine all
+quarantine-all-hint = Choose the quarantine folder and wait for the scan.
+empty-file = Empty file
+unreadable = Unreadable: { $error }
+suspicious = Suspicious: { $reason }
+looks-like-program = looks like a program, not an image
+contents-are = the contents are { $actual }, not { $expected }
+
+## Screenshots
+
+screenshots = Screenshots
+screenshots-intro = Finds screenshots by their names, and PNG images that no camera took with the size of a screen.
+screenshots-folder = Screenshots folder:
+find-screenshots = Find screenshots
+screenshot-count = { $count ->
+        [one] { $count } screenshot
+       *[other] { $count } screenshots
+    }
+review-screenshots-hint = Go through only the screenshots.
+choose-screenshots-folder-first = Choose the screenshots folder first.
+
+## Contact sheet
+
+contact-sheet = Contact sheet
+contact-sheet-intro = A sheet of the { $count } images shown, e.g. to share an overview of an album.
+columns = Columns
+thumbnail-size = Thumbnail size
+file-names = File names
+under-each-image = Under each image
+save-as = Save...
+save-sheet-hint = Save as PNG, JPEG, or PDF. A PDF gets a page for every few rows.
+saved = Saved { $path }
+open = Open
+no-images-for-sheet = There are no images to put on the sheet.
+encode-failed = Failed to encode { $path }
+export-stopped = The export stopped.
+
+## Faces
+
+faces = Faces
+faces-unsupported = This build cannot find faces. Build it with `--features faces`.
+faces-intro = Groups the photos by the people in them. Nothing leaves this computer.
+face-detector = Face detector:
+face-embedder = Face embedder:
+find-faces = Find faces
+same-person-from = Same person from:
+same-person-from-hint = How alike two faces must be. Raise it if different people are grouped together, lower it if one person is split into several groups.
+load-models-failed = Failed to load the models: { $error }
+faces-of-people = { $faces } faces of { $people } people
+person = Person
+person-number = Person { $number }
+photo-count = { $count ->
+        [one] { $count } photo
+       *[other] { $count } photos
+    }
+choose-folder = Choose folder
+
+## Storage usage
+
+storage-usage = Storage usage
+storage-intro = Size of each destination folder, including its subfolders.
+measuring = Measuring { $done }/{ $total }...
+no-destinations-yet = No destination folders yet.
+storage-folder = Folder
+storage-files = Files
+storage-size = Size
+storage-total = Total
@@ -0,0 +1,624 @@
+# 日本語の UI メッセージ。id は en.ftl と同じにすること。
+
+## Main window
+
+choose-image-folder = 画像フォルダーを選択
+add-folder = フォルダーを追加
+add-folder-hint = 同じセッションで整理するフォルダーを追加します。
+open-archive = アーカイブを開く
+open-archive-hint = .zip や .cbz ファイルの画像を整理します。アーカイブ自体は変更されません。
+scanning = スキャン中...
+already-scanning = すでにフォルダを読み込んでいます。
+images-gone = { $file } はアプリの外で削除または移動されました。なくなった画像 { $count } 枚をリストから外しました。
+cancel = キャンセル
+indexing = インデックス作成中 { $indexed }/{ $total }
+reading-dates = 撮影日を読み込み中...
+reading-sizes = ファイルサイズを読み込み中...
+reading-colors = 色を読み込み中...
+check-images = 画像を検査
+check-images-hint = すべての画像をデコードして壊れたファイルを探します。
+showing-only = 表示中: { $name }（{ $count } 枚）
+show-all = すべて表示
+filter-by-color = 絞り込み:
+dominant-color = 主な色: { $color }
+hue-red = 赤
+hue-orange = オレンジ
+hue-yellow = 黄
+hue-green = 緑
+hue-cyan = シアン
+hue-blue = 青
+hue-purple = 紫
+hue-pink = ピンク
+hue-gray = 灰色
+hidden-kept = 以前そのままにした { $count } 枚の画像を非表示にしています。
+show-them = 表示する
+deferred = { $count } 枚の画像を後回しにしました。最後の画像の後に表示されます。
+selected-folder = 選択したフォルダー:
+no-folder-selected = フォルダーが選択されていません。
+folders = フォルダー:
+remove-from-queue = キューから外します。
+remove-source = { $folder } をキューから外す
+status = ステータス:
+settings = 設定
+tools = ツール
+merge-folders-menu = フォルダーを統合...
+flatten-folders-menu = フォルダーを平坦化...
+organize-by-date-menu = 日付ごとに整理...
+locations-menu = 撮影場所...
+find-blurry-menu = ぼやけた画像を探す...
+find-screenshots-menu = スクリーンショットを探す...
+quarantine-menu = 問題のあるファイルを隔離...
+discard-menu = 破棄フォルダーを確認...
+faces-menu = 顔でグループ分け...
+storage-menu = 使用容量...
+contact-sheet-menu = コンタクトシート...
+sync-menu = ミラーと同期...
+move-log-menu = 移動ログの書き出し・再現...
+audit-menu = 操作の記録...
+log-menu = ログ...
+wizard = はじめに
+wizard-welcome = ようこそ
+wizard-welcome-text = このアプリは、フォルダ内の画像をキー1つで他のフォルダに振り分けます。次の手順で、振り分けるフォルダといくつかの移動先を設定します。設定やツールは後からいつでも使えます。
+wizard-source = 振り分けるフォルダ
+wizard-source-text = ダウンロードやスマートフォンから取り込んだフォルダなど、振り分ける画像のフォルダを選んでください。生成した画像のデモフォルダで先に試すこともできます。自分のファイルには触れません。
+wizard-demo = デモフォルダで試す
+wizard-demo-hint = 一時ディレクトリに赤・緑・青の画像のフォルダを作り、色ごとに移動先を用意します。
+wizard-destinations = 移動先
+wizard-destinations-text = 移動先のフォルダにはそれぞれキーが割り当てられます。キーを押すと画像がそこに移動します。移動先は後から画像の下で追加できます。
+wizard-keys = キー
+wizard-keys-text = 画像を見て、移動先のキーを押すと次の画像が表示されます。Ctrl + Z で最後の画像を元に戻せるので、何も失われません。
+wizard-back = 戻る
+wizard-next = 次へ
+wizard-finish = 振り分けを始める
+wizard-skip = スキップ
+wizard-done = 準備ができました。移動先のキーを押すと画像がそこに移動します。
+wizard-menu = はじめに...
+practice = 練習
+practice-menu = サンプル画像で練習
+practice-text = 一時ディレクトリに生成した画像です。練習を終えるまで、それ以外は移動されません。
+practice-task-move = R、G、B のキーで画像を移動する
+practice-task-undo = Ctrl + Z で移動を元に戻す
+practice-task-filter = Ctrl + 1 で画像にカラーラベルを付け、Ctrl + P でその画像だけを表示する
+practice-done = よくできました。基本はこれで終わりです。
+practice-reset = やり直す
+practice-leave = 練習を終える
+practice-outside = 練習中は練習用のフォルダしか使えません。
+exit-question = 処理中の作業がありますが終了しますか？
+exit-nothing-pending = その後すべて完了しました。
+exit-uploads = 完了していないアップロード { $count } 件
+exit-retries = 再試行待ちの移動 { $count } 件。画像は元の場所に残ります。
+exit-batch = { $title }: { $total } 件中 { $done } 件完了。残りはそのままになります。
+exit-revert = セッションを元に戻しています: { $total } 件中 { $done } 件
+exit-confirmation = 確認待ちの移動
+exit-plan = エクスポートしていない移動計画 { $count } 件
+exit-keep-open = 終了しない
+exit-quit = 終了する
+log-to-file = ログファイルを残す
+log-to-file-hint = 各画像の移動先など、アプリの操作を設定フォルダ内のファイルに書き込みます。大きくなると古い部分から削除されます。
+revert-session-menu = このセッションの移動をすべて元に戻す...
+labels = ラベル
+grid = グリッド
+timeline = タイムライン
+timeline-hint = 撮影日やイベントごとにまとめた画像
+timeline-days = { $days } 日分
+timeline-jump = 日付へ移動
+timeline-unknown-date = 日付不明
+timeline-images = { $count } 枚
+timeline-show-day = これだけを表示
+timeline-move-day = 移動...
+timeline-by-day = 日ごと
+timeline-by-day-hint = 1日ごとにまとめます
+timeline-by-event = イベントごと
+timeline-by-event-hint = 撮影の間隔がこれより空いたところで区切ります
+timeline-event-gap = 間隔:
+timeline-events = { $events } 件のイベント
+timeline-events-folder = 新しいフォルダの作成先:
+timeline-events-folder-unset = 先にイベントの移動先フォルダを選んでください
+timeline-choose-folder = 選択...
+timeline-move-event = 「{ $folder }」へ移動
+info = 情報
+histogram = ヒストグラム
+library = ライブラリ
+history = 履歴
+status-history = ステータス履歴
+read-only = 読み取り専用
+read-only-hint = ディスク上のものは何も変更しません。移動先のキーは移動を計画するだけで、計画は書き出せます。
+lock = ロック
+lock-hint = 移動先のキーを無効にします。席を外すときなどに。Escape でも切り替えられます。
+current-image = 現在の画像:
+page-of = { $page }/{ $pages } ページ
+previous-page = 前のページ (Page Up)
+burst-stack = { $count } 枚の連写
+burst-stack-hint = 連写の写真はまとめて表示・移動されます。クリックすると 1 枚ずつ見られます。
+burst-shot = 連写 { $shot }/{ $count } 枚目
+burst-shot-hint = クリックすると連写をまとめ直します。
+next-page = 次のページ (Page Down)
+from-folder = （{ $folder } から）
+full-resolution-hint = 画像を原寸で表示します。
+clipping = 白飛び・黒つぶれ
+clipping-hint = 白飛びを赤、黒つぶれを青で示します。
+detach-image = 切り離す
+detach-image-hint = 画像を別のウィンドウに表示します。別のモニターで全画面表示するときなどに。
+image-detached = 画像は別のウィンドウに表示されています。
+attach-image = 元に戻す
+freed-so-far = これまでに { $size } を空けました
+properties = プロパティ
+properties-hint = 画像のサイズ、形式、日時、フルパスを表示します。
+property-created = 作成 { $time }
+property-modified = 更新 { $time }
+failed-to-read-properties = プロパティを読み込めませんでした: { $error }
+end-of-folder = フォルダーの最後です。すべての画像を確認しました。
+start-over = 最初から
+pass-summary = このセッションで { $moved } 枚の画像を移動しました。フォルダーに { $remaining } 枚残っています。
+no-images-found = フォルダーに画像が見つかりません。
+all-sorted = すべての画像を整理しました。
+goal = 目標
+goal-empty-folder = フォルダーを空にする
+goal-sort-before = 整理する枚数:
+goal-sort-after = 枚
+goal-start = 開始
+goal-clear = 目標をやめる
+goal-progress = { $done } / { $target }
+goal-progress-eta = { $done } / { $target }、残り約 { $eta }
+goal-eta-minutes = { $minutes } 分
+goal-eta-hours = { $hours } 時間 { $minutes } 分
+session-stats = { $minutes } 分で { $moved } 枚の画像を移動し、{ $deleted } 枚を削除しました。
+open-next-folder = 次のフォルダーを開く: { $folder }
+
+## Destinations
+
+destinations-heading = 移動先フォルダーとキー:
+folder = フォルダー:
+choose-destination-folder = 移動先フォルダーを選択
+date-template-hint = {"{"}year{"}"}、{"{"}month{"}"}、{"{"}day{"}"} は撮影日に置き換えられます。例: /photos/{"{"}year{"}"}/{"{"}month{"}"}
+letter = キー:
+not-a-key = '{ $letter }' はキーとして使えません。
+destination-entry = フォルダー: { $folder }、キー: { $letter }
+add-destination = 移動先を追加
+remove-destination = 移動先 { $folder } を削除
+ask-first = 確認する
+ask-first-hint = ここに移動する前に確認します。削除する画像のフォルダーなどに。
+hard-link = リンク
+hard-link-hint = 移動する代わりにハードリンクを作り、容量を増やさずに複数のフォルダーに画像を置きます。別のドライブにはコピーします。
+copy-destination = コピー
+copy-destination-hint = このキーを押したときに画像をここにコピーします。画像は同じキーでコピーしない移動先に移動します。
+double-press = 2回押し
+double-press-hint = 1秒以内にキーを2回押したときだけ移動します。削除する画像のフォルダーなどに。
+press-again = もう一度 { $letter } を押すと { $folder } に移動します
+destination-icon = アイコン
+destination-icon-hint = 移動先の横に表示する絵文字や短い文字。例: 🐈
+destination-color = 色
+destination-color-hint = 移動先を色で示し、画像をそこに移動したときにウィンドウの縁をその色で光らせます。
+album-tree = フォルダーツリー
+album-tree-hint = この一覧の代わりにアルバムフォルダーのフォルダーを表示します。フォルダーをクリックすると画像をそこに移動し、右クリックするとキーを割り当てます。
+albums-folder = アルバムフォルダー:
+choose-albums-folder = アルバムフォルダーを選択
+albums-folder-unset = アルバムがあるフォルダーを選んでください。
+refresh = 再読み込み
+album-move-hint = 画像をここに移動します。右クリックでフォルダーにキーを割り当てます。
+assign-key = 割り当て
+remove-key = キーを外す
+new-subfolder = このフォルダーに新しいフォルダーを作成
+create-folder = 作成
+new-album = 新しいアルバム
+palette-hint = コマンド、または画像の移動先フォルダーを入力…
+palette-find-duplicates = ライブラリーの重複を探す
+palette-filter-label = { $label }のラベルの画像だけを表示
+palette-filter-rating = { $stars } 以上の画像だけを表示
+
+## Image menu and broken images
+
+move-to = 移動先
+keep-here = ここに残す
+keep-here-hint = 画像をそのままにして、次に整理するときはスキップします（Enter）。
+copy-path = パスをコピー
+open-externally = 外部アプリで開く
+show-in-file-manager = ファイルマネージャーで表示
+rename-menu = 名前を変更...
+delete = 削除
+delete-permanently = 完全に削除
+cannot-display-image = この画像は表示できません
+file-size = { $bytes } バイト
+failed-to-read-file-info = ファイル情報を読み込めませんでした: { $error }
+skip = スキップ
+move-to-quarantine = 隔離フォルダーに移動
+
+## Dialogs
+
+failed-to-move-heading = ファイルを移動できませんでした
+source = 移動元:
+destination = 移動先:
+retry = 再試行
+source-unreachable = { $folder } を読み込めません: { $error }
+confirm-move-file = { $file } を移動しますか？
+confirm-move-images = { $count } 枚の画像を移動しますか？
+confirm-destination = 移動先: { $folder }
+identical-exists = { $file } はすでに移動先にあります
+identical-existing = { $path } と同じ内容です
+skip-and-delete = 移動せずに元を削除
+move-anyway = それでも移動
+move = 移動
+rename = 名前を変更
+waiting-to-retry = 再試行待ち ({ $count })
+retry-attempt = { $attempt } 回目、{ $seconds } 秒後に再試行: { $error }
+retry-now = 今すぐ再試行
+
+## Keyboard shortcuts
+
+keyboard-shortcuts = キーボードショートカット
+key-space = スペース
+key-enter = Enter
+key-ctrl-digits = Ctrl + 1〜5
+key-alt-digits = Alt + 1〜5
+key-hold-shift = Shift を押し続ける
+key-tab = Tab
+key-page-up-down = Page Up / Page Down
+shortcut-navigate = 次 / 前の画像
+shortcut-page = TIFF ファイルの次 / 前のページ
+shortcut-skip = 後回しにする
+shortcut-keep = そのままにする
+shortcut-undo = 元に戻す
+shortcut-label = カラーラベルを付ける。Ctrl + 0 で外す
+shortcut-rating = 星の評価を付ける。Alt + 0 で外す
+shortcut-loupe = ポインタの下のピクセルを拡大して色を表示する
+shortcut-help = この一覧の表示を切り替える
+shortcut-rename = 画像の名前を変更
+shortcut-rescan = アプリの外で追加・削除された画像のためにフォルダを読み直す
+shortcut-fullscreen = 切り離した画像ウィンドウを全画面表示
+shortcut-open-folder = 画像フォルダーを選択
+shortcut-new-album = アルバムフォルダーに新しいアルバムを作って画像を移動
+shortcut-palette = コマンドを実行、またはフォルダー名の一部を入力して画像をそこに移動
+shortcut-lock = 移動先のキーをロック・解除
+shortcut-focus = ボタン間を移動。Enter で押し、Esc で離れる
+shortcut-move = { $folder } に移動
+shortcut-move-asks-first = { $folder } に移動（確認あり）
+shortcut-plan-move = { $folder } への移動を計画
+shortcuts-no-destinations = 下で移動先フォルダーを追加すると、キー 1 つで画像を移動できます。
+
+## Info window
+
+no-image = 画像がありません。
+info-file = ファイル:
+info-folder = フォルダー:
+info-link = リンク先:
+broken-link = リンク先のファイルがありません。
+info-taken = 撮影日時:
+info-description = 説明:
+info-location = 撮影場所:
+info-note = メモ:
+unknown = 不明
+note-hint = 例: これを母に送る
+notes-need-index = メモにはライブラリインデックスが必要です。設定で有効にしてください。
+
+## Settings
+
+images-in-memory = メモリ上の画像: 約 { $megabytes } MB
+settings-language = 言語 / Language:
+settings-max-texture-size = 最大テクスチャサイズ:
+settings-max-texture-size-hint = これより大きい画像は縮小されます。「100%」で原寸表示します。
+settings-color-management = カラーマネジメント:
+settings-color-management-hint = Display P3 で撮ったスマートフォンの写真など、カラープロファイル付きの画像を正しい色で表示します。作成済みのサムネイルの色は変わりません。
+settings-preload = 先読みする画像:
+settings-preload-hint = 現在の画像の前後に読み込んでおく画像の数です。
+settings-memory-budget = メモリの上限:
+settings-thumbnail-cache-size = サムネイルキャッシュのサイズ:
+settings-library-index = ライブラリインデックス:
+settings-library-index-hint = 開いたフォルダーの画像を記録し、大きなフォルダーをすぐに開き直したり検索したりできるようにします。
+enabled = 有効
+settings-raw-pairs = RAW+JPEG のペア:
+settings-raw-pairs-hint = 次にフォルダーを開いたときから有効になります。
+settings-bursts = 連写:
+settings-bursts-hint = 連番のファイル名で 1 秒以内に撮られた写真か、スマートフォンが連写と名付けた写真です。
+settings-stack-bursts = まとめて表示し、一緒に移動する
+settings-treat-as-one = 1 枚の画像として扱う
+settings-sort-by = 並べ替え:
+settings-file-name = ファイル名
+settings-date-taken = 撮影日時
+settings-date-taken-hint = 新しい順です。
+settings-largest-first = 大きい順
+settings-largest-first-hint = 大きいファイルから表示して、ディスクの空きをすばやく増やします。空いた容量も表示します。
+settings-color = 色
+settings-color-hint = 画像の大部分を占める色で色相環の順に並べ、灰色は最後にします。壁紙やデザイン資料の整理に便利です。
+settings-ignore = 除外:
+settings-ignore-hint = 1 行に 1 つのパターン（例: *.tmp.jpg、2024/raw/*）。フォルダー内の .imageorganizerignore ファイルのパターンも使われます。この後に開くフォルダーに適用されます。
+settings-include-hidden = 隠しファイルを表示する
+settings-symlinks = シンボリックリンク:
+settings-symlinks-hint = 整理するフォルダー内の画像へのリンク。除外はこの後に開くフォルダーに適用されます。
+settings-symlinks-skip = 除外する
+settings-symlinks-follow = リンク先のファイルを移動する
+settings-symlinks-move-link = リンクを移動する
+settings-collisions = 同じ名前:
+settings-collisions-hint = 移動先に同じ名前の別のファイルがあるとき。Windows や macOS のように大文字と小文字を区別しないドライブでは、大文字と小文字だけが違う名前も同じ名前とみなします。
+settings-collisions-keep-both = 別の名前で移動する
+settings-collisions-skip = 元のフォルダーに残す
+settings-download-flag = ダウンロードしたファイル:
+settings-download-flag-keep = ダウンロードの印を残す
+settings-download-flag-hint = macOS はダウンロードしたファイルに印を付け、開く前に確認します。オフにしない限り、別のドライブへのコピーにも印が残ります。Finder のタグなどの拡張属性は常に残ります。
+settings-network-timeout = ネットワークのタイムアウト:
+settings-network-timeout-hint = ネットワーク共有上のフォルダーが応答しないとき、到達できないと表示するまでの待ち時間。
+settings-maps = 地図:
+settings-maps-hint = GPS 位置情報のある写真の撮影場所を表示します。地図は OpenStreetMap からダウンロードされます。
+settings-show-in-info = 情報ウィンドウに表示する
+settings-tags = タグ:
+settings-tags-hint = タグにはライブラリインデックスが必要です。
+settings-suggest-destinations = タグから移動先を提案する
+settings-kept-images = そのままにした画像:
+settings-kept-images-hint = Enter で画像をその場に残します。ライブラリインデックスが記録します。
+settings-hide-kept = 再び整理するときに隠す
+settings-navigation = ナビゲーション:
+settings-wrap-around = 最後の画像から最初の画像に戻る
+settings-wrap-around-hint = オフにすると最後の画像で止まり、一巡したことがわかります。
+settings-pass-summary = 最後にまとめを表示する
+settings-after-move = 移動後に表示:
+settings-after-move-advance = 次の画像
+settings-after-move-stay = その位置の画像
+settings-after-move-stay-hint = 次の画像です。ただし最後の画像の後は、その前の画像です。
+settings-after-move-back = 前の画像
+settings-recent-moves = 最近移動した画像:
+settings-recent-moves-after = 枚
+settings-recent-moves-hint = 最近移動した画像のサムネイルを押したキーと一緒に表示します。クリックするとその移動を元に戻します。0 で非表示。
+settings-confirm = 確認:
+settings-confirm-ask-first = 「確認する」にした移動先への移動
+settings-confirm-batch-before = 一度に
+settings-confirm-batch-after = 枚を超える画像の移動
+settings-confirm-batch-hint = 0 なら確認しません。
+settings-confirm-identical = 同じファイルがすでにある移動先への移動
+settings-confirm-identical-hint = 移動先とその下のフォルダーにあるファイルと内容を比べます。
+settings-theme = テーマ:
+settings-theme-system = システム
+settings-theme-dark = ダーク
+settings-theme-light = ライト
+settings-accent-color = アクセントカラー:
+settings-custom = カスタム
+settings-ui-scale = UI の拡大率:
+settings-font-size = 文字サイズ:
+settings-image-background = 画像の背景:
+settings-image-background-hint = 透明な画像の後ろに表示されます。
+background-none = なし
+background-checkerboard = 市松模様
+background-black = 黒
+background-gray = グレー
+background-white = 白
+
+## Tray icon
+
+tray-icon = トレイアイコンで表示:
+tray-icon-hint = 監視するフォルダーで整理を待っている画像の数を時計の横に表示します。スクリーンショットなどに。
+tray-unsupported = このビルドにはトレイアイコンがありません。Linux と "tray" フィーチャーが必要です。
+choose-watched-folder = 監視するフォルダーを選択
+tray-summary = { $folder }: 未整理 { $count } 枚
+tray-sort = { $count } 枚の画像を整理
+tray-show = ウィンドウを表示
+tray-quit = 終了
+
+## Tools
+
+choose = 選択
+not-selected = 選択されていません。
+read-only-off = 読み取り専用モードでは使えません。
+show = 表示
+move-to-folder = 移動...
+image-count = { $count } 枚
+
+## Merge folders
+
+merge-folders = フォルダーを統合
+merge-intro = 統合元フォルダーの画像を統合先フォルダーに移動します。
+merge-source = 統合元:
+merge-target = 統合先:
+compare = 比較
+comparing = 比較中...
+compare-stopped = 比較が中断されました。
+compare-failed = フォルダーを比較できませんでした: { $error }
+only-in-source = 統合元のみ
+only-in-target = 統合先のみ
+identical = 同一
+conflicts = 同じ名前で内容が異なる
+conflicts-label = 同じ名前で内容が異なるもの:
+leave-in-source = 統合元に残す
+move-with-new-name = 名前を変えて移動
+delete-identical = 統合元の同一のコピーを削除
+merge = 統合
+merging = { $source } を { $target } に統合中
+
+## Flatten folders
+
+flatten-folders = フォルダーを平坦化
+flatten-intro = すべてのサブフォルダーの画像を 1 つのフォルダーに移動します。
+flatten-target = 移動先:
+find-images = 画像を探す
+searching = 検索中...
+search-stopped = 検索が中断されました。
+list-images-failed = 画像を一覧できませんでした: { $error }
+images-found = { $count } 枚の画像が見つかりました。
+move-all = すべて移動
+flattening = { $count } 枚の画像を { $folder } に移動中
+
+## Locations
+
+locations = 撮影場所
+locations-intro = 画像を撮影場所ごとにまとめます。
+read-locations = 撮影場所を読み込む
+within = 範囲:
+locations-read = { $total } 枚中 { $checked } 枚を読み込み、{ $found } 枚に位置情報があります。
+open-in-openstreetmap = OpenStreetMap で開きます。
+near = { $place } 付近
+
+## Blurry images
+
+blurry-images = ぼやけた画像
+blur-intro = ピンぼけや手ぶれの可能性がある画像を探します。
+measure-sharpness = シャープネスを測定
+blurry-below = この値未満はぼやけ:
+blurry-below-hint = ラプラシアンの分散です。上げるとより多くの画像が、下げるとより少ない画像が対象になります。
+blurry-count = ぼやけた画像 { $count } 枚
+review = 確認
+review-blurry-hint = ぼやけた画像だけを順に見ます。
+blurry-filter = ぼやけた画像
+
+## Tags
+
+tags = タグ:
+tags-hint = カンマ区切り、Enter で保存
+save = 保存
+show-only = 絞り込み
+no-tags = タグはまだありません。
+suggested = 候補:
+suggested-hint = これらのタグが付いた他の画像があるフォルダーです。クリックすると画像を移動します。
+
+## Revert session
+
+revert-session = セッションを元に戻す
+revert-session-intro = アプリの起動後に移動したすべての画像を、新しいものから順に元の場所に戻します。作成したリンクとコピーは削除されます。
+moves-to-undo = 元に戻す移動: { $count } 件
+revert-all = すべて元に戻す
+wait-for-moves = 先に他の移動が終わるのを待ってください。
+moved-back = { $count } 枚の画像を元に戻しました。
+revert-cancelled = キャンセルしました。{ $count } 件の移動はそのままです。
+revert-failed = { $count } 件は元に戻せませんでした:
+
+## Interrupted file operations
+
+interrupted-operations = 中断されたファイル操作
+interrupted-intro = これらのファイルを別のドライブにコピーしている途中でアプリが停止しました。
+interrupted-not-copied = コピーが完了していません。元のファイルはそのままです。
+interrupted-not-removed = コピーは完了しましたが、元のファイルが削除されていません。
+interrupted-conflict = 両方のファイルがあり、内容が異なります。手動で確認してください。
+interrupted-done = 完了しています。
+interrupted-missing = どちらのファイルも存在しません。
+remove-partial-copy = 途中までのコピーを削除
+remove-original = 元のファイルを削除
+dismiss = 閉じる
+repair-all = すべて修復
+
+## Log
+
+log = ログ
+search-log-hint = 検索（ファイル名など）
+warnings-only = 警告とエラーのみ
+reload = 再読み込み
+open-folder = フォルダーを開く
+nothing-logged = ログはありません。
+
+## Discard folder
+
+discard-folder = 破棄フォルダー
+discard-intro = 破棄フォルダーに移動した画像は、元に戻すか完全に削除するまでそこに残ります。それぞれの移動元はフォルダー内のログに記録されます。
+discard-folder-label = 破棄フォルダー:
+read-folder-failed = { $path } を読み込めませんでした: { $error }
+purge-confirm = { $count } 個のファイルを完全に削除しますか？
+restore-selected = 選択したものを元に戻す
+purge-selected = 選択したものを完全に削除
+purge-older-than = 次の日数より古いファイルを完全に削除
+days = 日
+files-selected = { $count } 個のファイル、{ $selected } 個を選択中
+not-in-log = ログにありません
+days-ago = { $count } 日前、{ $time }
+
+## Sync with mirror
+
+sync-with-mirror = ミラーと同期
+sync-intro = NAS や外付けドライブなどにあるライブラリのミラーをライブラリに合わせます。ライブラリで移動した画像はミラーでもコピーし直さずに移動し、新しい画像は両方向にコピーします。「同期」を押すまで何も変更しません。
+sync-library = ライブラリ:
+sync-mirror = ミラー:
+sync-summary = ミラーで移動: { $moves } 件、ミラーへコピー: { $to_mirror } 件、ライブラリへコピー: { $to_library } 件、競合: { $conflicts } 件。同じもの: { $unchanged } 件。
+move-on-mirror = ミラーで移動: { $from } → { $to }
+copy-to-mirror = ミラーへコピー: { $path }
+copy-to-library = ライブラリへコピー: { $path }
+sync-conflict = 競合: { $path }
+sync-moved-both = ライブラリでは { $path } に移動しましたが、ミラーには両方あります
+deleted-on-mirror = 前回の同期の後にミラーで削除されました
+deleted-in-library = 前回の同期の後にライブラリで削除されました
+keep-library = ライブラリの方を残す
+keep-mirror = ミラーの方を残す
+sync = 同期
+syncing = { $library } を { $mirror } と同期中
+
+## Move log
+
+move-log = 移動ログ
+session-moves = このセッションの移動: { $count } 件
+export = エクスポート...
+export-move-log-hint = 各画像の移動元と移動先をハッシュと一緒に保存します。
+replay-intro = ミラードライブなど、ライブラリの別のコピーで同じ移動を行います。画像は名前で探し、名前だけで決まらないときは内容で探します。
+move-log-label = 移動ログ:
+move-log-file = { $path }（{ $count } 件の移動）
+library-copy = ライブラリのコピー:
+read-move-log-failed = { $path } を読み込めませんでした: { $error }
+not-a-move-log = 移動ログではありません: 1 行目が「{ $header }」ではありません。
+line-not-a-move = { $line } 行目は移動ではありません。
+preview = プレビュー
+finding-images = 画像を探しています...
+replay-summary = 移動する画像: { $count } 枚。すでに移動先にあるもの: { $in_place } 枚、コピーにないもの: { $missing } 枚、複数の画像に一致するもの: { $ambiguous } 枚。
+replay-renamed = 画像の名前が変わった { $count } 件の移動は除外します。
+replay = 再実行
+replaying = { $folder } で { $count } 件の移動を再実行中
+
+## Quarantine
+
+quarantine = 隔離
+quarantine-intro = 空のファイル、読み込めないファイル、名前と内容が一致しないファイルを探します。隔離したファイルは、理由と一緒にフォルダー内のレポートに記録されます。
+quarantine-folder = 隔離フォルダー:
+find-problem-files = 問題のあるファイルを探す
+problem-files = 問題のあるファイル: { $count } 個
+quarantine-all = すべて隔離
+quarantine-all-hint = 隔離フォルダーを選び、スキャンが終わるのを待ってください。
+empty-file = 空のファイル
+unreadable = 読み込めません: { $error }
+suspicious = 不審なファイル: { $reason }
+looks-like-program = 画像ではなくプログラムのようです
+contents-are = 内容は { $expected } ではなく { $actual } です
+
+## Screenshots
+
+screenshots = スクリーンショット
+screenshots-intro = 名前からスクリーンショットを探します。カメラで撮影されていない、画面の大きさの PNG 画像も探します。
+screenshots-folder = スクリーンショットのフォルダー:
+find-screenshots = スクリーンショットを探す
+screenshot-count = スクリーンショット: { $count } 枚
+review-screenshots-hint = スクリーンショットだけを順に見ます。
+choose-screenshots-folder-first = 先にスクリーンショットのフォルダーを選んでください。
+
+## Contact sheet
+
+contact-sheet = コンタクトシート
+contact-sheet-intro = 表示中の { $count } 枚の画像を1枚のシートにまとめます。アルバムの概要を共有するときなどに使えます。
+columns = 列数
+thumbnail-size = サムネイルの大きさ
+file-names = ファイル名
+under-each-image = 各画像の下に表示
+save-as = 保存...
+save-sheet-hint = PNG、JPEG、PDF で保存します。PDF は数行ごとに1ページになります。
+saved = { $path } を保存しました
+open = 開く
+no-images-for-sheet = シートに載せる画像がありません。
+encode-failed = { $path } をエンコードできませんでした
+export-stopped = 書き出しが止まりました。
+
+## Faces
+
+faces = 顔
+faces-unsupported = このビルドでは顔を探せません。`--features faces` を付けてビルドしてください。
+faces-intro = 写っている人ごとに写真をまとめます。データがこのコンピューターの外に出ることはありません。
+face-detector = 顔検出モデル:
+face-embedder = 顔特徴モデル:
+find-faces = 顔を探す
+same-person-from = 同じ人とみなす類似度:
+same-person-from-hint = 2つの顔がどれだけ似ていれば同じ人とみなすかです。別の人が同じグループになるときは上げ、1人が複数のグループに分かれるときは下げてください。
+load-models-failed = モデルを読み込めませんでした: { $error }
+faces-of-people = { $people } 人の顔: { $faces } 個
+person = 人物
+person-number = 人物 { $number }
+photo-count = 写真 { $count } 枚
+choose-folder = フォルダーを選択
+
+## Storage usage
+
+storage-usage = 使用容量
+storage-intro = サブフォルダーを含めた、各移動先フォルダーの大きさです。
+measuring = 計測中 { $done }/{ $total }...
+no-destinations-yet = 移動先フォルダーはまだありません。
+storage-folder = フォルダー
+storage-files = ファイル数
+storage-size = 大きさ
+storage-total = 合計
@@ -0,0 +1,60 @@
+use eframe::egui::{self, accesskit};
+
+/// Makes screen readers read out changes of a label, e.g. the image shown after a key press.
+pub fn announce_changes(response: &egui::Response) {
+    response.ctx.accesskit_node_builder(response.id, |node| {
+        node.set_live(accesskit::Live::Polite);
+    });
+}
+
+/// Names a widget whose text alone says little, like a "+" button.
+pub fn set_name(response: &egui::Response, name: impl Into<String>) {
+    response.ctx.accesskit_node_builder(response.id, |node| {
+        node.set_label(name.into());
+    });
+}
+
+/// Tells screen readers what an image shows.
+pub fn describe_image(response: &egui::Response, description: impl Into<String>) {
+    response.ctx.accesskit_node_builder(response.id, |node| {
+        node.set_role(accesskit::Role::Image);
+        node.set_label(description.into());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_test() {
+        let ctx = egui::Context::default();
+        ctx.enable_accesskit();
+        let mut ids = Vec::new();
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let label = ui.label("a.png (1/3)");
+                announce_changes(&label);
+                let button = ui.button("+");
+                set_name(&button, "Add destination");
+                let (_, image) =
+                    ui.allocate_exact_size(egui::Vec2::splat(10.0), egui::Sense::click());
+                describe_image(&image, "a.png");
+                ids = vec![label.id, button.id, image.id];
+            });
+        });
+        let update = output.platform_output.accesskit_update.unwrap();
+        let node = |id: egui::Id| {
+            &update
+                .nodes
+                .iter()
+                .find(|(node_id, _)| *node_id == accesskit::NodeId(id.value()))
+                .unwrap()
+                .1
+        };
+        assert_eq!(node(ids[0]).live(), Some(accesskit::Live::Polite));
+        assert_eq!(node(ids[1]).label(), Some("Add destination"));
+        assert_eq!(node(ids[2]).role(), accesskit::Role::Image);
+        assert_eq!(node(ids[2]).label(), Some("a.png"));
+    }
+}
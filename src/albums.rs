@@ -0,0 +1,225 @@
+use eframe::egui;
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{i18n::tr, palette};
+
+/// The folders directly in `folder`, sorted by name. Hidden folders are left out.
+pub fn subfolders(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut folders: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    folders.sort_by_key(|folder| folder.file_name().map(|name| name.to_ascii_lowercase()));
+    folders
+}
+
+/// The folders under `root` at any depth, parents before their children, up to `limit` of them.
+pub fn all_folders(root: &Path, limit: usize) -> Vec<PathBuf> {
+    let mut folders = Vec::new();
+    let mut queue = VecDeque::from([root.to_path_buf()]);
+    while let Some(folder) = queue.pop_front() {
+        for child in subfolders(&folder) {
+            if folders.len() == limit {
+                return folders;
+            }
+            folders.push(child.clone());
+            queue.push_back(child);
+        }
+    }
+    folders
+}
+
+/// How a folder that is a destination is marked in the tree.
+pub struct DestinationMark {
+    pub letter: char,
+    pub color: Option<[u8; 3]>,
+    pub icon: String,
+}
+
+/// What the user did in the tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlbumAction {
+    // Move the current image to the folder.
+    MoveTo(PathBuf),
+    AssignLetter { folder: PathBuf, letter: char },
+    RemoveLetter(PathBuf),
+    CreateFolder(PathBuf),
+}
+
+/// State of the folder tree of the albums folder, shown instead of the list of destinations.
+#[derive(Default)]
+pub struct AlbumTree {
+    // The subfolders of the folders that were opened. Read once, until refresh() is called.
+    children: HashMap<PathBuf, Vec<PathBuf>>,
+    // The folder a new subfolder is being named in, and the name so far.
+    new_folder: Option<(PathBuf, String)>,
+    // What is typed in the "Key" field of a context menu.
+    letter: String,
+}
+
+impl AlbumTree {
+    /// Reads the folders again, e.g. after one was created.
+    pub fn refresh(&mut self) {
+        self.children.clear();
+    }
+
+    fn children(&mut self, folder: &Path) -> Vec<PathBuf> {
+        self.children
+            .entry(folder.to_path_buf())
+            .or_insert_with(|| subfolders(folder))
+            .clone()
+    }
+
+    /// Draws the tree under `root`. `mark_of` gives the letter and label of a destination.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        root: &Path,
+        mark_of: &dyn Fn(&Path) -> Option<DestinationMark>,
+    ) -> Option<AlbumAction> {
+        let mut action = None;
+        self.show_folder(ui, root, mark_of, &mut action);
+        if let Some(AlbumAction::CreateFolder(_)) = &action {
+            self.new_folder = None;
+        }
+        action
+    }
+
+    fn show_folder(
+        &mut self,
+        ui: &mut egui::Ui,
+        folder: &Path,
+        mark_of: &dyn Fn(&Path) -> Option<DestinationMark>,
+        action: &mut Option<AlbumAction>,
+    ) {
+        let id = ui.make_persistent_id(("album", folder));
+        let mut state =
+            egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false);
+        let naming = self
+            .new_folder
+            .as_ref()
+            .is_some_and(|(parent, _)| parent == folder);
+        if naming {
+            state.set_open(true);
+        }
+        let name = folder
+            .file_name()
+            .unwrap_or(folder.as_os_str())
+            .to_string_lossy()
+            .to_string();
+        let mark = mark_of(folder);
+        let mut add_subfolder = false;
+        state
+            .show_header(ui, |ui| {
+                let button = ui.button(&name).on_hover_text(tr!("album-move-hint"));
+                if button.clicked() {
+                    *action = Some(AlbumAction::MoveTo(folder.to_path_buf()));
+                }
+                button.context_menu(|ui| {
+                    ui.horizontal(|ui| {
+                        let label = ui.label(tr!("letter"));
+                        ui.add(egui::TextEdit::singleline(&mut self.letter).desired_width(24.0))
+                            .labelled_by(label.id);
+                        if ui.button(tr!("assign-key")).clicked() {
+                            if let Some(letter) = self.letter.chars().next() {
+                                *action = Some(AlbumAction::AssignLetter {
+                                    folder: folder.to_path_buf(),
+                                    letter,
+                                });
+                            }
+                            self.letter.clear();
+                            ui.close_menu();
+                        }
+                    });
+                    if mark.is_some() && ui.button(tr!("remove-key")).clicked() {
+                        *action = Some(AlbumAction::RemoveLetter(folder.to_path_buf()));
+                        ui.close_menu();
+                    }
+                });
+                if let Some(mark) = &mark {
+                    if let Some(color) = mark.color {
+                        palette::swatch(ui, color);
+                    }
+                    ui.weak(format!("{}[{}]", mark.icon, mark.letter));
+                }
+                if ui
+                    .small_button("+")
+                    .on_hover_text(tr!("new-subfolder"))
+                    .clicked()
+                {
+                    add_subfolder = true;
+                }
+            })
+            .body(|ui| {
+                let mut cancelled = false;
+                if let Some((parent, name)) = &mut self.new_folder {
+                    if parent == folder {
+                        ui.horizontal(|ui| {
+                            let edit = ui.text_edit_singleline(name);
+                            let entered =
+                                edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                            let name = name.trim();
+                            if (ui.button(tr!("create-folder")).clicked() || entered)
+                                && !name.is_empty()
+                            {
+                                *action = Some(AlbumAction::CreateFolder(parent.join(name)));
+                            }
+                            cancelled = ui.button(tr!("cancel")).clicked();
+                        });
+                    }
+                }
+                if cancelled {
+                    self.new_folder = None;
+                }
+                for child in self.children(folder) {
+                    self.show_folder(ui, &child, mark_of, action);
+                }
+            });
+        if add_subfolder {
+            self.new_folder = Some((folder.to_path_buf(), String::new()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subfolders_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        for folder in ["trips", "Family", ".hidden", "trips/2024"] {
+            fs::create_dir(root.join(folder)).unwrap();
+        }
+        fs::write(root.join("image.jpg"), b"").unwrap();
+        assert_eq!(
+            subfolders(root),
+            vec![root.join("Family"), root.join("trips")]
+        );
+        assert_eq!(
+            subfolders(&root.join("trips")),
+            vec![root.join("trips/2024")]
+        );
+        assert!(subfolders(&root.join("missing")).is_empty());
+
+        assert_eq!(
+            all_folders(root, 10),
+            vec![
+                root.join("Family"),
+                root.join("trips"),
+                root.join("trips/2024")
+            ]
+        );
+        assert_eq!(all_folders(root, 2).len(), 2);
+    }
+}
@@ -0,0 +1,105 @@
+use directories::ProjectDirs;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+// Name of the folder next to the executable that holds everything in portable mode.
+const PORTABLE_FOLDER: &str = "image-organizer-data";
+
+// Set by --portable. Every directory below is a folder in it instead of the one of the OS.
+static PORTABLE_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "image-organizer")
+}
+
+/// Keeps the settings, caches and the index in a folder next to the executable, e.g. to run the
+/// app from a USB stick on several machines. Must be called before any directory is used.
+pub fn set_portable() -> io::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    let root = exe.parent().unwrap_or(Path::new(".")).join(PORTABLE_FOLDER);
+    let _ = PORTABLE_ROOT.set(root.clone());
+    Ok(root)
+}
+
+// `name` in the portable folder, or else the directory that `standard` picks.
+fn dir(name: &str, standard: fn(&ProjectDirs) -> &Path) -> PathBuf {
+    if let Some(root) = PORTABLE_ROOT.get() {
+        return root.join(name);
+    }
+    match project_dirs() {
+        Some(dirs) => standard(&dirs).to_path_buf(),
+        None => {
+            log::warn!(
+                "Could not find the {} directory. Using the temp directory instead.",
+                name
+            );
+            std::env::temp_dir().join("image-organizer")
+        }
+    }
+}
+
+/// Directory for data that can be regenerated, e.g. thumbnails. This is the XDG cache directory
+/// on Linux and %LOCALAPPDATA% on Windows.
+pub fn cache_dir() -> PathBuf {
+    dir("cache", ProjectDirs::cache_dir)
+}
+
+/// Directory for data the user would not want to lose, e.g. the library index.
+pub fn data_dir() -> PathBuf {
+    dir("data", ProjectDirs::data_dir)
+}
+
+/// Directory for the settings: the XDG config directory on Linux and %APPDATA% on Windows.
+pub fn config_dir() -> PathBuf {
+    dir("config", ProjectDirs::config_dir)
+}
+
+/// Directory for the log files, in the config directory so that they are found next to the
+/// settings.
+pub fn log_dir() -> PathBuf {
+    config_dir().join("logs")
+}
+
+/// The file the settings and the window layout are saved to.
+pub fn settings_file() -> PathBuf {
+    config_dir().join("settings.ron")
+}
+
+/// Copies the settings from where earlier versions saved them, which was eframe's folder for
+/// the app in the data directory, the first time the app starts without a settings file.
+pub fn migrate_settings(old_file: &Path, settings_file: &Path) -> io::Result<bool> {
+    if settings_file.exists() || !old_file.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = settings_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(old_file, settings_file)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_settings_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let old_file = temp_dir.path().join("old/app.ron");
+        let settings_file = temp_dir.path().join("config/settings.ron");
+        assert!(!migrate_settings(&old_file, &settings_file).unwrap());
+
+        fs::create_dir(temp_dir.path().join("old")).unwrap();
+        fs::write(&old_file, "old").unwrap();
+        assert!(migrate_settings(&old_file, &settings_file).unwrap());
+        assert_eq!(fs::read_to_string(&settings_file).unwrap(), "old");
+
+        // Never over the settings of this version.
+        fs::write(&old_file, "older").unwrap();
+        assert!(!migrate_settings(&old_file, &settings_file).unwrap());
+        assert_eq!(fs::read_to_string(&settings_file).unwrap(), "old");
+    }
+}
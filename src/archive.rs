@@ -0,0 +1,324 @@
+use flate2::read::DeflateDecoder;
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{file_ops, scanner};
+
+/// Archives that can be opened like a folder. Comic book archives (CBZ) are zip files.
+pub const ARCHIVE_EXTENSIONS: [&str; 2] = ["zip", "cbz"];
+
+// Signatures of the zip records that are read.
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x0201_4b50;
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+
+pub fn is_archive(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        ARCHIVE_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())
+    })
+}
+
+/// The archive that `path` is an image in, and the name of the entry. Images of an archive are
+/// listed as if the archive were a folder, e.g. "comic.cbz/ch1/001.jpg".
+pub fn split(path: &Path) -> Option<(&Path, String)> {
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| is_archive(ancestor))?;
+    let name: Vec<String> = path
+        .strip_prefix(archive)
+        .ok()?
+        .components()
+        .map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some((archive, name.join("/")))
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u64,
+    size: u64,
+    local_header_offset: u64,
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> io::Result<u16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| damaged("Truncated record"))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> io::Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| damaged("Truncated record"))
+}
+
+fn damaged(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Reads the central directory at the end of the archive, which lists every entry.
+fn read_entries(file: &mut File) -> io::Result<Vec<Entry>> {
+    let length = file.seek(SeekFrom::End(0))?;
+    // The end record is 22 bytes plus a comment of up to 64 KiB.
+    let tail_length = length.min(22 + 0xFFFF);
+    file.seek(SeekFrom::Start(length - tail_length))?;
+    let mut tail = vec![0; tail_length as usize];
+    file.read_exact(&mut tail)?;
+    let end = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&offset| u32_at(&tail, offset).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| damaged("Not a zip file"))?;
+    let count = u16_at(&tail, end + 10)?;
+    let directory_size = u32_at(&tail, end + 12)?;
+    let directory_offset = u32_at(&tail, end + 16)?;
+    if count == 0xFFFF || directory_offset == 0xFFFF_FFFF {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Archives over 4 GB (ZIP64) are not supported",
+        ));
+    }
+    file.seek(SeekFrom::Start(directory_offset.into()))?;
+    let mut directory = vec![0; directory_size as usize];
+    file.read_exact(&mut directory)?;
+
+    let mut entries = Vec::with_capacity(count.into());
+    let mut offset = 0;
+    for _ in 0..count {
+        if u32_at(&directory, offset)? != CENTRAL_DIRECTORY_HEADER {
+            return Err(damaged("Damaged central directory"));
+        }
+        let name_length = usize::from(u16_at(&directory, offset + 28)?);
+        let extra_length = usize::from(u16_at(&directory, offset + 30)?);
+        let comment_length = usize::from(u16_at(&directory, offset + 32)?);
+        let name = directory
+            .get(offset + 46..offset + 46 + name_length)
+            .ok_or_else(|| damaged("Truncated record"))?;
+        entries.push(Entry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method: u16_at(&directory, offset + 10)?,
+            crc: u32_at(&directory, offset + 16)?,
+            compressed_size: u32_at(&directory, offset + 20)?.into(),
+            size: u32_at(&directory, offset + 24)?.into(),
+            local_header_offset: u32_at(&directory, offset + 42)?.into(),
+        });
+        offset += 46 + name_length + extra_length + comment_length;
+    }
+    Ok(entries)
+}
+
+/// The images in `archive`, in any of its folders, as paths that `split` takes apart.
+pub fn image_paths(archive: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = read_entries(&mut File::open(archive)?)?;
+    Ok(entries
+        .into_iter()
+        .filter(|entry| !entry.name.ends_with('/'))
+        .map(|entry| archive.join(entry.name))
+        .filter(|path| scanner::is_image_file(path))
+        .collect())
+}
+
+/// Decompresses the entry `name` of `archive`. Only this entry is read.
+pub fn read_entry(archive: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let mut file = File::open(archive)?;
+    let entry = read_entries(&mut file)?
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not in {}", name, archive.display()),
+            )
+        })?;
+    file.seek(SeekFrom::Start(entry.local_header_offset))?;
+    let mut header = [0; 30];
+    file.read_exact(&mut header)?;
+    if u32_at(&header, 0)? != LOCAL_FILE_HEADER {
+        return Err(damaged("Damaged entry"));
+    }
+    let skipped = i64::from(u16_at(&header, 26)?) + i64::from(u16_at(&header, 28)?);
+    file.seek(SeekFrom::Current(skipped))?;
+    let compressed = file.take(entry.compressed_size);
+    let mut bytes = Vec::with_capacity(entry.size as usize);
+    match entry.method {
+        0 => compressed.take(entry.size).read_to_end(&mut bytes)?,
+        8 => DeflateDecoder::new(compressed)
+            .take(entry.size)
+            .read_to_end(&mut bytes)?,
+        method => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Compression method {} is not supported", method),
+            ))
+        }
+    };
+    let mut crc = flate2::Crc::new();
+    crc.update(&bytes);
+    if bytes.len() as u64 != entry.size || crc.sum() != entry.crc {
+        return Err(damaged(&format!("{} is damaged", name)));
+    }
+    Ok(bytes)
+}
+
+/// Writes the image `src`, an entry of an archive, into `dest_dir`. The archive stays as it was.
+/// Like a copy, it is written under a temporary name until it is complete.
+pub fn extract(src: &Path, dest_dir: &Path) -> io::Result<PathBuf> {
+    let (archive, name) = split(src).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not in an archive", src.display()),
+        )
+    })?;
+    let file_name = src
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No file name"))?;
+    let dest = dest_dir.join(file_name);
+    if fs::symlink_metadata(&dest).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dest.display()),
+        ));
+    }
+    let bytes = read_entry(archive, &name)?;
+    file_ops::check_free_space(dest_dir, bytes.len() as u64)?;
+    let partial = file_ops::partial_path(&dest);
+    if let Err(e) = fs::write(&partial, &bytes).and_then(|()| fs::rename(&partial, &dest)) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    Ok(dest)
+}
+
+/// The bytes of the image at `path`, which may be in an archive.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    match split(path) {
+        Some((archive, name)) => read_entry(archive, &name),
+        None => fs::read(path),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use flate2::{write::DeflateEncoder, Compression};
+    use std::io::Write;
+
+    /// Writes a zip file with `files`, deflated if `deflate` is set and stored otherwise.
+    pub fn write_zip(path: &Path, files: &[(&str, &[u8])], deflate: bool) {
+        let mut zip = Vec::new();
+        let mut directory = Vec::new();
+        for (name, contents) in files {
+            let data = if deflate {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(contents).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                contents.to_vec()
+            };
+            let mut crc = flate2::Crc::new();
+            crc.update(contents);
+            let method: u16 = if deflate { 8 } else { 0 };
+            let offset = zip.len() as u32;
+            // Fields shared by the local and central headers, from "version needed" on.
+            let mut common = Vec::new();
+            common.extend(20u16.to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+            common.extend(method.to_le_bytes());
+            common.extend([0; 4]);
+            common.extend(crc.sum().to_le_bytes());
+            common.extend((data.len() as u32).to_le_bytes());
+            common.extend((contents.len() as u32).to_le_bytes());
+            common.extend((name.len() as u16).to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+
+            zip.extend(LOCAL_FILE_HEADER.to_le_bytes());
+            zip.extend(&common);
+            zip.extend(name.as_bytes());
+            zip.extend(&data);
+
+            directory.extend(CENTRAL_DIRECTORY_HEADER.to_le_bytes());
+            directory.extend(20u16.to_le_bytes());
+            directory.extend(&common);
+            directory.extend([0; 10]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+        let directory_offset = zip.len() as u32;
+        zip.extend(&directory);
+        zip.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((directory.len() as u32).to_le_bytes());
+        zip.extend(directory_offset.to_le_bytes());
+        zip.extend(0u16.to_le_bytes());
+        fs::write(path, zip).unwrap();
+    }
+
+    #[test]
+    fn split_test() {
+        let (archive, name) = split(Path::new("/downloads/comic.CBZ/ch1/001.jpg")).unwrap();
+        assert_eq!(archive, Path::new("/downloads/comic.CBZ"));
+        assert_eq!(name, "ch1/001.jpg");
+        assert_eq!(split(Path::new("/downloads/comic.cbz")), None);
+        assert_eq!(split(Path::new("/downloads/a.jpg")), None);
+    }
+
+    #[test]
+    fn read_entry_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for deflate in [false, true] {
+            let archive = temp_dir.path().join(format!("{}.zip", deflate));
+            write_zip(
+                &archive,
+                &[
+                    ("ch1/", b""),
+                    ("ch1/001.jpg", b"first page"),
+                    ("notes.txt", b"text"),
+                ],
+                deflate,
+            );
+            assert_eq!(
+                image_paths(&archive).unwrap(),
+                [archive.join("ch1/001.jpg")]
+            );
+            assert_eq!(read(&archive.join("ch1/001.jpg")).unwrap(), b"first page");
+            assert_eq!(
+                read(&archive.join("missing.jpg")).unwrap_err().kind(),
+                io::ErrorKind::NotFound
+            );
+        }
+        let not_zip = temp_dir.path().join("fake.zip");
+        fs::write(&not_zip, b"text").unwrap();
+        assert!(image_paths(&not_zip).is_err());
+    }
+
+    #[test]
+    fn extract_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("photos.zip");
+        write_zip(&archive, &[("a.png", b"image")], true);
+        let album = temp_dir.path().join("album");
+        fs::create_dir(&album).unwrap();
+        let dest = extract(&archive.join("a.png"), &album).unwrap();
+        assert_eq!(dest, album.join("a.png"));
+        assert_eq!(fs::read(&dest).unwrap(), b"image");
+        // The archive is only read.
+        assert_eq!(image_paths(&archive).unwrap(), [archive.join("a.png")]);
+        assert_eq!(
+            extract(&archive.join("a.png"), &album).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+    }
+}
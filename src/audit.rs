@@ -0,0 +1,133 @@
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{file_ops, metadata, plan::csv_field};
+
+/// What was done to a file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Operation {
+    #[default]
+    Move,
+    // A hard link, a copy or an upload that left the file where it was.
+    Copy,
+    Rename,
+    Delete,
+    // A move, copy or rename taken back. The source is where the file was put.
+    Undo,
+}
+
+impl Operation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Move => "move",
+            Operation::Copy => "copy",
+            Operation::Rename => "rename",
+            Operation::Delete => "delete",
+            Operation::Undo => "undo",
+        }
+    }
+}
+
+/// When a file operation was done, and the size and BLAKE3 hash of the file, so that the log can
+/// tell later what exactly went where.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stamp {
+    pub operation: Operation,
+    // Unix time in seconds.
+    pub time: i64,
+    // Unknown if the file could not be read, e.g. after it was deleted or uploaded.
+    pub size: Option<u64>,
+    pub hash: Option<String>,
+}
+
+impl Stamp {
+    /// Stamps `operation` on `path` now. For a move or a copy, `path` is where the file went;
+    /// for a delete, the file before it is deleted.
+    pub fn of(operation: Operation, path: &Path) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64);
+        let size = fs::metadata(path)
+            .ok()
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len());
+        let hash = size.and_then(|_| file_ops::hash_file(path).ok());
+        Self {
+            operation,
+            time,
+            size,
+            hash,
+        }
+    }
+}
+
+/// The audit trail as CSV, one line for each `(source, destination, stamp)`. Deletes have no
+/// destination.
+pub fn to_csv(entries: &[(&Path, &Path, &Stamp)]) -> String {
+    let mut csv = String::from("date,operation,source,destination,size,hash\n");
+    for (src, dest, stamp) in entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            metadata::format_timestamp(stamp.time),
+            stamp.operation.name(),
+            csv_field(&src.to_string_lossy()),
+            csv_field(&dest.to_string_lossy()),
+            stamp.size.map(|size| size.to_string()).unwrap_or_default(),
+            stamp.hash.as_deref().unwrap_or_default()
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamp_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("a.png");
+        fs::write(&path, b"image").unwrap();
+        let stamp = Stamp::of(Operation::Move, &path);
+        assert_eq!(stamp.operation, Operation::Move);
+        assert!(stamp.time > 0);
+        assert_eq!(stamp.size, Some(5));
+        assert_eq!(stamp.hash, Some(file_ops::hash_file(&path).unwrap()));
+
+        let gone = Stamp::of(Operation::Delete, &temp_dir.path().join("gone.png"));
+        assert_eq!((gone.size, gone.hash), (None, None));
+        assert_eq!(Stamp::of(Operation::Copy, temp_dir.path()).size, None);
+    }
+
+    #[test]
+    fn to_csv_test() {
+        let moved = Stamp {
+            operation: Operation::Move,
+            time: 86400,
+            size: Some(5),
+            hash: Some("ab12".to_string()),
+        };
+        let deleted = Stamp {
+            operation: Operation::Delete,
+            time: 86400,
+            size: None,
+            hash: None,
+        };
+        let csv = to_csv(&[
+            (Path::new("/in/a,b.png"), Path::new("/out/a,b.png"), &moved),
+            (Path::new("/in/c.png"), Path::new(""), &deleted),
+        ]);
+        let date = metadata::format_timestamp(86400);
+        assert_eq!(
+            csv,
+            format!(
+                "date,operation,source,destination,size,hash\n\
+                 {date},move,\"/in/a,b.png\",\"/out/a,b.png\",5,ab12\n\
+                 {date},delete,/in/c.png,,,\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,401 @@
+use eframe::egui;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+/// Called by the work of a batch after each file with what happened to it. Returns false once
+/// the batch was cancelled, and the work should stop there.
+pub type OnFile<'a> = dyn FnMut(&Path, Result<(), String>) -> bool + 'a;
+
+enum Update<R> {
+    File(PathBuf, Result<(), String>),
+    Finished(R),
+}
+
+/// A multi-file operation, like moving many images, running on a worker thread. Keeps what
+/// happened to every file it got to so far.
+pub struct Batch<R> {
+    title: String,
+    // Files the work reports on when it is not cancelled.
+    total: usize,
+    receiver: mpsc::Receiver<Update<R>>,
+    cancelled: Arc<AtomicBool>,
+    files: Vec<(PathBuf, Result<(), String>)>,
+    finished: bool,
+    only_failures: bool,
+}
+
+impl<R: Send + 'static> Batch<R> {
+    /// Runs `work` on a worker thread. It goes through `total` files, calls the given `OnFile`
+    /// after each, and returns a report that `poll` hands back.
+    pub fn start(
+        title: impl Into<String>,
+        total: usize,
+        work: impl FnOnce(&mut OnFile) -> R + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let file_sender = sender.clone();
+            let mut on_file = |path: &Path, result| {
+                let _ = file_sender.send(Update::File(path.to_path_buf(), result));
+                !thread_cancelled.load(Ordering::Relaxed)
+            };
+            let report = work(&mut on_file);
+            let _ = sender.send(Update::Finished(report));
+        });
+        Self {
+            title: title.into(),
+            total,
+            receiver,
+            cancelled,
+            files: Vec::new(),
+            finished: false,
+            only_failures: false,
+        }
+    }
+}
+
+impl<R> Batch<R> {
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Stops after the file that is being worked on.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Number of files done and the total.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.files.len(), self.total)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.files
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .count()
+    }
+
+    /// Collects what happened to the files since the last call. Returns the report of the work
+    /// once, when it has finished.
+    pub fn poll(&mut self) -> Option<R> {
+        while !self.finished {
+            match self.receiver.try_recv() {
+                Ok(Update::File(path, result)) => self.files.push((path, result)),
+                Ok(Update::Finished(report)) => {
+                    self.finished = true;
+                    return Some(report);
+                }
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    log::error!("{} stopped without a report.", self.title);
+                    self.finished = true;
+                }
+            }
+        }
+        None
+    }
+
+    /// Draws the progress with a cancel button, every file with its result, and a summary once
+    /// the work has finished.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let (done, total) = self.progress();
+        let failed = self.failed();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                    .text(format!("{}/{}", done, total)),
+            );
+            if !self.finished
+                && ui
+                    .add_enabled(!self.is_cancelled(), egui::Button::new("Cancel"))
+                    .clicked()
+            {
+                self.cancel();
+            }
+        });
+        if self.finished {
+            ui.label(format!(
+                "Done. {} succeeded, {} failed.",
+                done - failed,
+                failed
+            ));
+            if done < total {
+                ui.label(format!(
+                    "Cancelled. {} files were left as they were.",
+                    total - done
+                ));
+            }
+        } else if self.is_cancelled() {
+            ui.label("Cancelling...");
+        }
+        ui.checkbox(&mut self.only_failures, "Only show failures");
+
+        let files: Vec<&(PathBuf, Result<(), String>)> = self
+            .files
+            .iter()
+            .filter(|(_, result)| !self.only_failures || result.is_err())
+            .collect();
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, files.len(), |ui, rows| {
+                for (path, result) in &files[rows] {
+                    let name = path
+                        .file_name()
+                        .unwrap_or(path.as_os_str())
+                        .to_string_lossy();
+                    match result {
+                        Ok(()) => ui.label(format!("✔ {}", name)),
+                        Err(e) => ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("✖ {}: {}", name, e),
+                        ),
+                    }
+                    .on_hover_text(path.to_string_lossy());
+                }
+            });
+    }
+}
+
+// What the worker of an `ImageScan` reports.
+enum Checked<R> {
+    Found(PathBuf, R),
+    Nothing,
+    // The check could not be prepared, so no image is checked.
+    Failed(String),
+}
+
+/// A check of many images, like finding damaged or blurry ones, running on a worker thread.
+/// Keeps what the check found in each image. Call `poll()` every frame to collect the results.
+pub struct ImageScan<R> {
+    receiver: mpsc::Receiver<Checked<R>>,
+    cancelled: Arc<AtomicBool>,
+    total: usize,
+    checked: usize,
+    error: Option<String>,
+    found: Vec<(PathBuf, R)>,
+}
+
+impl<R: Send + 'static> ImageScan<R> {
+    /// Runs `check` on each of `paths`. What it returns for an image is kept in `found()`.
+    pub fn start(
+        paths: Vec<PathBuf>,
+        check: impl FnMut(&Path) -> Option<R> + Send + 'static,
+    ) -> Self {
+        Self::start_with(paths, move || Ok(check))
+    }
+
+    /// Like `start()` with the check that `prepare` makes on the worker thread, e.g. by loading
+    /// a model. If it fails, the scan ends with its error.
+    pub fn start_with<C: FnMut(&Path) -> Option<R>>(
+        paths: Vec<PathBuf>,
+        prepare: impl FnOnce() -> Result<C, String> + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let total = paths.len();
+        let thread_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let mut check = match prepare() {
+                Ok(check) => check,
+                Err(e) => {
+                    let _ = sender.send(Checked::Failed(e));
+                    return;
+                }
+            };
+            for path in paths {
+                if thread_cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                let checked = match check(&path) {
+                    Some(found) => Checked::Found(path, found),
+                    None => Checked::Nothing,
+                };
+                if sender.send(checked).is_err() {
+                    // The scan was dropped.
+                    return;
+                }
+            }
+        });
+        Self {
+            receiver,
+            cancelled,
+            total,
+            checked: 0,
+            error: None,
+            found: Vec::new(),
+        }
+    }
+}
+
+impl<R> ImageScan<R> {
+    /// Collects the results that arrived since the last call. Returns what was found in them.
+    pub fn poll(&mut self) -> &[(PathBuf, R)] {
+        let start = self.found.len();
+        while let Ok(checked) = self.receiver.try_recv() {
+            match checked {
+                Checked::Found(path, found) => {
+                    self.checked += 1;
+                    self.found.push((path, found));
+                }
+                Checked::Nothing => self.checked += 1,
+                Checked::Failed(e) => self.error = Some(e),
+            }
+        }
+        &self.found[start..]
+    }
+
+    /// Stops after the image that is being checked.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.checked == self.total || self.error.is_some() || self.is_cancelled()
+    }
+
+    /// Number of checked images and the total.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.checked, self.total)
+    }
+
+    /// Why no image could be checked.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn found(&self) -> &[(PathBuf, R)] {
+        &self.found
+    }
+
+    /// Takes what was found so far, for a caller that keeps it in another form.
+    pub fn drain_found(&mut self) -> impl Iterator<Item = (PathBuf, R)> + '_ {
+        self.found.drain(..)
+    }
+
+    /// Drops what was found in `path`, e.g. once the file has been dealt with.
+    pub fn forget(&mut self, path: &Path) {
+        self.found.retain(|(found, _)| found != path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wait<R>(batch: &mut Batch<R>) -> Option<R> {
+        for _ in 0..500 {
+            if let Some(report) = batch.poll() {
+                return Some(report);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        None
+    }
+
+    #[test]
+    fn batch_test() {
+        let paths: Vec<PathBuf> = ["a.png", "b.png", "c.png"].map(PathBuf::from).into();
+        let mut batch = Batch::start("Test", paths.len(), move |on_file: &mut OnFile| {
+            for path in &paths {
+                let result = if path.ends_with("b.png") {
+                    Err("in use".to_string())
+                } else {
+                    Ok(())
+                };
+                if !on_file(path, result) {
+                    break;
+                }
+            }
+            paths.len()
+        });
+        assert_eq!(wait(&mut batch), Some(3));
+        assert!(batch.is_finished());
+        assert_eq!(batch.progress(), (3, 3));
+        assert_eq!(batch.failed(), 1);
+        assert_eq!(batch.poll(), None);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let (sender, receiver) = mpsc::channel::<()>();
+        let mut batch = Batch::start("Test", 3, move |on_file: &mut OnFile| {
+            let mut done = 0;
+            for name in ["a.png", "b.png", "c.png"] {
+                // Waits for the test to cancel before the first file is reported.
+                let _ = receiver.recv();
+                done += 1;
+                if !on_file(Path::new(name), Ok(())) {
+                    break;
+                }
+            }
+            done
+        });
+        batch.cancel();
+        drop(sender);
+        assert_eq!(wait(&mut batch), Some(1));
+        assert!(batch.is_cancelled());
+        assert_eq!(batch.progress(), (1, 3));
+    }
+
+    fn wait_for_scan<R>(scan: &mut ImageScan<R>) {
+        for _ in 0..500 {
+            scan.poll();
+            if scan.is_done() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn image_scan_test() {
+        let paths: Vec<PathBuf> = ["a.png", "bb.png", "ccc.png"].map(PathBuf::from).into();
+        let mut scan = ImageScan::start(paths, |path| {
+            let length = path.as_os_str().len();
+            (length > 5).then_some(length)
+        });
+        wait_for_scan(&mut scan);
+        assert_eq!(scan.progress(), (3, 3));
+        assert_eq!(
+            scan.found(),
+            [(PathBuf::from("bb.png"), 6), (PathBuf::from("ccc.png"), 7)]
+        );
+        assert!(scan.poll().is_empty());
+        scan.forget(Path::new("bb.png"));
+        assert_eq!(scan.found().len(), 1);
+        assert_eq!(scan.drain_found().count(), 1);
+        assert!(scan.found().is_empty());
+
+        let mut failed = ImageScan::<()>::start_with(vec![PathBuf::from("a.png")], || {
+            Err::<fn(&Path) -> Option<()>, _>("No model".to_string())
+        });
+        wait_for_scan(&mut failed);
+        assert!(failed.is_done());
+        assert_eq!(failed.error(), Some("No model"));
+        assert_eq!(failed.progress(), (0, 1));
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use crate::metadata;
+
+// Phones mark the shots of a burst in their names, e.g. "IMG_20240101_120000_BURST001.jpg".
+const BURST_MARKER: &str = "BURST";
+
+// Shots of a burst that is not marked are at most this far apart. A camera shooting 10 frames a
+// second crosses into the next second within a burst.
+const MAX_GAP_SECS: i64 = 1;
+
+// What comes before the burst marker in `stem`, which all shots of that burst share.
+fn marked_burst(stem: &str) -> Option<&str> {
+    let position = stem.to_ascii_uppercase().find(BURST_MARKER)?;
+    Some(&stem[..position])
+}
+
+// Splits "IMG_1234" into "IMG_" and 1234, and the number of digits.
+fn split_number(stem: &str) -> Option<(&str, u64, usize)> {
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let (prefix, number) = stem.split_at(stem.len() - digits);
+    Some((prefix, number.parse().ok()?, digits))
+}
+
+// Whether `a` and `b` are the names a camera gives consecutive shots, e.g. "IMG_1234" and
+// "IMG_1235". Either may come first, as sorting by date lists the newest first.
+fn is_sequential(a: &str, b: &str) -> bool {
+    match (split_number(a), split_number(b)) {
+        (Some((a_prefix, a_number, a_digits)), Some((b_prefix, b_number, b_digits))) => {
+            a_prefix == b_prefix && a_digits == b_digits && a_number.abs_diff(b_number) == 1
+        }
+        _ => false,
+    }
+}
+
+/// Whether `a` and `b`, listed next to each other, are shots of the same burst: they are in one
+/// folder, and either both are marked as shots of one burst, or they have sequential names and
+/// were taken within a second of each other according to `dates`.
+pub fn same_burst(a: &Path, b: &Path, dates: impl Fn(&Path) -> Option<String>) -> bool {
+    if a.parent() != b.parent() {
+        return false;
+    }
+    let (Some(a_stem), Some(b_stem)) = (
+        a.file_stem().and_then(|stem| stem.to_str()),
+        b.file_stem().and_then(|stem| stem.to_str()),
+    ) else {
+        return false;
+    };
+    if let (Some(a_burst), Some(b_burst)) = (marked_burst(a_stem), marked_burst(b_stem)) {
+        return a_burst == b_burst;
+    }
+    if !is_sequential(a_stem, b_stem) {
+        return false;
+    }
+    match (
+        dates(a).as_deref().and_then(metadata::parse_timestamp),
+        dates(b).as_deref().and_then(metadata::parse_timestamp),
+    ) {
+        (Some(a), Some(b)) => (b - a).abs() <= MAX_GAP_SECS,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, path::PathBuf};
+
+    #[test]
+    fn same_burst_test() {
+        let dates: HashMap<PathBuf, String> = [
+            ("/photos/IMG_0099.JPG", "2024-01-31 12:00:00"),
+            ("/photos/IMG_0100.JPG", "2024-01-31 12:00:00"),
+            ("/photos/IMG_0101.JPG", "2024-01-31 12:00:01"),
+            ("/photos/IMG_0102.JPG", "2024-01-31 12:05:00"),
+            ("/other/IMG_0103.JPG", "2024-01-31 12:05:00"),
+        ]
+        .into_iter()
+        .map(|(path, date)| (PathBuf::from(path), date.to_string()))
+        .collect();
+        let same = |a: &str, b: &str| {
+            same_burst(Path::new(a), Path::new(b), |path| dates.get(path).cloned())
+        };
+        assert!(same("/photos/IMG_0099.JPG", "/photos/IMG_0100.JPG"));
+        assert!(same("/photos/IMG_0100.JPG", "/photos/IMG_0101.JPG"));
+        assert!(same("/photos/IMG_0101.JPG", "/photos/IMG_0100.JPG"));
+        // Minutes apart.
+        assert!(!same("/photos/IMG_0101.JPG", "/photos/IMG_0102.JPG"));
+        // Not sequential.
+        assert!(!same("/photos/IMG_0099.JPG", "/photos/IMG_0101.JPG"));
+        // Another folder.
+        assert!(!same("/photos/IMG_0102.JPG", "/other/IMG_0103.JPG"));
+        // Without dates, only marked bursts are known.
+        assert!(!same("/photos/IMG_0200.JPG", "/photos/IMG_0201.JPG"));
+        assert!(same(
+            "/photos/IMG_20240131_120000_BURST001.jpg",
+            "/photos/IMG_20240131_120000_BURST002_COVER.jpg"
+        ));
+        assert!(!same(
+            "/photos/IMG_20240131_120000_BURST001.jpg",
+            "/photos/IMG_20240131_130000_BURST001.jpg"
+        ));
+    }
+}
@@ -0,0 +1,378 @@
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult, RgbaImage};
+use std::{
+    io::{BufRead, Seek, SeekFrom},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::hdr;
+
+// Whether images with an embedded color profile, or the HDR signal of a PNG, are converted to
+// sRGB. Set from the settings, and read by decoders on any thread.
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns the conversion to sRGB on or off. Returns true if that changed it, in which case
+/// decoded images are stale.
+pub fn set_enabled(enabled: bool) -> bool {
+    ENABLED.swap(enabled, Ordering::Relaxed) != enabled
+}
+
+// XYZ (D50, as ICC profiles are) to linear sRGB, adapted with Bradford.
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.133_856, -1.616_867, -0.490_615],
+    [-0.978_768, 1.916_142, 0.033_454],
+    [0.071_945, -0.228_991, 1.405_243],
+];
+
+// The primaries of sRGB in the same terms as the rXYZ, gXYZ, and bXYZ tags of a profile.
+const SRGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.436_075, 0.385_065, 0.143_080],
+    [0.222_504, 0.716_879, 0.060_617],
+    [0.013_932, 0.097_105, 0.714_173],
+];
+
+// A tone curve of a profile, from encoded values to linear light. Both are 0 to 1.
+#[derive(Clone, Debug, PartialEq)]
+enum Curve {
+    Gamma(f32),
+    Table(Vec<f32>),
+    // The parametric curve of ICC.1 10.18 with parameters g, a, b, c, d, e, f.
+    Parametric([f32; 7]),
+}
+
+impl Curve {
+    fn linear(&self, value: f32) -> f32 {
+        match self {
+            Curve::Gamma(gamma) => value.powf(*gamma),
+            Curve::Table(table) => {
+                let position = value * (table.len() - 1) as f32;
+                let index = (position.floor() as usize).min(table.len() - 2);
+                let fraction = position - index as f32;
+                table[index] + (table[index + 1] - table[index]) * fraction
+            }
+            Curve::Parametric([g, a, b, c, d, e, f]) => {
+                if value >= *d {
+                    (a * value + b).max(0.0).powf(*g) + e
+                } else {
+                    c * value + f
+                }
+            }
+        }
+    }
+}
+
+/// An RGB profile of the matrix and tone curve kind, like Display P3, Adobe RGB, and the
+/// profiles of most cameras and phones. Profiles with lookup tables are not supported.
+#[derive(Clone, Debug, PartialEq)]
+struct Profile {
+    // Columns are the XYZ of the red, green, and blue primaries.
+    to_xyz: [[f32; 3]; 3],
+    curves: [Curve; 3],
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn s15_fixed16_at(data: &[u8], offset: usize) -> Option<f32> {
+    Some(u32_at(data, offset)? as i32 as f32 / 65536.0)
+}
+
+// The data of the tag with the signature `signature`.
+fn tag<'a>(icc: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = u32_at(icc, 128)? as usize;
+    (0..count).find_map(|i| {
+        let entry = 132 + i * 12;
+        if icc.get(entry..entry + 4)? != signature {
+            return None;
+        }
+        let offset = u32_at(icc, entry + 4)? as usize;
+        let size = u32_at(icc, entry + 8)? as usize;
+        icc.get(offset..offset.checked_add(size)?)
+    })
+}
+
+fn read_xyz(data: &[u8]) -> Option<[f32; 3]> {
+    if data.get(..4)? != b"XYZ " {
+        return None;
+    }
+    Some([
+        s15_fixed16_at(data, 8)?,
+        s15_fixed16_at(data, 12)?,
+        s15_fixed16_at(data, 16)?,
+    ])
+}
+
+fn read_curve(data: &[u8]) -> Option<Curve> {
+    match data.get(..4)? {
+        b"curv" => {
+            let count = u32_at(data, 8)? as usize;
+            match count {
+                0 => Some(Curve::Gamma(1.0)),
+                1 => Some(Curve::Gamma(f32::from(u16_at(data, 12)?) / 256.0)),
+                _ => (0..count)
+                    .map(|i| Some(f32::from(u16_at(data, 12 + i * 2)?) / 65535.0))
+                    .collect::<Option<Vec<f32>>>()
+                    .map(Curve::Table),
+            }
+        }
+        b"para" => {
+            let function = u16_at(data, 8)?;
+            let count = match function {
+                0 => 1,
+                1 => 3,
+                2 => 4,
+                3 => 5,
+                4 => 7,
+                _ => return None,
+            };
+            let mut read = [0.0; 7];
+            for (i, value) in read.iter_mut().take(count).enumerate() {
+                *value = s15_fixed16_at(data, 12 + i * 4)?;
+            }
+            let [g, a, b, c, d, e, f] = read;
+            // Every function type as type 4: Y = (aX + b)^g + e for X >= d, else cX + f.
+            let parameters = match function {
+                0 => [g, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                1 => [g, a, b, 0.0, -b / a, 0.0, 0.0],
+                2 => [g, a, b, 0.0, -b / a, c, c],
+                3 => [g, a, b, c, d, 0.0, 0.0],
+                _ => [g, a, b, c, d, e, f],
+            };
+            Some(Curve::Parametric(parameters))
+        }
+        _ => None,
+    }
+}
+
+fn parse(icc: &[u8]) -> Option<Profile> {
+    // The color space of the data, and the profile connection space.
+    if icc.get(16..20)? != b"RGB " || icc.get(20..24)? != b"XYZ " {
+        return None;
+    }
+    let primaries = [b"rXYZ", b"gXYZ", b"bXYZ"].map(|signature| read_xyz(tag(icc, signature)?));
+    let curves = [b"rTRC", b"gTRC", b"bTRC"].map(|signature| read_curve(tag(icc, signature)?));
+    let [Some(red), Some(green), Some(blue)] = primaries else {
+        return None;
+    };
+    let [Some(red_curve), Some(green_curve), Some(blue_curve)] = curves else {
+        return None;
+    };
+    let to_xyz = [0, 1, 2].map(|row| [red[row], green[row], blue[row]]);
+    Some(Profile {
+        to_xyz,
+        curves: [red_curve, green_curve, blue_curve],
+    })
+}
+
+impl Profile {
+    // Profiles that only differ from sRGB by rounding, e.g. the sRGB profiles that many
+    // cameras and editors embed, need no conversion.
+    fn is_srgb(&self) -> bool {
+        let same_primaries = self
+            .to_xyz
+            .iter()
+            .flatten()
+            .zip(SRGB_TO_XYZ.iter().flatten())
+            .all(|(a, b)| (a - b).abs() < 0.002);
+        let srgb_curve = |curve: &Curve| {
+            [0.04, 0.2, 0.5, 0.8]
+                .iter()
+                .all(|&value| (curve.linear(value) - srgb_to_linear(value)).abs() < 0.002)
+        };
+        same_primaries && self.curves.iter().all(srgb_curve)
+    }
+
+    /// Converts `image` from this profile to sRGB. Alpha stays as it is.
+    fn convert(&self, image: &DynamicImage) -> RgbaImage {
+        let mut matrix = [[0.0; 3]; 3];
+        for (row, out) in matrix.iter_mut().enumerate() {
+            for (column, value) in out.iter_mut().enumerate() {
+                *value = (0..3)
+                    .map(|k| XYZ_TO_SRGB[row][k] * self.to_xyz[k][column])
+                    .sum();
+            }
+        }
+        let linear: [Vec<f32>; 3] = [0, 1, 2].map(|channel| {
+            (0..=255)
+                .map(|value| self.curves[channel].linear(value as f32 / 255.0))
+                .collect()
+        });
+        // Encoding back to sRGB in steps fine enough for 8 bits.
+        const STEPS: usize = 4096;
+        let encode: Vec<u8> = (0..=STEPS)
+            .map(|i| (linear_to_srgb(i as f32 / STEPS as f32) * 255.0).round() as u8)
+            .collect();
+        let mut rgba = image.to_rgba8();
+        for pixel in rgba.pixels_mut() {
+            let input = [0, 1, 2].map(|channel| linear[channel][pixel[channel] as usize]);
+            for (channel, row) in matrix.iter().enumerate() {
+                let value = row[0] * input[0] + row[1] * input[1] + row[2] * input[2];
+                pixel[channel] = encode[(value.clamp(0.0, 1.0) * STEPS as f32).round() as usize];
+            }
+        }
+        rgba
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes linear light, 0 to 1, with the sRGB curve.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts `image` with the embedded color profile `icc` to sRGB, which is what the screen is
+/// assumed to show. Images stay as they are if color management is off, or the profile is sRGB
+/// or not supported.
+pub fn to_srgb(image: DynamicImage, icc: &[u8]) -> DynamicImage {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return image;
+    }
+    match parse(icc) {
+        Some(profile) if !profile.is_srgb() => DynamicImage::ImageRgba8(profile.convert(&image)),
+        Some(_) => image,
+        None => {
+            log::debug!("Unsupported color profile. Showing the image as sRGB.");
+            image
+        }
+    }
+}
+
+/// Tone maps `image`, whose code values are those of the HDR `signal`, to sRGB. Images stay as
+/// they are if color management is off.
+pub fn apply_signal(image: DynamicImage, signal: hdr::Signal) -> DynamicImage {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return image;
+    }
+    DynamicImage::ImageRgba8(hdr::tone_map_signal(&image, signal))
+}
+
+/// Decodes the image of `reader` and converts it to sRGB if it has a color profile. HDR images
+/// are tone mapped.
+pub fn decode<R: BufRead + Seek>(reader: ImageReader<R>) -> ImageResult<DynamicImage> {
+    let mut reader = reader.with_guessed_format()?;
+    let mut signal = None;
+    if let Some(ImageFormat::Png) = reader.format() {
+        let mut inner = reader.into_inner();
+        let start = inner.stream_position()?;
+        // A damaged PNG is left for the decoder to report.
+        signal = hdr::png_signal(&mut inner).ok().flatten();
+        inner.seek(SeekFrom::Start(start))?;
+        reader = ImageReader::with_format(inner, ImageFormat::Png);
+    }
+    let mut decoder = reader.into_decoder()?;
+    let icc = decoder.icc_profile().ok().flatten();
+    let image = DynamicImage::from_decoder(decoder)?;
+    if hdr::is_scene_referred(&image) {
+        return Ok(DynamicImage::ImageRgba8(hdr::tone_map_scene(&image)));
+    }
+    // The signal describes the colors of the image in place of a profile.
+    if let Some(signal) = signal {
+        return Ok(apply_signal(image, signal));
+    }
+    Ok(match icc {
+        Some(icc) => to_srgb(image, &icc),
+        None => image,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn s15_fixed16(value: f32) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+
+    // A matrix and tone curve profile with the primaries `to_xyz` and the sRGB curve.
+    fn profile(to_xyz: [[f32; 3]; 3]) -> Vec<u8> {
+        let mut tags: Vec<(&[u8; 4], Vec<u8>)> = Vec::new();
+        for (column, signature) in [b"rXYZ", b"gXYZ", b"bXYZ"].into_iter().enumerate() {
+            let mut data = b"XYZ \0\0\0\0".to_vec();
+            for row in to_xyz {
+                data.extend(s15_fixed16(row[column]));
+            }
+            tags.push((signature, data));
+        }
+        let mut curve = b"para\0\0\0\0\0\x03\0\0".to_vec();
+        for value in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+            curve.extend(s15_fixed16(value));
+        }
+        for signature in [b"rTRC", b"gTRC", b"bTRC"] {
+            tags.push((signature, curve.clone()));
+        }
+        let mut icc = vec![0; 128];
+        icc[16..20].copy_from_slice(b"RGB ");
+        icc[20..24].copy_from_slice(b"XYZ ");
+        icc.extend((tags.len() as u32).to_be_bytes());
+        let mut offset = 132 + tags.len() * 12;
+        let mut data: Vec<u8> = Vec::new();
+        for (signature, tag) in &tags {
+            icc.extend(*signature);
+            icc.extend((offset as u32).to_be_bytes());
+            icc.extend((tag.len() as u32).to_be_bytes());
+            offset += tag.len();
+            data.extend(tag);
+        }
+        icc.extend(data);
+        icc
+    }
+
+    const DISPLAY_P3_TO_XYZ: [[f32; 3]; 3] = [
+        [0.515_102, 0.291_965, 0.157_153],
+        [0.241_196, 0.692_236, 0.066_574],
+        [-0.001_050, 0.041_882, 0.784_073],
+    ];
+
+    #[test]
+    fn parse_test() {
+        let srgb = parse(&profile(SRGB_TO_XYZ)).unwrap();
+        assert!(srgb.is_srgb());
+        let p3 = parse(&profile(DISPLAY_P3_TO_XYZ)).unwrap();
+        assert!(!p3.is_srgb());
+        assert!((p3.to_xyz[0][0] - 0.515_102).abs() < 0.0001);
+        assert!((p3.curves[0].linear(0.5) - srgb_to_linear(0.5)).abs() < 0.001);
+        assert_eq!(parse(b"not a profile"), None);
+    }
+
+    #[test]
+    fn to_srgb_test() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 1, |x, _| match x {
+            0 => Rgba([128, 128, 128, 255]),
+            _ => Rgba([200, 0, 0, 128]),
+        }));
+        let converted = to_srgb(image.clone(), &profile(DISPLAY_P3_TO_XYZ)).to_rgba8();
+        // Gray stays gray.
+        let gray = converted.get_pixel(0, 0);
+        assert!(gray.0[..3].iter().all(|&value| value.abs_diff(128) <= 1));
+        // The red of Display P3 is redder than sRGB can show.
+        let red = converted.get_pixel(1, 0);
+        assert!((215..=221).contains(&red[0]), "{:?}", red);
+        assert_eq!(&red.0[1..], [0, 0, 128]);
+
+        // An sRGB profile changes nothing.
+        assert_eq!(to_srgb(image.clone(), &profile(SRGB_TO_XYZ)), image);
+        assert!(set_enabled(false));
+        assert_eq!(to_srgb(image.clone(), &profile(DISPLAY_P3_TO_XYZ)), image);
+        assert!(set_enabled(true));
+    }
+}
@@ -0,0 +1,179 @@
+use eframe::egui;
+
+// Rows shown at most. Typing more narrows them down.
+const MAX_ROWS: usize = 12;
+
+// Characters after which a new word starts, e.g. in "trips/2024-kyoto".
+const WORD_SEPARATORS: [char; 6] = [' ', '/', '\\', '-', '_', '.'];
+
+/// How an entry of the palette is shown.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaletteItem {
+    pub label: String,
+    // Shown dimmed after the label, e.g. the key of a destination.
+    pub detail: String,
+}
+
+/// How well `query` matches `text`, higher is better, or None if it does not match. The
+/// characters of the query must appear in the text in order. Runs of them and matches at the
+/// start of words count more, and shorter texts win ties.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|&t| t == c)?;
+        score += 10;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 50;
+        }
+        if found == 0 || WORD_SEPARATORS.contains(&text[found - 1]) {
+            score += 30;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score - text.len() as i32)
+}
+
+/// The indices of the items that match `query`, best first. Items that match equally well keep
+/// their order.
+pub fn best_matches(query: &str, items: &[PaletteItem]) -> Vec<usize> {
+    if query.trim().is_empty() {
+        return (0..items.len().min(MAX_ROWS)).collect();
+    }
+    let mut scored: Vec<(i32, usize)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| Some((fuzzy_score(query, &item.label)?, index)))
+        .collect();
+    scored.sort_by_key(|&(score, index)| (-score, index));
+    scored
+        .into_iter()
+        .take(MAX_ROWS)
+        .map(|(_, index)| index)
+        .collect()
+}
+
+/// A box to type in that finds one of its entries and returns what it stands for.
+pub struct CommandPalette<T> {
+    items: Vec<(PaletteItem, T)>,
+    open: bool,
+    query: String,
+    // The row that Enter picks.
+    selected: usize,
+}
+
+impl<T> Default for CommandPalette<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            open: false,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+}
+
+impl<T: Clone> CommandPalette<T> {
+    pub fn open(&mut self, items: Vec<(PaletteItem, T)>) {
+        self.items = items;
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Draws the palette while it is open. Returns the entry picked with Enter or a click, after
+    /// which the palette is closed.
+    pub fn show(&mut self, ctx: &egui::Context, hint: &str) -> Option<T> {
+        if !self.open {
+            return None;
+        }
+        let items: Vec<PaletteItem> = self.items.iter().map(|(item, _)| item.clone()).collect();
+        let mut picked = None;
+        let response = egui::Modal::new(egui::Id::new("command_palette")).show(ctx, |ui| {
+            ui.set_width(480.0);
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut self.query)
+                    .hint_text(hint)
+                    .desired_width(f32::INFINITY),
+            );
+            edit.request_focus();
+            if edit.changed() {
+                self.selected = 0;
+            }
+            let matches = best_matches(&self.query, &items);
+            ui.input(|input| {
+                if input.key_pressed(egui::Key::ArrowDown) {
+                    self.selected += 1;
+                }
+                if input.key_pressed(egui::Key::ArrowUp) {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+            });
+            self.selected = self.selected.min(matches.len().saturating_sub(1));
+            if ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                picked = matches.get(self.selected).copied();
+            }
+            for (row, &index) in matches.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let item = &items[index];
+                    if ui
+                        .selectable_label(row == self.selected, &item.label)
+                        .clicked()
+                    {
+                        picked = Some(index);
+                    }
+                    ui.weak(&item.detail);
+                });
+            }
+            if matches.is_empty() {
+                ui.weak("No matches.");
+            }
+        });
+        if response.should_close() || picked.is_some() {
+            self.open = false;
+        }
+        picked.map(|index| self.items[index].1.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_test() {
+        assert!(fuzzy_score("kyo", "trips/2024 Kyoto").is_some());
+        assert!(fuzzy_score("tk", "trips/2024 Kyoto").is_some());
+        assert!(fuzzy_score("kt", "Kyoto").is_some());
+        assert!(fuzzy_score("ky2", "trips/2024 Kyoto").is_none());
+        assert_eq!(fuzzy_score("", "cats"), Some(-4));
+        // A run beats scattered letters, and the start of a word beats the middle of one.
+        assert!(fuzzy_score("cat", "cats") > fuzzy_score("cat", "c-a-t"));
+        assert!(fuzzy_score("dog", "pets/dogs") > fuzzy_score("dog", "hotdogs"));
+        // Shorter wins a tie.
+        assert!(fuzzy_score("cat", "cats") > fuzzy_score("cat", "cats-old"));
+    }
+
+    #[test]
+    fn best_matches_test() {
+        let items: Vec<PaletteItem> = ["family", "pets/cats", "pets/dogs", "screenshots"]
+            .into_iter()
+            .map(|label| PaletteItem {
+                label: label.to_string(),
+                detail: String::new(),
+            })
+            .collect();
+        assert_eq!(best_matches("", &items), vec![0, 1, 2, 3]);
+        assert_eq!(best_matches("dog", &items), vec![2]);
+        assert_eq!(best_matches("s", &items)[0], 3);
+        assert_eq!(best_matches("pets", &items), vec![1, 2]);
+        assert!(best_matches("xyz", &items).is_empty());
+    }
+}
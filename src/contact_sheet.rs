@@ -0,0 +1,528 @@
+use ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use anyhow::{bail, Context, Result};
+use eframe::egui;
+use image::{imageops, Rgb, RgbImage};
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use crate::{file_ops, i18n::tr, raw};
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const TEXT: Rgb<u8> = Rgb([40, 40, 40]);
+// Drawn in place of an image that cannot be decoded.
+const PLACEHOLDER: Rgb<u8> = Rgb([220, 220, 220]);
+// Space around each cell, in pixels.
+const MARGIN: u32 = 12;
+const FONT_SIZE: f32 = 14.0;
+// PDF pages are as wide as A4, in points, and hold about as many rows as fit on A4.
+const A4_WIDTH: f32 = 595.0;
+const A4_ASPECT: f32 = 842.0 / 595.0;
+
+/// How the contact sheet looks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SheetOptions {
+    pub columns: u32,
+    // The longer side of each thumbnail, in pixels.
+    pub thumbnail_size: u32,
+    pub file_names: bool,
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self {
+            columns: 5,
+            thumbnail_size: 240,
+            file_names: true,
+        }
+    }
+}
+
+impl SheetOptions {
+    fn label_height(&self) -> u32 {
+        if self.file_names {
+            FONT_SIZE.ceil() as u32 + 6
+        } else {
+            0
+        }
+    }
+
+    fn cell_size(&self) -> (u32, u32) {
+        (
+            self.thumbnail_size + MARGIN,
+            self.thumbnail_size + self.label_height() + MARGIN,
+        )
+    }
+
+    // Rows on a PDF page, so that the pages come out about as tall as A4.
+    fn rows_per_page(&self) -> usize {
+        let (width, height) = self.cell_size();
+        let rows = (self.columns * width) as f32 * A4_ASPECT / height as f32;
+        (rows.floor() as usize).max(1)
+    }
+}
+
+// Shortens `name` with an ellipsis in the middle until it is at most `max_width` pixels wide.
+// The end is kept, as it tells the numbered files of a camera apart.
+fn fit_name(font: &impl Font, name: &str, max_width: f32) -> String {
+    let font = font.as_scaled(PxScale::from(FONT_SIZE));
+    let width =
+        |text: &str| -> f32 { text.chars().map(|c| font.h_advance(font.glyph_id(c))).sum() };
+    if width(name) <= max_width {
+        return name.to_string();
+    }
+    let chars: Vec<char> = name.chars().collect();
+    let mut keep = chars.len();
+    while keep > 0 {
+        keep -= 1;
+        let head: String = chars[..keep / 2].iter().collect();
+        let tail: String = chars[chars.len() - (keep - keep / 2)..].iter().collect();
+        let shortened = format!("{}…{}", head, tail);
+        if width(&shortened) <= max_width {
+            return shortened;
+        }
+    }
+    "…".to_string()
+}
+
+// Draws `text` centered on `center_x`, with its top at `top`.
+fn draw_text(image: &mut RgbImage, font: &impl Font, text: &str, center_x: f32, top: f32) {
+    let scaled = font.as_scaled(PxScale::from(FONT_SIZE));
+    let width: f32 = text
+        .chars()
+        .map(|c| scaled.h_advance(scaled.glyph_id(c)))
+        .sum();
+    let mut x = center_x - width / 2.0;
+    let baseline = top + scaled.ascent();
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        let glyph = id.with_scale_and_position(PxScale::from(FONT_SIZE), point(x, baseline));
+        x += scaled.h_advance(id);
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + i64::from(gx);
+            let py = bounds.min.y as i64 + i64::from(gy);
+            if px < 0 || py < 0 || px >= image.width() as i64 || py >= image.height() as i64 {
+                return;
+            }
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            for channel in 0..3 {
+                let background = f32::from(pixel[channel]);
+                let blended = background + (f32::from(TEXT[channel]) - background) * coverage;
+                pixel[channel] = blended.round() as u8;
+            }
+        });
+    }
+}
+
+// Renders `paths` into a grid of `options.columns` columns. `on_image` is called after each
+// image and stops the rendering when it returns false.
+fn render(
+    paths: &[PathBuf],
+    options: &SheetOptions,
+    font: &impl Font,
+    on_image: &mut dyn FnMut() -> bool,
+) -> Option<RgbImage> {
+    let columns = options.columns.max(1);
+    let rows = (paths.len() as u32).div_ceil(columns).max(1);
+    let (cell_width, cell_height) = options.cell_size();
+    let mut sheet = RgbImage::from_pixel(
+        columns * cell_width + MARGIN,
+        rows * cell_height + MARGIN,
+        BACKGROUND,
+    );
+    let size = options.thumbnail_size;
+    for (i, path) in paths.iter().enumerate() {
+        let left = MARGIN + (i as u32 % columns) * cell_width;
+        let top = MARGIN + (i as u32 / columns) * cell_height;
+        match raw::decode_file(path) {
+            Ok(image) => {
+                let thumbnail = image.thumbnail(size, size).to_rgb8();
+                // Centered in its square.
+                let x = left + (size - thumbnail.width()) / 2;
+                let y = top + (size - thumbnail.height()) / 2;
+                imageops::replace(&mut sheet, &thumbnail, x.into(), y.into());
+            }
+            Err(e) => {
+                log::warn!("Failed to decode {}: {}", path.display(), e);
+                let placeholder = RgbImage::from_pixel(size, size, PLACEHOLDER);
+                imageops::replace(&mut sheet, &placeholder, left.into(), top.into());
+            }
+        }
+        if options.file_names {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let name = fit_name(font, &name, size as f32);
+            let center = left as f32 + size as f32 / 2.0;
+            draw_text(&mut sheet, font, &name, center, (top + size + 4) as f32);
+        }
+        if !on_image() {
+            return None;
+        }
+    }
+    Some(sheet)
+}
+
+/// A PDF with one page for each of `pages`. The pages are as wide as A4.
+fn pdf(pages: &[RgbImage]) -> Result<Vec<u8>> {
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    // 1 is the catalog and 2 the page tree. Each page takes three objects after them.
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 3 + i * 3).collect();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    objects.push(
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+    );
+    for (page, id) in pages.iter().zip(&page_ids) {
+        let width = A4_WIDTH;
+        let height = A4_WIDTH * page.height() as f32 / page.width() as f32;
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                width,
+                height,
+                id + 2,
+                id + 1
+            )
+            .into_bytes(),
+        );
+        let content = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", width, height);
+        objects.push(
+            format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.len(),
+                content
+            )
+            .into_bytes(),
+        );
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut Cursor::new(&mut jpeg), 90)
+            .encode_image(page)?;
+        let mut image = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+            page.width(),
+            page.height(),
+            jpeg.len()
+        )
+        .into_bytes();
+        image.extend(jpeg);
+        image.extend(b"\nendstream");
+        objects.push(image);
+    }
+
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        out.extend(object);
+        out.extend(b"\nendobj\n");
+    }
+    let xref = out.len();
+    out.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        out.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    out.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .into_bytes(),
+    );
+    Ok(out)
+}
+
+fn is_pdf(dest: &Path) -> bool {
+    dest.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+// Renders the sheet of `paths` and writes it to `dest`, a PDF or an image in the format of its
+// extension. Returns false if `on_image` cancelled it.
+fn save(
+    paths: &[PathBuf],
+    options: &SheetOptions,
+    font: &impl Font,
+    dest: &Path,
+    on_image: &mut dyn FnMut() -> bool,
+) -> Result<bool> {
+    if paths.is_empty() {
+        bail!(tr!("no-images-for-sheet"));
+    }
+    let bytes = if is_pdf(dest) {
+        let mut pages = Vec::new();
+        let per_page = options.rows_per_page() * options.columns.max(1) as usize;
+        for chunk in paths.chunks(per_page) {
+            match render(chunk, options, font, on_image) {
+                Some(page) => pages.push(page),
+                None => return Ok(false),
+            }
+        }
+        pdf(&pages)?
+    } else {
+        let Some(sheet) = render(paths, options, font, on_image) else {
+            return Ok(false);
+        };
+        let format = image::ImageFormat::from_path(dest).unwrap_or(image::ImageFormat::Png);
+        let mut bytes = Vec::new();
+        sheet
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .with_context(|| tr!("encode-failed", path = dest.display().to_string()))?;
+        bytes
+    };
+    // Written under a temporary name, so that a cancelled or failed export leaves nothing.
+    let partial = file_ops::partial_path(dest);
+    fs::write(&partial, bytes)
+        .and_then(|()| fs::rename(&partial, dest))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&partial);
+        })?;
+    Ok(true)
+}
+
+/// A contact sheet being rendered on a worker thread.
+struct Export {
+    dest: PathBuf,
+    total: usize,
+    done: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+    receiver: mpsc::Receiver<Result<bool, String>>,
+}
+
+/// State of the "Contact sheet" window.
+#[derive(Default)]
+pub struct ContactSheetTool {
+    options: SheetOptions,
+    export: Option<Export>,
+    // What the last export ended with, for the window.
+    outcome: Option<Result<PathBuf, String>>,
+}
+
+impl ContactSheetTool {
+    fn start(&mut self, paths: Vec<PathBuf>, font: FontArc, dest: PathBuf) {
+        let done = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let options = self.options;
+        let thread_done = done.clone();
+        let thread_cancelled = cancelled.clone();
+        let thread_dest = dest.clone();
+        let total = paths.len();
+        thread::spawn(move || {
+            let mut on_image = || {
+                thread_done.fetch_add(1, Ordering::Relaxed);
+                !thread_cancelled.load(Ordering::Relaxed)
+            };
+            let result = save(&paths, &options, &font, &thread_dest, &mut on_image);
+            let _ = sender.send(result.map_err(|e| e.to_string()));
+        });
+        self.export = Some(Export {
+            dest,
+            total,
+            done,
+            cancelled,
+            receiver,
+        });
+        self.outcome = None;
+    }
+
+    /// Returns what the export ended with once it is finished: where the sheet was saved, or
+    /// why it failed. A cancelled export ends with nothing.
+    pub fn poll(&mut self) -> Option<Result<PathBuf, String>> {
+        let export = self.export.as_ref()?;
+        let result = match export.receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err(tr!("export-stopped")),
+        };
+        let export = self.export.take()?;
+        let outcome = match result {
+            Ok(true) => Ok(export.dest),
+            Ok(false) => return None,
+            Err(e) => Err(e),
+        };
+        self.outcome = Some(outcome.clone());
+        Some(outcome)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.export.is_some()
+    }
+
+    /// Images rendered so far and the number of images.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.export
+            .as_ref()
+            .map(|export| (export.done.load(Ordering::Relaxed), export.total))
+    }
+
+    /// Draws the window contents for the images `paths`. `font` is the font the file names are
+    /// written with.
+    pub fn show(&mut self, ui: &mut egui::Ui, paths: &[PathBuf], font: impl FnOnce() -> FontArc) {
+        ui.label(tr!("contact-sheet-intro", count = paths.len()));
+        egui::Grid::new("contact_sheet_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr!("columns"));
+                ui.add(egui::DragValue::new(&mut self.options.columns).range(1..=20));
+                ui.end_row();
+                ui.label(tr!("thumbnail-size"));
+                ui.add(
+                    egui::DragValue::new(&mut self.options.thumbnail_size)
+                        .range(64..=1024)
+                        .suffix(" px"),
+                );
+                ui.end_row();
+                ui.label(tr!("file-names"));
+                ui.checkbox(&mut self.options.file_names, tr!("under-each-image"));
+                ui.end_row();
+            });
+        if let Some(export) = &self.export {
+            let done = export.done.load(Ordering::Relaxed);
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(done as f32 / export.total.max(1) as f32)
+                        .text(format!("{}/{}", done, export.total)),
+                );
+                if ui.button(tr!("cancel")).clicked() {
+                    export.cancelled.store(true, Ordering::Relaxed);
+                }
+            });
+            return;
+        }
+        let save = ui
+            .add_enabled(!paths.is_empty(), egui::Button::new(tr!("save-as")))
+            .on_hover_text(tr!("save-sheet-hint"))
+            .clicked();
+        if save {
+            if let Some(dest) = rfd::FileDialog::new()
+                .set_file_name("contact-sheet.png")
+                .add_filter("PNG", &["png"])
+                .add_filter("JPEG", &["jpg", "jpeg"])
+                .add_filter("PDF", &["pdf"])
+                .save_file()
+            {
+                self.start(paths.to_vec(), font(), dest);
+            }
+        }
+        match &self.outcome {
+            Some(Ok(dest)) => {
+                ui.horizontal(|ui| {
+                    ui.label(tr!("saved", path = dest.display().to_string()));
+                    if ui.button(tr!("open")).clicked() {
+                        if let Err(e) = open::that(dest) {
+                            log::error!("Failed to open {}: {}", dest.display(), e);
+                        }
+                    }
+                });
+            }
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> FontArc {
+        let data = fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fonts/NotoSansJP-VariableFont_wght.ttf"
+        ))
+        .unwrap();
+        FontArc::try_from_vec(data).unwrap()
+    }
+
+    #[test]
+    fn fit_name_test() {
+        let font = font();
+        assert_eq!(fit_name(&font, "a.png", 100.0), "a.png");
+        let fitted = fit_name(&font, "IMG_20240131_120000_HDR_edited.jpg", 100.0);
+        assert!(fitted.contains('…'));
+        assert!(fitted.ends_with(".jpg"));
+        assert_eq!(fit_name(&font, "long name", 0.0), "…");
+    }
+
+    #[test]
+    fn save_test() {
+        let font = font();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for name in ["a.png", "b.png", "c.png"] {
+            let path = temp_dir.path().join(name);
+            RgbImage::from_pixel(40, 20, Rgb([255, 0, 0]))
+                .save(&path)
+                .unwrap();
+            paths.push(path);
+        }
+        // Damaged images get a gray square.
+        let broken = temp_dir.path().join("broken.png");
+        fs::write(&broken, b"not an image").unwrap();
+        paths.push(broken);
+        let options = SheetOptions {
+            columns: 3,
+            thumbnail_size: 64,
+            file_names: true,
+        };
+
+        let dest = temp_dir.path().join("sheet.png");
+        let mut rendered = 0;
+        assert!(save(&paths, &options, &font, &dest, &mut || {
+            rendered += 1;
+            true
+        })
+        .unwrap());
+        assert_eq!(rendered, 4);
+        let sheet = image::open(&dest).unwrap().to_rgb8();
+        let (cell_width, cell_height) = options.cell_size();
+        assert_eq!(sheet.width(), 3 * cell_width + MARGIN);
+        assert_eq!(sheet.height(), 2 * cell_height + MARGIN);
+        // The 40x20 image is scaled to 64x32 and centered in its square.
+        assert_eq!(sheet.get_pixel(MARGIN + 32, MARGIN + 32), &Rgb([255, 0, 0]));
+        assert_eq!(sheet.get_pixel(MARGIN + 32, MARGIN + 4), &BACKGROUND);
+        let second_row = MARGIN + cell_height;
+        assert_eq!(sheet.get_pixel(MARGIN + 1, second_row + 1), &PLACEHOLDER);
+
+        let dest = temp_dir.path().join("sheet.pdf");
+        assert!(save(&paths, &options, &font, &dest, &mut || true).unwrap());
+        let pdf = fs::read(&dest).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        let text = String::from_utf8_lossy(&pdf);
+        let xref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|rest| rest.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(pdf[xref..].starts_with(b"xref"));
+
+        // Cancelled after the first image, nothing is written.
+        let dest = temp_dir.path().join("cancelled.png");
+        assert!(!save(&paths, &options, &font, &dest, &mut || false).unwrap());
+        assert!(!dest.exists());
+        assert!(save(&[], &options, &font, &dest, &mut || true).is_err());
+    }
+}
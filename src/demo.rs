@@ -0,0 +1,109 @@
+use anyhow::Result;
+use image::{Rgb, RgbImage};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const IMAGE_WIDTH: u32 = 480;
+const IMAGE_HEIGHT: u32 = 320;
+// Images of each color in the inbox.
+const IMAGES_PER_COLOR: usize = 3;
+
+/// The destinations of the demo: the folder name, its key, and its color.
+pub const DEMO_DESTINATIONS: [(&str, char, [u8; 3]); 3] = [
+    ("red", 'R', [220, 60, 60]),
+    ("green", 'G', [60, 170, 90]),
+    ("blue", 'B', [60, 110, 220]),
+];
+
+/// A folder of generated images to sort by color, with a destination for each color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Demo {
+    pub inbox: PathBuf,
+    pub destinations: Vec<(PathBuf, char, [u8; 3])>,
+}
+
+// A gradient of `color` with a lighter disc, placed by `variant` so that the images of a color
+// can be told apart.
+fn sample_image(color: [u8; 3], variant: usize) -> RgbImage {
+    let center_x = IMAGE_WIDTH as f32 * (0.25 + 0.25 * (variant % 3) as f32);
+    let center_y = IMAGE_HEIGHT as f32 / 2.0;
+    let radius = IMAGE_HEIGHT as f32 / 4.0;
+    RgbImage::from_fn(IMAGE_WIDTH, IMAGE_HEIGHT, |x, y| {
+        let shade = 0.6 + 0.4 * y as f32 / IMAGE_HEIGHT as f32;
+        let inside = (x as f32 - center_x).hypot(y as f32 - center_y) < radius;
+        Rgb(color.map(|channel| match inside {
+            true => channel.saturating_add(80),
+            false => (channel as f32 * shade) as u8,
+        }))
+    })
+}
+
+/// Creates the demo in `root`, replacing an earlier one. The inbox holds images of every color
+/// in turn, named in the order they are shown.
+pub fn create(root: &Path) -> Result<Demo> {
+    if root.exists() {
+        fs::remove_dir_all(root)?;
+    }
+    let inbox = root.join("inbox");
+    fs::create_dir_all(&inbox)?;
+    let mut destinations = Vec::new();
+    for (name, letter, color) in DEMO_DESTINATIONS {
+        let folder = root.join(name);
+        fs::create_dir(&folder)?;
+        destinations.push((folder, letter, color));
+    }
+    for variant in 0..IMAGES_PER_COLOR {
+        for (index, (name, _, color)) in DEMO_DESTINATIONS.iter().enumerate() {
+            let number = variant * DEMO_DESTINATIONS.len() + index + 1;
+            sample_image(*color, variant)
+                .save(inbox.join(format!("{:02}-{}.png", number, name)))?;
+        }
+    }
+    Ok(Demo {
+        inbox,
+        destinations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("demo");
+        let demo = create(&root).unwrap();
+        assert_eq!(demo.inbox, root.join("inbox"));
+        assert_eq!(demo.destinations[0], (root.join("red"), 'R', [220, 60, 60]));
+        assert!(demo
+            .destinations
+            .iter()
+            .all(|(folder, _, _)| folder.is_dir()));
+
+        let mut images: Vec<String> = fs::read_dir(&demo.inbox)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        images.sort();
+        assert_eq!(images.len(), 9);
+        assert_eq!(
+            &images[..4],
+            ["01-red.png", "02-green.png", "03-blue.png", "04-red.png"]
+        );
+        let image = image::open(demo.inbox.join("01-red.png")).unwrap();
+        assert_eq!((image.width(), image.height()), (IMAGE_WIDTH, IMAGE_HEIGHT));
+
+        // Sorted images are put back by creating it again.
+        fs::rename(
+            demo.inbox.join("01-red.png"),
+            root.join("red").join("01-red.png"),
+        )
+        .unwrap();
+        create(&root).unwrap();
+        assert!(demo.inbox.join("01-red.png").exists());
+        assert!(!root.join("red").join("01-red.png").exists());
+    }
+}
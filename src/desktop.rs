@@ -0,0 +1,23 @@
+use std::{io, path::Path, process::Command};
+
+/// Opens the file with the app the system uses for its type.
+pub fn open_externally(path: &Path) -> io::Result<()> {
+    open::that_detached(path)
+}
+
+/// Shows the file in the system file manager. Where the file manager supports it, the file is
+/// selected. Otherwise its folder is opened.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    if cfg!(target_os = "windows") {
+        // Explorer takes "/select," and the path as a single argument.
+        let mut argument = std::ffi::OsString::from("/select,");
+        argument.push(path);
+        Command::new("explorer").arg(argument).spawn()?;
+        Ok(())
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg("-R").arg(path).spawn()?;
+        Ok(())
+    } else {
+        open::that_detached(path.parent().unwrap_or(path))
+    }
+}
@@ -0,0 +1,411 @@
+use eframe::egui;
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{file_ops, i18n::tr, metadata, scanner, thumbnails::Thumbnails};
+
+/// The log in the discard folder of where each file in it came from.
+pub const LOG_NAME: &str = "discard-log.jsonl";
+const SECONDS_PER_DAY: i64 = 86400;
+const THUMBNAIL_SIZE: f32 = 48.0;
+
+// A line of the log: a file moved into the folder.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Record {
+    // Unix time in seconds.
+    time: i64,
+    original: PathBuf,
+    // Its name in the folder, which may differ from the original one.
+    name: String,
+}
+
+/// A file in the discard folder.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Discarded {
+    pub path: PathBuf,
+    // Where it was moved from, if the log knows.
+    pub original: Option<PathBuf>,
+    // Unix time in seconds when it was moved there, or else when the file was last modified.
+    pub time: i64,
+}
+
+impl Discarded {
+    pub fn age_days(&self, now: i64) -> i64 {
+        (now - self.time).max(0) / SECONDS_PER_DAY
+    }
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+/// Adds a line to the log in `folder` for each of `moves`, a file moved from where it was into
+/// the folder.
+pub fn record(folder: &Path, moves: &[(PathBuf, PathBuf)], time: i64) -> io::Result<()> {
+    let mut lines = String::new();
+    for (src, dest) in moves {
+        let record = Record {
+            time,
+            original: src.clone(),
+            name: dest
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+        };
+        lines.push_str(&serde_json::to_string(&record)?);
+        lines.push('\n');
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(folder.join(LOG_NAME))?
+        .write_all(lines.as_bytes())
+}
+
+// The last record of each name. Lines that cannot be read are skipped.
+fn read_records(folder: &Path) -> HashMap<String, Record> {
+    let Ok(log) = fs::read_to_string(folder.join(LOG_NAME)) else {
+        return HashMap::new();
+    };
+    log.lines()
+        .filter_map(|line| serde_json::from_str::<Record>(line).ok())
+        .map(|record| (record.name.clone(), record))
+        .collect()
+}
+
+/// The files in `folder`, newest first, with where they came from.
+pub fn list(folder: &Path) -> io::Result<Vec<Discarded>> {
+    let records = read_records(folder);
+    let mut files = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let metadata = entry.metadata()?;
+        if name == LOG_NAME || !metadata.is_file() {
+            continue;
+        }
+        let record = records.get(&name);
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs() as i64);
+        files.push(Discarded {
+            path: entry.path(),
+            original: record.map(|record| record.original.clone()),
+            time: record.map_or(modified, |record| record.time),
+        });
+    }
+    files.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.path.cmp(&b.path)));
+    Ok(files)
+}
+
+/// The files that have been in the folder for at least `days`.
+pub fn older_than(files: &[Discarded], days: u32, now: i64) -> Vec<PathBuf> {
+    files
+        .iter()
+        .filter(|file| file.age_days(now) >= days as i64)
+        .map(|file| file.path.clone())
+        .collect()
+}
+
+/// Moves `file` back to where it came from, under a new name if another file took its name
+/// since. Its folder is created again if it was removed. Returns where it is now.
+pub fn restore(file: &Discarded) -> io::Result<PathBuf> {
+    let Some(original) = &file.original else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Where it came from is not in the log",
+        ));
+    };
+    let (Some(folder), Some(name)) = (original.parent(), original.file_name()) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "No file name"));
+    };
+    fs::create_dir_all(folder)?;
+    let dest = file_ops::unique_path(folder, name);
+    file_ops::rename_or_copy(&file.path, &dest)?;
+    Ok(dest)
+}
+
+/// Drops the lines of the files that are no longer in `folder` from its log.
+pub fn compact(folder: &Path) -> io::Result<()> {
+    let path = folder.join(LOG_NAME);
+    let log = match fs::read_to_string(&path) {
+        Ok(log) => log,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let kept: String = log
+        .lines()
+        .filter(|line| {
+            serde_json::from_str::<Record>(line)
+                .is_ok_and(|record| folder.join(&record.name).exists())
+        })
+        .map(|line| format!("{}\n", line))
+        .collect();
+    fs::write(path, kept)
+}
+
+/// What the user chose in the "Discard folder" window.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiscardAction {
+    Restore(Vec<Discarded>),
+    // Delete these files for good.
+    Purge(Vec<PathBuf>),
+}
+
+/// State of the "Discard folder" window.
+pub struct DiscardReview {
+    files: Vec<Discarded>,
+    selected: HashSet<PathBuf>,
+    // "Purge files older than" this many days.
+    days: u32,
+    // Files to delete once the user confirms.
+    purge: Option<Vec<PathBuf>>,
+    error: Option<String>,
+}
+
+impl Default for DiscardReview {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            selected: HashSet::new(),
+            days: 30,
+            purge: None,
+            error: None,
+        }
+    }
+}
+
+impl DiscardReview {
+    /// Lists the files of `folder` again.
+    pub fn load(&mut self, folder: &Path) {
+        match list(folder) {
+            Ok(files) => {
+                self.selected
+                    .retain(|path| files.iter().any(|file| file.path == *path));
+                self.files = files;
+                self.error = None;
+            }
+            Err(e) => {
+                self.files.clear();
+                self.error = Some(tr!(
+                    "read-folder-failed",
+                    path = folder.display().to_string(),
+                    error = e.to_string()
+                ));
+            }
+        }
+        self.purge = None;
+    }
+
+    fn selected_files(&self) -> Vec<Discarded> {
+        self.files
+            .iter()
+            .filter(|file| self.selected.contains(&file.path))
+            .cloned()
+            .collect()
+    }
+
+    /// Draws the window contents. `folder` is the discard folder, kept in the settings.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        folder: &mut Option<PathBuf>,
+        mut thumbnails: Option<&mut Thumbnails>,
+    ) -> Option<DiscardAction> {
+        ui.label(tr!("discard-intro"));
+        ui.horizontal(|ui| {
+            ui.label(tr!("discard-folder-label"));
+            if ui.button(tr!("choose")).clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    self.load(&path);
+                    *folder = Some(path);
+                }
+            }
+            match folder {
+                Some(folder) => ui.label(folder.to_string_lossy()),
+                None => ui.label(tr!("not-selected")),
+            };
+        });
+        let folder = folder.as_ref()?;
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let mut action = None;
+        let now = now();
+        if let Some(purge) = &self.purge {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                tr!("purge-confirm", count = purge.len()),
+            );
+            let (delete, cancel) = ui
+                .horizontal(|ui| {
+                    (
+                        ui.button(tr!("delete")).clicked(),
+                        ui.button(tr!("cancel")).clicked(),
+                    )
+                })
+                .inner;
+            if delete {
+                action = Some(DiscardAction::Purge(purge.clone()));
+            }
+            if delete || cancel {
+                self.purge = None;
+            }
+            return action;
+        }
+        ui.horizontal(|ui| {
+            if ui.button(tr!("refresh")).clicked() {
+                self.load(folder);
+            }
+            let selected = self.selected_files();
+            if ui
+                .add_enabled(
+                    !selected.is_empty(),
+                    egui::Button::new(tr!("restore-selected")),
+                )
+                .clicked()
+            {
+                action = Some(DiscardAction::Restore(selected.clone()));
+            }
+            if ui
+                .add_enabled(
+                    !selected.is_empty(),
+                    egui::Button::new(tr!("purge-selected")),
+                )
+                .clicked()
+            {
+                self.purge = Some(selected.into_iter().map(|file| file.path).collect());
+            }
+        });
+        ui.horizontal(|ui| {
+            let old = older_than(&self.files, self.days, now);
+            if ui
+                .add_enabled(!old.is_empty(), egui::Button::new(tr!("purge-older-than")))
+                .clicked()
+            {
+                self.purge = Some(old);
+            }
+            ui.add(
+                egui::DragValue::new(&mut self.days)
+                    .range(0..=3650)
+                    .suffix(format!(" {}", tr!("days"))),
+            );
+        });
+        ui.label(tr!(
+            "files-selected",
+            count = self.files.len(),
+            selected = self.selected.len()
+        ));
+
+        let row_height = THUMBNAIL_SIZE + ui.spacing().item_spacing.y;
+        egui::ScrollArea::vertical().show_rows(ui, row_height, self.files.len(), |ui, rows| {
+            for file in &self.files[rows] {
+                ui.horizontal(|ui| {
+                    let mut selected = self.selected.contains(&file.path);
+                    if ui.checkbox(&mut selected, "").changed() {
+                        match selected {
+                            true => self.selected.insert(file.path.clone()),
+                            false => self.selected.remove(&file.path),
+                        };
+                    }
+                    match thumbnails.as_deref_mut() {
+                        Some(thumbnails) if scanner::is_image_file(&file.path) => {
+                            thumbnails.show(ui, &file.path, THUMBNAIL_SIZE, false);
+                        }
+                        _ => {
+                            ui.allocate_space(egui::Vec2::splat(THUMBNAIL_SIZE));
+                        }
+                    }
+                    ui.vertical(|ui| {
+                        ui.strong(file.path.file_name().unwrap_or_default().to_string_lossy());
+                        match &file.original {
+                            Some(original) => ui.label(original.to_string_lossy()),
+                            None => ui.weak(tr!("not-in-log")),
+                        };
+                        ui.weak(tr!(
+                            "days-ago",
+                            count = file.age_days(now),
+                            time = metadata::format_timestamp(file.time)
+                        ));
+                    });
+                });
+            }
+        });
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discard_log_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let inbox = temp_dir.path().join("inbox");
+        let folder = temp_dir.path().join("discard");
+        fs::create_dir(&inbox).unwrap();
+        fs::create_dir(&folder).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            fs::write(folder.join(name), b"").unwrap();
+        }
+        record(
+            &folder,
+            &[
+                (inbox.join("a.png"), folder.join("a.png")),
+                (inbox.join("b.png"), folder.join("b.png")),
+            ],
+            SECONDS_PER_DAY,
+        )
+        .unwrap();
+        // Discarded again later.
+        record(
+            &folder,
+            &[(inbox.join("a.png"), folder.join("a.png"))],
+            40 * SECONDS_PER_DAY,
+        )
+        .unwrap();
+
+        let files = list(&folder).unwrap();
+        let names: Vec<_> = files.iter().map(|file| file.path.clone()).collect();
+        // c.png was put there by hand, so only its own date is known.
+        assert_eq!(
+            names,
+            [
+                folder.join("c.png"),
+                folder.join("a.png"),
+                folder.join("b.png")
+            ]
+        );
+        assert_eq!(files[0].original, None);
+        assert_eq!(files[1].original, Some(inbox.join("a.png")));
+        assert_eq!(files[1].age_days(50 * SECONDS_PER_DAY), 10);
+        assert_eq!(
+            older_than(&files[1..], 30, 50 * SECONDS_PER_DAY),
+            [folder.join("b.png")]
+        );
+
+        // Back where it came from. A file that took the name is not replaced.
+        fs::write(inbox.join("b.png"), b"new").unwrap();
+        assert_eq!(restore(&files[1]).unwrap(), inbox.join("a.png"));
+        assert_eq!(restore(&files[2]).unwrap(), inbox.join("b (1).png"));
+        assert!(restore(&files[0]).is_err());
+
+        compact(&folder).unwrap();
+        assert!(read_records(&folder).is_empty());
+        assert_eq!(list(&folder).unwrap().len(), 1);
+    }
+}
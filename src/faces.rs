@@ -0,0 +1,633 @@
+// The models only run in builds with the "faces" feature.
+#![cfg_attr(not(feature = "faces"), allow(dead_code))]
+
+use eframe::egui;
+use image::RgbaImage;
+use rfd::FileDialog;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{batch::ImageScan, i18n::tr};
+
+// Detections less likely to be a face than this are ignored.
+const MIN_SCORE: f32 = 0.7;
+
+// Detections that overlap more than this are the same face.
+const MAX_OVERLAP: f32 = 0.3;
+
+// Faces narrower than this part of the image are too small to tell who it is.
+const MIN_FACE_WIDTH: f32 = 0.03;
+
+// Size of the face shown for each person.
+const CROP_SIZE: u32 = 64;
+
+/// How alike two faces must be to count as the same person when the window opens.
+const DEFAULT_MIN_SIMILARITY: f32 = 0.45;
+
+/// Whether this build can find faces. It needs the "faces" feature.
+pub fn supported() -> bool {
+    cfg!(feature = "faces")
+}
+
+/// A face found by the detector. The corners are fractions of the image size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Detection {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub score: f32,
+}
+
+impl Detection {
+    fn area(&self) -> f32 {
+        (self.right - self.left).max(0.0) * (self.bottom - self.top).max(0.0)
+    }
+
+    /// The area both cover divided by the area either covers.
+    fn overlap(&self, other: &Detection) -> f32 {
+        let intersection = Detection {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+            score: 0.0,
+        }
+        .area();
+        let union = self.area() + other.area() - intersection;
+        if union <= 0.0 {
+            0.0
+        } else {
+            intersection / union
+        }
+    }
+}
+
+/// The likely faces among `detections`, with one detection for each face: the most likely of
+/// those that overlap.
+pub fn suppress(mut detections: Vec<Detection>) -> Vec<Detection> {
+    detections.retain(|detection| {
+        detection.score >= MIN_SCORE && detection.right - detection.left >= MIN_FACE_WIDTH
+    });
+    detections.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Detection> = Vec::new();
+    for detection in detections {
+        if kept
+            .iter()
+            .all(|face| face.overlap(&detection) <= MAX_OVERLAP)
+        {
+            kept.push(detection);
+        }
+    }
+    kept
+}
+
+fn normalize(vector: &mut [f32]) {
+    let length = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if length > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= length);
+    }
+}
+
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Assigns each of `embeddings`, normalized, to a person. A face joins the person whose average
+/// face it is most alike, if that is at least `min_similarity`, and is a new person otherwise.
+/// Returns the person of each face, numbered from 0 in the order they first appear.
+pub fn cluster(embeddings: &[Vec<f32>], min_similarity: f32) -> Vec<usize> {
+    // The sum of the faces of each person, and its normalized copy.
+    let mut sums: Vec<Vec<f32>> = Vec::new();
+    let mut centers: Vec<Vec<f32>> = Vec::new();
+    let mut people = Vec::with_capacity(embeddings.len());
+    for embedding in embeddings {
+        let closest = centers
+            .iter()
+            .map(|center| similarity(center, embedding))
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .filter(|(_, similarity)| *similarity >= min_similarity);
+        let person = match closest {
+            Some((person, _)) => {
+                sums[person]
+                    .iter_mut()
+                    .zip(embedding)
+                    .for_each(|(sum, x)| *sum += x);
+                centers[person] = sums[person].clone();
+                normalize(&mut centers[person]);
+                person
+            }
+            None => {
+                sums.push(embedding.clone());
+                centers.push(embedding.clone());
+                sums.len() - 1
+            }
+        };
+        people.push(person);
+    }
+    people
+}
+
+/// A face in a photo.
+pub struct Face {
+    pub path: PathBuf,
+    // Normalized.
+    pub embedding: Vec<f32>,
+    pub crop: RgbaImage,
+}
+
+/// The photos of one person, as found by `cluster()`.
+#[derive(Debug, PartialEq)]
+pub struct Person {
+    // The face shown for the person, as an index into the faces.
+    pub cover: usize,
+    pub paths: Vec<PathBuf>,
+}
+
+/// The people that `cluster()` found, with the most photographed first. A photo is listed once
+/// for each person in it.
+pub fn group(faces: &[Face], min_similarity: f32) -> Vec<Person> {
+    let embeddings: Vec<Vec<f32>> = faces.iter().map(|face| face.embedding.clone()).collect();
+    let mut people: Vec<(usize, BTreeSet<&Path>)> = Vec::new();
+    for (index, person) in cluster(&embeddings, min_similarity).into_iter().enumerate() {
+        if person == people.len() {
+            people.push((index, BTreeSet::new()));
+        }
+        people[person].1.insert(&faces[index].path);
+    }
+    let mut people: Vec<Person> = people
+        .into_iter()
+        .map(|(cover, paths)| Person {
+            cover,
+            paths: paths.into_iter().map(Path::to_path_buf).collect(),
+        })
+        .collect();
+    people.sort_by_key(|person| std::cmp::Reverse(person.paths.len()));
+    people
+}
+
+/// Crops `detection` out of `image`, widened a bit to include the whole head like the embedding
+/// models were trained on, and made square.
+fn crop(image: &image::RgbImage, detection: &Detection) -> image::RgbImage {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let center_x = (detection.left + detection.right) / 2.0 * width;
+    let center_y = (detection.top + detection.bottom) / 2.0 * height;
+    let side = ((detection.right - detection.left) * width)
+        .max((detection.bottom - detection.top) * height)
+        * 1.2;
+    let left = (center_x - side / 2.0).clamp(0.0, width - 1.0);
+    let top = (center_y - side / 2.0).clamp(0.0, height - 1.0);
+    let side_x = side.min(width - left).max(1.0);
+    let side_y = side.min(height - top).max(1.0);
+    image::imageops::crop_imm(image, left as u32, top as u32, side_x as u32, side_y as u32)
+        .to_image()
+}
+
+#[cfg(feature = "faces")]
+mod model {
+    use super::*;
+    use anyhow::Result;
+    use tract_onnx::prelude::*;
+
+    type Model = TypedRunnableModel<TypedModel>;
+
+    // Input of the detector, e.g. Ultra-Light-Fast-Generic-Face-Detector "version-RFB-320.onnx".
+    const DETECTOR_SIZE: (u32, u32) = (320, 240);
+
+    // Input of the embedder, e.g. the ArcFace models of InsightFace.
+    const EMBEDDER_SIZE: u32 = 112;
+
+    fn load(path: &Path, width: u32, height: u32) -> Result<Model> {
+        let shape = [1, 3, height as usize, width as usize];
+        tract_onnx::onnx()
+            .model_for_path(path)?
+            .with_input_fact(0, f32::fact(shape).into())?
+            .into_optimized()?
+            .into_runnable()
+    }
+
+    // The image as a 1x3xHxW tensor of (value - mean) / scale.
+    fn tensor(image: &image::RgbImage, mean: f32, scale: f32) -> Tensor {
+        let (width, height) = image.dimensions();
+        tract_ndarray::Array4::from_shape_fn(
+            (1, 3, height as usize, width as usize),
+            |(_, channel, y, x)| {
+                (f32::from(image.get_pixel(x as u32, y as u32).0[channel]) - mean) / scale
+            },
+        )
+        .into()
+    }
+
+    pub struct FaceModels {
+        detector: Model,
+        embedder: Model,
+    }
+
+    impl FaceModels {
+        pub fn load(detector: &Path, embedder: &Path) -> Result<Self> {
+            let (width, height) = DETECTOR_SIZE;
+            Ok(Self {
+                detector: load(detector, width, height)?,
+                embedder: load(embedder, EMBEDDER_SIZE, EMBEDDER_SIZE)?,
+            })
+        }
+
+        fn detect(&self, image: &image::RgbImage) -> Result<Vec<Detection>> {
+            let (width, height) = DETECTOR_SIZE;
+            let input = image::imageops::resize(
+                image,
+                width,
+                height,
+                image::imageops::FilterType::Triangle,
+            );
+            let outputs = self
+                .detector
+                .run(tvec!(tensor(&input, 127.0, 128.0).into()))?;
+            // Scores of "background" and "face", and the corners of each box.
+            let output = |columns: usize| {
+                outputs
+                    .iter()
+                    .find(|output| output.shape().last() == Some(&columns))
+                    .ok_or_else(|| anyhow::anyhow!("The detector has no output of {}", columns))
+            };
+            let scores = output(2)?.to_array_view::<f32>()?;
+            let boxes = output(4)?.to_array_view::<f32>()?;
+            let scores = scores.as_slice().unwrap_or_default();
+            let boxes = boxes.as_slice().unwrap_or_default();
+            let detections = scores
+                .chunks_exact(2)
+                .zip(boxes.chunks_exact(4))
+                .map(|(score, corners)| Detection {
+                    left: corners[0],
+                    top: corners[1],
+                    right: corners[2],
+                    bottom: corners[3],
+                    score: score[1],
+                })
+                .collect();
+            Ok(suppress(detections))
+        }
+
+        fn embed(&self, face: &image::RgbImage) -> Result<Vec<f32>> {
+            let input = image::imageops::resize(
+                face,
+                EMBEDDER_SIZE,
+                EMBEDDER_SIZE,
+                image::imageops::FilterType::Triangle,
+            );
+            let outputs = self
+                .embedder
+                .run(tvec!(tensor(&input, 127.5, 127.5).into()))?;
+            let Some(output) = outputs.first() else {
+                anyhow::bail!("The embedder has no output");
+            };
+            let mut embedding: Vec<f32> = output.to_array_view::<f32>()?.iter().copied().collect();
+            normalize(&mut embedding);
+            Ok(embedding)
+        }
+
+        /// The faces in `image`, found in `path`.
+        pub fn faces(&self, path: &Path, image: &image::RgbImage) -> Result<Vec<Face>> {
+            let mut faces = Vec::new();
+            for detection in self.detect(image)? {
+                let face = crop(image, &detection);
+                faces.push(Face {
+                    path: path.to_path_buf(),
+                    embedding: self.embed(&face)?,
+                    crop: image::DynamicImage::ImageRgb8(face)
+                        .thumbnail(CROP_SIZE, CROP_SIZE)
+                        .to_rgba8(),
+                });
+            }
+            Ok(faces)
+        }
+    }
+}
+
+/// Finds and describes the faces in `paths` on a background thread. What is found in an image
+/// are its faces.
+#[cfg(feature = "faces")]
+fn scan(paths: Vec<PathBuf>, detector: PathBuf, embedder: PathBuf) -> ImageScan<Vec<Face>> {
+    use crate::{image_loader, raw};
+
+    // The detector sees much less than this, but small faces need the pixels when cropped.
+    const ANALYSIS_SIZE: u32 = 1600;
+
+    ImageScan::start_with(paths, move || {
+        let models = model::FaceModels::load(&detector, &embedder)
+            .map_err(|e| tr!("load-models-failed", error = e.to_string()))?;
+        Ok(move |path: &Path| {
+            let faces = raw::decode_file(path)
+                .map_err(anyhow::Error::msg)
+                .and_then(|image| {
+                    let image = image_loader::downscale(image, ANALYSIS_SIZE).to_rgb8();
+                    models.faces(path, &image)
+                })
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to find faces in {}: {}", path.display(), e);
+                    Vec::new()
+                });
+            Some(faces).filter(|faces| !faces.is_empty())
+        })
+    })
+}
+
+pub enum FaceAction {
+    // Only show these images.
+    Review(Vec<PathBuf>),
+    MoveTo {
+        paths: Vec<PathBuf>,
+        folder: PathBuf,
+    },
+}
+
+/// Where the face models are, kept in the settings.
+pub struct FaceModelPaths<'a> {
+    pub detector: &'a mut Option<PathBuf>,
+    pub embedder: &'a mut Option<PathBuf>,
+}
+
+/// State of the "Faces" window.
+pub struct FaceTool {
+    scan: Option<ImageScan<Vec<Face>>>,
+    faces: Vec<Face>,
+    min_similarity: f32,
+    // Grouping starts over when the number of faces or the similarity changes.
+    people: Vec<Person>,
+    key: (usize, f32),
+    // The destination chosen for each person, by the cover face.
+    destinations: HashMap<usize, PathBuf>,
+    // The face of each person, by the cover face.
+    textures: HashMap<usize, egui::TextureHandle>,
+}
+
+impl Default for FaceTool {
+    fn default() -> Self {
+        Self {
+            scan: None,
+            faces: Vec::new(),
+            min_similarity: DEFAULT_MIN_SIMILARITY,
+            people: Vec::new(),
+            key: (0, DEFAULT_MIN_SIMILARITY),
+            destinations: HashMap::new(),
+            textures: HashMap::new(),
+        }
+    }
+}
+
+impl FaceTool {
+    pub fn poll(&mut self) {
+        if let Some(scan) = &mut self.scan {
+            scan.poll();
+            self.faces
+                .extend(scan.drain_found().flat_map(|(_, faces)| faces));
+        }
+        let key = (self.faces.len(), self.min_similarity);
+        if key != self.key {
+            self.people = group(&self.faces, self.min_similarity);
+            self.key = key;
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.scan.as_ref().is_some_and(|scan| !scan.is_done())
+    }
+
+    /// The images checked and the images to check, while a scan is running.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.scan
+            .as_ref()
+            .filter(|scan| !scan.is_done())
+            .map(|scan| scan.progress())
+    }
+
+    #[cfg(feature = "faces")]
+    fn start(&mut self, images: &[PathBuf], detector: PathBuf, embedder: PathBuf) {
+        self.scan = Some(scan(images.to_vec(), detector, embedder));
+        self.faces.clear();
+        self.destinations.clear();
+        self.textures.clear();
+    }
+
+    #[cfg(not(feature = "faces"))]
+    fn start(&mut self, _images: &[PathBuf], _detector: PathBuf, _embedder: PathBuf) {}
+
+    // Forgets the photos that were moved, so that another person in them does not move them
+    // again.
+    fn forget(&mut self, paths: &[PathBuf]) {
+        let paths: HashSet<&PathBuf> = paths.iter().collect();
+        self.faces.retain(|face| !paths.contains(&face.path));
+        self.destinations.clear();
+        self.textures.clear();
+    }
+
+    fn texture(&mut self, ctx: &egui::Context, cover: usize) -> egui::TextureHandle {
+        self.textures
+            .entry(cover)
+            .or_insert_with(|| {
+                let crop = &self.faces[cover].crop;
+                let image = egui::ColorImage::from_rgba_unmultiplied(
+                    [crop.width() as usize, crop.height() as usize],
+                    crop.as_raw(),
+                );
+                ctx.load_texture(format!("face{}", cover), image, Default::default())
+            })
+            .clone()
+    }
+
+    /// Draws the window contents. `images` are the images that a new scan looks at.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        images: &[PathBuf],
+        models: FaceModelPaths,
+    ) -> Option<FaceAction> {
+        if !supported() {
+            ui.label(tr!("faces-unsupported"));
+            return None;
+        }
+        ui.label(tr!("faces-intro"));
+        for (label, model) in [
+            (tr!("face-detector"), &mut *models.detector),
+            (tr!("face-embedder"), &mut *models.embedder),
+        ] {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                if ui.button(tr!("choose")).clicked() {
+                    if let Some(path) = FileDialog::new().add_filter("ONNX", &["onnx"]).pick_file()
+                    {
+                        *model = Some(path);
+                    }
+                }
+                match model {
+                    Some(path) => ui.label(path.to_string_lossy()),
+                    None => ui.label(tr!("not-selected")),
+                };
+            });
+        }
+        ui.horizontal(|ui| {
+            let ready = !self.is_busy() && !images.is_empty();
+            let paths = models.detector.clone().zip(models.embedder.clone());
+            if ui
+                .add_enabled(
+                    ready && paths.is_some(),
+                    egui::Button::new(tr!("find-faces")),
+                )
+                .clicked()
+            {
+                if let Some((detector, embedder)) = paths {
+                    self.start(images, detector, embedder);
+                }
+            }
+            ui.label(tr!("same-person-from"))
+                .on_hover_text(tr!("same-person-from-hint"));
+            ui.add(
+                egui::DragValue::new(&mut self.min_similarity)
+                    .range(0.1..=0.95)
+                    .speed(0.01),
+            );
+        });
+        let scan = self.scan.as_ref()?;
+        if let Some(error) = scan.error() {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            return None;
+        }
+        let (checked, total) = scan.progress();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::ProgressBar::new(checked as f32 / total.max(1) as f32)
+                    .text(format!("{}/{}", checked, total)),
+            );
+            if !scan.is_done() && ui.button(tr!("cancel")).clicked() {
+                scan.cancel();
+            }
+        });
+
+        ui.label(tr!(
+            "faces-of-people",
+            faces = self.faces.len(),
+            people = self.people.len()
+        ));
+        let mut action = None;
+        let row_height = CROP_SIZE as f32 + ui.spacing().item_spacing.y;
+        let people = std::mem::take(&mut self.people);
+        egui::ScrollArea::vertical().show_rows(ui, row_height, people.len(), |ui, rows| {
+            for (index, person) in people[rows.clone()].iter().enumerate() {
+                let texture = self.texture(ui.ctx(), person.cover);
+                ui.horizontal(|ui| {
+                    ui.image((texture.id(), egui::Vec2::splat(CROP_SIZE as f32)));
+                    ui.vertical(|ui| {
+                        ui.strong(tr!("person-number", number = rows.start + index + 1));
+                        ui.label(tr!("photo-count", count = person.paths.len()));
+                    });
+                    if ui.button(tr!("review")).clicked() {
+                        action = Some(FaceAction::Review(person.paths.clone()));
+                    }
+                    if ui.button(tr!("choose-folder")).clicked() {
+                        if let Some(folder) = FileDialog::new().pick_folder() {
+                            self.destinations.insert(person.cover, folder);
+                        }
+                    }
+                    let folder = self.destinations.get(&person.cover);
+                    if let Some(folder) = folder {
+                        ui.label(folder.to_string_lossy());
+                    }
+                    if ui
+                        .add_enabled(folder.is_some(), egui::Button::new(tr!("move")))
+                        .clicked()
+                    {
+                        if let Some(folder) = folder {
+                            action = Some(FaceAction::MoveTo {
+                                paths: person.paths.clone(),
+                                folder: folder.clone(),
+                            });
+                        }
+                    }
+                });
+            }
+        });
+        self.people = people;
+        if let Some(FaceAction::MoveTo { paths, .. }) = &action {
+            self.forget(paths);
+        }
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(left: f32, top: f32, right: f32, bottom: f32, score: f32) -> Detection {
+        Detection {
+            left,
+            top,
+            right,
+            bottom,
+            score,
+        }
+    }
+
+    #[test]
+    fn suppress_test() {
+        let best = detection(0.1, 0.1, 0.3, 0.3, 0.95);
+        let other_face = detection(0.6, 0.1, 0.8, 0.3, 0.8);
+        let faces = suppress(vec![
+            detection(0.11, 0.1, 0.31, 0.3, 0.9),
+            other_face,
+            best,
+            // Unlikely, or too small.
+            detection(0.4, 0.4, 0.5, 0.5, 0.2),
+            detection(0.4, 0.4, 0.41, 0.41, 0.99),
+        ]);
+        assert_eq!(faces, vec![best, other_face]);
+    }
+
+    #[test]
+    fn cluster_test() {
+        let face = |x: f32, y: f32| {
+            let mut embedding = vec![x, y];
+            normalize(&mut embedding);
+            embedding
+        };
+        let embeddings = vec![
+            face(1.0, 0.0),
+            face(0.0, 1.0),
+            face(1.0, 0.1),
+            face(0.1, 1.0),
+            face(1.0, 1.0),
+        ];
+        assert_eq!(cluster(&embeddings, 0.9), vec![0, 1, 0, 1, 2]);
+        // Everyone is alike enough.
+        assert_eq!(cluster(&embeddings, 0.0), vec![0; 5]);
+
+        let faces: Vec<Face> = [
+            ("a.jpg", 1.0, 0.0),
+            ("b.jpg", 0.0, 1.0),
+            ("c.jpg", 1.0, 0.1),
+        ]
+        .into_iter()
+        .map(|(path, x, y)| Face {
+            path: PathBuf::from(path),
+            embedding: face(x, y),
+            crop: RgbaImage::new(1, 1),
+        })
+        .collect();
+        assert_eq!(
+            group(&faces, 0.9),
+            vec![
+                Person {
+                    cover: 0,
+                    paths: vec![PathBuf::from("a.jpg"), PathBuf::from("c.jpg")],
+                },
+                Person {
+                    cover: 1,
+                    paths: vec![PathBuf::from("b.jpg")],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,1256 @@
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{journal, settings::CollisionPolicy};
+
+// The mark macOS puts on downloaded files, so that it asks before opening them.
+#[cfg(unix)]
+const DOWNLOAD_FLAG: &str = "com.apple.quarantine";
+
+// Set from the settings, and read by moves on any thread.
+static KEEP_DOWNLOAD_FLAG: AtomicBool = AtomicBool::new(true);
+static SKIP_TAKEN_NAMES: AtomicBool = AtomicBool::new(false);
+
+/// What `move_file()` does when the name is taken at the destination.
+pub fn set_collision_policy(policy: CollisionPolicy) {
+    SKIP_TAKEN_NAMES.store(policy == CollisionPolicy::Skip, Ordering::Relaxed);
+}
+
+/// Whether copies keep the mark of downloaded files. Other extended attributes are always kept.
+pub fn set_keep_download_flag(keep: bool) {
+    KEEP_DOWNLOAD_FLAG.store(keep, Ordering::Relaxed);
+}
+
+// Paths this long need the extended form on Windows. MAX_PATH is 260, and a folder must leave
+// room for a file name of 8.3 characters in it.
+#[cfg(windows)]
+const MAX_SHORT_PATH: usize = 248;
+
+/// `path` in the extended `\\?\` form on Windows when it is too long for the usual one, so that
+/// files deep in nested folders can still be moved. Elsewhere, and when it is short enough, it
+/// is returned as it is.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if path.as_os_str().len() >= MAX_SHORT_PATH {
+        if let Some(extended) = path.to_str().and_then(extended_form) {
+            return Cow::Owned(PathBuf::from(extended));
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// `path` without the extended `\\?\` prefix that e.g. `fs::canonicalize()` adds on Windows,
+/// so that it reads and compares like the paths the user chose. `\\?\UNC\nas\photos` is
+/// `\\nas\photos` again.
+pub fn simplified(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if let Some(short) = path.to_str().and_then(short_form) {
+        return Cow::Owned(PathBuf::from(short));
+    }
+    Cow::Borrowed(path)
+}
+
+// The extended form of an absolute path on a drive or a share. Windows passes it on as it is,
+// so "/", "." and ".." are resolved here.
+#[cfg(any(windows, test))]
+fn extended_form(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let path = path.replace('/', r"\");
+    let bytes = path.as_bytes();
+    let (prefix, rest, kept) = if let Some(share) = path.strip_prefix(r"\\") {
+        // The server and the share are never left with "..".
+        (r"\\?\UNC".to_string(), share, 2)
+    } else if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":\\" {
+        (format!(r"\\?\{}", &path[..2]), &path[2..], 0)
+    } else {
+        return None;
+    };
+    let mut parts = Vec::new();
+    for part in rest.split('\\') {
+        match part {
+            "" | "." => {}
+            ".." if parts.len() > kept => {
+                parts.pop();
+            }
+            ".." => {}
+            part => parts.push(part),
+        }
+    }
+    Some(format!(r"{}\{}", prefix, parts.join(r"\")))
+}
+
+#[cfg(any(windows, test))]
+fn short_form(path: &str) -> Option<String> {
+    if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        return Some(format!(r"\\{}", share));
+    }
+    let rest = path.strip_prefix(r"\\?\")?;
+    let bytes = rest.as_bytes();
+    (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+        .then(|| rest.to_string())
+}
+
+// Moves src to dest_dir. Returns the new file path on success. A file of the same name there is
+// never replaced. The move gets a new name or fails with `AlreadyExists`, as
+// `set_collision_policy()` says.
+pub fn move_file(src: &Path, dest_dir: &Path) -> io::Result<PathBuf> {
+    let policy = match SKIP_TAKEN_NAMES.load(Ordering::Relaxed) {
+        true => CollisionPolicy::Skip,
+        false => CollisionPolicy::KeepBoth,
+    };
+    move_file_as(src, dest_dir, policy)
+}
+
+/// Same as `move_file()` but with `policy` for a taken name instead of the setting, for tools
+/// that let the user choose.
+pub fn move_file_as(src: &Path, dest_dir: &Path, policy: CollisionPolicy) -> io::Result<PathBuf> {
+    let dest_path = dest_path(src, dest_dir, policy)?;
+    rename_or_copy(src, &dest_path)?;
+    Ok(dest_path)
+}
+
+// Where `src` goes in `dest_dir` with `policy`.
+fn dest_path(src: &Path, dest_dir: &Path, policy: CollisionPolicy) -> io::Result<PathBuf> {
+    available_path(&dest_dir.join(file_name(src)?), src, policy)
+}
+
+/// `wanted` if `src` can take that name, e.g. to move a file back on undo. Otherwise a new name
+/// next to it or an `AlreadyExists` error, as `policy` says.
+pub fn available_path(wanted: &Path, src: &Path, policy: CollisionPolicy) -> io::Result<PathBuf> {
+    if !is_taken(wanted, src) {
+        return Ok(wanted.to_path_buf());
+    }
+    let dir = wanted.parent().unwrap_or(Path::new("."));
+    let filename = file_name(wanted)?;
+    match policy {
+        CollisionPolicy::KeepBoth => Ok(unique_path(dir, filename)),
+        CollisionPolicy::Skip => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} is taken in {}",
+                filename.to_string_lossy(),
+                dir.display()
+            ),
+        )),
+    }
+}
+
+/// Moves `companion`, e.g. a sidecar, next to the image that was moved from `image_src` to
+/// `image_dest`. If the image got a new name there, e.g. "a (1).png", the companion is renamed
+/// along with it so that they still go together.
+pub fn move_companion(
+    companion: &Path,
+    image_src: &Path,
+    image_dest: &Path,
+    policy: &RetryPolicy,
+) -> io::Result<PathBuf> {
+    let dest_dir = image_dest.parent().unwrap_or(Path::new("."));
+    let renamed = match image_src.file_name() == image_dest.file_name() {
+        true => None,
+        false => renamed_companion(companion, image_src, image_dest),
+    };
+    match renamed.as_deref().and_then(Path::file_name) {
+        Some(name) => {
+            let dest = unique_path(dest_dir, name);
+            rename_or_copy(companion, &dest)?;
+            Ok(dest)
+        }
+        None => move_file_with_retry(companion, dest_dir, policy),
+    }
+}
+
+/// Whether the name `dest` is taken by a file other than `src`. Renaming "photo.jpg" to
+/// "Photo.JPG" on a filesystem that ignores case finds the file itself under the new name,
+/// which does not count.
+pub fn is_taken(dest: &Path, src: &Path) -> bool {
+    if fs::symlink_metadata(long_path(dest)).is_err() {
+        return false;
+    }
+    let same_name = match (dest.to_str(), src.to_str()) {
+        (Some(dest), Some(src)) => dest.to_lowercase() == src.to_lowercase(),
+        _ => dest == src,
+    };
+    !(same_name && same_file(dest, src))
+}
+
+/// Whether `a` and `b` are the same file, e.g. two spellings of one name where case is ignored.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (fs::symlink_metadata(a), fs::symlink_metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        // The real name of the file, whatever case it was given in.
+        match (fs::canonicalize(a), fs::canonicalize(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Whether `dir` is on a filesystem that ignores the case of names, as NTFS and APFS do by
+/// default. Found by looking the folder up under its name in another case. A folder without
+/// letters in its name is taken to be as usual on the platform.
+pub fn is_case_insensitive(dir: &Path) -> bool {
+    let name = dir.file_name().and_then(OsStr::to_str);
+    let swapped = name.map(|name| {
+        name.chars()
+            .flat_map(|c| match c.is_uppercase() {
+                true => c.to_lowercase().collect::<Vec<_>>(),
+                false => c.to_uppercase().collect(),
+            })
+            .collect::<String>()
+    });
+    match (dir.parent(), swapped) {
+        (Some(parent), Some(swapped)) if Some(swapped.as_str()) != name => {
+            same_file(dir, &parent.join(swapped))
+        }
+        _ => cfg!(any(windows, target_os = "macos")),
+    }
+}
+
+/// `name` as a filesystem that ignores case with `case_insensitive` compares it, to match names
+/// of files in different folders.
+pub fn name_key(name: &OsStr, case_insensitive: bool) -> std::ffi::OsString {
+    match name.to_str() {
+        Some(name) if case_insensitive => name.to_lowercase().into(),
+        _ => name.to_os_string(),
+    }
+}
+
+/// Renames `src` to `dest`. A file on another filesystem is copied and then removed, but only
+/// after checking that it fits. A symbolic link is moved as a link to the same file.
+pub fn rename_or_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    let (src, dest) = (long_path(src), long_path(dest));
+    if is_symlink(&src) {
+        return move_link(&src, &dest);
+    }
+    match fs::rename(&src, &dest) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => copy_and_remove(&src, &dest),
+        result => result,
+    }
+}
+
+/// The size and modification time of a file when it was listed, to tell whether another
+/// program replaced or changed it since.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStamp {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    /// The stamp of `path` itself, not of the file a symbolic link points to.
+    pub fn of(path: &Path) -> io::Result<Self> {
+        fs::symlink_metadata(long_path(path)).map(|metadata| Self::from_metadata(&metadata))
+    }
+}
+
+/// Checks that `src` is still there and, given the stamp it was listed with, still the same
+/// file. Moving a file that another program replaced would move something the user never saw.
+pub fn check_unchanged(src: &Path, listed: Option<&FileStamp>) -> io::Result<()> {
+    let current = match FileStamp::of(src) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "It was deleted or moved by another program",
+            ))
+        }
+        result => result?,
+    };
+    if listed.is_some_and(|listed| *listed != current) {
+        return Err(io::Error::other(
+            "It was changed by another program since it was listed",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `path` itself is a symbolic link, whatever it points to.
+pub fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+// Creates the link `dest` to where `src` points and removes `src`. A relative target is made
+// absolute, as it would point somewhere else from the new folder.
+fn move_link(src: &Path, dest: &Path) -> io::Result<()> {
+    let target = fs::read_link(src)?;
+    let target = match src.parent() {
+        Some(parent) if target.is_relative() => parent.join(target),
+        _ => target,
+    };
+    create_symlink(&target, dest)?;
+    fs::remove_file(src)
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn create_symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Symbolic links are not supported here",
+    ))
+}
+
+/// Copies `src` to `dest` with its dates, so that a photo that crossed filesystems still sorts
+/// by when it was taken in other tools, and its extended attributes, such as Finder tags.
+/// `fs::copy()` takes care of the permissions, and on Windows of the attributes. What cannot
+/// be kept is logged and otherwise ignored.
+pub fn copy_file(src: &Path, dest: &Path) -> io::Result<()> {
+    let (src, dest) = (&long_path(src), &long_path(dest));
+    // Taken first, as reading the file for the copy may change when it was accessed.
+    let metadata = fs::metadata(src)?;
+    fs::copy(src, dest)?;
+    if let Err(e) = copy_xattrs(src, dest) {
+        log::warn!("Failed to keep the attributes of {}: {}", dest.display(), e);
+    }
+    if let Err(e) = copy_times(&metadata, dest) {
+        log::warn!("Failed to keep the dates of {}: {}", dest.display(), e);
+    }
+    Ok(())
+}
+
+// Linux leaves the extended attributes behind on a copy, and macOS keeps the download mark
+// whatever the settings say. Attributes the destination does not take, e.g. those of another
+// filesystem, are skipped.
+#[cfg(unix)]
+fn copy_xattrs(src: &Path, dest: &Path) -> io::Result<()> {
+    let keep_download_flag = KEEP_DOWNLOAD_FLAG.load(Ordering::Relaxed);
+    for name in xattr::list(src)? {
+        if name == DOWNLOAD_FLAG && !keep_download_flag {
+            let _ = xattr::remove(dest, &name);
+            continue;
+        }
+        let Some(value) = xattr::get(src, &name)? else {
+            continue;
+        };
+        if let Err(e) = xattr::set(dest, &name, &value) {
+            log::debug!("Did not copy {:?} to {}: {}", name, dest.display(), e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_xattrs(_src: &Path, _dest: &Path) -> io::Result<()> {
+    // Alternate data streams are copied by `fs::copy()` on Windows.
+    Ok(())
+}
+
+fn copy_times(metadata: &fs::Metadata, dest: &Path) -> io::Result<()> {
+    let times = fs::FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+    #[cfg(windows)]
+    let times = match metadata.created() {
+        Ok(created) => std::os::windows::fs::FileTimesExt::set_created(times, created),
+        Err(_) => times,
+    };
+    set_times(dest, times, metadata.permissions())
+}
+
+#[cfg(not(windows))]
+fn set_times(dest: &Path, times: fs::FileTimes, _permissions: fs::Permissions) -> io::Result<()> {
+    fs::File::open(dest)?.set_times(times)
+}
+
+// Windows only sets the times of a file opened for writing, which a read-only one is not
+// until the attribute is cleared for a moment.
+#[cfg(windows)]
+fn set_times(dest: &Path, times: fs::FileTimes, permissions: fs::Permissions) -> io::Result<()> {
+    let readonly = permissions.readonly();
+    if readonly {
+        let mut writable = permissions.clone();
+        writable.set_readonly(false);
+        fs::set_permissions(dest, writable)?;
+    }
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .open(dest)
+        .and_then(|file| file.set_times(times));
+    if readonly {
+        fs::set_permissions(dest, permissions)?;
+    }
+    result
+}
+
+/// Where a copy to `dest` is written until it is complete.
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut partial = dest.as_os_str().to_os_string();
+    partial.push(".part");
+    PathBuf::from(partial)
+}
+
+// The copy is written under a temporary name, so a failed copy never leaves a partial file
+// behind under the real one. It is journaled in case the app dies halfway.
+fn copy_and_remove(src: &Path, dest: &Path) -> io::Result<()> {
+    let dest_dir = dest.parent().unwrap_or(Path::new("."));
+    check_free_space(dest_dir, fs::metadata(src)?.len())?;
+    let partial = partial_path(dest);
+    let id = journal::begin(src, dest);
+    if let Err(e) = copy_file(src, &partial).and_then(|()| fs::rename(&partial, dest)) {
+        let _ = fs::remove_file(&partial);
+        journal::end(id);
+        return Err(e);
+    }
+    if let Err(e) = fs::remove_file(src) {
+        // The image is safe at the destination. Only the original is left over.
+        log::warn!("Copied {} but failed to remove it: {}", src.display(), e);
+    }
+    journal::end(id);
+    Ok(())
+}
+
+/// Creates a hard link to `src` in `dest_dir`, so that the file is in both folders without
+/// taking up space twice. Across filesystems, where that cannot be done, it is copied. Returns
+/// the new path.
+pub fn link_or_copy(src: &Path, dest_dir: &Path) -> io::Result<PathBuf> {
+    let dest = dest_dir.join(file_name(src)?);
+    match fs::hard_link(long_path(src), long_path(&dest)) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => copy_to(src, dest_dir),
+        result => result.map(|()| dest),
+    }
+}
+
+/// Copies `src` into `dest_dir` and returns the path of the copy. Like a move across
+/// filesystems, it is written under a temporary name until it is complete.
+pub fn copy_to(src: &Path, dest_dir: &Path) -> io::Result<PathBuf> {
+    let dest = dest_dir.join(file_name(src)?);
+    let long_dest = long_path(&dest);
+    // Renaming the copy into place would replace a file of the same name.
+    if fs::symlink_metadata(&long_dest).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dest.display()),
+        ));
+    }
+    check_free_space(dest_dir, fs::metadata(long_path(src))?.len())?;
+    let partial = partial_path(&long_dest);
+    if let Err(e) = copy_file(src, &partial).and_then(|()| fs::rename(&partial, &long_dest)) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    Ok(dest)
+}
+
+fn file_name(path: &Path) -> io::Result<&OsStr> {
+    path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No file name"))
+}
+
+/// Sizes such as "12.3 MB" for messages.
+pub fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+/// Fails with `StorageFull` if `bytes` do not fit in the free space where `dir` is.
+pub fn check_free_space(dir: &Path, bytes: u64) -> io::Result<()> {
+    let available = fs4::available_space(existing_ancestor(dir))?;
+    if bytes > available {
+        return Err(io::Error::new(
+            io::ErrorKind::StorageFull,
+            format!(
+                "{} needed but only {} free at {}",
+                format_size(bytes),
+                format_size(available),
+                dir.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that `files` fit in `dest_dir` before moving them there. Files on the same filesystem
+/// are renamed and need no space, so only the others count.
+pub fn check_space_for(files: &[PathBuf], dest_dir: &Path) -> io::Result<()> {
+    let mut bytes = 0;
+    for file in files {
+        let Ok(metadata) = fs::metadata(file) else {
+            // Reported when it is moved.
+            continue;
+        };
+        if !same_filesystem(file, dest_dir)? {
+            bytes += metadata.len();
+        }
+    }
+    if bytes == 0 {
+        return Ok(());
+    }
+    check_free_space(dest_dir, bytes)
+}
+
+/// Runs `work` on a thread of its own and stops waiting for it after `timeout`, as a call on a
+/// stalled network share can block for minutes. The call then finishes in the background.
+/// `path` names what did not answer in the `TimedOut` error.
+pub fn with_timeout<T: Send + 'static>(
+    path: &Path,
+    timeout: Duration,
+    work: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> io::Result<T> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(work());
+    });
+    receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "{} is unreachable: no answer within {} s",
+                path.display(),
+                timeout.as_secs()
+            ),
+        ))
+    })
+}
+
+/// Fails with `TimedOut` if `path`, or the folder it would be created in, does not answer
+/// within `timeout`.
+pub fn check_reachable(path: &Path, timeout: Duration) -> io::Result<()> {
+    let owned = path.to_path_buf();
+    with_timeout(path, timeout, move || {
+        fs::metadata(existing_ancestor(&owned)).map(|_| ())
+    })
+}
+
+// `dir` or the closest folder above it that exists. Destinations with a date template are
+// created on the first move.
+fn existing_ancestor(dir: &Path) -> &Path {
+    dir.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(dir)
+}
+
+/// Runs `command` with `input` on its stdin and collects what it writes, giving up on it after
+/// `timeout` like `with_timeout()`. Fails if it does not exit with success.
+pub fn output_with_timeout(
+    mut command: Command,
+    input: Vec<u8>,
+    timeout: Duration,
+) -> io::Result<Vec<u8>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let work = move || {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // Written from another thread, as the program may fill stdout before reading all of
+        // stdin.
+        let writer = thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        // It may stop reading early, e.g. at a damaged file. Its exit status tells.
+        let _ = writer.join();
+        Ok(output)
+    };
+    let output = match with_timeout(Path::new(&program), timeout, work) {
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} did not finish within {} s", program, timeout.as_secs()),
+            ))
+        }
+        result => result?,
+    };
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// True if `a` and `b` are on the same filesystem, so that moving between them is a rename.
+pub fn same_filesystem(a: &Path, b: &Path) -> io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(fs::metadata(existing_ancestor(a))?.dev() == fs::metadata(existing_ancestor(b))?.dev())
+    }
+    #[cfg(not(unix))]
+    {
+        // The drive or share the paths are on.
+        let root = |path: &Path| -> io::Result<Option<std::ffi::OsString>> {
+            let path = fs::canonicalize(existing_ancestor(path))?;
+            Ok(path
+                .components()
+                .next()
+                .map(|component| component.as_os_str().to_ascii_lowercase()))
+        };
+        Ok(root(a)? == root(b)?)
+    }
+}
+
+/// BLAKE3 hash of the file contents as a hex string. Files with the same hash are duplicates.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// True if the two files have the same contents.
+pub fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    Ok(hash_file(a)? == hash_file(b)?)
+}
+
+/// A file in `dir` or a folder under it with the same contents as `src`, other than `src`
+/// itself. Only files of the same size are hashed. Links to folders are not followed, so that
+/// a link back up does not go round forever.
+pub fn find_identical(src: &Path, dir: &Path) -> io::Result<Option<PathBuf>> {
+    let size = fs::metadata(src)?.len();
+    let mut src_hash = None;
+    let mut folders = vec![dir.to_path_buf()];
+    let mut paths = Vec::new();
+    while let Some(folder) = folders.pop() {
+        // Only `dir` itself must be readable. A folder under it that is not is skipped.
+        let entries = match fs::read_dir(&folder) {
+            Ok(entries) => entries,
+            Err(e) if folder == dir => return Err(e),
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => folders.push(entry.path()),
+                Ok(_) => paths.push(entry.path()),
+                Err(_) => {}
+            }
+        }
+    }
+    for path in paths {
+        if path == src || !fs::metadata(&path).is_ok_and(|m| m.is_file() && m.len() == size) {
+            continue;
+        }
+        let src_hash = match &src_hash {
+            Some(hash) => hash,
+            None => src_hash.insert(hash_file(src)?),
+        };
+        if hash_file(&path).is_ok_and(|hash| hash == *src_hash) {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// A path in `dir` for a file named `file_name` that does not exist yet. If the name is taken,
+/// " (1)", " (2)", ... is appended to the file stem.
+pub fn unique_path(dir: &Path, file_name: &OsStr) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let file_name = Path::new(file_name);
+    let stem = file_name.file_stem().unwrap_or(file_name.as_os_str());
+    let extension = file_name.extension();
+    (1..)
+        .map(|i| {
+            let mut name = stem.to_os_string();
+            name.push(format!(" ({})", i));
+            if let Some(extension) = extension {
+                name.push(".");
+                name.push(extension);
+            }
+            dir.join(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+/// Where `companion`, e.g. a sidecar, goes when the image it belongs to is renamed from `old` to
+/// `new`. "IMG_1.xmp" and "IMG_1.JPG.xmp" of "IMG_1.JPG" become "a.xmp" and "a.JPG.xmp" when it is
+/// renamed to "a.JPG". None if the name does not follow the image's.
+pub fn renamed_companion(companion: &Path, old: &Path, new: &Path) -> Option<PathBuf> {
+    let name = companion.file_name()?.to_str()?;
+    let old_name = old.file_name()?.to_str()?;
+    let new_name = new.file_name()?.to_str()?;
+    let old_stem = old.file_stem()?.to_str()?;
+    let new_stem = new.file_stem()?.to_str()?;
+    let renamed = if let Some(rest) = name.strip_prefix(old_name) {
+        format!("{}{}", new_name, rest)
+    } else {
+        let rest = name
+            .strip_prefix(old_stem)
+            .filter(|rest| rest.starts_with('.'))?;
+        format!("{}{}", new_stem, rest)
+    };
+    Some(companion.with_file_name(renamed))
+}
+
+/// Returns true if the error is likely to go away by itself, e.g. another process still has the
+/// file open.
+pub fn is_transient_error(e: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION. This is what Windows returns when a
+    // screenshot tool is still writing the file or a previewer has it open.
+    #[cfg(windows)]
+    if matches!(e.raw_os_error(), Some(32) | Some(33)) {
+        return true;
+    }
+    e.kind() == io::ErrorKind::ResourceBusy
+}
+
+/// How many times and how quickly a file operation is retried before giving up.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    // Short enough to not hold up a batch noticeably. The UI makes one attempt, see `once()`.
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(400),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt, for callers that queue a transient failure rather than wait it out.
+    pub fn once() -> Self {
+        Self {
+            attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the retry that follows `attempt` failed attempts.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+/// Same as `move_file()` but retries transient errors with backoff. This blocks the calling
+/// thread.
+pub fn move_file_with_retry(
+    src: &Path,
+    dest_dir: &Path,
+    policy: &RetryPolicy,
+) -> io::Result<PathBuf> {
+    with_retry(src, policy, || move_file(src, dest_dir))
+}
+
+/// Runs `operation` on `src` until it succeeds, fails for good or runs out of attempts.
+pub fn with_retry<T>(
+    src: &Path,
+    policy: &RetryPolicy,
+    mut operation: impl FnMut() -> io::Result<T>,
+) -> io::Result<T> {
+    let mut attempt = 1;
+    loop {
+        match operation() {
+            Err(e) if is_transient_error(&e) && attempt < policy.attempts => {
+                let delay = policy.delay(attempt);
+                log::warn!(
+                    "Failed to move {} (attempt {}): {}. Retrying in {:?}.",
+                    src.display(),
+                    attempt,
+                    e,
+                    delay
+                );
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A move that is waiting to be retried in the background.
+#[derive(Clone, Debug)]
+pub struct QueuedMove {
+    pub src: PathBuf,
+    pub dest_dir: PathBuf,
+    // Number of failed attempts so far.
+    pub attempts: u32,
+    pub next_attempt: Instant,
+    pub last_error: String,
+    // Copies already made to other destinations. They are kept for when the move is done.
+    pub copies: Vec<PathBuf>,
+    // Remote destinations the file is uploaded to once it is moved.
+    pub uploads: Vec<PathBuf>,
+}
+
+/// Moves that failed with a transient error. Unlike `move_file_with_retry()` this does not block;
+/// the owner should call `take_due()` periodically and retry the returned moves.
+pub struct RetryQueue {
+    policy: RetryPolicy,
+    entries: Vec<QueuedMove>,
+}
+
+impl Default for RetryQueue {
+    fn default() -> Self {
+        Self {
+            policy: RetryPolicy {
+                attempts: 10,
+                initial_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(30),
+            },
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl RetryQueue {
+    pub fn push(
+        &mut self,
+        src: &Path,
+        dest_dir: &Path,
+        copies: Vec<PathBuf>,
+        uploads: Vec<PathBuf>,
+        error: &io::Error,
+        now: Instant,
+    ) {
+        self.entries.push(QueuedMove {
+            src: src.to_path_buf(),
+            dest_dir: dest_dir.to_path_buf(),
+            attempts: 1,
+            next_attempt: now + self.policy.delay(1),
+            last_error: error.to_string(),
+            copies,
+            uploads,
+        });
+    }
+
+    /// Removes and returns the moves that should be attempted now.
+    pub fn take_due(&mut self, now: Instant) -> Vec<QueuedMove> {
+        let (due, waiting) = self
+            .entries
+            .drain(..)
+            .partition(|entry| entry.next_attempt <= now);
+        self.entries = waiting;
+        due
+    }
+
+    /// Puts back a move whose retry failed again. Returns the move if it ran out of attempts.
+    pub fn requeue(
+        &mut self,
+        mut entry: QueuedMove,
+        error: &io::Error,
+        now: Instant,
+    ) -> Option<QueuedMove> {
+        entry.attempts += 1;
+        entry.last_error = error.to_string();
+        if entry.attempts >= self.policy.attempts {
+            return Some(entry);
+        }
+        entry.next_attempt = now + self.policy.delay(entry.attempts);
+        self.entries.push(entry);
+        None
+    }
+
+    pub fn retry_now(&mut self, index: usize) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            entry.next_attempt = Instant::now();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<QueuedMove> {
+        (index < self.entries.len()).then(|| self.entries.remove(index))
+    }
+
+    pub fn entries(&self) -> &[QueuedMove] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // Move a temporary file from one folder to another.
+    #[test]
+    fn move_file_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("test.jpg");
+        let dest_dir = temp_dir.path().join("test_dest");
+        fs::create_dir(&dest_dir).unwrap();
+        std::fs::write(&src_path, b"Hello, world!").unwrap();
+        assert!(src_path.exists());
+        move_file(&src_path, &dest_dir).unwrap();
+        assert!(!src_path.exists());
+        assert!(dest_dir.join("test.jpg").exists());
+    }
+
+    #[test]
+    fn taken_name_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dest_dir = dir.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        for path in [dir.join("a.png"), dir.join("a.xmp"), dest_dir.join("a.png")] {
+            fs::write(path, b"").unwrap();
+        }
+        assert!(is_taken(&dest_dir.join("a.png"), &dir.join("a.png")));
+        assert!(!is_taken(&dest_dir.join("b.png"), &dir.join("a.png")));
+        // Moving into its own folder.
+        assert!(!is_taken(&dir.join("a.png"), &dir.join("a.png")));
+        assert_eq!(
+            dest_path(&dir.join("a.png"), &dest_dir, CollisionPolicy::Skip)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::AlreadyExists
+        );
+
+        // Never replaced. The companion follows the new name.
+        let moved = move_file(&dir.join("a.png"), &dest_dir).unwrap();
+        assert_eq!(moved, dest_dir.join("a (1).png"));
+        let companion = move_companion(
+            &dir.join("a.xmp"),
+            &dir.join("a.png"),
+            &moved,
+            &RetryPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(companion, dest_dir.join("a (1).xmp"));
+        assert!(dest_dir.join("a.png").exists());
+
+        #[cfg(target_os = "linux")]
+        assert!(!is_case_insensitive(dir));
+        assert_eq!(name_key(OsStr::new("IMG.JPG"), true), "img.jpg");
+        assert_eq!(name_key(OsStr::new("IMG.JPG"), false), "IMG.JPG");
+    }
+
+    #[test]
+    fn check_unchanged_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("a.png");
+        fs::write(&src, "a").unwrap();
+        let listed = FileStamp::of(&src).unwrap();
+        assert!(check_unchanged(&src, Some(&listed)).is_ok());
+        assert!(check_unchanged(&src, None).is_ok());
+
+        // Replaced by a file of another size.
+        fs::write(&src, "replaced").unwrap();
+        let e = check_unchanged(&src, Some(&listed)).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Other);
+        // Never listed, so only whether it is there is known.
+        assert!(check_unchanged(&src, None).is_ok());
+
+        fs::remove_file(&src).unwrap();
+        let e = check_unchanged(&src, None).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn extended_form_test() {
+        assert_eq!(
+            extended_form(r"C:\photos\2024\a.jpg").as_deref(),
+            Some(r"\\?\C:\photos\2024\a.jpg")
+        );
+        assert_eq!(
+            extended_form(r"C:/photos/./cats\..\dogs\a.jpg").as_deref(),
+            Some(r"\\?\C:\photos\dogs\a.jpg")
+        );
+        assert_eq!(
+            extended_form(r"\\nas\photos\a.jpg").as_deref(),
+            Some(r"\\?\UNC\nas\photos\a.jpg")
+        );
+        assert_eq!(
+            extended_form(r"\\nas\photos\..\..\a.jpg").as_deref(),
+            Some(r"\\?\UNC\nas\photos\a.jpg")
+        );
+        assert_eq!(extended_form(r"\\?\C:\photos"), None);
+        assert_eq!(extended_form(r"photos\a.jpg"), None);
+        assert_eq!(extended_form("/photos/a.jpg"), None);
+
+        assert_eq!(short_form(r"\\?\C:\photos").as_deref(), Some(r"C:\photos"));
+        assert_eq!(
+            short_form(r"\\?\UNC\nas\photos").as_deref(),
+            Some(r"\\nas\photos")
+        );
+        assert_eq!(short_form(r"\\?\Volume{1234}\photos"), None);
+        assert_eq!(short_form(r"C:\photos"), None);
+        // Nothing changes elsewhere.
+        assert_eq!(
+            long_path(Path::new("/photos/a.jpg")),
+            Path::new("/photos/a.jpg")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn move_link_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest_dir = temp_dir.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        fs::write(temp_dir.path().join("a.png"), b"image").unwrap();
+        let link = temp_dir.path().join("link.png");
+        std::os::unix::fs::symlink("a.png", &link).unwrap();
+        assert!(is_symlink(&link));
+        assert!(!is_symlink(&temp_dir.path().join("a.png")));
+
+        let moved = move_file(&link, &dest_dir).unwrap();
+        assert!(!link.exists());
+        assert!(is_symlink(&moved));
+        // Still the same file, although the link was relative.
+        assert_eq!(fs::read(&moved).unwrap(), b"image");
+        assert!(temp_dir.path().join("a.png").exists());
+    }
+
+    #[test]
+    fn link_or_copy_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("a.png");
+        let album = temp_dir.path().join("album");
+        fs::create_dir(&album).unwrap();
+        fs::write(&src, b"image").unwrap();
+        let dest = link_or_copy(&src, &album).unwrap();
+        assert_eq!(dest, album.join("a.png"));
+        assert_eq!(fs::read(&dest).unwrap(), b"image");
+        assert!(src.exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(fs::metadata(&src).unwrap().nlink(), 2);
+        }
+        // Already in the album.
+        assert!(link_or_copy(&src, &album).is_err());
+    }
+
+    #[test]
+    fn with_timeout_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(
+            check_reachable(&temp_dir.path().join("new/album"), Duration::from_secs(5)).is_ok()
+        );
+
+        let stalled = with_timeout(Path::new("/mnt/nas"), Duration::from_millis(10), || {
+            thread::sleep(Duration::from_secs(1));
+            Ok(())
+        });
+        let e = stalled.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        assert!(e.to_string().starts_with("/mnt/nas is unreachable"));
+        assert_eq!(
+            with_timeout(Path::new("/mnt/nas"), Duration::from_secs(5), || Ok(1)).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn copy_to_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("a.png");
+        let archive = temp_dir.path().join("archive");
+        fs::create_dir(&archive).unwrap();
+        fs::write(&src, b"image").unwrap();
+        let dest = copy_to(&src, &archive).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"image");
+        assert!(src.exists());
+        assert!(!archive.join("a.png.part").exists());
+        assert_eq!(
+            copy_to(&src, &archive).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+    }
+
+    #[test]
+    fn copy_and_remove_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("a.png");
+        let dest = temp_dir.path().join("b.png");
+        fs::write(&src, b"image").unwrap();
+        copy_and_remove(&src, &dest).unwrap();
+        assert!(!src.exists());
+        assert_eq!(fs::read(&dest).unwrap(), b"image");
+        assert!(!temp_dir.path().join("b.png.part").exists());
+
+        // Across filesystems the dates are kept too.
+        let old = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let file = fs::File::options().write(true).open(&dest).unwrap();
+        file.set_times(fs::FileTimes::new().set_accessed(old).set_modified(old))
+            .unwrap();
+        drop(file);
+        let mut permissions = fs::metadata(&dest).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&dest, permissions).unwrap();
+        let copy = temp_dir.path().join("c.png");
+        copy_file(&dest, &copy).unwrap();
+        let metadata = fs::metadata(&copy).unwrap();
+        assert_eq!(metadata.modified().unwrap(), old);
+        assert_eq!(metadata.accessed().unwrap(), old);
+        assert!(metadata.permissions().readonly());
+
+        // A failed copy leaves nothing behind.
+        let missing_dir = temp_dir.path().join("missing").join("c.png");
+        assert!(copy_and_remove(&dest, &missing_dir).is_err());
+        assert!(dest.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_xattrs_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("a.png");
+        fs::write(&src, b"image").unwrap();
+        if xattr::set(&src, "user.xdg.tags", b"cats").is_err() {
+            // Not supported by the filesystem of the temp directory.
+            return;
+        }
+        let dest = temp_dir.path().join("b.png");
+        copy_file(&src, &dest).unwrap();
+        assert_eq!(
+            xattr::get(&dest, "user.xdg.tags").unwrap(),
+            Some(b"cats".to_vec())
+        );
+    }
+
+    #[test]
+    fn free_space_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        assert!(check_free_space(dir, 0).is_ok());
+        let e = check_free_space(&dir.join("{year}"), u64::MAX).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::StorageFull);
+
+        let file = dir.join("a.png");
+        fs::write(&file, b"image").unwrap();
+        assert!(same_filesystem(&file, dir).unwrap());
+        // Only files on other filesystems need space.
+        assert!(check_space_for(&[file], dir).is_ok());
+    }
+
+    #[test]
+    fn renamed_companion_test() {
+        let old = Path::new("/photos/IMG_1.JPG");
+        let new = Path::new("/photos/beach.JPG");
+        let renamed = |name: &str| renamed_companion(&Path::new("/photos").join(name), old, new);
+        assert_eq!(
+            renamed("IMG_1.xmp"),
+            Some(PathBuf::from("/photos/beach.xmp"))
+        );
+        assert_eq!(
+            renamed("IMG_1.JPG.json"),
+            Some(PathBuf::from("/photos/beach.JPG.json"))
+        );
+        assert_eq!(
+            renamed("IMG_1.CR2"),
+            Some(PathBuf::from("/photos/beach.CR2"))
+        );
+        assert_eq!(renamed("IMG_10.xmp"), None);
+    }
+
+    #[test]
+    fn unique_path_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        assert_eq!(unique_path(dir, OsStr::new("a.png")), dir.join("a.png"));
+        fs::write(dir.join("a.png"), b"1").unwrap();
+        fs::write(dir.join("a (1).png"), b"2").unwrap();
+        assert_eq!(unique_path(dir, OsStr::new("a.png")), dir.join("a (2).png"));
+
+        assert!(!same_contents(&dir.join("a.png"), &dir.join("a (1).png")).unwrap());
+        fs::write(dir.join("b.png"), b"1").unwrap();
+        assert!(same_contents(&dir.join("a.png"), &dir.join("b.png")).unwrap());
+    }
+
+    #[test]
+    fn find_identical_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src = temp_dir.path().join("a.png");
+        let dest_dir = temp_dir.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        fs::write(&src, b"image").unwrap();
+        fs::write(dest_dir.join("other.png"), b"other").unwrap();
+        assert_eq!(find_identical(&src, &dest_dir).unwrap(), None);
+
+        // In a folder under the destination.
+        fs::create_dir(dest_dir.join("2024")).unwrap();
+        fs::write(dest_dir.join("2024").join("copy.png"), b"image").unwrap();
+        assert_eq!(
+            find_identical(&src, &dest_dir).unwrap(),
+            Some(dest_dir.join("2024").join("copy.png"))
+        );
+        // Not the file itself.
+        fs::remove_dir_all(dest_dir.join("2024")).unwrap();
+        assert_eq!(find_identical(&src, temp_dir.path()).unwrap(), None);
+    }
+
+    #[test]
+    fn retry_policy_delay_test() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(10), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn retry_queue_gives_up_test() {
+        let mut queue = RetryQueue::default();
+        let error = io::Error::from(io::ErrorKind::ResourceBusy);
+        let now = Instant::now();
+        queue.push(
+            Path::new("a.png"),
+            Path::new("dest"),
+            Vec::new(),
+            Vec::new(),
+            &error,
+            now,
+        );
+        assert!(queue.take_due(now).is_empty());
+
+        let mut now = now;
+        for _ in 1..queue.policy.attempts - 1 {
+            now += queue.policy.max_delay;
+            let mut due = queue.take_due(now);
+            assert_eq!(due.len(), 1);
+            assert!(queue.requeue(due.remove(0), &error, now).is_none());
+        }
+        now += queue.policy.max_delay;
+        let mut due = queue.take_due(now);
+        let gave_up = queue.requeue(due.remove(0), &error, now).unwrap();
+        assert_eq!(gave_up.src, Path::new("a.png"));
+        assert!(queue.is_empty());
+    }
+}
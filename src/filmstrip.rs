@@ -0,0 +1,274 @@
+use eframe::egui;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{labels::ColorLabel, scanner::FolderOrder, thumbnails::Thumbnails};
+
+// Height of the strip and the size of each thumbnail in it.
+pub const FILMSTRIP_SIZE: f32 = 96.0;
+
+// Size of the thumbnails in the strip of recent moves.
+pub const RECENT_MOVE_SIZE: f32 = 48.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilmstripEntry {
+    pub path: PathBuf,
+    // Where the image was moved this session. Moved images keep their place in the strip.
+    pub moved_to: Option<PathBuf>,
+}
+
+/// What the user clicked in the strip.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilmstripClick {
+    Image(PathBuf),
+    Moved { src: PathBuf, dest: PathBuf },
+}
+
+/// Entries for the images in the queued folders and the ones moved out of them. `images` must be
+/// sorted with `order`.
+pub fn build_entries<'a>(
+    order: &FolderOrder,
+    images: &[PathBuf],
+    moves: impl Iterator<Item = (&'a Path, &'a Path)>,
+) -> Vec<FilmstripEntry> {
+    let moved: HashMap<PathBuf, PathBuf> = moves
+        .filter(|(src, _)| order.contains(src))
+        .map(|(src, dest)| (src.to_path_buf(), dest.to_path_buf()))
+        .collect();
+    let mut moved_paths: Vec<PathBuf> = moved.keys().cloned().collect();
+    order.sort(&mut moved_paths);
+    order
+        .merge(images.to_vec(), moved_paths)
+        .into_iter()
+        .map(|path| FilmstripEntry {
+            moved_to: moved.get(&path).cloned(),
+            path,
+        })
+        .collect()
+}
+
+/// A horizontal strip of thumbnails. Only the visible thumbnails are drawn, so it stays fast
+/// with hundreds of thousands of images.
+#[derive(Default)]
+pub struct Filmstrip {
+    entries: Vec<FilmstripEntry>,
+    // Rebuilding is linear in the number of images, so it only happens when this changes.
+    key: Option<(usize, usize, Option<PathBuf>, usize)>,
+    // The image that was last scrolled into view.
+    scrolled_to: Option<PathBuf>,
+}
+
+impl Filmstrip {
+    /// Refreshes the entries if the images or the moves changed.
+    pub fn update<'a>(
+        &mut self,
+        order: &FolderOrder,
+        images: &[PathBuf],
+        moves: impl ExactSizeIterator<Item = (&'a Path, &'a Path)>,
+    ) {
+        let key = (
+            images.len(),
+            moves.len(),
+            images.first().cloned(),
+            order.dates().len(),
+        );
+        if self.key.as_ref() == Some(&key) {
+            return;
+        }
+        self.entries = build_entries(order, images, moves);
+        self.key = Some(key);
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        thumbnails: &mut Thumbnails,
+        current: Option<&Path>,
+        labels: &HashMap<PathBuf, ColorLabel>,
+    ) -> Option<FilmstripClick> {
+        let stride = FILMSTRIP_SIZE + ui.spacing().item_spacing.x;
+        let scroll_to = match current {
+            Some(current) if self.scrolled_to.as_deref() != Some(current) => {
+                self.scrolled_to = Some(current.to_path_buf());
+                self.entries.iter().position(|entry| entry.path == current)
+            }
+            _ => None,
+        };
+        let mut scroll_area = egui::ScrollArea::horizontal()
+            .id_salt("filmstrip")
+            .auto_shrink([false, true]);
+        if let Some(index) = scroll_to {
+            // Center the current image.
+            let offset = index as f32 * stride - (ui.available_width() - FILMSTRIP_SIZE) / 2.0;
+            scroll_area = scroll_area.horizontal_scroll_offset(offset.max(0.0));
+        }
+
+        let mut clicked = None;
+        scroll_area.show_viewport(ui, |ui, viewport| {
+            let origin = ui.min_rect().min;
+            ui.set_width(self.entries.len() as f32 * stride);
+            ui.set_height(FILMSTRIP_SIZE);
+            let first = (viewport.min.x / stride).floor().max(0.0) as usize;
+            let last = ((viewport.max.x / stride).ceil() as usize + 1).min(self.entries.len());
+            for index in first..last {
+                let entry = &self.entries[index];
+                let rect = egui::Rect::from_min_size(
+                    origin + egui::vec2(index as f32 * stride, 0.0),
+                    egui::Vec2::splat(FILMSTRIP_SIZE),
+                );
+                let response = ui.allocate_rect(rect, egui::Sense::click());
+                let selected = current == Some(entry.path.as_path());
+                match &entry.moved_to {
+                    Some(dest) => {
+                        // The source is gone, so the thumbnail comes from the new location.
+                        thumbnails.paint(ui, rect, dest, selected);
+                        ui.painter()
+                            .rect_filled(rect, 2.0, egui::Color32::from_black_alpha(160));
+                        ui.painter().text(
+                            rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            "Moved",
+                            egui::FontId::proportional(14.0),
+                            egui::Color32::WHITE,
+                        );
+                    }
+                    None => thumbnails.paint(ui, rect, &entry.path, selected),
+                }
+                if let Some(label) = labels.get(&entry.path) {
+                    ui.painter().rect_stroke(
+                        rect.shrink(1.5),
+                        2.0,
+                        egui::Stroke::new(3.0, label.color()),
+                    );
+                }
+                let file_name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+                let hover_text = match &entry.moved_to {
+                    Some(dest) => format!("{} (moved to {})", file_name, dest.display()),
+                    None => file_name.to_string(),
+                };
+                response.widget_info(|| {
+                    egui::WidgetInfo::selected(
+                        egui::WidgetType::ImageButton,
+                        true,
+                        selected,
+                        &hover_text,
+                    )
+                });
+                if response.on_hover_text(hover_text).clicked() {
+                    clicked = Some(match &entry.moved_to {
+                        Some(dest) => FilmstripClick::Moved {
+                            src: entry.path.clone(),
+                            dest: dest.clone(),
+                        },
+                        None => FilmstripClick::Image(entry.path.clone()),
+                    });
+                }
+            }
+        });
+        clicked
+    }
+}
+
+/// An image moved this session, for the strip of recent moves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecentMove {
+    // Where the move is in the move log.
+    pub index: usize,
+    pub dest: PathBuf,
+    // The key of the destination and its icon, or "" if no key moves there.
+    pub key: String,
+}
+
+/// Draws small thumbnails of `moves` with their keys. Returns the index of the move that was
+/// clicked to be undone.
+pub fn show_recent_moves(
+    ui: &mut egui::Ui,
+    thumbnails: &mut Thumbnails,
+    moves: &[RecentMove],
+) -> Option<usize> {
+    let mut clicked = None;
+    ui.horizontal(|ui| {
+        for recent in moves {
+            let (rect, response) =
+                ui.allocate_exact_size(egui::Vec2::splat(RECENT_MOVE_SIZE), egui::Sense::click());
+            thumbnails.paint(ui, rect, &recent.dest, false);
+            if !recent.key.is_empty() {
+                let font = egui::FontId::proportional(12.0);
+                let galley =
+                    ui.painter()
+                        .layout_no_wrap(recent.key.clone(), font, egui::Color32::WHITE);
+                let key_rect = egui::Align2::RIGHT_BOTTOM
+                    .anchor_size(rect.right_bottom(), galley.size())
+                    .expand(2.0);
+                ui.painter()
+                    .rect_filled(key_rect, 2.0, egui::Color32::from_black_alpha(180));
+                ui.painter()
+                    .galley(key_rect.shrink(2.0).min, galley, egui::Color32::WHITE);
+            }
+            let file_name = recent
+                .dest
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let folder = recent.dest.parent().unwrap_or(Path::new(""));
+            let hover_text = format!(
+                "{} was moved to {}. Click to undo.",
+                file_name,
+                folder.display()
+            );
+            response.widget_info(|| {
+                egui::WidgetInfo::labeled(egui::WidgetType::ImageButton, true, &hover_text)
+            });
+            if response.on_hover_text(hover_text).clicked() {
+                clicked = Some(recent.index);
+            }
+        }
+    });
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_entries_test() {
+        let folder = Path::new("/images");
+        let order = FolderOrder::new(vec![folder.to_path_buf()]);
+        let images = vec![folder.join("c.png"), folder.join("a.png")];
+        let moves = [
+            (folder.join("b.png"), PathBuf::from("/sorted/b.png")),
+            // Moved out of another folder.
+            (
+                PathBuf::from("/other/d.png"),
+                PathBuf::from("/sorted/d.png"),
+            ),
+        ];
+        let entries = build_entries(
+            &order,
+            &images,
+            moves
+                .iter()
+                .map(|(src, dest)| (src.as_path(), dest.as_path())),
+        );
+        assert_eq!(
+            entries,
+            vec![
+                FilmstripEntry {
+                    path: folder.join("c.png"),
+                    moved_to: None,
+                },
+                FilmstripEntry {
+                    path: folder.join("b.png"),
+                    moved_to: Some(PathBuf::from("/sorted/b.png")),
+                },
+                FilmstripEntry {
+                    path: folder.join("a.png"),
+                    moved_to: None,
+                },
+            ]
+        );
+    }
+}
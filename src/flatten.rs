@@ -0,0 +1,247 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{
+    batch::OnFile,
+    file_ops::{self, RetryPolicy},
+    i18n::tr,
+    scanner,
+    settings::CollisionPolicy,
+};
+
+/// Images anywhere under `root`, except the ones directly in `target`. Symbolic links to
+/// folders are not followed so that loops cannot happen.
+pub fn find_nested_images(root: &Path, target: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    let mut folders = vec![root.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        for entry in fs::read_dir(&folder)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                folders.push(path);
+            } else if scanner::is_image_file(&path) && folder != target {
+                images.push(path);
+            }
+        }
+    }
+    images.sort();
+    Ok(images)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FlattenReport {
+    // (source, destination) of each moved file.
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// Moves `images` into `target`. Files whose name is taken get a new name, e.g. "a (1).png".
+/// Stops early when `on_file` says so.
+pub fn flatten(images: &[PathBuf], target: &Path, on_file: &mut OnFile) -> FlattenReport {
+    let mut report = FlattenReport::default();
+    if let Err(e) = fs::create_dir_all(target) {
+        report.errors.push((target.to_path_buf(), e.to_string()));
+        return report;
+    }
+    for src in images {
+        let result = file_ops::with_retry(src, &RetryPolicy::default(), || {
+            file_ops::move_file_as(src, target, CollisionPolicy::KeepBoth)
+        });
+        let result = match result {
+            Ok(dest) => {
+                report.moved.push((src.clone(), dest));
+                Ok(())
+            }
+            Err(e) => {
+                report.errors.push((src.clone(), e.to_string()));
+                Err(e.to_string())
+            }
+        };
+        if !on_file(src, result) {
+            break;
+        }
+    }
+    report
+}
+
+/// Images to move into one folder, chosen in the window.
+pub struct FlattenJob {
+    pub images: Vec<PathBuf>,
+    pub target: PathBuf,
+}
+
+/// State of the "Flatten folders" window.
+#[derive(Default)]
+pub struct FlattenTool {
+    root: Option<PathBuf>,
+    target: Option<PathBuf>,
+    // Walking a large tree takes a while, so it runs in the background.
+    receiver: Option<mpsc::Receiver<io::Result<Vec<PathBuf>>>>,
+    found: Option<Vec<PathBuf>>,
+    error: Option<String>,
+}
+
+impl FlattenTool {
+    fn start_search(&mut self, root: PathBuf, target: PathBuf) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(find_nested_images(&root, &target));
+        });
+        self.receiver = Some(receiver);
+        self.found = None;
+        self.error = None;
+    }
+
+    /// Collects the result of a running search.
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err(io::Error::other(tr!("search-stopped"))),
+        };
+        match result {
+            Ok(found) => self.found = Some(found),
+            Err(e) => self.error = Some(tr!("list-images-failed", error = e.to_string())),
+        }
+        self.receiver = None;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Draws the window contents. Returns the images to move when "Move all" was clicked.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<FlattenJob> {
+        ui.label(tr!("flatten-intro"));
+        let mut changed = false;
+        for (label, folder) in [
+            (tr!("folder"), &mut self.root),
+            (tr!("flatten-target"), &mut self.target),
+        ] {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                if ui.button(tr!("choose")).clicked() {
+                    if let Some(path) = FileDialog::new().pick_folder() {
+                        *folder = Some(path);
+                        changed = true;
+                    }
+                }
+                match folder {
+                    Some(folder) => ui.label(folder.to_string_lossy()),
+                    None => ui.label(tr!("not-selected")),
+                };
+            });
+        }
+        if changed {
+            self.found = None;
+        }
+
+        let folders = self.root.clone().zip(self.target.clone());
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    folders.is_some() && !self.is_busy(),
+                    egui::Button::new(tr!("find-images")),
+                )
+                .clicked()
+            {
+                if let Some((root, target)) = folders {
+                    self.start_search(root, target);
+                }
+            }
+            if self.is_busy() {
+                ui.spinner();
+                ui.label(tr!("searching"));
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let found = self.found.as_ref()?;
+        let target = self.target.as_ref()?;
+        ui.label(tr!("images-found", count = found.len()));
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical().max_height(200.0).show_rows(
+            ui,
+            row_height,
+            found.len(),
+            |ui, rows| {
+                for path in &found[rows] {
+                    ui.label(path.to_string_lossy());
+                }
+            },
+        );
+        if !ui
+            .add_enabled(!found.is_empty(), egui::Button::new(tr!("move-all")))
+            .clicked()
+        {
+            return None;
+        }
+        let job = FlattenJob {
+            images: self.found.take()?,
+            target: target.clone(),
+        };
+        Some(job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        let target = root.join("all");
+        fs::create_dir_all(root.join("2020/trip")).unwrap();
+        fs::create_dir_all(root.join("2021")).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(root.join("top.png"), b"top").unwrap();
+        fs::write(root.join("2020/trip/a.png"), b"2020").unwrap();
+        fs::write(root.join("2021/a.png"), b"2021").unwrap();
+        fs::write(root.join("2021/notes.txt"), b"").unwrap();
+        fs::write(target.join("already.png"), b"").unwrap();
+
+        let images = find_nested_images(&root, &target).unwrap();
+        assert_eq!(
+            images,
+            vec![
+                root.join("2020/trip/a.png"),
+                root.join("2021/a.png"),
+                root.join("top.png"),
+            ]
+        );
+
+        let mut reported = Vec::new();
+        let report = flatten(&images, &target, &mut |path, result| {
+            reported.push((path.to_path_buf(), result.is_ok()));
+            true
+        });
+        assert_eq!(reported.len(), 3);
+        assert!(reported.iter().all(|(_, ok)| *ok));
+        assert!(report.errors.is_empty());
+        assert_eq!(
+            report.moved,
+            vec![
+                (root.join("2020/trip/a.png"), target.join("a.png")),
+                (root.join("2021/a.png"), target.join("a (1).png")),
+                (root.join("top.png"), target.join("top.png")),
+            ]
+        );
+        assert_eq!(fs::read(target.join("a (1).png")).unwrap(), b"2021");
+        assert!(root.join("2021/notes.txt").exists());
+    }
+}
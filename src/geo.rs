@@ -0,0 +1,287 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::{f64::consts::PI, path::PathBuf};
+
+use crate::{
+    batch::ImageScan,
+    i18n::tr,
+    metadata::{self, GpsPosition},
+};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+// Zoom level of the map tile in the info window. About a neighborhood.
+const TILE_ZOOM: u32 = 14;
+
+/// Great-circle distance.
+pub fn distance_km(a: GpsPosition, b: GpsPosition) -> f64 {
+    let (lat1, lat2) = (a.latitude.to_radians(), b.latitude.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (b.longitude - a.longitude).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+pub fn openstreetmap_url(gps: GpsPosition) -> String {
+    format!(
+        "https://www.openstreetmap.org/?mlat={lat:.6}&mlon={lon:.6}#map=15/{lat:.6}/{lon:.6}",
+        lat = gps.latitude,
+        lon = gps.longitude
+    )
+}
+
+// The OpenStreetMap tile that contains `gps` and where in the tile it is, from 0.0 to 1.0.
+fn tile(gps: GpsPosition, zoom: u32) -> (u32, u32, egui::Vec2) {
+    let n = f64::from(1u32 << zoom);
+    let x = (gps.longitude + 180.0) / 360.0 * n;
+    let lat = gps.latitude.to_radians();
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+    let (x, y) = (x.clamp(0.0, n - 1e-9), y.clamp(0.0, n - 1e-9));
+    (
+        x as u32,
+        y as u32,
+        egui::vec2(x.fract() as f32, y.fract() as f32),
+    )
+}
+
+/// Draws the map tile around `gps` with a marker. The tile is downloaded from OpenStreetMap.
+pub fn show_map_tile(ui: &mut egui::Ui, gps: GpsPosition) {
+    let (x, y, offset) = tile(gps, TILE_ZOOM);
+    let url = format!(
+        "https://tile.openstreetmap.org/{}/{}/{}.png",
+        TILE_ZOOM, x, y
+    );
+    let size = egui::Vec2::splat(256.0);
+    let response = ui.add(egui::Image::from_uri(url).fit_to_exact_size(size));
+    let marker = response.rect.min + offset * size;
+    ui.painter()
+        .circle_filled(marker, 5.0, egui::Color32::from_rgb(220, 40, 40));
+    ui.painter()
+        .circle_stroke(marker, 5.0, egui::Stroke::new(1.5, egui::Color32::WHITE));
+    ui.small("© OpenStreetMap contributors");
+}
+
+/// Images taken close to each other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocationGroup {
+    // Position of the first image of the group.
+    pub center: GpsPosition,
+    pub paths: Vec<PathBuf>,
+}
+
+/// Groups images that are within `radius_km` of the first image of a group. Largest groups
+/// first.
+pub fn group_by_location(
+    positions: &[(PathBuf, GpsPosition)],
+    radius_km: f64,
+) -> Vec<LocationGroup> {
+    let mut groups: Vec<LocationGroup> = Vec::new();
+    for (path, gps) in positions {
+        match groups
+            .iter_mut()
+            .find(|group| distance_km(group.center, *gps) <= radius_km)
+        {
+            Some(group) => group.paths.push(path.clone()),
+            None => groups.push(LocationGroup {
+                center: *gps,
+                paths: vec![path.clone()],
+            }),
+        }
+    }
+    // Stable, so equal groups keep the order of the images.
+    groups.sort_by_key(|group| std::cmp::Reverse(group.paths.len()));
+    groups
+}
+
+/// What the user asked for in the "Locations" window.
+pub enum LocationAction {
+    // Only show these images.
+    Show {
+        name: String,
+        paths: Vec<PathBuf>,
+    },
+    MoveTo {
+        paths: Vec<PathBuf>,
+        folder: PathBuf,
+    },
+}
+
+/// State of the "Locations" window.
+pub struct LocationTool {
+    radius_km: f64,
+    scan: Option<ImageScan<GpsPosition>>,
+    groups: Vec<LocationGroup>,
+}
+
+impl Default for LocationTool {
+    fn default() -> Self {
+        Self {
+            radius_km: 10.0,
+            scan: None,
+            groups: Vec::new(),
+        }
+    }
+}
+
+impl LocationTool {
+    pub fn poll(&mut self) {
+        let Some(scan) = &mut self.scan else {
+            return;
+        };
+        let was_done = scan.is_done();
+        scan.poll();
+        if scan.is_done() && !was_done {
+            self.regroup();
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.scan.as_ref().is_some_and(|scan| !scan.is_done())
+    }
+
+    /// The images read and the images to read, while a scan is running.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.scan
+            .as_ref()
+            .filter(|scan| !scan.is_done())
+            .map(|scan| scan.progress())
+    }
+
+    fn regroup(&mut self) {
+        if let Some(scan) = &self.scan {
+            self.groups = group_by_location(scan.found(), self.radius_km);
+        }
+    }
+
+    /// Draws the window contents. `images` are the images that a new scan reads.
+    pub fn show(&mut self, ui: &mut egui::Ui, images: &[PathBuf]) -> Option<LocationAction> {
+        ui.label(tr!("locations-intro"));
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.is_busy(), egui::Button::new(tr!("read-locations")))
+                .clicked()
+            {
+                self.scan = Some(ImageScan::start(images.to_vec(), |path| {
+                    metadata::read_metadata(path).gps
+                }));
+                self.groups.clear();
+            }
+            ui.label(tr!("within"));
+            let radius = ui.add(
+                egui::DragValue::new(&mut self.radius_km)
+                    .range(0.1..=1000.0)
+                    .suffix(" km"),
+            );
+            if radius.changed() && !self.is_busy() {
+                self.regroup();
+            }
+        });
+        let mut cancelled = false;
+        if let Some(scan) = &self.scan {
+            let (checked, total) = scan.progress();
+            ui.horizontal(|ui| {
+                if self.is_busy() {
+                    ui.spinner();
+                    if ui.button(tr!("cancel")).clicked() {
+                        scan.cancel();
+                        cancelled = true;
+                    }
+                }
+                ui.label(tr!(
+                    "locations-read",
+                    checked = checked,
+                    total = total,
+                    found = scan.found().len()
+                ));
+            });
+        }
+        if cancelled {
+            // Group what was read so far.
+            self.regroup();
+        }
+
+        let mut action = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for group in &self.groups {
+                let name = format!(
+                    "{:.4}, {:.4}",
+                    group.center.latitude, group.center.longitude
+                );
+                ui.horizontal(|ui| {
+                    ui.hyperlink_to(&name, openstreetmap_url(group.center))
+                        .on_hover_text(tr!("open-in-openstreetmap"));
+                    ui.label(tr!("image-count", count = group.paths.len()));
+                    if ui.button(tr!("show")).clicked() {
+                        action = Some(LocationAction::Show {
+                            name: name.clone(),
+                            paths: group.paths.clone(),
+                        });
+                    }
+                    if ui.button(tr!("move-to-folder")).clicked() {
+                        if let Some(folder) = FileDialog::new().pick_folder() {
+                            action = Some(LocationAction::MoveTo {
+                                paths: group.paths.clone(),
+                                folder,
+                            });
+                        }
+                    }
+                });
+            }
+        });
+        if let Some(LocationAction::MoveTo { paths, .. }) = &action {
+            // The moved images are no longer where they were read.
+            for group in &mut self.groups {
+                group.paths.retain(|path| !paths.contains(path));
+            }
+            self.groups.retain(|group| !group.paths.is_empty());
+        }
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gps(latitude: f64, longitude: f64) -> GpsPosition {
+        GpsPosition {
+            latitude,
+            longitude,
+        }
+    }
+
+    #[test]
+    fn distance_test() {
+        let tokyo = gps(35.6812, 139.7671);
+        let osaka = gps(34.7025, 135.4959);
+        let distance = distance_km(tokyo, osaka);
+        assert!((distance - 403.0).abs() < 5.0, "{}", distance);
+        assert_eq!(distance_km(tokyo, tokyo), 0.0);
+    }
+
+    #[test]
+    fn tile_test() {
+        let (x, y, offset) = tile(gps(0.0, 0.0), 1);
+        assert_eq!((x, y), (1, 1));
+        assert!(offset.length() < 1e-3);
+        let (x, y, _) = tile(gps(35.6812, 139.7671), 14);
+        assert_eq!((x, y), (14552, 6451));
+    }
+
+    #[test]
+    fn group_by_location_test() {
+        let positions = vec![
+            (PathBuf::from("tokyo1.jpg"), gps(35.6812, 139.7671)),
+            (PathBuf::from("osaka.jpg"), gps(34.7025, 135.4959)),
+            (PathBuf::from("tokyo2.jpg"), gps(35.6586, 139.7454)),
+        ];
+        let groups = group_by_location(&positions, 10.0);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[0].paths,
+            vec![PathBuf::from("tokyo1.jpg"), PathBuf::from("tokyo2.jpg")]
+        );
+        assert_eq!(groups[1].paths, vec![PathBuf::from("osaka.jpg")]);
+        assert_eq!(group_by_location(&positions, 1000.0).len(), 1);
+    }
+}
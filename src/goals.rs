@@ -0,0 +1,160 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use crate::i18n::tr;
+
+// Images to sort before the pace is trusted for an estimate.
+const MIN_SORTED_FOR_ETA: usize = 3;
+
+/// What the user set out to do in this session.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Goal {
+    // Move or delete every image left in the open folders.
+    EmptyFolder,
+    // Move or delete this many images.
+    Sort(usize),
+}
+
+/// A goal and where the session stood when it was set. "Sorted" counts the images that were
+/// moved or deleted.
+#[derive(Clone, Debug)]
+pub struct SessionGoal {
+    pub goal: Goal,
+    // Images sorted in the session before the goal was set.
+    start: usize,
+    set_at: Instant,
+    // Whether reaching it was already announced.
+    pub reached: bool,
+}
+
+impl SessionGoal {
+    pub fn new(goal: Goal, sorted: usize, now: Instant) -> Self {
+        Self {
+            goal,
+            start: sorted,
+            set_at: now,
+            reached: false,
+        }
+    }
+
+    /// The images sorted towards the goal and the number it takes, given the images sorted in
+    /// the session and the images left in the folders.
+    pub fn progress(&self, sorted: usize, remaining: usize) -> (usize, usize) {
+        let done = sorted.saturating_sub(self.start);
+        match self.goal {
+            Goal::EmptyFolder => (done, done + remaining),
+            Goal::Sort(target) => (done.min(target), target),
+        }
+    }
+
+    pub fn is_reached(&self, sorted: usize, remaining: usize) -> bool {
+        let (done, target) = self.progress(sorted, remaining);
+        done >= target
+    }
+
+    /// How long the rest takes at the pace since the goal was set, once there is a pace to go
+    /// by.
+    pub fn eta(&self, sorted: usize, remaining: usize, now: Instant) -> Option<Duration> {
+        let (done, target) = self.progress(sorted, remaining);
+        if done < MIN_SORTED_FOR_ETA {
+            return None;
+        }
+        let per_image = now.duration_since(self.set_at).as_secs_f64() / done as f64;
+        Some(Duration::from_secs_f64(
+            per_image * target.saturating_sub(done) as f64,
+        ))
+    }
+}
+
+/// "12 min" or "1 h 5 min", rounded up to whole minutes.
+pub fn format_eta(eta: Duration) -> String {
+    let minutes = eta.as_secs().div_ceil(60);
+    match minutes {
+        0..60 => tr!("goal-eta-minutes", minutes = minutes),
+        _ => tr!(
+            "goal-eta-hours",
+            hours = minutes / 60,
+            minutes = minutes % 60
+        ),
+    }
+}
+
+/// The "Goal" menu where a goal is chosen.
+pub struct GoalEditor {
+    empty_folder: bool,
+    count: usize,
+}
+
+impl Default for GoalEditor {
+    fn default() -> Self {
+        Self {
+            empty_folder: true,
+            count: 100,
+        }
+    }
+}
+
+impl GoalEditor {
+    /// Draws the menu contents. Returns the goal once "Start" is clicked.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<Goal> {
+        ui.radio_value(&mut self.empty_folder, true, tr!("goal-empty-folder"));
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.empty_folder, false, tr!("goal-sort-before"));
+            let count = ui.add(egui::DragValue::new(&mut self.count).range(1..=100000));
+            if count.changed() {
+                self.empty_folder = false;
+            }
+            ui.label(tr!("goal-sort-after"));
+        });
+        if !ui.button(tr!("goal-start")).clicked() {
+            return None;
+        }
+        ui.close_menu();
+        Some(match self.empty_folder {
+            true => Goal::EmptyFolder,
+            false => Goal::Sort(self.count),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_test() {
+        let start = Instant::now();
+        let goal = SessionGoal::new(Goal::Sort(10), 5, start);
+        assert_eq!(goal.progress(5, 100), (0, 10));
+        assert_eq!(goal.progress(9, 100), (4, 10));
+        assert_eq!(goal.progress(20, 100), (10, 10));
+        // An undo below where it started.
+        assert_eq!(goal.progress(3, 100), (0, 10));
+        assert!(goal.is_reached(15, 100));
+
+        let goal = SessionGoal::new(Goal::EmptyFolder, 0, start);
+        assert_eq!(goal.progress(0, 40), (0, 40));
+        assert_eq!(goal.progress(30, 10), (30, 40));
+        assert!(!goal.is_reached(30, 10));
+        assert!(goal.is_reached(40, 0));
+    }
+
+    #[test]
+    fn eta_test() {
+        let start = Instant::now();
+        let goal = SessionGoal::new(Goal::Sort(100), 0, start);
+        assert_eq!(goal.eta(2, 500, start + Duration::from_secs(10)), None);
+        // 5 seconds an image, 90 to go.
+        assert_eq!(
+            goal.eta(10, 500, start + Duration::from_secs(50)),
+            Some(Duration::from_secs(450))
+        );
+        assert_eq!(
+            goal.eta(100, 500, start + Duration::from_secs(50)),
+            Some(Duration::ZERO)
+        );
+
+        assert_eq!(format_eta(Duration::from_secs(450)), "8 min");
+        assert_eq!(format_eta(Duration::from_secs(3900)), "1 h 5 min");
+    }
+}
@@ -0,0 +1,345 @@
+use image::{DynamicImage, Rgba32FImage, RgbaImage};
+use std::io::{self, Read};
+
+use crate::color;
+
+/// Extensions of high dynamic range formats whose pixels are floating point: Radiance RGBE and
+/// OpenEXR.
+pub const HDR_EXTENSIONS: [&str; 2] = ["hdr", "exr"];
+
+// How the code values of an image relate to light, from the cICP chunk of a PNG or the color
+// box of a HEIF file. Phones and editors export HDR photos as 16-bit PNGs this way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transfer {
+    // SMPTE ST 2084, absolute up to 10000 nits.
+    Pq,
+    // ARIB STD-B67, relative to the peak of the display.
+    Hlg,
+}
+
+/// The primaries and transfer of an HDR signal. Only signals that need tone mapping are described;
+/// ordinary sRGB images are left alone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signal {
+    // Linear RGB in the primaries of the signal to linear sRGB.
+    to_srgb: [[f32; 3]; 3],
+    transfer: Transfer,
+}
+
+const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+const BT2020_TO_SRGB: [[f32; 3]; 3] = [
+    [1.660_491, -0.587_641, -0.072_85],
+    [-0.124_55, 1.132_9, -0.008_349],
+    [-0.018_151, -0.100_579, 1.118_73],
+];
+
+const DISPLAY_P3_TO_SRGB: [[f32; 3]; 3] = [
+    [1.224_94, -0.224_94, 0.0],
+    [-0.042_057, 1.042_057, 0.0],
+    [-0.019_638, -0.078_636, 1.098_274],
+];
+
+// The luminance of white in SDR content, as BT.2408 puts it. PQ and HLG values are scaled so
+// that it becomes 1.
+const SDR_WHITE_NITS: f32 = 203.0;
+
+// The peak HLG is mastered for.
+const HLG_PEAK_NITS: f32 = 1000.0;
+
+// Luminance below this much of SDR white is shown as is, and only highlights are compressed.
+const KNEE: f32 = 0.75;
+
+// Scene-referred images are exposed so that their average, in the log, is middle gray.
+const MIDDLE_GRAY: f32 = 0.18;
+
+// The share of pixels allowed to clip, so that a few specular highlights or the sun do not
+// darken the rest.
+const CLIPPED_SHARE: f32 = 0.001;
+
+/// Reads the cICP chunk of the PNG in `reader`, which must be at the start of the file. Returns
+/// None when the image is not PQ or HLG.
+pub fn png_signal(reader: &mut impl Read) -> io::Result<Option<Signal>> {
+    let mut signature = [0; 8];
+    reader.read_exact(&mut signature)?;
+    if signature != *b"\x89PNG\r\n\x1a\n" {
+        return Ok(None);
+    }
+    loop {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let length = u32::from_be_bytes(header[..4].try_into().unwrap());
+        let kind = &header[4..];
+        // cICP must come before the image data.
+        if kind == b"IDAT" || kind == b"IEND" {
+            return Ok(None);
+        }
+        if kind == b"cICP" && length == 4 {
+            let mut cicp = [0; 4];
+            reader.read_exact(&mut cicp)?;
+            return Ok(coded_signal(cicp[0].into(), cicp[1].into()));
+        }
+        // The data and its CRC.
+        io::copy(&mut reader.take(u64::from(length) + 4), &mut io::sink())?;
+    }
+}
+
+/// The signal of the color primaries and transfer characteristics codes of ITU-T H.273, which
+/// the cICP chunk of a PNG and the nclx color box of a HEIF file give. None when it is not PQ or
+/// HLG.
+pub fn coded_signal(primaries: u16, transfer: u16) -> Option<Signal> {
+    let transfer = match transfer {
+        16 => Transfer::Pq,
+        18 => Transfer::Hlg,
+        _ => return None,
+    };
+    let to_srgb = match primaries {
+        9 => BT2020_TO_SRGB,
+        12 => DISPLAY_P3_TO_SRGB,
+        _ => IDENTITY,
+    };
+    Some(Signal { to_srgb, transfer })
+}
+
+// PQ code value to luminance relative to SDR white.
+fn pq_to_linear(value: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+    let power = value.max(0.0).powf(1.0 / M2);
+    let nits = 10000.0 * ((power - C1).max(0.0) / (C2 - C3 * power)).powf(1.0 / M1);
+    nits / SDR_WHITE_NITS
+}
+
+// HLG code value to scene light, 0 to 1.
+fn hlg_to_scene(value: f32) -> f32 {
+    const A: f32 = 0.178_832_77;
+    const B: f32 = 0.284_668_92;
+    const C: f32 = 0.559_910_7;
+    if value <= 0.5 {
+        value * value / 3.0
+    } else {
+        (((value - C) / A).exp() + B) / 12.0
+    }
+}
+
+fn luminance([r, g, b]: [f32; 3]) -> f32 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+// The luminance that ends up white: the brightest after the clipped share, but never below SDR
+// white.
+fn white_point(luminances: &mut [f32]) -> f32 {
+    if luminances.is_empty() {
+        return 1.0;
+    }
+    let index = ((luminances.len() - 1) as f32 * (1.0 - CLIPPED_SHARE)) as usize;
+    let (_, white, _) = luminances.select_nth_unstable_by(index, f32::total_cmp);
+    white.max(1.0)
+}
+
+// Compresses luminance above the knee so that `white` becomes 1, with Reinhard's extended
+// operator. Below the knee, and for images without highlights, nothing changes.
+fn compress(luminance: f32, white: f32) -> f32 {
+    if luminance <= KNEE || white <= 1.0 {
+        return luminance;
+    }
+    let excess = (luminance - KNEE) / (1.0 - KNEE);
+    let headroom = (white - KNEE) / (1.0 - KNEE);
+    KNEE + (1.0 - KNEE) * excess * (1.0 + excess / (headroom * headroom)) / (1.0 + excess)
+}
+
+// Tone maps linear sRGB where 1 is SDR white and encodes it for display.
+fn tone_map(mut linear: Rgba32FImage) -> RgbaImage {
+    let mut luminances: Vec<f32> = linear
+        .pixels()
+        .map(|pixel| luminance([pixel[0], pixel[1], pixel[2]]))
+        .collect();
+    let white = white_point(&mut luminances);
+    for pixel in linear.pixels_mut() {
+        let rgb = [pixel[0], pixel[1], pixel[2]].map(|value| value.max(0.0));
+        let before = luminance(rgb);
+        // Scaling all channels alike keeps the hue of highlights, which clipping would shift.
+        let scale = if before > 0.0 {
+            compress(before, white) / before
+        } else {
+            1.0
+        };
+        for (channel, value) in rgb.into_iter().enumerate() {
+            pixel[channel] = color::linear_to_srgb((value * scale).min(1.0));
+        }
+    }
+    DynamicImage::ImageRgba32F(linear).to_rgba8()
+}
+
+/// Whether `image` holds scene light in floating point, as Radiance and OpenEXR files do, which
+/// would clip when shown as is.
+pub fn is_scene_referred(image: &DynamicImage) -> bool {
+    matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    )
+}
+
+/// Tone maps the scene-referred `image` for display. It is exposed so that its average is
+/// middle gray, and highlights are rolled off rather than clipped.
+pub fn tone_map_scene(image: &DynamicImage) -> RgbaImage {
+    let mut linear = image.to_rgba32f();
+    let (sum, count) = linear
+        .pixels()
+        .map(|pixel| luminance([pixel[0], pixel[1], pixel[2]]))
+        .filter(|luminance| luminance.is_finite())
+        .fold((0.0, 0), |(sum, count), luminance| {
+            (sum + (luminance.max(0.0) + 1e-4).ln(), count + 1)
+        });
+    if count > 0 {
+        let exposure = MIDDLE_GRAY / (sum / count as f32).exp();
+        for pixel in linear.pixels_mut() {
+            for value in &mut pixel.0[..3] {
+                *value = if value.is_finite() {
+                    *value * exposure
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+    tone_map(linear)
+}
+
+/// Tone maps `image`, whose code values are those of `signal`, to sRGB for display.
+///
+/// Photos with a gain map, such as Ultra HDR JPEGs and the HEIC photos of iPhones, do not come
+/// here: their primary image is the SDR rendition the camera made with its own tone mapping,
+/// which is what a gain map is applied with a weight of 0 for an SDR screen, so they are shown
+/// as they are.
+pub fn tone_map_signal(image: &DynamicImage, signal: Signal) -> RgbaImage {
+    let mut linear = image.to_rgba32f();
+    for pixel in linear.pixels_mut() {
+        let rgb = match signal.transfer {
+            Transfer::Pq => [pixel[0], pixel[1], pixel[2]].map(pq_to_linear),
+            Transfer::Hlg => {
+                // The OOTF of BT.2100 with the system gamma of a 1000 nit display.
+                let scene = [pixel[0], pixel[1], pixel[2]].map(hlg_to_scene);
+                let gain = luminance(scene).max(0.0).powf(0.2) * HLG_PEAK_NITS / SDR_WHITE_NITS;
+                scene.map(|value| value * gain)
+            }
+        };
+        for (channel, row) in signal.to_srgb.iter().enumerate() {
+            pixel[channel] = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+        }
+    }
+    tone_map(linear)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb, Rgb32FImage};
+    use std::io::Cursor;
+
+    fn png_with_cicp(cicp: Option<[u8; 4]>) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut chunk = |kind: &[u8], data: &[u8]| {
+            png.extend((data.len() as u32).to_be_bytes());
+            png.extend(kind);
+            png.extend(data);
+            // The CRC is not checked.
+            png.extend([0; 4]);
+        };
+        chunk(b"IHDR", &[0; 13]);
+        if let Some(cicp) = cicp {
+            chunk(b"cICP", &cicp);
+        }
+        chunk(b"IDAT", &[]);
+        png
+    }
+
+    #[test]
+    fn png_signal_test() {
+        let signal = |cicp| png_signal(&mut Cursor::new(png_with_cicp(cicp))).unwrap();
+        assert_eq!(
+            signal(Some([9, 16, 0, 1])),
+            Some(Signal {
+                to_srgb: BT2020_TO_SRGB,
+                transfer: Transfer::Pq
+            })
+        );
+        assert_eq!(
+            signal(Some([12, 18, 0, 1])).unwrap().transfer,
+            Transfer::Hlg
+        );
+        // sRGB.
+        assert_eq!(signal(Some([1, 13, 0, 1])), None);
+        assert_eq!(signal(None), None);
+        assert_eq!(png_signal(&mut Cursor::new(b"GIF89a..")).unwrap(), None);
+    }
+
+    #[test]
+    fn curves_test() {
+        assert!((pq_to_linear(0.0)).abs() < 1e-6);
+        // 203 nits.
+        assert!((pq_to_linear(0.580_690) - 1.0).abs() < 0.01);
+        assert!((pq_to_linear(1.0) - 10000.0 / SDR_WHITE_NITS).abs() < 0.1);
+        assert!((hlg_to_scene(0.5) - 1.0 / 12.0).abs() < 1e-6);
+        assert!((hlg_to_scene(1.0) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn compress_test() {
+        assert_eq!(compress(0.5, 4.0), 0.5);
+        assert!((compress(4.0, 4.0) - 1.0).abs() < 1e-6);
+        assert!(compress(2.0, 4.0) < 1.0);
+        assert!(compress(2.0, 4.0) > compress(1.0, 4.0));
+        // Nothing to compress.
+        assert_eq!(compress(1.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn tone_map_scene_test() {
+        // A dim room with a window a thousand times brighter.
+        let image: Rgb32FImage = ImageBuffer::from_fn(100, 10, |x, _| {
+            if x < 90 {
+                Rgb([0.01, 0.01, 0.01])
+            } else {
+                Rgb([10.0, 5.0, 2.5])
+            }
+        });
+        let image = DynamicImage::ImageRgb32F(image);
+        assert!(is_scene_referred(&image));
+        let rgba = tone_map_scene(&image);
+        let room = rgba.get_pixel(0, 0);
+        let window = rgba.get_pixel(99, 0);
+        // The room is not black, and the window keeps its orange instead of clipping to white.
+        assert!(room[0] > 60 && room[0] < 200);
+        assert_eq!(window[0], 255);
+        assert!(window[1] < window[0] && window[2] < window[1]);
+        assert!(!is_scene_referred(&DynamicImage::new_rgb8(1, 1)));
+
+        // OpenEXR files come here when decoded.
+        let mut exr = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut exr), image::ImageFormat::OpenExr)
+            .unwrap();
+        let decoded = color::decode(image::ImageReader::new(Cursor::new(exr))).unwrap();
+        assert_eq!(decoded.as_rgba8(), Some(&rgba));
+    }
+
+    #[test]
+    fn tone_map_signal_test() {
+        let signal = Signal {
+            to_srgb: IDENTITY,
+            transfer: Transfer::Pq,
+        };
+        // SDR white stays close to white, and 10000 nits does not go beyond it.
+        let code = |value: f32| (value * 65535.0) as u16;
+        let image = ImageBuffer::from_fn(2, 1, |x, _| {
+            Rgb([code(if x == 0 { 0.580_690 } else { 1.0 }); 3])
+        });
+        let rgba = tone_map_signal(&DynamicImage::ImageRgb16(image), signal);
+        assert!(rgba.get_pixel(0, 0)[0] > 200);
+        assert_eq!(rgba.get_pixel(1, 0)[0], 255);
+    }
+}
@@ -0,0 +1,304 @@
+use image::{DynamicImage, ImageReader};
+use std::{
+    env, fs,
+    io::{self, Cursor},
+    path::Path,
+    process::{self, Command},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use crate::{color, file_ops, hdr};
+
+/// Extensions of HEIF files: the HEIC photos of phones and cameras, and AVIF.
+pub const HEIF_EXTENSIONS: [&str; 4] = ["heic", "heif", "hif", "avif"];
+
+// A decoder that takes longer than this on one photo is taken to hang.
+const DECODE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The type of the auxiliary image that holds the gain map in the HEIC photos of iPhones.
+const APPLE_GAIN_MAP: &[u8] = b"urn:com:apple:photo:2020:aux:hdrgainmap";
+
+// Numbers the folders that files are decoded in, which several threads do at once.
+static DECODES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn is_heif_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| HEIF_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+type Boxes<'a> = Vec<([u8; 4], &'a [u8])>;
+
+// The type and contents of each box in `data`. None if one goes past the end.
+fn boxes(data: &[u8]) -> Option<Boxes<'_>> {
+    let mut boxes = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let size = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?);
+        let kind: [u8; 4] = rest.get(4..8)?.try_into().ok()?;
+        let (header, size) = match size {
+            // The last box may go to the end of the file.
+            0 => (8, rest.len() as u64),
+            1 => (16, u64::from_be_bytes(rest.get(8..16)?.try_into().ok()?)),
+            size => (8, u64::from(size)),
+        };
+        let size = usize::try_from(size).ok()?;
+        boxes.push((kind, rest.get(header..size)?));
+        rest = &rest[size..];
+    }
+    Some(boxes)
+}
+
+fn find<'a>(boxes: &Boxes<'a>, kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes
+        .iter()
+        .find(|(found, _)| found == kind)
+        .map(|(_, contents)| *contents)
+}
+
+// The version and flags of the full box `contents`, and what follows them.
+fn full_box(contents: &[u8]) -> Option<(u8, u32, &[u8])> {
+    let flags = read(contents, &mut 1, 3)?;
+    Some((*contents.first()?, flags, contents.get(4..)?))
+}
+
+// Reads a number of `size` bytes at `*position` and moves past it.
+fn read(data: &[u8], position: &mut usize, size: usize) -> Option<u32> {
+    let bytes = data.get(*position..*position + size)?;
+    *position += size;
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, byte| value << 8 | u32::from(*byte)),
+    )
+}
+
+/// What the container of a HEIF file says about how to show its primary image.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Info {
+    // Of an HDR primary image, from its nclx color box.
+    signal: Option<hdr::Signal>,
+    // The file also has a gain map, as an ISO 21496-1 "tmap" item or the auxiliary image of an
+    // iPhone photo. The primary image is then the SDR rendition.
+    gain_map: bool,
+}
+
+fn info(data: &[u8]) -> Option<Info> {
+    let top = boxes(data)?;
+    let (_, _, meta) = full_box(find(&top, b"meta")?)?;
+    let meta_boxes = boxes(meta)?;
+
+    let (version, _, pitm) = full_box(find(&meta_boxes, b"pitm")?)?;
+    let primary = read(pitm, &mut 0, if version == 0 { 2 } else { 4 })?;
+
+    let mut gain_map = meta
+        .windows(APPLE_GAIN_MAP.len())
+        .any(|window| window == APPLE_GAIN_MAP);
+    if let Some((version, _, iinf)) = find(&meta_boxes, b"iinf").and_then(full_box) {
+        let entries = iinf.get(if version == 0 { 2 } else { 4 }..)?;
+        for (_, infe) in boxes(entries)?.iter().filter(|(kind, _)| kind == b"infe") {
+            let (version, _, infe) = full_box(infe)?;
+            // Older versions have no item type.
+            let item_type = match version {
+                2 => infe.get(4..8),
+                3 => infe.get(6..10),
+                _ => None,
+            };
+            gain_map |= item_type == Some(b"tmap");
+        }
+    }
+
+    // The properties are numbered from 1, and the primary image is associated with some.
+    let iprp = boxes(find(&meta_boxes, b"iprp")?)?;
+    let properties = boxes(find(&iprp, b"ipco")?)?;
+    let nclx = |index: usize| {
+        let (kind, colr) = properties.get(index.checked_sub(1)?)?;
+        if kind != b"colr" || colr.get(..4)? != b"nclx" {
+            return None;
+        }
+        let mut position = 4;
+        Some((read(colr, &mut position, 2)?, read(colr, &mut position, 2)?))
+    };
+    let mut associated = Vec::new();
+    if let Some((version, flags, ipma)) = find(&iprp, b"ipma").and_then(full_box) {
+        let mut position = 0;
+        let entries = read(ipma, &mut position, 4)?;
+        for _ in 0..entries {
+            let item = read(ipma, &mut position, if version < 1 { 2 } else { 4 })?;
+            let count = read(ipma, &mut position, 1)?;
+            for _ in 0..count {
+                let association = if flags & 1 == 1 {
+                    read(ipma, &mut position, 2)? & 0x7fff
+                } else {
+                    read(ipma, &mut position, 1)? & 0x7f
+                };
+                if item == primary {
+                    associated.push(association as usize);
+                }
+            }
+        }
+    }
+    // A grid image may leave the color to its tiles.
+    let (primaries, transfer) = associated
+        .into_iter()
+        .find_map(nclx)
+        .or_else(|| (1..=properties.len()).find_map(nclx))
+        .unwrap_or_default();
+    Some(Info {
+        signal: hdr::coded_signal(primaries as u16, transfer as u16),
+        gain_map,
+    })
+}
+
+// Runs `program` of libheif to decode `input` to `output`.
+fn run_libheif(program: &str, input: &Path, output: &Path) -> io::Result<()> {
+    let mut command = Command::new(program);
+    command.arg(input).arg(output);
+    file_ops::output_with_timeout(command, Vec::new(), DECODE_TIMEOUT).map(|_| ())
+}
+
+// Decodes the primary image of the HEIF `data` to PNG with heif-dec of libheif, which older
+// versions call heif-convert. It only reads and writes files, so they are put in a folder of
+// their own.
+fn decode_with_libheif(data: &[u8]) -> io::Result<Vec<u8>> {
+    let folder = env::temp_dir().join(format!(
+        "image-organizer-heif-{}-{}",
+        process::id(),
+        DECODES.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&folder)?;
+    let input = folder.join("image.heif");
+    let output = folder.join("image.png");
+    let result = fs::write(&input, data)
+        .and_then(|()| match run_libheif("heif-dec", &input, &output) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                run_libheif("heif-convert", &input, &output)
+            }
+            result => result,
+        })
+        .and_then(|()| {
+            // A file of several images is written as image-1.png, image-2.png, and so on.
+            fs::read(&output).or_else(|_| fs::read(folder.join("image-1.png")))
+        });
+    let _ = fs::remove_dir_all(&folder);
+    result
+}
+
+/// Decodes the primary image of the HEIF file contents `data` with libheif. HDR images are tone
+/// mapped, except for photos with a gain map, whose primary image is made for an SDR screen.
+pub fn decode(data: &[u8]) -> Result<DynamicImage, String> {
+    let png = decode_with_libheif(data).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => {
+            "Install libheif (heif-dec) to show HEIC and AVIF files.".to_string()
+        }
+        _ => e.to_string(),
+    })?;
+    let info = info(data).unwrap_or_default();
+    match info.signal.filter(|_| !info.gain_map) {
+        Some(signal) => image::load_from_memory(&png)
+            .map(|image| color::apply_signal(image, signal))
+            .map_err(|e| e.to_string()),
+        // libheif writes the color profile of the file into the PNG.
+        None => color::decode(ImageReader::new(Cursor::new(png))).map_err(|e| e.to_string()),
+    }
+}
+
+/// Checks that every box of the HEIF file `data` is complete, which the last one is not in a
+/// truncated file. Decoding would need libheif, which may not be installed.
+pub fn check(data: &[u8]) -> Result<(), String> {
+    let top = boxes(data).ok_or("The HEIF file is truncated")?;
+    if top.first().is_none_or(|(kind, _)| kind != b"ftyp") {
+        return Err("Not a HEIF file".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn isobmff_box(kind: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut bytes = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend(kind);
+        bytes.extend(contents);
+        bytes
+    }
+
+    fn full_box(kind: &[u8; 4], version: u8, flags: u8, contents: &[u8]) -> Vec<u8> {
+        isobmff_box(kind, &[&[version, 0, 0, flags][..], contents].concat())
+    }
+
+    fn nclx(primaries: u16, transfer: u16) -> Vec<u8> {
+        let mut contents = b"nclx".to_vec();
+        contents.extend(primaries.to_be_bytes());
+        contents.extend(transfer.to_be_bytes());
+        contents.extend([0, 9, 0x80]);
+        isobmff_box(b"colr", &contents)
+    }
+
+    // An AVIF file whose primary image, item 1, has the second color property. `extra_item` is
+    // the type of another item, e.g. of a gain map.
+    fn avif(extra_item: Option<&[u8; 4]>) -> Vec<u8> {
+        let infe =
+            |id: u8, kind: &[u8; 4]| full_box(b"infe", 2, 0, &[&[0, id, 0, 0][..], kind].concat());
+        let mut items = vec![infe(1, b"av01")];
+        items.extend(extra_item.map(|kind| infe(2, kind)));
+        let iinf = full_box(
+            b"iinf",
+            0,
+            0,
+            &[&[0, items.len() as u8][..], &items.concat()].concat(),
+        );
+        let ipco = isobmff_box(b"ipco", &[nclx(1, 13), nclx(9, 16)].concat());
+        // One entry: item 1 with two properties, the second one essential.
+        let ipma = full_box(b"ipma", 0, 0, &[0, 0, 0, 1, 0, 1, 2, 0x82, 0x02]);
+        let meta = full_box(
+            b"meta",
+            0,
+            0,
+            &[
+                full_box(b"pitm", 0, 0, &[0, 1]),
+                iinf,
+                isobmff_box(b"iprp", &[ipco, ipma].concat()),
+            ]
+            .concat(),
+        );
+        [
+            isobmff_box(b"ftyp", b"avifmif1"),
+            meta,
+            isobmff_box(b"mdat", &[0; 16]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn info_test() {
+        let hdr = avif(None);
+        assert_eq!(
+            info(&hdr),
+            Some(Info {
+                signal: hdr::coded_signal(9, 16),
+                gain_map: false,
+            })
+        );
+        assert!(info(&hdr).unwrap().signal.is_some());
+        assert!(info(&avif(Some(b"tmap"))).unwrap().gain_map);
+        assert!(!info(&avif(Some(b"Exif"))).unwrap().gain_map);
+        assert_eq!(info(b"GIF89a"), None);
+    }
+
+    #[test]
+    fn check_test() {
+        let file = avif(None);
+        assert!(check(&file).is_ok());
+        assert_eq!(
+            check(&file[..file.len() - 4]),
+            Err("The HEIF file is truncated".to_string())
+        );
+        assert!(check(&isobmff_box(b"moov", &[])).is_err());
+        assert!(is_heif_file(Path::new("IMG_0001.HEIC")));
+        assert!(is_heif_file(Path::new("photo.avif")));
+        assert!(!is_heif_file(Path::new("photo.jpg")));
+    }
+}
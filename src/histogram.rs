@@ -0,0 +1,279 @@
+use eframe::egui;
+use image::RgbImage;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{image_loader, raw};
+
+// Images are analyzed at this size. The histogram barely changes and it is much faster.
+const ANALYSIS_SIZE: u32 = 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub red: [u32; 256],
+    pub green: [u32; 256],
+    pub blue: [u32; 256],
+    pub luma: [u32; 256],
+    // Pixels with any channel at 255 and with all channels at 0.
+    pub highlights_clipped: u32,
+    pub shadows_clipped: u32,
+    pub total: u32,
+}
+
+impl Histogram {
+    pub fn compute(image: &RgbImage) -> Self {
+        let mut histogram = Self {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+            luma: [0; 256],
+            highlights_clipped: 0,
+            shadows_clipped: 0,
+            total: 0,
+        };
+        for pixel in image.pixels() {
+            let [r, g, b] = pixel.0;
+            histogram.red[r as usize] += 1;
+            histogram.green[g as usize] += 1;
+            histogram.blue[b as usize] += 1;
+            histogram.luma[luma(r, g, b) as usize] += 1;
+            if is_highlight_clipped(r, g, b) {
+                histogram.highlights_clipped += 1;
+            }
+            if is_shadow_clipped(r, g, b) {
+                histogram.shadows_clipped += 1;
+            }
+            histogram.total += 1;
+        }
+        histogram
+    }
+
+    // The highest bin, ignoring the ends so that a lot of clipping does not flatten the rest.
+    fn max(&self) -> u32 {
+        [&self.red, &self.green, &self.blue, &self.luma]
+            .iter()
+            .flat_map(|bins| bins[1..255].iter())
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+}
+
+// Rec. 709 luma.
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    (0.2126 * f32::from(r) + 0.7152 * f32::from(g) + 0.0722 * f32::from(b)).round() as u8
+}
+
+fn is_highlight_clipped(r: u8, g: u8, b: u8) -> bool {
+    r == 255 || g == 255 || b == 255
+}
+
+fn is_shadow_clipped(r: u8, g: u8, b: u8) -> bool {
+    r == 0 && g == 0 && b == 0
+}
+
+/// An overlay that marks blown highlights red and crushed shadows blue. Other pixels are
+/// transparent.
+pub fn clipping_mask(image: &RgbImage) -> egui::ColorImage {
+    let pixels = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b] = pixel.0;
+            if is_highlight_clipped(r, g, b) {
+                egui::Color32::from_rgba_unmultiplied(255, 0, 0, 200)
+            } else if is_shadow_clipped(r, g, b) {
+                egui::Color32::from_rgba_unmultiplied(0, 80, 255, 200)
+            } else {
+                egui::Color32::TRANSPARENT
+            }
+        })
+        .collect();
+    egui::ColorImage {
+        size: [image.width() as usize, image.height() as usize],
+        pixels,
+    }
+}
+
+type AnalysisResult = Result<(Histogram, egui::ColorImage), String>;
+
+fn analyze(path: &Path) -> AnalysisResult {
+    let image = image_loader::downscale(raw::decode_file(path)?, ANALYSIS_SIZE).to_rgb8();
+    Ok((Histogram::compute(&image), clipping_mask(&image)))
+}
+
+struct Analysis {
+    path: PathBuf,
+    histogram: Histogram,
+    mask: egui::TextureHandle,
+}
+
+/// Histogram and clipping overlay of the current image. The image is decoded again on a
+/// background thread since egui only keeps the texture on the GPU.
+#[derive(Default)]
+pub struct HistogramView {
+    pending: Option<(PathBuf, mpsc::Receiver<AnalysisResult>)>,
+    analysis: Option<Analysis>,
+    // The image that failed to decode and why.
+    error: Option<(PathBuf, String)>,
+}
+
+impl HistogramView {
+    /// Analyzes `path` unless it already is. Call every frame while the histogram or the overlay
+    /// is visible.
+    pub fn update(&mut self, ctx: &egui::Context, path: Option<&Path>) {
+        if let Some((pending_path, receiver)) = &self.pending {
+            match receiver.try_recv() {
+                Ok(Ok((histogram, mask))) => {
+                    let mask = ctx.load_texture("clipping_mask", mask, Default::default());
+                    self.analysis = Some(Analysis {
+                        path: pending_path.clone(),
+                        histogram,
+                        mask,
+                    });
+                    self.error = None;
+                    self.pending = None;
+                }
+                Ok(Err(e)) => {
+                    self.error = Some((pending_path.clone(), e));
+                    self.analysis = None;
+                    self.pending = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    ctx.request_repaint_after(std::time::Duration::from_millis(50))
+                }
+                Err(mpsc::TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+
+        let Some(path) = path else {
+            self.analysis = None;
+            return;
+        };
+        let analyzed = self
+            .analysis
+            .as_ref()
+            .map(|analysis| analysis.path.as_path());
+        let analyzing = self.pending.as_ref().map(|(path, _)| path.as_path());
+        let failed = self.error.as_ref().map(|(path, _)| path.as_path());
+        if [analyzed, analyzing, failed].contains(&Some(path)) {
+            return;
+        }
+        // A newer request replaces the pending one. Its result is dropped.
+        let (sender, receiver) = mpsc::channel();
+        let thread_path = path.to_path_buf();
+        thread::spawn(move || {
+            let _ = sender.send(analyze(&thread_path));
+        });
+        self.pending = Some((path.to_path_buf(), receiver));
+        self.error = None;
+    }
+
+    fn current(&self, path: Option<&Path>) -> Option<&Analysis> {
+        self.analysis
+            .as_ref()
+            .filter(|analysis| Some(analysis.path.as_path()) == path)
+    }
+
+    /// Draws the histogram of `path` if it has been analyzed.
+    pub fn show_histogram(&self, ui: &mut egui::Ui, path: Option<&Path>) {
+        if let Some((_, error)) = self
+            .error
+            .as_ref()
+            .filter(|(failed, _)| Some(failed.as_path()) == path)
+        {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            return;
+        }
+        let Some(analysis) = self.current(path) else {
+            ui.spinner();
+            return;
+        };
+        let histogram = &analysis.histogram;
+        let (response, painter) =
+            ui.allocate_painter(egui::vec2(256.0 * 1.5, 120.0), egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+        let max = histogram.max() as f32;
+        let bin_width = rect.width() / 256.0;
+        let height = |count: u32| (count as f32 / max).min(1.0) * rect.height();
+        for (bins, color) in [
+            (&histogram.luma, egui::Color32::from_gray(120)),
+            (
+                &histogram.red,
+                egui::Color32::from_rgba_unmultiplied(255, 60, 60, 140),
+            ),
+            (
+                &histogram.green,
+                egui::Color32::from_rgba_unmultiplied(60, 255, 60, 140),
+            ),
+            (
+                &histogram.blue,
+                egui::Color32::from_rgba_unmultiplied(80, 120, 255, 140),
+            ),
+        ] {
+            for (i, count) in bins.iter().enumerate() {
+                let x = rect.left() + i as f32 * bin_width;
+                painter.rect_filled(
+                    egui::Rect::from_min_max(
+                        egui::pos2(x, rect.bottom() - height(*count)),
+                        egui::pos2(x + bin_width, rect.bottom()),
+                    ),
+                    0.0,
+                    color,
+                );
+            }
+        }
+        let percent = |count: u32| 100.0 * count as f32 / histogram.total.max(1) as f32;
+        ui.label(format!(
+            "Clipped highlights: {:.2}%, shadows: {:.2}%",
+            percent(histogram.highlights_clipped),
+            percent(histogram.shadows_clipped)
+        ));
+    }
+
+    /// Paints the clipping overlay of `path` over `rect`, where the image is drawn.
+    pub fn paint_clipping(&self, ui: &egui::Ui, rect: egui::Rect, path: &Path) {
+        if let Some(analysis) = self.current(Some(path)) {
+            ui.painter().image(
+                analysis.mask.id(),
+                rect,
+                egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_test() {
+        let mut image = RgbImage::new(2, 2);
+        image.put_pixel(0, 0, image::Rgb([255, 255, 255]));
+        image.put_pixel(1, 0, image::Rgb([255, 0, 0]));
+        image.put_pixel(0, 1, image::Rgb([10, 20, 30]));
+        let histogram = Histogram::compute(&image);
+        assert_eq!(histogram.total, 4);
+        assert_eq!(histogram.highlights_clipped, 2);
+        // The remaining pixel is black.
+        assert_eq!(histogram.shadows_clipped, 1);
+        assert_eq!(histogram.red[255], 2);
+        assert_eq!(histogram.green[0], 2);
+        assert_eq!(histogram.luma[255], 1);
+        assert_eq!(histogram.luma[luma(255, 0, 0) as usize], 1);
+
+        let mask = clipping_mask(&image);
+        assert_eq!(mask.size, [2, 2]);
+        assert_eq!(
+            mask.pixels[3],
+            egui::Color32::from_rgba_unmultiplied(0, 80, 255, 200)
+        );
+        assert_eq!(mask.pixels[2], egui::Color32::TRANSPARENT);
+    }
+}
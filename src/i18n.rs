@@ -0,0 +1,167 @@
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource};
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, RwLock};
+use unic_langid::LanguageIdentifier;
+
+/// Language of the UI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    English,
+    Japanese,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Japanese];
+
+    /// The name of the language in itself, so that it can be found without reading the current
+    /// one.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Japanese => "日本語",
+        }
+    }
+
+    fn id(self) -> LanguageIdentifier {
+        let id = match self {
+            Language::English => "en",
+            Language::Japanese => "ja",
+        };
+        id.parse().unwrap()
+    }
+
+    fn ftl(self) -> &'static str {
+        match self {
+            Language::English => include_str!("../i18n/en.ftl"),
+            Language::Japanese => include_str!("../i18n/ja.ftl"),
+        }
+    }
+}
+
+type Bundle = FluentBundle<FluentResource>;
+
+fn bundle(language: Language) -> Bundle {
+    let resource =
+        FluentResource::try_new(language.ftl().to_string()).unwrap_or_else(|(resource, errors)| {
+            log::error!("Errors in the {:?} translations: {:?}", language, errors);
+            resource
+        });
+    let mut bundle = FluentBundle::new_concurrent(vec![language.id()]);
+    // The marks around arguments are for right-to-left text and show as boxes in egui.
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        log::error!("Errors in the {:?} translations: {:?}", language, errors);
+    }
+    bundle
+}
+
+// Built once. Looked up from everywhere in the UI, in the order of Language::ALL.
+static BUNDLES: LazyLock<Vec<Bundle>> =
+    LazyLock::new(|| Language::ALL.into_iter().map(bundle).collect());
+static LANGUAGE: RwLock<Language> = RwLock::new(Language::English);
+
+pub fn set_language(language: Language) {
+    *LANGUAGE.write().unwrap() = language;
+}
+
+// The message `id` of `bundle`, or None if it has no such message.
+fn format(bundle: &Bundle, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    let text = bundle.format_pattern(pattern, args, &mut errors);
+    if !errors.is_empty() {
+        log::warn!("Errors in the message {}: {:?}", id, errors);
+    }
+    Some(text.into_owned())
+}
+
+/// The message `id` in the current language. Falls back to English, and to the id itself so
+/// that a missing message is noticed but does not break the UI.
+pub fn translate(id: &str, args: Option<&FluentArgs>) -> String {
+    let language = *LANGUAGE.read().unwrap();
+    let index = Language::ALL.iter().position(|&l| l == language).unwrap();
+    format(&BUNDLES[index], id, args)
+        .or_else(|| format(&BUNDLES[0], id, args))
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Translates a message, e.g. `tr!("indexing", indexed = 3, total = 10)`.
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::translate($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent_bundle::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::translate($id, Some(&args))
+    }};
+}
+pub(crate) use tr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fluent_bundle::FluentValue;
+    use std::collections::BTreeSet;
+
+    // Ids of the messages in the file, which start at the beginning of a line.
+    fn message_ids(language: Language) -> BTreeSet<String> {
+        language
+            .ftl()
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .filter_map(|line| line.split_once(" =").map(|(id, _)| id.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn translations_test() {
+        let english = message_ids(Language::English);
+        assert_eq!(english, message_ids(Language::Japanese));
+
+        // Every message the code asks for exists.
+        let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for entry in std::fs::read_dir(src).unwrap() {
+            let code = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            for (start, _) in code.match_indices("tr!(") {
+                // Not the end of another macro, like include_str!.
+                if code[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                    continue;
+                }
+                let part = &code[start + "tr!(".len()..];
+                let Some(part) = part.trim_start().strip_prefix('"') else {
+                    continue;
+                };
+                let id = part.split('"').next().unwrap();
+                // The pattern of this test, which is not a message.
+                if !id.starts_with(|c: char| c.is_ascii_lowercase()) {
+                    continue;
+                }
+                assert!(english.contains(id), "No message {}", id);
+            }
+        }
+    }
+
+    #[test]
+    fn format_test() {
+        let mut args = FluentArgs::new();
+        args.set("count", FluentValue::from(1));
+        let english = bundle(Language::English);
+        assert_eq!(
+            format(&english, "confirm-move-images", Some(&args)).unwrap(),
+            "Move 1 image?"
+        );
+        args.set("count", FluentValue::from(3));
+        assert_eq!(
+            format(&english, "confirm-move-images", Some(&args)).unwrap(),
+            "Move 3 images?"
+        );
+        let japanese = bundle(Language::Japanese);
+        assert_eq!(
+            format(&japanese, "confirm-move-images", Some(&args)).unwrap(),
+            "3 枚の画像を移動しますか？"
+        );
+        assert_eq!(format(&english, "no-such-message", None), None);
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    path::{Component, Path},
+};
+
+use crate::file_ops;
+
+/// File in a source folder with patterns of files to leave out, like the ones in the settings.
+pub const IGNORE_FILE: &str = ".imageorganizerignore";
+
+// One line of an ignore file. `*` matches any run of characters and `?` any one, except `/`.
+#[derive(Clone, Debug)]
+struct Pattern {
+    // Lower case, as file names differ in case between cameras and systems.
+    glob: Vec<char>,
+    // "cache/" only matches folders.
+    folder_only: bool,
+    // "2024/raw" matches the path from the source folder, while "*.tmp" matches any name in it.
+    anchored: bool,
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let folder_only = line.ends_with('/');
+        let line = line.trim_end_matches('/').trim_start_matches('/');
+        Some(Self {
+            glob: line.to_lowercase().chars().collect(),
+            folder_only,
+            anchored: line.contains('/'),
+        })
+    }
+}
+
+fn wildcard_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len())
+            .take_while(|&skipped| skipped == 0 || text[skipped - 1] != '/')
+            .any(|skipped| wildcard_match(rest, &text[skipped..])),
+        Some((&expected, rest)) => text.split_first().is_some_and(|(&actual, text_rest)| {
+            (expected == actual || (expected == '?' && actual != '/'))
+                && wildcard_match(rest, text_rest)
+        }),
+    }
+}
+
+/// Whether a file manager hides `path` by default: its name starts with a dot, or on Windows it
+/// has the hidden attribute.
+pub fn is_hidden(path: &Path) -> bool {
+    let dot_file = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if fs::symlink_metadata(path)
+            .is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+        {
+            return true;
+        }
+    }
+    dot_file
+}
+
+/// Which files in a source folder are never shown for sorting.
+#[derive(Clone, Debug, Default)]
+pub struct IgnoreRules {
+    include_hidden: bool,
+    skip_symlinks: bool,
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    /// `patterns` has one pattern per line. Empty lines and lines starting with `#` are skipped.
+    pub fn new(include_hidden: bool, patterns: &str) -> Self {
+        Self {
+            include_hidden,
+            skip_symlinks: false,
+            patterns: patterns.lines().filter_map(Pattern::parse).collect(),
+        }
+    }
+
+    /// Also leaves out symbolic links.
+    pub fn skip_symlinks(self, skip_symlinks: bool) -> Self {
+        Self {
+            skip_symlinks,
+            ..self
+        }
+    }
+
+    /// Adds the patterns in `IGNORE_FILE` of `folder`, if it has one.
+    pub fn with_folder_file(self, folder: &Path) -> Self {
+        let mut rules = self;
+        match fs::read_to_string(folder.join(IGNORE_FILE)) {
+            Ok(contents) => rules
+                .patterns
+                .extend(contents.lines().filter_map(Pattern::parse)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!(
+                "Failed to read the ignore file of {}: {}",
+                folder.display(),
+                e
+            ),
+        }
+        rules
+    }
+
+    /// Whether `path`, which is in `folder` or one of its subfolders, is left out.
+    pub fn is_ignored(&self, folder: &Path, path: &Path, is_folder: bool) -> bool {
+        if (!self.include_hidden && is_hidden(path))
+            || (self.skip_symlinks && file_ops::is_symlink(path))
+        {
+            return true;
+        }
+        let Ok(relative) = path.strip_prefix(folder) else {
+            return false;
+        };
+        let components: Vec<String> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+                _ => None,
+            })
+            .collect();
+        let relative: Vec<char> = components.join("/").chars().collect();
+        let name: Vec<char> = components
+            .last()
+            .map_or(Vec::new(), |name| name.chars().collect());
+        self.patterns.iter().any(|pattern| {
+            if pattern.folder_only && !is_folder {
+                return false;
+            }
+            if pattern.anchored {
+                wildcard_match(&pattern.glob, &relative)
+            } else {
+                wildcard_match(&pattern.glob, &name)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignore_rules_test() {
+        let folder = Path::new("/photos");
+        let rules = IgnoreRules::new(
+            false,
+            "# Junk\n*.tmp.jpg\n\nthumbs.db\ncache/\n2024/raw/*\n",
+        );
+        let ignored =
+            |name: &str, is_folder| rules.is_ignored(folder, &folder.join(name), is_folder);
+        assert!(ignored("a.tmp.jpg", false));
+        assert!(ignored("Thumbs.db", false));
+        assert!(ignored("cache", true));
+        assert!(!ignored("cache", false));
+        assert!(ignored("2024/raw/a.png", false));
+        assert!(!ignored("2024/raw/deeper/a.png", false));
+        assert!(!ignored("2024/a.png", false));
+        assert!(!ignored("a.jpg", false));
+        assert!(ignored(".hidden.png", false));
+        assert!(ignored("._a.jpg", false));
+
+        let rules = IgnoreRules::new(true, "");
+        assert!(!rules.is_ignored(folder, &folder.join(".hidden.png"), false));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skip_symlinks_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("a.png"), b"").unwrap();
+        let link = temp_dir.path().join("link.png");
+        std::os::unix::fs::symlink("a.png", &link).unwrap();
+        let rules = IgnoreRules::default();
+        assert!(!rules.is_ignored(temp_dir.path(), &link, false));
+        let rules = rules.skip_symlinks(true);
+        assert!(rules.is_ignored(temp_dir.path(), &link, false));
+        assert!(!rules.is_ignored(temp_dir.path(), &temp_dir.path().join("a.png"), false));
+    }
+
+    #[test]
+    fn ignore_file_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join(IGNORE_FILE), "*-edited.jpg\n").unwrap();
+        let rules = IgnoreRules::new(false, "*.tmp.png").with_folder_file(temp_dir.path());
+        assert!(rules.is_ignored(
+            temp_dir.path(),
+            &temp_dir.path().join("a-edited.jpg"),
+            false
+        ));
+        assert!(rules.is_ignored(temp_dir.path(), &temp_dir.path().join("b.tmp.png"), false));
+        assert!(!rules.is_ignored(temp_dir.path(), &temp_dir.path().join("a.jpg"), false));
+    }
+}
@@ -0,0 +1,246 @@
+use eframe::egui;
+use egui::{
+    load::{BytesPoll, ImageLoadResult, ImageLoader, ImagePoll, LoadError, SizeHint},
+    ColorImage,
+};
+use image::{imageops::FilterType, DynamicImage, ImageFormat};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{heif, pages, pdf, raw};
+
+// Appended to an image URI to ask for the image without downscaling.
+const FULL_RESOLUTION_FRAGMENT: &str = "#full";
+// Appended to an image URI, before the above, to ask for another page of a multi-page TIFF.
+const PAGE_FRAGMENT: &str = "#page=";
+
+// Formats decoded by this loader. Animated formats (GIF, WebP) are left to egui_extras so that
+// they keep animating.
+const SUPPORTED_FORMATS: [ImageFormat; 8] = [
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Bmp,
+    ImageFormat::Tiff,
+    ImageFormat::Tga,
+    ImageFormat::Qoi,
+    ImageFormat::Hdr,
+    ImageFormat::OpenExr,
+];
+
+/// URI that loads the image at `uri` without capping its size. Textures are still limited by
+/// what the GPU supports.
+pub fn full_resolution_uri(uri: &str) -> String {
+    format!("{}{}", uri, FULL_RESOLUTION_FRAGMENT)
+}
+
+/// URI of page `page`, counting from 0, of the multi-page file at `uri`. The first page is the
+/// file's own URI.
+pub fn page_uri(uri: &str, page: usize) -> String {
+    if page == 0 {
+        uri.to_string()
+    } else {
+        format!("{}{}{}", uri, PAGE_FRAGMENT, page)
+    }
+}
+
+// Returns the URI of the underlying file, whether full resolution was asked for, and the page.
+fn split_uri(uri: &str) -> (&str, bool, usize) {
+    let (uri, full_resolution) = match uri.strip_suffix(FULL_RESOLUTION_FRAGMENT) {
+        Some(source_uri) => (source_uri, true),
+        None => (uri, false),
+    };
+    match uri
+        .rsplit_once(PAGE_FRAGMENT)
+        .and_then(|(source_uri, page)| Some((source_uri, page.parse().ok()?)))
+    {
+        Some((source_uri, page)) => (source_uri, full_resolution, page),
+        None => (uri, full_resolution, 0),
+    }
+}
+
+fn is_supported_uri(uri: &str) -> bool {
+    if !uri.starts_with("file://") && !uri.starts_with("bytes://") {
+        return false;
+    }
+    let path = Path::new(uri);
+    raw::is_raw_file(path)
+        || pdf::is_pdf_file(path)
+        || heif::is_heif_file(path)
+        || ImageFormat::from_path(path).is_ok_and(|format| SUPPORTED_FORMATS.contains(&format))
+}
+
+/// Shrinks `image` so that neither side is longer than `max_side`, keeping the aspect ratio.
+/// Images that already fit are returned as is.
+pub fn downscale(image: DynamicImage, max_side: u32) -> DynamicImage {
+    if image.width() <= max_side && image.height() <= max_side {
+        return image;
+    }
+    log::debug!(
+        "Downscaling {}x{} image to fit in {}",
+        image.width(),
+        image.height(),
+        max_side
+    );
+    image.resize(max_side, max_side, FilterType::Triangle)
+}
+
+// `uri` tells the format. RAW files are shown with their embedded preview and PDF files with
+// their first page. Other pages than the first are only read from TIFF files.
+fn decode(uri: &str, bytes: &[u8], max_side: u32, page: usize) -> Result<ColorImage, String> {
+    let image = if page > 0 {
+        pages::decode_page(bytes, page)?
+    } else {
+        raw::decode_bytes(Path::new(uri), bytes)?
+    };
+    let image = downscale(image, max_side).to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok(ColorImage::from_rgba_unmultiplied(
+        size,
+        image.as_flat_samples().as_slice(),
+    ))
+}
+
+#[derive(Clone)]
+enum Entry {
+    Pending,
+    Ready(Arc<ColorImage>),
+    Failed(String),
+}
+
+/// Image loader that decodes on a background thread and caps the texture size, so that huge
+/// panoramas do not allocate enormous textures or go over the GPU limit.
+#[derive(Default)]
+pub struct DownscalingLoader {
+    // Shared with the settings so changes apply without reinstalling the loader.
+    max_side: Arc<AtomicU32>,
+    cache: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl DownscalingLoader {
+    pub const ID: &'static str = egui::generate_loader_id!(DownscalingLoader);
+
+    pub fn new(max_side: Arc<AtomicU32>) -> Self {
+        Self {
+            max_side,
+            cache: Default::default(),
+        }
+    }
+}
+
+impl ImageLoader for DownscalingLoader {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str, _: SizeHint) -> ImageLoadResult {
+        let (source_uri, full_resolution, page) = split_uri(uri);
+        if !is_supported_uri(source_uri) {
+            return Err(LoadError::NotSupported);
+        }
+
+        if let Some(entry) = self.cache.lock().unwrap().get(uri).cloned() {
+            return match entry {
+                Entry::Pending => Ok(ImagePoll::Pending { size: None }),
+                Entry::Ready(image) => Ok(ImagePoll::Ready { image }),
+                Entry::Failed(error) => Err(LoadError::Loading(error)),
+            };
+        }
+
+        match ctx.try_load_bytes(source_uri) {
+            Ok(BytesPoll::Ready { bytes, .. }) => {
+                let gpu_max_side = ctx.input(|input| input.max_texture_side) as u32;
+                let max_side = if full_resolution {
+                    gpu_max_side
+                } else {
+                    self.max_side.load(Ordering::Relaxed).min(gpu_max_side)
+                };
+                self.cache
+                    .lock()
+                    .unwrap()
+                    .insert(uri.to_string(), Entry::Pending);
+
+                let cache = self.cache.clone();
+                let ctx = ctx.clone();
+                let uri = uri.to_string();
+                let source_uri = source_uri.to_string();
+                thread::spawn(move || {
+                    let entry = match decode(&source_uri, &bytes, max_side, page) {
+                        Ok(image) => Entry::Ready(Arc::new(image)),
+                        Err(e) => Entry::Failed(e),
+                    };
+                    let mut cache = cache.lock().unwrap();
+                    // Do not resurrect the entry if it was forgotten while decoding.
+                    if let Some(cached) = cache.get_mut(&uri) {
+                        *cached = entry;
+                        ctx.request_repaint();
+                    }
+                });
+                Ok(ImagePoll::Pending { size: None })
+            }
+            Ok(BytesPoll::Pending { size }) => Ok(ImagePoll::Pending { size }),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn forget(&self, uri: &str) {
+        self.cache.lock().unwrap().remove(uri);
+    }
+
+    fn forget_all(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        self.cache
+            .lock()
+            .unwrap()
+            .values()
+            .map(|entry| match entry {
+                Entry::Ready(image) => image.pixels.len() * std::mem::size_of::<egui::Color32>(),
+                Entry::Pending | Entry::Failed(_) => 0,
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downscale_test() {
+        let image = DynamicImage::new_rgb8(400, 100);
+        let small = downscale(image.clone(), 100);
+        assert_eq!((small.width(), small.height()), (100, 25));
+
+        let same = downscale(image, 400);
+        assert_eq!((same.width(), same.height()), (400, 100));
+    }
+
+    #[test]
+    fn uri_test() {
+        let uri = "file:///tmp/a.png";
+        assert_eq!(split_uri(uri), (uri, false, 0));
+        assert_eq!(split_uri(&full_resolution_uri(uri)), (uri, true, 0));
+        let tiff = "file:///tmp/scan.tif";
+        assert_eq!(page_uri(tiff, 0), tiff);
+        assert_eq!(split_uri(&page_uri(tiff, 2)), (tiff, false, 2));
+        assert_eq!(
+            split_uri(&full_resolution_uri(&page_uri(tiff, 2))),
+            (tiff, true, 2)
+        );
+        assert!(is_supported_uri(tiff));
+        assert!(is_supported_uri(uri));
+        assert!(is_supported_uri("file:///tmp/a.JPG"));
+        assert!(!is_supported_uri("file:///tmp/a.gif"));
+        assert!(is_supported_uri("file:///tmp/a.NEF"));
+        assert!(!is_supported_uri("https://example.com/a.png"));
+    }
+}
@@ -0,0 +1,727 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use crate::{file_ops, metadata};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS images (
+        path BLOB PRIMARY KEY,
+        folder BLOB NOT NULL,
+        name TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        mtime INTEGER NOT NULL,
+        hash TEXT,
+        exif_date TEXT
+    );
+    CREATE INDEX IF NOT EXISTS images_folder ON images(folder);
+    CREATE INDEX IF NOT EXISTS images_hash ON images(hash);
+    CREATE TABLE IF NOT EXISTS tags (
+        path BLOB NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (path, tag)
+    );
+    CREATE INDEX IF NOT EXISTS tags_tag ON tags(tag);
+    CREATE TABLE IF NOT EXISTS notes (
+        path BLOB PRIMARY KEY,
+        note TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS reviews (
+        path BLOB PRIMARY KEY,
+        first_viewed INTEGER,
+        kept INTEGER NOT NULL DEFAULT 0
+    );
+";
+
+// Commit after this many files so that an interrupted update keeps most of its work.
+const COMMIT_INTERVAL: usize = 200;
+
+// Paths are stored and sent as raw bytes so that non-UTF-8 file names round trip.
+#[cfg(unix)]
+pub fn path_to_blob(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+pub fn blob_to_path(blob: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(blob))
+}
+
+#[cfg(windows)]
+pub fn path_to_blob(path: &Path) -> Vec<u8> {
+    use std::os::windows::ffi::OsStrExt;
+    path.as_os_str()
+        .encode_wide()
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+#[cfg(windows)]
+pub fn blob_to_path(blob: Vec<u8>) -> PathBuf {
+    use std::os::windows::ffi::OsStringExt;
+    let wide: Vec<u16> = blob
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    PathBuf::from(std::ffi::OsString::from_wide(&wide))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+// Size and modification time in nanoseconds. A file whose stamp did not change is not reread.
+fn file_stamp(path: &Path) -> std::io::Result<(i64, i64)> {
+    let metadata = fs::metadata(path)?;
+    let mtime = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    Ok((metadata.len() as i64, mtime as i64))
+}
+
+/// What the index knows about an image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexedImage {
+    pub size: u64,
+    pub hash: Option<String>,
+    pub exif_date: Option<String>,
+}
+
+/// SQLite database of images that were seen before, so that large libraries can be reopened,
+/// searched, and checked for duplicates without reading every file again.
+pub struct LibraryIndex {
+    connection: Connection,
+}
+
+impl LibraryIndex {
+    pub fn open(db_path: &Path) -> Result<Self> {
+        if let Some(parent) = db_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(db_path)?;
+        // WAL lets the UI read while a background update writes.
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Images in `folder` as of the last update. The files are not checked.
+    pub fn images_in_folder(&self, folder: &Path) -> Result<Vec<PathBuf>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT path FROM images WHERE folder = ?1")?;
+        let paths = statement
+            .query_map(params![path_to_blob(folder)], |row| row.get(0))?
+            .map(|blob| blob.map(blob_to_path))
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(paths)
+    }
+
+    pub fn get(&self, path: &Path) -> Result<Option<IndexedImage>> {
+        let image = self
+            .connection
+            .query_row(
+                "SELECT size, hash, exif_date FROM images WHERE path = ?1",
+                params![path_to_blob(path)],
+                |row| {
+                    Ok(IndexedImage {
+                        size: row.get::<_, i64>(0)? as u64,
+                        hash: row.get(1)?,
+                        exif_date: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(image)
+    }
+
+    /// Brings the entries of `folder` in line with `paths`, the images currently in it. New and
+    /// changed files are hashed and their EXIF date is read. Entries for files that are gone are
+    /// removed. Returns the number of files that were (re)indexed.
+    pub fn update_folder(
+        &mut self,
+        folder: &Path,
+        paths: &[PathBuf],
+        progress: &AtomicUsize,
+        cancelled: &AtomicBool,
+    ) -> Result<usize> {
+        let mut known: HashMap<PathBuf, (i64, i64)> = HashMap::new();
+        {
+            let mut statement = self
+                .connection
+                .prepare("SELECT path, size, mtime FROM images WHERE folder = ?1")?;
+            let rows = statement.query_map(params![path_to_blob(folder)], |row| {
+                Ok((blob_to_path(row.get(0)?), (row.get(1)?, row.get(2)?)))
+            })?;
+            for row in rows {
+                let (path, stamp) = row?;
+                known.insert(path, stamp);
+            }
+        }
+
+        let folder_blob = path_to_blob(folder);
+        let mut updated = 0;
+        let mut transaction = self.connection.transaction()?;
+        for (i, path) in paths.iter().enumerate() {
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            progress.store(i, Ordering::Relaxed);
+            let stamp = match file_stamp(path) {
+                Ok(stamp) => stamp,
+                Err(e) => {
+                    log::warn!("Failed to index {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if known.remove(path) == Some(stamp) {
+                continue;
+            }
+            let hash = file_ops::hash_file(path)
+                .inspect_err(|e| log::warn!("Failed to hash {}: {}", path.display(), e))
+                .ok();
+            transaction.execute(
+                "INSERT OR REPLACE INTO images (path, folder, name, size, mtime, hash, exif_date)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    path_to_blob(path),
+                    folder_blob,
+                    file_name(path),
+                    stamp.0,
+                    stamp.1,
+                    hash,
+                    metadata::date_taken(path),
+                ],
+            )?;
+            updated += 1;
+            if updated % COMMIT_INTERVAL == 0 {
+                transaction.commit()?;
+                transaction = self.connection.transaction()?;
+            }
+        }
+        // Only a complete listing tells which files are gone.
+        if !cancelled.load(Ordering::Relaxed) {
+            progress.store(paths.len(), Ordering::Relaxed);
+            for path in known.keys() {
+                let blob = path_to_blob(path);
+                transaction.execute("DELETE FROM images WHERE path = ?1", params![blob])?;
+                transaction.execute("DELETE FROM tags WHERE path = ?1", params![blob])?;
+                transaction.execute("DELETE FROM notes WHERE path = ?1", params![blob])?;
+                transaction.execute("DELETE FROM reviews WHERE path = ?1", params![blob])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(updated)
+    }
+
+    /// Records that a file was moved so that its tags, note and review follow it.
+    pub fn rename(&self, src: &Path, dest: &Path) -> Result<()> {
+        let dest_folder = dest.parent().unwrap_or(Path::new(""));
+        self.connection.execute(
+            "UPDATE OR REPLACE images SET path = ?2, folder = ?3, name = ?4 WHERE path = ?1",
+            params![
+                path_to_blob(src),
+                path_to_blob(dest),
+                path_to_blob(dest_folder),
+                file_name(dest)
+            ],
+        )?;
+        self.connection.execute(
+            "UPDATE OR REPLACE tags SET path = ?2 WHERE path = ?1",
+            params![path_to_blob(src), path_to_blob(dest)],
+        )?;
+        self.connection.execute(
+            "UPDATE OR REPLACE notes SET path = ?2 WHERE path = ?1",
+            params![path_to_blob(src), path_to_blob(dest)],
+        )?;
+        self.connection.execute(
+            "UPDATE OR REPLACE reviews SET path = ?2 WHERE path = ?1",
+            params![path_to_blob(src), path_to_blob(dest)],
+        )?;
+        Ok(())
+    }
+
+    /// Images in `folder` whose file name or note contains `query` or that have a tag equal to
+    /// it. The comparison ignores ASCII case.
+    pub fn search(&self, folder: &Path, query: &str) -> Result<Vec<PathBuf>> {
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        let mut statement = self.connection.prepare(
+            "SELECT path FROM images WHERE folder = ?1 AND (
+                 name LIKE ?2 ESCAPE '\\'
+                 OR path IN (SELECT path FROM tags WHERE tag = ?3 COLLATE NOCASE)
+                 OR path IN (SELECT path FROM notes WHERE note LIKE ?2 ESCAPE '\\')
+             )",
+        )?;
+        let mut paths: Vec<PathBuf> = statement
+            .query_map(
+                params![path_to_blob(folder), pattern, query.trim()],
+                |row| row.get(0),
+            )?
+            .map(|blob| blob.map(blob_to_path))
+            .collect::<rusqlite::Result<_>>()?;
+        crate::scanner::sort_image_paths(&mut paths);
+        Ok(paths)
+    }
+
+    /// Groups of images anywhere in the index that have the same contents.
+    pub fn duplicates(&self) -> Result<Vec<Vec<PathBuf>>> {
+        let mut statement = self.connection.prepare(
+            "SELECT hash, path FROM images WHERE hash IN (
+                 SELECT hash FROM images WHERE hash IS NOT NULL
+                 GROUP BY hash HAVING COUNT(*) > 1
+             ) ORDER BY hash, path",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, blob_to_path(row.get(1)?)))
+        })?;
+        let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+        let mut last_hash = None;
+        for row in rows {
+            let (hash, path) = row?;
+            if last_hash.as_ref() != Some(&hash) {
+                groups.push(Vec::new());
+                last_hash = Some(hash);
+            }
+            groups.last_mut().unwrap().push(path);
+        }
+        Ok(groups)
+    }
+
+    /// Images in `folder` or any folder under it whose contents hash to `hash`.
+    pub fn find_by_hash(&self, hash: &str, folder: &Path) -> Result<Vec<PathBuf>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT path FROM images WHERE hash = ?1 ORDER BY path")?;
+        let paths = statement
+            .query_map(params![hash], |row| row.get(0))?
+            .map(|blob| blob.map(blob_to_path))
+            .filter(|path| path.as_ref().map_or(true, |path| path.starts_with(folder)))
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(paths)
+    }
+
+    pub fn tags(&self, path: &Path) -> Result<Vec<String>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT tag FROM tags WHERE path = ?1 ORDER BY tag")?;
+        let tags = statement
+            .query_map(params![path_to_blob(path)], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tags)
+    }
+
+    /// Replaces the tags of `path`. Blank and repeated tags are dropped.
+    pub fn set_tags(&mut self, path: &Path, tags: &[String]) -> Result<()> {
+        let blob = path_to_blob(path);
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM tags WHERE path = ?1", params![blob])?;
+        let unique: HashSet<&str> = tags
+            .iter()
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        for tag in unique {
+            transaction.execute(
+                "INSERT INTO tags (path, tag) VALUES (?1, ?2)",
+                params![blob, tag],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn note(&self, path: &Path) -> Result<Option<String>> {
+        let note = self
+            .connection
+            .query_row(
+                "SELECT note FROM notes WHERE path = ?1",
+                params![path_to_blob(path)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(note)
+    }
+
+    /// Replaces the note of `path`. A blank note removes it.
+    pub fn set_note(&self, path: &Path, note: &str) -> Result<()> {
+        let blob = path_to_blob(path);
+        if note.trim().is_empty() {
+            self.connection
+                .execute("DELETE FROM notes WHERE path = ?1", params![blob])?;
+        } else {
+            self.connection.execute(
+                "INSERT OR REPLACE INTO notes (path, note) VALUES (?1, ?2)",
+                params![blob, note.trim()],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Records that `path` was shown. Only the first time is kept.
+    pub fn mark_viewed(&self, path: &Path, now: i64) -> Result<()> {
+        let blob = path_to_blob(path);
+        self.connection.execute(
+            "INSERT OR IGNORE INTO reviews (path) VALUES (?1)",
+            params![blob],
+        )?;
+        self.connection.execute(
+            "UPDATE reviews SET first_viewed = ?2 WHERE path = ?1 AND first_viewed IS NULL",
+            params![blob, now],
+        )?;
+        Ok(())
+    }
+
+    /// When `path` was first shown, in seconds since the Unix epoch.
+    pub fn first_viewed(&self, path: &Path) -> Result<Option<i64>> {
+        let viewed = self
+            .connection
+            .query_row(
+                "SELECT first_viewed FROM reviews WHERE path = ?1",
+                params![path_to_blob(path)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(viewed.flatten())
+    }
+
+    /// Records whether the user decided to leave `path` where it is.
+    pub fn set_kept(&self, path: &Path, kept: bool) -> Result<()> {
+        let blob = path_to_blob(path);
+        self.connection.execute(
+            "INSERT OR IGNORE INTO reviews (path) VALUES (?1)",
+            params![blob],
+        )?;
+        self.connection.execute(
+            "UPDATE reviews SET kept = ?2 WHERE path = ?1",
+            params![blob, kept],
+        )?;
+        Ok(())
+    }
+
+    /// Images in `folder` that the user decided to leave where they are.
+    pub fn kept_in_folder(&self, folder: &Path) -> Result<HashSet<PathBuf>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT path FROM reviews WHERE kept != 0")?;
+        let paths: Vec<PathBuf> = statement
+            .query_map([], |row| row.get(0))?
+            .map(|blob| blob.map(blob_to_path))
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(paths
+            .into_iter()
+            .filter(|path| path.parent() == Some(folder))
+            .collect())
+    }
+
+    /// Every tag with the number of images that have it, most used first.
+    pub fn all_tags(&self) -> Result<Vec<(String, usize)>> {
+        let mut statement = self.connection.prepare(
+            "SELECT tag, COUNT(*) AS count FROM tags GROUP BY tag ORDER BY count DESC, tag",
+        )?;
+        let tags = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tags)
+    }
+
+    /// Images anywhere in the index that have `tag`, ignoring ASCII case.
+    pub fn paths_with_tag(&self, tag: &str) -> Result<Vec<PathBuf>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT path FROM tags WHERE tag = ?1 COLLATE NOCASE")?;
+        let paths = statement
+            .query_map(params![tag.trim()], |row| row.get(0))?
+            .map(|blob| blob.map(blob_to_path))
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(paths)
+    }
+
+    /// Folders that hold other images with any of `tags`, with the number of such images, most
+    /// first. Where images with the same tags went before is a good guess for the next one.
+    pub fn tag_folders(&self, tags: &[String], exclude: &Path) -> Result<Vec<(PathBuf, usize)>> {
+        let mut counts: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+        for tag in tags {
+            for path in self.paths_with_tag(tag)? {
+                if path == exclude {
+                    continue;
+                }
+                if let Some(folder) = path.parent() {
+                    counts.entry(folder.to_path_buf()).or_default().insert(path);
+                }
+            }
+        }
+        let mut folders: Vec<(PathBuf, usize)> = counts
+            .into_iter()
+            .map(|(folder, paths)| (folder, paths.len()))
+            .collect();
+        folders.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(folders)
+    }
+}
+
+/// Updates the index for a folder on a background thread. The thread opens its own connection.
+pub struct IndexUpdate {
+    folder: PathBuf,
+    total: usize,
+    progress: Arc<AtomicUsize>,
+    cancelled: Arc<AtomicBool>,
+    receiver: mpsc::Receiver<Result<usize, String>>,
+    result: Option<Result<usize, String>>,
+}
+
+impl IndexUpdate {
+    pub fn start(db_path: PathBuf, folder: &Path, paths: Vec<PathBuf>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let progress = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let total = paths.len();
+        let thread_progress = progress.clone();
+        let thread_cancelled = cancelled.clone();
+        let thread_folder = folder.to_path_buf();
+        thread::spawn(move || {
+            let result = LibraryIndex::open(&db_path)
+                .and_then(|mut index| {
+                    index.update_folder(&thread_folder, &paths, &thread_progress, &thread_cancelled)
+                })
+                .map_err(|e| e.to_string());
+            let _ = sender.send(result);
+        });
+        Self {
+            folder: folder.to_path_buf(),
+            total,
+            progress,
+            cancelled,
+            receiver,
+            result: None,
+        }
+    }
+
+    /// Returns the result once, when the update finishes.
+    pub fn poll(&mut self) -> Option<Result<usize, String>> {
+        if self.result.is_some() {
+            return None;
+        }
+        let result = match self.receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err("The index update stopped.".to_string()),
+        };
+        self.result = Some(result.clone());
+        Some(result)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    /// (indexed, total)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.progress.load(Ordering::Relaxed), self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(index: &mut LibraryIndex, folder: &Path) -> usize {
+        let paths = crate::scanner::get_image_paths(folder);
+        index
+            .update_folder(
+                folder,
+                &paths,
+                &AtomicUsize::new(0),
+                &AtomicBool::new(false),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn update_folder_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path().join("images");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("a.png"), b"a").unwrap();
+        fs::write(folder.join("b.png"), b"b").unwrap();
+        let mut index = LibraryIndex::open(&temp_dir.path().join("index.sqlite3")).unwrap();
+
+        assert_eq!(update(&mut index, &folder), 2);
+        let mut indexed = index.images_in_folder(&folder).unwrap();
+        indexed.sort();
+        assert_eq!(indexed, vec![folder.join("a.png"), folder.join("b.png")]);
+        let a = index.get(&folder.join("a.png")).unwrap().unwrap();
+        assert_eq!(a.size, 1);
+        assert_eq!(
+            a.hash,
+            Some(file_ops::hash_file(&folder.join("a.png")).unwrap())
+        );
+
+        // Unchanged files are skipped and deleted files are dropped.
+        fs::remove_file(folder.join("b.png")).unwrap();
+        assert_eq!(update(&mut index, &folder), 0);
+        assert_eq!(
+            index.images_in_folder(&folder).unwrap(),
+            vec![folder.join("a.png")]
+        );
+    }
+
+    #[test]
+    fn search_and_duplicates_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path();
+        fs::write(folder.join("cat_1.png"), b"same").unwrap();
+        fs::write(folder.join("cat_2.png"), b"same").unwrap();
+        fs::write(folder.join("dog.png"), b"other").unwrap();
+        let mut index = LibraryIndex::open(&folder.join("index.sqlite3")).unwrap();
+        update(&mut index, folder);
+
+        assert_eq!(
+            index.search(folder, "CAT").unwrap(),
+            vec![folder.join("cat_2.png"), folder.join("cat_1.png")]
+        );
+        assert!(index.search(folder, "%").unwrap().is_empty());
+
+        index
+            .set_tags(
+                &folder.join("dog.png"),
+                &["pet".to_string(), " ".to_string()],
+            )
+            .unwrap();
+        assert_eq!(index.tags(&folder.join("dog.png")).unwrap(), vec!["pet"]);
+        assert_eq!(
+            index.search(folder, "Pet").unwrap(),
+            vec![folder.join("dog.png")]
+        );
+
+        assert_eq!(
+            index.duplicates().unwrap(),
+            vec![vec![folder.join("cat_1.png"), folder.join("cat_2.png")]]
+        );
+
+        let hash = file_ops::hash_file(&folder.join("dog.png")).unwrap();
+        assert_eq!(
+            index.find_by_hash(&hash, folder).unwrap(),
+            vec![folder.join("dog.png")]
+        );
+        assert!(index
+            .find_by_hash(&hash, &folder.join("other"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rename_keeps_tags_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path();
+        fs::write(folder.join("a.png"), b"a").unwrap();
+        let mut index = LibraryIndex::open(&folder.join("index.sqlite3")).unwrap();
+        update(&mut index, folder);
+        index
+            .set_tags(&folder.join("a.png"), &["keep".to_string()])
+            .unwrap();
+
+        index
+            .set_note(&folder.join("a.png"), "Send this one to mom ")
+            .unwrap();
+
+        let dest = folder.join("sorted").join("a.png");
+        index.rename(&folder.join("a.png"), &dest).unwrap();
+        assert_eq!(index.tags(&dest).unwrap(), vec!["keep"]);
+        assert_eq!(
+            index.note(&dest).unwrap().as_deref(),
+            Some("Send this one to mom")
+        );
+        assert_eq!(
+            index.search(&folder.join("sorted"), "MOM").unwrap(),
+            vec![dest.clone()]
+        );
+        index.set_note(&dest, " ").unwrap();
+        assert_eq!(index.note(&dest).unwrap(), None);
+        assert_eq!(
+            index.images_in_folder(&folder.join("sorted")).unwrap(),
+            vec![dest]
+        );
+    }
+
+    #[test]
+    fn reviews_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path();
+        let index = LibraryIndex::open(&folder.join("index.sqlite3")).unwrap();
+        let a = folder.join("a.png");
+        assert_eq!(index.first_viewed(&a).unwrap(), None);
+        index.mark_viewed(&a, 100).unwrap();
+        index.mark_viewed(&a, 200).unwrap();
+        assert_eq!(index.first_viewed(&a).unwrap(), Some(100));
+
+        index.set_kept(&a, true).unwrap();
+        index
+            .set_kept(&folder.join("sub").join("b.png"), true)
+            .unwrap();
+        assert_eq!(
+            index.kept_in_folder(folder).unwrap(),
+            HashSet::from([a.clone()])
+        );
+        index.set_kept(&a, false).unwrap();
+        assert!(index.kept_in_folder(folder).unwrap().is_empty());
+        assert_eq!(index.first_viewed(&a).unwrap(), Some(100));
+    }
+
+    #[test]
+    fn tag_queries_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path();
+        let mut index = LibraryIndex::open(&folder.join("index.sqlite3")).unwrap();
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        let sorted = folder.join("cats");
+        index
+            .set_tags(&sorted.join("1.png"), &tags(&["cat", "home"]))
+            .unwrap();
+        index
+            .set_tags(&sorted.join("2.png"), &tags(&["cat"]))
+            .unwrap();
+        index
+            .set_tags(&folder.join("trip").join("3.png"), &tags(&["home"]))
+            .unwrap();
+        let new = folder.join("new.png");
+        index.set_tags(&new, &tags(&["cat"])).unwrap();
+
+        assert_eq!(
+            index.all_tags().unwrap(),
+            vec![("cat".to_string(), 3), ("home".to_string(), 2)]
+        );
+        assert_eq!(index.paths_with_tag("HOME").unwrap().len(), 2);
+        assert_eq!(
+            index.tag_folders(&tags(&["cat", "home"]), &new).unwrap(),
+            vec![(sorted, 2), (folder.join("trip"), 1)]
+        );
+    }
+}
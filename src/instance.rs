@@ -0,0 +1,162 @@
+use eframe::egui;
+use fs4::fs_std::FileExt;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use crate::index;
+
+// Sent first by the running app so that a stale port file that now points at some other program
+// is not mistaken for it.
+const GREETING: &str = "image-organizer";
+const TIMEOUT: Duration = Duration::from_secs(2);
+// Longer than any path.
+const MAX_PATH_BYTES: usize = 1 << 16;
+
+/// The running app. It listens on a local port, written to a file for later launches to find.
+pub struct Server {
+    listener: TcpListener,
+}
+
+/// Becomes the running app unless another one is. In that case `folder` is handed over to it
+/// and None is returned, so this launch should exit.
+pub fn start(port_file: &Path, folder: Option<&Path>) -> io::Result<Option<Server>> {
+    if let Some(parent) = port_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Two launches at the same moment would both find no app and both become it. The lock
+    // makes the second wait until the first wrote its port. It goes with the file when this
+    // returns.
+    let lock = fs::File::create(port_file.with_extension("lock"))?;
+    lock.lock_exclusive()?;
+    if let Ok(port) = fs::read_to_string(port_file) {
+        if let Ok(port) = port.trim().parse::<u16>() {
+            match hand_over(port, folder) {
+                Ok(()) => return Ok(None),
+                Err(e) => log::info!("No running app found on port {}: {}", port, e),
+            }
+        }
+    }
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    fs::write(port_file, listener.local_addr()?.port().to_string())?;
+    Ok(Some(Server { listener }))
+}
+
+fn hand_over(port: u16, folder: Option<&Path>) -> io::Result<()> {
+    let stream = TcpStream::connect_timeout(&(Ipv4Addr::LOCALHOST, port).into(), TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut greeting = String::new();
+    reader.read_line(&mut greeting)?;
+    if greeting.trim_end() != GREETING {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "another program is using the port",
+        ));
+    }
+    // The length of the path and its raw bytes, so that any name arrives as it is. No path
+    // only brings the running app to the front.
+    let bytes = folder.map(index::path_to_blob).unwrap_or_default();
+    let mut message = (bytes.len() as u32).to_be_bytes().to_vec();
+    message.extend(bytes);
+    (&stream).write_all(&message)
+}
+
+impl Server {
+    /// Listens for later launches on a background thread. The folders they hand over arrive on
+    /// the returned channel, None for a launch without one.
+    pub fn listen(self, ctx: egui::Context) -> mpsc::Receiver<Option<PathBuf>> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in self.listener.incoming() {
+                let folder = match stream.and_then(receive) {
+                    Ok(folder) => folder,
+                    Err(e) => {
+                        log::warn!("Failed to receive from another launch: {}", e);
+                        continue;
+                    }
+                };
+                if sender.send(folder).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+        receiver
+    }
+}
+
+fn receive(stream: TcpStream) -> io::Result<Option<PathBuf>> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    writeln!(&stream, "{}", GREETING)?;
+    let mut len = [0; 4];
+    (&stream).read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_PATH_BYTES {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "path too long"));
+    }
+    let mut bytes = vec![0; len];
+    (&stream).read_exact(&mut bytes)?;
+    Ok((len > 0).then(|| index::blob_to_path(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hand_over_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let port_file = temp_dir.path().join("instance.port");
+        let server = start(&port_file, None).unwrap().unwrap();
+        let receiver = server.listen(egui::Context::default());
+
+        let folder = temp_dir.path().join("photos");
+        assert!(start(&port_file, Some(&folder)).unwrap().is_none());
+        assert_eq!(
+            receiver.recv_timeout(TIMEOUT * 2).unwrap(),
+            Some(folder.clone())
+        );
+        assert!(start(&port_file, None).unwrap().is_none());
+        assert_eq!(receiver.recv_timeout(TIMEOUT * 2).unwrap(), None);
+
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+            let folder = temp_dir.path().join(OsStr::from_bytes(b"caf\xe9"));
+            assert!(start(&port_file, Some(&folder)).unwrap().is_none());
+            assert_eq!(receiver.recv_timeout(TIMEOUT * 2).unwrap(), Some(folder));
+        }
+
+        // A port file left behind by an app that is gone.
+        fs::write(&port_file, "1").unwrap();
+        assert!(start(&port_file, Some(&folder)).unwrap().is_some());
+    }
+
+    #[test]
+    fn simultaneous_start_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let port_file = temp_dir.path().join("instance.port");
+        let launches: Vec<_> = (0..2)
+            .map(|_| {
+                let port_file = port_file.clone();
+                thread::spawn(move || {
+                    let server = start(&port_file, None).unwrap();
+                    // Answers the other launch, if that one came second.
+                    server.map(|server| server.listen(egui::Context::default()))
+                })
+            })
+            .collect();
+        let servers: Vec<_> = launches
+            .into_iter()
+            .filter_map(|launch| launch.join().unwrap())
+            .collect();
+        assert_eq!(servers.len(), 1);
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+use crate::{archive, batch::ImageScan, heif, pdf, raw};
+
+/// Fully decodes the image to find truncated or otherwise damaged files. Reading the header alone
+/// is not enough since truncation is usually at the end of the file.
+pub fn check_image(path: &Path) -> Result<(), String> {
+    // A PDF may not be renderable here, but its end is there if it is complete.
+    if pdf::is_pdf_file(path) {
+        return pdf::check(&archive::read(path).map_err(|e| e.to_string())?);
+    }
+    // HEIF files are checked the same way, as libheif may not be installed.
+    if heif::is_heif_file(path) {
+        return heif::check(&archive::read(path).map_err(|e| e.to_string())?);
+    }
+    raw::decode_file(path)?;
+    Ok(())
+}
+
+/// A check of images for damage. What it finds in an image is why it could not be decoded.
+pub type IntegrityScan = ImageScan<String>;
+
+/// Checks `paths` for damage on a background thread.
+pub fn scan(paths: Vec<PathBuf>) -> IntegrityScan {
+    ImageScan::start(paths, |path| {
+        let error = check_image(path).err()?;
+        log::warn!("Corrupt image {}: {}", path.display(), error);
+        Some(error)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn write_png(path: &Path) {
+        image::RgbImage::from_pixel(8, 8, image::Rgb([255, 0, 0]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn check_image_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let good = temp_dir.path().join("good.png");
+        write_png(&good);
+        assert!(check_image(&good).is_ok());
+
+        let truncated = temp_dir.path().join("truncated.png");
+        let bytes = std::fs::read(&good).unwrap();
+        std::fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(check_image(&truncated).is_err());
+
+        let garbage = temp_dir.path().join("garbage.jpg");
+        std::fs::write(&garbage, b"Hello, world!").unwrap();
+        assert!(check_image(&garbage).is_err());
+    }
+
+    #[test]
+    fn integrity_scan_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let good = temp_dir.path().join("good.png");
+        let bad = temp_dir.path().join("bad.png");
+        write_png(&good);
+        std::fs::write(&bad, b"Hello, world!").unwrap();
+
+        let mut scan = scan(vec![good, bad.clone()]);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !scan.is_done() && Instant::now() < deadline {
+            scan.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(scan.progress(), (2, 2));
+        assert_eq!(scan.found().len(), 1);
+        assert_eq!(scan.found()[0].0, bad);
+    }
+}
@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{file_ops, i18n::tr};
+
+/// A file operation that can be interrupted halfway: a copy to another filesystem followed by
+/// removing the original. Renames are atomic and are not journaled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+    pub id: u64,
+    pub src: PathBuf,
+    pub dest: PathBuf,
+}
+
+// One line of the journal file.
+#[derive(Debug, Serialize, Deserialize)]
+enum Record {
+    Begin(Operation),
+    End(u64),
+}
+
+/// Where an interrupted operation stopped, found by looking at the files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interruption {
+    // The copy did not finish. The original is untouched.
+    NotCopied,
+    // The copy finished but the original was not removed.
+    NotRemoved,
+    // Both files exist with different contents. One of them was changed since.
+    Conflict,
+    // The operation finished, only its end was not recorded.
+    Done,
+    // Neither file exists. Something else moved or deleted it.
+    Missing,
+}
+
+impl Interruption {
+    pub fn of(operation: &Operation) -> Self {
+        match (operation.src.exists(), operation.dest.exists()) {
+            (true, false) => Interruption::NotCopied,
+            (true, true) => match file_ops::same_contents(&operation.src, &operation.dest) {
+                Ok(true) => Interruption::NotRemoved,
+                _ => Interruption::Conflict,
+            },
+            (false, true) => Interruption::Done,
+            (false, false) => Interruption::Missing,
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            Interruption::NotCopied => tr!("interrupted-not-copied"),
+            Interruption::NotRemoved => tr!("interrupted-not-removed"),
+            Interruption::Conflict => tr!("interrupted-conflict"),
+            Interruption::Done => tr!("interrupted-done"),
+            Interruption::Missing => tr!("interrupted-missing"),
+        }
+    }
+
+    /// What repairing does, if there is anything to do.
+    pub fn repair_label(self) -> Option<String> {
+        match self {
+            Interruption::NotCopied => Some(tr!("remove-partial-copy")),
+            Interruption::NotRemoved => Some(tr!("remove-original")),
+            _ => None,
+        }
+    }
+}
+
+/// Finishes an interrupted operation the safe way: the partial copy or the duplicated original is
+/// removed. Other states are left for the user.
+pub fn repair(operation: &Operation) -> io::Result<()> {
+    match Interruption::of(operation) {
+        Interruption::NotCopied => {
+            let partial = file_ops::partial_path(&operation.dest);
+            if partial.exists() {
+                fs::remove_file(partial)?;
+            }
+            Ok(())
+        }
+        Interruption::NotRemoved => fs::remove_file(&operation.src),
+        _ => Ok(()),
+    }
+}
+
+/// Records operations before they start and after they end, so that the ones a crash interrupted
+/// are found on the next start.
+pub struct Journal {
+    path: PathBuf,
+    file: fs::File,
+    next_id: u64,
+    // Begun and not ended, including the interrupted ones until they are resolved.
+    open: HashSet<u64>,
+}
+
+impl Journal {
+    /// Opens the journal at `path`. Returns it with the operations that were interrupted.
+    pub fn open(path: &Path) -> io::Result<(Self, Vec<Operation>)> {
+        let interrupted = match fs::File::open(path) {
+            Ok(file) => read_interrupted(io::BufReader::new(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Kept until the user resolves them. They are written to a new file that replaces the
+        // journal only once it is on disk, so that a crash now cannot lose them.
+        let mut survivors = String::new();
+        for operation in &interrupted {
+            survivors.push_str(&serde_json::to_string(&Record::Begin(operation.clone()))?);
+            survivors.push('\n');
+        }
+        let temp_path = path.with_extension("tmp");
+        let mut temp = fs::File::create(&temp_path)?;
+        temp.write_all(survivors.as_bytes())?;
+        temp.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        let journal = Self {
+            path: path.to_path_buf(),
+            file: fs::OpenOptions::new().append(true).open(path)?,
+            next_id: interrupted.iter().map(|op| op.id + 1).max().unwrap_or(0),
+            open: interrupted.iter().map(|op| op.id).collect(),
+        };
+        Ok((journal, interrupted))
+    }
+
+    /// Records that a copy from `src` to `dest` starts. Returns its id for `end()`.
+    pub fn begin(&mut self, src: &Path, dest: &Path) -> io::Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.write(&Record::Begin(Operation {
+            id,
+            src: src.to_path_buf(),
+            dest: dest.to_path_buf(),
+        }))?;
+        // It must be on disk before the copy starts.
+        self.file.sync_data()?;
+        self.open.insert(id);
+        Ok(id)
+    }
+
+    pub fn end(&mut self, id: u64) -> io::Result<()> {
+        self.open.remove(&id);
+        if self.open.is_empty() {
+            // Nothing to recover. Start over so that the file does not grow forever.
+            self.file = fs::File::create(&self.path)?;
+            return Ok(());
+        }
+        self.write(&Record::End(id))
+    }
+
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        let line = serde_json::to_string(record)?;
+        writeln!(self.file, "{}", line)
+    }
+}
+
+fn read_interrupted(reader: impl BufRead) -> Vec<Operation> {
+    let mut operations: Vec<Operation> = Vec::new();
+    for line in reader.lines().map_while(Result::ok) {
+        // The last line is cut short if the crash happened while writing it.
+        match serde_json::from_str(&line) {
+            Ok(Record::Begin(operation)) => operations.push(operation),
+            Ok(Record::End(id)) => operations.retain(|operation| operation.id != id),
+            Err(e) => log::warn!("Skipping a broken journal line: {}", e),
+        }
+    }
+    operations
+}
+
+// The journal of this process. File operations run on several threads, so it is shared.
+static JOURNAL: Mutex<Option<Journal>> = Mutex::new(None);
+
+/// Uses `journal` for the operations of this process.
+pub fn install(journal: Journal) {
+    *JOURNAL.lock().unwrap() = Some(journal);
+}
+
+/// Records the start of a copy in the installed journal. None if there is no journal, e.g. in
+/// tests. A failure to write it is logged and does not stop the copy.
+pub fn begin(src: &Path, dest: &Path) -> Option<u64> {
+    let mut journal = JOURNAL.lock().unwrap();
+    match journal.as_mut()?.begin(src, dest) {
+        Ok(id) => Some(id),
+        Err(e) => {
+            log::warn!("Failed to write the journal: {}", e);
+            None
+        }
+    }
+}
+
+pub fn end(id: Option<u64>) {
+    let Some(id) = id else {
+        return;
+    };
+    if let Some(journal) = JOURNAL.lock().unwrap().as_mut() {
+        if let Err(e) = journal.end(id) {
+            log::warn!("Failed to write the journal: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupted_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("journal.jsonl");
+        let (mut journal, interrupted) = Journal::open(&path).unwrap();
+        assert!(interrupted.is_empty());
+        let a = journal
+            .begin(Path::new("a.png"), Path::new("dest/a.png"))
+            .unwrap();
+        let b = journal
+            .begin(Path::new("b.png"), Path::new("dest/b.png"))
+            .unwrap();
+        journal.end(a).unwrap();
+        // The process dies here.
+        drop(journal);
+
+        let (journal, interrupted) = Journal::open(&path).unwrap();
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].id, b);
+        assert_eq!(interrupted[0].src, Path::new("b.png"));
+        // Still there until it is resolved, and written over the journal in one step.
+        assert!(!path.with_extension("tmp").exists());
+        drop(journal);
+        let (mut journal, interrupted) = Journal::open(&path).unwrap();
+        assert_eq!(interrupted.len(), 1);
+        journal.end(b).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"");
+    }
+
+    #[test]
+    fn repair_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let operation = Operation {
+            id: 0,
+            src: dir.join("a.png"),
+            dest: dir.join("b.png"),
+        };
+        fs::write(&operation.src, b"image").unwrap();
+        fs::write(file_ops::partial_path(&operation.dest), b"ima").unwrap();
+        assert_eq!(Interruption::of(&operation), Interruption::NotCopied);
+        repair(&operation).unwrap();
+        assert!(!file_ops::partial_path(&operation.dest).exists());
+        assert!(operation.src.exists());
+
+        fs::write(&operation.dest, b"image").unwrap();
+        assert_eq!(Interruption::of(&operation), Interruption::NotRemoved);
+        repair(&operation).unwrap();
+        assert!(!operation.src.exists());
+        assert_eq!(Interruption::of(&operation), Interruption::Done);
+
+        fs::write(&operation.src, b"other").unwrap();
+        assert_eq!(Interruption::of(&operation), Interruption::Conflict);
+        repair(&operation).unwrap();
+        assert!(operation.src.exists());
+    }
+}
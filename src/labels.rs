@@ -0,0 +1,284 @@
+use eframe::egui;
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::xmp;
+
+const LABEL_PROPERTY: &str = "xmp:Label";
+const RATING_PROPERTY: &str = "xmp:Rating";
+// Keywords, where Lightroom, darktable and digiKam keep tags.
+pub const SUBJECT_PROPERTY: &str = "dc:subject";
+
+/// Color labels for a quick first pass before images are moved. Stored as xmp:Label in XMP
+/// sidecars with the names Lightroom uses, so other tools see them too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorLabel {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ColorLabel {
+    pub const ALL: [ColorLabel; 5] = [
+        ColorLabel::Red,
+        ColorLabel::Yellow,
+        ColorLabel::Green,
+        ColorLabel::Blue,
+        ColorLabel::Purple,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorLabel::Red => "Red",
+            ColorLabel::Yellow => "Yellow",
+            ColorLabel::Green => "Green",
+            ColorLabel::Blue => "Blue",
+            ColorLabel::Purple => "Purple",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|label| label.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn color(self) -> egui::Color32 {
+        match self {
+            ColorLabel::Red => egui::Color32::from_rgb(230, 60, 60),
+            ColorLabel::Yellow => egui::Color32::from_rgb(240, 200, 40),
+            ColorLabel::Green => egui::Color32::from_rgb(60, 190, 80),
+            ColorLabel::Blue => egui::Color32::from_rgb(60, 120, 230),
+            ColorLabel::Purple => egui::Color32::from_rgb(160, 80, 210),
+        }
+    }
+
+    /// The label assigned with Ctrl and this number key.
+    pub fn from_key(key: egui::Key) -> Option<Self> {
+        let index = [
+            egui::Key::Num1,
+            egui::Key::Num2,
+            egui::Key::Num3,
+            egui::Key::Num4,
+            egui::Key::Num5,
+        ]
+        .iter()
+        .position(|k| *k == key)?;
+        Some(Self::ALL[index])
+    }
+}
+
+/// What the XMP sidecar of an image says, written by this app or by another photo manager.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SidecarInfo {
+    pub label: Option<ColorLabel>,
+    // 1 to 5 stars. Lightroom writes -1 for rejected images and 0 for none, which are both None.
+    pub rating: Option<u8>,
+    pub tags: Vec<String>,
+}
+
+pub fn parse_sidecar(xmp: &str) -> SidecarInfo {
+    SidecarInfo {
+        label: xmp::get_property(xmp, LABEL_PROPERTY)
+            .and_then(|label| ColorLabel::from_name(&label)),
+        rating: xmp::get_property(xmp, RATING_PROPERTY)
+            .and_then(|rating| rating.trim().parse::<u8>().ok())
+            .filter(|rating| (1..=5).contains(rating)),
+        tags: xmp::get_bag(xmp, SUBJECT_PROPERTY),
+    }
+}
+
+pub fn read_sidecar(image: &Path) -> Option<SidecarInfo> {
+    xmp::read(image).map(|xmp| parse_sidecar(&xmp))
+}
+
+pub fn write_label(image: &Path, label: Option<ColorLabel>) -> io::Result<()> {
+    xmp::write_property(
+        image,
+        xmp::XMP_NAMESPACE,
+        LABEL_PROPERTY,
+        label.map(ColorLabel::name),
+    )
+}
+
+pub fn write_rating(image: &Path, rating: Option<u8>) -> io::Result<()> {
+    let rating = rating.map(|rating| rating.to_string());
+    xmp::write_property(
+        image,
+        xmp::XMP_NAMESPACE,
+        RATING_PROPERTY,
+        rating.as_deref(),
+    )
+}
+
+pub fn write_tags(image: &Path, tags: &[String]) -> io::Result<()> {
+    xmp::write_bag(image, xmp::DC_NAMESPACE, SUBJECT_PROPERTY, tags)
+}
+
+/// Labels and star ratings of the queued images. They are read from the sidecars in the
+/// background.
+#[derive(Default)]
+pub struct Labels {
+    labels: HashMap<PathBuf, ColorLabel>,
+    ratings: HashMap<PathBuf, u8>,
+    receivers: Vec<mpsc::Receiver<Vec<(PathBuf, SidecarInfo)>>>,
+}
+
+impl Labels {
+    /// Reads the sidecars of `paths` on a background thread.
+    pub fn load(&mut self, paths: Vec<PathBuf>) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let found = paths
+                .into_iter()
+                .filter_map(|path| read_sidecar(&path).map(|info| (path, info)))
+                .collect();
+            let _ = sender.send(found);
+        });
+        self.receivers.push(receiver);
+    }
+
+    /// Collects the sidecars that were read since the last call. Returns the tags found in them,
+    /// which the caller keeps.
+    pub fn poll(&mut self) -> Vec<(PathBuf, Vec<String>)> {
+        let mut tags = Vec::new();
+        self.receivers.retain(|receiver| match receiver.try_recv() {
+            Ok(found) => {
+                for (path, info) in found {
+                    if let Some(label) = info.label {
+                        self.labels.insert(path.clone(), label);
+                    }
+                    if let Some(rating) = info.rating {
+                        self.ratings.insert(path.clone(), rating);
+                    }
+                    if !info.tags.is_empty() {
+                        tags.push((path, info.tags));
+                    }
+                }
+                false
+            }
+            Err(mpsc::TryRecvError::Empty) => true,
+            Err(mpsc::TryRecvError::Disconnected) => false,
+        });
+        tags
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.receivers.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.labels.clear();
+        self.ratings.clear();
+        self.receivers.clear();
+    }
+
+    pub fn get(&self, path: &Path) -> Option<ColorLabel> {
+        self.labels.get(path).copied()
+    }
+
+    pub fn all(&self) -> &HashMap<PathBuf, ColorLabel> {
+        &self.labels
+    }
+
+    /// Sets the label and writes it to the sidecar.
+    pub fn set(&mut self, path: &Path, label: Option<ColorLabel>) -> io::Result<()> {
+        write_label(path, label)?;
+        match label {
+            Some(label) => self.labels.insert(path.to_path_buf(), label),
+            None => self.labels.remove(path),
+        };
+        Ok(())
+    }
+
+    pub fn rating(&self, path: &Path) -> Option<u8> {
+        self.ratings.get(path).copied()
+    }
+
+    pub fn ratings(&self) -> &HashMap<PathBuf, u8> {
+        &self.ratings
+    }
+
+    /// Sets the rating and writes it to the sidecar.
+    pub fn set_rating(&mut self, path: &Path, rating: Option<u8>) -> io::Result<()> {
+        write_rating(path, rating)?;
+        match rating {
+            Some(rating) => self.ratings.insert(path.to_path_buf(), rating),
+            None => self.ratings.remove(path),
+        };
+        Ok(())
+    }
+
+    /// Keeps the label and rating of a moved image. The sidecar moves with the image.
+    pub fn rename(&mut self, src: &Path, dest: &Path) {
+        if let Some(label) = self.labels.remove(src) {
+            self.labels.insert(dest.to_path_buf(), label);
+        }
+        if let Some(rating) = self.ratings.remove(src) {
+            self.ratings.insert(dest.to_path_buf(), rating);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn labels_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let image = temp_dir.path().join("a.png");
+        fs::write(&image, b"").unwrap();
+        assert_eq!(read_sidecar(&image).and_then(|info| info.label), None);
+
+        let mut labels = Labels::default();
+        labels.set(&image, Some(ColorLabel::Green)).unwrap();
+        assert_eq!(
+            read_sidecar(&image).and_then(|info| info.label),
+            Some(ColorLabel::Green)
+        );
+        assert_eq!(labels.get(&image), Some(ColorLabel::Green));
+
+        let moved = temp_dir.path().join("b.png");
+        labels.rename(&image, &moved);
+        assert_eq!(labels.get(&image), None);
+        assert_eq!(labels.get(&moved), Some(ColorLabel::Green));
+
+        labels.set(&image, None).unwrap();
+        assert_eq!(read_sidecar(&image).and_then(|info| info.label), None);
+        assert_eq!(ColorLabel::from_name("purple"), Some(ColorLabel::Purple));
+    }
+
+    #[test]
+    fn sidecar_info_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let image = temp_dir.path().join("IMG_1.CR2");
+        fs::write(&image, b"").unwrap();
+        let mut labels = Labels::default();
+        labels.set(&image, Some(ColorLabel::Red)).unwrap();
+        labels.set_rating(&image, Some(4)).unwrap();
+        write_tags(&image, &["beach".to_string()]).unwrap();
+        assert_eq!(
+            read_sidecar(&image),
+            Some(SidecarInfo {
+                label: Some(ColorLabel::Red),
+                rating: Some(4),
+                tags: vec!["beach".to_string()],
+            })
+        );
+
+        // Lightroom marks rejected images with -1.
+        let rejected = r#"<rdf:Description rdf:about="" xmp:Rating="-1" xmp:Label="Purple"/>"#;
+        let info = parse_sidecar(rejected);
+        assert_eq!(info.rating, None);
+        assert_eq!(info.label, Some(ColorLabel::Purple));
+    }
+}
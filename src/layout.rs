@@ -0,0 +1,86 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+// Neither the image nor the destinations can be dragged out of sight.
+const MIN_IMAGE_FRACTION: f32 = 0.2;
+const MAX_IMAGE_FRACTION: f32 = 0.9;
+const SPLITTER_HEIGHT: f32 = 8.0;
+
+/// Proportions of the main window that were dragged into place. Persisted like the settings, so
+/// the window opens the way it was left. Its size and position are persisted by eframe.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    // Share of the height under the top bar that the image gets. The destinations get the rest.
+    pub image_fraction: f32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            image_fraction: 0.7,
+        }
+    }
+}
+
+impl Layout {
+    const STORAGE_KEY: &'static str = "layout";
+
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, Self::STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, Self::STORAGE_KEY, self);
+    }
+
+    /// Draws a handle between the image and the destinations. Dragging it moves the split of
+    /// `height`, and double-clicking puts it back.
+    pub fn splitter(&mut self, ui: &mut egui::Ui, height: f32) {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), SPLITTER_HEIGHT),
+            egui::Sense::click_and_drag(),
+        );
+        let response = response.on_hover_cursor(egui::CursorIcon::ResizeVertical);
+        if response.double_clicked() {
+            *self = Self::default();
+        } else if response.dragged() {
+            self.drag(response.drag_delta().y, height);
+        }
+        let stroke = if response.hovered() || response.dragged() {
+            ui.visuals().widgets.hovered.fg_stroke
+        } else {
+            ui.visuals().widgets.noninteractive.bg_stroke
+        };
+        ui.painter().hline(rect.x_range(), rect.center().y, stroke);
+    }
+
+    fn drag(&mut self, delta: f32, height: f32) {
+        if height <= 0.0 {
+            return;
+        }
+        self.image_fraction =
+            (self.image_fraction + delta / height).clamp(MIN_IMAGE_FRACTION, MAX_IMAGE_FRACTION);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drag_test() {
+        let mut layout = Layout::default();
+        layout.drag(-100.0, 1000.0);
+        assert!((layout.image_fraction - 0.6).abs() < 1e-6);
+        layout.drag(1000.0, 1000.0);
+        assert_eq!(layout.image_fraction, MAX_IMAGE_FRACTION);
+        layout.drag(-1000.0, 1000.0);
+        assert_eq!(layout.image_fraction, MIN_IMAGE_FRACTION);
+        // Before the window has a size.
+        layout.drag(10.0, 0.0);
+        assert_eq!(layout.image_fraction, MIN_IMAGE_FRACTION);
+    }
+}
@@ -0,0 +1,304 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{i18n::tr, metadata};
+
+// A log file is rotated once it would grow past this.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+// The current file and the rotated ones. The oldest is removed when another is rotated out.
+const MAX_FILES: usize = 5;
+// Records of this app at this level and above are written to the file. Other crates only write
+// warnings and errors, so that the file is not filled with the details of the GPU.
+const FILE_LEVEL: log::LevelFilter = log::LevelFilter::Info;
+const OTHER_CRATES_FILE_LEVEL: log::LevelFilter = log::LevelFilter::Warn;
+
+/// One line of the log file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    // Unix time in seconds.
+    pub time: i64,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+// "image-organizer.log" for `n` 0, then "image-organizer.1.log" and so on, older as `n` grows.
+fn file_path(dir: &Path, n: usize) -> PathBuf {
+    match n {
+        0 => dir.join("image-organizer.log"),
+        n => dir.join(format!("image-organizer.{}.log", n)),
+    }
+}
+
+/// The log file in a folder, rotated by size.
+pub struct RotatingFile {
+    dir: PathBuf,
+    file: fs::File,
+    size: u64,
+    max_size: u64,
+}
+
+impl RotatingFile {
+    /// Opens the current file in `dir` to append to it, creating the folder if needed.
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        Self::with_max_size(dir, MAX_FILE_SIZE)
+    }
+
+    fn with_max_size(dir: &Path, max_size: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path(dir, 0))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+            max_size,
+        })
+    }
+
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let oldest = file_path(&self.dir, MAX_FILES - 1);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for n in (0..MAX_FILES - 1).rev() {
+            let path = file_path(&self.dir, n);
+            if path.exists() {
+                fs::rename(path, file_path(&self.dir, n + 1))?;
+            }
+        }
+        *self = Self::with_max_size(&self.dir, self.max_size)?;
+        Ok(())
+    }
+}
+
+/// The records of all log files in `dir`, oldest first. Lines that are not records are skipped.
+pub fn read_records(dir: &Path) -> Vec<Record> {
+    (0..MAX_FILES)
+        .rev()
+        .filter_map(|n| fs::File::open(file_path(dir, n)).ok())
+        .flat_map(|file| io::BufReader::new(file).lines().map_while(Result::ok))
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+// The log file of this process, while writing to it is on.
+static FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+
+// Writes to stderr as configured with RUST_LOG, and to the log file.
+struct Logger {
+    stderr: env_logger::Logger,
+}
+
+fn file_level(target: &str) -> log::LevelFilter {
+    match target.starts_with(env!("CARGO_CRATE_NAME")) {
+        true => FILE_LEVEL,
+        false => OTHER_CRATES_FILE_LEVEL,
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.stderr.enabled(metadata) || metadata.level() <= file_level(metadata.target())
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.stderr.log(record);
+        if record.level() > file_level(record.target()) {
+            return;
+        }
+        // A record logged while writing to the file, e.g. by serde, would wait for itself.
+        let Ok(mut file) = FILE.try_lock() else {
+            return;
+        };
+        let Some(file) = file.as_mut() else {
+            return;
+        };
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64);
+        let record = Record {
+            time,
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        if let Err(e) = file.write(&record) {
+            eprintln!("Failed to write the log file: {}", e);
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+/// Installs the logger. Replaces `env_logger::init()`: RUST_LOG still sets what is written to
+/// stderr. The log file is written once `set_file_logging()` turns it on.
+pub fn init() {
+    let stderr = env_logger::Builder::from_default_env().build();
+    let max_level = stderr.filter().max(FILE_LEVEL);
+    if log::set_boxed_logger(Box::new(Logger { stderr })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Starts or stops writing the log file in `dir`.
+pub fn set_file_logging(dir: &Path, enabled: bool) {
+    let mut file = FILE.lock().unwrap();
+    if !enabled {
+        *file = None;
+        return;
+    }
+    if file.is_some() {
+        return;
+    }
+    match RotatingFile::open(dir) {
+        Ok(opened) => *file = Some(opened),
+        Err(e) => {
+            drop(file);
+            log::error!("Failed to open the log file in {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// The log window: the records of the log files, newest first, narrowed down by a search.
+#[derive(Default)]
+pub struct LogViewer {
+    records: Vec<Record>,
+    search: String,
+    warnings_only: bool,
+}
+
+impl LogViewer {
+    /// Reads the log files again.
+    pub fn load(&mut self, dir: &Path) {
+        self.records = read_records(dir);
+    }
+
+    fn matches(&self, record: &Record) -> bool {
+        if self.warnings_only && !matches!(record.level.as_str(), "WARN" | "ERROR") {
+            return false;
+        }
+        let search = self.search.to_lowercase();
+        search.is_empty() || record.message.to_lowercase().contains(&search)
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, dir: &Path) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.search).hint_text(tr!("search-log-hint")));
+            ui.checkbox(&mut self.warnings_only, tr!("warnings-only"));
+            if ui.button(tr!("reload")).clicked() {
+                self.load(dir);
+            }
+            if ui.button(tr!("open-folder")).clicked() {
+                if let Err(e) = open::that(dir) {
+                    log::error!("Failed to open {}: {}", dir.display(), e);
+                }
+            }
+        });
+        ui.separator();
+        let records: Vec<&Record> = self
+            .records
+            .iter()
+            .rev()
+            .filter(|record| self.matches(record))
+            .collect();
+        if records.is_empty() {
+            ui.weak(tr!("nothing-logged"));
+            return;
+        }
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::vertical().show_rows(ui, row_height, records.len(), |ui, rows| {
+            for record in &records[rows] {
+                ui.horizontal(|ui| {
+                    ui.monospace(metadata::format_timestamp(record.time));
+                    let level = egui::RichText::new(&record.level).monospace();
+                    match record.level.as_str() {
+                        "ERROR" => ui.label(level.color(ui.visuals().error_fg_color)),
+                        "WARN" => ui.label(level.color(ui.visuals().warn_fg_color)),
+                        _ => ui.label(level),
+                    };
+                    ui.label(&record.message).on_hover_text(&record.target);
+                });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(message: &str) -> Record {
+        Record {
+            time: 86400,
+            level: "INFO".to_string(),
+            target: "image_organizer".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn rotating_file_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("logs");
+        let line_size = serde_json::to_string(&record("a")).unwrap().len() as u64 + 1;
+        // Two records a file.
+        let mut file = RotatingFile::with_max_size(&dir, 2 * line_size).unwrap();
+        for message in "abcdefghijkl".chars() {
+            file.write(&record(&message.to_string())).unwrap();
+        }
+        assert!(file_path(&dir, MAX_FILES - 1).exists());
+        assert!(!file_path(&dir, MAX_FILES).exists());
+        // The oldest files were removed.
+        let messages: Vec<String> = read_records(&dir)
+            .into_iter()
+            .map(|record| record.message)
+            .collect();
+        assert_eq!(messages, ["c", "d", "e", "f", "g", "h", "i", "j", "k", "l"]);
+
+        // Appends to the current file when opened again.
+        let mut file = RotatingFile::with_max_size(&dir, 4 * line_size).unwrap();
+        file.write(&record("m")).unwrap();
+        assert_eq!(read_records(&dir).last().unwrap().message, "m");
+        assert_eq!(read_records(&dir).len(), 11);
+    }
+
+    #[test]
+    fn read_records_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        assert!(read_records(dir).is_empty());
+        fs::write(
+            file_path(dir, 0),
+            format!(
+                "{}\nnot a record\n",
+                serde_json::to_string(&record("moved")).unwrap()
+            ),
+        )
+        .unwrap();
+        assert_eq!(read_records(dir), vec![record("moved")]);
+    }
+}
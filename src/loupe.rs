@@ -0,0 +1,283 @@
+use eframe::egui;
+use image::RgbaImage;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{archive, pages, raw};
+
+// Pixels on each side of the patch. Odd, so that the pixel under the pointer is in the middle.
+const PATCH_PIXELS: u32 = 15;
+
+// How large each pixel of the patch is drawn.
+const MAGNIFICATION: f32 = 12.0;
+
+// Between the pointer and the loupe, so that the loupe does not cover what it magnifies.
+const POINTER_GAP: f32 = 24.0;
+
+type DecodeResult = Result<RgbaImage, String>;
+
+// A page and the image it decodes to.
+type Key = (PathBuf, usize);
+
+fn decode(path: &Path, page: usize) -> DecodeResult {
+    let image = if page == 0 {
+        raw::decode_file(path)?
+    } else {
+        let bytes = archive::read(path).map_err(|e| e.to_string())?;
+        pages::decode_page(&bytes, page)?
+    };
+    Ok(image.to_rgba8())
+}
+
+/// The pixel of an image `image_size` pixels large, drawn `rect_size` large, at `offset` from its
+/// top left corner. None outside of the image.
+fn pixel_at(
+    image_size: (u32, u32),
+    rect_size: egui::Vec2,
+    offset: egui::Vec2,
+) -> Option<(u32, u32)> {
+    if offset.x < 0.0 || offset.y < 0.0 || offset.x >= rect_size.x || offset.y >= rect_size.y {
+        return None;
+    }
+    let x = (offset.x / rect_size.x * image_size.0 as f32) as u32;
+    let y = (offset.y / rect_size.y * image_size.1 as f32) as u32;
+    Some((x.min(image_size.0 - 1), y.min(image_size.1 - 1)))
+}
+
+/// The pixels around (`x`, `y`) of `image`. Those beyond its edges are transparent.
+fn patch(image: &RgbaImage, x: u32, y: u32) -> egui::ColorImage {
+    let half = (PATCH_PIXELS / 2) as i64;
+    let pixels = (-half..=half)
+        .flat_map(|dy| (-half..=half).map(move |dx| (i64::from(x) + dx, i64::from(y) + dy)))
+        .map(|(x, y)| {
+            let inside = (0..i64::from(image.width())).contains(&x)
+                && (0..i64::from(image.height())).contains(&y);
+            if !inside {
+                return egui::Color32::TRANSPARENT;
+            }
+            let [r, g, b, a] = image.get_pixel(x as u32, y as u32).0;
+            egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+        })
+        .collect();
+    egui::ColorImage {
+        size: [PATCH_PIXELS as usize; 2],
+        pixels,
+    }
+}
+
+/// The color of a pixel as it is written in CSS and design tools. The alpha is left out when the
+/// pixel is opaque.
+fn describe_color([r, g, b, a]: [u8; 4]) -> String {
+    if a == 255 {
+        format!("RGB {} {} {}   #{:02X}{:02X}{:02X}", r, g, b, r, g, b)
+    } else {
+        format!(
+            "RGBA {} {} {} {}   #{:02X}{:02X}{:02X}{:02X}",
+            r, g, b, a, r, g, b, a
+        )
+    }
+}
+
+struct Patch {
+    pixel: (u32, u32),
+    texture: egui::TextureHandle,
+}
+
+/// A magnified patch of the image under the pointer with the color of the pixel there. The image
+/// is decoded again at full resolution on a background thread, as egui only keeps the texture on
+/// the GPU, and only while the loupe is used.
+#[derive(Default)]
+pub struct Loupe {
+    pending: Option<(Key, mpsc::Receiver<DecodeResult>)>,
+    image: Option<(Key, RgbaImage)>,
+    // The image that failed to decode and why.
+    error: Option<(Key, String)>,
+    patch: Option<Patch>,
+}
+
+impl Loupe {
+    /// Decodes `page` of `path` unless it already is. Call every frame while the loupe is shown.
+    fn update(&mut self, ctx: &egui::Context, path: &Path, page: usize) {
+        if let Some((key, receiver)) = &self.pending {
+            match receiver.try_recv() {
+                Ok(Ok(image)) => {
+                    self.image = Some((key.clone(), image));
+                    self.patch = None;
+                    self.pending = None;
+                }
+                Ok(Err(e)) => {
+                    self.error = Some((key.clone(), e));
+                    self.pending = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    ctx.request_repaint_after(std::time::Duration::from_millis(50))
+                }
+                Err(mpsc::TryRecvError::Disconnected) => self.pending = None,
+            }
+        }
+        let key = (path.to_path_buf(), page);
+        let decoded = self.image.as_ref().map(|(key, _)| key);
+        let decoding = self.pending.as_ref().map(|(key, _)| key);
+        let failed = self.error.as_ref().map(|(key, _)| key);
+        if [decoded, decoding, failed].contains(&Some(&key)) {
+            return;
+        }
+        // Only one image is kept, and a newer request replaces the pending one.
+        self.image = None;
+        self.patch = None;
+        let (sender, receiver) = mpsc::channel();
+        let thread_path = path.to_path_buf();
+        thread::spawn(move || {
+            let _ = sender.send(decode(&thread_path, page));
+        });
+        self.pending = Some((key, receiver));
+        self.error = None;
+    }
+
+    /// Shows the loupe for `page` of `path`, drawn over `rect`, if the pointer is over it.
+    pub fn show(&mut self, ui: &egui::Ui, rect: egui::Rect, path: &Path, page: usize) {
+        let visible = rect.intersect(ui.clip_rect());
+        let Some(pointer) = ui
+            .ctx()
+            .pointer_hover_pos()
+            .filter(|pos| visible.contains(*pos))
+        else {
+            return;
+        };
+        self.update(ui.ctx(), path, page);
+        let painter = ui.ctx().layer_painter(egui::LayerId::new(
+            egui::Order::Tooltip,
+            egui::Id::new("loupe"),
+        ));
+        let side = PATCH_PIXELS as f32 * MAGNIFICATION;
+        let text_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let size = egui::vec2(side, side + 2.0 * text_height + 8.0);
+        // Beside the pointer, on the side where it fits.
+        let screen = ui.ctx().screen_rect();
+        let mut min = pointer + egui::vec2(POINTER_GAP, POINTER_GAP);
+        if min.x + size.x > screen.right() {
+            min.x = pointer.x - POINTER_GAP - size.x;
+        }
+        if min.y + size.y > screen.bottom() {
+            min.y = pointer.y - POINTER_GAP - size.y;
+        }
+        let frame = egui::Rect::from_min_size(min, size);
+        painter.rect_filled(frame.expand(2.0), 4.0, ui.visuals().extreme_bg_color);
+        let patch_rect = egui::Rect::from_min_size(frame.min, egui::vec2(side, side));
+        let text_pos = patch_rect.left_bottom() + egui::vec2(4.0, 4.0);
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let text_color = ui.visuals().strong_text_color();
+
+        if let Some((_, error)) = &self.error {
+            painter.text(
+                text_pos,
+                egui::Align2::LEFT_TOP,
+                error,
+                font,
+                ui.visuals().error_fg_color,
+            );
+            return;
+        }
+        let Some((_, image)) = &self.image else {
+            painter.text(
+                patch_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "…",
+                font,
+                text_color,
+            );
+            return;
+        };
+        let Some((x, y)) = pixel_at(image.dimensions(), rect.size(), pointer - rect.min) else {
+            return;
+        };
+        if self.patch.as_ref().map(|patch| patch.pixel) != Some((x, y)) {
+            let texture =
+                ui.ctx()
+                    .load_texture("loupe", patch(image, x, y), egui::TextureOptions::NEAREST);
+            self.patch = Some(Patch {
+                pixel: (x, y),
+                texture,
+            });
+        }
+        let Some(patch) = &self.patch else {
+            return;
+        };
+        painter.image(
+            patch.texture.id(),
+            patch_rect,
+            egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+        let center = egui::Rect::from_center_size(
+            patch_rect.center(),
+            egui::vec2(MAGNIFICATION, MAGNIFICATION),
+        );
+        painter.rect_stroke(center, 0.0, egui::Stroke::new(1.0, egui::Color32::WHITE));
+        painter.rect_stroke(
+            center.expand(1.0),
+            0.0,
+            egui::Stroke::new(1.0, egui::Color32::BLACK),
+        );
+        painter.text(
+            text_pos,
+            egui::Align2::LEFT_TOP,
+            format!("{}, {}\n{}", x, y, describe_color(image.get_pixel(x, y).0)),
+            font,
+            text_color,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_at_test() {
+        let rect = egui::vec2(200.0, 100.0);
+        // Drawn at half its size.
+        assert_eq!(
+            pixel_at((400, 200), rect, egui::vec2(0.0, 0.0)),
+            Some((0, 0))
+        );
+        assert_eq!(
+            pixel_at((400, 200), rect, egui::vec2(100.5, 50.0)),
+            Some((201, 100))
+        );
+        assert_eq!(
+            pixel_at((400, 200), rect, egui::vec2(199.9, 99.9)),
+            Some((399, 199))
+        );
+        assert_eq!(pixel_at((400, 200), rect, egui::vec2(200.0, 50.0)), None);
+        assert_eq!(pixel_at((400, 200), rect, egui::vec2(-1.0, 50.0)), None);
+    }
+
+    #[test]
+    fn patch_test() {
+        let mut image = RgbaImage::new(3, 3);
+        image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 1, image::Rgba([0, 255, 0, 255]));
+        let patch = patch(&image, 0, 0);
+        assert_eq!(patch.size, [PATCH_PIXELS as usize; 2]);
+        let middle = (PATCH_PIXELS / 2) as usize;
+        let at = |x: usize, y: usize| patch.pixels[y * PATCH_PIXELS as usize + x];
+        assert_eq!(at(middle, middle), egui::Color32::RED);
+        // Above and to the left of the image.
+        assert_eq!(at(middle - 1, middle), egui::Color32::TRANSPARENT);
+        assert_eq!(at(middle, middle - 1), egui::Color32::TRANSPARENT);
+        assert_eq!(at(middle + 1, middle + 1), egui::Color32::GREEN);
+    }
+
+    #[test]
+    fn describe_color_test() {
+        assert_eq!(
+            describe_color([255, 128, 0, 255]),
+            "RGB 255 128 0   #FF8000"
+        );
+        assert_eq!(describe_color([1, 2, 3, 4]), "RGBA 1 2 3 4   #01020304");
+    }
+}
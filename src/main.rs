@@ -1,52 +1,525 @@
+use ab_glyph::FontArc;
 use eframe::egui;
-use egui::{FontData, FontDefinitions, FontFamily, SizeHint};
+use egui::{load::ImagePoll, FontData, FontDefinitions, FontFamily, SizeHint};
 use rfd::FileDialog;
 use rust_embed::Embed;
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     fs,
     hash::Hash,
+    io,
+    ops::Range,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{bail, Result};
 
+mod accessibility;
+mod albums;
+mod app_dirs;
+mod archive;
+mod audit;
+mod batch;
+mod burst;
+mod color;
+mod command_palette;
+mod contact_sheet;
+mod demo;
+mod desktop;
+mod discard;
+mod faces;
+mod file_ops;
+mod filmstrip;
+mod flatten;
+mod geo;
+mod goals;
+mod hdr;
+mod heif;
+mod histogram;
+mod i18n;
+mod ignore;
+mod image_loader;
+mod index;
+mod instance;
+mod integrity;
+mod journal;
+mod labels;
+mod layout;
+mod logging;
+mod loupe;
+mod merge;
+mod metadata;
+mod organize;
+mod pages;
+mod palette;
+mod pdf;
+mod plan;
+mod practice;
+mod properties;
+mod quarantine;
+mod raw;
+mod recovery;
+mod remote;
+mod replay;
+mod s3;
+mod scanner;
+mod screenshot;
+mod scripting;
+mod settings;
+mod sharpness;
+mod sidecar;
+mod status;
+mod storage;
+mod sync;
+mod tags;
+mod theme;
+mod thumbnails;
+mod timeline;
+mod tray;
+mod wizard;
+mod xmp;
+
+use albums::{AlbumAction, AlbumTree, DestinationMark};
+use audit::Stamp;
+use batch::{Batch, OnFile};
+use command_palette::{CommandPalette, PaletteItem};
+use contact_sheet::ContactSheetTool;
+use discard::{DiscardAction, DiscardReview};
+use faces::{FaceAction, FaceModelPaths, FaceTool};
+use file_ops::{FileStamp, RetryPolicy, RetryQueue};
+use filmstrip::{Filmstrip, FilmstripClick, RecentMove};
+use flatten::{FlattenJob, FlattenReport, FlattenTool};
+use geo::{LocationAction, LocationTool};
+use goals::{GoalEditor, SessionGoal};
+use histogram::HistogramView;
+use i18n::tr;
+use image_loader::DownscalingLoader;
+use index::{IndexUpdate, LibraryIndex};
+use integrity::IntegrityScan;
+use labels::{ColorLabel, Labels};
+use layout::Layout;
+use loupe::Loupe;
+use merge::{MergeJob, MergeReport, MergeTool};
+use metadata::{ColorScan, DateScan, ImageMetadata, SizeScan};
+use organize::{OrganizeAction, OrganizeTool};
+use practice::{Practice, PracticeAction};
+use quarantine::{QuarantineAction, QuarantineTool};
+use replay::{ReplayAction, ReplayTool};
+use scanner::FolderScan;
+use screenshot::{ScreenshotAction, ScreenshotTool};
+use settings::{AfterMove, CollisionPolicy, Settings, SortOrder, SymlinkPolicy};
+use sharpness::{BlurAction, BlurTool};
+use status::StatusLog;
+use storage::StorageTool;
+use sync::{SyncAction, SyncReport, SyncTool};
+use tags::{TagAction, TagEditor};
+use thumbnails::{ThumbnailStore, Thumbnails};
+use timeline::{Timeline, TimelineAction};
+use wizard::{Wizard, WizardAction};
+
 #[derive(Embed)]
 #[folder = "fonts"]
 struct FontAsset;
 
+// The color a destination gets when "Color" is checked, until another one is picked.
+const DEFAULT_DESTINATION_COLOR: [u8; 3] = [230, 80, 60];
+
+// Folders of the albums folder offered in the palette at most, to keep a huge tree from
+// stalling it.
+const PALETTE_FOLDER_LIMIT: usize = 5000;
+
+// How long a destination that wants its letter pressed twice waits for the second press.
+const DOUBLE_PRESS_WINDOW: Duration = Duration::from_secs(1);
+
+// How long the border flashes in the color of the destination an image was moved to.
+const MOVE_FLASH_DURATION: Duration = Duration::from_millis(600);
+
 #[derive(Clone, Debug)]
 struct FolderLetterEntry {
-    folder: String,
+    folder: PathBuf,
     letter: char,
+    // Ask before moving there, e.g. for a folder of images to delete.
+    confirm: bool,
+    // Add a hard link there instead of moving, so that an image can be in several albums.
+    link: bool,
+    // Gets a copy when the letter is pressed. The image is moved to the destination with the
+    // same letter that does not copy, e.g. archived to a NAS and filed into a local album.
+    copy: bool,
+    // Shown next to the destination and flashed around the window when an image is moved there,
+    // to see at a glance that the right key was pressed.
+    color: Option<[u8; 3]>,
+    // An emoji or a few letters shown next to the destination.
+    icon: String,
+    // The letter must be pressed twice in a row, e.g. for a folder of images to delete.
+    double_press: bool,
 }
 
 #[derive(Clone, Debug, Default)]
 struct MoveLogEntry {
     // Original source file path.
-    src: String,
+    src: PathBuf,
     // Where the file was moved. Full path (i.e. not just destination dir).
-    dest: String,
+    dest: PathBuf,
+    // (src, dest) of files moved together with the image, e.g. the RAW file of a RAW+JPEG pair
+    // and sidecar files. Undo restores them too.
+    companions: Vec<(PathBuf, PathBuf)>,
+    // The files stayed where they were, and `dest` is a hard link or a copy. Undo removes it.
+    linked: bool,
+    // Copies made to other destinations of the same letter. Undo removes them too.
+    copies: Vec<PathBuf>,
+    // What was done, when, and the size and hash of the file, for the audit trail.
+    stamp: Stamp,
+}
+
+// What a multi-file operation on a worker thread reports when it finishes.
+enum BatchReport {
+    Move(MoveReport),
+    Merge(MergeReport),
+    Flatten(FlattenReport),
+    Organize(MoveReport),
+    Sync(SyncReport),
+}
+
+#[derive(Debug, Default)]
+struct MoveReport {
+    dest_dir: PathBuf,
+    moved: Vec<MoveLogEntry>,
+    errors: Vec<(PathBuf, String)>,
+}
+
+// Moves the image `src` to `dest_dir`. A symbolic link is moved as a link, unless `symlinks`
+// says to move the file it points to. Then the link is removed, as it would point nowhere.
+// An image in an archive is extracted, as archives are only read. Transient errors are retried
+// as `policy` says.
+fn move_image(
+    src: &Path,
+    dest_dir: &Path,
+    symlinks: SymlinkPolicy,
+    policy: &RetryPolicy,
+) -> io::Result<PathBuf> {
+    if archive::split(src).is_some() {
+        return archive::extract(src, dest_dir);
+    }
+    if symlinks == SymlinkPolicy::Follow && file_ops::is_symlink(src) {
+        let target = fs::canonicalize(src)?;
+        let target = file_ops::simplified(&target);
+        let dest = file_ops::move_file_with_retry(&target, dest_dir, policy)?;
+        if let Err(e) = fs::remove_file(src) {
+            log::warn!("Failed to remove the link {}: {}", src.display(), e);
+        }
+        return Ok(dest);
+    }
+    file_ops::move_file_with_retry(src, dest_dir, policy)
+}
+
+// Checks that `src` is still the file that was listed, with `listed` if it was scanned. Images
+// in archives are only read, so there is nothing to check.
+fn check_source(src: &Path, listed: Option<&FileStamp>) -> io::Result<()> {
+    if archive::split(src).is_some() {
+        return Ok(());
+    }
+    file_ops::check_unchanged(src, listed)
+}
+
+// Moves each image of `files` to `dest_dir` with the companion files listed with it, or with
+// `link` adds hard links to them there. An image that changed since `stamps` were taken is left
+// where it is. A failed companion is reported but does not undo the move of the image itself.
+// Stops early when `on_file` says so.
+fn move_batch(
+    files: &[(PathBuf, Vec<PathBuf>)],
+    dest_dir: &Path,
+    stamps: &HashMap<PathBuf, FileStamp>,
+    symlinks: SymlinkPolicy,
+    link: bool,
+    on_file: &mut OnFile,
+) -> MoveReport {
+    let mut report = MoveReport {
+        dest_dir: dest_dir.to_path_buf(),
+        ..Default::default()
+    };
+    // A companion follows the image, under its new name if it got one.
+    let transfer = |companion: &Path, image: &Path, image_dest: &Path| {
+        if link {
+            file_ops::link_or_copy(companion, dest_dir)
+        } else {
+            file_ops::move_companion(companion, image, image_dest, &RetryPolicy::default())
+        }
+    };
+    for (src, companions) in files {
+        let result = check_source(src, stamps.get(src)).and_then(|()| {
+            if link && archive::split(src).is_none() {
+                file_ops::link_or_copy(src, dest_dir)
+            } else {
+                move_image(src, dest_dir, symlinks, &RetryPolicy::default())
+            }
+        });
+        let result = match result {
+            Ok(dest) => {
+                let mut moved_companions = Vec::new();
+                for companion in companions {
+                    match transfer(companion, src, &dest) {
+                        Ok(dest) => moved_companions.push((companion.clone(), dest)),
+                        Err(e) => report.errors.push((companion.clone(), e.to_string())),
+                    }
+                }
+                let operation = match link {
+                    true => audit::Operation::Copy,
+                    false => audit::Operation::Move,
+                };
+                report.moved.push(MoveLogEntry {
+                    src: src.clone(),
+                    stamp: Stamp::of(operation, &dest),
+                    dest,
+                    companions: moved_companions,
+                    linked: link,
+                    copies: Vec::new(),
+                });
+                Ok(())
+            }
+            Err(e) => {
+                report.errors.push((src.clone(), e.to_string()));
+                Err(e.to_string())
+            }
+        };
+        if !on_file(src, result) {
+            break;
+        }
+    }
+    report
+}
+
+// Moves each image with its companion files into its own folder, which is created if needed.
+// Reported as one move into `destination`, the folder above them all.
+fn organize_batch(
+    moves: &[(PathBuf, Vec<PathBuf>, PathBuf)],
+    destination: &Path,
+    stamps: &HashMap<PathBuf, FileStamp>,
+    symlinks: SymlinkPolicy,
+    on_file: &mut OnFile,
+) -> MoveReport {
+    let mut folders: BTreeMap<&Path, Vec<(PathBuf, Vec<PathBuf>)>> = BTreeMap::new();
+    for (src, companions, dest_dir) in moves {
+        folders
+            .entry(dest_dir)
+            .or_default()
+            .push((src.clone(), companions.clone()));
+    }
+    let mut report = MoveReport {
+        dest_dir: destination.to_path_buf(),
+        ..Default::default()
+    };
+    for (dest_dir, files) in folders {
+        if let Err(e) = fs::create_dir_all(file_ops::long_path(dest_dir)) {
+            for (src, _) in &files {
+                report.errors.push((src.clone(), e.to_string()));
+                if !on_file(src, Err(e.to_string())) {
+                    return report;
+                }
+            }
+            continue;
+        }
+        let mut go_on = true;
+        let folder_report = move_batch(
+            &files,
+            dest_dir,
+            stamps,
+            symlinks,
+            false,
+            &mut |path, result| {
+                go_on = on_file(path, result);
+                go_on
+            },
+        );
+        report.moved.extend(folder_report.moved);
+        report.errors.extend(folder_report.errors);
+        if !go_on {
+            break;
+        }
+    }
+    report
+}
+
+// What the user chose to do with an image that could not be decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BrokenImageAction {
+    Skip,
+    Delete,
+    Quarantine,
+}
+
+// What the user chose in the context menu of the image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ImageMenuAction {
+    MoveTo(PathBuf),
+    Keep,
+    CopyPath,
+    OpenExternally,
+    Reveal,
+    Rename,
+    Delete,
+}
+
+// What an entry of the command palette does.
+#[derive(Clone, Debug, PartialEq)]
+enum PaletteTarget {
+    // Move the current image there.
+    Folder(PathBuf),
+    Command(Command),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Command {
+    Undo,
+    Rename,
+    OpenFolder,
+    Rescan,
+    NewAlbum,
+    Shortcuts,
+    Toggle(Toggle),
+    FindDuplicates,
+    FilterLabel(ColorLabel),
+    FilterRating(u8),
+    ShowAll,
+    Practice,
+    Open(Tool),
+}
+
+// The windows and modes that the palette turns on and off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Toggle {
+    Settings,
+    Grid,
+    Timeline,
+    Info,
+    Histogram,
+    Library,
+    History,
+    ReadOnly,
+    Lock,
+    AlbumTree,
+    Properties,
+}
+
+// The windows of the tools menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tool {
+    Merge,
+    Flatten,
+    Organize,
+    Locations,
+    Blur,
+    Screenshots,
+    Quarantine,
+    Discard,
+    Faces,
+    Storage,
+    ContactSheet,
+    Sync,
+    MoveLog,
+    Audit,
+    Log,
+    RevertSession,
+}
+
+// An operation that waits for the user to confirm it.
+#[derive(Clone, Debug)]
+enum PendingConfirmation {
+    // Of the image `src` to a destination that asks first.
+    Move {
+        src: PathBuf,
+        dest_dir: PathBuf,
+    },
+    // Of more images than the settings allow without asking.
+    MoveImages {
+        paths: Vec<PathBuf>,
+        dest_dir: PathBuf,
+    },
+    // Of the image `src` to a destination that already has `existing`, a file with the same
+    // contents.
+    Identical {
+        src: PathBuf,
+        dest_dir: PathBuf,
+        existing: PathBuf,
+    },
+}
+
+// Practice mode and the destinations and folders it replaced, which come back when it ends.
+struct PracticeSession {
+    practice: Practice,
+    entries: Vec<FolderLetterEntry>,
+    folders: Vec<PathBuf>,
+}
+
+// A move that failed and is waiting for the user to retry or skip it.
+#[derive(Clone, Debug)]
+struct FailedMove {
+    src: PathBuf,
+    dest_dir: PathBuf,
+    error: String,
+}
+
+// "Revert all moves" of the session. A few moves are undone each frame, newest first, so that
+// the window can show the progress.
+#[derive(Debug, Default)]
+struct SessionRevert {
+    total: usize,
+    restored: usize,
+    // The source of each move that could not be undone, and why.
+    failed: Vec<(PathBuf, String)>,
+    // Moves that were left as they are when it was cancelled.
+    left: usize,
+    // Index in the move log of the move undone next. The moves that could not be undone stay
+    // on the log after it.
+    next: usize,
+    finished: bool,
+}
+
+// What was done since the folder was opened.
+#[derive(Debug, Default)]
+struct SessionStats {
+    // Out of their folder, so renames do not count.
+    moved: usize,
+    deleted: usize,
+    started: Option<Instant>,
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
 struct ImagePath {
-    path: String,
+    path: PathBuf,
 }
 
 impl ImagePath {
-    fn new(path: &str) -> Self {
+    fn new(path: &Path) -> Self {
         Self {
-            path: path.to_string(),
+            path: path.to_path_buf(),
         }
     }
 
-    fn path(&self) -> &str {
+    fn path(&self) -> &Path {
         &self.path
     }
 
+    // egui identifies images by URI strings. Paths that are not valid UTF-8 cannot be expressed
+    // as a file:// URI that egui's file loader understands, so those are loaded from bytes
+    // instead. So are images in an archive. See `Loader::add()`.
     fn uri(&self) -> String {
-        format!("file://{}", self.path)
+        match self.path.to_str() {
+            Some(path) if !self.is_in_archive() => format!("file://{}", path),
+            _ => format!("bytes://{}", self.path.to_string_lossy()),
+        }
+    }
+
+    fn is_utf8(&self) -> bool {
+        self.path.to_str().is_some()
+    }
+
+    fn is_in_archive(&self) -> bool {
+        archive::split(&self.path).is_some()
     }
 }
 
@@ -56,6 +529,12 @@ impl ImagePath {
 struct Loader {
     image_paths: HashSet<ImagePath>,
     context: egui::Context,
+    // Decoded size of the images in bytes. Remembered after an image is unloaded so that the
+    // memory budget does not keep loading and evicting the same image.
+    sizes: HashMap<PathBuf, usize>,
+    // When each image was last displayed, as a value of `view_clock`.
+    last_viewed: HashMap<PathBuf, u64>,
+    view_clock: u64,
 }
 
 // This struct does not provide a "remove" method as it makes it easy to unload images that
@@ -69,19 +548,25 @@ impl Loader {
     }
 
     /// Add a new image to be loaded.
-    fn add(&mut self, path: &str) -> egui::Image {
+    fn add(&mut self, path: &Path) -> egui::Image<'_> {
         let image_path = ImagePath::new(path);
         if self.image_paths.insert(image_path.clone()) {
             log::info!(
                 "Added image. Number of Loaded images: {}",
                 self.image_paths.len()
             );
+            if !image_path.is_utf8() || image_path.is_in_archive() {
+                match archive::read(image_path.path()) {
+                    Ok(bytes) => self.context.include_bytes(image_path.uri(), bytes),
+                    Err(e) => log::error!("Failed to read {}: {}", path.display(), e),
+                }
+            }
             match self
                 .context
                 .try_load_image(&image_path.uri(), SizeHint::default())
             {
                 Ok(_) => {
-                    log::info!("Loading image: {}", image_path.path());
+                    log::info!("Loading image: {}", image_path.path().display());
                 }
                 Err(e) => {
                     log::error!("Failed to load image: {}", e);
@@ -91,18 +576,124 @@ impl Loader {
         egui::Image::from_uri(image_path.uri())
     }
 
+    /// The image at `path` without downscaling. It is not tracked by this loader, so call
+    /// `forget_full_resolution()` when it is no longer displayed.
+    fn full_resolution(&self, path: &Path) -> egui::Image<'static> {
+        let uri = ImagePath::new(path).uri();
+        egui::Image::from_uri(image_loader::full_resolution_uri(&uri))
+    }
+
+    fn mark_viewed(&mut self, path: &Path) {
+        self.view_clock += 1;
+        self.last_viewed.insert(path.to_path_buf(), self.view_clock);
+    }
+
+    /// Records the size of images that finished loading since the last call.
+    fn update_sizes(&mut self) {
+        for image_path in &self.image_paths {
+            if self.sizes.contains_key(image_path.path()) {
+                continue;
+            }
+            if let Ok(ImagePoll::Ready { image }) = self
+                .context
+                .try_load_image(&image_path.uri(), SizeHint::default())
+            {
+                let size = image.pixels.len() * std::mem::size_of::<egui::Color32>();
+                self.sizes.insert(image_path.path().to_path_buf(), size);
+            }
+        }
+    }
+
+    /// Approximate memory used by the loaded images.
+    fn loaded_bytes(&self) -> usize {
+        self.image_paths
+            .iter()
+            .filter_map(|path| self.sizes.get(path.path()))
+            .sum()
+    }
+
+    /// Picks the images from `candidates` that fit in `budget` bytes. `current` is always kept,
+    /// then the most recently viewed ones. Otherwise `candidates` order decides.
+    fn select_within_budget(
+        &self,
+        candidates: Vec<PathBuf>,
+        current: Option<&Path>,
+        budget: usize,
+    ) -> HashSet<PathBuf> {
+        let mut candidates = candidates;
+        // Stable sort keeps the caller's order for images that were never viewed.
+        candidates.sort_by_key(|path| {
+            let is_current = Some(path.as_path()) == current;
+            let last_viewed = self.last_viewed.get(path).copied().unwrap_or(0);
+            (!is_current, std::cmp::Reverse(last_viewed))
+        });
+
+        let mut total = 0;
+        let mut keep = HashSet::new();
+        for path in candidates {
+            let size = self.sizes.get(&path).copied().unwrap_or(0);
+            let is_current = Some(path.as_path()) == current;
+            if !is_current && total + size > budget {
+                log::debug!("Over memory budget. Not keeping {}", path.display());
+                continue;
+            }
+            total += size;
+            keep.insert(path);
+        }
+        keep
+    }
+
+    /// Page `page` of the multi-page file at `path`, counting from 0. Call `forget_page()` when
+    /// it is no longer displayed.
+    fn page(&self, path: &Path, page: usize, full_resolution: bool) -> egui::Image<'static> {
+        let uri = image_loader::page_uri(&ImagePath::new(path).uri(), page);
+        if full_resolution {
+            egui::Image::from_uri(image_loader::full_resolution_uri(&uri))
+        } else {
+            egui::Image::from_uri(uri)
+        }
+    }
+
+    fn forget_page(&self, path: &Path, page: usize) {
+        if page == 0 {
+            // The first page is the image itself, which `only_keep()` takes care of.
+            return;
+        }
+        let uri = image_loader::page_uri(&ImagePath::new(path).uri(), page);
+        self.context.forget_image(&uri);
+        self.context
+            .forget_image(&image_loader::full_resolution_uri(&uri));
+    }
+
+    /// Loads the image at `path` again the next time it is displayed, e.g. after the file was
+    /// replaced.
+    fn reload(&mut self, path: &Path) {
+        let image_path = ImagePath::new(path);
+        self.image_paths.remove(&image_path);
+        self.context.forget_image(&image_path.uri());
+        self.forget_full_resolution(path);
+        self.sizes.remove(path);
+    }
+
+    fn forget_full_resolution(&self, path: &Path) {
+        let uri = ImagePath::new(path).uri();
+        self.context
+            .forget_image(&image_loader::full_resolution_uri(&uri));
+    }
+
     /// Remove images from the loader except those specified in `paths`.
     /// Images in `paths` are added to be loaded.
-    fn only_keep(&mut self, paths: HashSet<String>) {
+    fn only_keep(&mut self, paths: HashSet<PathBuf>) {
         let paths = paths.iter().map(|p| ImagePath::new(p)).collect();
         let still_loaded = &self.image_paths - &paths;
         if still_loaded.is_empty() {
             return;
         }
         for path in still_loaded {
-            log::debug!("OnlyKeep: Removing image: {}", path.path());
+            log::debug!("OnlyKeep: Removing image: {}", path.path().display());
             self.image_paths.remove(&path);
             self.context.forget_image(&path.uri());
+            self.forget_full_resolution(path.path());
         }
         let new_paths = &paths - &self.image_paths;
         for path in new_paths {
@@ -111,15 +702,67 @@ impl Loader {
     }
 }
 
-#[derive(Default)]
+/// A subset of the images that next and previous stay within, e.g. the ones taken in one place.
+struct ImageFilter {
+    name: String,
+    paths: HashSet<PathBuf>,
+    // The images skipped for later, after the rest has been gone through.
+    deferred: bool,
+}
+
 struct ImageManager {
-    all_images: Vec<String>,
+    all_images: Vec<PathBuf>,
     current_image_index: usize,
     loader: Loader,
+    // Number of images before and after the current one to keep loaded.
+    keep_window: usize,
+    // Approximate upper limit for the decoded images in bytes.
+    memory_budget: usize,
+    order: scanner::FolderOrder,
+    filter: Option<ImageFilter>,
+    // Images skipped for later. They are hidden until the rest has been gone through.
+    deferred: HashSet<PathBuf>,
+    // Images the user decided to leave where they are, e.g. in an earlier session.
+    kept: HashSet<PathBuf>,
+    hide_kept: bool,
+    // Going past the last image starts over from the first one.
+    wrap_around: bool,
+    // Going past the last image was tried with wrap-around off, so the pass is over.
+    end_reached: bool,
+    // The shots of a burst after the first are stacked behind it.
+    stack_bursts: bool,
+    // Dates read for bursts and the timeline while the images are not sorted by date, which
+    // reads its own.
+    known_dates: HashMap<PathBuf, String>,
+    // The first images of stacks whose shots are gone through one by one.
+    expanded_stacks: HashSet<PathBuf>,
+}
+
+impl Default for ImageManager {
+    fn default() -> Self {
+        let settings = Settings::default();
+        Self {
+            all_images: Vec::new(),
+            current_image_index: 0,
+            loader: Loader::default(),
+            keep_window: settings.keep_window,
+            memory_budget: settings.memory_budget_bytes(),
+            order: scanner::FolderOrder::default(),
+            filter: None,
+            deferred: HashSet::new(),
+            kept: HashSet::new(),
+            hide_kept: settings.hide_kept_images,
+            wrap_around: settings.wrap_around,
+            end_reached: false,
+            stack_bursts: settings.stack_bursts,
+            known_dates: HashMap::new(),
+            expanded_stacks: HashSet::new(),
+        }
+    }
 }
 
 struct LoadedImageInfo<'a> {
-    path: String,
+    path: PathBuf,
     image: egui::Image<'a>,
 }
 
@@ -128,537 +771,9076 @@ impl ImageManager {
         self.loader.set_context(context);
     }
 
-    fn set_image_folder(&mut self, folder_path: &str) {
-        self.all_images = get_image_paths(folder_path);
+    fn set_cache_limits(&mut self, keep_window: usize, memory_budget: usize) {
+        self.keep_window = keep_window;
+        self.memory_budget = memory_budget;
+    }
+
+    // The app scans folders in the background with `FolderScan`. Tests use this synchronous
+    // version.
+    #[cfg(test)]
+    fn set_image_folder(&mut self, folder_path: &Path) {
+        self.all_images = scanner::get_image_paths(folder_path);
         self.current_image_index = 0;
     }
 
-    fn load_current_image(&mut self) -> Option<LoadedImageInfo> {
+    fn clear(&mut self) {
+        self.all_images.clear();
+        self.current_image_index = 0;
+        self.deferred.clear();
+        self.kept.clear();
+        self.expanded_stacks.clear();
+    }
+
+    /// Sets the queued source folders. Images are shown folder by folder in this order.
+    fn set_folders(&mut self, folders: Vec<PathBuf>) {
+        let order = scanner::FolderOrder::new(folders)
+            .with_dates(self.order.dates().clone())
+            .with_sizes(self.order.sizes().clone())
+            .with_colors(self.order.colors().clone());
+        self.set_order(order);
+    }
+
+    /// Sorts the images by these dates inside each folder. Empty sorts by file name.
+    fn set_dates(&mut self, dates: HashMap<PathBuf, String>) {
+        let order = self.order.clone().with_dates(Arc::new(dates));
+        self.set_order(order);
+    }
+
+    /// Sorts the images by these file sizes inside each folder, largest first. Empty sorts by
+    /// file name.
+    fn set_sizes(&mut self, sizes: HashMap<PathBuf, u64>) {
+        let order = self.order.clone().with_sizes(Arc::new(sizes));
+        self.set_order(order);
+    }
+
+    /// Sorts the images around the color wheel by these dominant colors inside each folder. Empty
+    /// sorts by file name.
+    fn set_colors(&mut self, colors: HashMap<PathBuf, [u8; 3]>) {
+        let order = self.order.clone().with_colors(Arc::new(colors));
+        self.set_order(order);
+    }
+
+    fn set_order(&mut self, order: scanner::FolderOrder) {
+        let current = self.current_image_path().map(|path| path.to_path_buf());
+        self.order = order;
+        let order = &self.order;
+        self.all_images.sort_by(|a, b| order.compare(a, b));
+        if let Some(current) = current {
+            self.go_to_image(&current);
+        }
+    }
+
+    fn folders(&self) -> &[PathBuf] {
+        self.order.folders()
+    }
+
+    fn order(&self) -> &scanner::FolderOrder {
+        &self.order
+    }
+
+    /// Adds images found by a background scan. The current image stays the same.
+    fn add_scanned_images(&mut self, mut paths: Vec<PathBuf>) {
+        if paths.is_empty() {
+            return;
+        }
+        self.order.sort(&mut paths);
+        let current = self.current_image_path().map(|path| path.to_path_buf());
+        let all_images = std::mem::take(&mut self.all_images);
+        self.all_images = self.order.merge(all_images, paths);
+        if let Some(current) = current {
+            self.go_to_image(&current);
+        }
+    }
+
+    fn load_current_image(&mut self) -> Option<LoadedImageInfo<'_>> {
         let path = self.all_images.get(self.current_image_index);
         match path {
-            Some(path) => Some(LoadedImageInfo {
-                path: path.clone(),
-                image: self.loader.add(path),
-            }),
+            Some(path) => {
+                self.loader.mark_viewed(path);
+                Some(LoadedImageInfo {
+                    path: path.clone(),
+                    image: self.loader.add(path),
+                })
+            }
             None => None,
         }
     }
 
-    // Only load images within 3 indices of the current image.
+    // Only load images within `keep_window` indices of the current image, as long as they fit in
+    // the memory budget.
     fn cleanup(&mut self) {
-        let start = std::cmp::max(0, self.current_image_index.saturating_sub(3));
-        let end = std::cmp::min(
-            self.all_images.len(),
-            self.current_image_index.saturating_add(3),
+        self.loader.update_sizes();
+        let index = self.current_image_index;
+        // Closest images first so that they win when the budget is tight.
+        let mut candidates = Vec::new();
+        for distance in 0..=self.keep_window {
+            for i in [index.checked_add(distance), index.checked_sub(distance)] {
+                let Some(path) = i.and_then(|i| self.all_images.get(i)) else {
+                    continue;
+                };
+                if !candidates.contains(path) {
+                    candidates.push(path.clone());
+                }
+            }
+        }
+        let keep_images = self.loader.select_within_budget(
+            candidates,
+            self.all_images.get(index).map(|path| path.as_path()),
+            self.memory_budget,
         );
-        let keep_images: HashSet<String> = (start..end)
-            .map(|index| self.all_images[index].to_string())
-            .collect();
         self.loader.only_keep(keep_images);
     }
 
+    fn loaded_bytes(&self) -> usize {
+        self.loader.loaded_bytes()
+    }
+
     fn num_images(&self) -> usize {
         self.all_images.len()
     }
 
+    fn current_image_path(&self) -> Option<&Path> {
+        self.all_images
+            .get(self.current_image_index)
+            .map(|path| path.as_path())
+    }
+
     fn current_index(&self) -> usize {
         self.current_image_index
     }
 
-    fn next_image(&mut self) {
-        self.current_image_index = (self.current_image_index + 1) % self.num_images();
+    fn image_paths(&self) -> &[PathBuf] {
+        &self.all_images
     }
 
-    fn previous_image(&mut self) {
-        if self.current_image_index == 0 {
-            self.current_image_index = self.num_images() - 1;
-        } else {
-            self.current_image_index -= 1;
+    fn go_to_index(&mut self, index: usize) {
+        if index < self.all_images.len() {
+            self.current_image_index = index;
+            self.end_reached = false;
         }
     }
 
-    fn remove_current_image(&mut self) -> Option<String> {
-        if self.current_image_index >= self.all_images.len() {
-            log::error!(
-                "Current image index is {} but only has {}.",
-                self.current_image_index,
-                self.all_images.len()
-            );
-            return None;
-        }
-        let path = self.all_images.remove(self.current_image_index);
-
-        // Handling the case where the current_image_index is now out of bounds
-        // because it (re)moved the last file.
-        if self.current_image_index >= self.all_images.len() && self.current_image_index > 0 {
-            self.current_image_index = self.all_images.len() - 1;
+    /// Makes `path` the current image. Returns false if it is not in the list.
+    fn go_to_image(&mut self, path: &Path) -> bool {
+        match self.all_images.iter().position(|p| p == path) {
+            Some(index) => {
+                self.current_image_index = index;
+                self.end_reached = false;
+                true
+            }
+            None => false,
         }
+    }
 
-        log::debug!(
-            "Removed image. Current index {}. Number of images: {}",
-            self.current_image_index,
-            self.all_images.len()
-        );
+    /// Only shows `paths` until the filter is cleared.
+    fn set_filter(&mut self, name: String, paths: HashSet<PathBuf>) {
+        self.filter = Some(ImageFilter {
+            name,
+            paths,
+            deferred: false,
+        });
+        self.ensure_visible();
+    }
 
-        Some(path)
+    fn clear_filter(&mut self) {
+        self.filter = None;
     }
 
-    /// Add image to the current position.
-    fn add_image_to_current_position(&mut self, path: &str) {
-        self.all_images
-            .insert(self.current_image_index, path.to_string());
-        let _ = self.loader.add(path);
+    /// Name of the filter and the number of images in it.
+    fn filter(&self) -> Option<(&str, usize)> {
+        self.filter
+            .as_ref()
+            .map(|filter| (filter.name.as_str(), filter.paths.len()))
     }
-}
 
-#[derive(Default)]
-struct MyApp {
-    selected_folder: Option<String>,
-    folder_letter_entries: Vec<FolderLetterEntry>,
-    new_folder: String,
-    new_letter: String,
-    move_log: Vec<MoveLogEntry>,
-    status_message: String,
-    image_manager: ImageManager,
-}
+    fn is_visible(&self, path: &Path) -> bool {
+        let hidden_kept = self.hide_kept && self.kept.contains(path);
+        !self.deferred.contains(path)
+            && !hidden_kept
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.paths.contains(path))
+    }
 
-fn get_image_paths(folder_path: &str) -> Vec<String> {
-    let mut image_paths = Vec::new();
-    if let Ok(entries) = fs::read_dir(folder_path) {
-        for entry in entries {
-            let Ok(entry) = entry else { continue };
-            let path = entry.path();
-            let Some(extension) = path.extension() else {
-                continue;
+    // Moves to the next or previous image, skipping the images that are filtered out. Going past
+    // the last image goes on with the images skipped for later, and then wraps around unless
+    // that is turned off.
+    fn step(&mut self, forward: bool) {
+        let num_images = self.num_images();
+        for step in 1..=num_images {
+            let index = if forward {
+                (self.current_image_index + step) % num_images
+            } else {
+                (self.current_image_index + num_images - step) % num_images
             };
-            let Some(ext_str) = extension.to_str() else {
-                continue;
+            let wrapped = if forward {
+                index <= self.current_image_index
+            } else {
+                index >= self.current_image_index
             };
-
-            let ext_lower = ext_str.to_lowercase();
-            // TODO: There is also image::ImageFormat.all() and then call can_read() to see if
-            // the current features allow reading the file. Then use extension_str() to get
-            // all the extensions for that image format.
-            let image_extensions = ["jpg", "jpeg", "png", "gif", "webp"];
-            if image_extensions.contains(&ext_lower.as_str()) {
-                // Add more extensions as needed
-                if let Some(path_str) = path.to_str() {
-                    image_paths.push(path_str.to_string());
-                }
+            if forward && wrapped && !self.deferred.is_empty() {
+                self.review_deferred();
+                return;
+            }
+            if wrapped && !self.wrap_around {
+                break;
             }
+            if self.is_visible(&self.all_images[index]) && !self.is_stacked(index) {
+                self.current_image_index = index;
+                self.end_reached = false;
+                return;
+            }
+        }
+        if !self.deferred.is_empty() {
+            self.review_deferred();
+        } else if forward && !self.wrap_around {
+            self.end_reached = true;
         }
     }
 
-    // It's likely that screenshot names are named by date it was taken. Sorting
-    // and reversing it would show the latest images first.
-    image_paths.sort();
-    image_paths.reverse();
-    image_paths
-}
+    /// Hides the current image until the others have been gone through, and moves on. While
+    /// going through the skipped images, this just moves on since they come around again.
+    fn defer_current(&mut self) -> Option<PathBuf> {
+        let path = self.current_image_path()?.to_path_buf();
+        if !self.filter.as_ref().is_some_and(|filter| filter.deferred) {
+            self.deferred.insert(path.clone());
+        }
+        self.step(true);
+        Some(path)
+    }
 
-fn get_file_name(path: &str) -> String {
-    let path = Path::new(path);
-    path.file_name().unwrap().to_string_lossy().to_string()
-}
+    fn num_deferred(&self) -> usize {
+        self.deferred.len()
+    }
 
-// Moves src to dest_dir. Returns the new file path on success.
-fn move_file(src: &str, dest_dir: &str) -> std::io::Result<String> {
-    let src_path = Path::new(src);
-    let filename = src_path.file_name().unwrap();
-    let dest_path = PathBuf::from(dest_dir).join(filename);
-    std::fs::rename(src, &dest_path)?;
-    Ok(dest_path.to_string_lossy().to_string())
-}
+    /// Marks images that were kept in place before.
+    fn add_kept(&mut self, paths: HashSet<PathBuf>) {
+        self.kept.extend(paths);
+    }
 
-impl MyApp {
-    fn move_current_image_to_dest(&mut self, dest_dir: &str) -> Result<MoveLogEntry> {
-        let Some(image_path) = self.image_manager.remove_current_image() else {
-            bail!("Failed to find current image");
-        };
+    /// Marks the current image as kept in place, or unmarks it if it already is. Returns whether
+    /// it is kept now. A kept image is stepped over if they are hidden.
+    fn toggle_kept(&mut self) -> Option<(PathBuf, bool)> {
+        let path = self.current_image_path()?.to_path_buf();
+        let kept = self.kept.insert(path.clone());
+        if !kept {
+            self.kept.remove(&path);
+        }
+        if kept && self.hide_kept {
+            self.step(true);
+        }
+        Some((path, kept))
+    }
 
-        let image_path = image_path.clone();
-        match move_file(&image_path, dest_dir) {
-            Ok(new_path) => {
-                log::info!("Moved file {} to {}", image_path, dest_dir);
-                let log_entry = MoveLogEntry {
-                    src: image_path.clone(),
-                    dest: new_path.clone(),
-                };
-                self.move_log.push(log_entry.clone());
-                Ok(log_entry)
-            }
-            Err(e) => {
-                log::error!("Failed to move file: {}", e);
-                Err(e.into())
-            }
+    fn is_kept(&self, path: &Path) -> bool {
+        self.kept.contains(path)
+    }
+
+    fn set_hide_kept(&mut self, hide: bool) {
+        self.hide_kept = hide;
+    }
+
+    fn set_wrap_around(&mut self, wrap_around: bool) {
+        self.wrap_around = wrap_around;
+        if wrap_around {
+            self.end_reached = false;
         }
     }
 
-    fn next_image(&mut self) {
-        self.image_manager.next_image();
+    /// Whether the last image was gone past with wrap-around off.
+    fn at_end(&self) -> bool {
+        self.end_reached
     }
 
-    fn previous_image(&mut self) {
-        self.image_manager.previous_image();
+    /// Goes back to the first image for another pass.
+    fn start_over(&mut self) {
+        self.current_image_index = 0;
+        self.end_reached = false;
+        self.ensure_visible();
     }
 
-    fn remove_folder_letter_entries(&mut self, indecies: Vec<usize>) {
-        let mut indecies = indecies;
-        indecies.sort();
-        indecies.reverse();
-        for index in indecies {
-            self.folder_letter_entries.remove(index);
+    /// Number of queued images that are hidden because they were kept.
+    fn num_hidden_kept(&self) -> usize {
+        if !self.hide_kept {
+            return 0;
         }
+        self.all_images
+            .iter()
+            .filter(|path| self.kept.contains(*path))
+            .count()
     }
 
-    // Undo the last move. The image is reinserted to the current index.
-    // Returns the path to the un-done file.
-    fn undo_move(&mut self) -> Option<String> {
-        if self.move_log.is_empty() {
-            return None;
+    // Ends the main pass: only the images skipped for later are shown, from the first one.
+    fn review_deferred(&mut self) {
+        let paths = std::mem::take(&mut self.deferred);
+        if let Some(index) = self.all_images.iter().position(|path| paths.contains(path)) {
+            self.current_image_index = index;
+        }
+        self.set_filter("Skipped for later".to_string(), paths);
+        if let Some(filter) = &mut self.filter {
+            filter.deferred = true;
         }
-        let last_move = self.move_log.pop().unwrap();
-        let src = last_move.src;
-        let dest = last_move.dest;
-        std::fs::rename(&dest, &src).ok()?;
-        self.image_manager.add_image_to_current_position(&src);
-        Some(src)
     }
-}
 
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.image_manager.set_context(ctx);
-        self.image_manager.cleanup();
-        let mut status_message = String::new();
-        ctx.input(|input| {
-            if input.key_pressed(egui::Key::J) {
-                self.next_image();
+    /// Stacks bursts or stops that. Returns true if it changed.
+    fn set_stack_bursts(&mut self, stack_bursts: bool) -> bool {
+        let changed = self.stack_bursts != stack_bursts;
+        self.stack_bursts = stack_bursts;
+        changed
+    }
+
+    /// Adds dates read for bursts and the timeline.
+    fn add_dates(&mut self, dates: HashMap<PathBuf, String>) {
+        self.known_dates.extend(dates);
+    }
+
+    fn knows_date(&self, path: &Path) -> bool {
+        self.date(path).is_some()
+    }
+
+    /// When the image at `path` was taken, if that has been read.
+    fn date(&self, path: &Path) -> Option<&str> {
+        self.order
+            .dates()
+            .get(path)
+            .or_else(|| self.known_dates.get(path))
+            .map(|date| date.as_str())
+    }
+
+    /// The number of images whose date has been read.
+    fn num_dates(&self) -> usize {
+        self.order.dates().len() + self.known_dates.len()
+    }
+
+    fn same_burst(&self, a: &Path, b: &Path) -> bool {
+        burst::same_burst(a, b, |path| self.date(path).map(|date| date.to_string()))
+    }
+
+    // The indices of the shots of the burst that the image at `index` is in. Only `index` for
+    // images that are not in one.
+    fn burst_range(&self, index: usize) -> Range<usize> {
+        let num_images = self.num_images();
+        if !self.stack_bursts || index >= num_images {
+            return index..(index + 1).min(num_images);
+        }
+        let images = &self.all_images;
+        let mut start = index;
+        while start > 0 && self.same_burst(&images[start - 1], &images[start]) {
+            start -= 1;
+        }
+        let mut end = index + 1;
+        while end < num_images && self.same_burst(&images[end - 1], &images[end]) {
+            end += 1;
+        }
+        start..end
+    }
+
+    // Whether the image at `index` is stacked behind the first shot of its burst.
+    fn is_stacked(&self, index: usize) -> bool {
+        let range = self.burst_range(index);
+        range.start < index && !self.expanded_stacks.contains(&self.all_images[range.start])
+    }
+
+    /// The shots of the burst whose stack the current image is on top of. None if it is not, or
+    /// if the shots are gone through one by one.
+    fn current_stack(&self) -> Option<Vec<PathBuf>> {
+        let range = self.burst_range(self.current_image_index);
+        if range.len() < 2 || range.start != self.current_image_index {
+            return None;
+        }
+        let cover = &self.all_images[range.start];
+        (!self.expanded_stacks.contains(cover)).then(|| self.all_images[range].to_vec())
+    }
+
+    /// Where the current image is in its burst, counting from 1, the number of shots, and whether
+    /// they are gone through one by one. None if it is not in a burst.
+    fn burst_position(&self) -> Option<(usize, usize, bool)> {
+        let range = self.burst_range(self.current_image_index);
+        if range.len() < 2 {
+            return None;
+        }
+        let expanded = self.expanded_stacks.contains(&self.all_images[range.start]);
+        Some((
+            self.current_image_index - range.start + 1,
+            range.len(),
+            expanded,
+        ))
+    }
+
+    /// Goes through the shots of the current burst one by one, or stacks them again and shows the
+    /// first one.
+    fn toggle_stack(&mut self) {
+        let range = self.burst_range(self.current_image_index);
+        if range.len() < 2 {
+            return;
+        }
+        let cover = self.all_images[range.start].clone();
+        if !self.expanded_stacks.remove(&cover) {
+            self.expanded_stacks.insert(cover);
+        } else {
+            self.current_image_index = range.start;
+        }
+    }
+
+    fn next_image(&mut self) {
+        self.step(true);
+    }
+
+    fn previous_image(&mut self) {
+        self.step(false);
+    }
+
+    /// Moves off the current image if the filter hides it, e.g. after the previous one was
+    /// moved. Returns false if the filter hides every image.
+    fn ensure_visible(&mut self) -> bool {
+        let Some(current) = self.current_image_path() else {
+            return self.filter.is_none();
+        };
+        if self.is_visible(current) {
+            return true;
+        }
+        self.step(true);
+        self.current_image_path()
+            .is_some_and(|current| self.is_visible(current))
+    }
+
+    fn remove_current_image(&mut self) -> Option<PathBuf> {
+        if self.current_image_index >= self.all_images.len() {
+            log::error!(
+                "Current image index is {} but only has {}.",
+                self.current_image_index,
+                self.all_images.len()
+            );
+            return None;
+        }
+        let path = self.all_images.remove(self.current_image_index);
+        if let Some(filter) = &mut self.filter {
+            filter.paths.remove(&path);
+        }
+        self.deferred.remove(&path);
+        self.kept.remove(&path);
+
+        // Handling the case where the current_image_index is now out of bounds
+        // because it (re)moved the last file.
+        if self.current_image_index >= self.all_images.len() && self.current_image_index > 0 {
+            self.current_image_index = self.all_images.len() - 1;
+            // The image before it was already gone past.
+            self.end_reached = !self.wrap_around && self.deferred.is_empty();
+        }
+
+        log::debug!(
+            "Removed image. Current index {}. Number of images: {}",
+            self.current_image_index,
+            self.all_images.len()
+        );
+
+        Some(path)
+    }
+
+    /// Chooses the image shown after the one at `index` was removed.
+    fn go_on_after_removal(&mut self, index: usize, after: AfterMove) {
+        let num_images = self.num_images();
+        if num_images == 0 {
+            return;
+        }
+        match after {
+            // Only differs from staying when the last image was removed.
+            AfterMove::Advance if index >= num_images => {
+                self.current_image_index = num_images - 1;
+                self.end_reached = false;
+                self.step(true);
             }
-            if input.key_pressed(egui::Key::K) {
-                self.previous_image();
+            AfterMove::Advance | AfterMove::Stay => {}
+            AfterMove::Back => {
+                self.current_image_index = index.saturating_sub(1).min(num_images - 1);
+                self.end_reached = false;
+                if !self.is_visible(&self.all_images[self.current_image_index]) {
+                    self.step(false);
+                }
+            }
+        }
+    }
+
+    /// Removes `paths` from the list. The current image stays the same if it is not removed.
+    fn remove_images(&mut self, paths: &HashSet<PathBuf>) {
+        if paths.is_empty() {
+            return;
+        }
+        let current = self.current_image_path().map(|path| path.to_path_buf());
+        self.all_images.retain(|path| !paths.contains(path));
+        if let Some(filter) = &mut self.filter {
+            filter.paths.retain(|path| !paths.contains(path));
+        }
+        self.deferred.retain(|path| !paths.contains(path));
+        self.kept.retain(|path| !paths.contains(path));
+        match current {
+            Some(current) if !paths.contains(&current) => {
+                self.go_to_image(&current);
             }
+            _ => {
+                self.current_image_index = self
+                    .current_image_index
+                    .min(self.all_images.len().saturating_sub(1));
+            }
+        }
+    }
 
-            if input.modifiers.ctrl && input.key_pressed(egui::Key::Z) {
-                match self.undo_move() {
-                    Some(_) => {
-                        status_message = "Undo".to_string();
-                    }
-                    None => {
-                        status_message = "Nothing to undo.".to_string();
-                    }
+    /// Replaces `src` with `dest` where it is in the list, e.g. after it was renamed.
+    fn rename_image(&mut self, src: &Path, dest: &Path) {
+        if let Some(path) = self.all_images.iter_mut().find(|path| *path == src) {
+            *path = dest.to_path_buf();
+        }
+        if let Some(filter) = &mut self.filter {
+            if filter.paths.remove(src) {
+                filter.paths.insert(dest.to_path_buf());
+            }
+        }
+        if self.deferred.remove(src) {
+            self.deferred.insert(dest.to_path_buf());
+        }
+        if self.kept.remove(src) {
+            self.kept.insert(dest.to_path_buf());
+        }
+    }
+
+    /// Add image to the current position.
+    fn add_image_to_current_position(&mut self, path: &Path) {
+        self.all_images
+            .insert(self.current_image_index, path.to_path_buf());
+        // It is being brought back, e.g. by undo, so it must not be hidden.
+        if let Some(filter) = &mut self.filter {
+            filter.paths.insert(path.to_path_buf());
+        }
+        let _ = self.loader.add(path);
+    }
+}
+
+#[derive(Default)]
+struct MyApp {
+    folder_letter_entries: Vec<FolderLetterEntry>,
+    new_folder: String,
+    // The folder picked with the dialog. Kept separately from `new_folder`, which is only its
+    // lossy display form, so that non-UTF-8 paths survive.
+    picked_folder: Option<PathBuf>,
+    new_letter: String,
+    move_log: Vec<MoveLogEntry>,
+    status: StatusLog,
+    show_status_history: bool,
+    failed_move: Option<FailedMove>,
+    pending_confirmation: Option<PendingConfirmation>,
+    // Copies that a crash interrupted, found in the journal on startup, and where they stopped.
+    interrupted: Vec<(journal::Operation, journal::Interruption)>,
+    // The session the app crashed in last time, until the user resumes or dismisses it.
+    crash: Option<recovery::Crash>,
+    // The steps of setting up, shown on the first start.
+    wizard: Option<Wizard>,
+    practice: Option<PracticeSession>,
+    // Where the panic hook saves the session. None in tests.
+    recovery_path: Option<PathBuf>,
+    // The image to go to once the scans of a resumed session find it.
+    resume_image: Option<PathBuf>,
+    // Nothing on disk is changed. Destination keys add to `plan` instead of moving.
+    read_only: bool,
+    plan: plan::Plan,
+    // The user's script and the image whose hooks ran last, with its suggestion.
+    script: Option<scripting::Script>,
+    script_shown: Option<PathBuf>,
+    script_suggestion: Option<PathBuf>,
+    // The "?" overlay that lists the shortcuts.
+    show_shortcuts: bool,
+    // The current image if it is a TIFF file with several pages: the page shown, counting from 0,
+    // and the number of pages.
+    current_page: Option<(PathBuf, usize, usize)>,
+    // The theme and accent color that were last applied.
+    applied_theme: Option<(egui::ThemePreference, Option<[u8; 3]>)>,
+    applied_ui_scale: Option<f32>,
+    applied_font_size: Option<f32>,
+    // Folders handed over by later launches of the app.
+    handovers: Option<mpsc::Receiver<Option<PathBuf>>>,
+    retry_queue: RetryQueue,
+    // Uploads to destinations on other machines. Started the first time one is used.
+    uploader: Option<remote::Uploader>,
+    // The window was asked to close while something was still pending, and the user has not
+    // answered yet.
+    confirm_exit: bool,
+    // Closing was confirmed, so the next close request goes through.
+    exit_confirmed: bool,
+    // The length of the plan when it was last exported.
+    plan_exported: usize,
+    integrity_scan: Option<IntegrityScan>,
+    show_integrity_scan: bool,
+    // The multi-file operation that runs or ran last. Kept until its window is closed.
+    batch: Option<Batch<BatchReport>>,
+    show_batch: bool,
+    session_revert: Option<SessionRevert>,
+    show_session_revert: bool,
+    settings: Settings,
+    show_settings: bool,
+    layout: Layout,
+    // The image is shown in a window of its own, e.g. full screen on another monitor.
+    image_detached: bool,
+    tray: Option<tray::TrayIcon>,
+    // The watched folder of the tray icon that is shown.
+    applied_tray: Option<PathBuf>,
+    // Shared with `DownscalingLoader`.
+    max_texture_side: Arc<AtomicU32>,
+    show_full_resolution: bool,
+    // None in tests, which do not need the background thread.
+    thumbnails: Option<Thumbnails>,
+    show_grid: bool,
+    show_timeline: bool,
+    timeline: Timeline,
+    filmstrip: Filmstrip,
+    folder_scans: Vec<FolderScan>,
+    // Source folders that could not be listed, e.g. on a network share that is down, and why.
+    unreachable_folders: Vec<(PathBuf, String)>,
+    // Only open when enabled in the settings.
+    library_index: Option<LibraryIndex>,
+    index_update: Option<IndexUpdate>,
+    // Folders waiting for `index_update`.
+    index_queue: VecDeque<PathBuf>,
+    // Images listed from the index that the running scan has not found yet. Whatever is left
+    // when the scan finishes no longer exists.
+    unconfirmed_images: HashSet<PathBuf>,
+    // How the scanned files looked when they were listed, checked before each is moved.
+    file_stamps: HashMap<PathBuf, FileStamp>,
+    show_library: bool,
+    library_query: String,
+    library_results: Vec<PathBuf>,
+    duplicate_groups: Vec<Vec<PathBuf>>,
+    tag_editor: TagEditor,
+    show_merge: bool,
+    merge_tool: MergeTool,
+    show_flatten: bool,
+    flatten_tool: FlattenTool,
+    show_organize: bool,
+    organize_tool: OrganizeTool,
+    // The length of the move log before the last "Organize by date" run and the number of moves
+    // it added, to undo the run as a whole.
+    organize_undo: Option<(usize, usize)>,
+    show_locations: bool,
+    location_tool: LocationTool,
+    show_blur: bool,
+    blur_tool: BlurTool,
+    show_screenshots: bool,
+    screenshot_tool: ScreenshotTool,
+    show_faces: bool,
+    face_tool: FaceTool,
+    show_quarantine: bool,
+    quarantine_tool: QuarantineTool,
+    show_discard: bool,
+    discard_review: DiscardReview,
+    album_tree: AlbumTree,
+    show_replay: bool,
+    replay_tool: ReplayTool,
+    show_sync: bool,
+    sync_tool: SyncTool,
+    show_storage: bool,
+    contact_sheet: ContactSheetTool,
+    show_contact_sheet: bool,
+    storage_tool: StorageTool,
+    // Reads dates for `SortOrder::DateTaken`.
+    date_scan: Option<DateScan>,
+    // Some images have not had their date read yet.
+    dates_stale: bool,
+    // Reads file sizes for `SortOrder::Largest`.
+    size_scan: Option<SizeScan>,
+    // Some images have not had their size read yet.
+    sizes_stale: bool,
+    // Reads dominant colors for `SortOrder::Color`.
+    color_scan: Option<ColorScan>,
+    // Some images have not had their color read yet.
+    colors_stale: bool,
+    // Bytes of the images that were moved out of their folder or deleted since the largest files
+    // have been shown first.
+    freed_bytes: u64,
+    session: SessionStats,
+    // What the user set out to do in this session, with a progress bar under the folders.
+    goal: Option<SessionGoal>,
+    goal_editor: GoalEditor,
+    // The folder to offer once the last one is done, found for the last queued folder.
+    next_folder: Option<(PathBuf, Option<PathBuf>)>,
+    sort_order: SortOrder,
+    show_histogram: bool,
+    show_clipping: bool,
+    histogram: HistogramView,
+    loupe: Loupe,
+    labels: Labels,
+    show_info: bool,
+    // Metadata of the current image and where it points if it is a symbolic link. Reading them
+    // every frame would be slow.
+    info: Option<(PathBuf, ImageMetadata, Option<PathBuf>)>,
+    // Note of the image in `info` as it is being edited.
+    note_text: String,
+    // Properties of the image shown under its name, read once per image.
+    properties: Option<(PathBuf, Result<properties::FileProperties, String>)>,
+    // Size of the image that failed to decode, read once per image as it may be on a share that
+    // no longer answers.
+    broken_image_size: Option<(PathBuf, Result<u64, String>)>,
+    // The image being renamed and the name typed so far.
+    rename: Option<(PathBuf, String)>,
+    // The name typed so far for a new folder in the albums folder, while Ctrl+N asks for one.
+    new_album: Option<String>,
+    // The color of the destination the last image was moved to, and when.
+    move_flash: Option<([u8; 3], Instant)>,
+    // Escape ignores the keys of the destinations until it is pressed again.
+    locked: bool,
+    // Every move, copy, rename, delete and undo of the session, oldest first. Unlike
+    // `move_log`, nothing leaves it.
+    audit_log: Vec<MoveLogEntry>,
+    show_audit: bool,
+    // The log files, read when the log window is opened.
+    log_viewer: logging::LogViewer,
+    show_log: bool,
+    // A letter pressed once that moves only when it is pressed again, and when.
+    armed_letter: Option<(char, Instant)>,
+    // Ctrl+P: type part of the name of a command to run it, or of a folder to move the image
+    // there.
+    command_palette: CommandPalette<PaletteTarget>,
+    // The image last recorded as viewed in the index.
+    last_viewed: Option<PathBuf>,
+    image_manager: ImageManager,
+}
+
+fn index_path() -> PathBuf {
+    app_dirs::data_dir().join("index.sqlite3")
+}
+
+// Fills in the "{year}", "{month}" and "{day}" of a destination folder from the date of `image`,
+// and creates the folder. Other destinations are returned as they are.
+fn resolve_destination(image: &Path, dest_dir: &Path) -> Result<PathBuf> {
+    if !metadata::is_date_template(dest_dir) {
+        return Ok(dest_dir.to_path_buf());
+    }
+    let Some(date) = metadata::date_for_sorting(image) else {
+        bail!("The date of {} is unknown.", get_file_name(image));
+    };
+    let folder = metadata::expand_date_template(dest_dir, &date);
+    fs::create_dir_all(file_ops::long_path(&folder))?;
+    Ok(folder)
+}
+
+// Copies `files`, an image and its companion files, to each of `folders`. If a copy fails, the
+// ones made so far are removed again. Returns the paths of the copies.
+fn copy_files(files: &[PathBuf], folders: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut copies = Vec::new();
+    let mut copy_all = || -> Result<()> {
+        let Some(image) = files.first() else {
+            return Ok(());
+        };
+        for folder in folders {
+            let dest_dir = resolve_destination(image, folder)?;
+            for file in files {
+                copies.push(match archive::split(file) {
+                    Some(_) => archive::extract(file, &dest_dir)?,
+                    None => file_ops::copy_to(file, &dest_dir)?,
+                });
+            }
+        }
+        Ok(())
+    };
+    if let Err(e) = copy_all() {
+        remove_copies(&copies);
+        return Err(e);
+    }
+    Ok(copies)
+}
+
+// Removes the copies made for a move that did not happen.
+fn remove_copies(copies: &[PathBuf]) {
+    for copy in copies {
+        let _ = fs::remove_file(copy);
+    }
+}
+
+// Draws the properties of the current image `path`. They are read into `cache` once per image.
+fn show_properties(
+    ui: &mut egui::Ui,
+    path: &Path,
+    cache: &mut Option<(PathBuf, Result<properties::FileProperties, String>)>,
+    status: &mut StatusLog,
+) {
+    if cache.as_ref().map(|(read, _)| read.as_path()) != Some(path) {
+        let properties = properties::read(path).map_err(|e| e.to_string());
+        *cache = Some((path.to_path_buf(), properties));
+    }
+    match cache {
+        Some((_, Ok(properties))) => {
+            let copied = properties.show(ui);
+            if copied {
+                status.info(format!("Copied {}", path.display()));
+            }
+        }
+        Some((_, Err(e))) => {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                tr!("failed-to-read-properties", error = e.clone()),
+            );
+        }
+        None => {}
+    }
+}
+
+fn get_file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_string_lossy().to_string()
+}
+
+// Shows the window if it is minimized or behind others.
+fn bring_to_front(ctx: &egui::Context) {
+    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+}
+
+// Whether a menu, popup or modal was open in the last frame, which Escape closes instead of
+// doing what it does in the app. They are the areas in front that take the mouse.
+fn escape_closes_something(ctx: &egui::Context) -> bool {
+    let layers = ctx.memory(|memory| memory.areas().visible_layer_ids());
+    layers.into_iter().any(|layer| {
+        layer.order == egui::Order::Foreground
+            && egui::AreaState::load(ctx, layer.id).is_some_and(|area| area.interactable)
+    })
+}
+
+// Gives the keyboard focus to the main button of a dialog, so that Enter presses it and Tab goes
+// on from there.
+fn focus_if_nothing_is(response: &egui::Response) {
+    if response.ctx.memory(|memory| memory.focused().is_none()) {
+        response.request_focus();
+    }
+}
+
+// Context menu of the image. Offers with the mouse what the keys do.
+fn show_image_menu(ui: &mut egui::Ui, entries: &[FolderLetterEntry]) -> Option<ImageMenuAction> {
+    let mut action = None;
+    if !entries.is_empty() {
+        ui.menu_button(tr!("move-to"), |ui| {
+            for entry in entries {
+                let text = format!(
+                    "{} ({})",
+                    remote::display_destination(&entry.folder),
+                    entry.letter
+                );
+                if ui.button(text).clicked() {
+                    action = Some(ImageMenuAction::MoveTo(entry.folder.clone()));
                 }
             }
+        });
+        ui.separator();
+    }
+    if ui
+        .button(tr!("keep-here"))
+        .on_hover_text(tr!("keep-here-hint"))
+        .clicked()
+    {
+        action = Some(ImageMenuAction::Keep);
+    }
+    if ui.button(tr!("copy-path")).clicked() {
+        action = Some(ImageMenuAction::CopyPath);
+    }
+    if ui.button(tr!("open-externally")).clicked() {
+        action = Some(ImageMenuAction::OpenExternally);
+    }
+    if ui.button(tr!("show-in-file-manager")).clicked() {
+        action = Some(ImageMenuAction::Reveal);
+    }
+    ui.separator();
+    if ui.button(tr!("rename-menu")).clicked() {
+        action = Some(ImageMenuAction::Rename);
+    }
+    ui.menu_button(tr!("delete"), |ui| {
+        if ui.button(tr!("delete-permanently")).clicked() {
+            action = Some(ImageMenuAction::Delete);
+        }
+    });
+    if action.is_some() {
+        ui.close_menu();
+    }
+    action
+}
 
-            // If registered letter is pressed, move the file to the folder.
-            for entry in self.folder_letter_entries.clone().iter() {
-                let letter = entry.letter;
-                let Some(key) = egui::Key::from_name(&letter.to_string()) else {
-                    // TODO: This probably spams the log. Do it on register.
-                    log::error!("Invalid folder letter: {}", letter);
-                    continue;
+// Whether the file of a listed image is gone. Images in archives are not files of their own.
+fn is_missing(path: &Path) -> bool {
+    archive::split(path).is_none() && !path.exists()
+}
+
+// Whether undoing the move `entry`, which failed, may work later. Not if the moved file is gone,
+// nor if it was uploaded.
+fn can_undo_later(entry: &MoveLogEntry) -> bool {
+    remote::parse(&entry.dest).is_none()
+        && entry.dest.exists()
+        && !(entry.linked && is_missing(&entry.src))
+}
+
+// Placeholder shown instead of an image that failed to decode. The size of the file is read into
+// `size_cache` once per image.
+fn show_broken_image(
+    ui: &mut egui::Ui,
+    path: &Path,
+    error: &egui::load::LoadError,
+    size: egui::Vec2,
+    size_cache: &mut Option<(PathBuf, Result<u64, String>)>,
+) -> Option<BrokenImageAction> {
+    if size_cache.as_ref().map(|(read, _)| read.as_path()) != Some(path) {
+        let file_size = fs::metadata(path)
+            .map(|metadata| metadata.len())
+            .map_err(|e| e.to_string());
+        *size_cache = Some((path.to_path_buf(), file_size));
+    }
+    let mut action = None;
+    ui.allocate_ui(size, |ui| {
+        ui.set_min_size(size);
+        ui.vertical_centered(|ui| {
+            ui.heading(tr!("cannot-display-image"));
+            ui.colored_label(ui.visuals().error_fg_color, error.to_string());
+            ui.label(path.to_string_lossy());
+            if let Some((_, file_size)) = size_cache {
+                match file_size {
+                    Ok(bytes) => ui.label(tr!("file-size", bytes = *bytes)),
+                    Err(e) => ui.label(tr!("failed-to-read-file-info", error = e.as_str())),
                 };
-                if !input.key_pressed(key) {
-                    continue;
+            }
+            ui.horizontal(|ui| {
+                if ui.button(tr!("skip")).clicked() {
+                    action = Some(BrokenImageAction::Skip);
+                }
+                if ui.button(tr!("delete")).clicked() {
+                    action = Some(BrokenImageAction::Delete);
+                }
+                if ui.button(tr!("move-to-quarantine")).clicked() {
+                    action = Some(BrokenImageAction::Quarantine);
+                }
+            });
+        });
+    });
+    action
+}
+
+impl MyApp {
+    fn new(cc: &eframe::CreationContext<'_>, args: Args, server: Option<instance::Server>) -> Self {
+        let settings = Settings::load(cc.storage);
+        i18n::set_language(settings.language);
+        logging::set_file_logging(&app_dirs::log_dir(), settings.log_to_file);
+        let max_texture_side = Arc::new(AtomicU32::new(settings.max_texture_side));
+        cc.egui_ctx
+            .add_image_loader(Arc::new(DownscalingLoader::new(max_texture_side.clone())));
+        let thumbnails = Thumbnails::new(ThumbnailStore::new(
+            app_dirs::cache_dir().join("thumbnails"),
+            settings.thumbnail_cache_bytes(),
+        ));
+        let interrupted = match journal::Journal::open(&app_dirs::data_dir().join("journal.jsonl"))
+        {
+            Ok((journal, interrupted)) => {
+                journal::install(journal);
+                interrupted
+                    .into_iter()
+                    .map(|operation| {
+                        // Hashes both files when both exist, so only once.
+                        let interruption = journal::Interruption::of(&operation);
+                        (operation, interruption)
+                    })
+                    .collect()
+            }
+            Err(e) => {
+                log::error!("Failed to open the journal: {}", e);
+                Vec::new()
+            }
+        };
+        let recovery_path = app_dirs::data_dir().join("recovery.json");
+        let crash = match recovery::read(&recovery_path) {
+            Ok(crash) => crash,
+            Err(e) => {
+                log::error!("Failed to read the recovery file: {}", e);
+                None
+            }
+        };
+        let mut app = Self {
+            settings,
+            layout: Layout::load(cc.storage),
+            max_texture_side,
+            thumbnails: Some(thumbnails),
+            interrupted,
+            crash,
+            recovery_path: Some(recovery_path),
+            read_only: args.read_only,
+            handovers: server.map(|server| server.listen(cc.egui_ctx.clone())),
+            ..Default::default()
+        };
+        app.load_script();
+        match args.folder {
+            Some(folder) => app.open_image_folder(folder),
+            None if !Settings::is_saved(cc.storage) => app.wizard = Some(Wizard::default()),
+            None => {}
+        }
+        app
+    }
+
+    fn show_wizard(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.wizard.as_mut().and_then(|wizard| wizard.show(ctx)) else {
+            return;
+        };
+        self.wizard = None;
+        self.apply_wizard(action);
+    }
+
+    fn apply_wizard(&mut self, action: WizardAction) {
+        let (source, destinations) = match action {
+            WizardAction::Finish {
+                source,
+                destinations,
+            } => (source, destinations),
+            WizardAction::Practice => {
+                self.start_practice();
+                return;
+            }
+            WizardAction::Skip => return,
+        };
+        self.open_image_folder(source);
+        for (folder, letter, color) in destinations {
+            self.assign_letter(folder.clone(), letter);
+            if let Some(entry) = self
+                .folder_letter_entries
+                .iter_mut()
+                .find(|entry| entry.folder == folder)
+            {
+                entry.color = color;
+            }
+        }
+        self.status.info(tr!("wizard-done"));
+    }
+
+    // Opens the folders that later launches handed over and brings the window to the front.
+    fn poll_handovers(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.handovers else {
+            return;
+        };
+        let received: Vec<Option<PathBuf>> = receiver.try_iter().collect();
+        if received.is_empty() {
+            return;
+        }
+        for folder in received.into_iter().flatten() {
+            self.open_or_queue_folder(folder);
+        }
+        bring_to_front(ctx);
+    }
+
+    // Opens a folder that came from outside of the window, like another launch or the tray icon.
+    fn open_or_queue_folder(&mut self, folder: PathBuf) {
+        if self.image_manager.folders().contains(&folder) {
+            return;
+        }
+        // Queued after the open folders rather than replacing them, so that nothing is lost.
+        if self.image_manager.folders().is_empty() {
+            self.open_image_folder(folder);
+        } else {
+            self.add_source_folder(folder);
+        }
+    }
+
+    fn poll_tray(&mut self, ctx: &egui::Context) {
+        let Some(tray) = &self.tray else {
+            return;
+        };
+        for event in tray.poll() {
+            match event {
+                tray::TrayEvent::OpenFolder(folder) => {
+                    self.open_or_queue_folder(folder);
+                    bring_to_front(ctx);
+                }
+                tray::TrayEvent::Show => bring_to_front(ctx),
+                tray::TrayEvent::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            }
+        }
+    }
+
+    fn show_tray_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(tray::supported(), |ui| {
+                ui.checkbox(&mut self.settings.tray_icon, tr!("tray-icon"))
+                    .on_hover_text(tr!("tray-icon-hint"))
+                    .on_disabled_hover_text(tr!("tray-unsupported"));
+            });
+            match &self.settings.watched_folder {
+                Some(folder) => ui.label(folder.to_string_lossy()),
+                None => ui.weak(tr!("no-folder-selected")),
+            };
+            if ui.button(tr!("choose-watched-folder")).clicked() {
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    self.settings.watched_folder = Some(folder);
                 }
+            }
+        });
+    }
+
+    fn show_log_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let checkbox = ui
+                .checkbox(&mut self.settings.log_to_file, tr!("log-to-file"))
+                .on_hover_text(tr!("log-to-file-hint"));
+            if checkbox.changed() {
+                logging::set_file_logging(&app_dirs::log_dir(), self.settings.log_to_file);
+            }
+            if ui.button(tr!("log-menu")).clicked() {
+                self.open_log();
+            }
+        });
+    }
+
+    fn open_log(&mut self) {
+        self.log_viewer.load(&app_dirs::log_dir());
+        self.show_log = true;
+    }
+
+    fn show_log_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new(tr!("log"))
+            .open(&mut self.show_log)
+            .default_size(egui::vec2(700.0, 400.0))
+            .show(ctx, |ui| {
+                self.log_viewer.show(ui, &app_dirs::log_dir());
+            });
+    }
+
+    // Undoes moves for up to a few milliseconds, so that a long revert keeps the window
+    // responsive.
+    fn step_session_revert(&mut self) {
+        let Some(revert) = self
+            .session_revert
+            .as_ref()
+            .filter(|revert| !revert.finished)
+        else {
+            return;
+        };
+        let started = Instant::now();
+        let mut next = revert.next.min(self.move_log.len());
+        let mut restored = 0;
+        let mut failed = Vec::new();
+        while next > 0 && started.elapsed() <= Duration::from_millis(30) {
+            next -= 1;
+            let src = self.move_log[next].src.clone();
+            // A file that took the name since is reported rather than renamed, so that the
+            // user can sort out each one.
+            match self.undo_move_as(next, CollisionPolicy::Skip) {
+                Ok(_) => restored += 1,
+                Err(e) => failed.push((src, e)),
+            }
+        }
+        let Some(revert) = &mut self.session_revert else {
+            return;
+        };
+        revert.next = next;
+        revert.restored += restored;
+        revert.failed.extend(failed);
+        if next > 0 {
+            return;
+        }
+        revert.finished = true;
+        if revert.failed.is_empty() {
+            self.status
+                .done(format!("Moved {} images back.", revert.restored));
+        } else {
+            self.status.done(format!(
+                "Moved {} images back. {} could not be.",
+                revert.restored,
+                revert.failed.len()
+            ));
+        }
+    }
+
+    fn show_session_revert_window(&mut self, ctx: &egui::Context) {
+        self.step_session_revert();
+        let running = self
+            .session_revert
+            .as_ref()
+            .is_some_and(|revert| !revert.finished);
+        if running {
+            ctx.request_repaint();
+        }
+        let batch_running = self
+            .batch
+            .as_ref()
+            .is_some_and(|batch| !batch.is_finished());
+        let mut start = false;
+        let mut cancel = false;
+        egui::Window::new(tr!("revert-session"))
+            .open(&mut self.show_session_revert)
+            .default_size(egui::vec2(500.0, 300.0))
+            .show(ctx, |ui| {
+                ui.label(tr!("revert-session-intro"));
+                ui.horizontal(|ui| {
+                    ui.label(tr!("moves-to-undo", count = self.move_log.len()));
+                    if ui
+                        .add_enabled(
+                            !running && !batch_running && !self.move_log.is_empty(),
+                            egui::Button::new(tr!("revert-all")),
+                        )
+                        .on_disabled_hover_text(tr!("wait-for-moves"))
+                        .clicked()
+                    {
+                        start = true;
+                    }
+                });
+                let Some(revert) = &self.session_revert else {
+                    return;
+                };
+                let done = revert.restored + revert.failed.len();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / revert.total.max(1) as f32)
+                            .text(format!("{}/{}", done, revert.total)),
+                    );
+                    if running && ui.button(tr!("cancel")).clicked() {
+                        cancel = true;
+                    }
+                });
+                if !revert.finished {
+                    return;
+                }
+                ui.label(tr!("moved-back", count = revert.restored));
+                if revert.left > 0 {
+                    ui.label(tr!("revert-cancelled", count = revert.left));
+                }
+                if !revert.failed.is_empty() {
+                    ui.label(tr!("revert-failed", count = revert.failed.len()));
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for (src, error) in &revert.failed {
+                            ui.horizontal(|ui| {
+                                ui.label(get_file_name(src));
+                                ui.colored_label(ui.visuals().error_fg_color, error);
+                            });
+                        }
+                    });
+                }
+            });
+        if start {
+            self.session_revert = Some(SessionRevert {
+                total: self.move_log.len(),
+                next: self.move_log.len(),
+                ..Default::default()
+            });
+        }
+        if let Some(revert) = self.session_revert.as_mut().filter(|_| cancel) {
+            revert.left = revert.total - revert.restored - revert.failed.len();
+            revert.finished = true;
+            self.status.info(format!(
+                "Stopped reverting. Moved {} images back.",
+                revert.restored
+            ));
+        }
+    }
+
+    // Lists the copies that were interrupted last time and how to finish them.
+    fn show_interrupted_window(&mut self, ctx: &egui::Context) {
+        if self.interrupted.is_empty() {
+            return;
+        }
+        let mut resolved = Vec::new();
+        let mut repair = Vec::new();
+        egui::Window::new(tr!("interrupted-operations")).show(ctx, |ui| {
+            ui.label(tr!("interrupted-intro"));
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (operation, interruption) in &self.interrupted {
+                    ui.label(format!(
+                        "{} \u{2192} {}",
+                        operation.src.display(),
+                        operation.dest.display()
+                    ));
+                    ui.horizontal(|ui| {
+                        ui.label(interruption.describe());
+                        if let Some(label) = interruption.repair_label() {
+                            if ui.button(label).clicked() {
+                                repair.push(operation.clone());
+                            }
+                        }
+                        if ui.button(tr!("dismiss")).clicked() {
+                            resolved.push(operation.id);
+                        }
+                    });
+                    ui.separator();
+                }
+            });
+            if ui.button(tr!("repair-all")).clicked() {
+                // Conflicts need a look by hand.
+                repair.extend(
+                    self.interrupted
+                        .iter()
+                        .filter(|(_, interruption)| {
+                            *interruption != journal::Interruption::Conflict
+                        })
+                        .map(|(operation, _)| operation.clone()),
+                );
+            }
+        });
+        for operation in repair {
+            match journal::repair(&operation) {
+                Ok(()) => resolved.push(operation.id),
+                Err(e) => self.status.error(format!(
+                    "Failed to repair {}: {}",
+                    operation.src.display(),
+                    e
+                )),
+            }
+        }
+        for id in resolved {
+            journal::end(Some(id));
+            self.interrupted.retain(|(operation, _)| operation.id != id);
+        }
+    }
+
+    // Keeps what the panic hook saves up to date.
+    fn update_recovery_snapshot(&self) {
+        recovery::update(recovery::Snapshot {
+            folders: self.image_manager.folders().to_vec(),
+            image: self
+                .image_manager
+                .current_image_path()
+                .map(Path::to_path_buf),
+            index: self.image_manager.current_index(),
+            pending: self
+                .retry_queue
+                .entries()
+                .iter()
+                .map(|queued| recovery::PendingMove {
+                    src: queued.src.clone(),
+                    dest_dir: remote::without_password(&queued.dest_dir),
+                })
+                .collect(),
+        });
+    }
+
+    // Replaces the destinations and folders with the ones of generated images to try the keys on.
+    fn start_practice(&mut self) {
+        if self.practice.is_some() {
+            return;
+        }
+        let root = std::env::temp_dir().join("image-organizer-practice");
+        let (practice, demo) = match Practice::start(&root) {
+            Ok(started) => started,
+            Err(e) => {
+                self.status
+                    .error(format!("Failed to create the practice images: {}", e));
+                return;
+            }
+        };
+        self.practice = Some(PracticeSession {
+            practice,
+            entries: std::mem::take(&mut self.folder_letter_entries),
+            folders: self.image_manager.folders().to_vec(),
+        });
+        self.use_demo(demo);
+    }
+
+    fn use_demo(&mut self, demo: demo::Demo) {
+        self.folder_letter_entries.clear();
+        for (folder, letter, color) in demo.destinations {
+            self.assign_letter(folder.clone(), letter);
+            if let Some(entry) = self.folder_letter_entries.last_mut() {
+                entry.color = Some(color);
+            }
+        }
+        self.open_image_folder(demo.inbox);
+        self.image_manager.clear_filter();
+    }
+
+    // Forgets the practice moves, so that undo does not reach for them.
+    fn forget_practice_moves(&mut self) {
+        if let Some(session) = &self.practice {
+            self.move_log
+                .retain(|entry| !session.practice.contains(&entry.dest));
+        }
+    }
+
+    fn leave_practice(&mut self) {
+        self.forget_practice_moves();
+        let Some(session) = self.practice.take() else {
+            return;
+        };
+        self.folder_letter_entries = session.entries;
+        let mut folders = session.folders.into_iter();
+        match folders.next() {
+            Some(first) => self.open_image_folder(first),
+            None => {
+                self.image_manager.clear();
+                self.image_manager.set_folders(Vec::new());
+            }
+        }
+        for folder in folders {
+            self.add_source_folder(folder);
+        }
+        if let Err(e) = fs::remove_dir_all(session.practice.root()) {
+            log::warn!("Failed to remove the practice images: {}", e);
+        }
+    }
+
+    // Checks off what was tried since the last frame.
+    fn update_practice(&mut self) {
+        let Some(session) = &mut self.practice else {
+            return;
+        };
+        let practice = &mut session.practice;
+        let mut finished = false;
+        for entry in &self.audit_log {
+            let task = match entry.stamp.operation {
+                audit::Operation::Move if practice.contains(&entry.dest) => practice::Task::Move,
+                audit::Operation::Undo if practice.contains(&entry.src) => practice::Task::Undo,
+                _ => continue,
+            };
+            finished |= practice.complete(task);
+        }
+        if self.image_manager.filter().is_some() {
+            finished |= practice.complete(practice::Task::Filter);
+        }
+        if finished {
+            self.status.done(tr!("practice-done"));
+        }
+    }
+
+    fn show_practice_window(&mut self, ctx: &egui::Context) {
+        self.update_practice();
+        let Some(session) = &self.practice else {
+            return;
+        };
+        let mut action = None;
+        egui::Window::new(tr!("practice"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-16.0, 48.0))
+            .show(ctx, |ui| {
+                action = session.practice.show(ui);
+            });
+        match action {
+            Some(PracticeAction::Reset) => {
+                self.forget_practice_moves();
+                let Some(session) = &mut self.practice else {
+                    return;
+                };
+                match session.practice.reset() {
+                    Ok(demo) => self.use_demo(demo),
+                    Err(e) => self
+                        .status
+                        .error(format!("Failed to create the practice images: {}", e)),
+                }
+            }
+            Some(PracticeAction::Leave) => self.leave_practice(),
+            None => {}
+        }
+    }
+
+    fn show_crash_window(&mut self, ctx: &egui::Context) {
+        let Some(crash) = &self.crash else {
+            return;
+        };
+        let mut resume = false;
+        let mut dismiss = false;
+        egui::Window::new("The app closed unexpectedly")
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "It crashed at {} UTC. The session can be picked up where it stopped.",
+                    metadata::format_timestamp(crash.time)
+                ));
+                let snapshot = &crash.snapshot;
+                for folder in &snapshot.folders {
+                    ui.label(folder.to_string_lossy());
+                }
+                if let Some(image) = &snapshot.image {
+                    ui.label(format!(
+                        "At image {}: {}",
+                        snapshot.index + 1,
+                        get_file_name(image)
+                    ));
+                }
+                if !snapshot.pending.is_empty() {
+                    ui.separator();
+                    ui.label("These moves were waiting to be retried and were not done:");
+                    for pending in &snapshot.pending {
+                        ui.label(format!(
+                            "{} \u{2192} {}",
+                            pending.src.display(),
+                            pending.dest_dir.display()
+                        ));
+                    }
+                }
+                ui.collapsing("Details", |ui| {
+                    ui.monospace(&crash.message);
+                });
+                ui.horizontal(|ui| {
+                    resume = ui
+                        .add_enabled(!snapshot.folders.is_empty(), egui::Button::new("Resume"))
+                        .clicked();
+                    dismiss = ui.button("Dismiss").clicked();
+                });
+            });
+        if resume {
+            self.resume_crashed_session();
+        } else if dismiss {
+            self.dismiss_crash();
+        }
+    }
+
+    fn resume_crashed_session(&mut self) {
+        let Some(crash) = self.crash.clone() else {
+            return;
+        };
+        let mut folders = crash.snapshot.folders.into_iter();
+        if let Some(first) = folders.next() {
+            self.open_image_folder(first);
+        }
+        for folder in folders {
+            self.add_source_folder(folder);
+        }
+        self.resume_image = crash.snapshot.image;
+        self.dismiss_crash();
+    }
+
+    fn dismiss_crash(&mut self) {
+        self.crash = None;
+        let Some(path) = &self.recovery_path else {
+            return;
+        };
+        if let Err(e) = recovery::clear(path) {
+            self.status
+                .error(format!("Failed to remove the recovery file: {}", e));
+        }
+    }
+
+    // Applies settings that other parts of the app keep a copy of.
+    fn apply_settings(&mut self, ctx: &egui::Context) {
+        self.image_manager.set_cache_limits(
+            self.settings.keep_window,
+            self.settings.memory_budget_bytes(),
+        );
+        self.image_manager
+            .set_hide_kept(self.settings.hide_kept_images);
+        self.image_manager
+            .set_wrap_around(self.settings.wrap_around);
+        if self
+            .image_manager
+            .set_stack_bursts(self.settings.stack_bursts)
+        {
+            self.dates_stale = true;
+        }
+        if let Some(thumbnails) = &self.thumbnails {
+            thumbnails
+                .store()
+                .set_max_bytes(self.settings.thumbnail_cache_bytes());
+        }
+        if self.settings.use_library_index != self.library_index.is_some() {
+            self.toggle_library_index();
+        }
+        if self.settings.sort_order != self.sort_order {
+            self.sort_order = self.settings.sort_order;
+            self.dates_stale = true;
+            self.sizes_stale = true;
+            self.colors_stale = true;
+            if self.sort_order != SortOrder::DateTaken {
+                if let Some(scan) = self.date_scan.take() {
+                    scan.cancel();
+                }
+                self.image_manager.set_dates(HashMap::new());
+            }
+            if self.sort_order != SortOrder::Largest {
+                if let Some(scan) = self.size_scan.take() {
+                    scan.cancel();
+                }
+                self.image_manager.set_sizes(HashMap::new());
+                self.freed_bytes = 0;
+            }
+            if self.sort_order != SortOrder::Color {
+                if let Some(scan) = self.color_scan.take() {
+                    scan.cancel();
+                }
+                self.image_manager.set_colors(HashMap::new());
+            }
+        }
+        i18n::set_language(self.settings.language);
+        let tray_folder = match &self.settings.watched_folder {
+            Some(folder) if self.settings.tray_icon && tray::supported() => Some(folder.clone()),
+            _ => None,
+        };
+        if tray_folder != self.applied_tray {
+            // The old icon goes away when dropped.
+            self.tray = None;
+            if let Some(folder) = &tray_folder {
+                match tray::TrayIcon::start(folder, ctx.clone()) {
+                    Ok(icon) => self.tray = Some(icon),
+                    Err(e) => self
+                        .status
+                        .error(format!("Failed to show the tray icon: {}", e)),
+                }
+            }
+            self.applied_tray = tray_folder;
+        }
+        let theme = (self.settings.theme, self.settings.accent_color);
+        if self.applied_theme != Some(theme) {
+            theme::apply(ctx, theme.0, theme.1);
+            self.applied_theme = Some(theme);
+        }
+        // Ctrl + and Ctrl - zoom too. Keep what they set.
+        if self
+            .applied_ui_scale
+            .is_some_and(|scale| scale != ctx.zoom_factor())
+        {
+            self.settings.ui_scale = ctx.zoom_factor();
+            self.applied_ui_scale = Some(ctx.zoom_factor());
+        }
+        if self.applied_ui_scale != Some(self.settings.ui_scale) {
+            ctx.set_zoom_factor(self.settings.ui_scale);
+            self.applied_ui_scale = Some(self.settings.ui_scale);
+        }
+        if self.applied_font_size != Some(self.settings.font_size) {
+            theme::apply_font_size(ctx, self.settings.font_size);
+            self.applied_font_size = Some(self.settings.font_size);
+        }
+        let max_side = self.settings.max_texture_side;
+        let resized = self.max_texture_side.swap(max_side, Ordering::Relaxed) != max_side;
+        let recolored = color::set_enabled(self.settings.color_management);
+        file_ops::set_keep_download_flag(self.settings.keep_download_flag);
+        file_ops::set_collision_policy(self.settings.collisions);
+        if resized || recolored {
+            // Reload everything at the new size or in the new colors.
+            ctx.forget_all_images();
+        }
+    }
+
+    fn move_current_image_to_dest(&mut self, dest_dir: &Path) -> Result<MoveLogEntry> {
+        let index = self.image_manager.current_index();
+        let Some(image_path) = self.image_manager.remove_current_image() else {
+            bail!("Failed to find current image");
+        };
+
+        let image_path = image_path.clone();
+        match self.move_and_record(&image_path, dest_dir) {
+            Ok(log_entry) => {
+                self.image_manager
+                    .go_on_after_removal(index, self.settings.after_move);
+                Ok(log_entry)
+            }
+            Err(e) => {
+                log::error!("Failed to move file: {}", e);
+                // The file is still in the source folder, so keep showing it.
+                self.image_manager
+                    .add_image_to_current_position(&image_path);
+                Err(e.into())
+            }
+        }
+    }
+
+    // Moves the image `src` and the files that go with it to `dest_dir`, and records the move.
+    // The files are moved on another thread, which is given up on after the network timeout so
+    // that a share that stalls in the middle of a move does not freeze the window. Nothing is
+    // retried here; a file in use goes to the retry queue instead.
+    fn move_and_record(&mut self, src: &Path, dest_dir: &Path) -> io::Result<MoveLogEntry> {
+        let companion_files = self.companion_files(src);
+        let symlinks = self.settings.symlinks;
+        let (owned_src, owned_dest_dir) = (src.to_path_buf(), dest_dir.to_path_buf());
+        let (new_path, companions, stamp) =
+            file_ops::with_timeout(src, self.network_timeout(), move || {
+                let policy = RetryPolicy::once();
+                let new_path = move_image(&owned_src, &owned_dest_dir, symlinks, &policy)?;
+                let companions: Vec<_> = companion_files
+                    .into_iter()
+                    .map(|companion| {
+                        let moved =
+                            file_ops::move_companion(&companion, &owned_src, &new_path, &policy);
+                        (companion, moved)
+                    })
+                    .collect();
+                let stamp = Stamp::of(audit::Operation::Move, &new_path);
+                Ok((new_path, companions, stamp))
+            })?;
+        log::info!("Moved file {} to {}", src.display(), dest_dir.display());
+        let mut moved_companions = Vec::new();
+        // A failure is reported but does not undo the move of the image itself.
+        for (companion, moved) in companions {
+            match moved {
+                Ok(dest) => moved_companions.push((companion, dest)),
+                Err(e) => self.status.error(format!(
+                    "Failed to move {}: {}",
+                    get_file_name(&companion),
+                    e
+                )),
+            }
+        }
+        let log_entry = MoveLogEntry {
+            src: src.to_path_buf(),
+            dest: new_path,
+            companions: moved_companions,
+            linked: false,
+            copies: Vec::new(),
+            stamp,
+        };
+        self.record_move(log_entry.clone());
+        Ok(log_entry)
+    }
+
+    // Files that go with the image at `path`: the RAW file of a RAW+JPEG pair and the sidecar
+    // files of both.
+    fn companion_files(&self, path: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        if self.settings.pair_raw_with_jpeg {
+            files.extend(raw::paired_raw_files(path));
+        }
+        let mut sidecars = sidecar::sidecar_files(path);
+        for raw_file in &files {
+            sidecars.extend(sidecar::sidecar_files(raw_file));
+        }
+        for sidecar in sidecars {
+            // "IMG_1234.xmp" belongs to both files of a pair.
+            if !files.contains(&sidecar) {
+                files.push(sidecar);
+            }
+        }
+        files
+    }
+
+    // Moves the current image unless `dest_dir` asks first, in which case the user is asked.
+    fn request_move(&mut self, dest_dir: &Path) {
+        if let Some(session) = &self.practice {
+            let outside = |path: &Path| !session.practice.contains(path);
+            if outside(dest_dir) || self.image_manager.current_image_path().is_some_and(outside) {
+                self.status.warn(tr!("practice-outside"));
+                return;
+            }
+        }
+        let confirm = self.settings.confirm_dangerous_destinations
+            && self
+                .folder_letter_entries
+                .iter()
+                .any(|entry| entry.folder == dest_dir && entry.confirm);
+        // A stacked burst is moved as a whole.
+        if let Some(paths) = self.image_manager.current_stack() {
+            let dest_dir = dest_dir.to_path_buf();
+            if self.read_only {
+                self.request_move_images(paths, dest_dir);
+                self.next_image();
+            } else if confirm {
+                self.pending_confirmation =
+                    Some(PendingConfirmation::MoveImages { paths, dest_dir });
+            } else {
+                self.request_move_images(paths, dest_dir);
+            }
+            return;
+        }
+        if self.read_only {
+            self.plan_move(dest_dir);
+            return;
+        }
+        match self.image_manager.current_image_path() {
+            Some(src) if confirm => {
+                self.pending_confirmation = Some(PendingConfirmation::Move {
+                    src: src.to_path_buf(),
+                    dest_dir: dest_dir.to_path_buf(),
+                });
+            }
+            _ => self.move_unless_identical(dest_dir),
+        }
+    }
+
+    // A file anywhere under `dest_dir` with the same contents as `src`. The library index finds
+    // it without reading the folders, if it knows the file.
+    fn identical_at_destination(&self, src: &Path, dest_dir: &Path) -> Option<PathBuf> {
+        if let Some(index) = &self.library_index {
+            let hash = index
+                .get(src)
+                .ok()
+                .flatten()
+                .and_then(|image| image.hash)
+                .or_else(|| file_ops::hash_file(src).ok());
+            let found = hash
+                .and_then(|hash| index.find_by_hash(&hash, dest_dir).ok())
+                .unwrap_or_default()
+                .into_iter()
+                // The index may be out of date.
+                .find(|path| path != src && file_ops::same_contents(src, path).unwrap_or(false));
+            if found.is_some() {
+                return found;
+            }
+        }
+        file_ops::find_identical(src, dest_dir).ok().flatten()
+    }
+
+    // Moves the current image, but first asks if `dest_dir` already has the same file, which the
+    // move would only duplicate under another name.
+    fn move_unless_identical(&mut self, dest_dir: &Path) {
+        let src = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf());
+        let existing = src.as_ref().filter(|_| {
+            self.settings.confirm_identical
+                && remote::parse(dest_dir).is_none()
+                && !self.links_to(dest_dir)
+        });
+        if let Some(src) = existing {
+            if let Some(existing) = self.identical_at_destination(src, dest_dir) {
+                self.pending_confirmation = Some(PendingConfirmation::Identical {
+                    src: src.clone(),
+                    dest_dir: dest_dir.to_path_buf(),
+                    existing,
+                });
+                return;
+            }
+        }
+        self.move_current_image_and_report(dest_dir);
+    }
+
+    // Moves several images, asking first if there are more than the settings allow.
+    fn request_move_images(&mut self, paths: Vec<PathBuf>, dest_dir: PathBuf) {
+        if self.read_only {
+            for path in &paths {
+                self.plan.push(path, &dest_dir);
+            }
+            self.status.info(format!(
+                "Planned to move {} images to {}",
+                paths.len(),
+                remote::display_destination(&dest_dir)
+            ));
+            return;
+        }
+        let limit = self.settings.confirm_batch_size;
+        if limit > 0 && paths.len() > limit {
+            self.pending_confirmation = Some(PendingConfirmation::MoveImages { paths, dest_dir });
+        } else {
+            self.move_images(&paths, &dest_dir);
+        }
+    }
+
+    // Adds the move of the current image to the plan and goes on to the next image.
+    fn plan_move(&mut self, dest_dir: &Path) {
+        let Some(src) = self.image_manager.current_image_path() else {
+            return;
+        };
+        let src = src.to_path_buf();
+        self.plan.push(&src, dest_dir);
+        self.status.info(format!(
+            "Planned {} -> {}",
+            get_file_name(&src),
+            remote::display_destination(dest_dir)
+        ));
+        self.next_image();
+    }
+
+    // Warns and returns true if read-only mode forbids changing files.
+    fn refuse_in_read_only(&mut self) -> bool {
+        if self.read_only {
+            self.status
+                .warn("Read-only mode is on. Nothing on disk is changed.");
+        }
+        self.read_only
+    }
+
+    fn show_plan(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Read-only. {} moves planned.", self.plan.len()));
+            if ui
+                .add_enabled(!self.plan.is_empty(), egui::Button::new("Export plan..."))
+                .clicked()
+            {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name("plan.csv")
+                    .save_file()
+                {
+                    match self.plan.save(&path) {
+                        Ok(()) => {
+                            self.plan_exported = self.plan.len();
+                            self.status
+                                .info(format!("Exported the plan to {}", path.display()));
+                        }
+                        Err(e) => self
+                            .status
+                            .error(format!("Failed to export the plan: {}", e)),
+                    }
+                }
+            }
+            if ui
+                .add_enabled(!self.plan.is_empty(), egui::Button::new("Clear"))
+                .clicked()
+            {
+                self.plan.clear();
+            }
+        });
+    }
+
+    // What closing the app now would lose, one line each.
+    fn pending_on_exit(&self) -> Vec<String> {
+        let mut pending = Vec::new();
+        if let Some(uploader) = self
+            .uploader
+            .as_ref()
+            .filter(|uploader| uploader.pending() > 0)
+        {
+            pending.push(tr!("exit-uploads", count = uploader.pending()));
+        }
+        if !self.retry_queue.is_empty() {
+            pending.push(tr!(
+                "exit-retries",
+                count = self.retry_queue.entries().len()
+            ));
+        }
+        if let Some(batch) = self.batch.as_ref().filter(|batch| !batch.is_finished()) {
+            let (done, total) = batch.progress();
+            pending.push(tr!(
+                "exit-batch",
+                title = batch.title(),
+                done = done,
+                total = total
+            ));
+        }
+        if let Some(revert) = self
+            .session_revert
+            .as_ref()
+            .filter(|revert| !revert.finished)
+        {
+            pending.push(tr!(
+                "exit-revert",
+                done = revert.restored,
+                total = revert.total
+            ));
+        }
+        if self.pending_confirmation.is_some() {
+            pending.push(tr!("exit-confirmation"));
+        }
+        if !self.plan.is_empty() && self.plan.len() != self.plan_exported {
+            pending.push(tr!("exit-plan", count = self.plan.len()));
+        }
+        pending
+    }
+
+    // Keeps the window open when closing it would lose something, until the user confirms.
+    fn check_close_request(&mut self, ctx: &egui::Context) {
+        if !ctx.input(|input| input.viewport().close_requested()) || self.exit_confirmed {
+            return;
+        }
+        if !self.pending_on_exit().is_empty() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.confirm_exit = true;
+        }
+    }
+
+    fn show_exit_dialog(&mut self, ctx: &egui::Context) {
+        if !self.confirm_exit {
+            return;
+        }
+        let pending = self.pending_on_exit();
+        let mut quit = false;
+        let mut cancelled = false;
+        let response = egui::Modal::new(egui::Id::new("exit_dialog")).show(ctx, |ui| {
+            ui.heading(tr!("exit-question"));
+            if pending.is_empty() {
+                ui.label(tr!("exit-nothing-pending"));
+            }
+            for line in &pending {
+                ui.label(format!("\u{2022} {}", line));
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                let keep_button = ui.button(tr!("exit-keep-open"));
+                focus_if_nothing_is(&keep_button);
+                cancelled = keep_button.clicked();
+                quit = ui.button(tr!("exit-quit")).clicked();
+            });
+        });
+        if cancelled || response.should_close() {
+            self.confirm_exit = false;
+        }
+        if quit {
+            self.confirm_exit = false;
+            self.exit_confirmed = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    fn show_confirmation_dialog(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.pending_confirmation else {
+            return;
+        };
+        let (question, dest_dir) = match pending {
+            PendingConfirmation::Move { src, dest_dir } => (
+                tr!("confirm-move-file", file = get_file_name(src)),
+                dest_dir,
+            ),
+            PendingConfirmation::MoveImages { paths, dest_dir } => {
+                (tr!("confirm-move-images", count = paths.len()), dest_dir)
+            }
+            PendingConfirmation::Identical { .. } => {
+                self.show_identical_dialog(ctx);
+                return;
+            }
+        };
+        let mut confirmed = false;
+        let mut cancelled = false;
+        let response = egui::Modal::new(egui::Id::new("confirmation_dialog")).show(ctx, |ui| {
+            ui.heading(question);
+            ui.label(tr!(
+                "confirm-destination",
+                folder = remote::display_destination(dest_dir)
+            ));
+            ui.separator();
+            ui.horizontal(|ui| {
+                let move_button = ui.button(tr!("move"));
+                focus_if_nothing_is(&move_button);
+                confirmed = move_button.clicked();
+                cancelled = ui.button(tr!("cancel")).clicked();
+            });
+        });
+        if cancelled || response.should_close() {
+            self.pending_confirmation = None;
+            return;
+        }
+        if !confirmed {
+            return;
+        }
+        match self.pending_confirmation.take() {
+            // Only if it is still the image that was asked about.
+            Some(PendingConfirmation::Move { src, dest_dir })
+                if self.image_manager.current_image_path() == Some(src.as_path()) =>
+            {
+                self.move_unless_identical(&dest_dir);
+            }
+            Some(PendingConfirmation::MoveImages { paths, dest_dir }) => {
+                self.move_images(&paths, &dest_dir)
+            }
+            _ => {}
+        }
+    }
+
+    // Asks what to do with an image that is already at the destination.
+    fn show_identical_dialog(&mut self, ctx: &egui::Context) {
+        let Some(PendingConfirmation::Identical {
+            src,
+            dest_dir,
+            existing,
+        }) = &self.pending_confirmation
+        else {
+            return;
+        };
+        let mut choice = None;
+        let mut cancelled = false;
+        let response = egui::Modal::new(egui::Id::new("identical_dialog")).show(ctx, |ui| {
+            ui.heading(tr!("identical-exists", file = get_file_name(src)));
+            ui.label(tr!(
+                "confirm-destination",
+                folder = remote::display_destination(dest_dir)
+            ));
+            ui.label(tr!(
+                "identical-existing",
+                path = existing.to_string_lossy().to_string()
+            ));
+            ui.separator();
+            ui.horizontal(|ui| {
+                let skip_button = ui.button(tr!("skip-and-delete"));
+                focus_if_nothing_is(&skip_button);
+                if skip_button.clicked() {
+                    choice = Some(true);
+                }
+                if ui.button(tr!("move-anyway")).clicked() {
+                    choice = Some(false);
+                }
+                cancelled = ui.button(tr!("cancel")).clicked();
+            });
+        });
+        if cancelled || response.should_close() {
+            self.pending_confirmation = None;
+        }
+        let Some(delete) = choice else {
+            return;
+        };
+        let Some(PendingConfirmation::Identical {
+            src,
+            dest_dir,
+            existing,
+        }) = self.pending_confirmation.take()
+        else {
+            return;
+        };
+        // Only if it is still the image that was asked about.
+        if self.image_manager.current_image_path() != Some(src.as_path()) {
+            return;
+        }
+        if !delete {
+            self.move_current_image_and_report(&dest_dir);
+            return;
+        }
+        match self.delete_current_image() {
+            Ok(path) => self.status.info(format!(
+                "Deleted {}, which already is at {}",
+                get_file_name(&path),
+                existing.display()
+            )),
+            Err(e) => self.status.error(format!("Failed to delete file: {}", e)),
+        }
+    }
+
+    // Moves several images with their companion files in the background. Each move can be
+    // undone like a normal one.
+    fn move_images(&mut self, paths: &[PathBuf], dest_dir: &Path) {
+        let files: Vec<(PathBuf, Vec<PathBuf>)> = paths
+            .iter()
+            .map(|path| (path.clone(), self.companion_files(path)))
+            .collect();
+        if let Some(remote) = remote::parse(dest_dir) {
+            self.image_manager
+                .remove_images(&paths.iter().cloned().collect());
+            self.status
+                .info(format!("Uploading {} images to {}", paths.len(), remote));
+            for (path, companions) in files {
+                let mut files = vec![path];
+                files.extend(companions);
+                self.queue_upload(remote.clone(), files, false);
+            }
+            return;
+        }
+        if let Err(e) = file_ops::check_reachable(dest_dir, self.network_timeout()) {
+            self.status
+                .error(format!("Did not move {} images: {}", paths.len(), e));
+            return;
+        }
+        // Better than running out of space halfway through.
+        let all_files: Vec<PathBuf> = files
+            .iter()
+            .flat_map(|(path, companions)| std::iter::once(path).chain(companions))
+            .cloned()
+            .collect();
+        if let Err(e) = file_ops::check_space_for(&all_files, dest_dir) {
+            self.status
+                .error(format!("Did not move {} images: {}", paths.len(), e));
+            return;
+        }
+        let link = self.links_to(dest_dir);
+        let title = if link {
+            format!("Linking {} images into {}", paths.len(), dest_dir.display())
+        } else {
+            format!("Moving {} images to {}", paths.len(), dest_dir.display())
+        };
+        let dest_dir = dest_dir.to_path_buf();
+        let stamps = self.stamps_of(paths);
+        let symlinks = self.settings.symlinks;
+        self.start_batch(title, paths.len(), move |on_file| {
+            BatchReport::Move(move_batch(
+                &files, &dest_dir, &stamps, symlinks, link, on_file,
+            ))
+        });
+    }
+
+    // Runs a multi-file operation on a worker thread. Only one runs at a time, so that two of
+    // them cannot work on the same files.
+    fn start_batch(
+        &mut self,
+        title: String,
+        total: usize,
+        work: impl FnOnce(&mut OnFile) -> BatchReport + Send + 'static,
+    ) {
+        if let Some(batch) = self.batch.as_ref().filter(|batch| !batch.is_finished()) {
+            self.status
+                .warn(format!("Wait for \"{}\" to finish first.", batch.title()));
+            return;
+        }
+        self.batch = Some(Batch::start(title, total, work));
+        self.show_batch = true;
+    }
+
+    fn show_batch_window(&mut self, ctx: &egui::Context) {
+        let Some(batch) = &mut self.batch else {
+            return;
+        };
+        let report = batch.poll();
+        if !batch.is_finished() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        // Closing the window of a running batch leaves it running with its progress in a toast.
+        egui::Window::new(batch.title().to_string())
+            .id(egui::Id::new("batch"))
+            .open(&mut self.show_batch)
+            .default_size(egui::vec2(500.0, 300.0))
+            .show(ctx, |ui| batch.show(ui));
+        if batch.is_finished() && !self.show_batch {
+            self.batch = None;
+        }
+        match report {
+            Some(BatchReport::Move(report)) => self.handle_move_report(report),
+            Some(BatchReport::Merge(report)) => self.handle_merge_report(report),
+            Some(BatchReport::Flatten(report)) => self.handle_flatten_report(report),
+            Some(BatchReport::Organize(report)) => self.handle_organize_report(report),
+            Some(BatchReport::Sync(report)) => self.handle_sync_report(report),
+            None => {}
+        }
+    }
+
+    fn handle_move_report(&mut self, report: MoveReport) {
+        let linked = report.moved.iter().filter(|entry| entry.linked).count();
+        let moved: HashSet<PathBuf> = report
+            .moved
+            .iter()
+            .filter(|entry| !entry.linked)
+            .map(|entry| entry.src.clone())
+            .collect();
+        for entry in report.moved {
+            if entry.linked {
+                self.log_move(entry);
+            } else {
+                self.record_move(entry);
+            }
+        }
+        self.image_manager.remove_images(&moved);
+        for (path, error) in &report.errors {
+            self.status
+                .error(format!("Failed to move {}: {}", get_file_name(path), error));
+        }
+        if linked > 0 {
+            self.status.done(format!(
+                "Linked {} images into {}",
+                linked,
+                report.dest_dir.display()
+            ));
+        } else {
+            self.status.done(format!(
+                "Moved {} images to {}",
+                moved.len(),
+                report.dest_dir.display()
+            ));
+        }
+    }
+
+    // Whether `dest_dir` is a destination that gets hard links instead of the images.
+    fn links_to(&self, dest_dir: &Path) -> bool {
+        self.folder_letter_entries
+            .iter()
+            .any(|entry| entry.folder == dest_dir && entry.link)
+    }
+
+    // The destinations that get a copy when an image is moved to `dest_dir`.
+    fn copy_destinations(&self, dest_dir: &Path) -> Vec<PathBuf> {
+        let letters: Vec<char> = self
+            .folder_letter_entries
+            .iter()
+            .filter(|entry| entry.folder == dest_dir && !entry.copy)
+            .map(|entry| entry.letter)
+            .collect();
+        self.folder_letter_entries
+            .iter()
+            .filter(|entry| entry.copy && letters.contains(&entry.letter))
+            .map(|entry| entry.folder.clone())
+            .collect()
+    }
+
+    // Moves the current image to the destination of `letter` and copies it to the ones of the
+    // same letter that copy. Without one that does not copy, the image is only copied.
+    // Sends the image to the destinations of `letter`, unless one of them wants the letter
+    // pressed twice and this is the first press.
+    fn press_letter(&mut self, letter: char, now: Instant) {
+        let double_press = self
+            .folder_letter_entries
+            .iter()
+            .any(|entry| entry.letter == letter && entry.double_press);
+        let armed = self.armed_letter.take().is_some_and(|(armed, at)| {
+            armed == letter && now.duration_since(at) < DOUBLE_PRESS_WINDOW
+        });
+        if double_press && !armed {
+            self.armed_letter = Some((letter, now));
+            return;
+        }
+        self.send_to_letter(letter);
+    }
+
+    // Says which letter waits for its second press, until it is pressed or the time runs out.
+    fn show_armed_letter(&mut self, ctx: &egui::Context) {
+        let Some((letter, at)) = self.armed_letter else {
+            return;
+        };
+        let left = DOUBLE_PRESS_WINDOW.saturating_sub(at.elapsed());
+        if left.is_zero() {
+            self.armed_letter = None;
+            return;
+        }
+        let folder = self
+            .folder_letter_entries
+            .iter()
+            .find(|entry| entry.letter == letter && !entry.copy)
+            .or_else(|| {
+                self.folder_letter_entries
+                    .iter()
+                    .find(|entry| entry.letter == letter)
+            })
+            .map(|entry| remote::display_destination(&entry.folder))
+            .unwrap_or_default();
+        egui::Area::new(egui::Id::new("armed_letter"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style())
+                    .stroke(egui::Stroke::new(2.0, ui.visuals().warn_fg_color))
+                    .show(ui, |ui| {
+                        let response = ui.strong(tr!(
+                            "press-again",
+                            letter = letter.to_string(),
+                            folder = folder
+                        ));
+                        accessibility::announce_changes(&response);
+                        ui.add(
+                            egui::ProgressBar::new(
+                                left.as_secs_f32() / DOUBLE_PRESS_WINDOW.as_secs_f32(),
+                            )
+                            .desired_height(4.0),
+                        );
+                    });
+            });
+        ctx.request_repaint();
+    }
+
+    fn send_to_letter(&mut self, letter: char) {
+        let primary = self
+            .folder_letter_entries
+            .iter()
+            .find(|entry| entry.letter == letter && !entry.copy)
+            .map(|entry| entry.folder.clone());
+        if let Some(folder) = primary {
+            self.request_move(&folder);
+            return;
+        }
+        if self.refuse_in_read_only() {
+            return;
+        }
+        let Some(src) = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf())
+        else {
+            return;
+        };
+        let folders: Vec<PathBuf> = self
+            .folder_letter_entries
+            .iter()
+            .filter(|entry| entry.letter == letter)
+            .map(|entry| entry.folder.clone())
+            .collect();
+        self.copy_image(&src, &folders);
+    }
+
+    // Copies the image `src` and its companion files to `folders`, and uploads them to the ones
+    // that are URLs. Returns false if a copy failed, in which case none are left.
+    fn copy_image(&mut self, src: &Path, folders: &[PathBuf]) -> bool {
+        let mut files = vec![src.to_path_buf()];
+        files.extend(self.companion_files(src));
+        let (remotes, folders): (Vec<PathBuf>, Vec<PathBuf>) = folders
+            .iter()
+            .cloned()
+            .partition(|folder| remote::parse(folder).is_some());
+        match copy_files(&files, &folders) {
+            Ok(mut copies) if !copies.is_empty() => {
+                self.status.info(format!(
+                    "Copied {} to {} destinations",
+                    get_file_name(src),
+                    folders.len()
+                ));
+                let dest = copies.remove(0);
+                self.log_move(MoveLogEntry {
+                    src: src.to_path_buf(),
+                    stamp: Stamp::of(audit::Operation::Copy, &dest),
+                    dest,
+                    companions: Vec::new(),
+                    linked: true,
+                    copies,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => {
+                self.status.error(format!("Failed to copy file: {}", e));
+                return false;
+            }
+        }
+        for remote in remotes.iter().filter_map(|folder| remote::parse(folder)) {
+            self.queue_upload(remote, files.clone(), true);
+        }
+        true
+    }
+
+    // Uploads the current image and its companion files to `remote` in the background. Unless
+    // `keep_local`, the image leaves the list right away and comes back if the upload fails.
+    fn upload_current_image(&mut self, remote: remote::Remote, keep_local: bool) {
+        let Some(src) = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf())
+        else {
+            return;
+        };
+        if archive::split(&src).is_some() {
+            self.status.warn(format!(
+                "{} is in an archive. Move it to a folder first to upload it.",
+                get_file_name(&src)
+            ));
+            return;
+        }
+        let mut files = vec![src.clone()];
+        files.extend(self.companion_files(&src));
+        if !keep_local {
+            let index = self.image_manager.current_index();
+            self.image_manager.remove_current_image();
+            self.image_manager
+                .go_on_after_removal(index, self.settings.after_move);
+        }
+        self.status
+            .info(format!("Uploading {} to {}", get_file_name(&src), remote));
+        self.queue_upload(remote, files, keep_local);
+    }
+
+    fn queue_upload(&mut self, remote: remote::Remote, files: Vec<PathBuf>, keep_local: bool) {
+        self.uploader
+            .get_or_insert_with(remote::Uploader::default)
+            .push(remote::Upload {
+                remote,
+                files,
+                keep_local,
+            });
+    }
+
+    // Records the uploads that finished in the move log. A failed one puts its image back in
+    // the list.
+    fn poll_uploads(&mut self, ctx: &egui::Context) {
+        let Some(uploader) = &mut self.uploader else {
+            return;
+        };
+        let finished = uploader.poll();
+        if uploader.pending() > 0 {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        for remote::Uploaded { upload, result } in finished {
+            let src = upload.files[0].clone();
+            match result {
+                Ok(urls) => {
+                    self.status.done(format!(
+                        "Uploaded {} to {}",
+                        get_file_name(&src),
+                        upload.remote
+                    ));
+                    let mut urls = urls.into_iter().map(PathBuf::from);
+                    let operation = match upload.keep_local {
+                        true => audit::Operation::Copy,
+                        false => audit::Operation::Move,
+                    };
+                    let entry = MoveLogEntry {
+                        stamp: Stamp::of(operation, &src),
+                        src,
+                        dest: urls.next().unwrap_or_default(),
+                        companions: upload.files[1..].iter().cloned().zip(urls).collect(),
+                        linked: upload.keep_local,
+                        copies: Vec::new(),
+                    };
+                    if upload.keep_local {
+                        self.log_move(entry);
+                    } else {
+                        self.record_move(entry);
+                    }
+                }
+                Err(e) => {
+                    self.status
+                        .error(format!("Failed to upload {}: {}", get_file_name(&src), e));
+                    if !upload.keep_local && src.exists() {
+                        self.image_manager.add_image_to_current_position(&src);
+                    }
+                }
+            }
+        }
+    }
+
+    // Adds hard links to the current image and its companion files in `dest_dir`. The image
+    // stays the current one, so that it can be linked into more albums and then moved.
+    fn link_current_image_to_dest(&mut self, dest_dir: &Path) -> Result<MoveLogEntry> {
+        let Some(src) = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf())
+        else {
+            bail!("Failed to find current image");
+        };
+        let dest = match archive::split(&src) {
+            Some(_) => archive::extract(&src, dest_dir)?,
+            None => file_ops::link_or_copy(&src, dest_dir)?,
+        };
+        let mut companions = Vec::new();
+        for companion in self.companion_files(&src) {
+            match file_ops::link_or_copy(&companion, dest_dir) {
+                Ok(dest) => companions.push((companion, dest)),
+                Err(e) => self.status.error(format!(
+                    "Failed to link {}: {}",
+                    get_file_name(&companion),
+                    e
+                )),
+            }
+        }
+        let entry = MoveLogEntry {
+            src,
+            stamp: Stamp::of(audit::Operation::Copy, &dest),
+            dest,
+            companions,
+            linked: true,
+            copies: Vec::new(),
+        };
+        self.log_move(entry.clone());
+        Ok(entry)
+    }
+
+    // Adds a move to the log that undo goes back through, and to the audit trail.
+    fn log_move(&mut self, entry: MoveLogEntry) {
+        self.audit(entry.clone());
+        self.move_log.push(entry);
+    }
+
+    // Adds an operation to the audit trail, and to the log file for the days after the session.
+    fn audit(&mut self, entry: MoveLogEntry) {
+        log::info!(
+            "{} {} -> {} ({} bytes, hash {})",
+            entry.stamp.operation.name(),
+            entry.src.display(),
+            entry.dest.display(),
+            entry
+                .stamp
+                .size
+                .map_or("?".to_string(), |size| size.to_string()),
+            entry.stamp.hash.as_deref().unwrap_or("?")
+        );
+        self.audit_log.push(entry);
+    }
+
+    // Adds the copies made to other destinations of the same letter to the audit trail.
+    fn audit_copies(&mut self, src: &Path, copies: &[PathBuf]) {
+        for copy in copies {
+            self.audit(MoveLogEntry {
+                src: src.to_path_buf(),
+                dest: copy.clone(),
+                stamp: Stamp::of(audit::Operation::Copy, copy),
+                ..Default::default()
+            });
+        }
+    }
+
+    // The stamps of those of `paths` that were scanned, for a batch to check.
+    fn stamps_of<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a PathBuf>,
+    ) -> HashMap<PathBuf, FileStamp> {
+        paths
+            .into_iter()
+            .filter_map(|path| Some((path.clone(), *self.file_stamps.get(path)?)))
+            .collect()
+    }
+
+    fn record_move(&mut self, entry: MoveLogEntry) {
+        if let Some(script) = &self.script {
+            if let Err(e) = script.on_move(&entry.src, &entry.dest) {
+                self.status.error(format!("Script error: {}", e));
+            }
+        }
+        self.rename_in_index(&entry.src, &entry.dest);
+        self.labels.rename(&entry.src, &entry.dest);
+        // A file that comes back, e.g. by an undo, is checked for being there only.
+        self.file_stamps.remove(&entry.src);
+        // Renaming in place frees nothing.
+        if entry.src.parent() != entry.dest.parent() {
+            self.count_freed(&entry.src);
+            self.session.moved += 1;
+        }
+        self.log_discard(&entry);
+        self.log_move(entry);
+    }
+
+    // Writes down where the files moved into the discard folder came from, for its review window.
+    fn log_discard(&mut self, entry: &MoveLogEntry) {
+        let Some(folder) = &self.settings.discard_folder else {
+            return;
+        };
+        if entry.linked || entry.dest.parent() != Some(folder.as_path()) {
+            return;
+        }
+        let mut moves = vec![(entry.src.clone(), entry.dest.clone())];
+        moves.extend(entry.companions.iter().cloned());
+        if let Err(e) = discard::record(folder, &moves, discard::now()) {
+            self.status
+                .error(format!("Failed to write the discard log: {}", e));
+        }
+    }
+
+    // Keeps the tags of a moved file.
+    fn rename_in_index(&self, src: &Path, dest: &Path) {
+        if let Some(index) = &self.library_index {
+            if let Err(e) = index.rename(src, dest) {
+                log::warn!("Failed to update the index for {}: {}", src.display(), e);
+            }
+        }
+    }
+
+    // Moves the current image and reports the result. On failure the error dialog is opened so
+    // the user can decide what to do.
+    fn move_current_image_and_report(&mut self, dest_dir: &Path) {
+        let color = self
+            .folder_letter_entries
+            .iter()
+            .find(|entry| entry.folder == dest_dir)
+            .and_then(|entry| entry.color);
+        let link = self.links_to(dest_dir);
+        let copy_folders = self.copy_destinations(dest_dir);
+        let src = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf());
+        if let Some(remote) = remote::parse(dest_dir) {
+            if let (Some(src), false) = (&src, copy_folders.is_empty()) {
+                if !self.copy_image(src, &copy_folders) {
+                    return;
+                }
+            }
+            self.upload_current_image(remote, link);
+            return;
+        }
+        // Uploaded from where the image was moved, once it is there.
+        let (remote_copies, copy_folders): (Vec<PathBuf>, Vec<PathBuf>) = copy_folders
+            .into_iter()
+            .partition(|folder| remote::parse(folder).is_some());
+        // Checked first, as a move on a network share that stalls would freeze the window.
+        if let Some(src) = &src {
+            let timeout = self.network_timeout();
+            if let Err(e) = file_ops::check_reachable(src, timeout)
+                .and_then(|()| file_ops::check_reachable(dest_dir, timeout))
+            {
+                self.status
+                    .error(format!("Did not move {}: {}", get_file_name(src), e));
+                self.failed_move = Some(FailedMove {
+                    src: src.clone(),
+                    dest_dir: dest_dir.to_path_buf(),
+                    error: e.to_string(),
+                });
+                return;
+            }
+            if !self.check_current_source(src) {
+                return;
+            }
+        }
+        let dest_dir = match &src {
+            Some(src) => match resolve_destination(src, dest_dir) {
+                Ok(dest_dir) => dest_dir,
+                Err(e) => {
+                    self.status
+                        .error(format!("Failed to prepare {}: {}", dest_dir.display(), e));
+                    return;
+                }
+            },
+            None => dest_dir.to_path_buf(),
+        };
+        // Copied first, while the image is still where it was. Nothing is moved unless every
+        // copy was made.
+        let mut copies = Vec::new();
+        if let (Some(src), false) = (&src, copy_folders.is_empty()) {
+            let mut files = vec![src.clone()];
+            files.extend(self.companion_files(src));
+            match copy_files(&files, &copy_folders) {
+                Ok(copied) => copies = copied,
+                Err(e) => {
+                    self.status.error(format!("Failed to copy file: {}", e));
+                    return;
+                }
+            }
+        }
+        if link {
+            match self.link_current_image_to_dest(&dest_dir) {
+                Ok(entry) => {
+                    self.status.info(format!(
+                        "Linked {} -> {}",
+                        get_file_name(&entry.src),
+                        dest_dir.display()
+                    ));
+                    self.audit_copies(&entry.src, &copies);
+                    if let Some(entry) = self.move_log.last_mut() {
+                        entry.copies = copies;
+                    }
+                    self.move_flash = color.map(|color| (color, Instant::now()));
+                }
+                Err(e) => {
+                    remove_copies(&copies);
+                    self.status.error(format!("Failed to link file: {}", e));
+                }
+            }
+            return;
+        }
+        let dest_dir = dest_dir.as_path();
+        match self.move_current_image_to_dest(dest_dir) {
+            Ok(move_log) => {
+                let filename = get_file_name(&move_log.src);
+                self.status
+                    .info(format!("Moved {} -> {}", filename, dest_dir.display()));
+                self.fan_out(&move_log, copies, &remote_copies);
+                self.move_flash = color.map(|color| (color, Instant::now()));
+            }
+            Err(e) => {
+                let transient_error = e
+                    .downcast_ref::<std::io::Error>()
+                    .filter(|e| file_ops::is_transient_error(e));
+                if let (Some(io_error), Some(src)) = (transient_error, &src) {
+                    // Keep the file out of the way while it is retried in the background. The
+                    // copies wait for it.
+                    let index = self.image_manager.current_index();
+                    self.image_manager.remove_current_image();
+                    self.image_manager
+                        .go_on_after_removal(index, self.settings.after_move);
+                    self.retry_queue.push(
+                        src,
+                        dest_dir,
+                        copies,
+                        remote_copies,
+                        io_error,
+                        Instant::now(),
+                    );
+                    self.status.warn(format!(
+                        "{} is in use. It will be moved when it is released.",
+                        get_file_name(src)
+                    ));
+                    return;
+                }
+                remove_copies(&copies);
+                self.status.error(format!("Failed to move file: {}", e));
+                if let Some(src) = src {
+                    self.failed_move = Some(FailedMove {
+                        src,
+                        dest_dir: dest_dir.to_path_buf(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        };
+    }
+
+    // Records the `copies` made along with the move `moved` and uploads it to `uploads`, the
+    // remote destinations of the same letter.
+    fn fan_out(&mut self, moved: &MoveLogEntry, copies: Vec<PathBuf>, uploads: &[PathBuf]) {
+        self.audit_copies(&moved.src, &copies);
+        if let Some(entry) = self.move_log.last_mut() {
+            entry.copies = copies;
+        }
+        for folder in uploads.iter().filter_map(|f| remote::parse(f)) {
+            let mut files = vec![moved.dest.clone()];
+            files.extend(moved.companions.iter().map(|(_, dest)| dest.clone()));
+            self.queue_upload(folder, files, true);
+        }
+    }
+
+    fn retry_failed_move(&mut self) {
+        let Some(failed_move) = self.failed_move.take() else {
+            return;
+        };
+        if self.image_manager.current_image_path() != Some(failed_move.src.as_path()) {
+            self.status.error(format!(
+                "{} is no longer the current image.",
+                get_file_name(&failed_move.src)
+            ));
+            return;
+        }
+        self.move_current_image_and_report(&failed_move.dest_dir);
+    }
+
+    fn skip_failed_move(&mut self) {
+        if let Some(failed_move) = self.failed_move.take() {
+            self.status
+                .info(format!("Skipped {}", get_file_name(&failed_move.src)));
+            self.next_image();
+        }
+    }
+
+    // Retries the queued moves whose backoff has expired, with the same checks as the first
+    // attempt.
+    fn process_retry_queue(&mut self) {
+        let now = Instant::now();
+        let timeout = self.network_timeout();
+        for mut queued in self.retry_queue.take_due(now) {
+            let result = file_ops::check_reachable(&queued.src, timeout)
+                .and_then(|()| file_ops::check_reachable(&queued.dest_dir, timeout))
+                .and_then(|()| check_source(&queued.src, self.file_stamps.get(&queued.src)))
+                .and_then(|()| self.move_and_record(&queued.src, &queued.dest_dir));
+            match result {
+                Ok(moved) => {
+                    let copies = std::mem::take(&mut queued.copies);
+                    self.fan_out(&moved, copies, &queued.uploads);
+                    self.status.info(format!(
+                        "Moved {} -> {} after {} attempts",
+                        get_file_name(&queued.src),
+                        queued.dest_dir.display(),
+                        queued.attempts + 1
+                    ));
+                }
+                Err(e)
+                    if file_ops::is_transient_error(&e) || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    if let Some(gave_up) = self.retry_queue.requeue(queued, &e, now) {
+                        self.give_up_queued_move(gave_up, e.to_string());
+                    }
+                }
+                Err(e) => {
+                    self.give_up_queued_move(queued, e.to_string());
+                }
+            }
+        }
+    }
+
+    // Puts the image back in the list, unless it is gone, and lets the user decide what to do
+    // with it. The copies made for the move are removed, as the first attempt would have.
+    fn give_up_queued_move(&mut self, queued: file_ops::QueuedMove, error: String) {
+        remove_copies(&queued.copies);
+        self.status.error(format!(
+            "Failed to move {}: {}",
+            get_file_name(&queued.src),
+            error
+        ));
+        if is_missing(&queued.src) {
+            return;
+        }
+        self.image_manager
+            .add_image_to_current_position(&queued.src);
+        if self.failed_move.is_none() {
+            self.failed_move = Some(FailedMove {
+                src: queued.src,
+                dest_dir: queued.dest_dir,
+                error,
+            });
+        }
+    }
+
+    fn cancel_queued_move(&mut self, index: usize) {
+        if let Some(queued) = self.retry_queue.remove(index) {
+            remove_copies(&queued.copies);
+            self.image_manager
+                .add_image_to_current_position(&queued.src);
+            self.status
+                .info(format!("Cancelled moving {}", get_file_name(&queued.src)));
+        }
+    }
+
+    fn show_retry_queue(&mut self, ui: &mut egui::Ui) {
+        let now = Instant::now();
+        let mut retry_now = None;
+        let mut cancel = None;
+        egui::CollapsingHeader::new(tr!(
+            "waiting-to-retry",
+            count = self.retry_queue.entries().len()
+        ))
+        .default_open(true)
+        .show(ui, |ui| {
+            for (index, queued) in self.retry_queue.entries().iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} -> {}",
+                        get_file_name(&queued.src),
+                        queued.dest_dir.display()
+                    ));
+                    ui.weak(tr!(
+                        "retry-attempt",
+                        attempt = queued.attempts,
+                        seconds = queued.next_attempt.saturating_duration_since(now).as_secs(),
+                        error = queued.last_error.as_str()
+                    ));
+                    if ui.button(tr!("retry-now")).clicked() {
+                        retry_now = Some(index);
+                    }
+                    if ui.button(tr!("cancel")).clicked() {
+                        cancel = Some(index);
+                    }
+                });
+            }
+        });
+        if let Some(index) = retry_now {
+            self.retry_queue.retry_now(index);
+        }
+        if let Some(index) = cancel {
+            self.cancel_queued_move(index);
+        }
+    }
+
+    fn show_failed_move_dialog(&mut self, ctx: &egui::Context) {
+        let Some(failed_move) = &self.failed_move else {
+            return;
+        };
+        let mut retry = false;
+        let mut skip = false;
+        egui::Modal::new(egui::Id::new("failed_move_dialog")).show(ctx, |ui| {
+            ui.heading(tr!("failed-to-move-heading"));
+            ui.colored_label(ui.visuals().error_fg_color, &failed_move.error);
+            egui::Grid::new("failed_move_paths").show(ui, |ui| {
+                ui.label(tr!("source"));
+                ui.label(failed_move.src.to_string_lossy());
+                ui.end_row();
+                ui.label(tr!("destination"));
+                ui.label(failed_move.dest_dir.to_string_lossy());
+                ui.end_row();
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                let retry_button = ui.button(tr!("retry"));
+                focus_if_nothing_is(&retry_button);
+                retry = retry_button.clicked();
+                skip = ui.button(tr!("skip")).clicked();
+            });
+        });
+        if retry {
+            self.retry_failed_move();
+        } else if skip {
+            self.skip_failed_move();
+        }
+    }
+
+    fn next_image(&mut self) {
+        self.image_manager.next_image();
+    }
+
+    fn previous_image(&mut self) {
+        self.image_manager.previous_image();
+    }
+
+    // Marks the end of the pass when the images do not wrap around.
+    fn show_end_of_folder(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            let response = ui.strong(tr!("end-of-folder"));
+            accessibility::announce_changes(&response);
+            if self.settings.show_pass_summary {
+                ui.label(tr!(
+                    "pass-summary",
+                    moved = self.session.moved,
+                    remaining = self.image_manager.num_images()
+                ));
+            }
+            if ui.button(tr!("start-over")).clicked() {
+                self.image_manager.start_over();
+            }
+        });
+    }
+
+    // The progress bar of the goal of the session, with the time left at the current pace.
+    fn show_goal(&mut self, ui: &mut egui::Ui) {
+        let Some(goal) = &mut self.goal else {
+            return;
+        };
+        let sorted = self.session.moved + self.session.deleted;
+        let remaining = self.image_manager.num_images();
+        let (done, target) = goal.progress(sorted, remaining);
+        let reached = goal.is_reached(sorted, remaining);
+        if reached && !goal.reached {
+            self.status
+                .done(format!("Goal reached: {} images sorted.", done));
+        }
+        goal.reached = reached;
+        let text = match goal.eta(sorted, remaining, Instant::now()) {
+            Some(eta) if !reached => tr!(
+                "goal-progress-eta",
+                done = done,
+                target = target,
+                eta = goals::format_eta(eta)
+            ),
+            _ => tr!("goal-progress", done = done, target = target),
+        };
+        let mut clear = false;
+        ui.horizontal(|ui| {
+            ui.label(tr!("goal"));
+            ui.add(
+                egui::ProgressBar::new(done as f32 / target.max(1) as f32)
+                    .desired_width(300.0)
+                    .text(text),
+            );
+            let button = ui.small_button("x").on_hover_text(tr!("goal-clear"));
+            accessibility::set_name(&button, tr!("goal-clear"));
+            clear = button.clicked();
+        });
+        if clear {
+            self.goal = None;
+        }
+        // The estimate counts down between moves.
+        ui.ctx().request_repaint_after(Duration::from_secs(5));
+    }
+
+    // Shown instead of the image once every image of the source folders was moved or deleted.
+    fn show_completion(&mut self, ui: &mut egui::Ui) {
+        let minutes = self
+            .session
+            .started
+            .map_or(0, |started| started.elapsed().as_secs() / 60);
+        let response = ui.heading(tr!("all-sorted"));
+        accessibility::announce_changes(&response);
+        ui.label(tr!(
+            "session-stats",
+            moved = self.session.moved,
+            deleted = self.session.deleted,
+            minutes = minutes
+        ));
+        if let Some(last) = self.image_manager.folders().last() {
+            if self
+                .next_folder
+                .as_ref()
+                .is_none_or(|(folder, _)| folder != last)
+            {
+                let folder = last.clone();
+                // Not worth freezing for when the parent folder is on a share that stalls.
+                let next = file_ops::with_timeout(last, self.network_timeout(), move || {
+                    Ok(scanner::next_folder(&folder))
+                });
+                self.next_folder = Some((last.clone(), next.ok().flatten()));
+            }
+        }
+        let next_folder = self.next_folder.as_ref().and_then(|(_, next)| next.clone());
+        ui.horizontal(|ui| {
+            if let Some(next_folder) = next_folder {
+                let name = get_file_name(&next_folder);
+                if ui
+                    .button(tr!("open-next-folder", folder = name))
+                    .on_hover_text(next_folder.to_string_lossy())
+                    .clicked()
+                {
+                    self.open_image_folder(next_folder);
+                }
+            }
+            if ui.button(tr!("choose-image-folder")).clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    self.open_image_folder(path);
+                }
+            }
+        });
+    }
+
+    // The folder tree of the albums folder, in place of the list of destinations.
+    fn show_album_tree(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr!("albums-folder"));
+            if ui.button(tr!("choose-albums-folder")).clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.settings.albums_root = Some(path);
+                    self.album_tree.refresh();
+                }
+            }
+            if ui.button(tr!("refresh")).clicked() {
+                self.album_tree.refresh();
+            }
+        });
+        let Some(root) = self.settings.albums_root.clone() else {
+            ui.label(tr!("albums-folder-unset"));
+            return;
+        };
+        let entries = &self.folder_letter_entries;
+        let mark_of = |folder: &Path| {
+            entries
+                .iter()
+                .find(|entry| entry.folder == folder)
+                .map(|entry| DestinationMark {
+                    letter: entry.letter,
+                    color: entry.color,
+                    icon: entry.icon.clone(),
+                })
+        };
+        let Some(action) = self.album_tree.show(ui, &root, &mark_of) else {
+            return;
+        };
+        match action {
+            AlbumAction::MoveTo(folder) => self.request_move(&folder),
+            AlbumAction::AssignLetter { folder, letter } => self.assign_letter(folder, letter),
+            AlbumAction::RemoveLetter(folder) => self
+                .folder_letter_entries
+                .retain(|entry| entry.folder != folder),
+            AlbumAction::CreateFolder(folder) => {
+                if self.refuse_in_read_only() {
+                    return;
+                }
+                match fs::create_dir(&folder) {
+                    Ok(()) => {
+                        self.album_tree.refresh();
+                        self.status
+                            .info(format!("Created {}", folder.to_string_lossy()));
+                    }
+                    Err(e) => self.status.error(format!(
+                        "Could not create {}: {}",
+                        folder.to_string_lossy(),
+                        e
+                    )),
+                }
+            }
+        }
+    }
+
+    // Whether `letter` is a key that can be pressed for a destination. Warns if not.
+    fn check_key_letter(&mut self, letter: char) -> bool {
+        if egui::Key::from_name(&letter.to_string()).is_none() {
+            self.status
+                .warn(tr!("not-a-key", letter = letter.to_string()));
+            return false;
+        }
+        true
+    }
+
+    // Makes `letter` move images to `folder`, in place of what the folder or the letter had.
+    fn assign_letter(&mut self, folder: PathBuf, letter: char) {
+        if !self.check_key_letter(letter) {
+            return;
+        }
+        self.folder_letter_entries
+            .retain(|entry| entry.folder != folder && entry.letter != letter);
+        self.folder_letter_entries.push(FolderLetterEntry {
+            folder,
+            letter,
+            confirm: false,
+            link: false,
+            copy: false,
+            color: None,
+            icon: String::new(),
+            double_press: false,
+        });
+    }
+
+    fn remove_folder_letter_entries(&mut self, indecies: Vec<usize>) {
+        let mut indecies = indecies;
+        indecies.sort();
+        indecies.reverse();
+        for index in indecies {
+            self.folder_letter_entries.remove(index);
+        }
+    }
+
+    // Deletes the current image from disk. This cannot be undone.
+    fn delete_current_image(&mut self) -> Result<PathBuf> {
+        if self.read_only {
+            bail!("Read-only mode is on");
+        }
+        if let Some((archive, _)) = self
+            .image_manager
+            .current_image_path()
+            .and_then(archive::split)
+        {
+            bail!("{} is only read, not changed", archive.display());
+        }
+        let index = self.image_manager.current_index();
+        let Some(image_path) = self.image_manager.remove_current_image() else {
+            bail!("Failed to find current image");
+        };
+        let companions = self.companion_files(&image_path);
+        let timeout = self.network_timeout();
+        // Taken while the file is still there.
+        let mut stamp = Stamp::default();
+        if let Err(e) = file_ops::check_reachable(&image_path, timeout).and_then(|()| {
+            stamp = Stamp::of(audit::Operation::Delete, &image_path);
+            fs::remove_file(&image_path)
+        }) {
+            self.image_manager
+                .add_image_to_current_position(&image_path);
+            return Err(e.into());
+        }
+        log::info!("Deleted file {}", image_path.display());
+        self.audit(MoveLogEntry {
+            src: image_path.clone(),
+            stamp,
+            ..Default::default()
+        });
+        self.image_manager
+            .go_on_after_removal(index, self.settings.after_move);
+        self.count_freed(&image_path);
+        self.session.deleted += 1;
+        for companion in companions {
+            if let Err(e) = fs::remove_file(&companion) {
+                self.status.error(format!(
+                    "Failed to delete {}: {}",
+                    get_file_name(&companion),
+                    e
+                ));
+            }
+        }
+        Ok(image_path)
+    }
+
+    // Renames the current image in its folder, together with its companion files. It is recorded
+    // like a move so that it can be undone.
+    fn rename_current_image(&mut self, new_name: &str) -> Result<MoveLogEntry> {
+        if self.read_only {
+            bail!("Read-only mode is on");
+        }
+        let Some(src) = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf())
+        else {
+            bail!("Failed to find current image");
+        };
+        if let Some((archive, _)) = archive::split(&src) {
+            bail!("{} is only read, not changed", archive.display());
+        }
+        let new_name = new_name.trim();
+        if new_name.is_empty()
+            || new_name == "."
+            || new_name == ".."
+            || new_name.contains(['/', '\\'])
+        {
+            bail!("\"{}\" is not a valid file name.", new_name);
+        }
+        let dest = src.with_file_name(new_name);
+        // Only the case may change, where the filesystem ignores case.
+        if dest == src || file_ops::is_taken(&dest, &src) {
+            bail!("{} already exists.", new_name);
+        }
+        let companions = self.companion_files(&src);
+        fs::rename(&src, &dest)?;
+        log::info!("Renamed {} to {}", src.display(), dest.display());
+        let mut renamed = Vec::new();
+        for companion in companions {
+            let Some(target) = file_ops::renamed_companion(&companion, &src, &dest) else {
+                continue;
+            };
+            if target.exists() {
+                continue;
+            }
+            match fs::rename(&companion, &target) {
+                Ok(()) => renamed.push((companion, target)),
+                Err(e) => self.status.error(format!(
+                    "Failed to rename {}: {}",
+                    get_file_name(&companion),
+                    e
+                )),
+            }
+        }
+        self.image_manager.rename_image(&src, &dest);
+        let entry = MoveLogEntry {
+            src,
+            stamp: Stamp::of(audit::Operation::Rename, &dest),
+            dest,
+            companions: renamed,
+            linked: false,
+            copies: Vec::new(),
+        };
+        self.record_move(entry.clone());
+        Ok(entry)
+    }
+
+    fn handle_image_menu_action(&mut self, ctx: &egui::Context, action: ImageMenuAction) {
+        let Some(path) = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf())
+        else {
+            return;
+        };
+        match action {
+            ImageMenuAction::MoveTo(folder) => self.request_move(&folder),
+            ImageMenuAction::Keep => self.keep_current_image(),
+            ImageMenuAction::CopyPath => {
+                ctx.copy_text(path.to_string_lossy().to_string());
+                self.status.info(format!("Copied {}", path.display()));
+            }
+            ImageMenuAction::OpenExternally => {
+                if let Err(e) = desktop::open_externally(&path) {
+                    self.status
+                        .error(format!("Failed to open {}: {}", get_file_name(&path), e));
+                }
+            }
+            ImageMenuAction::Reveal => {
+                if let Err(e) = desktop::reveal(&path) {
+                    self.status
+                        .error(format!("Failed to open the file manager: {}", e));
+                }
+            }
+            ImageMenuAction::Rename => {
+                if !self.refuse_in_read_only() {
+                    self.rename = Some((path.clone(), get_file_name(&path)));
+                }
+            }
+            ImageMenuAction::Delete => match self.delete_current_image() {
+                Ok(path) => self
+                    .status
+                    .info(format!("Deleted {}", get_file_name(&path))),
+                Err(e) => self.status.error(format!("Failed to delete file: {}", e)),
+            },
+        }
+    }
+
+    fn show_rename_window(&mut self, ctx: &egui::Context) {
+        let Some((path, name)) = &mut self.rename else {
+            return;
+        };
+        // The image changed under the dialog, e.g. it was moved with a key.
+        if self.image_manager.current_image_path() != Some(path.as_path()) {
+            self.rename = None;
+            return;
+        }
+        let mut open = true;
+        let mut rename = false;
+        let mut cancel = false;
+        egui::Window::new(tr!("rename"))
+            .id(egui::Id::new("rename"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let response = ui.text_edit_singleline(name);
+                response.request_focus();
+                rename = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.horizontal(|ui| {
+                    rename |= ui.button(tr!("rename")).clicked();
+                    cancel = ui.button(tr!("cancel")).clicked();
+                });
+            });
+        if !open || cancel {
+            self.rename = None;
+            return;
+        }
+        if rename {
+            let name = name.clone();
+            match self.rename_current_image(&name) {
+                Ok(entry) => {
+                    self.status.info(format!(
+                        "Renamed {} to {}",
+                        get_file_name(&entry.src),
+                        get_file_name(&entry.dest)
+                    ));
+                    self.rename = None;
+                }
+                Err(e) => self.status.error(format!("Failed to rename: {}", e)),
+            }
+        }
+    }
+
+    // Draws a fading border around the window in the color of the destination the image was
+    // just moved to.
+    fn show_move_flash(&mut self, ctx: &egui::Context) {
+        let Some(([r, g, b], moved)) = self.move_flash else {
+            return;
+        };
+        let elapsed = moved.elapsed();
+        if elapsed >= MOVE_FLASH_DURATION {
+            self.move_flash = None;
+            return;
+        }
+        let fade = 1.0 - elapsed.as_secs_f32() / MOVE_FLASH_DURATION.as_secs_f32();
+        let color = egui::Color32::from_rgba_unmultiplied(r, g, b, (255.0 * fade) as u8);
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("move_flash"),
+        ));
+        painter.rect_stroke(
+            ctx.screen_rect().shrink(3.0),
+            0.0,
+            egui::Stroke::new(6.0, color),
+        );
+        ctx.request_repaint();
+    }
+
+    // Asks for the name of a new album, then creates it and moves the image there.
+    fn show_new_album_window(&mut self, ctx: &egui::Context) {
+        let Some(name) = &mut self.new_album else {
+            return;
+        };
+        let mut open = true;
+        let mut create = false;
+        let mut cancel = false;
+        egui::Window::new(tr!("new-album"))
+            .id(egui::Id::new("new_album"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let response = ui.text_edit_singleline(name);
+                response.request_focus();
+                create = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.horizontal(|ui| {
+                    create |= ui.button(tr!("create-folder")).clicked();
+                    cancel = ui.button(tr!("cancel")).clicked();
+                });
+            });
+        if !open || cancel {
+            self.new_album = None;
+            return;
+        }
+        if create {
+            let name = name.clone();
+            match self.create_album(&name) {
+                Ok((folder, letter)) => {
+                    self.status.info(format!(
+                        "Created {} with the key {}",
+                        folder.to_string_lossy(),
+                        letter
+                    ));
+                    self.new_album = None;
+                    self.request_move(&folder);
+                }
+                Err(e) => self
+                    .status
+                    .error(format!("Failed to create the album: {}", e)),
+            }
+        }
+    }
+
+    // Creates the folder `name` in the albums folder and gives it the first letter that no
+    // destination has. An existing folder is reused.
+    fn create_album(&mut self, name: &str) -> Result<(PathBuf, char)> {
+        if self.read_only {
+            bail!("Read-only mode is on");
+        }
+        let Some(root) = &self.settings.albums_root else {
+            bail!("Choose the albums folder first");
+        };
+        let name = name.trim();
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            bail!("\"{}\" is not a valid folder name.", name);
+        }
+        let folder = root.join(name);
+        if let Some(entry) = self
+            .folder_letter_entries
+            .iter()
+            .find(|entry| entry.folder == folder)
+        {
+            return Ok((folder, entry.letter));
+        }
+        // J and K go through the images.
+        let Some(letter) = ('A'..='Z').chain('0'..='9').find(|letter| {
+            !['J', 'K'].contains(letter)
+                && !self
+                    .folder_letter_entries
+                    .iter()
+                    .any(|entry| entry.letter.eq_ignore_ascii_case(letter))
+        }) else {
+            bail!("Every letter is taken");
+        };
+        match fs::create_dir(&folder) {
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e.into()),
+            _ => {}
+        }
+        self.album_tree.refresh();
+        self.assign_letter(folder.clone(), letter);
+        Ok((folder, letter))
+    }
+
+    fn handle_broken_image_action(&mut self, action: BrokenImageAction) {
+        match action {
+            BrokenImageAction::Skip => self.next_image(),
+            BrokenImageAction::Delete => match self.delete_current_image() {
+                Ok(path) => self
+                    .status
+                    .info(format!("Deleted {}", get_file_name(&path))),
+                Err(e) => self.status.error(format!("Failed to delete file: {}", e)),
+            },
+            BrokenImageAction::Quarantine => self.quarantine_current_image(),
+        }
+    }
+
+    // Moves the current image to the quarantine folder and writes down why in its report.
+    fn quarantine_current_image(&mut self) {
+        if self.refuse_in_read_only() {
+            return;
+        }
+        let Some(src) = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf())
+        else {
+            return;
+        };
+        if self.settings.quarantine_folder.is_none() {
+            self.settings.quarantine_folder = FileDialog::new()
+                .set_title("Choose Quarantine Folder")
+                .pick_folder();
+        }
+        let Some(folder) = self.settings.quarantine_folder.clone() else {
+            return;
+        };
+        let reason = quarantine::check(&src)
+            .map(|problem| problem.to_string())
+            .unwrap_or_else(|| "Cannot be displayed".to_string());
+        match self.move_current_image_to_dest(&folder) {
+            Ok(entry) => {
+                if let Err(e) =
+                    quarantine::append_report(&folder, &[(entry.src, entry.dest, reason.clone())])
+                {
+                    self.status
+                        .error(format!("Failed to write the quarantine report: {}", e));
+                }
+                self.status
+                    .info(format!("Quarantined {}: {}", get_file_name(&src), reason));
+            }
+            Err(e) => self.status.error(format!(
+                "Failed to quarantine {}: {}",
+                get_file_name(&src),
+                e
+            )),
+        }
+    }
+
+    // Moves files with their companion files into the quarantine `folder` in the background,
+    // with the reason for each in its report.
+    fn quarantine_images(&mut self, problems: Vec<(PathBuf, String)>, folder: PathBuf) {
+        if self.refuse_in_read_only() {
+            return;
+        }
+        if let Err(e) = file_ops::check_reachable(&folder, self.network_timeout()) {
+            self.status.error(format!(
+                "Did not quarantine {} files: {}",
+                problems.len(),
+                e
+            ));
+            return;
+        }
+        let files: Vec<(PathBuf, Vec<PathBuf>)> = problems
+            .iter()
+            .map(|(path, _)| (path.clone(), self.companion_files(path)))
+            .collect();
+        let reasons: HashMap<PathBuf, String> = problems.into_iter().collect();
+        let title = format!("Quarantining {} files in {}", files.len(), folder.display());
+        let stamps = self.stamps_of(reasons.keys());
+        let symlinks = self.settings.symlinks;
+        self.start_batch(title, files.len(), move |on_file| {
+            let mut report = move_batch(&files, &folder, &stamps, symlinks, false, on_file);
+            let entries: Vec<(PathBuf, PathBuf, String)> = report
+                .moved
+                .iter()
+                .map(|entry| {
+                    let reason = reasons.get(&entry.src).cloned().unwrap_or_default();
+                    (entry.src.clone(), entry.dest.clone(), reason)
+                })
+                .collect();
+            if let Err(e) = quarantine::append_report(&folder, &entries) {
+                report
+                    .errors
+                    .push((folder.join(quarantine::REPORT_NAME), e.to_string()));
+            }
+            BatchReport::Move(report)
+        });
+    }
+
+    fn toggle_library_index(&mut self) {
+        if !self.settings.use_library_index {
+            if let Some(update) = self.index_update.take() {
+                update.cancel();
+            }
+            self.index_queue.clear();
+            self.library_index = None;
+            return;
+        }
+        match LibraryIndex::open(&index_path()) {
+            Ok(index) => {
+                self.library_index = Some(index);
+                // Folders that are still being scanned are indexed when their scan finishes.
+                for folder in self.image_manager.folders().to_vec() {
+                    if !self.folder_scans.iter().any(|scan| scan.folder() == folder) {
+                        self.queue_index_update(folder);
+                    }
+                }
+            }
+            Err(e) => {
+                self.status
+                    .error(format!("Failed to open the library index: {}", e));
+                self.settings.use_library_index = false;
+            }
+        }
+    }
+
+    // Replaces the queued source folders with `folder`.
+    fn open_image_folder(&mut self, folder: PathBuf) {
+        for scan in self.folder_scans.drain(..) {
+            scan.cancel();
+        }
+        if let Some(update) = self.index_update.take() {
+            update.cancel();
+        }
+        self.index_queue.clear();
+        self.image_manager.clear();
+        self.image_manager.set_folders(Vec::new());
+        self.unreachable_folders.clear();
+        self.unconfirmed_images.clear();
+        self.file_stamps.clear();
+        self.library_results.clear();
+        self.labels.clear();
+        if let Some(scan) = self.date_scan.take() {
+            scan.cancel();
+        }
+        self.session = SessionStats {
+            started: Some(Instant::now()),
+            ..Default::default()
+        };
+        self.goal = None;
+        self.resume_image = None;
+        self.add_source_folder(folder);
+    }
+
+    /// Queues another source folder. Its images come after the ones of the folders already
+    /// queued.
+    fn add_source_folder(&mut self, folder: PathBuf) {
+        if self.image_manager.folders().contains(&folder) {
+            self.status
+                .warn(format!("{} is already open.", folder.display()));
+            return;
+        }
+        let mut folders = self.image_manager.folders().to_vec();
+        folders.push(folder.clone());
+        self.image_manager.set_folders(folders);
+        // Show what the index knows right away. The scan below confirms it.
+        if let Some(index) = &self.library_index {
+            match index.images_in_folder(&folder) {
+                Ok(known) => {
+                    self.unconfirmed_images.extend(known.iter().cloned());
+                    self.image_manager.add_scanned_images(known);
+                }
+                Err(e) => log::warn!("Failed to read the index: {}", e),
+            }
+        }
+        self.start_scan(&folder);
+    }
+
+    fn start_scan(&mut self, folder: &Path) {
+        let ignore = ignore::IgnoreRules::new(
+            self.settings.include_hidden_files,
+            &self.settings.ignore_patterns,
+        )
+        .skip_symlinks(self.settings.symlinks == SymlinkPolicy::Skip);
+        self.folder_scans.push(FolderScan::start(
+            folder,
+            self.settings.pair_raw_with_jpeg,
+            ignore,
+        ));
+    }
+
+    // F5: reads the source folders again. Images added outside the app are added, and the ones
+    // deleted or moved away are dropped. The current image stays.
+    fn rescan_folders(&mut self) {
+        if !self.folder_scans.is_empty() {
+            self.status.info(tr!("already-scanning"));
+            return;
+        }
+        let folders = self.image_manager.folders().to_vec();
+        for folder in &folders {
+            // Dropped unless the scan finds them again.
+            self.unconfirmed_images.extend(
+                self.image_manager
+                    .image_paths()
+                    .iter()
+                    .filter(|path| scanner::source_folder(path) == Some(folder))
+                    .cloned(),
+            );
+            self.unreachable_folders
+                .retain(|(unreachable, _)| unreachable != folder);
+            self.start_scan(folder);
+        }
+    }
+
+    // Whether the current image `src` is still the file that was shown. A deleted one is
+    // dropped. A changed one is shown again as it is now, so that the next move moves what
+    // the user saw.
+    fn check_current_source(&mut self, src: &Path) -> bool {
+        match check_source(src, self.file_stamps.get(src)) {
+            Ok(()) => true,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.drop_missing_images(src);
+                false
+            }
+            Err(e) => {
+                self.status
+                    .error(format!("Did not move {}: {}", get_file_name(src), e));
+                match FileStamp::of(src) {
+                    Ok(stamp) => self.file_stamps.insert(src.to_path_buf(), stamp),
+                    Err(_) => self.file_stamps.remove(src),
+                };
+                self.image_manager.loader.reload(src);
+                false
+            }
+        }
+    }
+
+    // Drops the images whose files are gone from the source folder of `path`, which was found
+    // missing. Files deleted in a file manager tend to go several at a time.
+    fn drop_missing_images(&mut self, path: &Path) {
+        let folder = scanner::source_folder(path);
+        let missing: HashSet<PathBuf> = self
+            .image_manager
+            .image_paths()
+            .iter()
+            .filter(|image| scanner::source_folder(image) == folder && is_missing(image))
+            .cloned()
+            .collect();
+        self.image_manager.remove_images(&missing);
+        self.status.warn(tr!(
+            "images-gone",
+            count = missing.len(),
+            file = get_file_name(path)
+        ));
+    }
+
+    // Lists the source folders that could not be read, each with a button to scan it again.
+    fn show_unreachable_folders(&mut self, ui: &mut egui::Ui) {
+        let mut retry = None;
+        for (folder, error) in &self.unreachable_folders {
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    tr!(
+                        "source-unreachable",
+                        folder = folder.display().to_string(),
+                        error = error.clone()
+                    ),
+                );
+                if ui.button(tr!("retry")).clicked() {
+                    retry = Some(folder.clone());
+                }
+            });
+        }
+        if let Some(folder) = retry {
+            self.unreachable_folders
+                .retain(|(queued, _)| *queued != folder);
+            self.start_scan(&folder);
+        }
+    }
+
+    fn network_timeout(&self) -> Duration {
+        Duration::from_secs(self.settings.network_timeout_secs)
+    }
+
+    fn remove_source_folder(&mut self, folder: &Path) {
+        self.unreachable_folders
+            .retain(|(queued, _)| queued != folder);
+        self.folder_scans.retain(|scan| {
+            if scan.folder() == folder {
+                scan.cancel();
+            }
+            scan.folder() != folder
+        });
+        self.index_queue.retain(|queued| queued != folder);
+        if self
+            .index_update
+            .as_ref()
+            .is_some_and(|update| update.folder() == folder)
+        {
+            if let Some(update) = self.index_update.take() {
+                update.cancel();
+            }
+            self.start_next_index_update();
+        }
+        let in_folder = |path: &PathBuf| scanner::source_folder(path) == Some(folder);
+        let images: HashSet<PathBuf> = self
+            .image_manager
+            .image_paths()
+            .iter()
+            .filter(|path| in_folder(path))
+            .cloned()
+            .collect();
+        self.image_manager.remove_images(&images);
+        self.unconfirmed_images.retain(|path| !in_folder(path));
+        let folders = self
+            .image_manager
+            .folders()
+            .iter()
+            .filter(|queued| queued.as_path() != folder)
+            .cloned()
+            .collect();
+        self.image_manager.set_folders(folders);
+        self.status.info(format!(
+            "Removed {} ({} images) from the queue.",
+            folder.display(),
+            images.len()
+        ));
+    }
+
+    fn queue_index_update(&mut self, folder: PathBuf) {
+        // Archives are listed again whenever they are opened.
+        if archive::is_archive(&folder) {
+            return;
+        }
+        self.index_queue.push_back(folder);
+        self.start_next_index_update();
+    }
+
+    // Folders are indexed one at a time so that the updates do not wait on each other's locks.
+    fn start_next_index_update(&mut self) {
+        if self.index_update.is_some() {
+            return;
+        }
+        let Some(folder) = self.index_queue.pop_front() else {
+            return;
+        };
+        let paths = self
+            .image_manager
+            .image_paths()
+            .iter()
+            .filter(|path| path.parent() == Some(folder.as_path()))
+            .cloned()
+            .collect();
+        self.index_update = Some(IndexUpdate::start(index_path(), &folder, paths));
+    }
+
+    fn poll_index_update(&mut self, ctx: &egui::Context) {
+        let Some(update) = &mut self.index_update else {
+            return;
+        };
+        match update.poll() {
+            Some(Ok(updated)) => {
+                self.status.done(format!(
+                    "Indexed {}. {} files were new or changed.",
+                    update.folder().display(),
+                    updated
+                ));
+                self.index_update = None;
+                self.start_next_index_update();
+            }
+            Some(Err(e)) => {
+                self.status
+                    .error(format!("Failed to update the index: {}", e));
+                self.index_update = None;
+                self.start_next_index_update();
+            }
+            None => ctx.request_repaint_after(Duration::from_millis(100)),
+        }
+    }
+
+    fn poll_folder_scans(&mut self, ctx: &egui::Context) {
+        if self.folder_scans.is_empty() {
+            return;
+        }
+        let mut found = Vec::new();
+        for scan in &mut self.folder_scans {
+            found.extend(scan.poll());
+            self.file_stamps.extend(scan.take_stamps());
+        }
+        found.retain(|path| !self.unconfirmed_images.remove(path));
+        self.image_manager.add_scanned_images(found);
+        if let Some(image) = &self.resume_image {
+            if self.image_manager.go_to_image(image) {
+                self.resume_image = None;
+            }
+        }
+
+        let (done, running): (Vec<_>, Vec<_>) = std::mem::take(&mut self.folder_scans)
+            .into_iter()
+            .partition(|scan| scan.is_done());
+        self.folder_scans = running;
+        if self.folder_scans.is_empty() {
+            // Moved or deleted since.
+            self.resume_image = None;
+        }
+        for scan in done {
+            if let Some(error) = scan.error() {
+                // Its indexed images may well still be there.
+                self.status.error(format!(
+                    "Failed to read {}: {}",
+                    scan.folder().display(),
+                    error
+                ));
+                self.unreachable_folders
+                    .push((scan.folder().to_path_buf(), error.to_string()));
+                continue;
+            }
+            self.status.done(format!(
+                "Found {} images in {}",
+                scan.found(),
+                scan.folder().display()
+            ));
+            let folder = scan.folder();
+            // Indexed images that the scan did not find no longer exist.
+            let gone: HashSet<PathBuf> = self
+                .unconfirmed_images
+                .iter()
+                .filter(|path| scanner::source_folder(path) == Some(folder))
+                .cloned()
+                .collect();
+            self.unconfirmed_images.retain(|path| !gone.contains(path));
+            self.image_manager.remove_images(&gone);
+            self.dates_stale = true;
+            self.sizes_stale = true;
+            self.colors_stale = true;
+            self.labels.load(
+                self.image_manager
+                    .image_paths()
+                    .iter()
+                    .filter(|path| scanner::source_folder(path) == Some(folder))
+                    .cloned()
+                    .collect(),
+            );
+            if let Some(index) = &self.library_index {
+                match index.kept_in_folder(folder) {
+                    Ok(kept) => self.image_manager.add_kept(kept),
+                    Err(e) => log::warn!("Failed to read kept images: {}", e),
+                }
+                self.queue_index_update(folder.to_path_buf());
+            }
+        }
+        if !self.folder_scans.is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    // Reads the dates of images that do not have one yet once the folders are scanned.
+    fn poll_date_scan(&mut self, ctx: &egui::Context) {
+        if let Some(scan) = &self.date_scan {
+            match scan.poll() {
+                Some(found) if self.sort_order == SortOrder::DateTaken => {
+                    let mut dates = self.image_manager.order().dates().as_ref().clone();
+                    dates.extend(found);
+                    self.image_manager.set_dates(dates);
+                    self.date_scan = None;
+                }
+                Some(found) => {
+                    self.image_manager.add_dates(found);
+                    self.date_scan = None;
+                }
+                None => ctx.request_repaint_after(Duration::from_millis(100)),
+            }
+            return;
+        }
+        let by_date = self.sort_order == SortOrder::DateTaken;
+        // Bursts and the timeline need dates too.
+        if !(by_date || self.settings.stack_bursts || self.show_timeline)
+            || !self.dates_stale
+            || !self.folder_scans.is_empty()
+        {
+            return;
+        }
+        self.dates_stale = false;
+        let dates = self.image_manager.order().dates();
+        let paths: Vec<PathBuf> = self
+            .image_manager
+            .image_paths()
+            .iter()
+            .filter(|path| {
+                if by_date {
+                    !dates.contains_key(*path)
+                } else {
+                    !self.image_manager.knows_date(path)
+                }
+            })
+            .cloned()
+            .collect();
+        if !paths.is_empty() {
+            self.date_scan = Some(DateScan::start(paths));
+        }
+    }
+
+    // Reads the sizes of images that do not have one yet once the folders are scanned.
+    fn poll_size_scan(&mut self, ctx: &egui::Context) {
+        if let Some(scan) = &self.size_scan {
+            match scan.poll() {
+                Some(found) => {
+                    let mut sizes = self.image_manager.order().sizes().as_ref().clone();
+                    sizes.extend(found);
+                    self.image_manager.set_sizes(sizes);
+                    self.size_scan = None;
+                }
+                None => ctx.request_repaint_after(Duration::from_millis(100)),
+            }
+            return;
+        }
+        if self.sort_order != SortOrder::Largest
+            || !self.sizes_stale
+            || !self.folder_scans.is_empty()
+        {
+            return;
+        }
+        self.sizes_stale = false;
+        let sizes = self.image_manager.order().sizes();
+        let paths: Vec<PathBuf> = self
+            .image_manager
+            .image_paths()
+            .iter()
+            .filter(|path| !sizes.contains_key(*path))
+            .cloned()
+            .collect();
+        if !paths.is_empty() {
+            self.size_scan = Some(SizeScan::start(paths));
+        }
+    }
+
+    // Reads the dominant colors of images that do not have one yet once the folders are scanned.
+    fn poll_color_scan(&mut self, ctx: &egui::Context) {
+        if let Some(scan) = &self.color_scan {
+            match scan.poll() {
+                Some(found) => {
+                    let mut colors = self.image_manager.order().colors().as_ref().clone();
+                    colors.extend(found);
+                    self.image_manager.set_colors(colors);
+                    self.color_scan = None;
+                }
+                None => ctx.request_repaint_after(Duration::from_millis(100)),
+            }
+            return;
+        }
+        if self.sort_order != SortOrder::Color
+            || !self.colors_stale
+            || !self.folder_scans.is_empty()
+        {
+            return;
+        }
+        self.colors_stale = false;
+        let colors = self.image_manager.order().colors();
+        let paths: Vec<PathBuf> = self
+            .image_manager
+            .image_paths()
+            .iter()
+            .filter(|path| !colors.contains_key(*path))
+            .cloned()
+            .collect();
+        if !paths.is_empty() {
+            self.color_scan = Some(ColorScan::start(paths));
+        }
+    }
+
+    // Only shows the images whose dominant color is `hue`.
+    fn filter_by_hue(&mut self, hue: palette::Hue) {
+        let paths: HashSet<PathBuf> = self
+            .image_manager
+            .order()
+            .colors()
+            .iter()
+            .filter(|(_, color)| palette::Hue::of(**color) == hue)
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.image_manager.set_filter(hue.name(), paths);
+    }
+
+    // Counts an image that left its folder towards the space freed in the largest-first order.
+    fn count_freed(&mut self, path: &Path) {
+        if let Some(size) = self.image_manager.order().sizes().get(path) {
+            self.freed_bytes += size;
+        }
+    }
+
+    // Shows the background work that is still running in the toasts.
+    fn report_progress(&mut self) {
+        let fraction = |(done, total): (usize, usize)| Some(done as f32 / total.max(1) as f32);
+        let timeout = self.network_timeout();
+        for scan in &self.folder_scans {
+            let stalled = if scan.is_stalled(timeout) {
+                " (not answering)"
+            } else {
+                ""
+            };
+            self.status.progress(
+                format!(
+                    "Scanning {}: {} images{}",
+                    scan.folder().display(),
+                    scan.found(),
+                    stalled
+                ),
+                None,
+            );
+        }
+        if let Some(update) = &self.index_update {
+            let (indexed, total) = update.progress();
+            self.status.progress(
+                format!(
+                    "Indexing {}: {}/{}",
+                    update.folder().display(),
+                    indexed,
+                    total
+                ),
+                fraction((indexed, total)),
+            );
+        }
+        if self.date_scan.is_some() {
+            self.status.progress("Reading dates", None);
+        }
+        if self.size_scan.is_some() {
+            self.status.progress("Reading file sizes", None);
+        }
+        if self.color_scan.is_some() {
+            self.status.progress("Reading colors", None);
+        }
+        if let Some(thumbnails) = &self.thumbnails {
+            let pending = thumbnails.pending();
+            if pending > 0 {
+                self.status
+                    .progress(format!("Creating {} thumbnails", pending), None);
+            }
+        }
+        if let Some(scan) = self.integrity_scan.as_ref().filter(|scan| !scan.is_done()) {
+            let (checked, total) = scan.progress();
+            self.status.progress(
+                format!("Checking for damage: {}/{}", checked, total),
+                fraction((checked, total)),
+            );
+        }
+        if let Some((checked, total)) = self.blur_tool.progress() {
+            self.status.progress(
+                format!("Measuring sharpness: {}/{}", checked, total),
+                fraction((checked, total)),
+            );
+        }
+        if let Some((checked, total)) = self.screenshot_tool.progress() {
+            self.status.progress(
+                format!("Looking for screenshots: {}/{}", checked, total),
+                fraction((checked, total)),
+            );
+        }
+        if let Some(revert) = self
+            .session_revert
+            .as_ref()
+            .filter(|revert| !revert.finished)
+        {
+            let done = revert.restored + revert.failed.len();
+            self.status.progress(
+                format!("Reverting moves: {}/{}", done, revert.total),
+                fraction((done, revert.total)),
+            );
+        }
+        if let Some((checked, total)) = self.quarantine_tool.progress() {
+            self.status.progress(
+                format!("Looking for problem files: {}/{}", checked, total),
+                fraction((checked, total)),
+            );
+        }
+        if let Some((checked, total)) = self.face_tool.progress() {
+            self.status.progress(
+                format!("Looking for faces: {}/{}", checked, total),
+                fraction((checked, total)),
+            );
+        }
+        if let Some((checked, total)) = self.location_tool.progress() {
+            self.status.progress(
+                format!("Reading locations: {}/{}", checked, total),
+                fraction((checked, total)),
+            );
+        }
+        if let Some(pending) = self
+            .uploader
+            .as_ref()
+            .map(|u| u.pending())
+            .filter(|&n| n > 0)
+        {
+            self.status
+                .progress(format!("Uploading {} images", pending), None);
+        }
+        if let Some(batch) = self.batch.as_ref().filter(|batch| !batch.is_finished()) {
+            let (done, total) = batch.progress();
+            self.status.progress(
+                format!("{}: {}/{}", batch.title(), done, total),
+                fraction((done, total)),
+            );
+        }
+        if self.merge_tool.is_busy() {
+            self.status.progress("Comparing folders", None);
+        }
+        if self.flatten_tool.is_busy() {
+            self.status.progress("Listing images in subfolders", None);
+        }
+        if self.organize_tool.is_busy() {
+            self.status
+                .progress("Reading the dates of the images", None);
+        }
+        if self.storage_tool.is_busy() {
+            self.status.progress("Measuring destination folders", None);
+        }
+        if let Some(progress) = self.contact_sheet.progress() {
+            self.status.progress(
+                format!("Rendering the contact sheet: {}/{}", progress.0, progress.1),
+                fraction(progress),
+            );
+        }
+    }
+
+    fn cancel_folder_scans(&mut self) {
+        for scan in self.folder_scans.drain(..) {
+            scan.cancel();
+            self.status.warn(format!(
+                "Stopped scanning {}. Only {} images were loaded.",
+                scan.folder().display(),
+                scan.found()
+            ));
+        }
+        // Without a complete listing, keep trusting the index.
+        self.unconfirmed_images.clear();
+    }
+
+    fn show_grid(&mut self, ctx: &egui::Context) {
+        let Some(thumbnails) = &mut self.thumbnails else {
+            return;
+        };
+        thumbnails.poll();
+        // The filmstrip is always visible, so finished thumbnails must be drawn even without the
+        // grid.
+        if thumbnails.has_pending() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
+        let mut go_to = None;
+        egui::Window::new(tr!("grid"))
+            .id(egui::Id::new("grid"))
+            .open(&mut self.show_grid)
+            .default_size(egui::vec2(600.0, 500.0))
+            .show(ctx, |ui| {
+                let cell_size = 128.0;
+                let spacing = ui.spacing().item_spacing;
+                let columns =
+                    ((ui.available_width() / (cell_size + spacing.x)).floor() as usize).max(1);
+                let paths = self.image_manager.image_paths();
+                let current = self.image_manager.current_index();
+                let num_rows = paths.len().div_ceil(columns);
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show_rows(ui, cell_size + spacing.y, num_rows, |ui, rows| {
+                        for row in rows {
+                            ui.horizontal(|ui| {
+                                let start = row * columns;
+                                let end = (start + columns).min(paths.len());
+                                for (index, path) in paths.iter().enumerate().take(end).skip(start)
+                                {
+                                    let selected = index == current;
+                                    if thumbnails.show(ui, path, cell_size, selected).clicked() {
+                                        go_to = Some(index);
+                                    }
+                                }
+                            });
+                        }
+                    });
+            });
+        thumbnails.end_frame(ctx);
+        if let Some(index) = go_to {
+            self.image_manager.go_to_index(index);
+        }
+    }
+
+    fn show_timeline_window(&mut self, ctx: &egui::Context) {
+        let Some(thumbnails) = &mut self.thumbnails else {
+            return;
+        };
+        if !self.show_timeline {
+            return;
+        }
+        let manager = &self.image_manager;
+        self.timeline.update(
+            manager.image_paths(),
+            |path| manager.date(path),
+            manager.num_dates(),
+        );
+        let mut action = None;
+        egui::Window::new(tr!("timeline"))
+            .id(egui::Id::new("timeline"))
+            .open(&mut self.show_timeline)
+            .default_size(egui::vec2(600.0, 500.0))
+            .show(ctx, |ui| {
+                action = self.timeline.show(
+                    ui,
+                    thumbnails,
+                    manager.image_paths(),
+                    manager.current_index(),
+                );
+            });
+        match action {
+            Some(TimelineAction::GoTo(index)) => self.image_manager.go_to_index(index),
+            Some(TimelineAction::Show { name, paths }) => {
+                self.image_manager
+                    .set_filter(name, paths.into_iter().collect());
+            }
+            Some(TimelineAction::MoveTo { paths, folder }) => {
+                self.request_move_images(paths, folder)
+            }
+            Some(TimelineAction::MoveToNew { paths, folder }) => {
+                // In read-only mode the folder is left to whoever carries out the plan.
+                if !self.read_only {
+                    if let Err(e) = fs::create_dir_all(&folder) {
+                        self.status
+                            .error(format!("Failed to create {}: {}", folder.display(), e));
+                        return;
+                    }
+                }
+                self.request_move_images(paths, folder)
+            }
+            None => {}
+        }
+    }
+
+    // The last moves of the session, newest first, with the keys of their destinations.
+    fn recent_moves(&self) -> Vec<RecentMove> {
+        self.move_log
+            .iter()
+            .enumerate()
+            .rev()
+            .take(self.settings.recent_moves)
+            .map(|(index, entry)| {
+                let key = self
+                    .folder_letter_entries
+                    .iter()
+                    .find(|destination| Some(destination.folder.as_path()) == entry.dest.parent())
+                    .map(|destination| format!("{}{}", destination.icon, destination.letter))
+                    .unwrap_or_default();
+                RecentMove {
+                    index,
+                    dest: entry.dest.clone(),
+                    key,
+                }
+            })
+            .collect()
+    }
+
+    fn show_recent_moves(&mut self, ui: &mut egui::Ui) {
+        let moves = self.recent_moves();
+        let Some(thumbnails) = &mut self.thumbnails else {
+            return;
+        };
+        if moves.is_empty() {
+            return;
+        }
+        let Some(index) = filmstrip::show_recent_moves(ui, thumbnails, &moves) else {
+            return;
+        };
+        if self.refuse_in_read_only() {
+            return;
+        }
+        match self.undo_move_at(index) {
+            Ok(path) => self.status.info(format!("Undo {}", get_file_name(&path))),
+            Err(e) => self.status.error(e),
+        }
+    }
+
+    fn show_filmstrip(&mut self, ui: &mut egui::Ui) {
+        let Some(thumbnails) = &mut self.thumbnails else {
+            return;
+        };
+        if self.image_manager.folders().is_empty() {
+            return;
+        }
+        self.filmstrip.update(
+            self.image_manager.order(),
+            self.image_manager.image_paths(),
+            self.move_log
+                .iter()
+                .map(|entry| (entry.src.as_path(), entry.dest.as_path())),
+        );
+        let current = self.image_manager.current_image_path();
+        match self
+            .filmstrip
+            .show(ui, thumbnails, current, self.labels.all())
+        {
+            Some(FilmstripClick::Image(path)) => {
+                self.image_manager.go_to_image(&path);
+            }
+            Some(FilmstripClick::Moved { src, dest }) => {
+                self.status.info(format!(
+                    "{} was moved to {}",
+                    get_file_name(&src),
+                    dest.display()
+                ));
+            }
+            None => {}
+        }
+    }
+
+    // Sets the label of the current image, or clears it if it already has that label.
+    fn toggle_label(&mut self, label: Option<ColorLabel>) {
+        let Some(path) = self.image_manager.current_image_path() else {
+            return;
+        };
+        let path = path.to_path_buf();
+        // Labels are written to the sidecar.
+        if self.refuse_in_read_only() {
+            return;
+        }
+        let label = label.filter(|label| self.labels.get(&path) != Some(*label));
+        if let Err(e) = self.labels.set(&path, label) {
+            self.status.error(format!(
+                "Failed to save the label of {}: {}",
+                get_file_name(&path),
+                e
+            ));
+        }
+    }
+
+    // Sets the rating of the current image, or clears it if it already has that rating.
+    fn toggle_rating(&mut self, rating: Option<u8>) {
+        let Some(path) = self.image_manager.current_image_path() else {
+            return;
+        };
+        let path = path.to_path_buf();
+        if self.refuse_in_read_only() {
+            return;
+        }
+        let rating = rating.filter(|rating| self.labels.rating(&path) != Some(*rating));
+        if let Err(e) = self.labels.set_rating(&path, rating) {
+            self.status.error(format!(
+                "Failed to save the rating of {}: {}",
+                get_file_name(&path),
+                e
+            ));
+        }
+    }
+
+    // Leaves the current image where it is and remembers that in the index, so that it can be
+    // hidden the next time the folder is sorted.
+    fn keep_current_image(&mut self) {
+        let Some(index) = &self.library_index else {
+            self.status.warn(
+                "Remembering kept images needs the library index. Turn it on in the settings.",
+            );
+            return;
+        };
+        let Some((path, kept)) = self.image_manager.toggle_kept() else {
+            return;
+        };
+        if let Err(e) = index.set_kept(&path, kept) {
+            self.status.error(format!("Failed to save: {}", e));
+            return;
+        }
+        let name = get_file_name(&path);
+        if kept {
+            self.status.info(format!("Keeping {} here", name));
+        } else {
+            self.status
+                .info(format!("{} is no longer marked as kept", name));
+        }
+    }
+
+    // Counts the pages when a TIFF file becomes the current image. Only the page directories
+    // are read, which is quick.
+    fn update_current_page(&mut self) {
+        let current = self.image_manager.current_image_path();
+        if self
+            .current_page
+            .as_ref()
+            .map(|(path, _, _)| path.as_path())
+            == current
+        {
+            return;
+        }
+        if let Some((path, page, _)) = self.current_page.take() {
+            self.image_manager.loader.forget_page(&path, page);
+        }
+        let Some(path) = current.filter(|path| pages::is_multipage_file(path)) else {
+            return;
+        };
+        let pages = pages::page_count(path).unwrap_or_else(|e| {
+            log::warn!("Failed to count the pages of {}: {}", path.display(), e);
+            1
+        });
+        self.current_page = Some((path.to_path_buf(), 0, pages));
+    }
+
+    // Shows the next or previous page of the current image, if it has several.
+    fn turn_page(&mut self, forward: bool) {
+        let Some((path, page, pages)) = &mut self.current_page else {
+            return;
+        };
+        let turned = if forward {
+            (*page + 1).min(*pages - 1)
+        } else {
+            page.saturating_sub(1)
+        };
+        if turned != *page {
+            self.image_manager.loader.forget_page(path, *page);
+            *page = turned;
+        }
+    }
+
+    // Records in the index that the current image was shown.
+    fn mark_current_viewed(&mut self) {
+        let current = self.image_manager.current_image_path();
+        if self.last_viewed.as_deref() == current {
+            return;
+        }
+        self.last_viewed = current.map(|path| path.to_path_buf());
+        let (Some(index), Some(path)) = (&self.library_index, &self.last_viewed) else {
+            return;
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        if let Err(e) = index.mark_viewed(path, now) {
+            log::warn!("Failed to record {} as viewed: {}", path.display(), e);
+        }
+    }
+
+    // The keys of the window that `ctx` is for. The detached image window takes the same keys.
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        // Keys must not move anything while the error dialog is waiting for an answer, while
+        // typing, e.g. tags, or while the moves of the session are reverted.
+        let dialog_open = self.failed_move.is_some()
+            || self.pending_confirmation.is_some()
+            || self.new_album.is_some()
+            || self.command_palette.is_open()
+            || self
+                .session_revert
+                .as_ref()
+                .is_some_and(|revert| !revert.finished)
+            || ctx.wants_keyboard_input();
+        // Enter and Space press the button that has the keyboard focus, e.g. after Tab.
+        let widget_focused = ctx.memory(|memory| memory.focused().is_some());
+        let escape_closes = escape_closes_something(ctx);
+        let mut open_folder = false;
+        let mut rename = false;
+        let mut rescan = false;
+        let mut open_palette = false;
+        ctx.input(|input| {
+            if dialog_open {
+                return;
+            }
+            let question_mark = input.key_pressed(egui::Key::Questionmark)
+                || input
+                    .events
+                    .iter()
+                    .any(|event| matches!(event, egui::Event::Text(text) if text == "?"));
+            if question_mark {
+                self.show_shortcuts = !self.show_shortcuts;
+            }
+            // Look, don't touch, while the shortcuts are listed.
+            if self.show_shortcuts {
+                return;
+            }
+            if input.key_pressed(egui::Key::Escape) && !escape_closes {
+                self.toggle_lock();
+            }
+            if input.key_pressed(egui::Key::J) {
+                self.next_image();
+            }
+            if input.key_pressed(egui::Key::K) {
+                self.previous_image();
+            }
+            if input.key_pressed(egui::Key::PageDown) {
+                self.turn_page(true);
+            }
+            if input.key_pressed(egui::Key::PageUp) {
+                self.turn_page(false);
+            }
+            if input.key_pressed(egui::Key::Enter) && !widget_focused {
+                self.keep_current_image();
+            }
+            if input.key_pressed(egui::Key::Space) && !widget_focused {
+                if let Some(path) = self.image_manager.defer_current() {
+                    self.status
+                        .info(format!("Skipped {} for later", get_file_name(&path)));
+                }
+            }
+
+            let number_keys = [
+                egui::Key::Num1,
+                egui::Key::Num2,
+                egui::Key::Num3,
+                egui::Key::Num4,
+                egui::Key::Num5,
+            ];
+            if input.modifiers.command {
+                for key in number_keys {
+                    if input.key_pressed(key) {
+                        self.toggle_label(ColorLabel::from_key(key));
+                    }
+                }
+                if input.key_pressed(egui::Key::Num0) {
+                    self.toggle_label(None);
+                }
+            }
+            if input.modifiers.alt {
+                for (stars, key) in (1..).zip(number_keys) {
+                    if input.key_pressed(key) {
+                        self.toggle_rating(Some(stars));
+                    }
+                }
+                if input.key_pressed(egui::Key::Num0) {
+                    self.toggle_rating(None);
+                }
+            }
+
+            open_folder = input.modifiers.command && input.key_pressed(egui::Key::O);
+            rename = input.key_pressed(egui::Key::F2);
+            rescan = input.key_pressed(egui::Key::F5);
+            open_palette = input.modifiers.command && input.key_pressed(egui::Key::P);
+            if input.modifiers.command && input.key_pressed(egui::Key::N) {
+                self.new_album = Some(String::new());
+            }
+
+            if input.modifiers.ctrl && input.key_pressed(egui::Key::Z) {
+                self.undo_and_report();
+            }
+
+            // If registered letter is pressed, move the file to the folder. Not with Ctrl, which
+            // is for shortcuts like undo.
+            let plain_key =
+                !input.modifiers.ctrl && !input.modifiers.command && !input.modifiers.alt;
+            // Several destinations can share a letter.
+            let mut letters = Vec::new();
+            for entry in self.folder_letter_entries.iter().filter(|_| plain_key) {
+                let letter = entry.letter;
+                let Some(key) = egui::Key::from_name(&letter.to_string()) else {
+                    // TODO: This probably spams the log. Do it on register.
+                    log::error!("Invalid folder letter: {}", letter);
+                    continue;
+                };
+                if input.key_pressed(key) && !letters.contains(&letter) {
+                    log::debug!("Pressed key: {}", letter);
+                    letters.push(letter);
+                }
+            }
+            if self.locked && !letters.is_empty() {
+                self.status
+                    .warn("Locked, so nothing was moved. Press Escape to unlock.");
+                return;
+            }
+            for letter in letters {
+                self.press_letter(letter, Instant::now());
+            }
+        });
+        // Outside of ctx.input(), which must not be held while a dialog blocks or the image menu
+        // uses the context.
+        if open_folder {
+            if let Some(path) = FileDialog::new().pick_folder() {
+                self.open_image_folder(path);
+            }
+        }
+        if rename {
+            self.handle_image_menu_action(ctx, ImageMenuAction::Rename);
+        }
+        if rescan {
+            self.rescan_folders();
+        }
+        if open_palette {
+            let mut items = self.palette_commands();
+            items.extend(
+                self.palette_folders()
+                    .into_iter()
+                    .map(|(item, folder)| (item, PaletteTarget::Folder(folder))),
+            );
+            self.command_palette.open(items);
+        }
+    }
+
+    // Escape: while locked, the keys of the destinations do nothing, e.g. when stepping away.
+    fn toggle_lock(&mut self) {
+        self.locked = !self.locked;
+        if self.locked {
+            self.status
+                .info("Locked. The keys of the destinations do nothing until Escape is pressed.");
+        } else {
+            self.status.info("Unlocked.");
+        }
+    }
+
+    // Ctrl+Z: undoes the last move, or the last planned one in read-only mode.
+    fn undo_and_report(&mut self) {
+        if self.read_only {
+            match self.plan.pop() {
+                Some(planned) => {
+                    self.image_manager.go_to_image(&planned.src);
+                    self.status
+                        .info(format!("Unplanned {}", get_file_name(&planned.src)));
+                }
+                None => self.status.info("Nothing to undo."),
+            }
+            return;
+        }
+        let nothing_to_undo = self.move_log.is_empty();
+        match self.undo_move() {
+            Some(path) => {
+                self.status.info(format!("Undo {}", get_file_name(&path)));
+            }
+            None if nothing_to_undo => {
+                self.status.info("Nothing to undo.");
+            }
+            // `undo_move` told why.
+            None => {}
+        }
+    }
+
+    // Everything the palette can do besides moving to a folder, with the shortcuts that do it too.
+    fn palette_commands(&self) -> Vec<(PaletteItem, PaletteTarget)> {
+        let item = |label: String, key: &str, command| {
+            let item = PaletteItem {
+                label,
+                detail: key.to_string(),
+            };
+            (item, PaletteTarget::Command(command))
+        };
+        let mut commands = vec![
+            item(tr!("shortcut-undo"), "Ctrl + Z", Command::Undo),
+            item(tr!("shortcut-rename"), "F2", Command::Rename),
+            item(tr!("shortcut-open-folder"), "Ctrl + O", Command::OpenFolder),
+            item(tr!("shortcut-rescan"), "F5", Command::Rescan),
+            item(tr!("shortcut-new-album"), "Ctrl + N", Command::NewAlbum),
+            item(tr!("keyboard-shortcuts"), "?", Command::Shortcuts),
+            item(tr!("settings"), "", Command::Toggle(Toggle::Settings)),
+            item(tr!("grid"), "", Command::Toggle(Toggle::Grid)),
+            item(tr!("timeline"), "", Command::Toggle(Toggle::Timeline)),
+            item(tr!("info"), "", Command::Toggle(Toggle::Info)),
+            item(tr!("histogram"), "", Command::Toggle(Toggle::Histogram)),
+            item(tr!("history"), "", Command::Toggle(Toggle::History)),
+            item(tr!("read-only"), "", Command::Toggle(Toggle::ReadOnly)),
+            item(tr!("lock"), "Esc", Command::Toggle(Toggle::Lock)),
+            item(tr!("album-tree"), "", Command::Toggle(Toggle::AlbumTree)),
+            item(tr!("properties"), "", Command::Toggle(Toggle::Properties)),
+            item(tr!("practice-menu"), "", Command::Practice),
+        ];
+        if self.library_index.is_some() {
+            commands.push(item(tr!("library"), "", Command::Toggle(Toggle::Library)));
+            commands.push(item(
+                tr!("palette-find-duplicates"),
+                "",
+                Command::FindDuplicates,
+            ));
+        }
+        for label in ColorLabel::ALL {
+            commands.push(item(
+                tr!("palette-filter-label", label = label.name()),
+                "",
+                Command::FilterLabel(label),
+            ));
+        }
+        for stars in (1..=5).rev() {
+            commands.push(item(
+                tr!("palette-filter-rating", stars = "★".repeat(stars as usize)),
+                "",
+                Command::FilterRating(stars),
+            ));
+        }
+        if self.image_manager.filter().is_some() {
+            commands.push(item(tr!("show-all"), "", Command::ShowAll));
+        }
+        for (name, tool) in [
+            (tr!("merge-folders-menu"), Tool::Merge),
+            (tr!("flatten-folders-menu"), Tool::Flatten),
+            (tr!("organize-by-date-menu"), Tool::Organize),
+            (tr!("locations-menu"), Tool::Locations),
+            (tr!("find-blurry-menu"), Tool::Blur),
+            (tr!("find-screenshots-menu"), Tool::Screenshots),
+            (tr!("quarantine-menu"), Tool::Quarantine),
+            (tr!("discard-menu"), Tool::Discard),
+            (tr!("faces-menu"), Tool::Faces),
+            (tr!("storage-menu"), Tool::Storage),
+            (tr!("contact-sheet-menu"), Tool::ContactSheet),
+            (tr!("sync-menu"), Tool::Sync),
+            (tr!("move-log-menu"), Tool::MoveLog),
+            (tr!("audit-menu"), Tool::Audit),
+            (tr!("log-menu"), Tool::Log),
+            (tr!("revert-session-menu"), Tool::RevertSession),
+        ] {
+            commands.push(item(name, "", Command::Open(tool)));
+        }
+        commands
+    }
+
+    fn run_command(&mut self, ctx: &egui::Context, command: Command) {
+        match command {
+            Command::Undo => self.undo_and_report(),
+            Command::Rename => self.handle_image_menu_action(ctx, ImageMenuAction::Rename),
+            Command::OpenFolder => {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    self.open_image_folder(path);
+                }
+            }
+            Command::Rescan => self.rescan_folders(),
+            Command::NewAlbum => self.new_album = Some(String::new()),
+            Command::Shortcuts => self.show_shortcuts = true,
+            Command::Toggle(toggle) => {
+                let value = match toggle {
+                    Toggle::Settings => &mut self.show_settings,
+                    Toggle::Grid => &mut self.show_grid,
+                    Toggle::Timeline => {
+                        // Read the dates that are not known yet.
+                        self.dates_stale = true;
+                        &mut self.show_timeline
+                    }
+                    Toggle::Info => &mut self.show_info,
+                    Toggle::Histogram => &mut self.show_histogram,
+                    Toggle::Library => &mut self.show_library,
+                    Toggle::History => &mut self.show_status_history,
+                    Toggle::ReadOnly => &mut self.read_only,
+                    Toggle::Lock => &mut self.locked,
+                    Toggle::AlbumTree => &mut self.settings.show_album_tree,
+                    Toggle::Properties => &mut self.settings.show_properties,
+                };
+                *value = !*value;
+            }
+            Command::FindDuplicates => self.find_duplicates(),
+            Command::FilterLabel(label) => {
+                let paths = self.label_paths(label);
+                if paths.is_empty() {
+                    self.status
+                        .info(format!("No images have the {} label.", label.name()));
+                } else {
+                    self.image_manager
+                        .set_filter(format!("{} label", label.name()), paths);
+                }
+            }
+            Command::FilterRating(stars) => {
+                let paths = self.rating_paths(stars);
+                if paths.is_empty() {
+                    self.status
+                        .info(format!("No images have {} stars or more.", stars));
+                } else {
+                    self.image_manager
+                        .set_filter(format!("{} stars or more", stars), paths);
+                }
+            }
+            Command::ShowAll => self.image_manager.clear_filter(),
+            Command::Practice => self.start_practice(),
+            Command::Open(tool) => {
+                let show = match tool {
+                    Tool::Merge => &mut self.show_merge,
+                    Tool::Flatten => &mut self.show_flatten,
+                    Tool::Organize => &mut self.show_organize,
+                    Tool::Locations => &mut self.show_locations,
+                    Tool::Blur => &mut self.show_blur,
+                    Tool::Screenshots => &mut self.show_screenshots,
+                    Tool::Quarantine => &mut self.show_quarantine,
+                    Tool::Discard => {
+                        self.open_discard_review();
+                        &mut self.show_discard
+                    }
+                    Tool::Faces => &mut self.show_faces,
+                    Tool::Storage => &mut self.show_storage,
+                    Tool::ContactSheet => &mut self.show_contact_sheet,
+                    Tool::Sync => &mut self.show_sync,
+                    Tool::MoveLog => &mut self.show_replay,
+                    Tool::Audit => &mut self.show_audit,
+                    Tool::Log => {
+                        self.log_viewer.load(&app_dirs::log_dir());
+                        &mut self.show_log
+                    }
+                    Tool::RevertSession => &mut self.show_session_revert,
+                };
+                *show = true;
+            }
+        }
+    }
+
+    // The destinations, then the folders of the albums folder, for the palette.
+    fn palette_folders(&self) -> Vec<(PaletteItem, PathBuf)> {
+        let mut items: Vec<(PaletteItem, PathBuf)> = Vec::new();
+        for entry in &self.folder_letter_entries {
+            if items.iter().any(|(_, folder)| *folder == entry.folder) {
+                continue;
+            }
+            let item = PaletteItem {
+                label: remote::display_destination(&entry.folder),
+                detail: format!("{}{}", entry.icon, entry.letter),
+            };
+            items.push((item, entry.folder.clone()));
+        }
+        let Some(root) = &self.settings.albums_root else {
+            return items;
+        };
+        let base = root.parent().unwrap_or(root);
+        for folder in albums::all_folders(root, PALETTE_FOLDER_LIMIT) {
+            if items.iter().any(|(_, known)| *known == folder) {
+                continue;
+            }
+            let label = folder.strip_prefix(base).unwrap_or(&folder);
+            let item = PaletteItem {
+                label: label.to_string_lossy().to_string(),
+                detail: String::new(),
+            };
+            items.push((item, folder));
+        }
+        items
+    }
+
+    fn show_command_palette(&mut self, ctx: &egui::Context) {
+        match self.command_palette.show(ctx, &tr!("palette-hint")) {
+            Some(PaletteTarget::Folder(folder)) => self.request_move(&folder),
+            Some(PaletteTarget::Command(command)) => self.run_command(ctx, command),
+            None => {}
+        }
+    }
+
+    // The keys that do something right now and what they do, with the registered destinations.
+    fn shortcuts(&self) -> Vec<(String, String)> {
+        let mut shortcuts: Vec<(String, String)> = vec![
+            ("J / K".to_string(), tr!("shortcut-navigate")),
+            (tr!("key-page-up-down"), tr!("shortcut-page")),
+            (tr!("key-space"), tr!("shortcut-skip")),
+            (tr!("key-enter"), tr!("shortcut-keep")),
+            ("Ctrl + Z".to_string(), tr!("shortcut-undo")),
+            (tr!("key-ctrl-digits"), tr!("shortcut-label")),
+            (tr!("key-alt-digits"), tr!("shortcut-rating")),
+            (tr!("key-hold-shift"), tr!("shortcut-loupe")),
+            ("F2".to_string(), tr!("shortcut-rename")),
+            ("F5".to_string(), tr!("shortcut-rescan")),
+            ("F11".to_string(), tr!("shortcut-fullscreen")),
+            ("Ctrl + O".to_string(), tr!("shortcut-open-folder")),
+            ("Ctrl + N".to_string(), tr!("shortcut-new-album")),
+            ("Ctrl + P".to_string(), tr!("shortcut-palette")),
+            ("Esc".to_string(), tr!("shortcut-lock")),
+            (tr!("key-tab"), tr!("shortcut-focus")),
+            ("?".to_string(), tr!("shortcut-help")),
+        ];
+        for entry in &self.folder_letter_entries {
+            let folder = remote::display_destination(&entry.folder);
+            let action = if self.read_only {
+                tr!("shortcut-plan-move", folder = folder)
+            } else if entry.confirm && self.settings.confirm_dangerous_destinations {
+                tr!("shortcut-move-asks-first", folder = folder)
+            } else {
+                tr!("shortcut-move", folder = folder)
+            };
+            let key = match entry.double_press {
+                true => format!("{0} {0}", entry.letter),
+                false => entry.letter.to_string(),
+            };
+            shortcuts.push((key, action));
+        }
+        shortcuts
+    }
+
+    // The image in a window of its own while it is detached. The keys work there too.
+    fn show_detached_image(&mut self, ctx: &egui::Context) {
+        if !self.image_detached {
+            return;
+        }
+        let title = match self.image_manager.current_image_path() {
+            Some(path) => get_file_name(path),
+            None => tr!("no-image"),
+        };
+        let mut image_menu_action = None;
+        let mut close = false;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("detached_image"),
+            egui::ViewportBuilder::default()
+                .with_title(title.clone())
+                .with_inner_size(egui::vec2(960.0, 720.0)),
+            |ctx, class| {
+                let shown_page = self
+                    .current_page
+                    .clone()
+                    .filter(|(_, page, _)| *page > 0)
+                    .map(|(path, page, _)| self.image_manager.loader.page(&path, page, false));
+                let show = |ui: &mut egui::Ui| {
+                    let Some(image_info) = self.image_manager.load_current_image() else {
+                        ui.label(tr!("no-image"));
+                        return None;
+                    };
+                    let image = shown_page.unwrap_or(image_info.image);
+                    let size = ui.available_size();
+                    if let Err(e) = image.load_for_size(ui.ctx(), size) {
+                        ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                        return None;
+                    }
+                    let mut action = None;
+                    ui.centered_and_justified(|ui| {
+                        let background = ui.painter().add(egui::Shape::Noop);
+                        let response =
+                            ui.add(image.fit_to_exact_size(size).sense(egui::Sense::click()));
+                        accessibility::describe_image(&response, &title);
+                        ui.painter().set(
+                            background,
+                            self.settings
+                                .image_background
+                                .shapes(response.rect, ui.clip_rect()),
+                        );
+                        response.context_menu(|ui| {
+                            action = show_image_menu(ui, &self.folder_letter_entries);
+                        });
+                    });
+                    action
+                };
+                if class == egui::ViewportClass::Embedded {
+                    // The backend has a single window. Show it in there.
+                    let mut open = true;
+                    egui::Window::new(title.clone())
+                        .id(egui::Id::new("detached_image"))
+                        .open(&mut open)
+                        .show(ctx, |ui| image_menu_action = show(ui));
+                    close = !open;
+                } else {
+                    egui::CentralPanel::default().show(ctx, |ui| image_menu_action = show(ui));
+                    close = ctx.input(|input| input.viewport().close_requested());
+                    if ctx.input(|input| input.key_pressed(egui::Key::F11)) {
+                        let fullscreen =
+                            ctx.input(|input| input.viewport().fullscreen.unwrap_or(false));
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+                    }
+                    self.handle_keys(ctx);
+                }
+            },
+        );
+        if close {
+            self.image_detached = false;
+        }
+        if let Some(action) = image_menu_action {
+            self.handle_image_menu_action(ctx, action);
+        }
+    }
+
+    fn show_shortcuts_overlay(&mut self, ctx: &egui::Context) {
+        if !self.show_shortcuts {
+            return;
+        }
+        let shortcuts = self.shortcuts();
+        let response = egui::Modal::new(egui::Id::new("shortcuts")).show(ctx, |ui| {
+            ui.heading(tr!("keyboard-shortcuts"));
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("shortcuts_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (key, action) in &shortcuts {
+                            ui.strong(key);
+                            ui.label(action);
+                            ui.end_row();
+                        }
+                    });
+            });
+            if self.folder_letter_entries.is_empty() {
+                ui.label(tr!("shortcuts-no-destinations"));
+            }
+        });
+        if response.should_close() {
+            self.show_shortcuts = false;
+        }
+    }
+
+    // Loads the script chosen in the settings, replacing the one that was loaded.
+    fn load_script(&mut self) {
+        self.script = None;
+        self.script_shown = None;
+        self.script_suggestion = None;
+        let Some(path) = &self.settings.script_path else {
+            return;
+        };
+        match scripting::Script::load(path) {
+            Ok(script) => {
+                self.status
+                    .info(format!("Loaded the script {}", path.display()));
+                self.script = Some(script);
+            }
+            Err(e) => self.status.error(format!("Script error: {}", e)),
+        }
+    }
+
+    // Runs the hooks of the script for a newly shown image.
+    fn run_image_shown_hooks(&mut self) {
+        let current = self.image_manager.current_image_path();
+        if self.script_shown.as_deref() == current {
+            return;
+        }
+        self.script_shown = current.map(|path| path.to_path_buf());
+        self.script_suggestion = None;
+        let (Some(script), Some(path)) = (&self.script, &self.script_shown) else {
+            return;
+        };
+        let result = script
+            .on_image_shown(path)
+            .and_then(|_| script.suggest_destination(path, &metadata::read_metadata(path)));
+        match result {
+            Ok(suggestion) => self.script_suggestion = suggestion,
+            Err(e) => self.status.error(format!("Script error: {}", e)),
+        }
+    }
+
+    fn show_script_suggestion(&mut self, ui: &mut egui::Ui) {
+        let Some(folder) = self.script_suggestion.clone() else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.label("Script suggests:")
+                .on_hover_text("From suggest_destination() in your script.");
+            if ui
+                .button(folder.to_string_lossy())
+                .on_hover_text("Move the image there.")
+                .clicked()
+            {
+                self.request_move(&folder);
+            }
+        });
+    }
+
+    fn show_script_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Script:").on_hover_text(
+                "A Rhai script that can define on_image_shown(path), \
+                 suggest_destination(path, exif) and on_move(src, dest).",
+            );
+            match &self.settings.script_path {
+                Some(path) => ui.label(path.to_string_lossy()),
+                None => ui.label("None"),
+            };
+            if ui.button("Choose...").clicked() {
+                if let Some(path) = FileDialog::new().add_filter("Rhai", &["rhai"]).pick_file() {
+                    self.settings.script_path = Some(path);
+                    self.load_script();
+                }
+            }
+            if self.settings.script_path.is_some() {
+                if ui.button("Reload").clicked() {
+                    self.load_script();
+                }
+                if ui.button("Remove").clicked() {
+                    self.settings.script_path = None;
+                    self.load_script();
+                }
+            }
+        });
+    }
+
+    // Adds tags found in XMP sidecars to the index. Tags already in the index are kept.
+    fn import_sidecar_tags(&mut self, found: Vec<(PathBuf, Vec<String>)>) {
+        let Some(index) = &mut self.library_index else {
+            return;
+        };
+        for (path, tags) in found {
+            let mut known = index.tags(&path).unwrap_or_default();
+            let before = known.len();
+            for tag in tags {
+                if !known.iter().any(|known| known.eq_ignore_ascii_case(&tag)) {
+                    known.push(tag);
+                }
+            }
+            if known.len() == before {
+                continue;
+            }
+            if let Err(e) = index.set_tags(&path, &known) {
+                log::warn!("Failed to import the tags of {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn label_paths(&self, label: ColorLabel) -> HashSet<PathBuf> {
+        self.labels
+            .all()
+            .iter()
+            .filter(|(_, l)| **l == label)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    fn rating_paths(&self, stars: u8) -> HashSet<PathBuf> {
+        self.labels
+            .ratings()
+            .iter()
+            .filter(|(_, rating)| **rating >= stars)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    fn show_label_filter_menu(&mut self, ui: &mut egui::Ui) {
+        for label in ColorLabel::ALL {
+            let paths = self.label_paths(label);
+            let text = egui::RichText::new(format!("{} ({})", label.name(), paths.len()))
+                .color(label.color());
+            if ui
+                .add_enabled(!paths.is_empty(), egui::Button::new(text))
+                .clicked()
+            {
+                self.image_manager
+                    .set_filter(format!("{} label", label.name()), paths);
+                ui.close_menu();
+            }
+        }
+        if ui.button("Any label").clicked() {
+            let paths = self.labels.all().keys().cloned().collect();
+            self.image_manager.set_filter("Labeled".to_string(), paths);
+            ui.close_menu();
+        }
+        ui.separator();
+        for stars in (1..=5).rev() {
+            let paths = self.rating_paths(stars);
+            let text = format!("{} or more ({})", "★".repeat(stars as usize), paths.len());
+            if ui
+                .add_enabled(!paths.is_empty(), egui::Button::new(text))
+                .clicked()
+            {
+                self.image_manager
+                    .set_filter(format!("{} stars or more", stars), paths);
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        ui.weak("Ctrl+1 to Ctrl+5 set a label, Ctrl+0 clears it.");
+        ui.weak("Alt+1 to Alt+5 set a rating, Alt+0 clears it.");
+    }
+
+    // Tags field of the current image. Tags live in the library index, so it needs to be on.
+    fn show_tag_editor(&mut self, ui: &mut egui::Ui) {
+        let Some(index) = &mut self.library_index else {
+            return;
+        };
+        let letters: Vec<(PathBuf, char)> = self
+            .folder_letter_entries
+            .iter()
+            .map(|entry| (entry.folder.clone(), entry.letter))
+            .collect();
+        let result = self
+            .tag_editor
+            .sync(index, self.image_manager.current_image_path())
+            .and_then(|_| {
+                self.tag_editor.show(
+                    ui,
+                    index,
+                    self.read_only,
+                    self.settings.suggest_tag_destinations,
+                    &letters,
+                    self.image_manager.folders(),
+                )
+            });
+        match result {
+            Ok(Some(TagAction::Filter(tag))) => match index.paths_with_tag(&tag) {
+                Ok(paths) => self
+                    .image_manager
+                    .set_filter(format!("Tag {}", tag), paths.into_iter().collect()),
+                Err(e) => self
+                    .status
+                    .error(format!("Failed to find tagged images: {}", e)),
+            },
+            Ok(Some(TagAction::MoveTo(folder))) => self.request_move(&folder),
+            Ok(None) => {}
+            Err(e) => self.status.error(format!("Failed to save tags: {}", e)),
+        }
+    }
+
+    fn show_histogram_window(&mut self, ctx: &egui::Context) {
+        if !self.show_histogram && !self.show_clipping {
+            return;
+        }
+        let current = self.image_manager.current_image_path();
+        self.histogram.update(ctx, current);
+        egui::Window::new(tr!("histogram"))
+            .id(egui::Id::new("histogram"))
+            .open(&mut self.show_histogram)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.histogram.show_histogram(ui, current);
+            });
+    }
+
+    fn show_info_window(&mut self, ctx: &egui::Context) {
+        if !self.show_info {
+            return;
+        }
+        let current = self.image_manager.current_image_path();
+        if self.info.as_ref().map(|(path, _, _)| path.as_path()) != current {
+            self.info = current.map(|path| {
+                (
+                    path.to_path_buf(),
+                    metadata::read_metadata(path),
+                    fs::read_link(path).ok(),
+                )
+            });
+            self.note_text = match (&self.library_index, current) {
+                (Some(index), Some(path)) => index.note(path).ok().flatten().unwrap_or_default(),
+                _ => String::new(),
+            };
+        }
+        let show_map_tiles = self.settings.show_map_tiles;
+        let mut error = None;
+        egui::Window::new(tr!("info"))
+            .id(egui::Id::new("info"))
+            .open(&mut self.show_info)
+            .show(ctx, |ui| {
+                let Some((path, metadata, link)) = &self.info else {
+                    ui.label(tr!("no-image"));
+                    return;
+                };
+                egui::Grid::new("info_grid").num_columns(2).show(ui, |ui| {
+                    ui.label(tr!("info-file"));
+                    ui.label(get_file_name(path));
+                    ui.end_row();
+                    ui.label(tr!("info-folder"));
+                    ui.label(path.parent().unwrap_or(path).to_string_lossy());
+                    ui.end_row();
+                    if let Some(target) = link {
+                        ui.label(tr!("info-link"));
+                        ui.horizontal(|ui| {
+                            ui.label(target.to_string_lossy());
+                            if !path.exists() {
+                                ui.colored_label(ui.visuals().error_fg_color, tr!("broken-link"));
+                            }
+                        });
+                        ui.end_row();
+                    }
+                    ui.label(tr!("info-taken"));
+                    ui.label(metadata.taken.clone().unwrap_or_else(|| tr!("unknown")));
+                    ui.end_row();
+                    ui.label(tr!("info-description"));
+                    ui.label(metadata.description.as_deref().unwrap_or(""));
+                    ui.end_row();
+                    ui.label(tr!("info-location"));
+                    match metadata.gps {
+                        Some(gps) => {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{:.6}, {:.6}", gps.latitude, gps.longitude));
+                                ui.hyperlink_to("OpenStreetMap", geo::openstreetmap_url(gps));
+                            });
+                        }
+                        None => {
+                            ui.label(tr!("unknown"));
+                        }
+                    };
+                    ui.end_row();
+                    ui.label(tr!("info-note"));
+                    match &self.library_index {
+                        Some(index) => {
+                            let response = ui.add(
+                                egui::TextEdit::multiline(&mut self.note_text)
+                                    .desired_rows(2)
+                                    .hint_text(tr!("note-hint")),
+                            );
+                            if response.lost_focus() {
+                                if let Err(e) = index.set_note(path, &self.note_text) {
+                                    error = Some(format!("Failed to save the note: {}", e));
+                                }
+                            }
+                        }
+                        None => {
+                            ui.weak(tr!("notes-need-index"));
+                        }
+                    }
+                    ui.end_row();
+                });
+                if let Some(gps) = metadata.gps.filter(|_| show_map_tiles) {
+                    geo::show_map_tile(ui, gps);
+                }
+            });
+        if let Some(e) = error {
+            self.status.error(e);
+        }
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        egui::Window::new(tr!("settings"))
+            .id(egui::Id::new("settings"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                self.settings.show(ui);
+                ui.separator();
+                ui.label(tr!(
+                    "images-in-memory",
+                    megabytes = self.image_manager.loaded_bytes() / (1024 * 1024)
+                ));
+                self.show_thumbnail_cache_settings(ui);
+                self.show_script_settings(ui);
+                self.show_tray_settings(ui);
+                self.show_log_settings(ui);
+            });
+        self.show_settings = open;
+    }
+
+    fn show_thumbnail_cache_settings(&mut self, ui: &mut egui::Ui) {
+        let Some(thumbnails) = &mut self.thumbnails else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Thumbnail cache: {} MB used",
+                thumbnails.store().disk_usage() / (1024 * 1024)
+            ));
+            if ui.button("Clear cache").clicked() {
+                match thumbnails.clear(ui.ctx()) {
+                    Ok(()) => self.status.info("Cleared the thumbnail cache."),
+                    Err(e) => self
+                        .status
+                        .error(format!("Failed to clear the thumbnail cache: {}", e)),
+                }
+            }
+        });
+    }
+
+    fn show_library(&mut self, ctx: &egui::Context) {
+        let Some(index) = &mut self.library_index else {
+            return;
+        };
+        let current = self
+            .image_manager
+            .current_image_path()
+            .map(|path| path.to_path_buf());
+
+        let mut show_path = None;
+        let mut error = None;
+        let mut find_duplicates = false;
+        egui::Window::new(tr!("library"))
+            .id(egui::Id::new("library"))
+            .open(&mut self.show_library)
+            .default_size(egui::vec2(500.0, 400.0))
+            .show(ctx, |ui| {
+                if let Some(path) = &current {
+                    if let Ok(Some(indexed)) = index.get(path) {
+                        ui.label(format!(
+                            "{}: {} KB, taken {}, hash {}",
+                            get_file_name(path),
+                            indexed.size / 1024,
+                            indexed.exif_date.as_deref().unwrap_or("unknown"),
+                            indexed
+                                .hash
+                                .as_deref()
+                                .map_or("unknown", |hash| &hash[..12]),
+                        ));
+                    }
+                    if let Ok(Some(viewed)) = index.first_viewed(path) {
+                        let kept = if self.image_manager.is_kept(path) {
+                            ", kept in place"
+                        } else {
+                            ""
+                        };
+                        ui.label(format!(
+                            "First viewed {}{}",
+                            metadata::format_timestamp(viewed),
+                            kept
+                        ));
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    let response = ui
+                        .text_edit_singleline(&mut self.library_query)
+                        .on_hover_text("Part of a file name or a note, or a whole tag.");
+                    let submitted =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Search").clicked() || submitted {
+                        self.library_results.clear();
+                        for folder in self.image_manager.folders() {
+                            match index.search(folder, &self.library_query) {
+                                Ok(results) => self.library_results.extend(results),
+                                Err(e) => error = Some(format!("Search failed: {}", e)),
+                            }
+                        }
+                    }
+                    find_duplicates = ui
+                        .button("Find duplicates")
+                        .on_hover_text("Images with the same contents anywhere in the index.")
+                        .clicked();
+                });
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    if !self.library_results.is_empty() {
+                        ui.label(format!("{} matches", self.library_results.len()));
+                        for path in &self.library_results {
+                            ui.horizontal(|ui| {
+                                if ui.button("Show").clicked() {
+                                    show_path = Some(path.clone());
+                                }
+                                ui.label(get_file_name(path));
+                            });
+                        }
+                    }
+                    if !self.duplicate_groups.is_empty() {
+                        ui.separator();
+                        ui.label(format!(
+                            "{} groups of duplicates",
+                            self.duplicate_groups.len()
+                        ));
+                        for group in &self.duplicate_groups {
+                            for path in group {
+                                ui.horizontal(|ui| {
+                                    if ui.button("Show").clicked() {
+                                        show_path = Some(path.clone());
+                                    }
+                                    ui.label(path.to_string_lossy());
+                                });
+                            }
+                            ui.separator();
+                        }
+                    }
+                });
+            });
+
+        if let Some(e) = error {
+            self.status.error(e);
+        }
+        if find_duplicates {
+            self.find_duplicates();
+        }
+        if let Some(path) = show_path {
+            if !self.image_manager.go_to_image(&path) {
+                self.status
+                    .warn(format!("{} is not in the open folder.", path.display()));
+            }
+        }
+    }
+
+    // Groups the images with the same contents anywhere in the library index, for the library
+    // window.
+    fn find_duplicates(&mut self) {
+        let Some(index) = &self.library_index else {
+            self.status
+                .warn("Turn on the library index in the settings to find duplicates.");
+            return;
+        };
+        match index.duplicates() {
+            Ok(groups) => {
+                self.status
+                    .done(format!("Found {} groups of duplicates.", groups.len()));
+                self.duplicate_groups = groups;
+                self.show_library = true;
+            }
+            Err(e) => self
+                .status
+                .error(format!("Failed to find duplicates: {}", e)),
+        }
+    }
+
+    fn show_merge_window(&mut self, ctx: &egui::Context) {
+        self.merge_tool.poll();
+        let mut job = None;
+        egui::Window::new(tr!("merge-folders"))
+            .open(&mut self.show_merge)
+            .default_size(egui::vec2(500.0, 400.0))
+            .show(ctx, |ui| {
+                if self.read_only {
+                    ui.label(tr!("read-only-off"));
+                }
+                ui.add_enabled_ui(!self.read_only, |ui| job = self.merge_tool.show(ui));
+            });
+        if self.merge_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        if let Some(job) = job {
+            self.start_merge(job);
+        }
+    }
+
+    // Updates the image list and the move log after a tool moved or deleted files.
+    fn apply_tool_changes(&mut self, moved: &[(PathBuf, PathBuf)], deleted: &[PathBuf]) {
+        let mut gone: HashSet<PathBuf> = deleted.iter().cloned().collect();
+        for path in deleted {
+            self.audit(MoveLogEntry {
+                src: path.clone(),
+                stamp: Stamp::of(audit::Operation::Delete, path),
+                ..Default::default()
+            });
+        }
+        let mut arrived = Vec::new();
+        for (src, dest) in moved {
+            gone.insert(src.clone());
+            if self.image_manager.order().contains(dest) {
+                arrived.push(dest.clone());
+            }
+            // Each move can be undone like a normal one.
+            self.record_move(MoveLogEntry {
+                src: src.clone(),
+                dest: dest.clone(),
+                companions: Vec::new(),
+                linked: false,
+                copies: Vec::new(),
+                stamp: Stamp::of(audit::Operation::Move, dest),
+            });
+        }
+        self.image_manager.remove_images(&gone);
+        self.dates_stale |= !arrived.is_empty();
+        self.sizes_stale |= !arrived.is_empty();
+        self.colors_stale |= !arrived.is_empty();
+        self.labels.load(arrived.clone());
+        self.image_manager.add_scanned_images(arrived);
+    }
+
+    fn start_merge(&mut self, job: MergeJob) {
+        let title = tr!(
+            "merging",
+            source = job.comparison.source.display().to_string(),
+            target = job.comparison.target.display().to_string()
+        );
+        let total = job.file_count();
+        self.start_batch(title, total, move |on_file| {
+            BatchReport::Merge(job.run(on_file))
+        });
+    }
+
+    fn handle_merge_report(&mut self, report: MergeReport) {
+        self.apply_tool_changes(&report.moved, &report.removed_identical);
+        for (path, error) in &report.errors {
+            self.status
+                .error(format!("Failed to merge {}: {}", path.display(), error));
+        }
+        self.status.done(format!(
+            "Merged folders. Moved {}, deleted {} identical, left {} in the source.",
+            report.moved.len(),
+            report.removed_identical.len(),
+            report.skipped
+        ));
+    }
+
+    fn show_flatten_window(&mut self, ctx: &egui::Context) {
+        self.flatten_tool.poll();
+        let mut job = None;
+        egui::Window::new(tr!("flatten-folders"))
+            .open(&mut self.show_flatten)
+            .default_size(egui::vec2(500.0, 400.0))
+            .show(ctx, |ui| {
+                if self.read_only {
+                    ui.label(tr!("read-only-off"));
+                }
+                ui.add_enabled_ui(!self.read_only, |ui| job = self.flatten_tool.show(ui));
+            });
+        if self.flatten_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        if let Some(job) = job {
+            self.start_flatten(job);
+        }
+    }
+
+    fn start_flatten(&mut self, job: FlattenJob) {
+        let title = tr!(
+            "flattening",
+            count = job.images.len(),
+            folder = job.target.display().to_string()
+        );
+        let total = job.images.len();
+        self.start_batch(title, total, move |on_file| {
+            BatchReport::Flatten(flatten::flatten(&job.images, &job.target, on_file))
+        });
+    }
+
+    fn handle_flatten_report(&mut self, report: FlattenReport) {
+        self.apply_tool_changes(&report.moved, &[]);
+        for (path, error) in &report.errors {
+            self.status
+                .error(format!("Failed to move {}: {}", path.display(), error));
+        }
+        self.status.done(format!(
+            "Flattened folders. Moved {} images. Press Ctrl+Z to undo one at a time.",
+            report.moved.len()
+        ));
+    }
+
+    fn show_organize_window(&mut self, ctx: &egui::Context) {
+        self.organize_tool.poll();
+        let can_undo = self
+            .organize_undo
+            .is_some_and(|(start, count)| self.move_log.len() == start + count);
+        let mut action = None;
+        egui::Window::new("Organize by date")
+            .open(&mut self.show_organize)
+            .default_size(egui::vec2(600.0, 450.0))
+            .show(ctx, |ui| {
+                if self.read_only {
+                    ui.label("Turned off in read-only mode.");
+                }
+                ui.add_enabled_ui(!self.read_only, |ui| {
+                    action = self
+                        .organize_tool
+                        .show(ui, self.image_manager.image_paths(), can_undo)
+                });
+            });
+        if self.organize_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match action {
+            Some(OrganizeAction::Start { moves, destination }) => {
+                self.start_organize(moves, destination)
+            }
+            Some(OrganizeAction::Undo) => self.undo_organize(),
+            None => {}
+        }
+    }
+
+    fn start_organize(&mut self, moves: Vec<plan::PlannedMove>, destination: PathBuf) {
+        if let Err(e) = file_ops::check_reachable(&destination, self.network_timeout()) {
+            self.status
+                .error(format!("Did not move {} images: {}", moves.len(), e));
+            return;
+        }
+        let moves: Vec<(PathBuf, Vec<PathBuf>, PathBuf)> = moves
+            .into_iter()
+            .map(|planned| {
+                let companions = self.companion_files(&planned.src);
+                (planned.src, companions, planned.dest_dir)
+            })
+            .collect();
+        let title = format!(
+            "Organizing {} images in {}",
+            moves.len(),
+            destination.display()
+        );
+        let total = moves.len();
+        let stamps = self.stamps_of(moves.iter().map(|(src, _, _)| src));
+        let symlinks = self.settings.symlinks;
+        self.start_batch(title, total, move |on_file| {
+            BatchReport::Organize(organize_batch(
+                &moves,
+                &destination,
+                &stamps,
+                symlinks,
+                on_file,
+            ))
+        });
+    }
+
+    fn handle_organize_report(&mut self, report: MoveReport) {
+        let start = self.move_log.len();
+        let count = report.moved.len();
+        self.handle_move_report(report);
+        self.organize_undo = Some((start, count));
+    }
+
+    // Moves the images of the last "Organize by date" run back, newest first, and removes the
+    // folders it left empty.
+    fn undo_organize(&mut self) {
+        let Some((start, _)) = self.organize_undo.take() else {
+            return;
+        };
+        let mut folders = BTreeSet::new();
+        let mut restored = 0;
+        while self.move_log.len() > start {
+            if let Some(parent) = self.move_log.last().and_then(|entry| entry.dest.parent()) {
+                folders.insert(parent.to_path_buf());
+            }
+            if self.undo_move().is_some() {
+                restored += 1;
+            }
+        }
+        // Deepest first, so that a year folder goes after its months. Only empty ones go.
+        for folder in folders.iter().rev() {
+            let _ = fs::remove_dir(folder);
+            if let Some(parent) = folder.parent() {
+                let _ = fs::remove_dir(parent);
+            }
+        }
+        self.status
+            .done(format!("Moved {} images back where they were.", restored));
+    }
+
+    fn show_replay_window(&mut self, ctx: &egui::Context) {
+        self.replay_tool.poll();
+        let session_moves = self.exportable_moves().count();
+        let mut action = None;
+        egui::Window::new(tr!("move-log"))
+            .open(&mut self.show_replay)
+            .default_size(egui::vec2(600.0, 450.0))
+            .show(ctx, |ui| {
+                action = self.replay_tool.show(ui, session_moves, !self.read_only);
+            });
+        if self.replay_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match action {
+            Some(ReplayAction::Export) => self.export_move_log(),
+            Some(ReplayAction::Start { moves, destination }) => {
+                self.start_replay(moves, destination)
+            }
+            None => {}
+        }
+    }
+
+    // Every file operation of the session, newest first, with when it was done and the size and
+    // hash of the file.
+    fn show_audit_window(&mut self, ctx: &egui::Context) {
+        let mut export = false;
+        egui::Window::new("Audit trail")
+            .open(&mut self.show_audit)
+            .default_size(egui::vec2(700.0, 400.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} operations this session. Times are in UTC.",
+                        self.audit_log.len()
+                    ));
+                    export = ui
+                        .add_enabled(!self.audit_log.is_empty(), egui::Button::new("Export CSV"))
+                        .clicked();
+                });
+                ui.separator();
+                let row_height = ui.spacing().interact_size.y;
+                let entries = &self.audit_log;
+                egui::ScrollArea::vertical().show_rows(
+                    ui,
+                    row_height,
+                    entries.len(),
+                    |ui, rows| {
+                        for entry in rows.map(|row| &entries[entries.len() - 1 - row]) {
+                            ui.horizontal(|ui| {
+                                let stamp = &entry.stamp;
+                                ui.monospace(metadata::format_timestamp(stamp.time));
+                                ui.strong(stamp.operation.name());
+                                ui.label(get_file_name(&entry.src))
+                                    .on_hover_text(entry.src.to_string_lossy());
+                                if !entry.dest.as_os_str().is_empty() {
+                                    ui.label("->");
+                                    ui.label(remote::display_destination(&entry.dest));
+                                }
+                                if let Some(size) = stamp.size {
+                                    ui.weak(format!("{} KB", size.div_ceil(1024)));
+                                }
+                                if let Some(hash) = &stamp.hash {
+                                    ui.weak(&hash[..12]).on_hover_text(hash);
+                                }
+                            });
+                        }
+                    },
+                );
+            });
+        if export {
+            self.export_audit_log();
+        }
+    }
+
+    fn export_audit_log(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("audit.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let entries: Vec<(&Path, &Path, &Stamp)> = self
+            .audit_log
+            .iter()
+            .map(|entry| (entry.src.as_path(), entry.dest.as_path(), &entry.stamp))
+            .collect();
+        match fs::write(&path, audit::to_csv(&entries)) {
+            Ok(()) => self.status.info(format!(
+                "Exported {} operations to {}",
+                entries.len(),
+                path.display()
+            )),
+            Err(e) => self
+                .status
+                .error(format!("Failed to export the audit trail: {}", e)),
+        }
+    }
+
+    // The moves of the session that another copy of the library can replay. Links, and uploads
+    // to other machines, are not moves there.
+    fn exportable_moves(&self) -> impl Iterator<Item = &MoveLogEntry> {
+        self.move_log
+            .iter()
+            .filter(|entry| !entry.linked && remote::parse(&entry.dest).is_none())
+    }
+
+    fn export_move_log(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("moves.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let moves: Vec<replay::LoggedMove> = self
+            .exportable_moves()
+            .map(|entry| replay::LoggedMove {
+                src: entry.src.clone(),
+                dest: entry.dest.clone(),
+                hash: entry
+                    .stamp
+                    .hash
+                    .clone()
+                    .or_else(|| file_ops::hash_file(&entry.dest).ok()),
+            })
+            .collect();
+        match fs::write(&path, replay::to_csv(&moves)) {
+            Ok(()) => self.status.info(format!(
+                "Exported {} moves to {}",
+                moves.len(),
+                path.display()
+            )),
+            Err(e) => self
+                .status
+                .error(format!("Failed to export the move log: {}", e)),
+        }
+    }
+
+    fn start_replay(&mut self, moves: Vec<plan::PlannedMove>, destination: PathBuf) {
+        if self.refuse_in_read_only() {
+            return;
+        }
+        if let Err(e) = file_ops::check_reachable(&destination, self.network_timeout()) {
+            self.status
+                .error(format!("Did not move {} images: {}", moves.len(), e));
+            return;
+        }
+        let moves: Vec<(PathBuf, Vec<PathBuf>, PathBuf)> = moves
+            .into_iter()
+            .map(|planned| {
+                let companions = self.companion_files(&planned.src);
+                (planned.src, companions, planned.dest_dir)
+            })
+            .collect();
+        let title = tr!(
+            "replaying",
+            count = moves.len(),
+            folder = destination.display().to_string()
+        );
+        let total = moves.len();
+        let stamps = self.stamps_of(moves.iter().map(|(src, _, _)| src));
+        let symlinks = self.settings.symlinks;
+        self.start_batch(title, total, move |on_file| {
+            BatchReport::Move(organize_batch(
+                &moves,
+                &destination,
+                &stamps,
+                symlinks,
+                on_file,
+            ))
+        });
+    }
+
+    fn show_sync_window(&mut self, ctx: &egui::Context) {
+        let was_busy = self.sync_tool.is_busy();
+        self.sync_tool.poll();
+        if was_busy && !self.sync_tool.is_busy() {
+            self.status
+                .done("Finished comparing the library with the mirror.");
+        }
+        let mut action = None;
+        egui::Window::new(tr!("sync-with-mirror"))
+            .open(&mut self.show_sync)
+            .default_size(egui::vec2(600.0, 450.0))
+            .show(ctx, |ui| {
+                action = self.sync_tool.show(
+                    ui,
+                    &mut self.settings.sync_library,
+                    &mut self.settings.sync_mirror,
+                    !self.read_only,
+                );
+            });
+        if self.sync_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match action {
+            Some(SyncAction::Compare { library, mirror }) => {
+                let log = self
+                    .exportable_moves()
+                    .map(|entry| (entry.src.clone(), entry.dest.clone()))
+                    .collect();
+                self.sync_tool.start_compare(library, mirror, log);
+            }
+            Some(SyncAction::Start(plan)) => self.start_sync(plan),
+            None => {}
+        }
+    }
+
+    fn start_sync(&mut self, plan: sync::SyncPlan) {
+        if self.refuse_in_read_only() {
+            return;
+        }
+        let timeout = self.network_timeout();
+        if let Err(e) = file_ops::check_reachable(&plan.library, timeout)
+            .and_then(|()| file_ops::check_reachable(&plan.mirror, timeout))
+        {
+            self.status.error(format!("Did not sync: {}", e));
+            return;
+        }
+        let title = tr!(
+            "syncing",
+            library = plan.library.display().to_string(),
+            mirror = plan.mirror.display().to_string()
+        );
+        self.start_batch(title, plan.ops.len(), move |on_file| {
+            BatchReport::Sync(sync::apply(&plan, on_file))
+        });
+    }
+
+    fn handle_sync_report(&mut self, report: SyncReport) {
+        for (path, error) in &report.errors {
+            self.status
+                .error(format!("Failed to sync {}: {}", path.display(), error));
+        }
+        self.status.done(format!(
+            "Synced the library with the mirror. {} changes made.",
+            report.applied
+        ));
+    }
+
+    fn show_locations_window(&mut self, ctx: &egui::Context) {
+        let was_busy = self.location_tool.is_busy();
+        self.location_tool.poll();
+        if was_busy && !self.location_tool.is_busy() {
+            self.status.done("Finished reading the locations.");
+        }
+        let mut action = None;
+        egui::Window::new(tr!("locations"))
+            .open(&mut self.show_locations)
+            .default_size(egui::vec2(400.0, 400.0))
+            .show(ctx, |ui| {
+                action = self
+                    .location_tool
+                    .show(ui, self.image_manager.image_paths());
+            });
+        if self.location_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match action {
+            Some(LocationAction::Show { name, paths }) => {
+                self.image_manager
+                    .set_filter(tr!("near", place = name), paths.into_iter().collect());
+            }
+            Some(LocationAction::MoveTo { paths, folder }) => {
+                self.request_move_images(paths, folder)
+            }
+            None => {}
+        }
+    }
+
+    fn show_blur_window(&mut self, ctx: &egui::Context) {
+        let was_busy = self.blur_tool.is_busy();
+        self.blur_tool.poll();
+        if was_busy && !self.blur_tool.is_busy() {
+            self.status.done("Finished measuring the sharpness.");
+        }
+        let mut action = None;
+        egui::Window::new(tr!("blurry-images"))
+            .open(&mut self.show_blur)
+            .default_size(egui::vec2(400.0, 400.0))
+            .show(ctx, |ui| {
+                action = self.blur_tool.show(
+                    ui,
+                    self.image_manager.image_paths(),
+                    &mut self.settings.blur_threshold,
+                );
+            });
+        if self.blur_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match action {
+            Some(BlurAction::Show(path)) => {
+                let found = self.image_manager.go_to_image(&path);
+                if !found {
+                    self.status.warn(format!(
+                        "{} is no longer in the folder.",
+                        get_file_name(&path)
+                    ));
+                }
+            }
+            Some(BlurAction::Review(paths)) => {
+                self.image_manager
+                    .set_filter(tr!("blurry-filter"), paths.into_iter().collect());
+            }
+            None => {}
+        }
+    }
+
+    fn show_screenshots_window(&mut self, ctx: &egui::Context) {
+        let was_busy = self.screenshot_tool.is_busy();
+        self.screenshot_tool.poll();
+        if was_busy && !self.screenshot_tool.is_busy() {
+            self.status.done("Finished looking for screenshots.");
+        }
+        let mut action = None;
+        egui::Window::new(tr!("screenshots"))
+            .open(&mut self.show_screenshots)
+            .default_size(egui::vec2(400.0, 400.0))
+            .show(ctx, |ui| {
+                action = self.screenshot_tool.show(
+                    ui,
+                    self.image_manager.image_paths(),
+                    &mut self.settings.screenshot_folder,
+                );
+            });
+        if self.screenshot_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match action {
+            Some(ScreenshotAction::Show(path)) => {
+                let found = self.image_manager.go_to_image(&path);
+                if !found {
+                    self.status.warn(format!(
+                        "{} is no longer in the folder.",
+                        get_file_name(&path)
+                    ));
+                }
+            }
+            Some(ScreenshotAction::Review(paths)) => {
+                self.image_manager
+                    .set_filter(tr!("screenshots"), paths.into_iter().collect());
+            }
+            Some(ScreenshotAction::MoveTo { paths, folder }) => {
+                self.request_move_images(paths, folder)
+            }
+            None => {}
+        }
+    }
+
+    fn show_quarantine_window(&mut self, ctx: &egui::Context) {
+        let was_busy = self.quarantine_tool.is_busy();
+        self.quarantine_tool.poll();
+        if was_busy && !self.quarantine_tool.is_busy() {
+            self.status.done("Finished looking for problem files.");
+        }
+        let mut action = None;
+        egui::Window::new(tr!("quarantine"))
+            .open(&mut self.show_quarantine)
+            .default_size(egui::vec2(500.0, 400.0))
+            .show(ctx, |ui| {
+                action = self.quarantine_tool.show(
+                    ui,
+                    self.image_manager.image_paths(),
+                    &mut self.settings.quarantine_folder,
+                );
+            });
+        if self.quarantine_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match action {
+            Some(QuarantineAction::Show(path)) => {
+                let found = self.image_manager.go_to_image(&path);
+                if !found {
+                    self.status.warn(format!(
+                        "{} is no longer in the folder.",
+                        get_file_name(&path)
+                    ));
+                }
+            }
+            Some(QuarantineAction::Quarantine { problems, folder }) => {
+                self.quarantine_images(problems, folder)
+            }
+            None => {}
+        }
+    }
+
+    fn open_discard_review(&mut self) {
+        if let Some(folder) = &self.settings.discard_folder {
+            self.discard_review.load(folder);
+        }
+    }
+
+    fn show_discard_window(&mut self, ctx: &egui::Context) {
+        let mut action = None;
+        egui::Window::new(tr!("discard-folder"))
+            .open(&mut self.show_discard)
+            .default_size(egui::vec2(500.0, 500.0))
+            .show(ctx, |ui| {
+                if self.read_only {
+                    ui.label(tr!("read-only-off"));
+                }
+                ui.add_enabled_ui(!self.read_only, |ui| {
+                    action = self.discard_review.show(
+                        ui,
+                        &mut self.settings.discard_folder,
+                        self.thumbnails.as_mut(),
+                    );
+                });
+            });
+        let Some(action) = action else {
+            return;
+        };
+        match action {
+            DiscardAction::Restore(files) => {
+                let mut restored = Vec::new();
+                for file in files {
+                    match discard::restore(&file) {
+                        Ok(dest) => restored.push((file.path, dest)),
+                        Err(e) => self.status.error(format!(
+                            "Failed to restore {}: {}",
+                            file.path.display(),
+                            e
+                        )),
+                    }
+                }
+                self.apply_tool_changes(&restored, &[]);
+                self.status
+                    .info(format!("Restored {} files.", restored.len()));
+            }
+            DiscardAction::Purge(paths) => {
+                let mut deleted = Vec::new();
+                for path in paths {
+                    match fs::remove_file(&path) {
+                        Ok(()) => deleted.push(path),
+                        Err(e) => {
+                            self.status
+                                .error(format!("Failed to delete {}: {}", path.display(), e))
+                        }
+                    }
+                }
+                self.apply_tool_changes(&[], &deleted);
+                self.status
+                    .info(format!("Deleted {} files for good.", deleted.len()));
+            }
+        }
+        if let Some(folder) = &self.settings.discard_folder {
+            if let Err(e) = discard::compact(folder) {
+                log::warn!("Failed to tidy the discard log: {}", e);
+            }
+        }
+        self.open_discard_review();
+    }
+
+    fn show_faces_window(&mut self, ctx: &egui::Context) {
+        let was_busy = self.face_tool.is_busy();
+        self.face_tool.poll();
+        if was_busy && !self.face_tool.is_busy() {
+            self.status.done("Finished looking for faces.");
+        }
+        let mut action = None;
+        egui::Window::new(tr!("faces"))
+            .open(&mut self.show_faces)
+            .default_size(egui::vec2(500.0, 500.0))
+            .show(ctx, |ui| {
+                let models = FaceModelPaths {
+                    detector: &mut self.settings.face_detector_model,
+                    embedder: &mut self.settings.face_embedder_model,
+                };
+                action = self
+                    .face_tool
+                    .show(ui, self.image_manager.image_paths(), models);
+            });
+        if self.face_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        match action {
+            Some(FaceAction::Review(paths)) => {
+                self.image_manager
+                    .set_filter(tr!("person"), paths.into_iter().collect());
+            }
+            Some(FaceAction::MoveTo { paths, folder }) => self.request_move_images(paths, folder),
+            None => {}
+        }
+    }
+
+    fn show_storage_window(&mut self, ctx: &egui::Context) {
+        self.storage_tool.poll();
+        let destinations: Vec<PathBuf> = self
+            .folder_letter_entries
+            .iter()
+            .map(|entry| entry.folder.clone())
+            .collect();
+        egui::Window::new(tr!("storage-usage"))
+            .open(&mut self.show_storage)
+            .default_size(egui::vec2(500.0, 300.0))
+            .show(ctx, |ui| self.storage_tool.show(ui, &destinations));
+        if self.storage_tool.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    // The sheet shows the images that are shown, in their order, so a filter picks them.
+    fn show_contact_sheet_window(&mut self, ctx: &egui::Context) {
+        match self.contact_sheet.poll() {
+            Some(Ok(dest)) => self
+                .status
+                .done(format!("Saved the contact sheet to {}", dest.display())),
+            Some(Err(e)) => self
+                .status
+                .error(format!("Failed to save the contact sheet: {}", e)),
+            None => {}
+        }
+        let paths: Vec<PathBuf> = self
+            .image_manager
+            .image_paths()
+            .iter()
+            .filter(|path| self.image_manager.is_visible(path))
+            .cloned()
+            .collect();
+        let font = || {
+            let data = FontAsset::get("NotoSansJP-VariableFont_wght.ttf").unwrap();
+            FontArc::try_from_vec(data.data.to_vec()).unwrap()
+        };
+        egui::Window::new(tr!("contact-sheet"))
+            .open(&mut self.show_contact_sheet)
+            .show(ctx, |ui| self.contact_sheet.show(ui, &paths, font));
+        if self.contact_sheet.is_busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+    }
+
+    fn start_integrity_scan(&mut self) {
+        if let Some(scan) = &self.integrity_scan {
+            scan.cancel();
+        }
+        let paths = self.image_manager.image_paths().to_vec();
+        self.status
+            .info(format!("Checking {} images for damage.", paths.len()));
+        self.integrity_scan = Some(integrity::scan(paths));
+        self.show_integrity_scan = true;
+    }
+
+    fn show_integrity_scan(&mut self, ctx: &egui::Context) {
+        let Some(scan) = &mut self.integrity_scan else {
+            return;
+        };
+        let was_done = scan.is_done();
+        scan.poll();
+        if !scan.is_done() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        } else if !was_done {
+            self.status.done(format!(
+                "Integrity check finished. {} damaged images found.",
+                scan.found().len()
+            ));
+        }
+
+        let mut show_path = None;
+        let mut quarantine_path = None;
+        egui::Window::new("Integrity check")
+            .open(&mut self.show_integrity_scan)
+            .default_size(egui::vec2(500.0, 300.0))
+            .show(ctx, |ui| {
+                let (checked, total) = scan.progress();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::ProgressBar::new(checked as f32 / total.max(1) as f32)
+                            .text(format!("{}/{}", checked, total)),
+                    );
+                    if !scan.is_done() && ui.button("Cancel").clicked() {
+                        scan.cancel();
+                    }
+                });
+                if scan.is_cancelled() {
+                    ui.label("Cancelled.");
+                }
+                ui.label(format!("Damaged images: {}", scan.found().len()));
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (path, error) in scan.found() {
+                        ui.horizontal(|ui| {
+                            if ui.button("Show").clicked() {
+                                show_path = Some(path.clone());
+                            }
+                            if ui.button("Quarantine").clicked() {
+                                quarantine_path = Some(path.clone());
+                            }
+                            ui.label(get_file_name(path));
+                            ui.colored_label(ui.visuals().error_fg_color, error);
+                        });
+                    }
+                });
+            });
+
+        if let Some(path) = show_path {
+            if !self.image_manager.go_to_image(&path) {
+                self.status.warn(format!(
+                    "{} is no longer in the folder.",
+                    get_file_name(&path)
+                ));
+            }
+        }
+        if let Some(path) = quarantine_path {
+            if self.image_manager.go_to_image(&path) {
+                self.handle_broken_image_action(BrokenImageAction::Quarantine);
+            }
+            if !path.exists() {
+                if let Some(scan) = &mut self.integrity_scan {
+                    scan.forget(&path);
+                }
+            }
+        }
+    }
+
+    // Undo the last move. The image is reinserted to the current index.
+    // Returns the path to the un-done file.
+    fn undo_move(&mut self) -> Option<PathBuf> {
+        if self.move_log.is_empty() {
+            return None;
+        }
+        self.undo_last_move()
+            .inspect_err(|e| self.status.error(e.clone()))
+            .ok()
+    }
+
+    // Takes the last move off the log and moves the files back. The error says why the move
+    // could not be undone; it stays on the log if undoing it may work later, e.g. once a file
+    // that took the name is moved away.
+    fn undo_last_move(&mut self) -> Result<PathBuf, String> {
+        match self.move_log.len() {
+            0 => Err("Nothing to undo.".to_string()),
+            len => self.undo_move_at(len - 1),
+        }
+    }
+
+    // Like undo_last_move(), for the move at `index` in the log, e.g. one clicked in the strip of
+    // recent moves.
+    fn undo_move_at(&mut self, index: usize) -> Result<PathBuf, String> {
+        self.undo_move_as(index, self.settings.collisions)
+    }
+
+    // Like undo_move_at(), with `collisions` for when another file took the original name since.
+    fn undo_move_as(
+        &mut self,
+        index: usize,
+        collisions: CollisionPolicy,
+    ) -> Result<PathBuf, String> {
+        if index >= self.move_log.len() {
+            return Err("Nothing to undo.".to_string());
+        }
+        let entry = self.move_log.remove(index);
+        let dest = entry.dest.clone();
+        let copies = entry.copies.clone();
+        let src = match self.restore_move(entry.clone(), collisions) {
+            Ok(src) => src,
+            Err(e) => {
+                if can_undo_later(&entry) {
+                    self.move_log.insert(index, entry);
+                }
+                return Err(e);
+            }
+        };
+        // Only once the image is back, as they may be the only copies otherwise.
+        for copy in &copies {
+            if let Err(e) = fs::remove_file(copy) {
+                log::warn!("Failed to undo {}: {}", copy.display(), e);
+            }
+        }
+        self.audit(MoveLogEntry {
+            src: dest,
+            dest: src.clone(),
+            stamp: Stamp::of(audit::Operation::Undo, &src),
+            ..Default::default()
+        });
+        Ok(src)
+    }
+
+    // Moves the files of a move that is off the log back, or removes the link or the copy. The
+    // copies made to other destinations are left to the caller.
+    fn restore_move(
+        &mut self,
+        last_move: MoveLogEntry,
+        collisions: CollisionPolicy,
+    ) -> Result<PathBuf, String> {
+        if remote::parse(&last_move.dest).is_some() {
+            return Err(format!(
+                "{} was uploaded to {} and cannot be undone.",
+                get_file_name(&last_move.src),
+                remote::display_destination(&last_move.dest)
+            ));
+        }
+        let src = last_move.src;
+        let dest = last_move.dest;
+        let from_archive = archive::split(&src).is_some();
+        if last_move.linked {
+            // The link may be the only copy left if the image was deleted since.
+            if !from_archive && !src.exists() {
+                return Err(format!(
+                    "Kept {} as {} is gone.",
+                    dest.display(),
+                    src.display()
+                ));
+            }
+            if let Err(e) = fs::remove_file(&dest) {
+                return Err(format!("Failed to undo {}: {}", dest.display(), e));
+            }
+            for (_, companion_dest) in &last_move.companions {
+                if let Err(e) = fs::remove_file(companion_dest) {
+                    log::warn!("Failed to undo {}: {}", companion_dest.display(), e);
+                }
+            }
+            self.image_manager.go_to_image(&src);
+            return Ok(src);
+        }
+        if from_archive {
+            // The image is still in the archive. Only the extracted file goes.
+            if let Err(e) = fs::remove_file(&dest) {
+                return Err(format!("Failed to undo {}: {}", dest.display(), e));
+            }
+            self.session.moved = self.session.moved.saturating_sub(1);
+            self.labels.rename(&dest, &src);
+            self.image_manager.add_image_to_current_position(&src);
+            return Ok(src);
+        }
+        // A file that took the name since is never replaced.
+        let restored = file_ops::available_path(&src, &dest, collisions)
+            .and_then(|restored| file_ops::rename_or_copy(&dest, &restored).map(|()| restored))
+            .map_err(|e| format!("Failed to undo {}: {}", dest.display(), e))?;
+        if restored != src {
+            self.status.warn(format!(
+                "{} is taken by another file, so it was moved back as {}.",
+                src.display(),
+                get_file_name(&restored)
+            ));
+        }
+        if src.parent() != dest.parent() {
+            let size = self.image_manager.order().sizes().get(&src).copied();
+            self.freed_bytes = self.freed_bytes.saturating_sub(size.unwrap_or(0));
+            self.session.moved = self.session.moved.saturating_sub(1);
+        }
+        self.rename_in_index(&dest, &restored);
+        self.labels.rename(&dest, &restored);
+        // A renamed image is still in the list under its new name.
+        self.image_manager
+            .remove_images(&HashSet::from([dest.clone()]));
+        for (companion_src, companion_dest) in &last_move.companions {
+            // Renamed along with the image, so that they still go together.
+            let wanted = match restored == src {
+                true => None,
+                false => file_ops::renamed_companion(companion_src, &src, &restored),
+            };
+            let wanted = wanted.unwrap_or_else(|| companion_src.clone());
+            let result =
+                file_ops::available_path(&wanted, companion_dest, CollisionPolicy::KeepBoth)
+                    .and_then(|wanted| file_ops::rename_or_copy(companion_dest, &wanted));
+            if let Err(e) = result {
+                log::warn!("Failed to undo {}: {}", companion_dest.display(), e);
+            }
+        }
+        self.image_manager.add_image_to_current_position(&restored);
+        Ok(restored)
+    }
+}
+
+impl eframe::App for MyApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.settings.save(storage);
+        self.layout.save(storage);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.image_manager.set_context(ctx);
+        self.update_recovery_snapshot();
+        self.check_close_request(ctx);
+        self.poll_folder_scans(ctx);
+        self.poll_index_update(ctx);
+        self.poll_date_scan(ctx);
+        self.poll_uploads(ctx);
+        self.poll_size_scan(ctx);
+        self.poll_color_scan(ctx);
+        self.poll_handovers(ctx);
+        self.poll_tray(ctx);
+        let found_tags = self.labels.poll();
+        self.import_sidecar_tags(found_tags);
+        if self.labels.is_loading() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        if !self.image_manager.ensure_visible() {
+            self.image_manager.clear_filter();
+            self.status
+                .info("No images are left in the filter. Showing all images.");
+        }
+        self.image_manager.cleanup();
+        self.mark_current_viewed();
+        self.update_current_page();
+        self.run_image_shown_hooks();
+        if !self.retry_queue.is_empty() {
+            self.process_retry_queue();
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
+        self.handle_keys(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    if ui.button(tr!("choose-image-folder")).clicked() {
+                        if let Some(path) = FileDialog::new().pick_folder() {
+                            self.open_image_folder(path);
+                        }
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.image_manager.folders().is_empty(),
+                            egui::Button::new(tr!("add-folder")),
+                        )
+                        .on_hover_text(tr!("add-folder-hint"))
+                        .clicked()
+                    {
+                        if let Some(path) = FileDialog::new().pick_folder() {
+                            self.add_source_folder(path);
+                        }
+                    }
+                    if ui
+                        .button(tr!("open-archive"))
+                        .on_hover_text(tr!("open-archive-hint"))
+                        .clicked()
+                    {
+                        if let Some(path) = FileDialog::new()
+                            .add_filter("Archives", &archive::ARCHIVE_EXTENSIONS)
+                            .pick_file()
+                        {
+                            self.add_source_folder(path);
+                        }
+                    }
+                    ui.label(format!("({})", self.image_manager.num_images()));
+                    if !self.folder_scans.is_empty() {
+                        ui.spinner();
+                        ui.label(tr!("scanning"));
+                        if ui.button(tr!("cancel")).clicked() {
+                            self.cancel_folder_scans();
+                        }
+                    }
+                    if let Some(update) = &self.index_update {
+                        let (indexed, total) = update.progress();
+                        ui.spinner();
+                        ui.label(tr!("indexing", indexed = indexed, total = total));
+                    }
+                    if self.date_scan.is_some() {
+                        ui.spinner();
+                        ui.label(tr!("reading-dates"));
+                    }
+                    if self.size_scan.is_some() {
+                        ui.spinner();
+                        ui.label(tr!("reading-sizes"));
+                    }
+                    if self.color_scan.is_some() {
+                        ui.spinner();
+                        ui.label(tr!("reading-colors"));
+                    }
+                    if ui
+                        .add_enabled(
+                            self.image_manager.num_images() > 0,
+                            egui::Button::new(tr!("check-images")),
+                        )
+                        .on_hover_text(tr!("check-images-hint"))
+                        .clicked()
+                    {
+                        self.start_integrity_scan();
+                    }
+                });
+
+                if self.sort_order == SortOrder::Color
+                    && !self.image_manager.order().colors().is_empty()
+                {
+                    let mut hue = None;
+                    ui.horizontal(|ui| {
+                        ui.label(tr!("filter-by-color"));
+                        for candidate in palette::Hue::ALL {
+                            if ui.button(candidate.name()).clicked() {
+                                hue = Some(candidate);
+                            }
+                        }
+                    });
+                    if let Some(hue) = hue {
+                        self.filter_by_hue(hue);
+                    }
+                }
+                let mut clear_filter = false;
+                if let Some((name, count)) = self.image_manager.filter() {
+                    ui.horizontal(|ui| {
+                        ui.label(tr!("showing-only", name = name, count = count));
+                        clear_filter = ui.button(tr!("show-all")).clicked();
+                    });
+                }
+                let hidden_kept = self.image_manager.num_hidden_kept();
+                if hidden_kept > 0 {
+                    ui.horizontal(|ui| {
+                        ui.label(tr!("hidden-kept", count = hidden_kept));
+                        if ui.button(tr!("show-them")).clicked() {
+                            self.settings.hide_kept_images = false;
+                        }
+                    });
+                }
+                let deferred = self.image_manager.num_deferred();
+                if deferred > 0 {
+                    ui.label(tr!("deferred", count = deferred));
+                }
+                if clear_filter {
+                    self.image_manager.clear_filter();
+                }
+
+                let mut remove_folder = None;
+                ui.horizontal_wrapped(|ui| {
+                    let folders = self.image_manager.folders();
+                    match folders.len() {
+                        0 => {
+                            ui.label(tr!("selected-folder"));
+                            ui.label(tr!("no-folder-selected"));
+                        }
+                        1 => {
+                            ui.label(tr!("selected-folder"));
+                            ui.label(folders[0].to_string_lossy());
+                        }
+                        _ => {
+                            ui.label(tr!("folders"));
+                            for folder in folders {
+                                ui.label(folder.to_string_lossy());
+                                let remove =
+                                    ui.small_button("x").on_hover_text(tr!("remove-from-queue"));
+                                accessibility::set_name(
+                                    &remove,
+                                    tr!("remove-source", folder = folder.display().to_string()),
+                                );
+                                if remove.clicked() {
+                                    remove_folder = Some(folder.clone());
+                                }
+                            }
+                        }
+                    }
+                });
+                if let Some(folder) = remove_folder {
+                    self.remove_source_folder(&folder);
+                }
+                self.show_goal(ui);
+
+                ui.horizontal(|ui| {
+                    ui.label(tr!("status"));
+                    if let Some(latest) = self.status.latest() {
+                        let response =
+                            ui.colored_label(latest.color(ui.visuals()), &latest.message);
+                        accessibility::announce_changes(&response);
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.toggle_value(&mut self.show_settings, tr!("settings"));
+                        ui.menu_button(tr!("tools"), |ui| {
+                            if ui.button(tr!("merge-folders-menu")).clicked() {
+                                self.show_merge = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("flatten-folders-menu")).clicked() {
+                                self.show_flatten = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("organize-by-date-menu")).clicked() {
+                                self.show_organize = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("locations-menu")).clicked() {
+                                self.show_locations = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("find-blurry-menu")).clicked() {
+                                self.show_blur = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("find-screenshots-menu")).clicked() {
+                                self.show_screenshots = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("quarantine-menu")).clicked() {
+                                self.show_quarantine = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("discard-menu")).clicked() {
+                                self.open_discard_review();
+                                self.show_discard = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("faces-menu")).clicked() {
+                                self.show_faces = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("storage-menu")).clicked() {
+                                self.show_storage = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("contact-sheet-menu")).clicked() {
+                                self.show_contact_sheet = true;
+                                ui.close_menu();
+                            }
+                            ui.separator();
+                            if ui.button(tr!("sync-menu")).clicked() {
+                                self.show_sync = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("move-log-menu")).clicked() {
+                                self.show_replay = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("audit-menu")).clicked() {
+                                self.show_audit = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("log-menu")).clicked() {
+                                self.open_log();
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("wizard-menu")).clicked() {
+                                self.wizard = Some(Wizard::default());
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("practice-menu")).clicked() {
+                                self.start_practice();
+                                ui.close_menu();
+                            }
+                            if ui.button(tr!("revert-session-menu")).clicked() {
+                                self.show_session_revert = true;
+                                ui.close_menu();
+                            }
+                        });
+                        ui.menu_button(tr!("labels"), |ui| self.show_label_filter_menu(ui));
+                        ui.menu_button(tr!("goal"), |ui| {
+                            if let Some(goal) = self.goal_editor.show(ui) {
+                                let sorted = self.session.moved + self.session.deleted;
+                                self.goal = Some(SessionGoal::new(goal, sorted, Instant::now()));
+                            }
+                        });
+                        ui.toggle_value(&mut self.show_grid, tr!("grid"));
+                        if ui
+                            .toggle_value(&mut self.show_timeline, tr!("timeline"))
+                            .on_hover_text(tr!("timeline-hint"))
+                            .changed()
+                        {
+                            // Read the dates that are not known yet.
+                            self.dates_stale = true;
+                        }
+                        ui.toggle_value(&mut self.show_info, tr!("info"));
+                        ui.toggle_value(&mut self.show_histogram, tr!("histogram"));
+                        if self.library_index.is_some() {
+                            ui.toggle_value(&mut self.show_library, tr!("library"));
+                        }
+                        ui.toggle_value(&mut self.show_status_history, tr!("history"));
+                        ui.toggle_value(&mut self.read_only, tr!("read-only"))
+                            .on_hover_text(tr!("read-only-hint"));
+                        ui.toggle_value(&mut self.locked, tr!("lock"))
+                            .on_hover_text(tr!("lock-hint"));
+                    });
+                });
+
+                if self.read_only {
+                    self.show_plan(ui);
+                }
+
+                if !self.retry_queue.is_empty() {
+                    self.show_retry_queue(ui);
+                }
+
+                if !self.unreachable_folders.is_empty() {
+                    self.show_unreachable_folders(ui);
+                }
+
+                if self.image_manager.at_end() {
+                    self.show_end_of_folder(ui);
+                }
+
+                let available_height = ui.available_size().y;
+                let image_height = available_height * self.layout.image_fraction;
+                // The properties take two rows of the image's share.
+                let properties_height = if self.settings.show_properties {
+                    2.0 * (ui.spacing().interact_size.y + ui.spacing().item_spacing.y)
+                } else {
+                    0.0
+                };
+                let image_area = egui::Rect::from_min_size(
+                    egui::Pos2::ZERO,
+                    egui::Vec2::new(
+                        ui.available_width(),
+                        (image_height - properties_height).max(0.0),
+                    ),
+                );
+
+                // TODO: Tidy this up. It used to be in if let below but was
+                // extracted due to borrow checker.
+                let n_out_of_all = format!(
+                    "({}/{})",
+                    self.image_manager.current_index() + 1,
+                    self.image_manager.num_images(),
+                );
+                let multiple_folders = self.image_manager.folders().len() > 1;
+                let current_size = self
+                    .image_manager
+                    .current_image_path()
+                    .and_then(|path| self.image_manager.order().sizes().get(path).copied());
+                let current_color = self
+                    .image_manager
+                    .current_image_path()
+                    .and_then(|path| self.image_manager.order().colors().get(path).copied());
+                let mut broken_image_action = None;
+                let mut missing_image = None;
+                let mut image_menu_action = None;
+                let mut turn_page = None;
+                let burst_position = self.image_manager.burst_position();
+                let mut toggle_stack = false;
+                // Another page than the first of a multi-page TIFF, instead of the image.
+                let shown_page = self
+                    .current_page
+                    .clone()
+                    .filter(|(_, page, _)| *page > 0)
+                    .map(|(path, page, _)| self.image_manager.loader.page(&path, page, false));
+                let page = self.current_page.as_ref().map_or(0, |(_, page, _)| *page);
+                // The loupe is shown while Shift is held, but not while typing.
+                let loupe_held =
+                    ctx.input(|input| input.modifiers.shift) && !ctx.wants_keyboard_input();
+                // Display the current image:
+                let mut full_resolution_image = None;
+                // Where the splitter goes when the image is narrower than the area.
+                let mut image_bottom = None;
+                if let Some(image_info) = self.image_manager.load_current_image() {
+                    let filename = get_file_name(&image_info.path);
+                    // Tell where the image came from when sorting several folders.
+                    let source = match image_info.path.parent() {
+                        Some(folder) if multiple_folders => {
+                            format!(
+                                " {}",
+                                tr!("from-folder", folder = folder.display().to_string())
+                            )
+                        }
+                        _ => String::new(),
+                    };
+                    let mut pair = String::new();
+                    if self.settings.pair_raw_with_jpeg {
+                        for raw_file in raw::paired_raw_files(&image_info.path) {
+                            if let Some(extension) = raw_file.extension() {
+                                pair.push_str(&format!(" + {}", extension.to_string_lossy()));
+                            }
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        let response = ui.label(format!(
+                            "{} {} {}{}{}",
+                            tr!("current-image"),
+                            n_out_of_all,
+                            filename,
+                            pair,
+                            source
+                        ));
+                        accessibility::announce_changes(&response);
+                        if let Some(rating) = self.labels.rating(&image_info.path) {
+                            ui.label("★".repeat(rating as usize));
+                        }
+                        if let Some(color) = current_color {
+                            palette::swatch(ui, color).on_hover_text(tr!(
+                                "dominant-color",
+                                color = palette::Hue::of(color).name()
+                            ));
+                        }
+                        if self.sort_order == SortOrder::Largest {
+                            if let Some(size) = current_size {
+                                ui.label(file_ops::format_size(size));
+                            }
+                            ui.weak(tr!(
+                                "freed-so-far",
+                                size = file_ops::format_size(self.freed_bytes)
+                            ));
+                        }
+                        if let Some((_, page, pages)) = &self.current_page {
+                            if *pages > 1 {
+                                if ui
+                                    .add_enabled(*page > 0, egui::Button::new("◀"))
+                                    .on_hover_text(tr!("previous-page"))
+                                    .clicked()
+                                {
+                                    turn_page = Some(false);
+                                }
+                                ui.label(tr!("page-of", page = page + 1, pages = *pages));
+                                if ui
+                                    .add_enabled(page + 1 < *pages, egui::Button::new("▶"))
+                                    .on_hover_text(tr!("next-page"))
+                                    .clicked()
+                                {
+                                    turn_page = Some(true);
+                                }
+                            }
+                        }
+                        match burst_position {
+                            Some((_, count, false)) => {
+                                toggle_stack = ui
+                                    .button(format!("▸ {}", tr!("burst-stack", count = count)))
+                                    .on_hover_text(tr!("burst-stack-hint"))
+                                    .clicked();
+                            }
+                            Some((shot, count, true)) => {
+                                toggle_stack = ui
+                                    .button(format!(
+                                        "▾ {}",
+                                        tr!("burst-shot", shot = shot, count = count)
+                                    ))
+                                    .on_hover_text(tr!("burst-shot-hint"))
+                                    .clicked();
+                            }
+                            None => {}
+                        }
+                        ui.toggle_value(&mut self.show_full_resolution, "100%")
+                            .on_hover_text(tr!("full-resolution-hint"));
+                        ui.toggle_value(&mut self.show_clipping, tr!("clipping"))
+                            .on_hover_text(tr!("clipping-hint"));
+                        ui.toggle_value(&mut self.image_detached, tr!("detach-image"))
+                            .on_hover_text(tr!("detach-image-hint"));
+                        ui.toggle_value(&mut self.settings.show_properties, tr!("properties"))
+                            .on_hover_text(tr!("properties-hint"));
+                    });
+                    if self.settings.show_properties {
+                        show_properties(
+                            ui,
+                            &image_info.path,
+                            &mut self.properties,
+                            &mut self.status,
+                        );
+                    }
+                    let image = shown_page.unwrap_or(image_info.image);
+                    match image.load_for_size(ctx, image_area.size()) {
+                        Ok(_) if self.image_detached => {
+                            ui.label(tr!("image-detached"));
+                            if ui.button(tr!("attach-image")).clicked() {
+                                self.image_detached = false;
+                            }
+                        }
+                        Ok(_) if self.show_full_resolution => {
+                            full_resolution_image = Some(image_info.path);
+                        }
+                        Ok(_) => {
+                            // Filled in once the size of the image is known.
+                            let background = ui.painter().add(egui::Shape::Noop);
+                            let response = ui.add(
+                                image
+                                    .fit_to_exact_size(image_area.size())
+                                    .sense(egui::Sense::click()),
+                            );
+                            image_bottom = Some(response.rect.top() + image_area.height());
+                            accessibility::describe_image(&response, &filename);
+                            ui.painter().set(
+                                background,
+                                self.settings
+                                    .image_background
+                                    .shapes(response.rect, ui.clip_rect()),
+                            );
+                            response.context_menu(|ui| {
+                                image_menu_action =
+                                    show_image_menu(ui, &self.folder_letter_entries);
+                            });
+                            if let Some(label) = self.labels.get(&image_info.path) {
+                                ui.painter().rect_stroke(
+                                    response.rect.expand(2.0),
+                                    0.0,
+                                    egui::Stroke::new(4.0, label.color()),
+                                );
+                            }
+                            if self.show_clipping {
+                                self.histogram
+                                    .paint_clipping(ui, response.rect, &image_info.path);
+                            }
+                            if loupe_held {
+                                self.loupe.show(ui, response.rect, &image_info.path, page);
+                            }
+                        }
+                        // Deleted or moved away outside the app.
+                        Err(_) if is_missing(&image_info.path) => {
+                            missing_image = Some(image_info.path.clone());
+                        }
+                        Err(e) => {
+                            broken_image_action = show_broken_image(
+                                ui,
+                                &image_info.path,
+                                &e,
+                                image_area.size(),
+                                &mut self.broken_image_size,
+                            );
+                        }
+                    }
+                } else if self.image_manager.folders().is_empty() {
+                    ui.label(tr!("no-folder-selected"));
+                } else if !self.folder_scans.is_empty() {
+                    ui.label(tr!("scanning"));
+                } else if self.session.moved + self.session.deleted > 0 {
+                    self.show_completion(ui);
+                } else {
+                    ui.label(tr!("no-images-found"));
+                }
+
+                if let Some(forward) = turn_page {
+                    self.turn_page(forward);
+                }
+                if toggle_stack {
+                    self.image_manager.toggle_stack();
+                }
+                if let Some(path) = full_resolution_image {
+                    let image = match &self.current_page {
+                        Some((_, page, _)) if *page > 0 => {
+                            self.image_manager.loader.page(&path, *page, true)
+                        }
+                        _ => self.image_manager.loader.full_resolution(&path),
+                    };
+                    egui::ScrollArea::both()
+                        .max_height(image_area.height())
+                        .auto_shrink([false, false])
+                        .show(ui, |ui| {
+                            let background = ui.painter().add(egui::Shape::Noop);
+                            let response =
+                                ui.add(image.fit_to_original_size(1.0).sense(egui::Sense::click()));
+                            ui.painter().set(
+                                background,
+                                self.settings
+                                    .image_background
+                                    .shapes(response.rect, ui.clip_rect()),
+                            );
+                            response.context_menu(|ui| {
+                                image_menu_action =
+                                    show_image_menu(ui, &self.folder_letter_entries);
+                            });
+                            if self.show_clipping {
+                                self.histogram.paint_clipping(ui, response.rect, &path);
+                            }
+                            if loupe_held {
+                                self.loupe.show(ui, response.rect, &path, page);
+                            }
+                        });
+                }
+
+                // The splitter stays put while images of other shapes are shown.
+                if let Some(bottom) = image_bottom {
+                    ui.add_space((bottom - ui.cursor().top()).max(0.0));
+                }
+                self.layout.splitter(ui, available_height);
+
+                if let Some(action) = broken_image_action {
+                    self.handle_broken_image_action(action);
+                }
+                if let Some(path) = missing_image {
+                    self.drop_missing_images(&path);
+                }
+                if let Some(action) = image_menu_action {
+                    self.handle_image_menu_action(ctx, action);
+                }
+
+                self.show_tag_editor(ui);
+                self.show_script_suggestion(ui);
+
+                self.show_filmstrip(ui);
+                self.show_recent_moves(ui);
+
+                ui.separator();
+
+                // Control area.
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(tr!("destinations-heading"));
+                        ui.checkbox(&mut self.settings.show_album_tree, tr!("album-tree"))
+                            .on_hover_text(tr!("album-tree-hint"));
+                    });
+
+                    let available_height = ui.available_size().y;
+                    let control_height = available_height * 0.3;
+                    egui::ScrollArea::vertical()
+                        .min_scrolled_height(control_height)
+                        .show(ui, |ui| {
+                            if self.settings.show_album_tree {
+                                self.show_album_tree(ui);
+                                return;
+                            }
+                            ui.horizontal(|ui| {
+                                let folder_label = ui.label(tr!("folder"));
+                                if ui.button(tr!("choose-destination-folder")).clicked() {
+                                    // Button to open file dialog
+                                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                        self.new_folder = path.to_string_lossy().to_string();
+                                        self.picked_folder = Some(path);
+                                    }
+                                }
+                                // Display the chosen path
+                                ui.text_edit_singleline(&mut self.new_folder)
+                                    .labelled_by(folder_label.id)
+                                    .on_hover_text(tr!("date-template-hint"));
+
+                                let letter_label = ui.label(tr!("letter"));
+                                ui.text_edit_singleline(&mut self.new_letter)
+                                    .labelled_by(letter_label.id);
+                            });
+
+                            let add = ui.button("+");
+                            accessibility::set_name(&add, tr!("add-destination"));
+                            if add.clicked()
+                                && !self.new_folder.is_empty()
+                                && !self.new_letter.is_empty()
+                            {
+                                let letter = self
+                                    .new_letter
+                                    .chars()
+                                    .next()
+                                    .filter(|&letter| self.check_key_letter(letter));
+                                if let Some(letter) = letter {
+                                    let folder = match self.picked_folder.take() {
+                                        // Use the picked path unless the user edited it.
+                                        Some(picked)
+                                            if picked.to_string_lossy() == self.new_folder =>
+                                        {
+                                            picked
+                                        }
+                                        _ => PathBuf::from(&self.new_folder),
+                                    };
+                                    self.folder_letter_entries.push(FolderLetterEntry {
+                                        folder,
+                                        letter,
+                                        confirm: false,
+                                        link: false,
+                                        copy: false,
+                                        color: None,
+                                        icon: String::new(),
+                                        double_press: false,
+                                    });
+                                    self.new_folder.clear();
+                                    self.new_letter.clear();
+                                }
+                            }
+
+                            let mut remove_index = vec![];
+                            // Display Folder & Letter Entries:
+                            for (index, entry) in self.folder_letter_entries.iter_mut().enumerate()
+                            {
+                                ui.horizontal(|ui| {
+                                    if let Some(color) = entry.color {
+                                        palette::swatch(ui, color);
+                                    }
+                                    ui.label(tr!(
+                                        "destination-entry",
+                                        folder = remote::display_destination(&entry.folder),
+                                        letter = entry.letter.to_string()
+                                    ));
+                                    ui.checkbox(&mut entry.confirm, tr!("ask-first"))
+                                        .on_hover_text(tr!("ask-first-hint"));
+                                    ui.checkbox(&mut entry.link, tr!("hard-link"))
+                                        .on_hover_text(tr!("hard-link-hint"));
+                                    ui.checkbox(&mut entry.copy, tr!("copy-destination"))
+                                        .on_hover_text(tr!("copy-destination-hint"));
+                                    ui.checkbox(&mut entry.double_press, tr!("double-press"))
+                                        .on_hover_text(tr!("double-press-hint"));
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut entry.icon)
+                                            .desired_width(28.0)
+                                            .hint_text(tr!("destination-icon")),
+                                    )
+                                    .on_hover_text(tr!("destination-icon-hint"));
+                                    let mut colored = entry.color.is_some();
+                                    if ui
+                                        .checkbox(&mut colored, tr!("destination-color"))
+                                        .on_hover_text(tr!("destination-color-hint"))
+                                        .changed()
+                                    {
+                                        entry.color = colored.then_some(DEFAULT_DESTINATION_COLOR);
+                                    }
+                                    if let Some(color) = &mut entry.color {
+                                        ui.color_edit_button_srgb(color);
+                                    }
+                                    let remove = ui.button("X");
+                                    accessibility::set_name(
+                                        &remove,
+                                        tr!(
+                                            "remove-destination",
+                                            folder = remote::display_destination(&entry.folder)
+                                        ),
+                                    );
+                                    if remove.clicked() {
+                                        remove_index.push(index);
+                                    }
+                                });
+                            }
+
+                            self.remove_folder_letter_entries(remove_index);
+                        });
+                });
+            })
+        });
+
+        egui::Window::new(tr!("status-history"))
+            .id(egui::Id::new("status_history"))
+            .open(&mut self.show_status_history)
+            .default_size(egui::vec2(500.0, 300.0))
+            .show(ctx, |ui| {
+                self.status.show_history(ui);
+            });
+        self.show_settings_window(ctx);
+        self.apply_settings(ctx);
+        self.show_grid(ctx);
+        self.show_timeline_window(ctx);
+        self.show_info_window(ctx);
+        self.show_histogram_window(ctx);
+        self.show_library(ctx);
+        self.show_merge_window(ctx);
+        self.show_flatten_window(ctx);
+        self.show_organize_window(ctx);
+        self.show_locations_window(ctx);
+        self.show_blur_window(ctx);
+        self.show_screenshots_window(ctx);
+        self.show_quarantine_window(ctx);
+        self.show_discard_window(ctx);
+        self.show_faces_window(ctx);
+        self.show_storage_window(ctx);
+        self.show_contact_sheet_window(ctx);
+        self.show_integrity_scan(ctx);
+        self.show_batch_window(ctx);
+        self.show_session_revert_window(ctx);
+        self.show_replay_window(ctx);
+        self.show_audit_window(ctx);
+        self.show_log_window(ctx);
+        self.show_sync_window(ctx);
+        self.show_failed_move_dialog(ctx);
+        self.show_confirmation_dialog(ctx);
+        self.show_exit_dialog(ctx);
+        self.show_interrupted_window(ctx);
+        self.show_crash_window(ctx);
+        self.show_wizard(ctx);
+        self.show_practice_window(ctx);
+        self.show_shortcuts_overlay(ctx);
+        self.show_detached_image(ctx);
+        self.show_rename_window(ctx);
+        self.show_new_album_window(ctx);
+        self.show_command_palette(ctx);
+        self.show_move_flash(ctx);
+        self.show_armed_letter(ctx);
+        self.report_progress();
+        self.status.show_toasts(ctx);
+    }
+}
+
+// Command line arguments.
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    folder: Option<PathBuf>,
+    // --read-only
+    read_only: bool,
+    // --portable: keep the settings and caches next to the executable.
+    portable: bool,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = std::ffi::OsString>) -> Self {
+        let mut parsed = Self::default();
+        for arg in args {
+            if arg == "--read-only" {
+                parsed.read_only = true;
+            } else if arg == "--portable" {
+                parsed.portable = true;
+            } else {
+                parsed.folder = Some(PathBuf::from(arg));
+            }
+        }
+        parsed
+    }
+}
+
+// The title of the window. Earlier versions also saved the settings under it.
+const APP_NAME: &str = "Image organizer";
+
+fn main() -> Result<(), eframe::Error> {
+    logging::init();
+    let args = Args::parse(std::env::args_os().skip(1));
+    if args.portable {
+        match app_dirs::set_portable() {
+            Ok(root) => log::info!("Portable mode: keeping everything in {}", root.display()),
+            Err(e) => log::error!("Failed to find the folder of the executable: {}", e),
+        }
+    }
+    recovery::install_panic_hook(app_dirs::data_dir().join("recovery.json"));
+    // Two apps sorting the same folder would each move files the other still lists.
+    let server = match instance::start(
+        &app_dirs::data_dir().join("instance.port"),
+        args.folder.as_deref(),
+    ) {
+        Ok(Some(server)) => Some(server),
+        Ok(None) => {
+            log::info!("Handed over to the app that is already running.");
+            return Ok(());
+        }
+        Err(e) => {
+            log::warn!("Failed to check for a running app: {}", e);
+            None
+        }
+    };
+    let settings_file = app_dirs::settings_file();
+    if let Some(old_dir) = eframe::storage_dir(APP_NAME) {
+        match app_dirs::migrate_settings(&old_dir.join("app.ron"), &settings_file) {
+            Ok(true) => log::info!("Copied the settings from {}", old_dir.display()),
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to copy the settings: {}", e),
+        }
+    }
+    if let Some(folder) = settings_file.parent() {
+        if let Err(e) = fs::create_dir_all(folder) {
+            log::error!("Failed to create {}: {}", folder.display(), e);
+        }
+    }
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size(egui::Vec2::new(1280.0, 960.0)),
+        // The size and position the window was closed with replace the size above.
+        persist_window: true,
+        persistence_path: Some(settings_file),
+        ..Default::default()
+    };
+
+    let noto_sans_font = FontAsset::get("NotoSansJP-VariableFont_wght.ttf").unwrap();
+
+    let mut fonts = FontDefinitions::default();
+    fonts.font_data.insert(
+        "my_font".to_owned(),
+        FontData::from_owned(noto_sans_font.data.to_vec()).into(),
+    );
+    fonts
+        .families
+        .get_mut(&FontFamily::Proportional)
+        .unwrap()
+        .insert(0, "my_font".to_owned());
+    eframe::run_native(
+        APP_NAME,
+        native_options,
+        Box::new(|cc| {
+            cc.egui_ctx.set_fonts(fonts);
+            egui_extras::install_image_loaders(&cc.egui_ctx);
+            Ok(Box::new(MyApp::new(cc, args, server)))
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_current_image_to_dest_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("test.jpg");
+        let dest_dir = temp_dir.path().join("test_dest");
+        fs::create_dir(&dest_dir).unwrap();
+        std::fs::write(&src_path, b"Hello, world!").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+        app.move_current_image_to_dest(&dest_dir).unwrap();
+        assert!(!src_path.exists());
+        assert!(dest_dir.join("test.jpg").exists());
+    }
+
+    // Given there are mulitple files in the src folder, move the current image to the dest folder.
+    #[test]
+    fn multiple_one_file_move_current_image_to_dest_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path1 = temp_dir.path().join("test1.jpg");
+        let src_path2 = temp_dir.path().join("test2.jpg");
+        let dest_dir = temp_dir.path().join("test_dest");
+        fs::create_dir(&dest_dir).unwrap();
+        std::fs::write(&src_path1, b"Hello, world!").unwrap();
+        std::fs::write(&src_path2, b"Hello, world!").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+
+        app.move_current_image_to_dest(&dest_dir).unwrap();
+
+        // Which file gets moved as a result of move_current_image_to_dest() changes depending on
+        // implementation.
+        assert!(src_path1.exists());
+        assert!(!src_path2.exists());
+        assert!(!dest_dir.join("test1.jpg").exists());
+        assert!(dest_dir.join("test2.jpg").exists());
+    }
+
+    #[test]
+    fn move_all_files_move_current_image_to_dest_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path1 = temp_dir.path().join("test1.jpg");
+        let src_path2 = temp_dir.path().join("test2.jpg");
+        let dest_dir = temp_dir.path().join("test_dest");
+        fs::create_dir(&dest_dir).unwrap();
+        std::fs::write(&src_path1, b"Hello, world!").unwrap();
+        std::fs::write(&src_path2, b"Hello, world!").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+        app.move_current_image_to_dest(&dest_dir).unwrap();
+        app.move_current_image_to_dest(&dest_dir).unwrap();
+        assert!(!src_path1.exists());
+        assert!(!src_path2.exists());
+        assert!(dest_dir.join("test1.jpg").exists());
+        assert!(dest_dir.join("test2.jpg").exists());
+    }
+
+    // A failed move must not drop the image from the list.
+    #[test]
+    fn failed_move_keeps_image_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("test.jpg");
+        let dest_dir = temp_dir.path().join("does_not_exist");
+        std::fs::write(&src_path, b"Hello, world!").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+
+        app.move_current_image_and_report(&dest_dir);
+
+        assert!(src_path.exists());
+        assert_eq!(app.image_manager.num_images(), 1);
+        let failed_move = app.failed_move.clone().unwrap();
+        assert_eq!(failed_move.src, src_path);
+
+        // Retrying after the destination shows up succeeds.
+        fs::create_dir(&dest_dir).unwrap();
+        app.retry_failed_move();
+        assert!(app.failed_move.is_none());
+        assert!(dest_dir.join("test.jpg").exists());
+        assert_eq!(app.image_manager.num_images(), 0);
+    }
+
+    #[test]
+    fn remove_folder_letter_entries_test() {
+        let mut app = MyApp {
+            folder_letter_entries: vec![
+                FolderLetterEntry {
+                    folder: PathBuf::from("folder1"),
+                    letter: 'A',
+                    confirm: false,
+                    link: false,
+                    copy: false,
+                    color: None,
+                    icon: String::new(),
+                    double_press: false,
+                },
+                FolderLetterEntry {
+                    folder: PathBuf::from("folder2"),
+                    letter: 'B',
+                    confirm: true,
+                    link: false,
+                    copy: false,
+                    color: None,
+                    icon: String::new(),
+                    double_press: false,
+                },
+            ],
+            ..Default::default()
+        };
+        let indecies = vec![0, 1];
+        app.remove_folder_letter_entries(indecies);
+        assert!(app.folder_letter_entries.is_empty());
+    }
+
+    #[test]
+    fn assign_letter_test() {
+        let mut app = MyApp::default();
+        let letters = |app: &MyApp| {
+            app.folder_letter_entries
+                .iter()
+                .map(|entry| (entry.folder.clone(), entry.letter))
+                .collect::<Vec<_>>()
+        };
+        app.assign_letter(PathBuf::from("albums/trips"), 'T');
+        app.assign_letter(PathBuf::from("albums/family"), 'F');
+        // A folder has one letter, and a letter goes to one folder.
+        app.assign_letter(PathBuf::from("albums/trips"), 'R');
+        app.assign_letter(PathBuf::from("albums/friends"), 'F');
+        assert_eq!(
+            letters(&app),
+            vec![
+                (PathBuf::from("albums/trips"), 'R'),
+                (PathBuf::from("albums/friends"), 'F'),
+            ]
+        );
+        app.assign_letter(PathBuf::from("albums/other"), 'あ');
+        assert_eq!(app.folder_letter_entries.len(), 2);
+    }
+
+    #[test]
+    fn delete_current_image_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("test.jpg");
+        std::fs::write(&src_path, b"Not really a jpeg").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+
+        let deleted = app.delete_current_image().unwrap();
+        assert_eq!(deleted, src_path);
+        assert!(!src_path.exists());
+        assert_eq!(app.image_manager.num_images(), 0);
+        assert!(app.delete_current_image().is_err());
+    }
+
+    #[test]
+    fn add_scanned_images_keeps_current_image_test() {
+        let mut manager = ImageManager::default();
+        manager.add_scanned_images(vec![PathBuf::from("b.png")]);
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+
+        manager.add_scanned_images(vec![PathBuf::from("c.png"), PathBuf::from("a.png")]);
+        assert_eq!(manager.num_images(), 3);
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+        assert_eq!(manager.current_index(), 1);
+    }
+
+    #[test]
+    fn set_folders_test() {
+        let mut manager = ImageManager::default();
+        manager.add_scanned_images(
+            ["/a/2.png", "/a/1.png", "/b/1.png"]
+                .map(PathBuf::from)
+                .to_vec(),
+        );
+        manager.go_to_image(Path::new("/a/1.png"));
+
+        // Queued folders come in order.
+        manager.set_folders(vec![PathBuf::from("/b"), PathBuf::from("/a")]);
+        assert_eq!(
+            manager.image_paths(),
+            &["/b/1.png", "/a/2.png", "/a/1.png"].map(PathBuf::from)
+        );
+        assert_eq!(manager.current_image_path(), Some(Path::new("/a/1.png")));
+
+        manager.add_scanned_images(vec![PathBuf::from("/b/2.png")]);
+        assert_eq!(manager.image_paths()[0], PathBuf::from("/b/2.png"));
+        assert_eq!(manager.current_image_path(), Some(Path::new("/a/1.png")));
+    }
+
+    #[test]
+    fn remove_images_test() {
+        let mut manager = ImageManager::default();
+        manager.add_scanned_images(
+            ["d.png", "c.png", "b.png", "a.png"]
+                .map(PathBuf::from)
+                .to_vec(),
+        );
+        manager.go_to_index(2);
+        manager.remove_images(&HashSet::from([PathBuf::from("d.png")]));
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+
+        manager.remove_images(&HashSet::from([
+            PathBuf::from("b.png"),
+            PathBuf::from("a.png"),
+        ]));
+        assert_eq!(manager.num_images(), 1);
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+    }
+
+    #[test]
+    fn filter_test() {
+        let mut manager = ImageManager::default();
+        manager.add_scanned_images(
+            ["d.png", "c.png", "b.png", "a.png"]
+                .map(PathBuf::from)
+                .to_vec(),
+        );
+        manager.set_filter(
+            "test".to_string(),
+            HashSet::from([PathBuf::from("c.png"), PathBuf::from("a.png")]),
+        );
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("a.png")));
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+        manager.previous_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("a.png")));
+
+        // Moving the current image lands on the next one in the filter.
+        manager.previous_image();
+        manager.remove_current_image();
+        assert!(manager.ensure_visible());
+        assert_eq!(manager.current_image_path(), Some(Path::new("a.png")));
+        assert_eq!(manager.filter(), Some(("test", 1)));
+        manager.remove_current_image();
+        assert!(!manager.ensure_visible());
+
+        manager.clear_filter();
+        assert!(manager.ensure_visible());
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("d.png")));
+    }
+
+    #[test]
+    fn defer_test() {
+        let mut manager = ImageManager::default();
+        manager.add_scanned_images(
+            ["d.png", "c.png", "b.png", "a.png"]
+                .map(PathBuf::from)
+                .to_vec(),
+        );
+        assert_eq!(manager.defer_current(), Some(PathBuf::from("d.png")));
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+        manager.next_image();
+        manager.defer_current();
+        assert_eq!(manager.current_image_path(), Some(Path::new("a.png")));
+        manager.previous_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+        assert_eq!(manager.num_deferred(), 2);
+
+        // After the last image come the skipped ones.
+        manager.next_image();
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("d.png")));
+        assert_eq!(manager.filter(), Some(("Skipped for later", 2)));
+        assert_eq!(manager.num_deferred(), 0);
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+
+        // Skipping again loops over what is still left.
+        manager.defer_current();
+        assert_eq!(manager.current_image_path(), Some(Path::new("d.png")));
+        manager.remove_current_image();
+        assert!(manager.ensure_visible());
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+        assert_eq!(manager.filter(), Some(("Skipped for later", 1)));
+    }
+
+    #[test]
+    fn kept_test() {
+        let mut manager = ImageManager::default();
+        manager.add_scanned_images(["c.png", "b.png", "a.png"].map(PathBuf::from).to_vec());
+        manager.set_hide_kept(true);
+        manager.add_kept(HashSet::from([PathBuf::from("b.png")]));
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("a.png")));
+        assert_eq!(manager.num_hidden_kept(), 1);
+
+        assert_eq!(manager.toggle_kept(), Some((PathBuf::from("a.png"), true)));
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+        manager.set_hide_kept(false);
+        assert_eq!(manager.num_hidden_kept(), 0);
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+        assert_eq!(manager.toggle_kept(), Some((PathBuf::from("b.png"), false)));
+        assert!(!manager.is_kept(Path::new("b.png")));
+    }
+
+    #[test]
+    fn burst_stack_test() {
+        let mut app = MyApp::default();
+        let manager = &mut app.image_manager;
+        let paths = [
+            "IMG_0001.JPG",
+            "IMG_0002.JPG",
+            "IMG_0003.JPG",
+            "IMG_0010.JPG",
+        ]
+        .map(|name| Path::new("/photos").join(name));
+        manager.add_scanned_images(paths.to_vec());
+        manager.add_dates(
+            paths
+                .iter()
+                .map(|path| (path.clone(), "2024-01-31 12:00:00".to_string()))
+                .collect(),
+        );
+        // Sorted newest name first: 10, 3, 2, 1.
+        assert_eq!(manager.current_image_path(), Some(paths[3].as_path()));
+        assert_eq!(manager.burst_position(), None);
+        manager.next_image();
+        assert_eq!(manager.burst_position(), Some((1, 3, false)));
+        assert_eq!(
+            manager.current_stack(),
+            Some(vec![paths[2].clone(), paths[1].clone(), paths[0].clone()])
+        );
+        // The rest of the burst is stepped over both ways.
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(paths[3].as_path()));
+        manager.previous_image();
+        assert_eq!(manager.current_image_path(), Some(paths[2].as_path()));
+
+        manager.toggle_stack();
+        assert_eq!(manager.current_stack(), None);
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(paths[1].as_path()));
+        assert_eq!(manager.burst_position(), Some((2, 3, true)));
+        // Stacking it again goes back to its first shot.
+        manager.toggle_stack();
+        assert_eq!(manager.current_image_path(), Some(paths[2].as_path()));
+
+        // The whole stack is moved.
+        app.read_only = true;
+        app.request_move(Path::new("/sorted"));
+        assert_eq!(app.plan.len(), 3);
+        assert_eq!(
+            app.image_manager.current_image_path(),
+            Some(paths[3].as_path())
+        );
+
+        app.image_manager.set_stack_bursts(false);
+        app.image_manager.next_image();
+        assert_eq!(app.image_manager.burst_position(), None);
+    }
+
+    #[test]
+    fn wrap_around_test() {
+        // Sorted newest name first: c, b, a.
+        let mut manager = ImageManager::default();
+        manager.add_scanned_images(["c.png", "b.png", "a.png"].map(PathBuf::from).to_vec());
+        manager.go_to_image(Path::new("a.png"));
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+        assert!(!manager.at_end());
+
+        manager.set_wrap_around(false);
+        manager.go_to_image(Path::new("a.png"));
+        manager.next_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("a.png")));
+        assert!(manager.at_end());
+        manager.previous_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+        assert!(!manager.at_end());
+        manager.go_to_index(0);
+        manager.previous_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+
+        // Moving the last image away leaves one that was already gone past.
+        manager.go_to_image(Path::new("a.png"));
+        manager.remove_current_image();
+        assert_eq!(manager.current_image_path(), Some(Path::new("b.png")));
+        assert!(manager.at_end());
+        manager.start_over();
+        assert_eq!(manager.current_image_path(), Some(Path::new("c.png")));
+        assert!(!manager.at_end());
+    }
+
+    #[test]
+    fn after_move_test() {
+        let images = ["d.png", "c.png", "b.png", "a.png"].map(PathBuf::from);
+        let remove = |path: &str, after: AfterMove| {
+            let mut manager = ImageManager::default();
+            manager.add_scanned_images(images.to_vec());
+            manager.go_to_image(Path::new(path));
+            let index = manager.current_index();
+            manager.remove_current_image();
+            manager.go_on_after_removal(index, after);
+            manager.current_image_path().map(|path| path.to_path_buf())
+        };
+        assert_eq!(remove("c.png", AfterMove::Advance), Some("b.png".into()));
+        assert_eq!(remove("c.png", AfterMove::Stay), Some("b.png".into()));
+        assert_eq!(remove("c.png", AfterMove::Back), Some("d.png".into()));
+        // After the last image.
+        assert_eq!(remove("a.png", AfterMove::Advance), Some("d.png".into()));
+        assert_eq!(remove("a.png", AfterMove::Stay), Some("b.png".into()));
+        assert_eq!(remove("a.png", AfterMove::Back), Some("b.png".into()));
+        // Nothing comes before the first image.
+        assert_eq!(remove("d.png", AfterMove::Back), Some("c.png".into()));
+    }
+
+    #[test]
+    fn select_within_budget_test() {
+        let mut loader = Loader::default();
+        let paths: Vec<PathBuf> = ["a.png", "b.png", "c.png", "d.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        for path in &paths {
+            loader.sizes.insert(path.clone(), 100);
+        }
+        loader.mark_viewed(&paths[3]);
+        loader.mark_viewed(&paths[2]);
+
+        // The current image is kept even if it alone is over budget.
+        let keep = loader.select_within_budget(paths.clone(), Some(&paths[0]), 50);
+        assert_eq!(keep, HashSet::from([paths[0].clone()]));
+
+        // Then the most recently viewed.
+        let keep = loader.select_within_budget(paths.clone(), Some(&paths[0]), 200);
+        assert_eq!(keep, HashSet::from([paths[0].clone(), paths[2].clone()]));
+
+        // Everything fits.
+        let keep = loader.select_within_budget(paths.clone(), Some(&paths[0]), 400);
+        assert_eq!(keep.len(), 4);
+    }
+
+    #[test]
+    fn undo_move_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("test.jpg");
+        let dest_dir = temp_dir.path().join("test_dest");
+        fs::create_dir(&dest_dir).unwrap();
+
+        std::fs::write(&src_path, b"Hello, world!").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+
+        app.move_current_image_to_dest(&dest_dir).unwrap();
+
+        // Make sure its not in image paths anymore and has been moved.
+        assert!(!src_path.exists());
+        assert!(dest_dir.join("test.jpg").exists());
+        assert_eq!(app.session.moved, 1);
+
+        // Now undo and check that everything is rolled back.
+        let Some(undo_path) = app.undo_move() else {
+            panic!("undo_move() returned None");
+        };
+        assert_eq!(undo_path, src_path);
+        assert!(src_path.exists());
+        assert!(!dest_dir.join("test.jpg").exists());
+        assert_eq!(app.session.moved, 0);
+
+        // Further undo should return None.
+        assert!(app.undo_move().is_none());
+        assert!(app.undo_move().is_none());
+    }
+
+    #[test]
+    fn undo_taken_name_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("test.jpg");
+        let dest_dir = temp_dir.path().join("test_dest");
+        fs::create_dir(&dest_dir).unwrap();
+        fs::write(&src_path, b"moved").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+        app.move_current_image_to_dest(&dest_dir).unwrap();
+        let entry = app.move_log.last().unwrap().clone();
+
+        // Another file took the name since. It stays as it is.
+        fs::write(&src_path, b"new").unwrap();
+        let undo_path = app.undo_move().unwrap();
+        assert_eq!(undo_path, temp_dir.path().join("test (1).jpg"));
+        assert_eq!(fs::read(&undo_path).unwrap(), b"moved");
+        assert_eq!(fs::read(&src_path).unwrap(), b"new");
+
+        // Or the move is left where it is.
+        fs::rename(&undo_path, dest_dir.join("test.jpg")).unwrap();
+        app.move_log.push(entry);
+        app.settings.collisions = CollisionPolicy::Skip;
+        assert!(app.undo_move().is_none());
+        assert!(dest_dir.join("test.jpg").exists());
+        assert_eq!(fs::read(&src_path).unwrap(), b"new");
+    }
+
+    #[test]
+    fn recent_moves_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let cats = dir.join("cats");
+        let dogs = dir.join("dogs");
+        fs::create_dir(&cats).unwrap();
+        fs::create_dir(&dogs).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        app.image_manager.set_image_folder(dir);
+        app.assign_letter(cats.clone(), 'C');
+        app.folder_letter_entries[0].icon = "🐈".to_string();
+        app.move_current_image_to_dest(&cats).unwrap();
+        app.move_current_image_to_dest(&dogs).unwrap();
+        app.move_current_image_to_dest(&cats).unwrap();
+
+        app.settings.recent_moves = 2;
+        let recent = app.recent_moves();
+        assert_eq!(
+            recent,
+            vec![
+                RecentMove {
+                    index: 2,
+                    dest: app.move_log[2].dest.clone(),
+                    key: "🐈C".to_string(),
+                },
+                RecentMove {
+                    index: 1,
+                    dest: app.move_log[1].dest.clone(),
+                    key: String::new(),
+                },
+            ]
+        );
+        assert_eq!(recent[1].dest.parent(), Some(dogs.as_path()));
+
+        // A move before the last one is undone on its own.
+        let (middle, last) = (app.move_log[1].clone(), app.move_log[2].clone());
+        assert_eq!(app.undo_move_at(1), Ok(middle.src.clone()));
+        assert!(middle.src.exists());
+        assert!(last.dest.exists());
+        assert_eq!(app.move_log.len(), 2);
+        assert_eq!(app.move_log[1].dest, last.dest);
+        assert!(app.undo_move_at(2).is_err());
+    }
+
+    #[test]
+    fn session_revert_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dest_dir = dir.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        app.image_manager.set_image_folder(dir);
+        for _ in 0..3 {
+            app.move_current_image_to_dest(&dest_dir).unwrap();
+        }
+        // Gone by the time the session is reverted.
+        fs::remove_file(dest_dir.join("b.png")).unwrap();
+        // A new file took the name. It is not replaced.
+        fs::write(dir.join("c.png"), "new").unwrap();
+
+        // The copy of a move that cannot be undone is kept.
+        let copy = dir.join("copy.png");
+        fs::write(&copy, "c.png").unwrap();
+        let moved_c = app
+            .move_log
+            .iter_mut()
+            .find(|entry| entry.src == dir.join("c.png"))
+            .unwrap();
+        moved_c.copies = vec![copy.clone()];
+        app.session_revert = Some(SessionRevert {
+            total: app.move_log.len(),
+            next: app.move_log.len(),
+            ..Default::default()
+        });
+        while !app.session_revert.as_ref().unwrap().finished {
+            app.step_session_revert();
+        }
+        let revert = app.session_revert.as_ref().unwrap();
+        assert_eq!(revert.restored, 1);
+        assert_eq!(revert.failed.len(), 2);
+        assert_eq!(revert.failed[0].0, dir.join("b.png"));
+        assert_eq!(revert.failed[1].0, dir.join("c.png"));
+        assert!(revert.failed[1].1.contains("is taken"));
+        assert!(dir.join("a.png").exists());
+        assert_eq!(fs::read(dir.join("c.png")).unwrap(), b"new");
+        assert_eq!(fs::read(dest_dir.join("c.png")).unwrap(), b"c.png");
+        assert_eq!(app.image_manager.num_images(), 1);
+        assert!(copy.exists());
+
+        // The move of c.png stays on the log, to be undone once the new file is out of the way.
+        assert_eq!(app.move_log.len(), 1);
+        fs::remove_file(dir.join("c.png")).unwrap();
+        assert_eq!(app.undo_last_move(), Ok(dir.join("c.png")));
+        assert!(!copy.exists());
+        assert!(app.move_log.is_empty());
+    }
+
+    #[test]
+    fn fan_out_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("a.png");
+        let album = temp_dir.path().join("album");
+        let nas = temp_dir.path().join("nas");
+        fs::create_dir(&album).unwrap();
+        fs::create_dir(&nas).unwrap();
+        fs::write(&src_path, b"image").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+        let entry = |folder: &Path, copy| FolderLetterEntry {
+            folder: folder.to_path_buf(),
+            letter: 'A',
+            confirm: false,
+            link: false,
+            copy,
+            color: None,
+            icon: String::new(),
+            double_press: false,
+        };
+        app.folder_letter_entries = vec![entry(&nas, true), entry(&album, false)];
+
+        app.send_to_letter('A');
+        assert!(!src_path.exists());
+        assert_eq!(fs::read(album.join("a.png")).unwrap(), b"image");
+        assert_eq!(fs::read(nas.join("a.png")).unwrap(), b"image");
+
+        // Undo takes back the copy too.
+        assert_eq!(app.undo_move(), Some(src_path.clone()));
+        assert!(src_path.exists());
+        assert!(!album.join("a.png").exists());
+        assert!(!nas.join("a.png").exists());
+
+        // Nothing is moved when a copy cannot be made.
+        fs::write(nas.join("a.png"), b"other").unwrap();
+        app.send_to_letter('A');
+        assert!(src_path.exists());
+        assert!(!album.join("a.png").exists());
+    }
+
+    #[test]
+    fn link_destination_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("a.png");
+        let album = temp_dir.path().join("album");
+        fs::create_dir(&album).unwrap();
+        fs::write(&src_path, b"image").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+        app.folder_letter_entries = vec![FolderLetterEntry {
+            folder: album.clone(),
+            letter: 'A',
+            confirm: false,
+            link: true,
+            copy: false,
+            color: None,
+            icon: String::new(),
+            double_press: false,
+        }];
+
+        app.request_move(&album);
+        assert!(src_path.exists());
+        assert_eq!(fs::read(album.join("a.png")).unwrap(), b"image");
+        // Still there to link into more albums.
+        assert_eq!(
+            app.image_manager.current_image_path(),
+            Some(src_path.as_path())
+        );
+        assert_eq!(app.session.moved, 0);
+
+        assert_eq!(app.undo_move(), Some(src_path.clone()));
+        assert!(src_path.exists());
+        assert!(!album.join("a.png").exists());
+    }
+
+    #[test]
+    fn remote_destination_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join("a.png");
+        fs::write(&src_path, b"image").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+        // Nothing listens there, so the upload is still being retried when the test ends.
+        let nas = PathBuf::from("dav://127.0.0.1:1/photos");
+
+        app.request_move(&nas);
+        assert_eq!(app.image_manager.num_images(), 0);
+        // Only removed once it has been uploaded.
+        assert!(src_path.exists());
+        assert_eq!(app.uploader.as_ref().map(|u| u.pending()), Some(1));
+        assert!(app.move_log.is_empty());
+
+        app.move_log.push(MoveLogEntry {
+            src: src_path.clone(),
+            dest: PathBuf::from("http://127.0.0.1:1/photos/a.png"),
+            ..Default::default()
+        });
+        assert_eq!(app.undo_move(), None);
+        assert!(app.move_log.is_empty());
+    }
+
+    #[test]
+    fn archive_source_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let comic = temp_dir.path().join("comic.cbz");
+        archive::tests::write_zip(&comic, &[("001.png", b"one"), ("002.png", b"two")], true);
+        let dest_dir = temp_dir.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        app.image_manager.set_folders(vec![comic.clone()]);
+        app.image_manager
+            .add_scanned_images(archive::image_paths(&comic).unwrap());
+        assert_eq!(
+            app.image_manager.image_paths(),
+            [comic.join("002.png"), comic.join("001.png")]
+        );
+        assert!(app.delete_current_image().is_err());
+        assert!(app.rename_current_image("b.png").is_err());
+        assert_eq!(app.image_manager.num_images(), 2);
+
+        let entry = app.move_current_image_to_dest(&dest_dir).unwrap();
+        assert_eq!(entry.dest, dest_dir.join("002.png"));
+        assert_eq!(fs::read(&entry.dest).unwrap(), b"two");
+        assert_eq!(app.image_manager.image_paths(), [comic.join("001.png")]);
+
+        // Undo removes the extracted file and shows the image of the archive again.
+        assert_eq!(app.undo_move(), Some(comic.join("002.png")));
+        assert!(!dest_dir.join("002.png").exists());
+        assert_eq!(app.image_manager.num_images(), 2);
+        assert_eq!(archive::image_paths(&comic).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn turn_page_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("b.tif"),
+            pages::tests::three_page_tiff(),
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("a.png"), b"").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+        app.update_current_page();
+        let tiff = temp_dir.path().join("b.tif");
+        assert_eq!(app.current_page, Some((tiff.clone(), 0, 3)));
+
+        app.turn_page(false);
+        assert_eq!(app.current_page, Some((tiff.clone(), 0, 3)));
+        for _ in 0..3 {
+            app.turn_page(true);
+        }
+        assert_eq!(app.current_page, Some((tiff, 2, 3)));
+
+        // Other images have no pages.
+        app.next_image();
+        app.update_current_page();
+        assert_eq!(app.current_page, None);
+    }
+
+    #[test]
+    fn largest_first_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dest_dir = dir.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        fs::write(dir.join("a.png"), vec![0; 10]).unwrap();
+        fs::write(dir.join("b.png"), vec![0; 1000]).unwrap();
+        fs::write(dir.join("c.png"), vec![0; 100]).unwrap();
+        app.image_manager.set_image_folder(dir);
+        app.image_manager.set_sizes(HashMap::from([
+            (dir.join("a.png"), 10),
+            (dir.join("b.png"), 1000),
+            (dir.join("c.png"), 100),
+        ]));
+        assert_eq!(
+            app.image_manager.image_paths(),
+            [dir.join("b.png"), dir.join("c.png"), dir.join("a.png")]
+        );
+        // The current image stays the same when the order changes.
+        assert!(app.image_manager.go_to_image(&dir.join("b.png")));
+
+        app.move_current_image_to_dest(&dest_dir).unwrap();
+        assert_eq!(app.freed_bytes, 1000);
+        app.delete_current_image().unwrap();
+        assert_eq!(app.freed_bytes, 1100);
+        app.undo_move().unwrap();
+        assert_eq!(app.freed_bytes, 100);
+    }
+
+    #[test]
+    fn move_raw_jpeg_pair_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let jpeg = temp_dir.path().join("IMG_1.JPG");
+        let raw_file = temp_dir.path().join("IMG_1.CR2");
+        let dest_dir = temp_dir.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        fs::write(&jpeg, b"").unwrap();
+        fs::write(&raw_file, b"").unwrap();
+        app.image_manager.add_scanned_images(vec![jpeg.clone()]);
+
+        app.move_current_image_to_dest(&dest_dir).unwrap();
+        assert!(dest_dir.join("IMG_1.JPG").exists());
+        assert!(dest_dir.join("IMG_1.CR2").exists());
+
+        app.undo_move().unwrap();
+        assert!(jpeg.exists());
+        assert!(raw_file.exists());
+        assert!(!dest_dir.join("IMG_1.CR2").exists());
+    }
+
+    #[test]
+    fn move_sidecars_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let image = temp_dir.path().join("a.png");
+        let dest_dir = temp_dir.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        for name in ["a.png", "a.xmp", "a.png.json"] {
+            fs::write(temp_dir.path().join(name), b"").unwrap();
+        }
+        app.image_manager.add_scanned_images(vec![image.clone()]);
+
+        let entry = app.move_current_image_to_dest(&dest_dir).unwrap();
+        assert_eq!(entry.companions.len(), 2);
+        assert!(dest_dir.join("a.xmp").exists());
+        assert!(dest_dir.join("a.png.json").exists());
+
+        app.undo_move().unwrap();
+        assert!(temp_dir.path().join("a.xmp").exists());
+        assert!(temp_dir.path().join("a.png.json").exists());
+    }
+
+    #[test]
+    fn rename_current_image_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        for name in ["a.png", "a.xmp", "b.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        app.image_manager.set_image_folder(dir);
+        app.image_manager.go_to_image(&dir.join("a.png"));
+
+        assert!(app.rename_current_image("b.png").is_err());
+        assert!(app.rename_current_image("sub/c.png").is_err());
+        let entry = app.rename_current_image("beach.png").unwrap();
+        assert_eq!(entry.dest, dir.join("beach.png"));
+        assert!(dir.join("beach.xmp").exists());
+        assert_eq!(
+            app.image_manager.current_image_path(),
+            Some(dir.join("beach.png").as_path())
+        );
+
+        app.undo_move().unwrap();
+        assert!(dir.join("a.png").exists());
+        assert!(dir.join("a.xmp").exists());
+        assert_eq!(app.image_manager.num_images(), 2);
+        assert_eq!(
+            app.image_manager.current_image_path(),
+            Some(dir.join("a.png").as_path())
+        );
+    }
+
+    #[test]
+    fn palette_folders_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("albums");
+        for folder in ["cats", "trips", "trips/kyoto"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+        }
+        app.assign_letter(root.join("cats"), 'C');
+        app.assign_letter(PathBuf::from("/archive"), 'A');
+        assert_eq!(app.palette_folders().len(), 2);
+
+        app.settings.albums_root = Some(root.clone());
+        let items = app.palette_folders();
+        let labels: Vec<(&str, &str)> = items
+            .iter()
+            .map(|(item, _)| (item.label.as_str(), item.detail.as_str()))
+            .collect();
+        let cats = root.join("cats").to_string_lossy().to_string();
+        assert_eq!(
+            labels,
+            vec![
+                (cats.as_str(), "C"),
+                ("/archive", "A"),
+                ("albums/trips", ""),
+                ("albums/trips/kyoto", ""),
+            ]
+        );
+        assert_eq!(items[3].1, root.join("trips/kyoto"));
+    }
+
+    #[test]
+    fn palette_commands_test() {
+        let mut app = MyApp::default();
+        let ctx = egui::Context::default();
+        let commands = app.palette_commands();
+        let find = |name: &str| {
+            commands
+                .iter()
+                .find(|(item, _)| item.label == name)
+                .map(|(_, target)| target.clone())
+        };
+        assert_eq!(
+            find(&tr!("grid")),
+            Some(PaletteTarget::Command(Command::Toggle(Toggle::Grid)))
+        );
+        // Only with the library index.
+        assert_eq!(find(&tr!("palette-find-duplicates")), None);
+
+        app.run_command(&ctx, Command::Toggle(Toggle::Grid));
+        assert!(app.show_grid);
+        app.run_command(&ctx, Command::Toggle(Toggle::Grid));
+        assert!(!app.show_grid);
+        app.run_command(&ctx, Command::Open(Tool::Sync));
+        assert!(app.show_sync);
+        app.run_command(&ctx, Command::NewAlbum);
+        assert_eq!(app.new_album, Some(String::new()));
+        // Nothing to show, so the filter stays off.
+        app.run_command(&ctx, Command::FilterLabel(ColorLabel::Red));
+        assert!(app.image_manager.filter().is_none());
+    }
+
+    #[test]
+    fn create_album_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        assert!(app.create_album("trips").is_err());
+
+        app.settings.albums_root = Some(root.to_path_buf());
+        app.assign_letter(root.join("family"), 'a');
+        assert!(app.create_album("../trips").is_err());
+        assert_eq!(
+            app.create_album(" trips ").unwrap(),
+            (root.join("trips"), 'B')
+        );
+        assert!(root.join("trips").is_dir());
+        // An existing album keeps its letter.
+        assert_eq!(
+            app.create_album("trips").unwrap(),
+            (root.join("trips"), 'B')
+        );
+        assert_eq!(app.create_album("pets").unwrap(), (root.join("pets"), 'C'));
+        assert_eq!(app.folder_letter_entries.len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn move_image_follow_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dest_dir = temp_dir.path().join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        let album = temp_dir.path().join("album");
+        fs::create_dir(&album).unwrap();
+        fs::write(album.join("a.png"), b"image").unwrap();
+        let link = temp_dir.path().join("a.png");
+        std::os::unix::fs::symlink(album.join("a.png"), &link).unwrap();
+
+        let dest = move_image(
+            &link,
+            &dest_dir,
+            SymlinkPolicy::Follow,
+            &RetryPolicy::once(),
+        )
+        .unwrap();
+        assert_eq!(dest, dest_dir.join("a.png"));
+        assert!(!file_ops::is_symlink(&dest));
+        assert!(!album.join("a.png").exists());
+        assert!(!file_ops::is_symlink(&link));
+    }
+
+    #[test]
+    fn organize_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let destination = dir.join("photos");
+        fs::create_dir(&destination).unwrap();
+        for name in ["a.png", "a.xmp", "b.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let paths = vec![dir.join("a.png"), dir.join("b.png")];
+        app.image_manager.add_scanned_images(paths.clone());
+        let moves = vec![
+            (
+                dir.join("a.png"),
+                vec![dir.join("a.xmp")],
+                destination.join("2024/01"),
+            ),
+            (dir.join("b.png"), Vec::new(), destination.join("2023/12")),
+        ];
+        let report = organize_batch(
+            &moves,
+            &destination,
+            &HashMap::new(),
+            SymlinkPolicy::default(),
+            &mut |_, _| true,
+        );
+        assert!(report.errors.is_empty());
+        assert!(destination.join("2024/01/a.png").exists());
+        assert!(destination.join("2024/01/a.xmp").exists());
+        assert!(destination.join("2023/12/b.png").exists());
+
+        app.handle_organize_report(report);
+        assert_eq!(app.move_log.len(), 2);
+        assert_eq!(app.image_manager.image_paths().len(), 0);
+        assert_eq!(app.organize_undo, Some((0, 2)));
+
+        app.undo_organize();
+        assert!(app.move_log.is_empty());
+        assert!(dir.join("a.png").exists());
+        assert!(dir.join("a.xmp").exists());
+        assert!(dir.join("b.png").exists());
+        // The folders it made are gone, the destination stays.
+        assert!(!destination.join("2024").exists());
+        assert!(!destination.join("2023").exists());
+        assert!(destination.exists());
+        assert_eq!(app.organize_undo, None);
+    }
+
+    #[test]
+    fn move_images_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dest_dir = dir.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        fs::write(dir.join("a.png"), b"").unwrap();
+        fs::write(dir.join("a.xmp"), b"").unwrap();
+        let paths = vec![dir.join("a.png"), dir.join("missing.png")];
+        app.image_manager.add_scanned_images(paths.clone());
+
+        app.move_images(&paths, &dest_dir);
+        let batch = app.batch.as_mut().unwrap();
+        let mut report = None;
+        for _ in 0..500 {
+            report = batch.poll();
+            if report.is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(batch.progress(), (2, 2));
+        assert_eq!(batch.failed(), 1);
+        let Some(BatchReport::Move(report)) = report else {
+            panic!("no move report");
+        };
+        app.handle_move_report(report);
+        assert!(dest_dir.join("a.png").exists());
+        assert!(dest_dir.join("a.xmp").exists());
+        assert_eq!(app.move_log.len(), 1);
+        assert_eq!(app.move_log[0].companions.len(), 1);
+        assert_eq!(app.image_manager.num_images(), 1);
+    }
+
+    #[test]
+    fn confirm_move_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let trash = dir.join("trash");
+        let keep = dir.join("keep");
+        fs::create_dir(&trash).unwrap();
+        fs::create_dir(&keep).unwrap();
+        fs::write(dir.join("a.png"), b"").unwrap();
+        app.image_manager
+            .add_scanned_images(vec![dir.join("a.png")]);
+        app.folder_letter_entries = vec![FolderLetterEntry {
+            folder: trash.clone(),
+            letter: 'D',
+            confirm: true,
+            link: false,
+            copy: false,
+            color: None,
+            icon: String::new(),
+            double_press: false,
+        }];
+
+        app.request_move(&trash);
+        assert!(matches!(
+            app.pending_confirmation,
+            Some(PendingConfirmation::Move { .. })
+        ));
+        assert!(dir.join("a.png").exists());
+
+        app.pending_confirmation = None;
+        app.settings.confirm_dangerous_destinations = false;
+        app.request_move(&trash);
+        assert!(app.pending_confirmation.is_none());
+        assert!(trash.join("a.png").exists());
+
+        let paths = vec![PathBuf::from("b.png"), PathBuf::from("c.png")];
+        app.settings.confirm_batch_size = 1;
+        app.request_move_images(paths, keep);
+        assert!(matches!(
+            app.pending_confirmation,
+            Some(PendingConfirmation::MoveImages { ref paths, .. }) if paths.len() == 2
+        ));
+    }
+
+    // Runs a frame of handle_keys() with `key` pressed.
+    fn press_key(app: &mut MyApp, ctx: &egui::Context, key: egui::Key) {
+        let input = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| app.handle_keys(ctx));
+    }
+
+    #[test]
+    fn lock_test() {
+        let mut app = MyApp::default();
+        let ctx = egui::Context::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let cats = dir.join("cats");
+        fs::create_dir(&cats).unwrap();
+        fs::write(dir.join("a.png"), b"").unwrap();
+        app.image_manager
+            .add_scanned_images(vec![dir.join("a.png")]);
+        app.assign_letter(cats.clone(), 'C');
+
+        press_key(&mut app, &ctx, egui::Key::Escape);
+        assert!(app.locked);
+        press_key(&mut app, &ctx, egui::Key::C);
+        assert!(dir.join("a.png").exists());
+
+        press_key(&mut app, &ctx, egui::Key::Escape);
+        assert!(!app.locked);
+        press_key(&mut app, &ctx, egui::Key::C);
+        assert!(cats.join("a.png").exists());
+
+        // Escape closes an open menu without locking.
+        let _ = ctx.run(Default::default(), |ctx| {
+            egui::Area::new(egui::Id::new("menu"))
+                .order(egui::Order::Foreground)
+                .show(ctx, |ui| ui.label("menu"));
+        });
+        press_key(&mut app, &ctx, egui::Key::Escape);
+        assert!(!app.locked);
+        press_key(&mut app, &ctx, egui::Key::Escape);
+        assert!(app.locked);
+    }
+
+    #[test]
+    fn double_press_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let trash = dir.join("trash");
+        fs::create_dir(&trash).unwrap();
+        for name in ["a.png", "b.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+            app.image_manager.add_scanned_images(vec![dir.join(name)]);
+        }
+        app.assign_letter(trash.clone(), 'D');
+        app.folder_letter_entries[0].double_press = true;
+        let start = Instant::now();
+
+        app.press_letter('D', start);
+        assert_eq!(app.armed_letter, Some(('D', start)));
+        assert!(app.move_log.is_empty());
+        app.press_letter('D', start + Duration::from_millis(500));
+        assert_eq!(app.move_log.len(), 1);
+        assert!(app.armed_letter.is_none());
+
+        // Too slow: the second press arms it again.
+        app.press_letter('D', start + Duration::from_secs(2));
+        app.press_letter('D', start + Duration::from_secs(4));
+        assert_eq!(app.move_log.len(), 1);
+        assert!(app.armed_letter.is_some());
+    }
+
+    #[test]
+    fn audit_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let cats = dir.join("cats");
+        fs::create_dir(&cats).unwrap();
+        for name in ["a.png", "b.png"] {
+            fs::write(dir.join(name), b"image").unwrap();
+            app.image_manager.add_scanned_images(vec![dir.join(name)]);
+        }
+
+        app.request_move(&cats);
+        app.delete_current_image().unwrap();
+        app.undo_move_at(0).unwrap();
+        let operations: Vec<audit::Operation> = app
+            .audit_log
+            .iter()
+            .map(|entry| entry.stamp.operation)
+            .collect();
+        assert_eq!(
+            operations,
+            vec![
+                audit::Operation::Move,
+                audit::Operation::Delete,
+                audit::Operation::Undo
+            ]
+        );
+        let moved = &app.audit_log[0];
+        assert_eq!(moved.dest, cats.join("a.png"));
+        assert_eq!(moved.stamp.size, Some(5));
+        assert!(moved.stamp.hash.is_some());
+        assert_eq!(app.audit_log[1].stamp.size, Some(5));
+        // Undone moves leave the move log but stay in the audit trail.
+        assert!(app.move_log.is_empty());
+        assert!(dir.join("a.png").exists());
+    }
+
+    #[test]
+    fn resume_crash_test() {
+        let mut app = MyApp::default();
+        let ctx = egui::Context::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        for name in ["a.png", "b.png", "c.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        app.crash = Some(recovery::Crash {
+            snapshot: recovery::Snapshot {
+                folders: vec![dir.to_path_buf()],
+                image: Some(dir.join("b.png")),
+                index: 1,
+                pending: Vec::new(),
+            },
+            message: String::new(),
+            time: 0,
+        });
+
+        app.resume_crashed_session();
+        assert!(app.crash.is_none());
+        let started = Instant::now();
+        while !app.folder_scans.is_empty() && started.elapsed() < Duration::from_secs(10) {
+            app.poll_folder_scans(&ctx);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(app.image_manager.num_images(), 3);
+        assert_eq!(
+            app.image_manager.current_image_path(),
+            Some(dir.join("b.png").as_path())
+        );
+        assert!(app.resume_image.is_none());
+    }
 
-                let dest_dir = &entry.folder;
-                log::debug!(
-                    "Pressed key: {}. Moving image to folder: {}",
-                    letter,
-                    &dest_dir
-                );
-                match self.move_current_image_to_dest(dest_dir) {
-                    Ok(move_log) => {
-                        let filename = get_file_name(&move_log.src);
-                        status_message = format!("Moved {} -> {}", filename, dest_dir);
-                        log::info!("{}", &status_message);
-                    }
-                    Err(e) => {
-                        status_message = format!("Failed to move file: {}", e);
-                        log::error!("{}", &status_message);
-                    }
-                };
-            }
+    #[test]
+    fn apply_wizard_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let demo = demo::create(temp_dir.path()).unwrap();
+        app.apply_wizard(WizardAction::Skip);
+        assert!(app.image_manager.folders().is_empty());
+
+        app.apply_wizard(WizardAction::Finish {
+            source: demo.inbox.clone(),
+            destinations: demo
+                .destinations
+                .iter()
+                .map(|(folder, letter, color)| (folder.clone(), *letter, Some(*color)))
+                .collect(),
         });
+        assert_eq!(app.image_manager.folders().to_vec(), vec![demo.inbox]);
+        let entries: Vec<(char, Option<[u8; 3]>)> = app
+            .folder_letter_entries
+            .iter()
+            .map(|entry| (entry.letter, entry.color))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ('R', Some([220, 60, 60])),
+                ('G', Some([60, 170, 90])),
+                ('B', Some([60, 110, 220]))
+            ]
+        );
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.vertical(|ui| {
-                ui.horizontal(|ui| {
-                    if ui.button("Choose Image Folder").clicked() {
-                        if let Some(path) = FileDialog::new().pick_folder() {
-                            self.selected_folder = Some(path.to_string_lossy().to_string());
-                            if let Some(folder) = &self.selected_folder {
-                                self.image_manager.set_image_folder(folder);
-                            }
-                        }
-                    }
-                    ui.label("Selected Folder:");
-                    match &self.selected_folder {
-                        Some(folder) => ui.label(folder),
-                        None => ui.label("No folder selected."),
-                    };
-                    ui.label(format!("({})", self.image_manager.num_images()));
-                });
+    #[test]
+    fn practice_test() {
+        let mut app = MyApp::default();
+        let ctx = egui::Context::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cats = temp_dir.path().join("cats");
+        fs::create_dir(&cats).unwrap();
+        app.assign_letter(cats.clone(), 'C');
 
-                ui.horizontal(|ui| {
-                    ui.label("Status:");
-                    if !status_message.is_empty() {
-                        // The status message has to be copied because any update will set the
-                        // cleared status message.
-                        self.status_message = status_message;
-                    }
-                    ui.label(&self.status_message);
-                });
+        app.start_practice();
+        let root = app.practice.as_ref().unwrap().practice.root().to_path_buf();
+        let letters: Vec<char> = app
+            .folder_letter_entries
+            .iter()
+            .map(|entry| entry.letter)
+            .collect();
+        assert_eq!(letters, vec!['R', 'G', 'B']);
+        let started = Instant::now();
+        while !app.folder_scans.is_empty() && started.elapsed() < Duration::from_secs(10) {
+            app.poll_folder_scans(&ctx);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(app.image_manager.num_images(), 9);
 
-                let available_height = ui.available_size().y;
-                // Limit upper part to 70%.
-                let image_height = available_height * 0.7;
-                let image_area = egui::Rect::from_min_size(
-                    egui::Pos2::ZERO,
-                    egui::Vec2::new(ui.available_width(), image_height),
-                );
+        // Real folders are out of reach.
+        app.request_move(&cats);
+        assert!(app.move_log.is_empty());
+        app.request_move(&root.join("red"));
+        assert_eq!(app.move_log.len(), 1);
+        app.undo_and_report();
+        app.image_manager
+            .set_filter("labeled".to_string(), HashSet::new());
+        app.update_practice();
+        assert!(app.practice.as_ref().unwrap().practice.is_finished());
 
-                // TODO: Tidy this up. It used to be in if let below but was
-                // extracted due to borrow checker.
-                let n_out_of_all = format!(
-                    "({}/{})",
-                    self.image_manager.current_index() + 1,
-                    self.image_manager.num_images(),
-                );
-                // Display the current image:
-                if let Some(image_info) = self.image_manager.load_current_image() {
-                    let filename = get_file_name(&image_info.path);
-                    ui.label(format!("Current Image: {} {}", n_out_of_all, filename));
-                    ui.add(image_info.image.fit_to_exact_size(image_area.size()));
-                } else if !self.image_manager.num_images() == 0 {
-                    ui.label("No images found in the folder.");
-                } else {
-                    ui.label("No folder selected.");
-                }
+        app.request_move(&root.join("red"));
+        app.leave_practice();
+        assert!(app.practice.is_none());
+        assert!(app.move_log.is_empty());
+        assert!(!root.exists());
+        assert_eq!(app.folder_letter_entries.len(), 1);
+        assert_eq!(app.folder_letter_entries[0].folder, cats);
+    }
 
-                ui.separator();
+    #[test]
+    fn pending_on_exit_test() {
+        let mut app = MyApp::default();
+        assert!(app.pending_on_exit().is_empty());
 
-                // Control area.
-                ui.vertical(|ui| {
-                    ui.label("Folder & Letter Entries:");
+        app.plan.push(Path::new("a.png"), Path::new("cats"));
+        assert_eq!(app.pending_on_exit(), vec![tr!("exit-plan", count = 1)]);
+        app.plan_exported = 1;
+        assert!(app.pending_on_exit().is_empty());
 
-                    let available_height = ui.available_size().y;
-                    let control_height = available_height * 0.3;
-                    egui::ScrollArea::vertical()
-                        .min_scrolled_height(control_height)
-                        .show(ui, |ui| {
-                            ui.horizontal(|ui| {
-                                ui.label("Folder:");
-                                if ui.button("Choose Destination Folder").clicked() {
-                                    // Button to open file dialog
-                                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                                        self.new_folder = path.to_string_lossy().to_string();
-                                    }
-                                }
-                                ui.text_edit_singleline(&mut self.new_folder); // Display the chosen path
+        let error = io::Error::from(io::ErrorKind::PermissionDenied);
+        app.retry_queue.push(
+            Path::new("b.png"),
+            Path::new("cats"),
+            Vec::new(),
+            Vec::new(),
+            &error,
+            Instant::now(),
+        );
+        assert_eq!(app.pending_on_exit(), vec![tr!("exit-retries", count = 1)]);
+    }
 
-                                ui.label("Letter:");
-                                ui.text_edit_singleline(&mut self.new_letter);
-                            });
+    #[test]
+    fn retry_queue_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dest_dir = dir.join("dest");
+        let copies = dir.join("copies");
+        fs::create_dir(&dest_dir).unwrap();
+        fs::create_dir(&copies).unwrap();
+        for name in ["a.png", "b.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+            fs::write(copies.join(name), b"").unwrap();
+            app.file_stamps
+                .insert(dir.join(name), FileStamp::of(&dir.join(name)).unwrap());
+        }
+        let error = io::Error::from(io::ErrorKind::ResourceBusy);
+        let retry = |app: &mut MyApp, name: &str| {
+            app.retry_queue.push(
+                &dir.join(name),
+                &dest_dir,
+                vec![copies.join(name)],
+                Vec::new(),
+                &error,
+                Instant::now(),
+            );
+            app.retry_queue.retry_now(0);
+            app.process_retry_queue();
+        };
 
-                            if ui.button("+").clicked()
-                                && !self.new_folder.is_empty()
-                                && !self.new_letter.is_empty()
-                            {
-                                if let Some(letter) = self.new_letter.chars().next() {
-                                    self.folder_letter_entries.push(FolderLetterEntry {
-                                        folder: self.new_folder.clone(),
-                                        letter,
-                                    });
-                                    self.new_folder.clear();
-                                    self.new_letter.clear();
-                                }
-                            }
+        // The copy made with the first attempt is kept with the move.
+        retry(&mut app, "a.png");
+        assert!(dest_dir.join("a.png").exists());
+        assert_eq!(
+            app.move_log.last().unwrap().copies,
+            vec![copies.join("a.png")]
+        );
+        assert!(copies.join("a.png").exists());
 
-                            let mut remove_index = vec![];
-                            // Display Folder & Letter Entries:
-                            for (index, entry) in self.folder_letter_entries.iter().enumerate() {
-                                ui.horizontal(|ui| {
-                                    ui.label(format!(
-                                        "Folder: {}, Letter: {}",
-                                        entry.folder, entry.letter
-                                    ));
-                                    if ui.button("X").clicked() {
-                                        remove_index.push(index);
-                                    }
-                                });
-                            }
+        // Changed while it waited, so it is not moved and neither is the copy kept.
+        fs::write(dir.join("b.png"), b"replaced").unwrap();
+        retry(&mut app, "b.png");
+        assert!(dir.join("b.png").exists());
+        assert!(!copies.join("b.png").exists());
+        assert_eq!(app.image_manager.image_paths(), [dir.join("b.png")]);
+        assert!(app.failed_move.is_some());
+        assert!(app.retry_queue.is_empty());
+    }
 
-                            self.remove_folder_letter_entries(remove_index);
-                        });
-                });
-            })
-        });
+    #[test]
+    fn move_changed_source_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dest_dir = dir.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+            app.image_manager.add_scanned_images(vec![dir.join(name)]);
+            app.file_stamps
+                .insert(dir.join(name), FileStamp::of(&dir.join(name)).unwrap());
+        }
+
+        // Replaced by another program after it was listed.
+        app.image_manager.go_to_image(&dir.join("a.png"));
+        fs::write(dir.join("a.png"), b"replaced").unwrap();
+        app.move_current_image_and_report(&dest_dir);
+        assert!(dir.join("a.png").exists());
+        assert_eq!(app.image_manager.num_images(), 3);
+        // Now that it was shown again, it is moved.
+        app.move_current_image_and_report(&dest_dir);
+        assert!(dest_dir.join("a.png").exists());
+
+        // Deleted by another program.
+        app.image_manager.go_to_image(&dir.join("b.png"));
+        fs::remove_file(dir.join("b.png")).unwrap();
+        app.move_current_image_and_report(&dest_dir);
+        assert_eq!(app.image_manager.image_paths(), [dir.join("c.png")]);
+
+        fs::write(dir.join("c.png"), b"replaced").unwrap();
+        let files = vec![(dir.join("c.png"), Vec::new())];
+        let stamps = app.stamps_of([&dir.join("c.png")]);
+        let report = move_batch(
+            &files,
+            &dest_dir,
+            &stamps,
+            SymlinkPolicy::default(),
+            false,
+            &mut |_, _| true,
+        );
+        assert!(report.moved.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        assert!(dir.join("c.png").exists());
     }
-}
 
-fn main() -> Result<(), eframe::Error> {
-    env_logger::init();
-    let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size(egui::Vec2::new(1280.0, 960.0)),
-        ..Default::default()
-    };
+    #[test]
+    fn rescan_folders_test() {
+        let mut app = MyApp::default();
+        let ctx = egui::Context::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        for name in ["a.png", "b.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let wait_for_scans = |app: &mut MyApp| {
+            let started = Instant::now();
+            while !app.folder_scans.is_empty() && started.elapsed() < Duration::from_secs(10) {
+                app.poll_folder_scans(&ctx);
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+        app.open_image_folder(dir.to_path_buf());
+        wait_for_scans(&mut app);
+        assert_eq!(app.image_manager.num_images(), 2);
 
-    let noto_sans_font = FontAsset::get("NotoSansJP-VariableFont_wght.ttf").unwrap();
+        fs::remove_file(dir.join("a.png")).unwrap();
+        fs::write(dir.join("c.png"), b"").unwrap();
+        app.rescan_folders();
+        wait_for_scans(&mut app);
+        let mut images = app.image_manager.image_paths().to_vec();
+        images.sort();
+        assert_eq!(images, vec![dir.join("b.png"), dir.join("c.png")]);
+        assert!(app.unconfirmed_images.is_empty());
+    }
 
-    let mut fonts = FontDefinitions::default();
-    fonts.font_data.insert(
-        "my_font".to_owned(),
-        FontData::from_owned(noto_sans_font.data.to_vec()).into(),
-    );
-    fonts
-        .families
-        .get_mut(&FontFamily::Proportional)
-        .unwrap()
-        .insert(0, "my_font".to_owned());
-    eframe::run_native(
-        "Image organizer",
-        native_options,
-        Box::new(|cc| {
-            cc.egui_ctx.set_fonts(fonts);
-            egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(MyApp::default()))
-        }),
-    )
-}
+    #[test]
+    fn drop_missing_images_test() {
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        for name in ["a.png", "b.png", "c.png", "d.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+            app.image_manager.add_scanned_images(vec![dir.join(name)]);
+        }
+        app.image_manager.go_to_image(&dir.join("b.png"));
+        fs::remove_file(dir.join("b.png")).unwrap();
+        fs::remove_file(dir.join("d.png")).unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        app.drop_missing_images(&dir.join("b.png"));
+        let mut images = app.image_manager.image_paths().to_vec();
+        images.sort();
+        assert_eq!(images, vec![dir.join("a.png"), dir.join("c.png")]);
+        assert!(app
+            .image_manager
+            .current_image_path()
+            .is_some_and(|path| path.exists()));
+    }
 
-    // Move a temporary file from one folder to another.
     #[test]
-    fn move_file_test() {
+    fn move_flash_test() {
+        let mut app = MyApp::default();
         let temp_dir = tempfile::tempdir().unwrap();
-        let src_path = temp_dir.path().join("test.jpg");
-        let dest_dir = temp_dir.path().join("test_dest");
-        fs::create_dir(&dest_dir).unwrap();
-        std::fs::write(&src_path, b"Hello, world!").unwrap();
-        assert!(src_path.exists());
-        move_file(&src_path.to_string_lossy(), &dest_dir.to_string_lossy()).unwrap();
-        assert!(!src_path.exists());
-        assert!(dest_dir.join("test.jpg").exists());
+        let dir = temp_dir.path();
+        let cats = dir.join("cats");
+        let dogs = dir.join("dogs");
+        fs::create_dir(&cats).unwrap();
+        fs::create_dir(&dogs).unwrap();
+        for name in ["a.png", "b.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+            app.image_manager.add_scanned_images(vec![dir.join(name)]);
+        }
+        app.assign_letter(cats.clone(), 'C');
+        app.assign_letter(dogs.clone(), 'D');
+        app.folder_letter_entries[0].color = Some([0, 128, 255]);
+
+        app.request_move(&dogs);
+        assert!(app.move_flash.is_none());
+        app.request_move(&cats);
+        assert!(matches!(app.move_flash, Some(([0, 128, 255], _))));
     }
 
     #[test]
-    fn move_current_image_to_dest_test() {
+    fn confirm_identical_test() {
         let mut app = MyApp::default();
         let temp_dir = tempfile::tempdir().unwrap();
-        let src_path = temp_dir.path().join("test.jpg");
-        let dest_dir = temp_dir.path().join("test_dest");
+        let dir = temp_dir.path();
+        let dest_dir = dir.join("dest");
         fs::create_dir(&dest_dir).unwrap();
-        std::fs::write(&src_path, b"Hello, world!").unwrap();
+        fs::write(dir.join("a.png"), b"image").unwrap();
+        fs::write(dir.join("b.png"), b"other").unwrap();
+        fs::write(dest_dir.join("a.png"), b"image").unwrap();
         app.image_manager
-            .set_image_folder(&temp_dir.path().to_string_lossy());
-        app.move_current_image_to_dest(&dest_dir.to_string_lossy())
-            .unwrap();
-        assert!(!src_path.exists());
-        assert!(dest_dir.join("test.jpg").exists());
+            .add_scanned_images(vec![dir.join("a.png")]);
+
+        app.request_move(&dest_dir);
+        assert!(matches!(
+            app.pending_confirmation,
+            Some(PendingConfirmation::Identical { ref existing, .. })
+                if *existing == dest_dir.join("a.png")
+        ));
+        assert!(dir.join("a.png").exists());
+        assert!(!dest_dir.join("a (1).png").exists());
+
+        // Nothing like it at the destination.
+        app.pending_confirmation = None;
+        app.image_manager.remove_current_image();
+        app.image_manager
+            .add_scanned_images(vec![dir.join("b.png")]);
+        app.request_move(&dest_dir);
+        assert!(app.pending_confirmation.is_none());
+        assert!(dest_dir.join("b.png").exists());
     }
 
-    // Given there are mulitple files in the src folder, move the current image to the dest folder.
     #[test]
-    fn multiple_one_file_move_current_image_to_dest_test() {
+    fn discard_review_test() {
         let mut app = MyApp::default();
         let temp_dir = tempfile::tempdir().unwrap();
-        let src_path1 = temp_dir.path().join("test1.jpg");
-        let src_path2 = temp_dir.path().join("test2.jpg");
-        let dest_dir = temp_dir.path().join("test_dest");
-        fs::create_dir(&dest_dir).unwrap();
-        std::fs::write(&src_path1, b"Hello, world!").unwrap();
-        std::fs::write(&src_path2, b"Hello, world!").unwrap();
-        app.image_manager
-            .set_image_folder(&temp_dir.path().to_string_lossy());
+        let dir = temp_dir.path().join("inbox");
+        let folder = temp_dir.path().join("discard");
+        fs::create_dir(&dir).unwrap();
+        fs::create_dir(&folder).unwrap();
+        fs::write(dir.join("a.png"), b"a").unwrap();
+        fs::write(dir.join("b.png"), b"b").unwrap();
+        app.image_manager.set_folders(vec![dir.clone()]);
+        app.image_manager.set_image_folder(&dir);
+        app.settings.discard_folder = Some(folder.clone());
 
-        app.move_current_image_to_dest(&dest_dir.to_string_lossy())
-            .unwrap();
+        app.move_current_image_and_report(&folder);
+        app.move_current_image_and_report(&folder);
+        app.open_discard_review();
+        let files = discard::list(&folder).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files
+            .iter()
+            .all(|file| file.original == Some(dir.join(get_file_name(&file.path)))));
+        assert_eq!(app.image_manager.num_images(), 0);
 
-        // Which file gets moved as a result of move_current_image_to_dest() changes depending on
-        // implementation.
-        assert!(src_path1.exists());
-        assert!(!src_path2.exists());
-        assert!(!dest_dir.join("test1.jpg").exists());
-        assert!(dest_dir.join("test2.jpg").exists());
+        // Restored files are back in the list, and can be moved again.
+        let a = files
+            .iter()
+            .find(|file| file.path == folder.join("a.png"))
+            .unwrap();
+        app.apply_tool_changes(&[(a.path.clone(), discard::restore(a).unwrap())], &[]);
+        assert_eq!(app.image_manager.image_paths(), [dir.join("a.png")]);
+        app.move_current_image_and_report(&folder);
+        assert_eq!(discard::list(&folder).unwrap().len(), 2);
     }
 
     #[test]
-    fn move_all_files_move_current_image_to_dest_test() {
+    fn quarantine_current_image_test() {
         let mut app = MyApp::default();
         let temp_dir = tempfile::tempdir().unwrap();
-        let src_path1 = temp_dir.path().join("test1.jpg");
-        let src_path2 = temp_dir.path().join("test2.jpg");
-        let dest_dir = temp_dir.path().join("test_dest");
-        fs::create_dir(&dest_dir).unwrap();
-        std::fs::write(&src_path1, b"Hello, world!").unwrap();
-        std::fs::write(&src_path2, b"Hello, world!").unwrap();
+        let dir = temp_dir.path();
+        let folder = dir.join("quarantine");
+        fs::create_dir(&folder).unwrap();
+        fs::write(dir.join("a.png"), b"").unwrap();
         app.image_manager
-            .set_image_folder(&temp_dir.path().to_string_lossy());
-        app.move_current_image_to_dest(&dest_dir.to_string_lossy())
-            .unwrap();
-        app.move_current_image_to_dest(&dest_dir.to_string_lossy())
-            .unwrap();
-        assert!(!src_path1.exists());
-        assert!(!src_path2.exists());
-        assert!(dest_dir.join("test1.jpg").exists());
-        assert!(dest_dir.join("test2.jpg").exists());
+            .add_scanned_images(vec![dir.join("a.png")]);
+        app.settings.quarantine_folder = Some(folder.clone());
+
+        app.quarantine_current_image();
+        assert!(folder.join("a.png").exists());
+        assert_eq!(app.image_manager.num_images(), 0);
+        let report = fs::read_to_string(folder.join(quarantine::REPORT_NAME)).unwrap();
+        assert!(report
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with(",a.png,Empty file"));
+        // Undone like any move.
+        app.undo_move();
+        assert!(dir.join("a.png").exists());
     }
 
     #[test]
-    fn remove_folder_letter_entries_test() {
+    fn read_only_test() {
+        let mut app = MyApp {
+            read_only: true,
+            ..Default::default()
+        };
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let dest_dir = dir.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+        for name in ["a.png", "b.png"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        app.image_manager.set_image_folder(dir);
+
+        let first = app
+            .image_manager
+            .current_image_path()
+            .unwrap()
+            .to_path_buf();
+        app.request_move(&dest_dir);
+        assert!(first.exists());
+        assert_eq!(app.plan.len(), 1);
+        // On to the next image as if it had been moved.
+        let second = app
+            .image_manager
+            .current_image_path()
+            .unwrap()
+            .to_path_buf();
+        assert_ne!(first, second);
+        assert!(app.delete_current_image().is_err());
+        assert!(app.rename_current_image("c.png").is_err());
+        assert!(second.exists());
+
+        assert_eq!(
+            Args::parse(["--read-only".into(), "photos".into()]),
+            Args {
+                folder: Some(PathBuf::from("photos")),
+                read_only: true,
+                portable: false,
+            }
+        );
+        assert!(Args::parse(["--portable".into()]).portable);
+    }
+
+    #[test]
+    fn shortcuts_test() {
         let mut app = MyApp {
             folder_letter_entries: vec![
                 FolderLetterEntry {
-                    folder: "folder1".to_string(),
-                    letter: 'A',
+                    folder: PathBuf::from("cats"),
+                    letter: 'C',
+                    confirm: false,
+                    link: false,
+                    copy: false,
+                    color: None,
+                    icon: String::new(),
+                    double_press: false,
                 },
                 FolderLetterEntry {
-                    folder: "folder2".to_string(),
-                    letter: 'B',
+                    folder: PathBuf::from("trash"),
+                    letter: 'D',
+                    confirm: true,
+                    link: false,
+                    copy: false,
+                    color: None,
+                    icon: String::new(),
+                    double_press: false,
                 },
             ],
             ..Default::default()
         };
-        let indecies = vec![0, 1];
-        app.remove_folder_letter_entries(indecies);
-        assert!(app.folder_letter_entries.is_empty());
+        let shortcuts = app.shortcuts();
+        assert!(shortcuts.contains(&("C".to_string(), "Move to cats".to_string())));
+        assert!(shortcuts.contains(&("D".to_string(), "Move to trash (asks first)".to_string())));
+
+        app.read_only = true;
+        assert!(app
+            .shortcuts()
+            .contains(&("C".to_string(), "Plan to move to cats".to_string())));
     }
 
     #[test]
-    fn undo_move_test() {
+    fn date_template_destination_test() {
         let mut app = MyApp::default();
         let temp_dir = tempfile::tempdir().unwrap();
-        let src_path = temp_dir.path().join("test.jpg");
-        let dest_dir = temp_dir.path().join("test_dest");
-        fs::create_dir(&dest_dir).unwrap();
-
-        std::fs::write(&src_path, b"Hello, world!").unwrap();
-        app.image_manager
-            .set_image_folder(&temp_dir.path().to_string_lossy());
-
-        app.move_current_image_to_dest(&dest_dir.to_string_lossy())
-            .unwrap();
-
-        // Make sure its not in image paths anymore and has been moved.
-        assert!(!src_path.exists());
-        assert!(dest_dir.join("test.jpg").exists());
-
-        // Now undo and check that everything is rolled back.
-        let Some(undo_path) = app.undo_move() else {
-            panic!("undo_move() returned None");
-        };
-        assert_eq!(undo_path, src_path.to_string_lossy());
-        assert!(src_path.exists());
-        assert!(!dest_dir.join("test.jpg").exists());
+        let image = temp_dir.path().join("a.png");
+        fs::write(&image, b"").unwrap();
+        fs::write(
+            temp_dir.path().join("a.png.json"),
+            r#"{"photoTakenTime": {"timestamp": "951827696"}}"#,
+        )
+        .unwrap();
+        app.image_manager.add_scanned_images(vec![image.clone()]);
 
-        // Further undo should return None.
-        assert!(app.undo_move().is_none());
-        assert!(app.undo_move().is_none());
+        app.move_current_image_and_report(&temp_dir.path().join("{year}/{month}"));
+        assert!(app.failed_move.is_none());
+        assert!(temp_dir.path().join("2000/02/a.png").exists());
+        assert!(temp_dir.path().join("2000/02/a.png.json").exists());
     }
 
     #[test]
@@ -667,13 +9849,32 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let src_path = temp_dir.path().join("test.jpg");
         std::fs::write(&src_path, b"Hello, world!").unwrap();
-        app.image_manager
-            .set_image_folder(&temp_dir.path().to_string_lossy());
+        app.image_manager.set_image_folder(temp_dir.path());
         assert!(app.image_manager.load_current_image().is_some());
         let Some(path) = app.image_manager.remove_current_image() else {
             panic!("remove_current_image() returned None");
         };
-        assert_eq!(path, src_path.to_string_lossy());
+        assert_eq!(path, src_path);
         assert!(app.image_manager.load_current_image().is_none());
     }
+
+    // Files whose names are not valid UTF-8 must still be listed and movable.
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_file_name_test() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let mut app = MyApp::default();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let src_path = temp_dir.path().join(OsStr::from_bytes(b"caf\xe9.jpg"));
+        let dest_dir = temp_dir.path().join("test_dest");
+        fs::create_dir(&dest_dir).unwrap();
+        std::fs::write(&src_path, b"Hello, world!").unwrap();
+        app.image_manager.set_image_folder(temp_dir.path());
+        assert_eq!(app.image_manager.num_images(), 1);
+
+        let move_log = app.move_current_image_to_dest(&dest_dir).unwrap();
+        assert_eq!(move_log.src, src_path);
+        assert!(dest_dir.join(OsStr::from_bytes(b"caf\xe9.jpg")).exists());
+    }
 }
@@ -0,0 +1,399 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{
+    batch::OnFile,
+    file_ops::{self, RetryPolicy},
+    i18n::tr,
+    scanner,
+    settings::CollisionPolicy,
+};
+
+/// How two folders differ. Files are matched by name and then compared by contents.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FolderComparison {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub only_in_source: Vec<OsString>,
+    pub only_in_target: Vec<OsString>,
+    // Same name and same contents.
+    pub identical: Vec<OsString>,
+    // Same name but different contents.
+    pub conflicts: Vec<OsString>,
+}
+
+fn image_names(folder: &Path) -> io::Result<BTreeSet<OsString>> {
+    let mut names = BTreeSet::new();
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_file() && scanner::is_image_file(&path) {
+            names.extend(path.file_name().map(|name| name.to_os_string()));
+        }
+    }
+    Ok(names)
+}
+
+/// Names that differ only in case are the same file where the target ignores case, as on
+/// Windows and macOS.
+pub fn compare_folders(source: &Path, target: &Path) -> io::Result<FolderComparison> {
+    let case_insensitive = file_ops::is_case_insensitive(target);
+    let keyed = |names: BTreeSet<OsString>| -> BTreeMap<OsString, OsString> {
+        names
+            .into_iter()
+            .map(|name| (file_ops::name_key(&name, case_insensitive), name))
+            .collect()
+    };
+    let source_names = keyed(image_names(source)?);
+    let target_names = keyed(image_names(target)?);
+    let mut comparison = FolderComparison {
+        source: source.to_path_buf(),
+        target: target.to_path_buf(),
+        ..Default::default()
+    };
+    for (key, name) in &source_names {
+        let Some(target_name) = target_names.get(key) else {
+            comparison.only_in_source.push(name.clone());
+            continue;
+        };
+        if file_ops::same_contents(&source.join(name), &target.join(target_name))? {
+            comparison.identical.push(name.clone());
+        } else {
+            comparison.conflicts.push(name.clone());
+        }
+    }
+    comparison.only_in_target = target_names
+        .iter()
+        .filter(|(key, _)| !source_names.contains_key(*key))
+        .map(|(_, name)| name.clone())
+        .collect();
+    Ok(comparison)
+}
+
+/// What to do with a source file whose name is taken by a different file in the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictResolution {
+    #[default]
+    Skip,
+    // Move it with a new name, e.g. "a (1).png".
+    KeepBoth,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MergeReport {
+    // (source, destination) of each moved file.
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    pub removed_identical: Vec<PathBuf>,
+    pub skipped: usize,
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// Moves the images of `comparison.source` into `comparison.target`. Source copies of identical
+/// files are deleted only if `remove_identical` is set, otherwise they are left in place.
+/// Stops early when `on_file` says so.
+pub fn merge_folders(
+    comparison: &FolderComparison,
+    resolution: ConflictResolution,
+    remove_identical: bool,
+    on_file: &mut OnFile,
+) -> MergeReport {
+    let mut report = MergeReport::default();
+    let mut to_move: Vec<&OsString> = comparison.only_in_source.iter().collect();
+    match resolution {
+        ConflictResolution::Skip => report.skipped += comparison.conflicts.len(),
+        ConflictResolution::KeepBoth => to_move.extend(&comparison.conflicts),
+    }
+    for name in to_move {
+        let src = comparison.source.join(name);
+        // Something may have appeared since the comparison. Never overwrite it.
+        let result = file_ops::with_retry(&src, &RetryPolicy::default(), || {
+            file_ops::move_file_as(&src, &comparison.target, CollisionPolicy::KeepBoth)
+        });
+        let result = match result {
+            Ok(dest) => {
+                report.moved.push((src.clone(), dest));
+                Ok(())
+            }
+            Err(e) => {
+                report.errors.push((src.clone(), e.to_string()));
+                Err(e.to_string())
+            }
+        };
+        if !on_file(&src, result) {
+            return report;
+        }
+    }
+    if !remove_identical {
+        report.skipped += comparison.identical.len();
+        return report;
+    }
+    for name in &comparison.identical {
+        let src = comparison.source.join(name);
+        let result = fs::remove_file(&src).map_err(|e| e.to_string());
+        match &result {
+            Ok(()) => report.removed_identical.push(src.clone()),
+            Err(e) => report.errors.push((src.clone(), e.clone())),
+        }
+        if !on_file(&src, result) {
+            return report;
+        }
+    }
+    report
+}
+
+/// A merge chosen in the window.
+pub struct MergeJob {
+    pub comparison: FolderComparison,
+    pub resolution: ConflictResolution,
+    pub remove_identical: bool,
+}
+
+impl MergeJob {
+    /// Number of files that are moved or deleted.
+    pub fn file_count(&self) -> usize {
+        let mut len = self.comparison.only_in_source.len();
+        if self.resolution == ConflictResolution::KeepBoth {
+            len += self.comparison.conflicts.len();
+        }
+        if self.remove_identical {
+            len += self.comparison.identical.len();
+        }
+        len
+    }
+
+    pub fn run(&self, on_file: &mut OnFile) -> MergeReport {
+        merge_folders(
+            &self.comparison,
+            self.resolution,
+            self.remove_identical,
+            on_file,
+        )
+    }
+}
+
+/// State of the "Merge folders" window.
+#[derive(Default)]
+pub struct MergeTool {
+    source: Option<PathBuf>,
+    target: Option<PathBuf>,
+    resolution: ConflictResolution,
+    remove_identical: bool,
+    // Comparing hashes every file with a shared name, so it runs in the background.
+    receiver: Option<mpsc::Receiver<io::Result<FolderComparison>>>,
+    comparison: Option<FolderComparison>,
+    error: Option<String>,
+}
+
+fn show_names(ui: &mut egui::Ui, title: &str, names: &[OsString]) {
+    egui::CollapsingHeader::new(format!("{} ({})", title, names.len()))
+        .id_salt(title)
+        .show(ui, |ui| {
+            let row_height = ui.text_style_height(&egui::TextStyle::Body);
+            egui::ScrollArea::vertical()
+                .id_salt(title)
+                .max_height(150.0)
+                .show_rows(ui, row_height, names.len(), |ui, rows| {
+                    for name in &names[rows] {
+                        ui.label(name.to_string_lossy());
+                    }
+                });
+        });
+}
+
+fn pick_folder(ui: &mut egui::Ui, label: &str, folder: &mut Option<PathBuf>) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label(label);
+        if ui.button(tr!("choose")).clicked() {
+            if let Some(path) = FileDialog::new().pick_folder() {
+                *folder = Some(path);
+                changed = true;
+            }
+        }
+        match folder {
+            Some(folder) => ui.label(folder.to_string_lossy()),
+            None => ui.label(tr!("not-selected")),
+        };
+    });
+    changed
+}
+
+impl MergeTool {
+    fn start_comparison(&mut self, source: PathBuf, target: PathBuf) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(compare_folders(&source, &target));
+        });
+        self.receiver = Some(receiver);
+        self.comparison = None;
+        self.error = None;
+    }
+
+    /// Collects the result of a running comparison.
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err(io::Error::other(tr!("compare-stopped"))),
+        };
+        match result {
+            Ok(comparison) => self.comparison = Some(comparison),
+            Err(e) => self.error = Some(tr!("compare-failed", error = e.to_string())),
+        }
+        self.receiver = None;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Draws the window contents. Returns the merge to run when "Merge" was clicked.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<MergeJob> {
+        ui.label(tr!("merge-intro"));
+        let mut changed = pick_folder(ui, &tr!("merge-source"), &mut self.source);
+        changed |= pick_folder(ui, &tr!("merge-target"), &mut self.target);
+        if changed {
+            self.comparison = None;
+        }
+
+        let folders = match (&self.source, &self.target) {
+            (Some(source), Some(target)) if source != target => {
+                Some((source.clone(), target.clone()))
+            }
+            _ => None,
+        };
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    folders.is_some() && !self.is_busy(),
+                    egui::Button::new(tr!("compare")),
+                )
+                .clicked()
+            {
+                if let Some((source, target)) = folders {
+                    self.start_comparison(source, target);
+                }
+            }
+            if self.is_busy() {
+                ui.spinner();
+                ui.label(tr!("comparing"));
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let comparison = self.comparison.as_ref()?;
+        show_names(ui, &tr!("only-in-source"), &comparison.only_in_source);
+        show_names(ui, &tr!("only-in-target"), &comparison.only_in_target);
+        show_names(ui, &tr!("identical"), &comparison.identical);
+        show_names(ui, &tr!("conflicts"), &comparison.conflicts);
+
+        ui.separator();
+        ui.label(tr!("conflicts-label"));
+        ui.radio_value(
+            &mut self.resolution,
+            ConflictResolution::Skip,
+            tr!("leave-in-source"),
+        );
+        ui.radio_value(
+            &mut self.resolution,
+            ConflictResolution::KeepBoth,
+            tr!("move-with-new-name"),
+        );
+        ui.checkbox(&mut self.remove_identical, tr!("delete-identical"));
+        if !ui.button(tr!("merge")).clicked() {
+            return None;
+        }
+        // The folders are about to change, so the comparison goes stale.
+        let job = MergeJob {
+            comparison: self.comparison.take()?,
+            resolution: self.resolution,
+            remove_identical: self.remove_identical,
+        };
+        Some(job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_and_merge_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        fs::create_dir(&source).unwrap();
+        fs::create_dir(&target).unwrap();
+        fs::write(source.join("new.png"), b"new").unwrap();
+        fs::write(source.join("same.png"), b"same").unwrap();
+        fs::write(target.join("same.png"), b"same").unwrap();
+        fs::write(source.join("conflict.png"), b"source").unwrap();
+        fs::write(target.join("conflict.png"), b"target").unwrap();
+        fs::write(target.join("old.png"), b"old").unwrap();
+        fs::write(source.join("notes.txt"), b"not an image").unwrap();
+
+        let comparison = compare_folders(&source, &target).unwrap();
+        assert_eq!(comparison.only_in_source, vec![OsString::from("new.png")]);
+        assert_eq!(comparison.only_in_target, vec![OsString::from("old.png")]);
+        assert_eq!(comparison.identical, vec![OsString::from("same.png")]);
+        assert_eq!(comparison.conflicts, vec![OsString::from("conflict.png")]);
+
+        let job = MergeJob {
+            comparison,
+            resolution: ConflictResolution::KeepBoth,
+            remove_identical: true,
+        };
+        assert_eq!(job.file_count(), 3);
+        let mut reported = 0;
+        let report = job.run(&mut |_, _| {
+            reported += 1;
+            true
+        });
+        assert_eq!(reported, 3);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.moved.len(), 2);
+        assert_eq!(report.removed_identical, vec![source.join("same.png")]);
+        assert_eq!(fs::read(target.join("conflict.png")).unwrap(), b"target");
+        assert_eq!(
+            fs::read(target.join("conflict (1).png")).unwrap(),
+            b"source"
+        );
+        assert!(target.join("new.png").exists());
+        assert!(source.join("notes.txt").exists());
+        assert_eq!(image_names(&source).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn merge_skips_conflicts_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("source");
+        let target = temp_dir.path().join("target");
+        fs::create_dir(&source).unwrap();
+        fs::create_dir(&target).unwrap();
+        fs::write(source.join("a.png"), b"source").unwrap();
+        fs::write(target.join("a.png"), b"target").unwrap();
+        fs::write(source.join("b.png"), b"same").unwrap();
+        fs::write(target.join("b.png"), b"same").unwrap();
+
+        let comparison = compare_folders(&source, &target).unwrap();
+        let report = merge_folders(&comparison, ConflictResolution::Skip, false, &mut |_, _| {
+            true
+        });
+        assert_eq!(report.skipped, 2);
+        assert!(report.moved.is_empty());
+        assert!(source.join("a.png").exists());
+        assert!(source.join("b.png").exists());
+    }
+}
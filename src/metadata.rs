@@ -0,0 +1,413 @@
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::UNIX_EPOCH,
+};
+
+use crate::{palette, sidecar};
+
+/// Where a photo was taken, in degrees. South and west are negative.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpsPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// What is known about a photo from its EXIF data and, for Google Takeout exports, the JSON file
+/// next to it. EXIF wins when both have a value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImageMetadata {
+    // e.g. "2024-01-31 12:34:56".
+    pub taken: Option<String>,
+    pub description: Option<String>,
+    pub gps: Option<GpsPosition>,
+}
+
+impl ImageMetadata {
+    // Fills in what is missing from `other`.
+    fn or(self, other: Self) -> Self {
+        Self {
+            taken: self.taken.or(other.taken),
+            description: self.description.or(other.description),
+            gps: self.gps.or(other.gps),
+        }
+    }
+}
+
+fn read_exif(path: &Path) -> Option<exif::Exif> {
+    let file = fs::File::open(path).ok()?;
+    exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()
+}
+
+// Formats as "YYYY-MM-DD HH:MM:SS" so that dates sort as strings.
+fn format_date(date: &exif::DateTime) -> String {
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        date.year, date.month, date.day, date.hour, date.minute, date.second
+    )
+}
+
+fn ascii_field(exif: &exif::Exif, tag: exif::Tag) -> Option<&[u8]> {
+    let field = exif.get_field(tag, exif::In::PRIMARY)?;
+    let exif::Value::Ascii(values) = &field.value else {
+        return None;
+    };
+    values.first().map(|value| value.as_slice())
+}
+
+fn exif_taken(exif: &exif::Exif) -> Option<String> {
+    [exif::Tag::DateTimeOriginal, exif::Tag::DateTime]
+        .iter()
+        .find_map(|tag| {
+            let date = exif::DateTime::from_ascii(ascii_field(exif, *tag)?).ok()?;
+            Some(format_date(&date))
+        })
+}
+
+// Degrees, minutes and seconds to degrees. `reference` is N/S or E/W.
+fn exif_coordinate(exif: &exif::Exif, tag: exif::Tag, reference: exif::Tag) -> Option<f64> {
+    let field = exif.get_field(tag, exif::In::PRIMARY)?;
+    let exif::Value::Rational(values) = &field.value else {
+        return None;
+    };
+    let [degrees, minutes, seconds] = values.get(..3)? else {
+        return None;
+    };
+    let value = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+    match ascii_field(exif, reference)? {
+        [b'S' | b'W', ..] => Some(-value),
+        _ => Some(value),
+    }
+}
+
+fn exif_metadata(exif: &exif::Exif) -> ImageMetadata {
+    let latitude = exif_coordinate(exif, exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef);
+    let longitude = exif_coordinate(exif, exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef);
+    ImageMetadata {
+        taken: exif_taken(exif),
+        description: ascii_field(exif, exif::Tag::ImageDescription)
+            .map(|value| String::from_utf8_lossy(value).trim().to_string())
+            .filter(|value| !value.is_empty()),
+        gps: latitude
+            .zip(longitude)
+            .map(|(latitude, longitude)| GpsPosition {
+                latitude,
+                longitude,
+            }),
+    }
+}
+
+/// When the photo was taken according to its EXIF data, e.g. "2024-01-31 12:34:56". None if the
+/// file has no EXIF data or no date.
+pub fn exif_date(path: &Path) -> Option<String> {
+    exif_taken(&read_exif(path)?)
+}
+
+/// Whether the EXIF data of `path` names the camera that took it. Screenshots and exported
+/// images often have EXIF data, but no camera.
+pub fn has_camera_exif(path: &Path) -> bool {
+    read_exif(path).is_some_and(|exif| exif.get_field(exif::Tag::Make, exif::In::PRIMARY).is_some())
+}
+
+// The parts of a Google Takeout JSON file that are used here.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TakeoutJson {
+    #[serde(default)]
+    description: String,
+    photo_taken_time: Option<TakeoutTime>,
+    geo_data: Option<TakeoutGeo>,
+}
+
+#[derive(Deserialize)]
+struct TakeoutTime {
+    // Unix time in seconds, as a string.
+    timestamp: String,
+}
+
+#[derive(Deserialize)]
+struct TakeoutGeo {
+    latitude: f64,
+    longitude: f64,
+}
+
+fn parse_takeout(json: &str) -> Option<ImageMetadata> {
+    let takeout: TakeoutJson = serde_json::from_str(json).ok()?;
+    let description = takeout.description.trim();
+    Some(ImageMetadata {
+        taken: takeout
+            .photo_taken_time
+            .and_then(|time| time.timestamp.parse().ok())
+            .map(format_timestamp),
+        description: (!description.is_empty()).then(|| description.to_string()),
+        gps: takeout
+            .geo_data
+            // Takeout writes 0.0 for both when the location is unknown.
+            .filter(|geo| geo.latitude != 0.0 || geo.longitude != 0.0)
+            .map(|geo| GpsPosition {
+                latitude: geo.latitude,
+                longitude: geo.longitude,
+            }),
+    })
+}
+
+// Metadata from the JSON file that Google Takeout exported with the image, if there is one.
+fn takeout_metadata(path: &Path) -> Option<ImageMetadata> {
+    sidecar::sidecar_files(path)
+        .into_iter()
+        .filter(|sidecar| {
+            sidecar
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+        })
+        .find_map(|json| parse_takeout(&fs::read_to_string(json).ok()?))
+}
+
+/// Everything known about the image at `path`. Missing values are None.
+pub fn read_metadata(path: &Path) -> ImageMetadata {
+    let metadata = read_exif(path)
+        .map(|exif| exif_metadata(&exif))
+        .unwrap_or_default();
+    match takeout_metadata(path) {
+        Some(takeout) => metadata.or(takeout),
+        None => metadata,
+    }
+}
+
+/// When the photo was taken, from EXIF or else a Google Takeout JSON file.
+pub fn date_taken(path: &Path) -> Option<String> {
+    exif_date(path).or_else(|| takeout_metadata(path)?.taken)
+}
+
+/// `date_taken()`, or the modification time of the file for images that do not know when they
+/// were taken. Used for sorting and date-based destinations.
+pub fn date_for_sorting(path: &Path) -> Option<String> {
+    date_taken(path).or_else(|| {
+        let modified = fs::metadata(path).ok()?.modified().ok()?;
+        let secs = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some(format_timestamp(secs as i64))
+    })
+}
+
+/// Formats a Unix time in UTC like `format_date()`.
+pub fn format_timestamp(secs: i64) -> String {
+    let days = secs.div_euclid(86400);
+    let time = secs.rem_euclid(86400);
+    // Days to a civil date. See http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// The Unix time of a date formatted like `format_timestamp()`.
+pub fn parse_timestamp(date: &str) -> Option<i64> {
+    let number = |range: std::ops::Range<usize>| date.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    // Days from a civil date, the inverse of the above.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+const DATE_PLACEHOLDERS: [&str; 3] = ["{year}", "{month}", "{day}"];
+
+/// Whether a destination folder contains "{year}", "{month}" or "{day}".
+pub fn is_date_template(folder: &Path) -> bool {
+    folder
+        .to_str()
+        .is_some_and(|folder| DATE_PLACEHOLDERS.iter().any(|p| folder.contains(p)))
+}
+
+/// Fills in the placeholders of a destination folder from a date formatted like
+/// `format_date()`, e.g. "/photos/{year}/{month}" becomes "/photos/2024/01".
+pub fn expand_date_template(folder: &Path, date: &str) -> PathBuf {
+    let (Some(template), Some(year), Some(month), Some(day)) = (
+        folder.to_str(),
+        date.get(0..4),
+        date.get(5..7),
+        date.get(8..10),
+    ) else {
+        return folder.to_path_buf();
+    };
+    PathBuf::from(
+        template
+            .replace("{year}", year)
+            .replace("{month}", month)
+            .replace("{day}", day),
+    )
+}
+
+/// Reads a sort key of images on a background thread, e.g. their dates. Reading EXIF of a large
+/// folder takes a while.
+pub struct KeyScan<K> {
+    receiver: mpsc::Receiver<HashMap<PathBuf, K>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Reads `date_for_sorting()` of images.
+pub type DateScan = KeyScan<String>;
+/// Reads the sizes of image files in bytes.
+pub type SizeScan = KeyScan<u64>;
+/// Reads `palette::dominant_color_of()` of images.
+pub type ColorScan = KeyScan<[u8; 3]>;
+
+impl DateScan {
+    pub fn start(paths: Vec<PathBuf>) -> Self {
+        Self::start_with(paths, date_for_sorting)
+    }
+}
+
+impl SizeScan {
+    pub fn start(paths: Vec<PathBuf>) -> Self {
+        Self::start_with(paths, |path| fs::metadata(path).ok().map(|m| m.len()))
+    }
+}
+
+impl ColorScan {
+    pub fn start(paths: Vec<PathBuf>) -> Self {
+        Self::start_with(paths, palette::dominant_color_of)
+    }
+}
+
+impl<K: Send + 'static> KeyScan<K> {
+    fn start_with(paths: Vec<PathBuf>, read: fn(&Path) -> Option<K>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let mut keys = HashMap::new();
+            for path in paths {
+                if thread_cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Some(key) = read(&path) {
+                    keys.insert(path, key);
+                }
+            }
+            let _ = sender.send(keys);
+        });
+        Self {
+            receiver,
+            cancelled,
+        }
+    }
+
+    /// The keys once all of them have been read.
+    pub fn poll(&self) -> Option<HashMap<PathBuf, K>> {
+        self.receiver.try_recv().ok()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_date_test() {
+        let date = exif::DateTime::from_ascii(b"2024:01:02 03:04:05").unwrap();
+        assert_eq!(format_date(&date), "2024-01-02 03:04:05");
+    }
+
+    #[test]
+    fn exif_date_without_exif_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("a.png");
+        image::RgbImage::new(4, 4).save(&path).unwrap();
+        assert_eq!(exif_date(&path), None);
+        assert_eq!(exif_date(&temp_dir.path().join("missing.jpg")), None);
+    }
+
+    #[test]
+    fn format_timestamp_test() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951827696), "2000-02-29 12:34:56");
+        assert_eq!(format_timestamp(1735689599), "2024-12-31 23:59:59");
+        assert_eq!(format_timestamp(-1), "1969-12-31 23:59:59");
+        for secs in [0, 951827696, 1735689599, -1] {
+            assert_eq!(parse_timestamp(&format_timestamp(secs)), Some(secs));
+        }
+        assert_eq!(parse_timestamp("unknown"), None);
+    }
+
+    #[test]
+    fn takeout_metadata_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("IMG_1.png");
+        image::RgbImage::new(4, 4).save(&path).unwrap();
+        assert_eq!(read_metadata(&path), ImageMetadata::default());
+
+        fs::write(
+            temp_dir.path().join("IMG_1.png.json"),
+            r#"{
+                "title": "IMG_1.png",
+                "description": "Beach ",
+                "photoTakenTime": {"timestamp": "951827696", "formatted": "Feb 29, 2000"},
+                "geoData": {"latitude": 35.5, "longitude": -139.25, "altitude": 0.0}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_metadata(&path),
+            ImageMetadata {
+                taken: Some("2000-02-29 12:34:56".to_string()),
+                description: Some("Beach".to_string()),
+                gps: Some(GpsPosition {
+                    latitude: 35.5,
+                    longitude: -139.25,
+                }),
+            }
+        );
+        assert_eq!(date_taken(&path).as_deref(), Some("2000-02-29 12:34:56"));
+
+        // Unknown location.
+        let metadata =
+            parse_takeout(r#"{"geoData": {"latitude": 0.0, "longitude": 0.0}}"#).unwrap();
+        assert_eq!(metadata.gps, None);
+        assert_eq!(parse_takeout("not json"), None);
+    }
+
+    #[test]
+    fn expand_date_template_test() {
+        let template = Path::new("/photos/{year}/{year}-{month}-{day}");
+        assert!(is_date_template(template));
+        assert!(!is_date_template(Path::new("/photos/2024")));
+        assert_eq!(
+            expand_date_template(template, "2024-01-31 12:34:56"),
+            PathBuf::from("/photos/2024/2024-01-31")
+        );
+    }
+}
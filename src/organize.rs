@@ -0,0 +1,258 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{metadata, plan::PlannedMove};
+
+/// The folder under `destination` for an image taken on `date`, e.g. "destination/2024/01".
+pub fn date_folder(destination: &Path, date: &str) -> PathBuf {
+    metadata::expand_date_template(&destination.join("{year}").join("{month}"), date)
+}
+
+/// Where each image would go, before anything is moved.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Preview {
+    // The move of each image with a date, and that date.
+    pub moves: Vec<(PlannedMove, String)>,
+    // Images whose date is not known, which stay where they are.
+    pub undated: Vec<PathBuf>,
+    // Images that already are in the folder of their date.
+    pub in_place: usize,
+}
+
+impl Preview {
+    /// The folders the images go to.
+    pub fn folders(&self) -> BTreeSet<&Path> {
+        self.moves
+            .iter()
+            .map(|(planned, _)| planned.dest_dir.as_path())
+            .collect()
+    }
+}
+
+/// Plans to move each of `images` into the folder of its date under `destination`.
+pub fn preview(
+    images: &[PathBuf],
+    destination: &Path,
+    date: impl Fn(&Path) -> Option<String>,
+) -> Preview {
+    let mut preview = Preview::default();
+    for src in images {
+        let Some(date) = date(src) else {
+            preview.undated.push(src.clone());
+            continue;
+        };
+        let dest_dir = date_folder(destination, &date);
+        if src.parent() == Some(dest_dir.as_path()) {
+            preview.in_place += 1;
+            continue;
+        }
+        preview.moves.push((
+            PlannedMove {
+                src: src.clone(),
+                dest_dir,
+            },
+            date,
+        ));
+    }
+    preview
+}
+
+/// What the user asked for in the "Organize by date" window.
+pub enum OrganizeAction {
+    // Carry out these moves into folders under `destination`.
+    Start {
+        moves: Vec<PlannedMove>,
+        destination: PathBuf,
+    },
+    // Move the images of the last run back.
+    Undo,
+}
+
+/// State of the "Organize by date" window.
+#[derive(Default)]
+pub struct OrganizeTool {
+    destination: Option<PathBuf>,
+    // Reading the dates of a large folder takes a while, so it runs in the background.
+    receiver: Option<mpsc::Receiver<Preview>>,
+    preview: Option<Preview>,
+}
+
+impl OrganizeTool {
+    fn start_preview(&mut self, images: Vec<PathBuf>, destination: PathBuf) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(preview(&images, &destination, metadata::date_for_sorting));
+        });
+        self.receiver = Some(receiver);
+        self.preview = None;
+    }
+
+    /// Collects the result of a running preview.
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(preview) => self.preview = Some(preview),
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {}
+        }
+        self.receiver = None;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Draws the window contents for the listed `images`. `can_undo` is whether the last run can
+    /// still be undone as a whole.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        images: &[PathBuf],
+        can_undo: bool,
+    ) -> Option<OrganizeAction> {
+        ui.label(
+            "Moves every listed image into a YYYY/MM folder of the date it was taken, or of \
+             when the file was last changed.",
+        );
+        ui.horizontal(|ui| {
+            ui.label("Move to:");
+            if ui.button("Choose").clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    self.destination = Some(path);
+                    self.preview = None;
+                }
+            }
+            match &self.destination {
+                Some(folder) => ui.label(folder.to_string_lossy()),
+                None => ui.label("Not selected."),
+            };
+        });
+
+        let mut action = None;
+        ui.horizontal(|ui| {
+            let ready = self.destination.is_some() && !images.is_empty() && !self.is_busy();
+            if ui
+                .add_enabled(ready, egui::Button::new("Preview"))
+                .clicked()
+            {
+                if let Some(destination) = self.destination.clone() {
+                    self.start_preview(images.to_vec(), destination);
+                }
+            }
+            if self.is_busy() {
+                ui.spinner();
+                ui.label("Reading dates...");
+            }
+            if ui
+                .add_enabled(can_undo, egui::Button::new("Undo last run"))
+                .on_hover_text("Moves the images of the last run back where they were.")
+                .clicked()
+            {
+                action = Some(OrganizeAction::Undo);
+            }
+        });
+
+        let (Some(preview), Some(destination)) = (&self.preview, &self.destination) else {
+            return action;
+        };
+        ui.label(format!(
+            "{} images into {} folders. {} are already in place, {} have no date and stay.",
+            preview.moves.len(),
+            preview.folders().len(),
+            preview.in_place,
+            preview.undated.len()
+        ));
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical().max_height(300.0).show_rows(
+            ui,
+            row_height,
+            preview.moves.len(),
+            |ui, rows| {
+                for (planned, date) in &preview.moves[rows] {
+                    let folder = planned
+                        .dest_dir
+                        .strip_prefix(destination)
+                        .unwrap_or(&planned.dest_dir);
+                    ui.horizontal(|ui| {
+                        ui.label(planned.src.to_string_lossy());
+                        ui.weak(date);
+                        ui.label(format!("→ {}", folder.display()));
+                    });
+                }
+            },
+        );
+        if ui
+            .add_enabled(!preview.moves.is_empty(), egui::Button::new("Organize"))
+            .clicked()
+        {
+            let destination = destination.clone();
+            let moves = self.preview.take()?.moves;
+            action = Some(OrganizeAction::Start {
+                moves: moves.into_iter().map(|(planned, _)| planned).collect(),
+                destination,
+            });
+        }
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn preview_test() {
+        let destination = Path::new("/photos");
+        assert_eq!(
+            date_folder(destination, "2024-01-31 12:00:00"),
+            Path::new("/photos/2024/01")
+        );
+
+        let dates = HashMap::from([
+            (PathBuf::from("/in/a.jpg"), "2024-01-31 12:00:00"),
+            (PathBuf::from("/in/b.jpg"), "2023-12-24 18:00:00"),
+            (PathBuf::from("/in/c.jpg"), "2024-01-01 00:00:00"),
+            (
+                PathBuf::from("/photos/2024/02/d.jpg"),
+                "2024-02-29 09:00:00",
+            ),
+        ]);
+        let images: Vec<PathBuf> = [
+            "/in/a.jpg",
+            "/in/b.jpg",
+            "/in/c.jpg",
+            "/in/e.jpg",
+            "/photos/2024/02/d.jpg",
+        ]
+        .map(PathBuf::from)
+        .to_vec();
+        let preview = preview(&images, destination, |path| {
+            dates.get(path).map(|date| date.to_string())
+        });
+        let dest_dirs: Vec<&Path> = preview
+            .moves
+            .iter()
+            .map(|(planned, _)| planned.dest_dir.as_path())
+            .collect();
+        assert_eq!(
+            dest_dirs,
+            [
+                Path::new("/photos/2024/01"),
+                Path::new("/photos/2023/12"),
+                Path::new("/photos/2024/01"),
+            ]
+        );
+        assert_eq!(preview.folders().len(), 2);
+        assert_eq!(preview.undated, [PathBuf::from("/in/e.jpg")]);
+        assert_eq!(preview.in_place, 1);
+    }
+}
@@ -0,0 +1,146 @@
+use image::{DynamicImage, ImageBuffer};
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Seek},
+    path::Path,
+};
+use tiff::{
+    decoder::{Decoder, DecodingResult},
+    ColorType,
+};
+
+use crate::archive;
+
+/// Whether the file at `path` can have more than one page. Scanners put every page of a document
+/// in one TIFF file.
+pub fn is_multipage_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("tif") || extension.eq_ignore_ascii_case("tiff")
+    })
+}
+
+fn count_pages(reader: impl Read + Seek) -> Result<usize, String> {
+    let mut decoder = Decoder::new(reader).map_err(|e| e.to_string())?;
+    let mut pages = 1;
+    while decoder.more_images() {
+        decoder.next_image().map_err(|e| e.to_string())?;
+        pages += 1;
+    }
+    Ok(pages)
+}
+
+/// The number of pages of the TIFF file at `path`. Only the directories of the pages are read.
+pub fn page_count(path: &Path) -> Result<usize, String> {
+    if archive::split(path).is_some() {
+        let bytes = archive::read(path).map_err(|e| e.to_string())?;
+        return count_pages(Cursor::new(bytes));
+    }
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    count_pages(BufReader::new(file))
+}
+
+// CMYK as printers mean it, without a color profile.
+fn cmyk_to_rgb(cmyk: &[u8]) -> Vec<u8> {
+    cmyk.chunks_exact(4)
+        .flat_map(|pixel| {
+            let white = 255 - u16::from(pixel[3]);
+            pixel[..3]
+                .iter()
+                .map(move |&ink| ((255 - u16::from(ink)) * white / 255) as u8)
+        })
+        .collect()
+}
+
+/// Decodes page `page`, counting from 0, of the TIFF file contents `bytes`.
+pub fn decode_page(bytes: &[u8], page: usize) -> Result<DynamicImage, String> {
+    let mut decoder = Decoder::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    decoder.seek_to_image(page).map_err(|e| e.to_string())?;
+    let (width, height) = decoder.dimensions().map_err(|e| e.to_string())?;
+    let color_type = decoder.colortype().map_err(|e| e.to_string())?;
+    let data = decoder.read_image().map_err(|e| e.to_string())?;
+    let image = match (color_type, data) {
+        (ColorType::Gray(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba16)
+        }
+        (ColorType::CMYK(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, cmyk_to_rgb(&data)).map(DynamicImage::ImageRgb8)
+        }
+        (color_type, _) => {
+            return Err(format!(
+                "Pages with {:?} pixels are not supported",
+                color_type
+            ))
+        }
+    };
+    image.ok_or_else(|| format!("Page {} is damaged", page + 1))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    pub fn three_page_tiff() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = TiffEncoder::new(Cursor::new(&mut bytes)).unwrap();
+        encoder
+            .write_image::<colortype::Gray8>(2, 1, &[10, 20])
+            .unwrap();
+        encoder
+            .write_image::<colortype::RGB8>(1, 1, &[1, 2, 3])
+            .unwrap();
+        encoder
+            .write_image::<colortype::CMYK8>(1, 1, &[0, 255, 0, 0])
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn decode_page_test() {
+        let bytes = three_page_tiff();
+        assert_eq!(count_pages(Cursor::new(&bytes)).unwrap(), 3);
+        assert_eq!(
+            decode_page(&bytes, 0).unwrap().to_luma8().into_raw(),
+            [10, 20]
+        );
+        assert_eq!(
+            decode_page(&bytes, 1).unwrap().to_rgb8().into_raw(),
+            [1, 2, 3]
+        );
+        // Magenta.
+        assert_eq!(
+            decode_page(&bytes, 2).unwrap().to_rgb8().into_raw(),
+            [255, 0, 255]
+        );
+        assert!(decode_page(&bytes, 3).is_err());
+    }
+
+    #[test]
+    fn page_count_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("scan.TIF");
+        std::fs::write(&path, three_page_tiff()).unwrap();
+        assert!(is_multipage_file(&path));
+        assert_eq!(page_count(&path).unwrap(), 3);
+        std::fs::write(&path, b"not a tiff").unwrap();
+        assert!(page_count(&path).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use eframe::egui;
+use image::RgbImage;
+use std::path::Path;
+
+use crate::{i18n::tr, image_loader, raw};
+
+// Images are looked at this small. The dominant color does not need details.
+const ANALYSIS_SIZE: u32 = 64;
+
+// Each channel is cut into this many levels to put pixels of similar colors together.
+const LEVELS: usize = 8;
+
+// Colors less saturated or darker than this have no hue to speak of.
+const MIN_SATURATION: f32 = 0.2;
+const MIN_VALUE: f32 = 0.15;
+
+/// The color that covers most of `image`: pixels of similar colors are counted together, and the
+/// average of the most common ones wins.
+pub fn dominant_color(image: &RgbImage) -> [u8; 3] {
+    let bucket = |value: u8| usize::from(value) * LEVELS / 256;
+    // The number of pixels in each bucket and the sum of their channels.
+    let mut buckets = vec![(0u32, [0u32; 3]); LEVELS * LEVELS * LEVELS];
+    for pixel in image.pixels() {
+        let [r, g, b] = pixel.0;
+        let (count, sum) = &mut buckets[(bucket(r) * LEVELS + bucket(g)) * LEVELS + bucket(b)];
+        *count += 1;
+        sum.iter_mut()
+            .zip(pixel.0)
+            .for_each(|(sum, value)| *sum += u32::from(value));
+    }
+    let Some((count, sum)) = buckets
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .max_by_key(|(count, _)| *count)
+    else {
+        return [0, 0, 0];
+    };
+    sum.map(|sum| ((sum + count / 2) / count) as u8)
+}
+
+/// `dominant_color()` of an image file.
+pub fn dominant_color_of(path: &Path) -> Option<[u8; 3]> {
+    let image = raw::decode_file(path).ok()?;
+    Some(dominant_color(
+        &image_loader::downscale(image, ANALYSIS_SIZE).to_rgb8(),
+    ))
+}
+
+// Hue in degrees, saturation and value of a color.
+fn hsv([r, g, b]: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = [r, g, b].map(|value| f32::from(value) / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let range = max - min;
+    let hue = if range == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / range).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / range + 2.0)
+    } else {
+        60.0 * ((r - g) / range + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { range / max };
+    (hue, saturation, max)
+}
+
+/// Sorts colors around the color wheel from red, followed by the grays from black to white.
+pub fn hue_key(color: [u8; 3]) -> u16 {
+    let (hue, saturation, value) = hsv(color);
+    if saturation < MIN_SATURATION || value < MIN_VALUE {
+        let [r, g, b] = color.map(u16::from);
+        360 + (r + g + b) / 3
+    } else {
+        (hue as u16).min(359)
+    }
+}
+
+/// The colors that images can be filtered by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hue {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Cyan,
+    Blue,
+    Purple,
+    Pink,
+    // Black, white, and anything with too little color.
+    Gray,
+}
+
+impl Hue {
+    pub const ALL: [Hue; 9] = [
+        Hue::Red,
+        Hue::Orange,
+        Hue::Yellow,
+        Hue::Green,
+        Hue::Cyan,
+        Hue::Blue,
+        Hue::Purple,
+        Hue::Pink,
+        Hue::Gray,
+    ];
+
+    pub fn of(color: [u8; 3]) -> Self {
+        match hue_key(color) {
+            0..15 => Hue::Red,
+            15..45 => Hue::Orange,
+            45..70 => Hue::Yellow,
+            70..165 => Hue::Green,
+            165..195 => Hue::Cyan,
+            195..255 => Hue::Blue,
+            255..290 => Hue::Purple,
+            290..345 => Hue::Pink,
+            345..360 => Hue::Red,
+            _ => Hue::Gray,
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Hue::Red => tr!("hue-red"),
+            Hue::Orange => tr!("hue-orange"),
+            Hue::Yellow => tr!("hue-yellow"),
+            Hue::Green => tr!("hue-green"),
+            Hue::Cyan => tr!("hue-cyan"),
+            Hue::Blue => tr!("hue-blue"),
+            Hue::Purple => tr!("hue-purple"),
+            Hue::Pink => tr!("hue-pink"),
+            Hue::Gray => tr!("hue-gray"),
+        }
+    }
+}
+
+/// A small square of `color`, e.g. next to the name of an image.
+pub fn swatch(ui: &mut egui::Ui, [r, g, b]: [u8; 3]) -> egui::Response {
+    let size = egui::Vec2::splat(ui.text_style_height(&egui::TextStyle::Body));
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    ui.painter()
+        .rect_filled(rect, 2.0, egui::Color32::from_rgb(r, g, b));
+    ui.painter()
+        .rect_stroke(rect, 2.0, ui.visuals().widgets.noninteractive.bg_stroke);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dominant_color_test() {
+        // Mostly blue, in two shades, with a red corner.
+        let image = RgbImage::from_fn(10, 10, |x, y| match (x, y) {
+            (0..3, 0..3) => image::Rgb([255, 0, 0]),
+            _ if (x + y) % 2 == 0 => image::Rgb([0, 0, 250]),
+            _ => image::Rgb([0, 0, 240]),
+        });
+        assert_eq!(dominant_color(&image), [0, 0, 245]);
+        assert_eq!(dominant_color(&RgbImage::new(0, 0)), [0, 0, 0]);
+    }
+
+    #[test]
+    fn hue_test() {
+        assert_eq!(hue_key([255, 0, 0]), 0);
+        assert_eq!(hue_key([0, 255, 0]), 120);
+        assert_eq!(hue_key([0, 0, 255]), 240);
+        assert_eq!(hue_key([255, 0, 128]), 329);
+        // Grays come after all colors, from black to white.
+        assert_eq!(hue_key([0, 0, 0]), 360);
+        assert_eq!(hue_key([200, 190, 195]), 555);
+        assert!(hue_key([255, 0, 128]) < hue_key([10, 10, 10]));
+
+        assert_eq!(Hue::of([250, 20, 20]), Hue::Red);
+        assert_eq!(Hue::of([250, 20, 60]), Hue::Red);
+        assert_eq!(Hue::of([250, 150, 20]), Hue::Orange);
+        assert_eq!(Hue::of([30, 200, 60]), Hue::Green);
+        assert_eq!(Hue::of([30, 60, 200]), Hue::Blue);
+        assert_eq!(Hue::of([128, 128, 128]), Hue::Gray);
+    }
+}
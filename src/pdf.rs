@@ -0,0 +1,285 @@
+use std::{io, path::Path, process::Command, time::Duration};
+
+use crate::file_ops;
+
+// The longer side of the rendered first page, in pixels.
+const RENDER_SIZE: u32 = 2048;
+
+// A PDF that takes longer than this to render is given up on, and its embedded image shown.
+const RENDER_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn is_pdf_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+// Renders the first page of the PDF `data` to PNG with pdftoppm of Poppler, which most Linux
+// desktops have and Homebrew and MSYS2 offer.
+fn render_with_pdftoppm(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut command = Command::new("pdftoppm");
+    command
+        .args(["-f", "1", "-l", "1", "-singlefile", "-png", "-scale-to"])
+        .arg(RENDER_SIZE.to_string())
+        // The PDF is read from stdin and the PNG written to stdout.
+        .arg("-");
+    let png = file_ops::output_with_timeout(command, data.to_vec(), RENDER_TIMEOUT)?;
+    if png.is_empty() {
+        return Err(io::Error::other("pdftoppm wrote no image"));
+    }
+    Ok(png)
+}
+
+// Page tree nodes nested deeper than this are taken to be a loop.
+const MAX_DEPTH: usize = 32;
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// The first JPEG image in `data`, in the order of the file.
+fn first_jpeg(data: &[u8]) -> Option<&[u8]> {
+    let mut position = 0;
+    while let Some(found) = find(&data[position..], b"/DCTDecode") {
+        let filter = position + found;
+        position = filter + 1;
+        let stream = filter + find(&data[filter..], b"stream")? + b"stream".len();
+        // The data starts after the end of the line.
+        let start = match data.get(stream..stream + 2)? {
+            [b'\r', b'\n'] => stream + 2,
+            [b'\n', _] | [b'\r', _] => stream + 1,
+            _ => continue,
+        };
+        let jpeg = &data[start..];
+        // A JPEG starts with SOI. Otherwise the filter was of another object.
+        if jpeg.starts_with(&[0xff, 0xd8]) {
+            let end = find(jpeg, b"endstream").unwrap_or(jpeg.len());
+            return Some(jpeg[..end].trim_ascii_end());
+        }
+    }
+    None
+}
+
+// Object `number` of the PDF `data`, up to "endobj". Objects packed into the object streams of
+// newer writers are not found.
+fn object(data: &[u8], number: u32) -> Option<&[u8]> {
+    let header = format!("{} 0 obj", number);
+    let mut position = 0;
+    while let Some(found) = find(&data[position..], header.as_bytes()) {
+        let start = position + found;
+        position = start + 1;
+        // "1 0 obj" is also the end of "11 0 obj".
+        if start > 0 && data[start - 1].is_ascii_digit() {
+            continue;
+        }
+        let contents = &data[start + header.len()..];
+        return Some(&contents[..find(contents, b"endobj").unwrap_or(contents.len())]);
+    }
+    None
+}
+
+// What follows the name `key` in `dictionary`.
+fn value<'a>(dictionary: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let mut position = 0;
+    while let Some(found) = find(&dictionary[position..], key) {
+        let end = position + found + key.len();
+        position = end;
+        // "/Page" is also the start of "/Pages".
+        if !dictionary.get(end).is_some_and(u8::is_ascii_alphanumeric) {
+            return Some(dictionary[end..].trim_ascii_start());
+        }
+    }
+    None
+}
+
+// The object numbers of the references "N G R" that `value` starts with, in an array or not.
+fn references(value: &[u8]) -> Vec<u32> {
+    let value = value.strip_prefix(b"[").unwrap_or(value);
+    let end = value
+        .iter()
+        .position(|byte| b"]/<>".contains(byte))
+        .unwrap_or(value.len());
+    let tokens: Vec<&[u8]> = value[..end]
+        .split(u8::is_ascii_whitespace)
+        .filter(|token| !token.is_empty())
+        .collect();
+    tokens
+        .chunks_exact(3)
+        .map_while(|reference| match reference {
+            [number, _, b"R"] => std::str::from_utf8(number).ok()?.parse().ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+// The dictionary that `value` is, in place or as a reference to another object.
+fn dictionary<'a>(data: &'a [u8], value: &'a [u8]) -> Option<&'a [u8]> {
+    if !value.starts_with(b"<<") {
+        return object(data, *references(value).first()?);
+    }
+    // Up to the ">>" that closes it.
+    let mut depth = 0;
+    let mut position = 0;
+    while position + 1 < value.len() {
+        match &value[position..position + 2] {
+            b"<<" => depth += 1,
+            b">>" => depth -= 1,
+            _ => {
+                position += 1;
+                continue;
+            }
+        }
+        position += 2;
+        if depth == 0 {
+            return Some(&value[..position]);
+        }
+    }
+    None
+}
+
+// The JPEG image drawn on the first page of `data`, found through the page tree. Scanners write
+// each page as one JPEG, so for scanned receipts and documents it is the page.
+fn first_page_jpeg(data: &[u8]) -> Option<&[u8]> {
+    // The last trailer names the catalog after incremental updates.
+    let root = data.windows(5).rposition(|window| window == b"/Root")?;
+    let catalog = object(
+        data,
+        *references(data[root + 5..].trim_ascii_start()).first()?,
+    )?;
+    let mut node = object(data, *references(value(catalog, b"/Pages")?).first()?)?;
+    // Pages take the resources of the nodes above them unless they have their own.
+    let mut resources = value(node, b"/Resources");
+    for _ in 0..MAX_DEPTH {
+        let Some(kids) = value(node, b"/Kids") else {
+            break;
+        };
+        node = object(data, *references(kids).first()?)?;
+        resources = value(node, b"/Resources").or(resources);
+    }
+    if value(node, b"/Kids").is_some() {
+        return None;
+    }
+    let resources = dictionary(data, resources?)?;
+    let images = dictionary(data, value(resources, b"/XObject")?)?;
+    // "/Im0 5 0 R /Im1 6 0 R".
+    images
+        .split(|byte| *byte == b'/')
+        .skip(1)
+        .filter_map(|entry| {
+            let name_end = entry.iter().position(u8::is_ascii_whitespace)?;
+            references(entry[name_end..].trim_ascii_start())
+                .first()
+                .copied()
+        })
+        .find_map(|number| first_jpeg(object(data, number)?))
+}
+
+/// An image of the first page of the PDF `data`, in PNG or JPEG. Rendered with pdftoppm when it
+/// is installed, or else the JPEG image on the first page, which is the page of a scanned
+/// document. Where the page cannot be found, e.g. in compressed objects, it is an embedded image
+/// that may be of another page.
+pub fn first_page(data: &[u8]) -> Result<Vec<u8>, String> {
+    let render_error = match render_with_pdftoppm(data) {
+        Ok(png) => return Ok(png),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            "Install Poppler (pdftoppm) to preview PDF files that are not scans.".to_string()
+        }
+        Err(e) => e.to_string(),
+    };
+    log::debug!(
+        "Falling back to the image of the first page: {}",
+        render_error
+    );
+    first_page_jpeg(data)
+        .or_else(|| {
+            log::debug!("No image found on the first page. Showing an embedded image.");
+            first_jpeg(data)
+        })
+        .map(|jpeg| jpeg.to_vec())
+        .ok_or(render_error)
+}
+
+/// Checks that `data` is a complete PDF file. A PDF ends with "%%EOF", so a truncated one does
+/// not, and decoding a page would not find every kind of damage anyway.
+pub fn check(data: &[u8]) -> Result<(), String> {
+    if !data.starts_with(b"%PDF-") {
+        return Err("Not a PDF file".to_string());
+    }
+    // Some writers put a few bytes of whitespace or garbage after it.
+    let tail = &data[data.len().saturating_sub(1024)..];
+    if !tail.windows(5).any(|window| window == b"%%EOF") {
+        return Err("The PDF file is truncated".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanned_pdf(jpeg: &[u8]) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n<< /Type /XObject /Subtype /Image /Filter /FlateDecode >>\nstream\nxx\nendstream\nendobj\n2 0 obj\n<< /Subtype /Image /Filter /DCTDecode /Length 4 >>\nstream\r\n".to_vec();
+        pdf.extend(jpeg);
+        pdf.extend(b"\nendstream\nendobj\ntrailer\n<< >>\n%%EOF\n");
+        pdf
+    }
+
+    #[test]
+    fn first_jpeg_test() {
+        let jpeg = [0xff, 0xd8, 0xff, 0xd9];
+        let pdf = scanned_pdf(&jpeg);
+        assert_eq!(first_jpeg(&pdf), Some(&jpeg[..]));
+        assert_eq!(first_jpeg(b"%PDF-1.4\n/Filter /FlateDecode stream\n"), None);
+        // The filter of an image whose data is not a JPEG is skipped.
+        assert_eq!(first_jpeg(b"/DCTDecode stream\nxx /DCTDecode"), None);
+        // There is no page tree.
+        assert_eq!(first_page_jpeg(&pdf), None);
+    }
+
+    #[test]
+    fn first_page_jpeg_test() {
+        let second = [0xff, 0xd8, 0x02, 0xff, 0xd9];
+        let first = [0xff, 0xd8, 0x01, 0xff, 0xd9];
+        let image = |number: u32, jpeg: &[u8]| {
+            let mut object = format!(
+                "{} 0 obj\n<< /Subtype /Image /Filter /DCTDecode >>\nstream\n",
+                number
+            )
+            .into_bytes();
+            object.extend(jpeg);
+            object.extend(b"\nendstream\nendobj\n");
+            object
+        };
+        // The image of the second page comes first in the file, and the first page takes its
+        // resources from the page tree.
+        let pdf = [
+            b"%PDF-1.4\n".to_vec(),
+            image(11, &second),
+            image(12, &first),
+            b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n\
+              2 0 obj\n<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 \
+              /Resources << /XObject << /Im1 12 0 R >> >> >>\nendobj\n\
+              3 0 obj\n<< /Type /Page /Parent 2 0 R >>\nendobj\n\
+              4 0 obj\n<< /Type /Page /Parent 2 0 R /Resources 5 0 R >>\nendobj\n\
+              5 0 obj\n<< /XObject << /Im0 11 0 R >> >>\nendobj\n\
+              trailer\n<< /Root 1 0 R >>\n%%EOF\n"
+                .to_vec(),
+        ]
+        .concat();
+        assert_eq!(first_page_jpeg(&pdf), Some(&first[..]));
+        assert_eq!(first_jpeg(&pdf), Some(&second[..]));
+        assert_eq!(references(b"[3 0 R 4 0 R] /Count 2"), [3, 4]);
+        assert_eq!(references(b"2 0 R /Type /Pages"), [2]);
+        assert_eq!(references(b"/Type"), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn check_test() {
+        let pdf = scanned_pdf(&[0xff, 0xd8, 0xff, 0xd9]);
+        assert!(check(&pdf).is_ok());
+        assert!(check(&pdf[..pdf.len() - 8]).is_err());
+        assert!(check(b"PK\x03\x04").is_err());
+        assert!(is_pdf_file(Path::new("receipt.PDF")));
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    demo::{self, Demo},
+    i18n::tr,
+};
+
+/// Something to try in practice mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    Move,
+    Undo,
+    Filter,
+}
+
+impl Task {
+    pub const ALL: [Task; 3] = [Task::Move, Task::Undo, Task::Filter];
+
+    fn label(self) -> String {
+        match self {
+            Task::Move => tr!("practice-task-move"),
+            Task::Undo => tr!("practice-task-undo"),
+            Task::Filter => tr!("practice-task-filter"),
+        }
+    }
+}
+
+/// What the user chose in the practice window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PracticeAction {
+    // Put the images back to try again.
+    Reset,
+    Leave,
+}
+
+/// Sorting generated images in the temp directory, with a list of things to try. Nothing
+/// outside that folder is moved while it is on.
+pub struct Practice {
+    root: PathBuf,
+    done: Vec<Task>,
+}
+
+impl Practice {
+    /// Creates the images in `root`. Returns the practice and its demo folders.
+    pub fn start(root: &Path) -> Result<(Self, Demo)> {
+        let demo = demo::create(root)?;
+        let practice = Self {
+            root: root.to_path_buf(),
+            done: Vec::new(),
+        };
+        Ok((practice, demo))
+    }
+
+    /// Creates the images again, as they were at the start.
+    pub fn reset(&mut self) -> Result<Demo> {
+        self.done.clear();
+        demo::create(&self.root)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether `path` is one of the practice files or folders.
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.root)
+    }
+
+    /// Marks `task` as done. Returns true if that finished the last one.
+    pub fn complete(&mut self, task: Task) -> bool {
+        if self.done.contains(&task) {
+            return false;
+        }
+        self.done.push(task);
+        self.is_finished()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        Task::ALL.iter().all(|task| self.done.contains(task))
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) -> Option<PracticeAction> {
+        ui.label(tr!("practice-text"));
+        for task in Task::ALL {
+            let mut done = self.done.contains(&task);
+            ui.add_enabled(false, egui::Checkbox::new(&mut done, task.label()));
+        }
+        if self.is_finished() {
+            ui.strong(tr!("practice-done"));
+        }
+        ui.separator();
+        let mut action = None;
+        ui.horizontal(|ui| {
+            if ui.button(tr!("practice-reset")).clicked() {
+                action = Some(PracticeAction::Reset);
+            }
+            if ui.button(tr!("practice-leave")).clicked() {
+                action = Some(PracticeAction::Leave);
+            }
+        });
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn practice_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("practice");
+        let (mut practice, demo) = Practice::start(&root).unwrap();
+        assert!(practice.contains(&demo.inbox.join("01-red.png")));
+        assert!(!practice.contains(temp_dir.path()));
+
+        assert!(!practice.complete(Task::Undo));
+        assert!(!practice.complete(Task::Move));
+        assert!(practice.complete(Task::Filter));
+        // Only once.
+        assert!(!practice.complete(Task::Filter));
+        assert!(practice.is_finished());
+
+        practice.reset().unwrap();
+        assert!(!practice.is_finished());
+    }
+}
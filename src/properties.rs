@@ -0,0 +1,116 @@
+use eframe::egui;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::i18n::tr;
+use crate::{file_ops, metadata};
+
+/// What a file manager tells about an image file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileProperties {
+    pub path: PathBuf,
+    // None if the header cannot be read, e.g. of RAW files.
+    pub dimensions: Option<(u32, u32)>,
+    // e.g. "PNG". From the contents if they are recognized, or else from the extension.
+    pub format: String,
+    pub size: u64,
+    // Unix times. Not every file system records when a file was created.
+    pub created: Option<i64>,
+    pub modified: Option<i64>,
+}
+
+fn unix_time(time: io::Result<SystemTime>) -> Option<i64> {
+    let duration = time.ok()?.duration_since(UNIX_EPOCH).ok()?;
+    i64::try_from(duration.as_secs()).ok()
+}
+
+/// Reads the properties of `path`. Only the header of the image is read, not the pixels.
+pub fn read(path: &Path) -> io::Result<FileProperties> {
+    let file_metadata = fs::metadata(path)?;
+    let reader = image::ImageReader::open(path)?.with_guessed_format()?;
+    let format = match reader.format() {
+        Some(format) => format.extensions_str()[0].to_uppercase(),
+        None => path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_uppercase())
+            .unwrap_or_default(),
+    };
+    Ok(FileProperties {
+        path: path.to_path_buf(),
+        dimensions: reader.into_dimensions().ok(),
+        format,
+        size: file_metadata.len(),
+        created: unix_time(file_metadata.created()),
+        modified: unix_time(file_metadata.modified()),
+    })
+}
+
+impl FileProperties {
+    /// e.g. "1920 × 1080 · PNG · 2.3 MB".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some((width, height)) = self.dimensions {
+            parts.push(format!("{} × {}", width, height));
+        }
+        if !self.format.is_empty() {
+            parts.push(self.format.clone());
+        }
+        parts.push(file_ops::format_size(self.size));
+        parts.join(" · ")
+    }
+
+    /// Draws the properties under the name of the image. Returns true if the path was copied.
+    pub fn show(&self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(self.summary());
+            if let Some(time) = self.created {
+                let time = metadata::format_timestamp(time);
+                ui.weak(tr!("property-created", time = time));
+            }
+            if let Some(time) = self.modified {
+                let time = metadata::format_timestamp(time);
+                ui.weak(tr!("property-modified", time = time));
+            }
+        });
+        ui.horizontal(|ui| {
+            let copied = ui.button(tr!("copy-path")).clicked();
+            if copied {
+                ui.ctx().copy_text(self.path.to_string_lossy().to_string());
+            }
+            ui.add(egui::Label::new(self.path.to_string_lossy()).truncate());
+            copied
+        })
+        .inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("a.png");
+        image::RgbImage::new(3, 2).save(&path).unwrap();
+        let properties = read(&path).unwrap();
+        assert_eq!(properties.dimensions, Some((3, 2)));
+        assert_eq!(properties.format, "PNG");
+        assert_eq!(properties.size, fs::metadata(&path).unwrap().len());
+        assert!(properties.modified.is_some());
+        assert!(properties.summary().starts_with("3 × 2 · PNG · "));
+
+        // Not an image after all.
+        let path = temp_dir.path().join("b.jpg");
+        fs::write(&path, b"text").unwrap();
+        let properties = read(&path).unwrap();
+        assert_eq!(properties.dimensions, None);
+        assert_eq!(properties.format, "JPG");
+        assert_eq!(properties.summary(), "JPG · 0.0 MB");
+
+        assert!(read(&temp_dir.path().join("missing.png")).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use eframe::egui;
+use image::ImageFormat;
+use rfd::FileDialog;
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{batch::ImageScan, i18n::tr, integrity, metadata, plan::csv_field};
+
+/// Name of the file in the quarantine folder that says why each file is there.
+pub const REPORT_NAME: &str = "quarantine-report.csv";
+
+// The first bytes of programs and scripts: Windows, Linux and macOS executables, and a shebang.
+const PROGRAM_SIGNATURES: [&[u8]; 5] = [
+    b"MZ",
+    b"\x7fELF",
+    b"\xcf\xfa\xed\xfe",
+    b"\xca\xfe\xba\xbe",
+    b"#!",
+];
+
+/// What is wrong with a file that belongs in quarantine.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    Empty,
+    // The decoder's error.
+    Unreadable(String),
+    // What makes it suspicious.
+    Suspicious(String),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Empty => write!(f, "{}", tr!("empty-file")),
+            Problem::Unreadable(error) => {
+                write!(f, "{}", tr!("unreadable", error = error.as_str()))
+            }
+            Problem::Suspicious(reason) => {
+                write!(f, "{}", tr!("suspicious", reason = reason.as_str()))
+            }
+        }
+    }
+}
+
+// Why the first bytes of a file named `path` do not look like the image its extension says.
+fn suspicious_contents(path: &Path, header: &[u8]) -> Option<String> {
+    if PROGRAM_SIGNATURES
+        .iter()
+        .any(|signature| header.starts_with(signature))
+    {
+        return Some(tr!("looks-like-program"));
+    }
+    // RAW files, PDFs and archives have extensions that the image crate does not know.
+    let expected = ImageFormat::from_path(path).ok()?;
+    let actual = image::guess_format(header).ok()?;
+    // TIFF-based formats share the header.
+    (actual != expected && actual != ImageFormat::Tiff).then(|| {
+        tr!(
+            "contents-are",
+            actual = format_name(actual),
+            expected = format_name(expected)
+        )
+    })
+}
+
+fn format_name(format: ImageFormat) -> &'static str {
+    format.extensions_str().first().copied().unwrap_or("?")
+}
+
+/// Checks whether the file at `path` should be quarantined: it is empty, cannot be decoded, or
+/// its contents are not what its name says. None if it is fine.
+pub fn check(path: &Path) -> Option<Problem> {
+    let mut header = Vec::new();
+    match fs::File::open(path).and_then(|file| file.take(16).read_to_end(&mut header)) {
+        Ok(0) => return Some(Problem::Empty),
+        Ok(_) => {}
+        Err(e) => return Some(Problem::Unreadable(e.to_string())),
+    }
+    if let Some(reason) = suspicious_contents(path, &header) {
+        return Some(Problem::Suspicious(reason));
+    }
+    integrity::check_image(path).err().map(Problem::Unreadable)
+}
+
+/// Adds a line for each quarantined file to the report in `folder`: when it was moved there,
+/// where it came from, its name in the folder, and why. The report is created with a header.
+pub fn append_report(folder: &Path, entries: &[(PathBuf, PathBuf, String)]) -> io::Result<()> {
+    let path = folder.join(REPORT_NAME);
+    let new = !path.exists();
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    let mut lines = String::new();
+    if new {
+        lines.push_str("date,original,quarantined as,reason\n");
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+    let date = metadata::format_timestamp(now);
+    for (src, dest, reason) in entries {
+        lines.push_str(&format!(
+            "{},{},{},{}\n",
+            date,
+            csv_field(&src.to_string_lossy()),
+            csv_field(&dest.file_name().unwrap_or_default().to_string_lossy()),
+            csv_field(reason)
+        ));
+    }
+    file.write_all(lines.as_bytes())
+}
+
+pub enum QuarantineAction {
+    Show(PathBuf),
+    // Move these files with the reason for each into `folder`.
+    Quarantine {
+        problems: Vec<(PathBuf, String)>,
+        folder: PathBuf,
+    },
+}
+
+/// State of the "Quarantine" window.
+#[derive(Default)]
+pub struct QuarantineTool {
+    scan: Option<ImageScan<Problem>>,
+}
+
+impl QuarantineTool {
+    pub fn poll(&mut self) {
+        if let Some(scan) = &mut self.scan {
+            scan.poll();
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.scan.as_ref().is_some_and(|scan| !scan.is_done())
+    }
+
+    /// The images checked and the images to check, while a scan is running.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.scan
+            .as_ref()
+            .filter(|scan| !scan.is_done())
+            .map(|scan| scan.progress())
+    }
+
+    /// Draws the window contents. `images` are the images that a new scan checks. `folder` is
+    /// the quarantine folder, kept in the settings.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        images: &[PathBuf],
+        folder: &mut Option<PathBuf>,
+    ) -> Option<QuarantineAction> {
+        ui.label(tr!("quarantine-intro"));
+        ui.horizontal(|ui| {
+            ui.label(tr!("quarantine-folder"));
+            if ui.button(tr!("choose")).clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    *folder = Some(path);
+                }
+            }
+            match folder {
+                Some(folder) => ui.label(folder.to_string_lossy()),
+                None => ui.label(tr!("not-selected")),
+            };
+        });
+        if ui
+            .add_enabled(
+                !self.is_busy(),
+                egui::Button::new(tr!("find-problem-files")),
+            )
+            .clicked()
+        {
+            self.scan = Some(ImageScan::start(images.to_vec(), |path| {
+                let problem = check(path)?;
+                log::warn!("Problem with {}: {}", path.display(), problem);
+                Some(problem)
+            }));
+        }
+        let scan = self.scan.as_ref()?;
+        let (checked, total) = scan.progress();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::ProgressBar::new(checked as f32 / total.max(1) as f32)
+                    .text(format!("{}/{}", checked, total)),
+            );
+            if !scan.is_done() && ui.button(tr!("cancel")).clicked() {
+                scan.cancel();
+            }
+        });
+
+        let found = scan.found();
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(tr!("problem-files", count = found.len()));
+            let target = folder
+                .as_ref()
+                .filter(|_| !found.is_empty() && scan.is_done());
+            let button = ui
+                .add_enabled(target.is_some(), egui::Button::new(tr!("quarantine-all")))
+                .on_disabled_hover_text(tr!("quarantine-all-hint"));
+            if let (true, Some(folder)) = (button.clicked(), target) {
+                action = Some(QuarantineAction::Quarantine {
+                    problems: found
+                        .iter()
+                        .map(|(path, problem)| (path.clone(), problem.to_string()))
+                        .collect(),
+                    folder: folder.clone(),
+                });
+            }
+        });
+        if matches!(action, Some(QuarantineAction::Quarantine { .. })) {
+            self.scan = None;
+            return action;
+        }
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::vertical().show_rows(ui, row_height, found.len(), |ui, rows| {
+            for (path, problem) in &found[rows] {
+                ui.horizontal(|ui| {
+                    if ui.button(tr!("show")).clicked() {
+                        action = Some(QuarantineAction::Show(path.clone()));
+                    }
+                    ui.label(path.file_name().unwrap_or_default().to_string_lossy());
+                    ui.colored_label(ui.visuals().error_fg_color, problem.to_string());
+                });
+            }
+        });
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let image = dir.join("image.png");
+        image::RgbImage::new(4, 4).save(&image).unwrap();
+        assert_eq!(check(&image), None);
+
+        fs::write(dir.join("empty.jpg"), b"").unwrap();
+        assert_eq!(check(&dir.join("empty.jpg")), Some(Problem::Empty));
+        fs::write(dir.join("program.jpg"), b"MZ\x90\x00 not an image").unwrap();
+        assert!(matches!(
+            check(&dir.join("program.jpg")),
+            Some(Problem::Suspicious(_))
+        ));
+        fs::copy(&image, dir.join("renamed.jpg")).unwrap();
+        assert_eq!(
+            check(&dir.join("renamed.jpg")),
+            Some(Problem::Suspicious(
+                "the contents are png, not jpg".to_string()
+            ))
+        );
+        let png = fs::read(&image).unwrap();
+        fs::write(dir.join("truncated.png"), &png[..png.len() / 2]).unwrap();
+        assert!(matches!(
+            check(&dir.join("truncated.png")),
+            Some(Problem::Unreadable(_))
+        ));
+    }
+
+    #[test]
+    fn append_report_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path();
+        let entry = |src: &str, dest: &str, reason: &str| {
+            (PathBuf::from(src), folder.join(dest), reason.to_string())
+        };
+        append_report(folder, &[entry("/photos/a.jpg", "a.jpg", "Empty file")]).unwrap();
+        append_report(
+            folder,
+            &[entry("/photos/b.png", "b (1).png", "Unreadable: bad, very")],
+        )
+        .unwrap();
+        let report = fs::read_to_string(folder.join(REPORT_NAME)).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "date,original,quarantined as,reason");
+        assert!(lines[1].ends_with(",/photos/a.jpg,a.jpg,Empty file"));
+        assert!(lines[2].ends_with(",/photos/b.png,b (1).png,\"Unreadable: bad, very\""));
+    }
+}
@@ -0,0 +1,346 @@
+use image::DynamicImage;
+use std::{
+    collections::HashSet,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use crate::{archive, color, heif, pdf};
+
+/// Camera RAW formats. They are shown using the JPEG preview that cameras embed in them.
+pub const RAW_EXTENSIONS: [&str; 5] = ["cr2", "nef", "arw", "dng", "raf"];
+
+// TIFF tags used to find the previews.
+const TAG_NEW_SUBFILE_TYPE: u16 = 0x00fe;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014a;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+
+// Guards against malformed files that link IFDs in a loop.
+const MAX_IFDS: usize = 64;
+
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW ";
+
+const JPEG_EXTENSIONS: [&str; 2] = ["jpg", "jpeg"];
+
+pub fn is_raw_file(path: &Path) -> bool {
+    has_extension(path, &RAW_EXTENSIONS)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.contains(&extension.to_lowercase().as_str()))
+}
+
+// Files next to `path` with the same stem and one of `extensions`, in lower or upper case.
+fn siblings(path: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    extensions
+        .iter()
+        .filter_map(|extension| {
+            [extension.to_string(), extension.to_uppercase()]
+                .into_iter()
+                .map(|extension| path.with_extension(extension))
+                .find(|sibling| sibling.exists())
+        })
+        .collect()
+}
+
+/// True if `path` is a RAW file with a JPEG of the same name next to it, e.g. IMG_1234.CR2 and
+/// IMG_1234.JPG. Only the JPEG of such a pair is listed.
+pub fn has_jpeg_pair(path: &Path) -> bool {
+    is_raw_file(path) && !siblings(path, &JPEG_EXTENSIONS).is_empty()
+}
+
+/// The RAW files that are paired with the JPEG at `path`.
+pub fn paired_raw_files(path: &Path) -> Vec<PathBuf> {
+    if !has_extension(path, &JPEG_EXTENSIONS) {
+        return Vec::new();
+    }
+    siblings(path, &RAW_EXTENSIONS)
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    // The first value of an entry. SHORT and LONG values are stored in the entry itself when
+    // there is only one.
+    fn value(&self, entry: usize) -> Option<u32> {
+        let value_type = self.u16_at(entry + 2)?;
+        match value_type {
+            3 => self.u16_at(entry + 8).map(u32::from),
+            4 | 13 => self.u32_at(entry + 8),
+            _ => None,
+        }
+    }
+
+    fn offsets(&self, entry: usize) -> Vec<u32> {
+        let count = self.u32_at(entry + 4).unwrap_or(0) as usize;
+        if count <= 1 {
+            return self.value(entry).into_iter().collect();
+        }
+        let Some(start) = self.u32_at(entry + 8) else {
+            return Vec::new();
+        };
+        (0..count.min(MAX_IFDS))
+            .filter_map(|i| self.u32_at(start as usize + i * 4))
+            .collect()
+    }
+}
+
+/// Byte ranges of the JPEG images in a TIFF based RAW file (CR2, NEF, ARW, DNG).
+fn tiff_jpegs(data: &[u8]) -> Vec<(usize, usize)> {
+    let little_endian = match data.get(0..4) {
+        Some(b"II*\0") => true,
+        Some(b"MM\0*") => false,
+        _ => return Vec::new(),
+    };
+    let tiff = Tiff {
+        data,
+        little_endian,
+    };
+    let mut jpegs = Vec::new();
+    let mut pending: Vec<u32> = tiff.u32_at(4).into_iter().collect();
+    let mut visited = HashSet::new();
+    while let Some(ifd) = pending.pop() {
+        if ifd == 0 || visited.len() >= MAX_IFDS || !visited.insert(ifd) {
+            continue;
+        }
+        let ifd = ifd as usize;
+        let Some(count) = tiff.u16_at(ifd) else {
+            continue;
+        };
+        let mut jpeg_offset = None;
+        let mut jpeg_length = None;
+        let mut strip_offset = None;
+        let mut strip_length = None;
+        let mut compression = None;
+        let mut subfile_type = None;
+        for i in 0..count as usize {
+            let entry = ifd + 2 + i * 12;
+            match tiff.u16_at(entry) {
+                Some(TAG_JPEG_OFFSET) => jpeg_offset = tiff.value(entry),
+                Some(TAG_JPEG_LENGTH) => jpeg_length = tiff.value(entry),
+                Some(TAG_STRIP_OFFSETS) if tiff.u32_at(entry + 4) == Some(1) => {
+                    strip_offset = tiff.value(entry)
+                }
+                Some(TAG_STRIP_BYTE_COUNTS) if tiff.u32_at(entry + 4) == Some(1) => {
+                    strip_length = tiff.value(entry)
+                }
+                Some(TAG_COMPRESSION) => compression = tiff.value(entry),
+                Some(TAG_NEW_SUBFILE_TYPE) => subfile_type = tiff.value(entry),
+                Some(TAG_SUB_IFDS) => pending.extend(tiff.offsets(entry)),
+                _ => {}
+            }
+        }
+        if let (Some(offset), Some(length)) = (jpeg_offset, jpeg_length) {
+            jpegs.push((offset as usize, length as usize));
+        }
+        // Old style (6) and new style (7) JPEG compression. The sensor data itself can be a
+        // lossless JPEG, which `is_decodable_jpeg()` filters out later.
+        let is_jpeg = matches!(compression, Some(6) | Some(7));
+        let is_full_raw = subfile_type == Some(0) && compression == Some(7);
+        if let (true, false, Some(offset), Some(length)) =
+            (is_jpeg, is_full_raw, strip_offset, strip_length)
+        {
+            jpegs.push((offset as usize, length as usize));
+        }
+        pending.extend(tiff.u32_at(ifd + 2 + count as usize * 12));
+    }
+    jpegs
+}
+
+/// Byte range of the JPEG preview in a Fujifilm RAF file.
+fn raf_jpeg(data: &[u8]) -> Option<(usize, usize)> {
+    if !data.starts_with(RAF_MAGIC) {
+        return None;
+    }
+    let offset = u32::from_be_bytes(data.get(84..88)?.try_into().ok()?);
+    let length = u32::from_be_bytes(data.get(88..92)?.try_into().ok()?);
+    Some((offset as usize, length as usize))
+}
+
+// True if `jpeg` is a baseline or progressive JPEG. Lossless JPEGs, which hold the sensor data
+// in some formats, cannot be decoded by the image crate.
+fn is_decodable_jpeg(jpeg: &[u8]) -> bool {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return false;
+    }
+    let mut position = 2;
+    while let Some([0xff, marker, high, low]) = jpeg.get(position..position + 4) {
+        match marker {
+            0xc0..=0xc2 => return true,
+            0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => return false,
+            0xda | 0xd9 => return false,
+            _ => position += 2 + u16::from_be_bytes([*high, *low]) as usize,
+        }
+    }
+    false
+}
+
+/// The largest JPEG preview embedded in the RAW file contents `data`.
+pub fn extract_preview(data: &[u8]) -> Option<&[u8]> {
+    let mut candidates = tiff_jpegs(data);
+    candidates.extend(raf_jpeg(data));
+    candidates.sort_by_key(|(_, length)| std::cmp::Reverse(*length));
+    candidates
+        .into_iter()
+        .filter_map(|(offset, length)| data.get(offset..offset.checked_add(length)?))
+        .find(|jpeg| is_decodable_jpeg(jpeg))
+}
+
+/// Decodes image file contents. RAW files are decoded from their embedded preview, PDF files
+/// show their first page, and HEIF files are decoded with libheif. Embedded color profiles are
+/// applied. `path` is only used to tell the format.
+pub fn decode_bytes(path: &Path, bytes: &[u8]) -> Result<DynamicImage, String> {
+    if pdf::is_pdf_file(path) {
+        let page = pdf::first_page(bytes)?;
+        return image::load_from_memory(&page).map_err(|e| e.to_string());
+    }
+    if heif::is_heif_file(path) {
+        return heif::decode(bytes);
+    }
+    let bytes = if is_raw_file(path) {
+        extract_preview(bytes).ok_or("No preview found in the RAW file.")?
+    } else {
+        bytes
+    };
+    color::decode(image::ImageReader::new(Cursor::new(bytes))).map_err(|e| e.to_string())
+}
+
+/// Decodes the image file at `path`, which may be in an archive. See `decode_bytes()`.
+pub fn decode_file(path: &Path) -> Result<DynamicImage, String> {
+    if is_raw_file(path)
+        || pdf::is_pdf_file(path)
+        || heif::is_heif_file(path)
+        || archive::split(path).is_some()
+    {
+        let bytes = archive::read(path).map_err(|e| e.to_string())?;
+        return decode_bytes(path, &bytes);
+    }
+    let reader = image::ImageReader::open(path).map_err(|e| e.to_string())?;
+    color::decode(reader).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Jpeg)
+            .unwrap();
+        bytes
+    }
+
+    // A little endian TIFF with one IFD that points to a small JPEG with JPEGInterchangeFormat
+    // and a SubIFD that holds a larger one in a strip.
+    fn fake_raw(small: &[u8], large: &[u8]) -> Vec<u8> {
+        let ifd0 = 8;
+        let ifd0_size = 2 + 3 * 12 + 4;
+        let sub_ifd = ifd0 + ifd0_size;
+        let sub_ifd_size = 2 + 3 * 12 + 4;
+        let small_offset = sub_ifd + sub_ifd_size;
+        let large_offset = small_offset + small.len();
+
+        let mut data = b"II*\0".to_vec();
+        data.extend((ifd0 as u32).to_le_bytes());
+        let entry = |data: &mut Vec<u8>, tag: u16, value_type: u16, value: u32| {
+            data.extend(tag.to_le_bytes());
+            data.extend(value_type.to_le_bytes());
+            data.extend(1u32.to_le_bytes());
+            data.extend(value.to_le_bytes());
+        };
+        data.extend(3u16.to_le_bytes());
+        entry(&mut data, TAG_SUB_IFDS, 4, sub_ifd as u32);
+        entry(&mut data, TAG_JPEG_OFFSET, 4, small_offset as u32);
+        entry(&mut data, TAG_JPEG_LENGTH, 4, small.len() as u32);
+        data.extend(0u32.to_le_bytes());
+
+        data.extend(3u16.to_le_bytes());
+        entry(&mut data, TAG_COMPRESSION, 3, 6);
+        entry(&mut data, TAG_STRIP_OFFSETS, 4, large_offset as u32);
+        entry(&mut data, TAG_STRIP_BYTE_COUNTS, 4, large.len() as u32);
+        data.extend(0u32.to_le_bytes());
+
+        data.extend(small);
+        data.extend(large);
+        data
+    }
+
+    #[test]
+    fn extract_preview_test() {
+        let small = jpeg(16, 16);
+        let large = jpeg(64, 32);
+        let raw = fake_raw(&small, &large);
+        assert_eq!(extract_preview(&raw), Some(large.as_slice()));
+
+        let image = decode_bytes(Path::new("a.CR2"), &raw).unwrap();
+        assert_eq!((image.width(), image.height()), (64, 32));
+        assert!(decode_bytes(Path::new("a.nef"), b"not a raw file").is_err());
+    }
+
+    #[test]
+    fn raf_preview_test() {
+        let preview = jpeg(8, 8);
+        let mut raf = RAF_MAGIC.to_vec();
+        raf.resize(100, 0);
+        raf[84..88].copy_from_slice(&100u32.to_be_bytes());
+        raf[88..92].copy_from_slice(&(preview.len() as u32).to_be_bytes());
+        raf.extend(&preview);
+        assert_eq!(extract_preview(&raf), Some(preview.as_slice()));
+    }
+
+    #[test]
+    fn pair_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("IMG_1.JPG"), b"").unwrap();
+        fs::write(dir.join("IMG_1.CR2"), b"").unwrap();
+        fs::write(dir.join("IMG_2.nef"), b"").unwrap();
+        fs::write(dir.join("IMG_3.jpg"), b"").unwrap();
+
+        assert!(has_jpeg_pair(&dir.join("IMG_1.CR2")));
+        assert!(!has_jpeg_pair(&dir.join("IMG_2.nef")));
+        assert_eq!(
+            paired_raw_files(&dir.join("IMG_1.JPG")),
+            vec![dir.join("IMG_1.CR2")]
+        );
+        assert!(paired_raw_files(&dir.join("IMG_3.jpg")).is_empty());
+        assert!(paired_raw_files(&dir.join("IMG_1.CR2")).is_empty());
+    }
+
+    #[test]
+    fn is_decodable_jpeg_test() {
+        assert!(is_decodable_jpeg(&jpeg(4, 4)));
+        // SOF3, lossless.
+        assert!(!is_decodable_jpeg(&[0xff, 0xd8, 0xff, 0xc3, 0x00, 0x02]));
+        assert!(!is_decodable_jpeg(b"not a jpeg"));
+    }
+}
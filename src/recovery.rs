@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, TryLockError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A move that was waiting to be retried.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingMove {
+    pub src: PathBuf,
+    pub dest_dir: PathBuf,
+}
+
+/// Where the session stood, kept up to date while the app runs.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub folders: Vec<PathBuf>,
+    pub image: Option<PathBuf>,
+    pub index: usize,
+    pub pending: Vec<PendingMove>,
+}
+
+/// The recovery file: the session when the app crashed, and why it crashed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Crash {
+    pub snapshot: Snapshot,
+    pub message: String,
+    // Unix time in seconds.
+    pub time: i64,
+}
+
+// The session of this process, for the panic hook.
+static SNAPSHOT: Mutex<Option<Snapshot>> = Mutex::new(None);
+
+/// Records where the session stands. Cheap when nothing changed.
+pub fn update(snapshot: Snapshot) {
+    let mut current = SNAPSHOT.lock().unwrap();
+    if current.as_ref() != Some(&snapshot) {
+        *current = Some(snapshot);
+    }
+}
+
+fn current_snapshot() -> Option<Snapshot> {
+    // The panic may have happened while the snapshot was being updated.
+    match SNAPSHOT.try_lock() {
+        Ok(snapshot) => snapshot.clone(),
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+pub fn write(path: &Path, crash: &Crash) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(crash)?)
+}
+
+/// The crash recorded at `path`, if the app crashed last time.
+pub fn read(path: &Path) -> io::Result<Option<Crash>> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn clear(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Writes the session to `path` when the UI thread panics, on top of the default message.
+/// Panics of the background threads only lose their own work and are left out.
+pub fn install_panic_hook(path: PathBuf) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if std::thread::current().name() != Some("main") {
+            return;
+        }
+        let Some(snapshot) = current_snapshot() else {
+            return;
+        };
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs() as i64);
+        let crash = Crash {
+            snapshot,
+            message: info.to_string(),
+            time,
+        };
+        match write(&path, &crash) {
+            Ok(()) => eprintln!("Saved the session to {}", path.display()),
+            Err(e) => eprintln!("Failed to save the session to {}: {}", path.display(), e),
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovery_file_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("recovery.json");
+        assert_eq!(read(&path).unwrap(), None);
+
+        let crash = Crash {
+            snapshot: Snapshot {
+                folders: vec![PathBuf::from("/photos/inbox")],
+                image: Some(PathBuf::from("/photos/inbox/b.png")),
+                index: 1,
+                pending: vec![PendingMove {
+                    src: PathBuf::from("/photos/inbox/a.png"),
+                    dest_dir: PathBuf::from("/nas/cats"),
+                }],
+            },
+            message: "panicked at src/main.rs:1:1".to_string(),
+            time: 86400,
+        };
+        write(&path, &crash).unwrap();
+        assert_eq!(read(&path).unwrap(), Some(crash));
+        clear(&path).unwrap();
+        assert_eq!(read(&path).unwrap(), None);
+        clear(&path).unwrap();
+
+        fs::write(&path, "{").unwrap();
+        assert!(read(&path).is_err());
+    }
+}
@@ -0,0 +1,476 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{
+    file_ops, flatten,
+    i18n::tr,
+    plan::{csv_field, parse_csv, PlannedMove},
+};
+
+const HEADER: [&str; 3] = ["source", "destination", "hash"];
+
+/// A move as written to an exported move log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoggedMove {
+    pub src: PathBuf,
+    // The full path the file was moved to.
+    pub dest: PathBuf,
+    // Of the contents, to tell files of the same name apart on the other copy.
+    pub hash: Option<String>,
+}
+
+/// The moves as CSV with a "source,destination,hash" header.
+pub fn to_csv(moves: &[LoggedMove]) -> String {
+    let mut csv = format!("{}\n", HEADER.join(","));
+    for logged in moves {
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            csv_field(&logged.src.to_string_lossy()),
+            csv_field(&logged.dest.to_string_lossy()),
+            logged.hash.as_deref().unwrap_or_default()
+        ));
+    }
+    csv
+}
+
+/// Reads a move log written by `to_csv()`.
+pub fn parse(text: &str) -> Result<Vec<LoggedMove>, String> {
+    let mut records = parse_csv(text).into_iter();
+    if records.next().is_none_or(|header| header != HEADER) {
+        return Err(tr!("not-a-move-log", header = HEADER.join(",")));
+    }
+    records
+        .enumerate()
+        .map(|(i, record)| match record.as_slice() {
+            [src, dest, hash] if !src.is_empty() && !dest.is_empty() => Ok(LoggedMove {
+                src: PathBuf::from(src),
+                dest: PathBuf::from(dest),
+                hash: Some(hash.clone()).filter(|hash| !hash.is_empty()),
+            }),
+            _ => Err(tr!("line-not-a-move", line = i + 2)),
+        })
+        .collect()
+}
+
+/// The deepest folder that has the sources and destinations of all `moves` under it: the
+/// library the log was made in.
+pub fn common_root(moves: &[LoggedMove]) -> PathBuf {
+    let mut folders = moves
+        .iter()
+        .flat_map(|logged| [&logged.src, &logged.dest])
+        .filter_map(|path| path.parent());
+    let Some(first) = folders.next() else {
+        return PathBuf::new();
+    };
+    let mut root = first.to_path_buf();
+    for folder in folders {
+        while !folder.starts_with(&root) {
+            if !root.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+    root
+}
+
+/// What replaying a log on another copy of the library would do.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayPlan {
+    // Each image of the copy goes where its last move in the log took it.
+    pub moves: Vec<PlannedMove>,
+    // Images that already are where the log took them.
+    pub in_place: usize,
+    // Sources in the log that are not in the copy.
+    pub missing: Vec<PathBuf>,
+    // Sources in the log that match more than one image of the copy.
+    pub ambiguous: Vec<PathBuf>,
+    // Sources in the log that were moved under another name, by a rename or to keep both of
+    // two files. The moves only keep names.
+    pub renamed: Vec<PathBuf>,
+}
+
+/// Plans the moves of `log` on the copy of the library at `copy`, whose images are `images`.
+/// Images are found by their file name. Where the log has a hash, it must match `hash` of the
+/// image, and where several images still match, the one at the same place in the copy is taken.
+/// Moves that changed the name are left to the user.
+pub fn plan(
+    log: &[LoggedMove],
+    copy: &Path,
+    images: &[PathBuf],
+    hash: impl Fn(&Path) -> Option<String>,
+) -> ReplayPlan {
+    let root = common_root(log);
+    let in_copy = |path: &Path| copy.join(path.strip_prefix(&root).unwrap_or(path));
+    let mut by_name: HashMap<OsString, Vec<PathBuf>> = HashMap::new();
+    for image in images {
+        if let Some(name) = image.file_name() {
+            by_name
+                .entry(name.to_os_string())
+                .or_default()
+                .push(image.clone());
+        }
+    }
+    let mut hashes: HashMap<PathBuf, Option<String>> = HashMap::new();
+    // Where each image is after the moves planned so far.
+    let mut current: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut destinations: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut plan = ReplayPlan::default();
+    for logged in log {
+        let Some(name) = logged.src.file_name() else {
+            continue;
+        };
+        if logged.dest.file_name() != Some(name) {
+            plan.renamed.push(logged.src.clone());
+            continue;
+        }
+        let mut candidates: Vec<&PathBuf> = by_name.get(name).into_iter().flatten().collect();
+        if let Some(expected) = &logged.hash {
+            candidates.retain(|image| {
+                hashes
+                    .entry(image.to_path_buf())
+                    .or_insert_with(|| hash(image))
+                    .as_ref()
+                    == Some(expected)
+            });
+        }
+        let at = |image: &PathBuf| current.get(image).unwrap_or(image).clone();
+        let image = match candidates.as_slice() {
+            [] => {
+                plan.missing.push(logged.src.clone());
+                continue;
+            }
+            [image] => (*image).clone(),
+            _ => {
+                let expected = in_copy(&logged.src);
+                match candidates.iter().find(|image| at(image) == expected) {
+                    Some(image) => (*image).clone(),
+                    None => {
+                        plan.ambiguous.push(logged.src.clone());
+                        continue;
+                    }
+                }
+            }
+        };
+        let dest_dir = in_copy(logged.dest.parent().unwrap_or(Path::new("")));
+        current.insert(image.clone(), dest_dir.join(name));
+        destinations.insert(image, dest_dir);
+    }
+    let mut moves: Vec<PlannedMove> = destinations
+        .into_iter()
+        .map(|(src, dest_dir)| PlannedMove { src, dest_dir })
+        .collect();
+    moves.sort_by(|a, b| a.src.cmp(&b.src));
+    for planned in moves {
+        if planned.src.parent() == Some(planned.dest_dir.as_path()) {
+            plan.in_place += 1;
+        } else {
+            plan.moves.push(planned);
+        }
+    }
+    plan
+}
+
+/// What the user asked for in the "Move log" window.
+pub enum ReplayAction {
+    // Save the moves of this session to a file.
+    Export,
+    // Carry out these moves in the copy of the library at `destination`.
+    Start {
+        moves: Vec<PlannedMove>,
+        destination: PathBuf,
+    },
+}
+
+/// State of the "Move log" window.
+#[derive(Default)]
+pub struct ReplayTool {
+    log: Option<(PathBuf, Vec<LoggedMove>)>,
+    copy: Option<PathBuf>,
+    error: Option<String>,
+    // Finding and hashing the images of a large library takes a while.
+    receiver: Option<mpsc::Receiver<Result<ReplayPlan, String>>>,
+    plan: Option<ReplayPlan>,
+}
+
+impl ReplayTool {
+    fn open_log(&mut self, path: PathBuf) {
+        self.plan = None;
+        match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| parse(&text))
+        {
+            Ok(moves) => {
+                self.log = Some((path, moves));
+                self.error = None;
+            }
+            Err(e) => {
+                self.log = None;
+                self.error = Some(tr!(
+                    "read-move-log-failed",
+                    path = path.display().to_string(),
+                    error = e
+                ));
+            }
+        }
+    }
+
+    fn start_preview(&mut self, log: Vec<LoggedMove>, copy: PathBuf) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // Nothing is left out: no folder is named "".
+            let result = flatten::find_nested_images(&copy, Path::new(""))
+                .map(|images| plan(&log, &copy, &images, |path| file_ops::hash_file(path).ok()))
+                .map_err(|e| tr!("list-images-failed", error = e.to_string()));
+            let _ = sender.send(result);
+        });
+        self.receiver = Some(receiver);
+        self.plan = None;
+        self.error = None;
+    }
+
+    /// Collects the result of a running preview.
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Ok(plan)) => self.plan = Some(plan),
+            Ok(Err(e)) => self.error = Some(e),
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {}
+        }
+        self.receiver = None;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Draws the window contents. `session_moves` is the number of moves that can be exported.
+    /// `can_move` is false in read-only mode.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        session_moves: usize,
+        can_move: bool,
+    ) -> Option<ReplayAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(tr!("session-moves", count = session_moves));
+            if ui
+                .add_enabled(session_moves > 0, egui::Button::new(tr!("export")))
+                .on_hover_text(tr!("export-move-log-hint"))
+                .clicked()
+            {
+                action = Some(ReplayAction::Export);
+            }
+        });
+        ui.separator();
+        ui.label(tr!("replay-intro"));
+        ui.horizontal(|ui| {
+            ui.label(tr!("move-log-label"));
+            if ui.button(tr!("choose")).clicked() {
+                if let Some(path) = FileDialog::new().add_filter("CSV", &["csv"]).pick_file() {
+                    self.open_log(path);
+                }
+            }
+            match &self.log {
+                Some((path, moves)) => ui.label(tr!(
+                    "move-log-file",
+                    path = path.display().to_string(),
+                    count = moves.len()
+                )),
+                None => ui.label(tr!("not-selected")),
+            };
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr!("library-copy"));
+            if ui.button(tr!("choose")).clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    self.copy = Some(path);
+                    self.plan = None;
+                }
+            }
+            match &self.copy {
+                Some(folder) => ui.label(folder.to_string_lossy()),
+                None => ui.label(tr!("not-selected")),
+            };
+        });
+        ui.horizontal(|ui| {
+            let ready = self.log.is_some() && self.copy.is_some() && !self.is_busy();
+            if ui
+                .add_enabled(ready, egui::Button::new(tr!("preview")))
+                .clicked()
+            {
+                if let (Some((_, log)), Some(copy)) = (&self.log, &self.copy) {
+                    self.start_preview(log.clone(), copy.clone());
+                }
+            }
+            if self.is_busy() {
+                ui.spinner();
+                ui.label(tr!("finding-images"));
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let (Some(plan), Some(copy)) = (&self.plan, &self.copy) else {
+            return action;
+        };
+        ui.label(tr!(
+            "replay-summary",
+            count = plan.moves.len(),
+            in_place = plan.in_place,
+            missing = plan.missing.len(),
+            ambiguous = plan.ambiguous.len()
+        ));
+        if !plan.renamed.is_empty() {
+            ui.label(tr!("replay-renamed", count = plan.renamed.len()));
+        }
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical().max_height(300.0).show_rows(
+            ui,
+            row_height,
+            plan.moves.len(),
+            |ui, rows| {
+                for planned in &plan.moves[rows] {
+                    let relative = |path: &Path| {
+                        path.strip_prefix(copy)
+                            .unwrap_or(path)
+                            .display()
+                            .to_string()
+                    };
+                    ui.label(format!(
+                        "{} → {}",
+                        relative(&planned.src),
+                        relative(&planned.dest_dir)
+                    ));
+                }
+            },
+        );
+        if ui
+            .add_enabled(
+                can_move && !plan.moves.is_empty(),
+                egui::Button::new(tr!("replay")),
+            )
+            .clicked()
+        {
+            let destination = copy.clone();
+            let moves = self.plan.take()?.moves;
+            action = Some(ReplayAction::Start { moves, destination });
+        }
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logged(src: &str, dest: &str, hash: Option<&str>) -> LoggedMove {
+        LoggedMove {
+            src: PathBuf::from(src),
+            dest: PathBuf::from(dest),
+            hash: hash.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn csv_test() {
+        let moves = vec![
+            logged("/photos/in/a.png", "/photos/cats/a.png", Some("abc")),
+            logged("/photos/in/b, c.png", "/photos/dogs/b, c.png", None),
+        ];
+        let csv = to_csv(&moves);
+        assert_eq!(parse(&csv), Ok(moves));
+        assert!(parse("source,destination\n/a,/b\n").is_err());
+        assert!(parse("source,destination,hash\n/a\n").is_err());
+        assert_eq!(
+            common_root(&[
+                logged("/photos/in/a.png", "/photos/cats/a.png", None),
+                logged("/photos/in/x/b.png", "/photos/in/b.png", None),
+            ]),
+            Path::new("/photos")
+        );
+    }
+
+    #[test]
+    fn plan_test() {
+        let log = vec![
+            logged("/photos/in/a.png", "/photos/cats/a.png", None),
+            logged("/photos/in/b.png", "/photos/dogs/b.png", Some("b")),
+            // Moved again later.
+            logged("/photos/cats/a.png", "/photos/best/a.png", None),
+            // Two images of this name, one of them at the same place.
+            logged("/photos/in/c.png", "/photos/cats/c.png", None),
+            logged("/photos/in/d.png", "/photos/cats/d.png", None),
+            logged("/photos/in/e.png", "/photos/in/e.png", None),
+            logged("/photos/in/f.png", "/photos/dogs/f.png", None),
+            // Renamed in place, and moved next to another g.png.
+            logged("/photos/in/e.png", "/photos/in/e2.png", None),
+            logged("/photos/in/g.png", "/photos/cats/g (1).png", None),
+        ];
+        let images: Vec<PathBuf> = [
+            "/mirror/in/a.png",
+            // Another file of the same name.
+            "/mirror/old/b.png",
+            "/mirror/in/b.png",
+            "/mirror/in/c.png",
+            "/mirror/old/c.png",
+            "/mirror/x/d.png",
+            "/mirror/y/d.png",
+            "/mirror/in/e.png",
+            "/mirror/in/g.png",
+        ]
+        .map(PathBuf::from)
+        .to_vec();
+        let hash = |path: &Path| {
+            Some(
+                if path.starts_with("/mirror/in") {
+                    "b"
+                } else {
+                    "x"
+                }
+                .to_string(),
+            )
+        };
+        let plan = plan(&log, Path::new("/mirror"), &images, hash);
+        let moves: Vec<(&str, &str)> = plan
+            .moves
+            .iter()
+            .map(|planned| {
+                (
+                    planned.src.to_str().unwrap(),
+                    planned.dest_dir.to_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                ("/mirror/in/a.png", "/mirror/best"),
+                ("/mirror/in/b.png", "/mirror/dogs"),
+                ("/mirror/in/c.png", "/mirror/cats"),
+            ]
+        );
+        assert_eq!(plan.in_place, 1);
+        assert_eq!(plan.missing, [PathBuf::from("/photos/in/f.png")]);
+        assert_eq!(plan.ambiguous, [PathBuf::from("/photos/in/d.png")]);
+        assert_eq!(
+            plan.renamed,
+            [
+                PathBuf::from("/photos/in/e.png"),
+                PathBuf::from("/photos/in/g.png")
+            ]
+        );
+    }
+}
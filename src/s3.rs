@@ -0,0 +1,326 @@
+use anyhow::{bail, Context, Result};
+use ring::{digest, hmac};
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::metadata;
+
+/// Keys and the service to use, found like the AWS command line tools do: in the environment,
+/// or else in the profile of ~/.aws/credentials and ~/.aws/config.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    region: String,
+    // e.g. "https://s3.eu-west-1.amazonaws.com", or the address of MinIO, R2, or B2.
+    endpoint: String,
+}
+
+// The "key = value" lines of `[section]` in an ini file.
+fn ini_section(contents: &str, section: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut in_section = false;
+    for line in contents.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name.trim() == section;
+        } else if let Some((key, value)) = line.split_once('=').filter(|_| in_section) {
+            values.insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    values
+}
+
+fn read_config(variable: &str, default: &str) -> String {
+    let path = match env::var_os(variable) {
+        Some(path) => PathBuf::from(path),
+        None => match directories::BaseDirs::new() {
+            Some(dirs) => dirs.home_dir().join(".aws").join(default),
+            None => return String::new(),
+        },
+    };
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+impl Credentials {
+    pub fn load() -> Result<Self> {
+        let profile = env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string());
+        let credentials = ini_section(
+            &read_config("AWS_SHARED_CREDENTIALS_FILE", "credentials"),
+            &profile,
+        );
+        let config_section = if profile == "default" {
+            profile.clone()
+        } else {
+            format!("profile {}", profile)
+        };
+        let config = ini_section(&read_config("AWS_CONFIG_FILE", "config"), &config_section);
+        Self::from_sources(
+            |name| env::var(name).ok().filter(|value| !value.is_empty()),
+            &credentials,
+            &config,
+        )
+    }
+
+    fn from_sources(
+        env: impl Fn(&str) -> Option<String>,
+        credentials: &HashMap<String, String>,
+        config: &HashMap<String, String>,
+    ) -> Result<Self> {
+        let find = |variable: &str, key: &str| {
+            env(variable).or_else(|| credentials.get(key).or_else(|| config.get(key)).cloned())
+        };
+        let (Some(access_key), Some(secret_key)) = (
+            find("AWS_ACCESS_KEY_ID", "aws_access_key_id"),
+            find("AWS_SECRET_ACCESS_KEY", "aws_secret_access_key"),
+        ) else {
+            bail!("No S3 keys. Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or add them to ~/.aws/credentials.");
+        };
+        let region = find("AWS_REGION", "region")
+            .or_else(|| env("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        let endpoint = find("AWS_ENDPOINT_URL_S3", "endpoint_url")
+            .or_else(|| env("AWS_ENDPOINT_URL"))
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        Ok(Self {
+            access_key,
+            secret_key,
+            session_token: find("AWS_SESSION_TOKEN", "aws_session_token"),
+            region,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+        .as_ref()
+        .to_vec()
+}
+
+// Encodes an object key for the path of a request. Slashes separate "folders" and stay.
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+// The Signature Version 4 of a request without a query, as the "SignedHeaders" list and the
+// signature. `headers` are lower case and sorted. See
+// https://docs.aws.amazon.com/AmazonS3/latest/API/sig-v4-header-based-auth.html
+fn sign(
+    secret_key: &str,
+    region: &str,
+    amz_date: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+) -> (String, String) {
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method, path, canonical_headers, signed_headers, payload_hash
+    );
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+    let key = ["s3", "aws4_request"].iter().fold(
+        hmac_sha256(
+            &hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date),
+            region,
+        ),
+        |key, part| hmac_sha256(&key, part),
+    );
+    (signed_headers, hex(&hmac_sha256(&key, &string_to_sign)))
+}
+
+// e.g. "20240131T120000Z".
+fn amz_date(now: SystemTime) -> String {
+    let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    metadata::format_timestamp(secs as i64)
+        .replace(['-', ':'], "")
+        .replace(' ', "T")
+        + "Z"
+}
+
+/// Uploads `file` as `key` in `bucket`. Fails with `AlreadyExists` rather than replace an
+/// object, where the service supports conditional writes.
+pub fn put(credentials: &Credentials, bucket: &str, key: &str, file: &Path) -> Result<()> {
+    // Path-style addressing, which every S3-compatible service understands.
+    let path = format!("/{}/{}", bucket, encode_key(key));
+    let host = credentials
+        .endpoint
+        .split_once("://")
+        .map_or(credentials.endpoint.as_str(), |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let amz_date = amz_date(SystemTime::now());
+    // Over HTTPS the contents need not be hashed, which would mean reading them twice.
+    let payload_hash = "UNSIGNED-PAYLOAD";
+    let mut headers = vec![
+        ("host", host.as_str()),
+        ("if-none-match", "*"),
+        ("x-amz-content-sha256", payload_hash),
+        ("x-amz-date", amz_date.as_str()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token));
+    }
+    let (signed_headers, signature) = sign(
+        &credentials.secret_key,
+        &credentials.region,
+        &amz_date,
+        "PUT",
+        &path,
+        &headers,
+        payload_hash,
+    );
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}/{}/s3/aws4_request, SignedHeaders={}, Signature={}",
+        credentials.access_key,
+        &amz_date[..8],
+        credentials.region,
+        signed_headers,
+        signature
+    );
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(10))
+        .timeout_read(Duration::from_secs(60))
+        .build();
+    let mut request = agent
+        .put(&format!("{}{}", credentials.endpoint, path))
+        .set("Authorization", &authorization)
+        .set("Content-Length", &fs::metadata(file)?.len().to_string());
+    // Host is set from the URL.
+    for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+        request = request.set(name, value);
+    }
+    match request.send(fs::File::open(file)?) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(412, _)) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("s3://{}/{} already exists", bucket, key),
+        )
+        .into()),
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            // S3 explains itself in <Message>.
+            let message = body
+                .split_once("<Message>")
+                .and_then(|(_, rest)| rest.split_once("</Message>"))
+                .map_or(body.as_str(), |(message, _)| message);
+            bail!("S3 answered {}: {}", status, message.trim())
+        }
+        Err(e) => Err(e).context("Failed to reach S3"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example of the AWS documentation for a GET request with a Range header.
+    #[test]
+    fn sign_test() {
+        let (signed_headers, signature) = sign(
+            "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY",
+            "us-east-1",
+            "20130524T000000Z",
+            "GET",
+            "/test.txt",
+            &[
+                ("host", "examplebucket.s3.amazonaws.com"),
+                ("range", "bytes=0-9"),
+                (
+                    "x-amz-content-sha256",
+                    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                ),
+                ("x-amz-date", "20130524T000000Z"),
+            ],
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        );
+        assert_eq!(signed_headers, "host;range;x-amz-content-sha256;x-amz-date");
+        assert_eq!(
+            signature,
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+    }
+
+    #[test]
+    fn amz_date_test() {
+        assert_eq!(
+            amz_date(UNIX_EPOCH + Duration::from_secs(1_369_353_600)),
+            "20130524T000000Z"
+        );
+        assert_eq!(encode_key("2024/a b+c.png"), "2024/a%20b%2Bc.png");
+    }
+
+    #[test]
+    fn credentials_test() {
+        let credentials = ini_section(
+            "[default]\naws_access_key_id = AKID\naws_secret_access_key = SECRET\n\
+             [work]\naws_access_key_id = OTHER\n",
+            "default",
+        );
+        let config = ini_section(
+            "[default]\nregion = eu-west-1\nendpoint_url = http://localhost:9000/\n",
+            "default",
+        );
+        let no_env = |_: &str| None;
+        assert_eq!(
+            Credentials::from_sources(no_env, &credentials, &config).unwrap(),
+            Credentials {
+                access_key: "AKID".to_string(),
+                secret_key: "SECRET".to_string(),
+                session_token: None,
+                region: "eu-west-1".to_string(),
+                endpoint: "http://localhost:9000".to_string(),
+            }
+        );
+
+        // The environment wins over the files.
+        let env = |name: &str| match name {
+            "AWS_ACCESS_KEY_ID" => Some("ENV".to_string()),
+            "AWS_SECRET_ACCESS_KEY" => Some("ENVSECRET".to_string()),
+            _ => None,
+        };
+        let loaded = Credentials::from_sources(env, &HashMap::new(), &HashMap::new()).unwrap();
+        assert_eq!(loaded.access_key, "ENV");
+        assert_eq!(loaded.endpoint, "https://s3.us-east-1.amazonaws.com");
+
+        assert!(Credentials::from_sources(no_env, &HashMap::new(), &config).is_err());
+    }
+}
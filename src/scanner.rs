@@ -0,0 +1,541 @@
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{archive, file_ops::FileStamp, hdr, heif, ignore::IgnoreRules, palette, pdf, raw};
+
+// Found images are sent to the UI in batches of this size.
+const BATCH_SIZE: usize = 500;
+
+pub fn is_image_file(path: &Path) -> bool {
+    let Some(extension) = path.extension() else {
+        return false;
+    };
+    let Some(ext_str) = extension.to_str() else {
+        return false;
+    };
+
+    let ext_lower = ext_str.to_lowercase();
+    // TODO: There is also image::ImageFormat.all() and then call can_read() to see if
+    // the current features allow reading the file. Then use extension_str() to get
+    // all the extensions for that image format.
+    let image_extensions = ["jpg", "jpeg", "png", "gif", "webp", "tif", "tiff"];
+    // Add more extensions as needed
+    image_extensions.contains(&ext_lower.as_str())
+        || raw::RAW_EXTENSIONS.contains(&ext_lower.as_str())
+        || hdr::HDR_EXTENSIONS.contains(&ext_lower.as_str())
+        || heif::HEIF_EXTENSIONS.contains(&ext_lower.as_str())
+        // Sorted together with the images, e.g. receipts that were scanned or downloaded.
+        || pdf::is_pdf_file(path)
+}
+
+/// The source folder that lists `path`: its folder, or the archive it is in.
+pub fn source_folder(path: &Path) -> Option<&Path> {
+    archive::split(path).map_or(path.parent(), |(archive, _)| Some(archive))
+}
+
+// It's likely that screenshot names are named by date it was taken. Sorting
+// and reversing it would show the latest images first.
+pub fn sort_image_paths(image_paths: &mut [PathBuf]) {
+    image_paths.sort_by(|a, b| b.cmp(a));
+}
+
+// Merges two lists that are sorted by `compare` into one sorted list.
+fn merge_sorted_by(
+    a: Vec<PathBuf>,
+    b: Vec<PathBuf>,
+    compare: impl Fn(&Path, &Path) -> CmpOrdering,
+) -> Vec<PathBuf> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let mut a = a.into_iter().peekable();
+    let mut b = b.into_iter().peekable();
+    loop {
+        let next = match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) => {
+                if compare(x, y) != CmpOrdering::Greater {
+                    a.next()
+                } else {
+                    b.next()
+                }
+            }
+            (Some(_), None) => a.next(),
+            (None, Some(_)) => b.next(),
+            (None, None) => break,
+        };
+        merged.extend(next);
+    }
+    merged
+}
+
+/// Merges two lists that are sorted with `sort_image_paths()` into one sorted list. The app uses
+/// `FolderOrder::merge()`, which is the same for a single folder.
+#[cfg(test)]
+pub fn merge_image_paths(a: Vec<PathBuf>, b: Vec<PathBuf>) -> Vec<PathBuf> {
+    // Both are in descending order.
+    merge_sorted_by(a, b, |x, y| y.cmp(x))
+}
+
+/// Order of the images when several source folders are queued: folder by folder in the order
+/// they were queued, and `sort_image_paths()` order inside each folder. With sizes, larger files
+/// come first inside each folder, with dates, newer images, and with colors, images go around the
+/// color wheel. Images without one go last.
+#[derive(Clone, Debug, Default)]
+pub struct FolderOrder {
+    folders: Vec<PathBuf>,
+    // In bytes.
+    sizes: Arc<HashMap<PathBuf, u64>>,
+    // Formatted like `metadata::format_timestamp()` so that they compare as strings.
+    dates: Arc<HashMap<PathBuf, String>>,
+    // The dominant color of each image.
+    colors: Arc<HashMap<PathBuf, [u8; 3]>>,
+}
+
+impl FolderOrder {
+    pub fn new(folders: Vec<PathBuf>) -> Self {
+        Self {
+            folders,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_dates(self, dates: Arc<HashMap<PathBuf, String>>) -> Self {
+        Self { dates, ..self }
+    }
+
+    pub fn with_sizes(self, sizes: Arc<HashMap<PathBuf, u64>>) -> Self {
+        Self { sizes, ..self }
+    }
+
+    pub fn with_colors(self, colors: Arc<HashMap<PathBuf, [u8; 3]>>) -> Self {
+        Self { colors, ..self }
+    }
+
+    pub fn sizes(&self) -> &Arc<HashMap<PathBuf, u64>> {
+        &self.sizes
+    }
+
+    pub fn folders(&self) -> &[PathBuf] {
+        &self.folders
+    }
+
+    pub fn dates(&self) -> &Arc<HashMap<PathBuf, String>> {
+        &self.dates
+    }
+
+    pub fn colors(&self) -> &Arc<HashMap<PathBuf, [u8; 3]>> {
+        &self.colors
+    }
+
+    // Position of the folder that contains `path`. Images outside the queue go last.
+    fn rank(&self, path: &Path) -> usize {
+        let parent = source_folder(path);
+        self.folders
+            .iter()
+            .position(|folder| Some(folder.as_path()) == parent)
+            .unwrap_or(self.folders.len())
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.rank(path) < self.folders.len()
+    }
+
+    pub fn compare(&self, a: &Path, b: &Path) -> CmpOrdering {
+        self.rank(a)
+            .cmp(&self.rank(b))
+            .then_with(|| self.sizes.get(b).cmp(&self.sizes.get(a)))
+            .then_with(|| self.dates.get(b).cmp(&self.dates.get(a)))
+            .then_with(|| {
+                let hue = |path| {
+                    self.colors
+                        .get(path)
+                        .map_or(u16::MAX, |c| palette::hue_key(*c))
+                };
+                hue(a).cmp(&hue(b))
+            })
+            .then_with(|| b.cmp(a))
+    }
+
+    pub fn sort(&self, paths: &mut [PathBuf]) {
+        paths.sort_by(|a, b| self.compare(a, b));
+    }
+
+    /// Merges two lists that are sorted with `sort()` into one sorted list.
+    pub fn merge(&self, a: Vec<PathBuf>, b: Vec<PathBuf>) -> Vec<PathBuf> {
+        merge_sorted_by(a, b, |x, y| self.compare(x, y))
+    }
+}
+
+/// Synchronous version of `FolderScan`.
+#[cfg(test)]
+pub fn get_image_paths(folder_path: &Path) -> Vec<PathBuf> {
+    let mut image_paths = Vec::new();
+    if let Ok(entries) = fs::read_dir(folder_path) {
+        for entry in entries {
+            let Ok(entry) = entry else { continue };
+            let path = entry.path();
+            if is_image_file(&path) {
+                image_paths.push(path);
+            }
+        }
+    }
+    sort_image_paths(&mut image_paths);
+    image_paths
+}
+
+/// The folder next to `folder`, in name order, that has images to sort. Offered once every image
+/// of `folder` has been sorted.
+pub fn next_folder(folder: &Path) -> Option<PathBuf> {
+    let parent = folder.parent()?;
+    let mut siblings: Vec<PathBuf> = fs::read_dir(parent)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| entry.path())
+        .filter(|path| path.as_path() > folder)
+        .collect();
+    siblings.sort();
+    siblings.into_iter().find(|sibling| {
+        fs::read_dir(sibling)
+            .is_ok_and(|entries| entries.flatten().any(|entry| is_image_file(&entry.path())))
+    })
+}
+
+// A found image, with its stamp if it could be read.
+type FoundFile = (PathBuf, Option<FileStamp>);
+
+/// Lists the images in a folder on a background thread, so that huge folders do not freeze the
+/// UI. Call `poll()` every frame to receive what was found so far.
+pub struct FolderScan {
+    folder: PathBuf,
+    receiver: mpsc::Receiver<Result<Vec<FoundFile>, String>>,
+    cancelled: Arc<AtomicBool>,
+    found: usize,
+    // How the found files looked when they were listed. Not known for images in archives.
+    stamps: HashMap<PathBuf, FileStamp>,
+    done: bool,
+    // Why the folder could not be listed, e.g. an unreachable network share.
+    error: Option<String>,
+    // Entries looked at so far, and when that last changed. A network share that stalls stops
+    // it from growing.
+    examined: Arc<AtomicUsize>,
+    last_examined: (usize, Instant),
+}
+
+impl FolderScan {
+    /// With `pair_raw`, RAW files that have a JPEG of the same name are left out. The JPEG
+    /// stands for both. So are the files that `ignore` matches, together with the patterns of
+    /// the ignore file of the folder.
+    pub fn start(folder: &Path, pair_raw: bool, ignore: IgnoreRules) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = cancelled.clone();
+        let examined = Arc::new(AtomicUsize::new(0));
+        let thread_examined = examined.clone();
+        let thread_folder = folder.to_path_buf();
+        thread::spawn(move || {
+            // Read here, as it blocks on a stalled network share too.
+            let ignore = ignore.with_folder_file(&thread_folder);
+            if archive::is_archive(&thread_folder) && thread_folder.is_file() {
+                let paths = archive::image_paths(&thread_folder)
+                    .map(|paths| paths.into_iter().map(|path| (path, None)).collect())
+                    .map_err(|e| e.to_string());
+                let _ = sender.send(paths);
+                return;
+            }
+            let entries = match fs::read_dir(&thread_folder) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::error!("Failed to read {}: {}", thread_folder.display(), e);
+                    let _ = sender.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            let mut batch = Vec::new();
+            for entry in entries {
+                if thread_cancelled.load(Ordering::Relaxed) {
+                    log::info!("Scan of {} cancelled.", thread_folder.display());
+                    return;
+                }
+                thread_examined.fetch_add(1, Ordering::Relaxed);
+                let Ok(entry) = entry else { continue };
+                let path = entry.path();
+                if !is_image_file(&path)
+                    || (pair_raw && raw::has_jpeg_pair(&path))
+                    || ignore.is_ignored(&thread_folder, &path, false)
+                {
+                    continue;
+                }
+                // Read along with the entry on most platforms.
+                let stamp = entry.metadata().ok().map(|m| FileStamp::from_metadata(&m));
+                batch.push((path, stamp));
+                if batch.len() >= BATCH_SIZE && sender.send(Ok(std::mem::take(&mut batch))).is_err()
+                {
+                    return;
+                }
+            }
+            if !batch.is_empty() {
+                let _ = sender.send(Ok(batch));
+            }
+        });
+        Self {
+            folder: folder.to_path_buf(),
+            receiver,
+            cancelled,
+            found: 0,
+            stamps: HashMap::new(),
+            done: false,
+            error: None,
+            examined,
+            last_examined: (0, Instant::now()),
+        }
+    }
+
+    /// Images found since the last call, sorted with `sort_image_paths()`.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut found = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Ok(batch)) => {
+                    for (path, stamp) in batch {
+                        if let Some(stamp) = stamp {
+                            self.stamps.insert(path.clone(), stamp);
+                        }
+                        found.push(path);
+                    }
+                }
+                Ok(Err(e)) => self.error = Some(e),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        self.found += found.len();
+        let examined = self.examined.load(Ordering::Relaxed);
+        if examined != self.last_examined.0 {
+            self.last_examined = (examined, Instant::now());
+        }
+        sort_image_paths(&mut found);
+        found
+    }
+
+    /// The stamps of the images that `poll()` returned so far, since the last call.
+    pub fn take_stamps(&mut self) -> HashMap<PathBuf, FileStamp> {
+        std::mem::take(&mut self.stamps)
+    }
+
+    /// Why the folder could not be listed, once the scan is done.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Whether the scan has not got any further for `timeout`.
+    pub fn is_stalled(&self, timeout: Duration) -> bool {
+        !self.done && self.last_examined.1.elapsed() > timeout
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    pub fn found(&self) -> usize {
+        self.found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn merge_image_paths_test() {
+        let mut a: Vec<PathBuf> = ["1.png", "3.png", "5.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let mut b: Vec<PathBuf> = ["2.png", "4.png"].iter().map(PathBuf::from).collect();
+        sort_image_paths(&mut a);
+        sort_image_paths(&mut b);
+        let merged = merge_image_paths(a, b);
+        let expected: Vec<PathBuf> = ["5.png", "4.png", "3.png", "2.png", "1.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn folder_order_test() {
+        let order = FolderOrder::new(vec![PathBuf::from("/b"), PathBuf::from("/a")]);
+        let mut paths: Vec<PathBuf> = ["/a/2.png", "/b/1.png", "/c/1.png", "/a/1.png", "/b/2.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        order.sort(&mut paths);
+        let expected: Vec<PathBuf> = ["/b/2.png", "/b/1.png", "/a/2.png", "/a/1.png", "/c/1.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(paths, expected);
+        assert!(order.contains(Path::new("/a/1.png")));
+        assert!(!order.contains(Path::new("/c/1.png")));
+
+        let merged = order.merge(
+            vec![PathBuf::from("/b/3.png"), PathBuf::from("/a/1.png")],
+            vec![PathBuf::from("/a/5.png")],
+        );
+        assert_eq!(
+            merged,
+            ["/b/3.png", "/a/5.png", "/a/1.png"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+
+        let dates = HashMap::from([
+            (PathBuf::from("/a/1.png"), "2024-01-01 00:00:00".to_string()),
+            (PathBuf::from("/a/2.png"), "2020-01-01 00:00:00".to_string()),
+        ]);
+        let order = order.with_dates(Arc::new(dates));
+        let mut paths: Vec<PathBuf> = ["/a/2.png", "/a/3.png", "/a/1.png"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        order.sort(&mut paths);
+        assert_eq!(
+            paths,
+            ["/a/1.png", "/a/2.png", "/a/3.png"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+
+        let sizes = HashMap::from([
+            (PathBuf::from("/a/3.png"), 10_000_000),
+            (PathBuf::from("/a/2.png"), 20),
+        ]);
+        let order = order.with_sizes(Arc::new(sizes));
+        order.sort(&mut paths);
+        assert_eq!(
+            paths,
+            ["/a/3.png", "/a/2.png", "/a/1.png"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+
+        // Red, then blue, then gray, then the image whose color is not known.
+        let colors = HashMap::from([
+            (PathBuf::from("/a/1.png"), [128, 128, 128]),
+            (PathBuf::from("/a/2.png"), [0, 0, 255]),
+            (PathBuf::from("/a/4.png"), [255, 0, 0]),
+        ]);
+        let order = FolderOrder::new(vec![PathBuf::from("/a")]).with_colors(Arc::new(colors));
+        paths.push(PathBuf::from("/a/4.png"));
+        order.sort(&mut paths);
+        assert_eq!(
+            paths,
+            ["/a/4.png", "/a/2.png", "/a/1.png", "/a/3.png"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn folder_scan_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for i in 0..(BATCH_SIZE + 10) {
+            fs::write(temp_dir.path().join(format!("{:04}.png", i)), b"").unwrap();
+        }
+        fs::write(temp_dir.path().join("notes.txt"), b"").unwrap();
+
+        let mut scan = FolderScan::start(temp_dir.path(), true, IgnoreRules::default());
+        let mut found = Vec::new();
+        let mut stamps = HashMap::new();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !scan.is_done() && Instant::now() < deadline {
+            found = merge_image_paths(found, scan.poll());
+            stamps.extend(scan.take_stamps());
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(scan.found(), BATCH_SIZE + 10);
+        assert_eq!(found, get_image_paths(temp_dir.path()));
+        assert_eq!(stamps.len(), found.len());
+        assert_eq!(stamps[&found[0]], FileStamp::of(&found[0]).unwrap());
+    }
+
+    #[test]
+    fn folder_scan_ignore_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for name in ["a.png", ".b.png", "c.tmp.png"] {
+            fs::write(temp_dir.path().join(name), b"").unwrap();
+        }
+        let ignore = IgnoreRules::new(false, "*.tmp.png");
+        let mut scan = FolderScan::start(temp_dir.path(), true, ignore);
+        let mut found = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !scan.is_done() && Instant::now() < deadline {
+            found = merge_image_paths(found, scan.poll());
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(found, vec![temp_dir.path().join("a.png")]);
+    }
+
+    #[test]
+    fn folder_scan_error_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut scan = FolderScan::start(
+            &temp_dir.path().join("unmounted"),
+            true,
+            IgnoreRules::default(),
+        );
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !scan.is_done() && Instant::now() < deadline {
+            assert!(scan.poll().is_empty());
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(scan.error().is_some());
+        assert!(!scan.is_stalled(Duration::ZERO));
+    }
+
+    #[test]
+    fn next_folder_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for folder in ["2024-01", "2024-02", "2024-03", "2024-04"] {
+            fs::create_dir(temp_dir.path().join(folder)).unwrap();
+        }
+        // Nothing to sort in 2024-02.
+        fs::write(temp_dir.path().join("2024-02/notes.txt"), b"").unwrap();
+        fs::write(temp_dir.path().join("2024-03/a.png"), b"").unwrap();
+        fs::write(temp_dir.path().join("2024-04/b.png"), b"").unwrap();
+        assert_eq!(
+            next_folder(&temp_dir.path().join("2024-01")),
+            Some(temp_dir.path().join("2024-03"))
+        );
+        assert_eq!(
+            next_folder(&temp_dir.path().join("2024-03")),
+            Some(temp_dir.path().join("2024-04"))
+        );
+        assert_eq!(next_folder(&temp_dir.path().join("2024-04")), None);
+    }
+}
@@ -0,0 +1,260 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::path::{Path, PathBuf};
+
+use crate::{batch::ImageScan, i18n::tr, metadata};
+
+// Words that screenshot tools put in the file names, in lower case: Android, Windows and most
+// apps, macOS before Mojave, CleanShot, and the Japanese versions of Windows and macOS.
+const NAME_PATTERNS: [&str; 5] = [
+    "screenshot",
+    "screen shot",
+    "cleanshot",
+    "スクリーンショット",
+    "画面収録",
+];
+
+// Resolutions of common monitors and phones, in landscape. Photos are rarely cropped to exactly
+// one of these.
+const SCREEN_SIZES: [(u32, u32); 24] = [
+    (1280, 720),
+    (1280, 800),
+    (1366, 768),
+    (1440, 900),
+    (1536, 864),
+    (1600, 900),
+    (1680, 1050),
+    (1920, 1080),
+    (1920, 1200),
+    (2048, 1536),
+    (2560, 1440),
+    (2560, 1600),
+    (2732, 2048),
+    (2880, 1800),
+    (3024, 1964),
+    (3456, 2234),
+    (3840, 2160),
+    (1334, 750),
+    (2208, 1242),
+    (2436, 1125),
+    (2532, 1170),
+    (2556, 1179),
+    (2400, 1080),
+    (2796, 1290),
+];
+
+/// Whether the file name says the image is a screenshot, e.g. "Screenshot_20240131-120000.png"
+/// or "Screen Shot 2019-01-31 at 12.00.00.png".
+fn has_screenshot_name(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let name = name.to_lowercase().replace('_', " ");
+    NAME_PATTERNS.iter().any(|pattern| name.contains(pattern))
+}
+
+fn is_screen_size((width, height): (u32, u32)) -> bool {
+    SCREEN_SIZES.contains(&(width, height)) || SCREEN_SIZES.contains(&(height, width))
+}
+
+/// Whether an image is likely a screenshot: its name says so, or it is a PNG that no camera took
+/// with the exact size of a common screen. `camera` is whether its EXIF data names a camera.
+pub fn looks_like_screenshot(path: &Path, camera: bool, dimensions: Option<(u32, u32)>) -> bool {
+    if has_screenshot_name(path) {
+        return true;
+    }
+    let png = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+    png && !camera && dimensions.is_some_and(is_screen_size)
+}
+
+/// `looks_like_screenshot()` of an image on disk. Only the headers are read.
+pub fn is_screenshot(path: &Path) -> bool {
+    if has_screenshot_name(path) {
+        return true;
+    }
+    let dimensions = image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok());
+    looks_like_screenshot(path, metadata::has_camera_exif(path), dimensions)
+}
+
+pub enum ScreenshotAction {
+    Show(PathBuf),
+    // Only show these images.
+    Review(Vec<PathBuf>),
+    MoveTo {
+        paths: Vec<PathBuf>,
+        folder: PathBuf,
+    },
+}
+
+/// State of the "Screenshots" window.
+#[derive(Default)]
+pub struct ScreenshotTool {
+    scan: Option<ImageScan<()>>,
+}
+
+impl ScreenshotTool {
+    pub fn poll(&mut self) {
+        if let Some(scan) = &mut self.scan {
+            scan.poll();
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.scan.as_ref().is_some_and(|scan| !scan.is_done())
+    }
+
+    /// The images checked and the images to check, while a scan is running.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.scan
+            .as_ref()
+            .filter(|scan| !scan.is_done())
+            .map(|scan| scan.progress())
+    }
+
+    /// Draws the window contents. `images` are the images that a new scan checks. `folder` is
+    /// where "Move all" sends the screenshots, kept in the settings.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        images: &[PathBuf],
+        folder: &mut Option<PathBuf>,
+    ) -> Option<ScreenshotAction> {
+        ui.label(tr!("screenshots-intro"));
+        ui.horizontal(|ui| {
+            ui.label(tr!("screenshots-folder"));
+            if ui.button(tr!("choose")).clicked() {
+                if let Some(path) = FileDialog::new().pick_folder() {
+                    *folder = Some(path);
+                }
+            }
+            match folder {
+                Some(folder) => ui.label(folder.to_string_lossy()),
+                None => ui.label(tr!("not-selected")),
+            };
+        });
+        if ui
+            .add_enabled(!self.is_busy(), egui::Button::new(tr!("find-screenshots")))
+            .clicked()
+        {
+            self.scan = Some(ImageScan::start(images.to_vec(), |path| {
+                is_screenshot(path).then_some(())
+            }));
+        }
+        let scan = self.scan.as_ref()?;
+        let (checked, total) = scan.progress();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::ProgressBar::new(checked as f32 / total.max(1) as f32)
+                    .text(format!("{}/{}", checked, total)),
+            );
+            if !scan.is_done() && ui.button(tr!("cancel")).clicked() {
+                scan.cancel();
+            }
+        });
+
+        let found = scan.found();
+        let paths = || found.iter().map(|(path, _)| path.clone()).collect();
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(tr!("screenshot-count", count = found.len()));
+            if ui
+                .add_enabled(!found.is_empty(), egui::Button::new(tr!("review")))
+                .on_hover_text(tr!("review-screenshots-hint"))
+                .clicked()
+            {
+                action = Some(ScreenshotAction::Review(paths()));
+            }
+            let target = folder.as_ref().filter(|_| !found.is_empty());
+            let button = ui
+                .add_enabled(target.is_some(), egui::Button::new(tr!("move-all")))
+                .on_disabled_hover_text(tr!("choose-screenshots-folder-first"));
+            if let (true, Some(folder)) = (button.clicked(), target) {
+                action = Some(ScreenshotAction::MoveTo {
+                    paths: paths(),
+                    folder: folder.clone(),
+                });
+            }
+        });
+        if matches!(action, Some(ScreenshotAction::MoveTo { .. })) {
+            // They are gone from the list once moved. Another scan finds what is left.
+            self.scan = None;
+            return action;
+        }
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::vertical().show_rows(ui, row_height, found.len(), |ui, rows| {
+            for (path, _) in &found[rows] {
+                ui.horizontal(|ui| {
+                    if ui.button(tr!("show")).clicked() {
+                        action = Some(ScreenshotAction::Show(path.clone()));
+                    }
+                    ui.label(path.file_name().unwrap_or_default().to_string_lossy());
+                });
+            }
+        });
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_like_screenshot_test() {
+        let path = Path::new;
+        for name in [
+            "Screenshot_20240131-120000.png",
+            "Screen Shot 2019-01-31 at 12.00.00.png",
+            "screen_shot.jpg",
+            "CleanShot 2024-01-31.png",
+            "スクリーンショット 2024-01-31 12.00.00.png",
+        ] {
+            assert!(looks_like_screenshot(path(name), true, None), "{}", name);
+        }
+        // The size of a screen, in either orientation.
+        assert!(looks_like_screenshot(
+            path("IMG_0001.PNG"),
+            false,
+            Some((1170, 2532))
+        ));
+        assert!(looks_like_screenshot(
+            path("image.png"),
+            false,
+            Some((1920, 1080))
+        ));
+        // Taken by a camera, not the size of a screen, or not a PNG.
+        assert!(!looks_like_screenshot(
+            path("image.png"),
+            true,
+            Some((1920, 1080))
+        ));
+        assert!(!looks_like_screenshot(
+            path("image.png"),
+            false,
+            Some((1919, 1080))
+        ));
+        assert!(!looks_like_screenshot(
+            path("IMG_0001.JPG"),
+            false,
+            Some((1920, 1080))
+        ));
+        assert!(!looks_like_screenshot(path("image.png"), false, None));
+    }
+
+    #[test]
+    fn is_screenshot_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let screen = temp_dir.path().join("image.png");
+        image::RgbImage::new(1366, 768).save(&screen).unwrap();
+        assert!(is_screenshot(&screen));
+        let other = temp_dir.path().join("other.png");
+        image::RgbImage::new(1000, 768).save(&other).unwrap();
+        assert!(!is_screenshot(&other));
+        assert!(!is_screenshot(&temp_dir.path().join("missing.png")));
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::{anyhow, Result};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use std::path::{Path, PathBuf};
+
+use crate::metadata::ImageMetadata;
+
+// A script that loops forever is stopped after this many operations instead of freezing the app.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A user's Rhai script with functions that the app calls at certain points. All of them are
+/// optional:
+///
+/// - `on_image_shown(path)` when an image is shown.
+/// - `suggest_destination(path, exif)` returns the folder the image should go to, or "" for
+///   none. `exif` has `taken`, `description`, `latitude` and `longitude` when they are known.
+/// - `on_move(src, dest)` after a file was moved or renamed.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    path: PathBuf,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::compile(&source, path)
+    }
+
+    fn compile(source: &str, path: &Path) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| log::info!("Script: {}", text));
+        engine.on_debug(|text, _, position| log::debug!("Script at {}: {}", position, text));
+        let ast = engine
+            .compile(source)
+            .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        Ok(Self {
+            engine,
+            ast,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn has_function(&self, name: &str, params: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == params)
+    }
+
+    // Calls `name` if the script defines it. Ok(None) if it does not.
+    fn call(&self, name: &str, args: impl rhai::FuncArgs) -> Result<Option<Dynamic>> {
+        let mut args_vec = Vec::new();
+        args.parse(&mut args_vec);
+        if !self.has_function(name, args_vec.len()) {
+            return Ok(None);
+        }
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args_vec)
+            .map_err(|e| anyhow!("{} in {}: {}", name, self.path.display(), e))?;
+        Ok(Some(result))
+    }
+
+    pub fn on_image_shown(&self, path: &Path) -> Result<()> {
+        self.call("on_image_shown", (path_arg(path),))?;
+        Ok(())
+    }
+
+    pub fn suggest_destination(
+        &self,
+        path: &Path,
+        metadata: &ImageMetadata,
+    ) -> Result<Option<PathBuf>> {
+        let Some(result) =
+            self.call("suggest_destination", (path_arg(path), exif_map(metadata)))?
+        else {
+            return Ok(None);
+        };
+        // Returning nothing at all is fine too.
+        if result.is_unit() {
+            return Ok(None);
+        }
+        let folder = result
+            .into_string()
+            .map_err(|kind| anyhow!("suggest_destination returned {} instead of a string", kind))?;
+        Ok((!folder.is_empty()).then(|| PathBuf::from(folder)))
+    }
+
+    pub fn on_move(&self, src: &Path, dest: &Path) -> Result<()> {
+        self.call("on_move", (path_arg(src), path_arg(dest)))?;
+        Ok(())
+    }
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn exif_map(metadata: &ImageMetadata) -> Map {
+    let mut map = Map::new();
+    if let Some(taken) = &metadata.taken {
+        map.insert("taken".into(), taken.clone().into());
+    }
+    if let Some(description) = &metadata.description {
+        map.insert("description".into(), description.clone().into());
+    }
+    if let Some(gps) = &metadata.gps {
+        map.insert("latitude".into(), gps.latitude.into());
+        map.insert("longitude".into(), gps.longitude.into());
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hooks_test() {
+        let script = Script::compile(
+            r#"
+            fn suggest_destination(path, exif) {
+                if path.ends_with(".png") && "taken" in exif {
+                    return "/sorted/" + exif.taken.sub_string(0, 4);
+                }
+                ""
+            }
+            fn on_move(src, dest) { print(src + " -> " + dest); }
+            "#,
+            Path::new("test.rhai"),
+        )
+        .unwrap();
+        let metadata = ImageMetadata {
+            taken: Some("2024-01-31 12:34:56".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            script
+                .suggest_destination(Path::new("a.png"), &metadata)
+                .unwrap(),
+            Some(PathBuf::from("/sorted/2024"))
+        );
+        assert_eq!(
+            script
+                .suggest_destination(Path::new("a.jpg"), &metadata)
+                .unwrap(),
+            None
+        );
+        assert!(script
+            .on_move(Path::new("a.png"), Path::new("b.png"))
+            .is_ok());
+        // Not defined in the script.
+        assert!(script.on_image_shown(Path::new("a.png")).is_ok());
+
+        let endless = Script::compile(
+            "fn on_image_shown(path) { loop {} }",
+            Path::new("test.rhai"),
+        )
+        .unwrap();
+        assert!(endless.on_image_shown(Path::new("a.png")).is_err());
+        assert!(Script::compile("fn broken(", Path::new("test.rhai")).is_err());
+    }
+}
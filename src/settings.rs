@@ -0,0 +1,536 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::i18n::{tr, Language};
+use crate::theme::{self, ImageBackground};
+
+/// Order of the images inside each source folder.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    #[default]
+    FileName,
+    // Newest first. Uses EXIF or Google Takeout metadata, or else the modification time.
+    DateTaken,
+    // Largest files first, to free disk space quickly.
+    Largest,
+    // Around the color wheel by the dominant color, grays last.
+    Color,
+}
+
+/// Which image is shown after the current one was moved or deleted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AfterMove {
+    // The one after it, wrapping around after the last image like `j`.
+    #[default]
+    Advance,
+    // The one that slid into its place, which is the one before it after the last image.
+    Stay,
+    // The one before it.
+    Back,
+}
+
+/// What to do with symbolic links to images in the source folders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymlinkPolicy {
+    // Leave them out, so that only real files are sorted.
+    Skip,
+    // Show them and move the file they point to. The link is removed.
+    Follow,
+    // Show them and move the link itself, still pointing to the same file.
+    #[default]
+    MoveLink,
+}
+
+/// What a move does when the name of the image is taken at the destination.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollisionPolicy {
+    // Move it with a new name, e.g. "a (1).png".
+    #[default]
+    KeepBoth,
+    // Leave it where it is.
+    Skip,
+}
+
+/// User preferences. Persisted with eframe's storage so they survive restarts.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Images with a side longer than this are downscaled before they are uploaded to the GPU.
+    pub max_texture_side: u32,
+    // Convert images with a color profile, e.g. the Display P3 photos of phones, to sRGB.
+    pub color_management: bool,
+    // Number of images before and after the current one that are kept loaded.
+    pub keep_window: usize,
+    // Approximate memory for decoded images. Least recently viewed images are unloaded first
+    // when it is exceeded.
+    pub memory_budget_mb: usize,
+    pub thumbnail_cache_mb: u64,
+    // Keep a database of seen images for instant reopening, search, and duplicate detection.
+    pub use_library_index: bool,
+    // Show IMG_1234.CR2 and IMG_1234.JPG as one image and move or delete them together.
+    pub pair_raw_with_jpeg: bool,
+    // Show the shots of a burst as one stack that is moved as a whole.
+    pub stack_bursts: bool,
+    pub sort_order: SortOrder,
+    // Show files that start with a dot, or are hidden on Windows.
+    pub include_hidden_files: bool,
+    // Files to leave out of every folder, one glob pattern per line.
+    pub ignore_patterns: String,
+    pub symlinks: SymlinkPolicy,
+    // When the name of an image is taken at the destination, by a file of another case too
+    // where the filesystem ignores case.
+    pub collisions: CollisionPolicy,
+    // Keep the mark macOS puts on downloaded files when they are copied to another drive.
+    pub keep_download_flag: bool,
+    // Seconds to wait for a folder, e.g. on a network share, before it counts as unreachable.
+    pub network_timeout_secs: u64,
+    // Download a map of where the photo was taken for the info window.
+    pub show_map_tiles: bool,
+    // Images whose sharpness score is below this are flagged as blurry.
+    pub blur_threshold: f64,
+    // Where "Move all" in the screenshots window sends them.
+    pub screenshot_folder: Option<PathBuf>,
+    // Where empty, unreadable and suspicious files are moved, with a report of why.
+    pub quarantine_folder: Option<PathBuf>,
+    // A destination for images to throw away later. Where each came from is logged in it.
+    pub discard_folder: Option<PathBuf>,
+    // Shown as a folder tree instead of the list of destinations when `show_album_tree` is on.
+    pub albums_root: Option<PathBuf>,
+    pub show_album_tree: bool,
+    // The two sides of "Sync with mirror".
+    pub sync_library: Option<PathBuf>,
+    pub sync_mirror: Option<PathBuf>,
+    // ONNX models that find faces and tell them apart, for the faces window.
+    pub face_detector_model: Option<PathBuf>,
+    pub face_embedder_model: Option<PathBuf>,
+    // Offer the folders of other images with the same tags as destinations.
+    pub suggest_tag_destinations: bool,
+    // Skip images that were kept in place with Enter in an earlier pass.
+    pub hide_kept_images: bool,
+    // Go from the last image back to the first. Without it, the pass ends at the last image.
+    pub wrap_around: bool,
+    // Sum up what was done when the end of the folder is reached.
+    pub show_pass_summary: bool,
+    pub after_move: AfterMove,
+    // Thumbnails of the last moves shown under the image, to catch a wrong key. 0 hides them.
+    pub recent_moves: usize,
+    // Ask before moving to destinations marked "Ask first".
+    pub confirm_dangerous_destinations: bool,
+    // Ask before moving more images than this at once. 0 never asks.
+    pub confirm_batch_size: usize,
+    // Ask before moving an image to a destination that already has a file with the same contents.
+    pub confirm_identical: bool,
+    // Rhai script with hooks for custom sorting logic.
+    pub script_path: Option<PathBuf>,
+    pub theme: egui::ThemePreference,
+    // Color of selections and links instead of the theme's blue.
+    pub accent_color: Option<[u8; 3]>,
+    // Shown through transparent pixels, e.g. of PNG screenshots.
+    pub image_background: ImageBackground,
+    // Zoom of the whole UI on top of the scaling of the OS. Ctrl + and Ctrl - change it too.
+    pub ui_scale: f32,
+    // Size of body text in points. Headings and small text scale with it.
+    pub font_size: f32,
+    pub language: Language,
+    // Show a tray icon with the number of images waiting in `watched_folder`.
+    pub tray_icon: bool,
+    pub watched_folder: Option<PathBuf>,
+    // Show the size, format, dates, and path of the image under its name.
+    pub show_properties: bool,
+    // Write what the app does to a log file, kept for a few days' worth of sorting.
+    pub log_to_file: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_texture_side: 4096,
+            color_management: true,
+            keep_window: 3,
+            memory_budget_mb: 1024,
+            thumbnail_cache_mb: 512,
+            use_library_index: false,
+            pair_raw_with_jpeg: true,
+            stack_bursts: true,
+            sort_order: SortOrder::default(),
+            include_hidden_files: false,
+            ignore_patterns: String::new(),
+            symlinks: SymlinkPolicy::default(),
+            collisions: CollisionPolicy::default(),
+            keep_download_flag: true,
+            network_timeout_secs: 5,
+            show_map_tiles: false,
+            blur_threshold: 100.0,
+            screenshot_folder: None,
+            quarantine_folder: None,
+            discard_folder: None,
+            albums_root: None,
+            show_album_tree: false,
+            sync_library: None,
+            sync_mirror: None,
+            face_detector_model: None,
+            face_embedder_model: None,
+            suggest_tag_destinations: true,
+            hide_kept_images: true,
+            wrap_around: true,
+            show_pass_summary: true,
+            after_move: AfterMove::default(),
+            recent_moves: 8,
+            confirm_dangerous_destinations: true,
+            confirm_batch_size: 20,
+            confirm_identical: true,
+            script_path: None,
+            theme: egui::ThemePreference::System,
+            accent_color: None,
+            image_background: ImageBackground::default(),
+            ui_scale: 1.0,
+            font_size: theme::DEFAULT_FONT_SIZE,
+            language: Language::default(),
+            tray_icon: false,
+            watched_folder: None,
+            show_properties: false,
+            log_to_file: true,
+        }
+    }
+}
+
+impl Settings {
+    const STORAGE_KEY: &'static str = "settings";
+
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|storage| eframe::get_value(storage, Self::STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    /// Whether settings were saved before, i.e. this is not the first start.
+    pub fn is_saved(storage: Option<&dyn eframe::Storage>) -> bool {
+        storage.is_some_and(|storage| storage.get_string(Self::STORAGE_KEY).is_some())
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, Self::STORAGE_KEY, self);
+    }
+
+    pub fn memory_budget_bytes(&self) -> usize {
+        self.memory_budget_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn thumbnail_cache_bytes(&self) -> u64 {
+        self.thumbnail_cache_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// Draws the settings form.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("settings_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let label = ui.label(tr!("settings-language"));
+                egui::ComboBox::from_id_salt("language")
+                    .selected_text(self.language.name())
+                    .show_ui(ui, |ui| {
+                        for language in Language::ALL {
+                            ui.selectable_value(&mut self.language, language, language.name());
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                ui.end_row();
+
+                let label = ui
+                    .label(tr!("settings-max-texture-size"))
+                    .on_hover_text(tr!("settings-max-texture-size-hint"));
+                ui.add(
+                    egui::DragValue::new(&mut self.max_texture_side)
+                        .range(256..=16384)
+                        .suffix(" px"),
+                )
+                .labelled_by(label.id);
+                ui.end_row();
+
+                ui.label(tr!("settings-color-management"))
+                    .on_hover_text(tr!("settings-color-management-hint"));
+                ui.checkbox(&mut self.color_management, tr!("enabled"));
+                ui.end_row();
+
+                let label = ui
+                    .label(tr!("settings-preload"))
+                    .on_hover_text(tr!("settings-preload-hint"));
+                ui.add(egui::DragValue::new(&mut self.keep_window).range(0..=50))
+                    .labelled_by(label.id);
+                ui.end_row();
+
+                let label = ui.label(tr!("settings-memory-budget"));
+                ui.add(
+                    egui::DragValue::new(&mut self.memory_budget_mb)
+                        .range(64..=65536)
+                        .suffix(" MB"),
+                )
+                .labelled_by(label.id);
+                ui.end_row();
+
+                let label = ui.label(tr!("settings-thumbnail-cache-size"));
+                ui.add(
+                    egui::DragValue::new(&mut self.thumbnail_cache_mb)
+                        .range(16..=65536)
+                        .suffix(" MB"),
+                )
+                .labelled_by(label.id);
+                ui.end_row();
+
+                ui.label(tr!("settings-library-index"))
+                    .on_hover_text(tr!("settings-library-index-hint"));
+                ui.checkbox(&mut self.use_library_index, tr!("enabled"));
+                ui.end_row();
+
+                ui.label(tr!("settings-raw-pairs"))
+                    .on_hover_text(tr!("settings-raw-pairs-hint"));
+                ui.checkbox(&mut self.pair_raw_with_jpeg, tr!("settings-treat-as-one"));
+                ui.end_row();
+
+                ui.label(tr!("settings-bursts"))
+                    .on_hover_text(tr!("settings-bursts-hint"));
+                ui.checkbox(&mut self.stack_bursts, tr!("settings-stack-bursts"));
+                ui.end_row();
+
+                ui.label(tr!("settings-sort-by"));
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.sort_order,
+                        SortOrder::FileName,
+                        tr!("settings-file-name"),
+                    );
+                    ui.radio_value(
+                        &mut self.sort_order,
+                        SortOrder::DateTaken,
+                        tr!("settings-date-taken"),
+                    )
+                    .on_hover_text(tr!("settings-date-taken-hint"));
+                    ui.radio_value(
+                        &mut self.sort_order,
+                        SortOrder::Largest,
+                        tr!("settings-largest-first"),
+                    )
+                    .on_hover_text(tr!("settings-largest-first-hint"));
+                    ui.radio_value(
+                        &mut self.sort_order,
+                        SortOrder::Color,
+                        tr!("settings-color"),
+                    )
+                    .on_hover_text(tr!("settings-color-hint"));
+                });
+                ui.end_row();
+
+                ui.label(tr!("settings-ignore"))
+                    .on_hover_text(tr!("settings-ignore-hint"));
+                ui.vertical(|ui| {
+                    ui.checkbox(
+                        &mut self.include_hidden_files,
+                        tr!("settings-include-hidden"),
+                    );
+                    ui.add(
+                        egui::TextEdit::multiline(&mut self.ignore_patterns)
+                            .hint_text("*.tmp.jpg")
+                            .desired_rows(3),
+                    );
+                });
+                ui.end_row();
+
+                ui.label(tr!("settings-symlinks"))
+                    .on_hover_text(tr!("settings-symlinks-hint"));
+                ui.horizontal(|ui| {
+                    for (policy, name) in [
+                        (SymlinkPolicy::Skip, tr!("settings-symlinks-skip")),
+                        (SymlinkPolicy::Follow, tr!("settings-symlinks-follow")),
+                        (SymlinkPolicy::MoveLink, tr!("settings-symlinks-move-link")),
+                    ] {
+                        ui.radio_value(&mut self.symlinks, policy, name);
+                    }
+                });
+                ui.end_row();
+
+                ui.label(tr!("settings-collisions"))
+                    .on_hover_text(tr!("settings-collisions-hint"));
+                ui.horizontal(|ui| {
+                    for (policy, name) in [
+                        (
+                            CollisionPolicy::KeepBoth,
+                            tr!("settings-collisions-keep-both"),
+                        ),
+                        (CollisionPolicy::Skip, tr!("settings-collisions-skip")),
+                    ] {
+                        ui.radio_value(&mut self.collisions, policy, name);
+                    }
+                });
+                ui.end_row();
+
+                if cfg!(unix) {
+                    ui.label(tr!("settings-download-flag"))
+                        .on_hover_text(tr!("settings-download-flag-hint"));
+                    ui.checkbox(
+                        &mut self.keep_download_flag,
+                        tr!("settings-download-flag-keep"),
+                    );
+                    ui.end_row();
+                }
+
+                let label = ui
+                    .label(tr!("settings-network-timeout"))
+                    .on_hover_text(tr!("settings-network-timeout-hint"));
+                ui.add(
+                    egui::DragValue::new(&mut self.network_timeout_secs)
+                        .range(1..=300)
+                        .suffix(" s"),
+                )
+                .labelled_by(label.id);
+                ui.end_row();
+
+                ui.label(tr!("settings-maps"))
+                    .on_hover_text(tr!("settings-maps-hint"));
+                ui.checkbox(&mut self.show_map_tiles, tr!("settings-show-in-info"));
+                ui.end_row();
+
+                ui.label(tr!("settings-tags"))
+                    .on_hover_text(tr!("settings-tags-hint"));
+                ui.checkbox(
+                    &mut self.suggest_tag_destinations,
+                    tr!("settings-suggest-destinations"),
+                );
+                ui.end_row();
+
+                ui.label(tr!("settings-kept-images"))
+                    .on_hover_text(tr!("settings-kept-images-hint"));
+                ui.checkbox(&mut self.hide_kept_images, tr!("settings-hide-kept"));
+                ui.end_row();
+
+                ui.label(tr!("settings-navigation"));
+                ui.vertical(|ui| {
+                    ui.checkbox(&mut self.wrap_around, tr!("settings-wrap-around"))
+                        .on_hover_text(tr!("settings-wrap-around-hint"));
+                    ui.add_enabled(
+                        !self.wrap_around,
+                        egui::Checkbox::new(
+                            &mut self.show_pass_summary,
+                            tr!("settings-pass-summary"),
+                        ),
+                    );
+                });
+                ui.end_row();
+
+                ui.label(tr!("settings-after-move"));
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.after_move,
+                        AfterMove::Advance,
+                        tr!("settings-after-move-advance"),
+                    );
+                    ui.radio_value(
+                        &mut self.after_move,
+                        AfterMove::Stay,
+                        tr!("settings-after-move-stay"),
+                    )
+                    .on_hover_text(tr!("settings-after-move-stay-hint"));
+                    ui.radio_value(
+                        &mut self.after_move,
+                        AfterMove::Back,
+                        tr!("settings-after-move-back"),
+                    );
+                });
+                ui.end_row();
+
+                let label = ui.label(tr!("settings-recent-moves"));
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.recent_moves).range(0..=50))
+                        .labelled_by(label.id);
+                    ui.label(tr!("settings-recent-moves-after"))
+                        .on_hover_text(tr!("settings-recent-moves-hint"));
+                });
+                ui.end_row();
+
+                ui.label(tr!("settings-confirm"));
+                ui.vertical(|ui| {
+                    ui.checkbox(
+                        &mut self.confirm_dangerous_destinations,
+                        tr!("settings-confirm-ask-first"),
+                    );
+                    ui.horizontal(|ui| {
+                        let label = ui.label(tr!("settings-confirm-batch-before"));
+                        ui.add(
+                            egui::DragValue::new(&mut self.confirm_batch_size).range(0..=100000),
+                        )
+                        .labelled_by(label.id);
+                        ui.label(tr!("settings-confirm-batch-after"))
+                            .on_hover_text(tr!("settings-confirm-batch-hint"));
+                    });
+                    ui.checkbox(
+                        &mut self.confirm_identical,
+                        tr!("settings-confirm-identical"),
+                    )
+                    .on_hover_text(tr!("settings-confirm-identical-hint"));
+                });
+                ui.end_row();
+
+                ui.label(tr!("settings-theme"));
+                ui.horizontal(|ui| {
+                    for (theme, name) in [
+                        (egui::ThemePreference::System, tr!("settings-theme-system")),
+                        (egui::ThemePreference::Dark, tr!("settings-theme-dark")),
+                        (egui::ThemePreference::Light, tr!("settings-theme-light")),
+                    ] {
+                        ui.radio_value(&mut self.theme, theme, name);
+                    }
+                });
+                ui.end_row();
+
+                ui.label(tr!("settings-accent-color"));
+                ui.horizontal(|ui| {
+                    let mut custom = self.accent_color.is_some();
+                    if ui.checkbox(&mut custom, tr!("settings-custom")).changed() {
+                        self.accent_color = custom.then_some([230, 120, 40]);
+                    }
+                    if let Some(color) = &mut self.accent_color {
+                        ui.color_edit_button_srgb(color);
+                    }
+                });
+                ui.end_row();
+
+                let label = ui.label(tr!("settings-ui-scale"));
+                ui.add(
+                    egui::Slider::new(&mut self.ui_scale, 0.5..=3.0)
+                        .step_by(0.1)
+                        .suffix("x"),
+                )
+                .labelled_by(label.id);
+                ui.end_row();
+
+                let label = ui.label(tr!("settings-font-size"));
+                ui.add(
+                    egui::DragValue::new(&mut self.font_size)
+                        .range(8.0..=32.0)
+                        .speed(0.1)
+                        .suffix(" pt"),
+                )
+                .labelled_by(label.id);
+                ui.end_row();
+
+                let label = ui
+                    .label(tr!("settings-image-background"))
+                    .on_hover_text(tr!("settings-image-background-hint"));
+                egui::ComboBox::from_id_salt("image_background")
+                    .selected_text(self.image_background.name())
+                    .show_ui(ui, |ui| {
+                        for background in ImageBackground::ALL {
+                            ui.selectable_value(
+                                &mut self.image_background,
+                                background,
+                                background.name(),
+                            );
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                ui.end_row();
+            });
+    }
+}
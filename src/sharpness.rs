@@ -0,0 +1,169 @@
+use eframe::egui;
+use image::GrayImage;
+use std::path::{Path, PathBuf};
+
+use crate::{batch::ImageScan, i18n::tr, image_loader, raw};
+
+// Images are measured at this size so that scores of different resolutions are comparable.
+const ANALYSIS_SIZE: u32 = 1024;
+
+/// Variance of the Laplacian. Sharp images have strong edges and score high, blurry ones low.
+pub fn laplacian_variance(image: &GrayImage) -> f64 {
+    let (width, height) = image.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let pixel = |x: u32, y: u32| f64::from(image.get_pixel(x, y).0[0]);
+    let mut sum = 0.0;
+    let mut sum_of_squares = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian = pixel(x - 1, y) + pixel(x + 1, y) + pixel(x, y - 1) + pixel(x, y + 1)
+                - 4.0 * pixel(x, y);
+            sum += laplacian;
+            sum_of_squares += laplacian * laplacian;
+        }
+    }
+    let count = f64::from((width - 2) * (height - 2));
+    let mean = sum / count;
+    sum_of_squares / count - mean * mean
+}
+
+pub fn sharpness(path: &Path) -> Result<f64, String> {
+    let image = image_loader::downscale(raw::decode_file(path)?, ANALYSIS_SIZE).to_luma8();
+    Ok(laplacian_variance(&image))
+}
+
+/// Measures the sharpness of `paths` on a background thread.
+fn scan(paths: Vec<PathBuf>) -> ImageScan<f64> {
+    ImageScan::start(paths, |path| {
+        sharpness(path)
+            // The integrity check is the place to find those.
+            .inspect_err(|e| log::warn!("Failed to measure {}: {}", path.display(), e))
+            .ok()
+    })
+}
+
+/// What the user asked for in the "Blurry images" window.
+pub enum BlurAction {
+    Show(PathBuf),
+    // Only show these images.
+    Review(Vec<PathBuf>),
+}
+
+/// State of the "Blurry images" window.
+#[derive(Default)]
+pub struct BlurTool {
+    scan: Option<ImageScan<f64>>,
+    // Of the images measured so far, sorted from the blurriest.
+    scores: Vec<(PathBuf, f64)>,
+}
+
+impl BlurTool {
+    pub fn poll(&mut self) {
+        let Some(scan) = &mut self.scan else {
+            return;
+        };
+        let measured = scan.poll();
+        if !measured.is_empty() {
+            self.scores.extend_from_slice(measured);
+            self.scores.sort_by(|a, b| a.1.total_cmp(&b.1));
+        }
+    }
+
+    /// Images that score below `threshold`, blurriest first.
+    fn blurry(&self, threshold: f64) -> &[(PathBuf, f64)] {
+        let end = self.scores.partition_point(|(_, score)| *score < threshold);
+        &self.scores[..end]
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.scan.as_ref().is_some_and(|scan| !scan.is_done())
+    }
+
+    /// The images measured and the images to measure, while a scan is running.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.scan
+            .as_ref()
+            .filter(|scan| !scan.is_done())
+            .map(|scan| scan.progress())
+    }
+
+    /// Draws the window contents. `images` are the images that a new scan measures.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        images: &[PathBuf],
+        threshold: &mut f64,
+    ) -> Option<BlurAction> {
+        ui.label(tr!("blur-intro"));
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.is_busy(), egui::Button::new(tr!("measure-sharpness")))
+                .clicked()
+            {
+                self.scan = Some(scan(images.to_vec()));
+                self.scores.clear();
+            }
+            ui.label(tr!("blurry-below"))
+                .on_hover_text(tr!("blurry-below-hint"));
+            ui.add(egui::DragValue::new(threshold).range(1.0..=5000.0));
+        });
+        let scan = self.scan.as_ref()?;
+        let (checked, total) = scan.progress();
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::ProgressBar::new(checked as f32 / total.max(1) as f32)
+                    .text(format!("{}/{}", checked, total)),
+            );
+            if !scan.is_done() && ui.button(tr!("cancel")).clicked() {
+                scan.cancel();
+            }
+        });
+
+        let blurry = self.blurry(*threshold);
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(tr!("blurry-count", count = blurry.len()));
+            if ui
+                .add_enabled(!blurry.is_empty(), egui::Button::new(tr!("review")))
+                .on_hover_text(tr!("review-blurry-hint"))
+                .clicked()
+            {
+                action = Some(BlurAction::Review(
+                    blurry.iter().map(|(path, _)| path.clone()).collect(),
+                ));
+            }
+        });
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::vertical().show_rows(ui, row_height, blurry.len(), |ui, rows| {
+            for (path, score) in &blurry[rows] {
+                ui.horizontal(|ui| {
+                    if ui.button(tr!("show")).clicked() {
+                        action = Some(BlurAction::Show(path.clone()));
+                    }
+                    ui.label(format!("{:.0}", score));
+                    ui.label(path.file_name().unwrap_or_default().to_string_lossy());
+                });
+            }
+        });
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn laplacian_variance_test() {
+        let flat = GrayImage::from_pixel(16, 16, image::Luma([128]));
+        assert_eq!(laplacian_variance(&flat), 0.0);
+
+        let checkerboard = GrayImage::from_fn(16, 16, |x, y| {
+            image::Luma([if (x + y) % 2 == 0 { 0 } else { 255 }])
+        });
+        let blurred = image::imageops::blur(&checkerboard, 2.0);
+        assert!(laplacian_variance(&checkerboard) > laplacian_variance(&blurred) * 10.0);
+    }
+}
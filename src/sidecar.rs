@@ -0,0 +1,79 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+// Metadata that other tools keep next to images: XMP from photo editors, JSON from Google
+// Takeout, and text captions.
+const SIDECAR_EXTENSIONS: [&str; 3] = ["xmp", "json", "txt"];
+
+// Google Takeout names some of its JSON files "<image name>.supplemental-metadata.json".
+const TAKEOUT_SUFFIX: &str = ".supplemental-metadata.json";
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Sidecar files of the image at `path` that exist. Both "IMG_1234.xmp" and "IMG_1234.JPG.xmp"
+/// styles are found.
+pub fn sidecar_files(path: &Path) -> Vec<PathBuf> {
+    let mut sidecars: Vec<PathBuf> = Vec::new();
+    for extension in SIDECAR_EXTENSIONS {
+        let cases = [extension.to_string(), extension.to_uppercase()];
+        let replaced = cases.iter().map(|extension| path.with_extension(extension));
+        let appended = cases
+            .iter()
+            .map(|extension| with_suffix(path, &format!(".{}", extension)));
+        // Only the first case that exists, so that case-insensitive file systems do not list the
+        // same file twice.
+        for mut candidates in [replaced.collect::<Vec<_>>(), appended.collect()] {
+            candidates.retain(|candidate| candidate != path);
+            if let Some(found) = candidates.into_iter().find(|candidate| candidate.is_file()) {
+                if !sidecars.contains(&found) {
+                    sidecars.push(found);
+                }
+            }
+        }
+    }
+    let takeout = with_suffix(path, TAKEOUT_SUFFIX);
+    if takeout.is_file() {
+        sidecars.push(takeout);
+    }
+    sidecars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn sidecar_files_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let image = dir.join("IMG_1.jpg");
+        for name in [
+            "IMG_1.jpg",
+            "IMG_1.xmp",
+            "IMG_1.jpg.json",
+            "IMG_1.jpg.supplemental-metadata.json",
+            "IMG_1.TXT",
+            "IMG_10.xmp",
+        ] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let mut sidecars = sidecar_files(&image);
+        sidecars.sort();
+        let mut expected = vec![
+            dir.join("IMG_1.xmp"),
+            dir.join("IMG_1.jpg.json"),
+            dir.join("IMG_1.jpg.supplemental-metadata.json"),
+            dir.join("IMG_1.TXT"),
+        ];
+        expected.sort();
+        assert_eq!(sidecars, expected);
+    }
+}
@@ -0,0 +1,236 @@
+use eframe::egui;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// Older entries are dropped once the history grows past this.
+const MAX_HISTORY: usize = 500;
+
+// How long a toast stays on screen.
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct StatusEntry {
+    pub level: log::Level,
+    pub message: String,
+    pub time: Instant,
+    // Shown as a toast. Warnings and errors always are, and so is finished background work.
+    pub toast: bool,
+}
+
+impl StatusEntry {
+    /// Color used to render this entry.
+    pub fn color(&self, visuals: &egui::Visuals) -> egui::Color32 {
+        match self.level {
+            log::Level::Error => visuals.error_fg_color,
+            log::Level::Warn => visuals.warn_fg_color,
+            log::Level::Info => visuals.text_color(),
+            log::Level::Debug | log::Level::Trace => visuals.weak_text_color(),
+        }
+    }
+}
+
+// Background work that is still running, reported again every frame.
+struct Task {
+    label: String,
+    // None while the amount of work is unknown.
+    fraction: Option<f32>,
+}
+
+/// All the status messages shown in this session, oldest first.
+/// Pushing a message also writes it to the log, so callers do not have to do both.
+#[derive(Default)]
+pub struct StatusLog {
+    entries: VecDeque<StatusEntry>,
+    tasks: Vec<Task>,
+}
+
+impl StatusLog {
+    pub fn push(&mut self, level: log::Level, message: impl Into<String>) {
+        self.push_entry(level, message.into(), level <= log::Level::Warn);
+    }
+
+    fn push_entry(&mut self, level: log::Level, message: String, toast: bool) {
+        log::log!(level, "{}", message);
+        if self.entries.len() >= MAX_HISTORY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(StatusEntry {
+            level,
+            message,
+            time: Instant::now(),
+            toast,
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(log::Level::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(log::Level::Warn, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(log::Level::Error, message);
+    }
+
+    /// An info message about background work that finished, which is also shown as a toast
+    /// since the user may have been doing something else meanwhile.
+    pub fn done(&mut self, message: impl Into<String>) {
+        self.push_entry(log::Level::Info, message.into(), true);
+    }
+
+    /// Shows background work that is running in the next toasts. Call it every frame until the
+    /// work is done. `fraction` is None while it is unknown how much is left.
+    pub fn progress(&mut self, label: impl Into<String>, fraction: Option<f32>) {
+        self.tasks.push(Task {
+            label: label.into(),
+            fraction,
+        });
+    }
+
+    pub fn latest(&self) -> Option<&StatusEntry> {
+        self.entries.back()
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &StatusEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Toasts that are recent enough to be shown.
+    pub fn active_toasts(&self, now: Instant) -> impl Iterator<Item = &StatusEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .take_while(move |entry| now.duration_since(entry.time) < TOAST_DURATION)
+            .filter(|entry| entry.toast)
+    }
+
+    /// Draw the running work and the toasts in the bottom right corner. Returns true if any
+    /// toast is visible so the caller can schedule a repaint to make them disappear.
+    pub fn show_toasts(&mut self, ctx: &egui::Context) -> bool {
+        let now = Instant::now();
+        let tasks = std::mem::take(&mut self.tasks);
+        let toasts: Vec<&StatusEntry> = self.active_toasts(now).collect();
+        if toasts.is_empty() && tasks.is_empty() {
+            return false;
+        }
+        egui::Area::new(egui::Id::new("status_toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                for task in &tasks {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| match task.fraction {
+                        Some(fraction) => {
+                            ui.add(
+                                egui::ProgressBar::new(fraction)
+                                    .desired_width(250.0)
+                                    .text(&task.label),
+                            );
+                        }
+                        None => {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label(&task.label);
+                            });
+                        }
+                    });
+                }
+                for toast in toasts {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(toast.color(ui.visuals()), &toast.message);
+                    });
+                }
+            });
+        ctx.request_repaint_after(Duration::from_millis(250));
+        true
+    }
+
+    /// Scrollable list of every message, newest first.
+    pub fn show_history(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} messages", self.len()));
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+        });
+        let now = Instant::now();
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for entry in self.entries().rev() {
+                    let age = now.duration_since(entry.time).as_secs();
+                    ui.horizontal(|ui| {
+                        ui.weak(format!("{}s ago", age));
+                        ui.colored_label(
+                            entry.color(ui.visuals()),
+                            format!("[{}] {}", entry.level, entry.message),
+                        );
+                    });
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_cap_test() {
+        let mut status = StatusLog::default();
+        for i in 0..MAX_HISTORY + 10 {
+            status.info(format!("message {}", i));
+        }
+        assert_eq!(status.len(), MAX_HISTORY);
+        assert_eq!(
+            status.latest().unwrap().message,
+            format!("message {}", MAX_HISTORY + 9)
+        );
+        assert_eq!(status.entries().next().unwrap().message, "message 10");
+    }
+
+    #[test]
+    fn toasts_test() {
+        let mut status = StatusLog::default();
+        status.info("moved");
+        status.error("failed");
+        status.warn("careful");
+        let now = Instant::now();
+        let toasts: Vec<_> = status.active_toasts(now).map(|t| &t.message).collect();
+        assert_eq!(toasts, vec!["careful", "failed"]);
+
+        let later = now + TOAST_DURATION;
+        assert_eq!(status.active_toasts(later).count(), 0);
+    }
+
+    #[test]
+    fn finished_work_test() {
+        let mut status = StatusLog::default();
+        status.done("indexed");
+        let toasts: Vec<_> = status
+            .active_toasts(Instant::now())
+            .map(|t| (t.level, t.message.as_str()))
+            .collect();
+        assert_eq!(toasts, vec![(log::Level::Info, "indexed")]);
+
+        // Running work is only shown in the frame it was reported in.
+        status.progress("scanning", None);
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            assert!(status.show_toasts(ctx));
+        });
+        assert!(status.tasks.is_empty());
+    }
+}
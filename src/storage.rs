@@ -0,0 +1,218 @@
+use eframe::egui;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{file_ops, i18n::tr};
+
+/// Number and total size of the files in a folder and all of its subfolders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FolderUsage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Adds up the files under `folder`. Symbolic links are not followed, and subfolders that cannot
+/// be read are left out.
+pub fn folder_usage(folder: &Path) -> io::Result<FolderUsage> {
+    let mut usage = FolderUsage::default();
+    let mut folders = vec![folder.to_path_buf()];
+    while let Some(current) = folders.pop() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(e) if current == folder => return Err(e),
+            Err(e) => {
+                log::warn!("Failed to read {}: {}", current.display(), e);
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                folders.push(entry.path());
+            } else if file_type.is_file() {
+                usage.files += 1;
+                usage.bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+    }
+    Ok(usage)
+}
+
+// The folder that holds everything a destination receives. For "/photos/{year}/{month}" that is
+// "/photos".
+fn measured_folder(destination: &Path) -> PathBuf {
+    destination
+        .components()
+        .take_while(|component| !component.as_os_str().to_string_lossy().contains('{'))
+        .collect()
+}
+
+/// State of the "Storage usage" window.
+#[derive(Default)]
+pub struct StorageTool {
+    // Measuring large albums takes a while, so it runs in the background.
+    receiver: Option<mpsc::Receiver<(PathBuf, io::Result<FolderUsage>)>>,
+    usage: Vec<(PathBuf, Result<FolderUsage, String>)>,
+    // Folders being measured, as templates under the same folder are measured once.
+    measuring: usize,
+}
+
+impl StorageTool {
+    fn start(&mut self, destinations: &[PathBuf]) {
+        let mut folders: Vec<PathBuf> = destinations.iter().map(|d| measured_folder(d)).collect();
+        folders.sort();
+        folders.dedup();
+        self.measuring = folders.len();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for folder in folders {
+                let usage = folder_usage(&folder);
+                if sender.send((folder, usage)).is_err() {
+                    return;
+                }
+            }
+        });
+        self.receiver = Some(receiver);
+        self.usage.clear();
+    }
+
+    /// Collects the folders measured since the last call.
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok((folder, usage)) => self.usage.push((folder, usage.map_err(|e| e.to_string()))),
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => break,
+            }
+        }
+        self.receiver = None;
+        // Largest first, so that the ones to prune stand out.
+        self.usage
+            .sort_by_key(|(_, usage)| std::cmp::Reverse(usage.as_ref().map_or(0, |u| u.bytes)));
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Draws the window contents for the destination folders `destinations`.
+    pub fn show(&mut self, ui: &mut egui::Ui, destinations: &[PathBuf]) {
+        ui.label(tr!("storage-intro"));
+        let start = ui
+            .add_enabled(!self.is_busy(), egui::Button::new(tr!("refresh")))
+            .clicked();
+        // Measured the first time the window is opened.
+        if start || (self.usage.is_empty() && !self.is_busy() && !destinations.is_empty()) {
+            self.start(destinations);
+        }
+        if self.is_busy() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(tr!(
+                    "measuring",
+                    done = self.usage.len(),
+                    total = self.measuring
+                ));
+            });
+        }
+        if destinations.is_empty() {
+            ui.label(tr!("no-destinations-yet"));
+            return;
+        }
+
+        let largest = self
+            .usage
+            .iter()
+            .filter_map(|(_, usage)| usage.as_ref().ok())
+            .map(|usage| usage.bytes)
+            .max()
+            .unwrap_or(0);
+        let total = self
+            .usage
+            .iter()
+            .filter_map(|(_, usage)| usage.as_ref().ok())
+            .fold(FolderUsage::default(), |total, usage| FolderUsage {
+                files: total.files + usage.files,
+                bytes: total.bytes + usage.bytes,
+            });
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("storage_grid")
+                .num_columns(4)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong(tr!("storage-folder"));
+                    ui.strong(tr!("storage-files"));
+                    ui.strong(tr!("storage-size"));
+                    ui.end_row();
+                    for (folder, usage) in &self.usage {
+                        ui.label(folder.to_string_lossy());
+                        match usage {
+                            Ok(usage) => {
+                                ui.label(usage.files.to_string());
+                                ui.label(file_ops::format_size(usage.bytes));
+                                ui.add(
+                                    egui::ProgressBar::new(
+                                        usage.bytes as f32 / largest.max(1) as f32,
+                                    )
+                                    .desired_width(100.0),
+                                );
+                            }
+                            Err(e) => {
+                                ui.label("");
+                                ui.colored_label(ui.visuals().error_fg_color, e);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                    ui.strong(tr!("storage-total"));
+                    ui.strong(total.files.to_string());
+                    ui.strong(file_ops::format_size(total.bytes));
+                    ui.end_row();
+                });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folder_usage_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path().join("album");
+        fs::create_dir_all(folder.join("2024/01")).unwrap();
+        fs::write(folder.join("a.png"), vec![0; 100]).unwrap();
+        fs::write(folder.join("notes.txt"), vec![0; 10]).unwrap();
+        fs::write(folder.join("2024/01/b.jpg"), vec![0; 1000]).unwrap();
+        assert_eq!(
+            folder_usage(&folder).unwrap(),
+            FolderUsage {
+                files: 3,
+                bytes: 1110
+            }
+        );
+        assert!(folder_usage(&temp_dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn measured_folder_test() {
+        assert_eq!(
+            measured_folder(Path::new("/photos/{year}/{month}")),
+            PathBuf::from("/photos")
+        );
+        assert_eq!(
+            measured_folder(Path::new("/photos/trips")),
+            PathBuf::from("/photos/trips")
+        );
+    }
+}
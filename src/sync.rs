@@ -0,0 +1,676 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{batch::OnFile, file_ops, flatten, i18n::tr};
+
+/// The file on the mirror that lists the images both sides had after the last sync, to tell a
+/// file deleted on one side from a file added on the other.
+pub const MANIFEST_NAME: &str = ".image-organizer-sync.json";
+
+/// Which side wins where the library and the mirror disagree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Resolution {
+    #[default]
+    Skip,
+    // Make the mirror like the library.
+    UseLibrary,
+    // Make the library like the mirror.
+    UseMirror,
+}
+
+/// One change that brings the library and the mirror in line. Paths are relative to both.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyncOp {
+    // Moved or renamed in the library. The file on the mirror is moved the same way instead of
+    // being copied again.
+    MoveOnMirror {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    CopyToMirror(PathBuf),
+    CopyToLibrary(PathBuf),
+    // Only the user can tell which side is right.
+    Conflict {
+        path: PathBuf,
+        reason: String,
+        resolution: Resolution,
+    },
+}
+
+/// The difference between the library and the mirror. Nothing is changed until it is applied.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncPlan {
+    pub library: PathBuf,
+    pub mirror: PathBuf,
+    pub ops: Vec<SyncOp>,
+    // Files that are the same on both sides.
+    pub unchanged: usize,
+}
+
+/// The images under `root` by their path relative to it, with their sizes.
+pub fn list(root: &Path) -> io::Result<BTreeMap<PathBuf, u64>> {
+    // Nothing is left out: no folder is named "".
+    let images = flatten::find_nested_images(root, Path::new(""))?;
+    Ok(images
+        .into_iter()
+        .filter_map(|path| {
+            let size = fs::metadata(&path).ok()?.len();
+            Some((path.strip_prefix(root).ok()?.to_path_buf(), size))
+        })
+        .collect())
+}
+
+/// The moves and renames in `log` within `library`, relative to it. A file moved several times
+/// goes straight from where it was first to where it is now.
+pub fn library_moves(log: &[(PathBuf, PathBuf)], library: &Path) -> Vec<(PathBuf, PathBuf)> {
+    // Where each file at the end of a chain of moves started.
+    let mut origins: BTreeMap<&Path, &Path> = BTreeMap::new();
+    for (src, dest) in log {
+        let origin = origins.remove(src.as_path()).unwrap_or(src);
+        origins.insert(dest, origin);
+    }
+    origins
+        .into_iter()
+        .filter(|(dest, origin)| dest != origin)
+        .filter_map(|(dest, origin)| {
+            Some((
+                origin.strip_prefix(library).ok()?.to_path_buf(),
+                dest.strip_prefix(library).ok()?.to_path_buf(),
+            ))
+        })
+        .collect()
+}
+
+/// The images both sides had after the last sync, or None before the first one.
+pub fn read_manifest(mirror: &Path) -> Option<BTreeSet<PathBuf>> {
+    let manifest = fs::read_to_string(mirror.join(MANIFEST_NAME)).ok()?;
+    serde_json::from_str(&manifest)
+        .inspect_err(|e| log::warn!("Ignoring a broken sync manifest: {}", e))
+        .ok()
+}
+
+/// Writes down the images that both sides have now.
+pub fn write_manifest(library: &Path, mirror: &Path) -> io::Result<()> {
+    let library_files = list(library)?;
+    let both: BTreeSet<&PathBuf> = list(mirror)?
+        .into_keys()
+        .filter_map(|path| library_files.get_key_value(&path).map(|(path, _)| path))
+        .collect();
+    fs::write(mirror.join(MANIFEST_NAME), serde_json::to_string(&both)?)
+}
+
+/// Compares the files of the library and the mirror, as listed by `list()`. A file that only
+/// one side has is copied to the other, unless `moves`, from `library_moves()`, say it was
+/// moved in the library, or a file of the same name and contents, as `same_contents` tells
+/// by their paths in the library and on the mirror, is only on the mirror. Then it is moved on
+/// the mirror too. A file that `base`, from `read_manifest()`, says both sides had was deleted
+/// on the other side, which only the user can confirm. Files of different sizes at the same
+/// place are conflicts, and so are files that the library moved away but the mirror still has
+/// next to the moved one.
+pub fn compare(
+    library: &BTreeMap<PathBuf, u64>,
+    mirror: &BTreeMap<PathBuf, u64>,
+    moves: &[(PathBuf, PathBuf)],
+    base: Option<&BTreeSet<PathBuf>>,
+    same_contents: &dyn Fn(&Path, &Path) -> bool,
+) -> Vec<SyncOp> {
+    let mut ops = Vec::new();
+    let mut library_only: BTreeMap<&Path, u64> = BTreeMap::new();
+    for (path, &size) in library {
+        match mirror.get(path) {
+            Some(&mirror_size) if mirror_size != size => ops.push(SyncOp::Conflict {
+                path: path.clone(),
+                reason: format!(
+                    "{} bytes in the library, {} on the mirror",
+                    size, mirror_size
+                ),
+                resolution: Resolution::Skip,
+            }),
+            Some(_) => {}
+            None => {
+                library_only.insert(path, size);
+            }
+        }
+    }
+    let mut mirror_only: BTreeMap<&Path, u64> = mirror
+        .iter()
+        .filter(|(path, _)| !library.contains_key(*path))
+        .map(|(path, &size)| (path.as_path(), size))
+        .collect();
+
+    for (from, to) in moves {
+        match (
+            library_only.get(to.as_path()),
+            mirror_only.get(from.as_path()),
+        ) {
+            (Some(size), Some(mirror_size)) if size == mirror_size => {
+                library_only.remove(to.as_path());
+                mirror_only.remove(from.as_path());
+                ops.push(SyncOp::MoveOnMirror {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+            (None, Some(_)) if mirror.contains_key(to) => {
+                // Copying it back would undo the move in the library.
+                mirror_only.remove(from.as_path());
+                ops.push(SyncOp::Conflict {
+                    path: from.clone(),
+                    reason: tr!("sync-moved-both", path = to.display().to_string()),
+                    resolution: Resolution::Skip,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // Moves from earlier sessions are not in the log. A file of the same name and contents that
+    // only the mirror has, and only once, is taken for the same file. Photos from different
+    // cameras easily share a name and even a size.
+    let mut by_name: HashMap<(&std::ffi::OsStr, u64), Vec<&Path>> = HashMap::new();
+    for (path, &size) in &mirror_only {
+        if let Some(name) = path.file_name() {
+            by_name.entry((name, size)).or_default().push(path);
+        }
+    }
+    for (to, size) in library_only.clone() {
+        let Some(name) = to.file_name() else {
+            continue;
+        };
+        if let Some([from]) = by_name.get(&(name, size)).map(Vec::as_slice) {
+            if !same_contents(to, from) {
+                continue;
+            }
+            if mirror_only.remove(from).is_some() {
+                library_only.remove(to);
+                ops.push(SyncOp::MoveOnMirror {
+                    from: from.to_path_buf(),
+                    to: to.to_path_buf(),
+                });
+            }
+        }
+    }
+
+    let synced = |path: &Path| base.is_some_and(|base| base.contains(path));
+    let deleted = |path: &Path, reason: String| SyncOp::Conflict {
+        path: path.to_path_buf(),
+        reason,
+        resolution: Resolution::Skip,
+    };
+    ops.extend(library_only.into_keys().map(|path| match synced(path) {
+        true => deleted(path, tr!("deleted-on-mirror")),
+        false => SyncOp::CopyToMirror(path.to_path_buf()),
+    }));
+    ops.extend(mirror_only.into_keys().map(|path| match synced(path) {
+        true => deleted(path, tr!("deleted-in-library")),
+        false => SyncOp::CopyToLibrary(path.to_path_buf()),
+    }));
+    ops
+}
+
+/// Compares `library` with `mirror`, taking the moves of `log` into account.
+pub fn plan(library: &Path, mirror: &Path, log: &[(PathBuf, PathBuf)]) -> io::Result<SyncPlan> {
+    let library_files = list(library)?;
+    let mirror_files = list(mirror)?;
+    let same_contents = |in_library: &Path, on_mirror: &Path| {
+        file_ops::same_contents(&library.join(in_library), &mirror.join(on_mirror)).unwrap_or(false)
+    };
+    let ops = compare(
+        &library_files,
+        &mirror_files,
+        &library_moves(log, library),
+        read_manifest(mirror).as_ref(),
+        &same_contents,
+    );
+    let unchanged = library_files
+        .iter()
+        .filter(|(path, size)| mirror_files.get(*path) == Some(size))
+        .count();
+    Ok(SyncPlan {
+        library: library.to_path_buf(),
+        mirror: mirror.to_path_buf(),
+        ops,
+        unchanged,
+    })
+}
+
+// Copies `src` over `dest`, creating its folder. Like a move across filesystems, the copy is
+// written under a temporary name until it is complete.
+fn copy_over(src: &Path, dest: &Path) -> io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+        file_ops::check_free_space(parent, fs::metadata(src)?.len())?;
+    }
+    let partial = file_ops::partial_path(dest);
+    file_ops::copy_file(src, &partial)
+        .and_then(|()| fs::rename(&partial, dest))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&partial);
+        })
+}
+
+fn apply_op(plan: &SyncPlan, op: &SyncOp) -> io::Result<()> {
+    let (library, mirror) = (&plan.library, &plan.mirror);
+    match op {
+        SyncOp::MoveOnMirror { from, to } => {
+            let dest = mirror.join(to);
+            if dest.exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", dest.display()),
+                ));
+            }
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            file_ops::rename_or_copy(&mirror.join(from), &dest)
+        }
+        SyncOp::CopyToMirror(path) => copy_over(&library.join(path), &mirror.join(path)),
+        SyncOp::CopyToLibrary(path) => copy_over(&mirror.join(path), &library.join(path)),
+        SyncOp::Conflict {
+            path, resolution, ..
+        } => {
+            let (src, dest) = match resolution {
+                Resolution::Skip => return Ok(()),
+                Resolution::UseLibrary => (library.join(path), mirror.join(path)),
+                Resolution::UseMirror => (mirror.join(path), library.join(path)),
+            };
+            // The winning side no longer has the file, e.g. it was moved away.
+            if !src.exists() {
+                return fs::remove_file(dest);
+            }
+            copy_over(&src, &dest)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub applied: usize,
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// Carries out the changes of `plan`. Stops early when `on_file` says so.
+pub fn apply(plan: &SyncPlan, on_file: &mut OnFile) -> SyncReport {
+    let mut report = SyncReport::default();
+    for op in &plan.ops {
+        if matches!(
+            op,
+            SyncOp::Conflict {
+                resolution: Resolution::Skip,
+                ..
+            }
+        ) {
+            continue;
+        }
+        let path = match op {
+            SyncOp::MoveOnMirror { to: path, .. }
+            | SyncOp::CopyToMirror(path)
+            | SyncOp::CopyToLibrary(path)
+            | SyncOp::Conflict { path, .. } => path,
+        };
+        let result = apply_op(plan, op).map_err(|e| e.to_string());
+        match &result {
+            Ok(()) => report.applied += 1,
+            Err(e) => report.errors.push((path.clone(), e.clone())),
+        }
+        if !on_file(path, result) {
+            break;
+        }
+    }
+    if let Err(e) = write_manifest(&plan.library, &plan.mirror) {
+        report
+            .errors
+            .push((plan.mirror.join(MANIFEST_NAME), e.to_string()));
+    }
+    report
+}
+
+/// What the user asked for in the "Sync with mirror" window.
+pub enum SyncAction {
+    // Compare the folders, taking the moves of this session into account.
+    Compare { library: PathBuf, mirror: PathBuf },
+    Start(SyncPlan),
+}
+
+/// State of the "Sync with mirror" window.
+#[derive(Default)]
+pub struct SyncTool {
+    // Listing both sides of a large library takes a while.
+    receiver: Option<mpsc::Receiver<io::Result<SyncPlan>>>,
+    plan: Option<SyncPlan>,
+    error: Option<String>,
+}
+
+impl SyncTool {
+    /// Compares the folders in the background. `log` is the (source, destination) of each move
+    /// of the session.
+    pub fn start_compare(
+        &mut self,
+        library: PathBuf,
+        mirror: PathBuf,
+        log: Vec<(PathBuf, PathBuf)>,
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(plan(&library, &mirror, &log));
+        });
+        self.receiver = Some(receiver);
+        self.plan = None;
+        self.error = None;
+    }
+
+    /// Collects the result of a running comparison.
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(Ok(plan)) => self.plan = Some(plan),
+            Ok(Err(e)) => self.error = Some(tr!("compare-failed", error = e.to_string())),
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {}
+        }
+        self.receiver = None;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Draws the window contents. The folders are kept in the settings. `can_change` is false
+    /// in read-only mode.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        library: &mut Option<PathBuf>,
+        mirror: &mut Option<PathBuf>,
+        can_change: bool,
+    ) -> Option<SyncAction> {
+        ui.label(tr!("sync-intro"));
+        for (label, folder) in [
+            (tr!("sync-library"), &mut *library),
+            (tr!("sync-mirror"), &mut *mirror),
+        ] {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                if ui.button(tr!("choose")).clicked() {
+                    if let Some(path) = FileDialog::new().pick_folder() {
+                        *folder = Some(path);
+                        self.plan = None;
+                    }
+                }
+                match folder {
+                    Some(folder) => ui.label(folder.to_string_lossy()),
+                    None => ui.label(tr!("not-selected")),
+                };
+            });
+        }
+
+        let mut action = None;
+        ui.horizontal(|ui| {
+            let folders = library.clone().zip(mirror.clone());
+            if ui
+                .add_enabled(
+                    folders.is_some() && !self.is_busy(),
+                    egui::Button::new(tr!("compare")),
+                )
+                .clicked()
+            {
+                if let Some((library, mirror)) = folders {
+                    action = Some(SyncAction::Compare { library, mirror });
+                }
+            }
+            if self.is_busy() {
+                ui.spinner();
+                ui.label(tr!("comparing"));
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let plan = self.plan.as_mut()?;
+        let count = |wanted: fn(&SyncOp) -> bool| plan.ops.iter().filter(|op| wanted(op)).count();
+        ui.label(tr!(
+            "sync-summary",
+            moves = count(|op| matches!(op, SyncOp::MoveOnMirror { .. })),
+            to_mirror = count(|op| matches!(op, SyncOp::CopyToMirror(_))),
+            to_library = count(|op| matches!(op, SyncOp::CopyToLibrary(_))),
+            conflicts = count(|op| matches!(op, SyncOp::Conflict { .. })),
+            unchanged = plan.unchanged
+        ));
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::vertical().max_height(300.0).show_rows(
+            ui,
+            row_height,
+            plan.ops.len(),
+            |ui, rows| {
+                for op in &mut plan.ops[rows] {
+                    ui.horizontal(|ui| match op {
+                        SyncOp::MoveOnMirror { from, to } => {
+                            ui.label(tr!(
+                                "move-on-mirror",
+                                from = from.display().to_string(),
+                                to = to.display().to_string()
+                            ));
+                        }
+                        SyncOp::CopyToMirror(path) => {
+                            ui.label(tr!("copy-to-mirror", path = path.display().to_string()));
+                        }
+                        SyncOp::CopyToLibrary(path) => {
+                            ui.label(tr!("copy-to-library", path = path.display().to_string()));
+                        }
+                        SyncOp::Conflict {
+                            path,
+                            reason,
+                            resolution,
+                        } => {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                tr!("sync-conflict", path = path.display().to_string()),
+                            )
+                            .on_hover_text(reason.as_str());
+                            ui.radio_value(resolution, Resolution::Skip, tr!("skip"));
+                            ui.radio_value(resolution, Resolution::UseLibrary, tr!("keep-library"));
+                            ui.radio_value(resolution, Resolution::UseMirror, tr!("keep-mirror"));
+                        }
+                    });
+                }
+            },
+        );
+        let has_changes = plan.ops.iter().any(|op| {
+            !matches!(
+                op,
+                SyncOp::Conflict {
+                    resolution: Resolution::Skip,
+                    ..
+                }
+            )
+        });
+        if ui
+            .add_enabled(can_change && has_changes, egui::Button::new(tr!("sync")))
+            .clicked()
+        {
+            action = self.plan.take().map(SyncAction::Start);
+        }
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, u64)]) -> BTreeMap<PathBuf, u64> {
+        entries
+            .iter()
+            .map(|&(path, size)| (PathBuf::from(path), size))
+            .collect()
+    }
+
+    #[test]
+    fn library_moves_test() {
+        let library = Path::new("/library");
+        let log = [
+            ("/library/in/a.png", "/library/cats/a.png"),
+            ("/library/cats/a.png", "/library/best/a.png"),
+            ("/library/in/b.png", "/library/in/renamed.png"),
+            // Moved back.
+            ("/library/in/c.png", "/library/dogs/c.png"),
+            ("/library/dogs/c.png", "/library/in/c.png"),
+            // Out of the library.
+            ("/library/in/d.png", "/elsewhere/d.png"),
+        ]
+        .map(|(src, dest)| (PathBuf::from(src), PathBuf::from(dest)));
+        assert_eq!(
+            library_moves(&log, library),
+            [
+                (PathBuf::from("in/a.png"), PathBuf::from("best/a.png")),
+                (PathBuf::from("in/b.png"), PathBuf::from("in/renamed.png")),
+            ]
+        );
+    }
+
+    #[test]
+    fn compare_test() {
+        let library = files(&[
+            ("best/a.png", 1),
+            ("in/renamed.png", 2),
+            ("same.png", 3),
+            ("changed.png", 4),
+            ("new.png", 5),
+            ("2024/e.png", 6),
+            ("dogs/f.png", 7),
+            ("g.png", 9),
+        ]);
+        let mirror = files(&[
+            ("in/a.png", 1),
+            ("in/b.png", 2),
+            ("same.png", 3),
+            ("changed.png", 40),
+            ("only-mirror.png", 8),
+            ("old/e.png", 6),
+            ("in/f.png", 7),
+            ("dogs/f.png", 7),
+            ("other/g.png", 9),
+        ]);
+        let moves = [
+            (PathBuf::from("in/a.png"), PathBuf::from("best/a.png")),
+            (PathBuf::from("in/b.png"), PathBuf::from("in/renamed.png")),
+            (PathBuf::from("in/f.png"), PathBuf::from("dogs/f.png")),
+        ];
+        // e.png has the same contents on both sides, g.png only the same size.
+        let same_contents = |in_library: &Path, _: &Path| in_library != Path::new("g.png");
+        let ops = compare(&library, &mirror, &moves, None, &same_contents);
+        let path = PathBuf::from;
+        assert_eq!(
+            ops,
+            [
+                SyncOp::Conflict {
+                    path: path("changed.png"),
+                    reason: "4 bytes in the library, 40 on the mirror".to_string(),
+                    resolution: Resolution::Skip,
+                },
+                SyncOp::MoveOnMirror {
+                    from: path("in/a.png"),
+                    to: path("best/a.png"),
+                },
+                SyncOp::MoveOnMirror {
+                    from: path("in/b.png"),
+                    to: path("in/renamed.png"),
+                },
+                SyncOp::Conflict {
+                    path: path("in/f.png"),
+                    reason: "Moved to dogs/f.png in the library, but the mirror has both"
+                        .to_string(),
+                    resolution: Resolution::Skip,
+                },
+                // Found by name and size.
+                SyncOp::MoveOnMirror {
+                    from: path("old/e.png"),
+                    to: path("2024/e.png"),
+                },
+                SyncOp::CopyToMirror(path("g.png")),
+                SyncOp::CopyToMirror(path("new.png")),
+                SyncOp::CopyToLibrary(path("only-mirror.png")),
+                SyncOp::CopyToLibrary(path("other/g.png")),
+            ]
+        );
+
+        // After a sync, a file that one side lacks was deleted there, not added on the other.
+        let base = BTreeSet::from([path("new.png"), path("only-mirror.png")]);
+        let ops = compare(&library, &mirror, &moves, Some(&base), &same_contents);
+        assert!(ops.contains(&SyncOp::Conflict {
+            path: path("new.png"),
+            reason: "Deleted on the mirror since the last sync".to_string(),
+            resolution: Resolution::Skip,
+        }));
+        assert!(ops.contains(&SyncOp::Conflict {
+            path: path("only-mirror.png"),
+            reason: "Deleted in the library since the last sync".to_string(),
+            resolution: Resolution::Skip,
+        }));
+        assert!(ops.contains(&SyncOp::CopyToMirror(path("g.png"))));
+    }
+
+    #[test]
+    fn apply_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let library = temp_dir.path().join("library");
+        let mirror = temp_dir.path().join("mirror");
+        for dir in [&library, &mirror] {
+            fs::create_dir_all(dir.join("in")).unwrap();
+        }
+        fs::create_dir_all(library.join("cats")).unwrap();
+        fs::write(library.join("cats/a.png"), b"a").unwrap();
+        fs::write(mirror.join("in/a.png"), b"a").unwrap();
+        fs::write(library.join("new.png"), b"new").unwrap();
+        fs::write(mirror.join("only.png"), b"only").unwrap();
+        fs::write(library.join("changed.png"), b"library").unwrap();
+        fs::write(mirror.join("changed.png"), b"mirror!!").unwrap();
+
+        let log = [(library.join("in/a.png"), library.join("cats/a.png"))];
+        let mut plan = plan(&library, &mirror, &log).unwrap();
+        assert_eq!(plan.ops.len(), 4);
+        for op in &mut plan.ops {
+            if let SyncOp::Conflict { resolution, .. } = op {
+                *resolution = Resolution::UseMirror;
+            }
+        }
+        let report = apply(&plan, &mut |_, _| true);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.applied, 4);
+        assert!(!mirror.join("in/a.png").exists());
+        assert_eq!(fs::read(mirror.join("cats/a.png")).unwrap(), b"a");
+        assert_eq!(fs::read(mirror.join("new.png")).unwrap(), b"new");
+        assert_eq!(fs::read(library.join("only.png")).unwrap(), b"only");
+        assert_eq!(fs::read(library.join("changed.png")).unwrap(), b"mirror!!");
+        // In line now.
+        assert!(super::plan(&library, &mirror, &[]).unwrap().ops.is_empty());
+
+        // Deleting in the library is not undone by copying it back.
+        fs::remove_file(library.join("new.png")).unwrap();
+        let mut plan = super::plan(&library, &mirror, &[]).unwrap();
+        assert_eq!(
+            plan.ops,
+            [SyncOp::Conflict {
+                path: PathBuf::from("new.png"),
+                reason: "Deleted in the library since the last sync".to_string(),
+                resolution: Resolution::Skip,
+            }]
+        );
+        if let SyncOp::Conflict { resolution, .. } = &mut plan.ops[0] {
+            *resolution = Resolution::UseLibrary;
+        }
+        apply(&plan, &mut |_, _| true);
+        assert!(!mirror.join("new.png").exists());
+    }
+}
@@ -0,0 +1,213 @@
+use anyhow::Result;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+use crate::{i18n::tr, index::LibraryIndex, labels};
+
+// Most suggestions shown below the tags field.
+const MAX_SUGGESTIONS: usize = 8;
+// Most destination folders suggested from the tags.
+const MAX_DESTINATIONS: usize = 3;
+
+/// Splits comma separated tags. Blank ones are dropped.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// The tag being typed: what follows the last comma.
+fn last_fragment(text: &str) -> &str {
+    text.rsplit(',').next().unwrap_or("").trim_start()
+}
+
+/// Known tags that complete the one being typed at the end of `text`. Tags that start with it
+/// come first, then the ones that contain it. Tags already entered are left out.
+pub fn suggestions<'a>(known: &'a [(String, usize)], text: &str) -> Vec<&'a str> {
+    let typed = last_fragment(text).to_lowercase();
+    if typed.is_empty() {
+        return Vec::new();
+    }
+    let before = text.rfind(',').map_or("", |comma| &text[..comma]);
+    let entered: Vec<String> = parse_tags(before)
+        .iter()
+        .map(|tag| tag.to_lowercase())
+        .collect();
+    let candidates = known
+        .iter()
+        .map(|(tag, _)| tag.as_str())
+        .filter(|tag| !entered.contains(&tag.to_lowercase()));
+    let (mut starts, contains): (Vec<&str>, Vec<&str>) = candidates
+        .filter(|tag| tag.to_lowercase().contains(&typed))
+        .partition(|tag| tag.to_lowercase().starts_with(&typed));
+    starts.extend(contains);
+    starts.truncate(MAX_SUGGESTIONS);
+    starts
+}
+
+/// Replaces the tag being typed at the end of `text` with `tag`, ready for the next one.
+pub fn complete(text: &str, tag: &str) -> String {
+    let kept = match text.rfind(',') {
+        Some(comma) => format!("{} ", &text[..=comma]),
+        None => String::new(),
+    };
+    format!("{}{}, ", kept, tag)
+}
+
+/// What the user asked for in the tags row.
+pub enum TagAction {
+    // Only show the images with this tag.
+    Filter(String),
+    MoveTo(PathBuf),
+}
+
+/// Tags field of the current image. Tags are kept in the library index and the XMP sidecar.
+#[derive(Default)]
+pub struct TagEditor {
+    path: Option<PathBuf>,
+    text: String,
+    known: Vec<(String, usize)>,
+    // Folders suggested as the destination of `path`.
+    destinations: Vec<(PathBuf, usize)>,
+}
+
+impl TagEditor {
+    /// Loads the tags of `current` when it changed since the last call.
+    pub fn sync(&mut self, index: &LibraryIndex, current: Option<&Path>) -> Result<()> {
+        if self.path.as_deref() == current {
+            return Ok(());
+        }
+        self.path = current.map(|path| path.to_path_buf());
+        self.text = match current {
+            Some(path) => index.tags(path)?.join(", "),
+            None => String::new(),
+        };
+        self.refresh(index)
+    }
+
+    // Rereads what depends on the tags of every image.
+    fn refresh(&mut self, index: &LibraryIndex) -> Result<()> {
+        self.known = index.all_tags()?;
+        self.destinations = match &self.path {
+            Some(path) => index.tag_folders(&parse_tags(&self.text), path)?,
+            None => Vec::new(),
+        };
+        Ok(())
+    }
+
+    /// Draws the tags row. `letters` are the registered destinations, whose keys are shown next
+    /// to suggested folders. Folders in `sources` are never suggested. Tags cannot be changed
+    /// when `read_only`, since they are written to the sidecar.
+    #[allow(clippy::too_many_arguments)]
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: &mut LibraryIndex,
+        read_only: bool,
+        suggest_destinations: bool,
+        letters: &[(PathBuf, char)],
+        sources: &[PathBuf],
+    ) -> Result<Option<TagAction>> {
+        let Some(path) = self.path.clone() else {
+            return Ok(None);
+        };
+        let mut action = None;
+        let mut save = false;
+        ui.horizontal(|ui| {
+            ui.label(tr!("tags"));
+            let response = ui.add_enabled(
+                !read_only,
+                egui::TextEdit::singleline(&mut self.text).hint_text(tr!("tags-hint")),
+            );
+            save = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            save |= ui
+                .add_enabled(!read_only, egui::Button::new(tr!("save")))
+                .clicked();
+            ui.menu_button(tr!("show-only"), |ui| {
+                for (tag, count) in &self.known {
+                    if ui.button(format!("{} ({})", tag, count)).clicked() {
+                        action = Some(TagAction::Filter(tag.clone()));
+                        ui.close_menu();
+                    }
+                }
+                if self.known.is_empty() {
+                    ui.label(tr!("no-tags"));
+                }
+            });
+
+            let mut completed = None;
+            for tag in suggestions(&self.known, &self.text) {
+                if ui.small_button(tag).clicked() {
+                    completed = Some(complete(&self.text, tag));
+                }
+            }
+            if let Some(text) = completed {
+                self.text = text;
+                response.request_focus();
+            }
+        });
+        if save {
+            index.set_tags(&path, &parse_tags(&self.text))?;
+            let tags = index.tags(&path)?;
+            // Also as keywords in the XMP sidecar, for other photo managers.
+            labels::write_tags(&path, &tags)?;
+            self.text = tags.join(", ");
+            self.refresh(index)?;
+        }
+
+        let destinations: Vec<&PathBuf> = self
+            .destinations
+            .iter()
+            .map(|(folder, _)| folder)
+            .filter(|folder| !sources.contains(folder) && folder.is_dir())
+            .take(MAX_DESTINATIONS)
+            .collect();
+        if suggest_destinations && !destinations.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label(tr!("suggested"))
+                    .on_hover_text(tr!("suggested-hint"));
+                for folder in destinations {
+                    let name = folder.file_name().unwrap_or_default().to_string_lossy();
+                    let text = match letters.iter().find(|(dest, _)| dest == folder) {
+                        Some((_, letter)) => format!("{} ({})", name, letter),
+                        None => name.to_string(),
+                    };
+                    if ui
+                        .button(text)
+                        .on_hover_text(folder.to_string_lossy())
+                        .clicked()
+                    {
+                        action = Some(TagAction::MoveTo(folder.clone()));
+                    }
+                }
+            });
+        }
+        Ok(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autocomplete_test() {
+        let known = vec![
+            ("cat".to_string(), 5),
+            ("bobcat".to_string(), 2),
+            ("car".to_string(), 1),
+            ("dog".to_string(), 1),
+        ];
+        assert!(suggestions(&known, "").is_empty());
+        assert!(suggestions(&known, "dog, ").is_empty());
+        assert_eq!(suggestions(&known, "CA"), vec!["cat", "car", "bobcat"]);
+        assert_eq!(suggestions(&known, "dog, cat"), vec!["cat", "bobcat"]);
+        assert_eq!(suggestions(&known, "cat, ca"), vec!["car", "bobcat"]);
+
+        assert_eq!(complete("ca", "cat"), "cat, ");
+        assert_eq!(complete("dog,ca", "cat"), "dog, cat, ");
+        assert_eq!(parse_tags("dog, cat, , "), vec!["dog", "cat"]);
+    }
+}
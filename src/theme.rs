@@ -0,0 +1,152 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::i18n::tr;
+
+// Side of a checkerboard square in points.
+const CHECKER_SIZE: f32 = 8.0;
+/// Size of body text in egui's default style.
+pub const DEFAULT_FONT_SIZE: f32 = 12.5;
+
+/// What is drawn behind images, visible through transparent pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageBackground {
+    // The window background.
+    #[default]
+    None,
+    Checkerboard,
+    Black,
+    Gray,
+    White,
+}
+
+impl ImageBackground {
+    pub const ALL: [ImageBackground; 5] = [
+        ImageBackground::None,
+        ImageBackground::Checkerboard,
+        ImageBackground::Black,
+        ImageBackground::Gray,
+        ImageBackground::White,
+    ];
+
+    pub fn name(self) -> String {
+        match self {
+            ImageBackground::None => tr!("background-none"),
+            ImageBackground::Checkerboard => tr!("background-checkerboard"),
+            ImageBackground::Black => tr!("background-black"),
+            ImageBackground::Gray => tr!("background-gray"),
+            ImageBackground::White => tr!("background-white"),
+        }
+    }
+
+    /// Shapes that fill `rect`. Only the part inside `clip` is drawn, since images shown at
+    /// full resolution can be far larger than the window.
+    pub fn shapes(self, rect: egui::Rect, clip: egui::Rect) -> Vec<egui::Shape> {
+        let fill = |color| vec![egui::Shape::rect_filled(rect, 0.0, color)];
+        match self {
+            ImageBackground::None => Vec::new(),
+            ImageBackground::Black => fill(egui::Color32::BLACK),
+            ImageBackground::Gray => fill(egui::Color32::from_gray(128)),
+            ImageBackground::White => fill(egui::Color32::WHITE),
+            ImageBackground::Checkerboard => checkerboard(rect, clip),
+        }
+    }
+}
+
+fn checkerboard(rect: egui::Rect, clip: egui::Rect) -> Vec<egui::Shape> {
+    let visible = rect.intersect(clip);
+    if !visible.is_positive() {
+        return Vec::new();
+    }
+    let mut shapes = vec![egui::Shape::rect_filled(
+        visible,
+        0.0,
+        egui::Color32::from_gray(204),
+    )];
+    // Squares are counted from the corner of the image so that they stay put while scrolling.
+    let first_column = ((visible.left() - rect.left()) / CHECKER_SIZE).floor() as i64;
+    let first_row = ((visible.top() - rect.top()) / CHECKER_SIZE).floor() as i64;
+    let columns = (visible.width() / CHECKER_SIZE).ceil() as i64 + 1;
+    let rows = (visible.height() / CHECKER_SIZE).ceil() as i64 + 1;
+    for row in first_row..first_row + rows {
+        for column in first_column..first_column + columns {
+            if (row + column) % 2 == 0 {
+                continue;
+            }
+            let min = rect.min + egui::vec2(column as f32, row as f32) * CHECKER_SIZE;
+            let square =
+                egui::Rect::from_min_size(min, egui::Vec2::splat(CHECKER_SIZE)).intersect(visible);
+            if square.is_positive() {
+                shapes.push(egui::Shape::rect_filled(
+                    square,
+                    0.0,
+                    egui::Color32::from_gray(153),
+                ));
+            }
+        }
+    }
+    shapes
+}
+
+/// Sets the theme, with `accent` for selections and links if there is one.
+pub fn apply(ctx: &egui::Context, theme: egui::ThemePreference, accent: Option<[u8; 3]>) {
+    ctx.set_theme(theme);
+    for (theme, visuals) in [
+        (egui::Theme::Dark, egui::Visuals::dark()),
+        (egui::Theme::Light, egui::Visuals::light()),
+    ] {
+        ctx.set_visuals_of(theme, visuals);
+        if let Some([r, g, b]) = accent {
+            let color = egui::Color32::from_rgb(r, g, b);
+            ctx.style_mut_of(theme, |style| {
+                style.visuals.selection.bg_fill = color;
+                style.visuals.hyperlink_color = color;
+            });
+        }
+    }
+}
+
+// egui's text styles with body text at `size`. The others keep their size relative to it.
+fn text_styles(size: f32) -> BTreeMap<egui::TextStyle, egui::FontId> {
+    let mut styles = egui::style::default_text_styles();
+    for font in styles.values_mut() {
+        font.size *= size / DEFAULT_FONT_SIZE;
+    }
+    styles
+}
+
+/// Makes body text `size` points tall in both themes.
+pub fn apply_font_size(ctx: &egui::Context, size: f32) {
+    let styles = text_styles(size);
+    ctx.all_styles_mut(|style| style.text_styles = styles.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkerboard_test() {
+        let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(32.0, 16.0));
+        // A light base and every other square of 4 x 2.
+        assert_eq!(ImageBackground::Checkerboard.shapes(rect, rect).len(), 5);
+        // Only what is visible.
+        let clip = egui::Rect::from_min_size(egui::pos2(8.0, 0.0), egui::vec2(8.0, 8.0));
+        let shapes = ImageBackground::Checkerboard.shapes(rect, clip);
+        assert_eq!(shapes.len(), 2);
+        assert_eq!(shapes[1].visual_bounding_rect(), clip);
+        assert!(ImageBackground::None.shapes(rect, rect).is_empty());
+    }
+
+    #[test]
+    fn text_styles_test() {
+        let styles = text_styles(25.0);
+        assert_eq!(styles[&egui::TextStyle::Body].size, 25.0);
+        assert_eq!(styles[&egui::TextStyle::Heading].size, 36.0);
+        assert_eq!(
+            styles[&egui::TextStyle::Monospace].family,
+            egui::FontFamily::Monospace
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use eframe::egui;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{archive, raw};
+
+// Longest side of a thumbnail in pixels.
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+// Prune the cache after this many thumbnails were created.
+const PRUNE_INTERVAL: usize = 100;
+
+/// Thumbnails stored on disk. A thumbnail is keyed by the source path, size, and modification
+/// time so that edited files get a new thumbnail.
+pub struct ThumbnailStore {
+    dir: PathBuf,
+    max_bytes: AtomicU64,
+}
+
+impl ThumbnailStore {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self {
+            dir,
+            max_bytes: AtomicU64::new(max_bytes),
+        }
+    }
+
+    pub fn set_max_bytes(&self, max_bytes: u64) {
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+    }
+
+    fn thumbnail_path(&self, path: &Path) -> io::Result<PathBuf> {
+        // An image in an archive changes with the archive.
+        let metadata = fs::metadata(archive::split(path).map_or(path, |(archive, _)| archive))?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        modified.hash(&mut hasher);
+        Ok(self.dir.join(format!("{:016x}.png", hasher.finish())))
+    }
+
+    /// Returns the path to the thumbnail of `path`, creating it if needed.
+    pub fn get_or_create(&self, path: &Path) -> Result<PathBuf, String> {
+        let thumbnail_path = self.thumbnail_path(path).map_err(|e| e.to_string())?;
+        if thumbnail_path.exists() {
+            // The modification time is used to decide what to prune.
+            if let Ok(file) = fs::File::options().append(true).open(&thumbnail_path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            return Ok(thumbnail_path);
+        }
+
+        let image = raw::decode_file(path)?;
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        image
+            .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+            .to_rgba8()
+            .save(&thumbnail_path)
+            .map_err(|e| e.to_string())?;
+        Ok(thumbnail_path)
+    }
+
+    fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let Ok(read_dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        read_dir
+            .flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+                Some((entry.path(), metadata.len(), modified))
+            })
+            .collect()
+    }
+
+    /// Total size of the cached thumbnails.
+    pub fn disk_usage(&self) -> u64 {
+        self.entries().iter().map(|(_, size, _)| size).sum()
+    }
+
+    /// Deletes the least recently used thumbnails until the cache fits in the limit.
+    pub fn prune(&self) {
+        let max_bytes = self.max_bytes.load(Ordering::Relaxed);
+        let mut entries = self.entries();
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if total <= max_bytes {
+            return;
+        }
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => total -= size,
+                Err(e) => log::warn!("Failed to remove thumbnail {}: {}", path.display(), e),
+            }
+        }
+        log::info!("Pruned thumbnail cache to {} bytes.", total);
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        for (path, _, _) in self.entries() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+enum ThumbnailState {
+    Pending,
+    Ready(String),
+    Failed,
+}
+
+/// Creates thumbnails on a background thread and hands out URIs for the finished ones.
+pub struct Thumbnails {
+    store: Arc<ThumbnailStore>,
+    sender: mpsc::Sender<PathBuf>,
+    receiver: mpsc::Receiver<(PathBuf, Result<PathBuf, String>)>,
+    states: HashMap<PathBuf, ThumbnailState>,
+    // URIs drawn in the current and the previous frame. Thumbnails that scrolled out of view are
+    // unloaded from egui.
+    shown: HashSet<String>,
+    shown_last_frame: HashSet<String>,
+}
+
+impl Thumbnails {
+    pub fn new(store: ThumbnailStore) -> Self {
+        let store = Arc::new(store);
+        let (sender, request_receiver) = mpsc::channel::<PathBuf>();
+        let (result_sender, receiver) = mpsc::channel();
+        let worker_store = store.clone();
+        thread::spawn(move || {
+            worker_store.prune();
+            let mut created = 0;
+            for path in request_receiver {
+                let result = worker_store.get_or_create(&path);
+                if result_sender.send((path, result)).is_err() {
+                    return;
+                }
+                created += 1;
+                if created % PRUNE_INTERVAL == 0 {
+                    worker_store.prune();
+                }
+            }
+        });
+        Self {
+            store,
+            sender,
+            receiver,
+            states: HashMap::new(),
+            shown: HashSet::new(),
+            shown_last_frame: HashSet::new(),
+        }
+    }
+
+    pub fn store(&self) -> &ThumbnailStore {
+        &self.store
+    }
+
+    /// Collects the thumbnails finished since the last call. Returns true if there were any.
+    pub fn poll(&mut self) -> bool {
+        let mut updated = false;
+        while let Ok((path, result)) = self.receiver.try_recv() {
+            let state = match result {
+                Ok(thumbnail_path) => {
+                    ThumbnailState::Ready(format!("file://{}", thumbnail_path.display()))
+                }
+                Err(e) => {
+                    log::warn!("Failed to create thumbnail for {}: {}", path.display(), e);
+                    ThumbnailState::Failed
+                }
+            };
+            self.states.insert(path, state);
+            updated = true;
+        }
+        updated
+    }
+
+    /// The thumbnail for `path`, or None if it is still being created or failed.
+    pub fn get(&mut self, path: &Path) -> Option<egui::Image<'static>> {
+        match self.states.get(path) {
+            Some(ThumbnailState::Ready(uri)) => {
+                self.shown.insert(uri.clone());
+                Some(egui::Image::from_uri(uri.clone()))
+            }
+            Some(ThumbnailState::Pending) | Some(ThumbnailState::Failed) => None,
+            None => {
+                self.states
+                    .insert(path.to_path_buf(), ThumbnailState::Pending);
+                let _ = self.sender.send(path.to_path_buf());
+                None
+            }
+        }
+    }
+
+    /// Draws the thumbnail of `path` in a `size` by `size` cell. `selected` draws a frame around
+    /// it.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        path: &Path,
+        size: f32,
+        selected: bool,
+    ) -> egui::Response {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::splat(size), egui::Sense::click());
+        self.paint(ui, rect, path, selected);
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        response.widget_info(|| {
+            egui::WidgetInfo::selected(egui::WidgetType::ImageButton, true, selected, &file_name)
+        });
+        response.on_hover_text(file_name)
+    }
+
+    /// Draws the thumbnail of `path` in `rect`, which must already be allocated.
+    pub fn paint(&mut self, ui: &egui::Ui, rect: egui::Rect, path: &Path, selected: bool) {
+        if ui.is_rect_visible(rect) {
+            match self.get(path) {
+                Some(image) => {
+                    let image_size = image
+                        .load_and_calc_size(ui, rect.size())
+                        .unwrap_or(rect.size());
+                    image.paint_at(ui, egui::Rect::from_center_size(rect.center(), image_size));
+                }
+                None => {
+                    ui.painter()
+                        .rect_filled(rect, 2.0, ui.visuals().faint_bg_color);
+                }
+            }
+            if selected {
+                ui.painter()
+                    .rect_stroke(rect, 2.0, ui.visuals().selection.stroke);
+            }
+        }
+    }
+
+    /// Call once per frame after drawing. Unloads thumbnails that are no longer shown.
+    pub fn end_frame(&mut self, ctx: &egui::Context) {
+        for uri in self.shown_last_frame.difference(&self.shown) {
+            ctx.forget_image(uri);
+        }
+        self.shown_last_frame = std::mem::take(&mut self.shown);
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending() > 0
+    }
+
+    /// The number of thumbnails that are still being created.
+    pub fn pending(&self) -> usize {
+        self.states
+            .values()
+            .filter(|state| matches!(state, ThumbnailState::Pending))
+            .count()
+    }
+
+    pub fn clear(&mut self, ctx: &egui::Context) -> io::Result<()> {
+        for state in self.states.values() {
+            if let ThumbnailState::Ready(uri) = state {
+                ctx.forget_image(uri);
+            }
+        }
+        self.states.clear();
+        self.store.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, width: u32, height: u32) {
+        image::RgbImage::new(width, height).save(path).unwrap();
+    }
+
+    #[test]
+    fn get_or_create_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = ThumbnailStore::new(temp_dir.path().join("cache"), u64::MAX);
+        let src = temp_dir.path().join("big.png");
+        write_png(&src, 1024, 512);
+
+        let thumbnail = store.get_or_create(&src).unwrap();
+        let (width, height) = image::image_dimensions(&thumbnail).unwrap();
+        assert_eq!((width, height), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2));
+        // Cached.
+        assert_eq!(store.get_or_create(&src).unwrap(), thumbnail);
+
+        // A changed file gets a new thumbnail.
+        write_png(&src, 64, 64);
+        assert_ne!(store.get_or_create(&src).unwrap(), thumbnail);
+
+        store.clear().unwrap();
+        assert_eq!(store.disk_usage(), 0);
+    }
+
+    #[test]
+    fn prune_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = ThumbnailStore::new(temp_dir.path().join("cache"), u64::MAX);
+        for i in 0..3 {
+            let src = temp_dir.path().join(format!("{}.png", i));
+            write_png(&src, 32, 32);
+            store.get_or_create(&src).unwrap();
+        }
+        let usage = store.disk_usage();
+        assert!(usage > 0);
+
+        store.set_max_bytes(usage - 1);
+        store.prune();
+        assert!(store.disk_usage() < usage);
+        assert_eq!(store.entries().len(), 2);
+    }
+}
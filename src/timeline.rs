@@ -0,0 +1,552 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{i18n::tr, metadata, thumbnails::Thumbnails};
+
+// Size of each thumbnail.
+const CELL_SIZE: f32 = 128.0;
+
+// Height of the row with the date and the buttons of a day.
+const HEADER_HEIGHT: f32 = 32.0;
+
+// The gap between events when the timeline starts.
+const DEFAULT_EVENT_GAP_HOURS: u32 = 4;
+
+/// Images taken together, as indices into the list of images. `span` is the first and the last
+/// date among them, or None for the images whose date is not known (yet).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    pub span: Option<(String, String)>,
+    pub indices: Vec<usize>,
+}
+
+// The images without a date, as the last group if there are any.
+fn push_unknown(groups: &mut Vec<Group>, unknown: Vec<usize>) {
+    if !unknown.is_empty() {
+        groups.push(Group {
+            span: None,
+            indices: unknown,
+        });
+    }
+}
+
+/// Groups `images` by the day `date` gives, newest first, like sorting by date does. Images
+/// keep their order within a day. Those without a date come last.
+pub fn group_by_day<'a>(images: &[PathBuf], date: impl Fn(&Path) -> Option<&'a str>) -> Vec<Group> {
+    let mut days: BTreeMap<&str, (Vec<usize>, &str, &str)> = BTreeMap::new();
+    let mut unknown = Vec::new();
+    for (index, path) in images.iter().enumerate() {
+        // "2024-01-31 12:34:56" is on "2024-01-31".
+        let Some((date, day)) = date(path).and_then(|date| Some((date, date.get(..10)?))) else {
+            unknown.push(index);
+            continue;
+        };
+        let (indices, first, last) = days.entry(day).or_insert((Vec::new(), date, date));
+        indices.push(index);
+        *first = (*first).min(date);
+        *last = (*last).max(date);
+    }
+    let mut groups: Vec<Group> = days
+        .into_values()
+        .rev()
+        .map(|(indices, first, last)| Group {
+            span: Some((first.to_string(), last.to_string())),
+            indices,
+        })
+        .collect();
+    push_unknown(&mut groups, unknown);
+    groups
+}
+
+/// Groups `images` into events: runs of images taken at most `gap_secs` after the one before.
+/// The newest event comes first, and images keep their order within an event. Those without a
+/// date come last.
+pub fn group_by_event<'a>(
+    images: &[PathBuf],
+    date: impl Fn(&Path) -> Option<&'a str>,
+    gap_secs: i64,
+) -> Vec<Group> {
+    let mut dated = Vec::new();
+    let mut unknown = Vec::new();
+    for (index, path) in images.iter().enumerate() {
+        match date(path).and_then(|date| Some((date, metadata::parse_timestamp(date)?))) {
+            Some((date, secs)) => dated.push((secs, date, index)),
+            None => unknown.push(index),
+        }
+    }
+    dated.sort();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut previous = None;
+    for (secs, date, index) in dated {
+        match groups.last_mut() {
+            Some(Group {
+                span: Some((_, last)),
+                indices,
+            }) if previous.is_some_and(|previous| secs - previous <= gap_secs) => {
+                *last = date.to_string();
+                indices.push(index);
+            }
+            _ => groups.push(Group {
+                span: Some((date.to_string(), date.to_string())),
+                indices: vec![index],
+            }),
+        }
+        previous = Some(secs);
+    }
+    for group in &mut groups {
+        group.indices.sort_unstable();
+    }
+    groups.reverse();
+    push_unknown(&mut groups, unknown);
+    groups
+}
+
+/// The name of the folder for the images of `span`, from the days it was on, e.g. "2024-01-31"
+/// or "2024-01-31 - 2024-02-01".
+pub fn folder_name((first, last): &(String, String)) -> String {
+    let (first, last) = (day(first), day(last));
+    if first == last {
+        first.to_string()
+    } else {
+        format!("{} - {}", first, last)
+    }
+}
+
+fn day(date: &str) -> &str {
+    date.get(..10).unwrap_or(date)
+}
+
+// "2024-01-31 12:00 – 15:30", or with the day of the end too if it is another one.
+fn describe_span((first, last): &(String, String)) -> String {
+    let minutes = |date: &str| date.get(..16).unwrap_or(date).to_string();
+    let end = if day(first) == day(last) {
+        minutes(last).get(11..).unwrap_or_default().to_string()
+    } else {
+        minutes(last)
+    };
+    format!("{} – {}", minutes(first), end)
+}
+
+/// How the timeline groups the images.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Grouping {
+    Day,
+    // Split where more than this many hours pass between images.
+    Event { gap_hours: u32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Row {
+    // The header of the group at this index.
+    Header(usize),
+    // Thumbnails of a group, from `start` in its images.
+    Images { group: usize, start: usize },
+}
+
+fn rows(groups: &[Group], columns: usize) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for (index, group) in groups.iter().enumerate() {
+        rows.push(Row::Header(index));
+        rows.extend(
+            (0..group.indices.len())
+                .step_by(columns)
+                .map(|start| Row::Images {
+                    group: index,
+                    start,
+                }),
+        );
+    }
+    rows
+}
+
+/// What the user asked for in the timeline.
+pub enum TimelineAction {
+    // Show the image at this index.
+    GoTo(usize),
+    // Only show the images of this group, named `name`.
+    Show {
+        name: String,
+        paths: Vec<PathBuf>,
+    },
+    MoveTo {
+        paths: Vec<PathBuf>,
+        folder: PathBuf,
+    },
+    // Like `MoveTo`, into a folder that may not exist yet.
+    MoveToNew {
+        paths: Vec<PathBuf>,
+        folder: PathBuf,
+    },
+}
+
+/// The images grouped by the day or the event they were taken on, with a header for each group.
+/// Only the visible rows are drawn.
+pub struct Timeline {
+    groups: Vec<Group>,
+    grouping: Grouping,
+    // The gap of the event grouping, kept while grouping by day.
+    gap_hours: u32,
+    // Grouping is linear in the number of images, so it only happens when this changes.
+    key: Option<(usize, Option<PathBuf>, usize, Grouping)>,
+    // The group to scroll to in the next frame.
+    jump_to: Option<usize>,
+    // Where "Move to new folder" creates the folder of an event.
+    events_folder: Option<PathBuf>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            groups: Vec::new(),
+            grouping: Grouping::Day,
+            gap_hours: DEFAULT_EVENT_GAP_HOURS,
+            key: None,
+            jump_to: None,
+            events_folder: None,
+        }
+    }
+}
+
+impl Timeline {
+    /// Groups the images again if they or the known dates changed. `num_dates` is the number of
+    /// dates `date` knows.
+    pub fn update<'a>(
+        &mut self,
+        images: &[PathBuf],
+        date: impl Fn(&Path) -> Option<&'a str>,
+        num_dates: usize,
+    ) {
+        let key = (
+            images.len(),
+            images.first().cloned(),
+            num_dates,
+            self.grouping,
+        );
+        if self.key.as_ref() == Some(&key) {
+            return;
+        }
+        self.groups = match self.grouping {
+            Grouping::Day => group_by_day(images, date),
+            Grouping::Event { gap_hours } => {
+                group_by_event(images, date, i64::from(gap_hours) * 3600)
+            }
+        };
+        self.key = Some(key);
+    }
+
+    fn group_name(&self, group: &Group) -> String {
+        match (&group.span, self.grouping) {
+            (None, _) => tr!("timeline-unknown-date"),
+            (Some((first, _)), Grouping::Day) => day(first).to_string(),
+            (Some(span), Grouping::Event { .. }) => describe_span(span),
+        }
+    }
+
+    // The choice between days and events, and where events are moved to.
+    fn show_grouping(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let by_event = matches!(self.grouping, Grouping::Event { .. });
+            if ui
+                .radio(!by_event, tr!("timeline-by-day"))
+                .on_hover_text(tr!("timeline-by-day-hint"))
+                .clicked()
+            {
+                self.grouping = Grouping::Day;
+            }
+            if ui
+                .radio(by_event, tr!("timeline-by-event"))
+                .on_hover_text(tr!("timeline-by-event-hint"))
+                .clicked()
+            {
+                self.grouping = Grouping::Event {
+                    gap_hours: self.gap_hours,
+                };
+            }
+            ui.add_enabled_ui(by_event, |ui| {
+                ui.label(tr!("timeline-event-gap"));
+                let gap = ui.add(
+                    egui::DragValue::new(&mut self.gap_hours)
+                        .range(1..=168)
+                        .suffix(" h"),
+                );
+                if gap.changed() && by_event {
+                    self.grouping = Grouping::Event {
+                        gap_hours: self.gap_hours,
+                    };
+                }
+            });
+        });
+        if !matches!(self.grouping, Grouping::Event { .. }) {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label(tr!("timeline-events-folder"));
+            match &self.events_folder {
+                Some(folder) => ui.monospace(folder.to_string_lossy()),
+                None => ui.weak(tr!("timeline-events-folder-unset")),
+            };
+            if ui.button(tr!("timeline-choose-folder")).clicked() {
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    self.events_folder = Some(folder);
+                }
+            }
+        });
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        thumbnails: &mut Thumbnails,
+        images: &[PathBuf],
+        current: usize,
+    ) -> Option<TimelineAction> {
+        let mut action = None;
+        self.show_grouping(ui);
+        ui.horizontal(|ui| {
+            let count = self.groups.len();
+            ui.label(match self.grouping {
+                Grouping::Day => tr!("timeline-days", days = count),
+                Grouping::Event { .. } => tr!("timeline-events", events = count),
+            });
+            egui::ComboBox::from_id_salt("timeline_jump")
+                .selected_text(tr!("timeline-jump"))
+                .show_ui(ui, |ui| {
+                    for (index, group) in self.groups.iter().enumerate() {
+                        let text = format!("{} ({})", self.group_name(group), group.indices.len());
+                        if ui.selectable_label(false, text).clicked() {
+                            self.jump_to = Some(index);
+                        }
+                    }
+                });
+        });
+        ui.separator();
+
+        let spacing = ui.spacing().item_spacing;
+        let stride = CELL_SIZE + spacing.x;
+        let columns = ((ui.available_width() / stride).floor() as usize).max(1);
+        let rows = rows(&self.groups, columns);
+        let height = |row: &Row| match row {
+            Row::Header(_) => HEADER_HEIGHT,
+            Row::Images { .. } => CELL_SIZE + spacing.y,
+        };
+        let tops: Vec<f32> = rows
+            .iter()
+            .scan(0.0, |top, row| {
+                let row_top = *top;
+                *top += height(row);
+                Some(row_top)
+            })
+            .collect();
+        let total_height = rows
+            .last()
+            .map_or(0.0, |row| tops[tops.len() - 1] + height(row));
+
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .id_salt("timeline")
+            .auto_shrink([false, false]);
+        if let Some(group) = self.jump_to.take() {
+            if let Some(row) = rows.iter().position(|row| *row == Row::Header(group)) {
+                scroll_area = scroll_area.vertical_scroll_offset(tops[row]);
+            }
+        }
+        scroll_area.show_viewport(ui, |ui, viewport| {
+            let origin = ui.min_rect().min;
+            ui.set_height(total_height);
+            ui.set_width(ui.available_width());
+            let first = tops
+                .partition_point(|top| *top < viewport.min.y)
+                .saturating_sub(1);
+            for (row, top) in rows.iter().zip(&tops).skip(first) {
+                if *top > viewport.max.y {
+                    break;
+                }
+                match *row {
+                    Row::Header(index) => {
+                        let group = &self.groups[index];
+                        let rect = egui::Rect::from_min_size(
+                            origin + egui::vec2(0.0, *top),
+                            egui::vec2(ui.available_width(), HEADER_HEIGHT),
+                        );
+                        let mut header = ui.new_child(
+                            egui::UiBuilder::new()
+                                .max_rect(rect)
+                                .layout(egui::Layout::left_to_right(egui::Align::Center)),
+                        );
+                        let name = self.group_name(group);
+                        header.strong(&name);
+                        header.label(tr!("timeline-images", count = group.indices.len()));
+                        let paths = || {
+                            group
+                                .indices
+                                .iter()
+                                .map(|&index| images[index].clone())
+                                .collect()
+                        };
+                        if header.button(tr!("timeline-show-day")).clicked() {
+                            action = Some(TimelineAction::Show {
+                                name,
+                                paths: paths(),
+                            });
+                        }
+                        if header.button(tr!("timeline-move-day")).clicked() {
+                            if let Some(folder) = FileDialog::new().pick_folder() {
+                                action = Some(TimelineAction::MoveTo {
+                                    paths: paths(),
+                                    folder,
+                                });
+                            }
+                        }
+                        let (Grouping::Event { .. }, Some(span)) = (self.grouping, &group.span)
+                        else {
+                            continue;
+                        };
+                        let name = folder_name(span);
+                        let button = header.add_enabled(
+                            self.events_folder.is_some(),
+                            egui::Button::new(tr!("timeline-move-event", folder = name.as_str())),
+                        );
+                        let button = match &self.events_folder {
+                            Some(parent) => {
+                                button.on_hover_text(parent.join(&name).to_string_lossy())
+                            }
+                            None => {
+                                button.on_disabled_hover_text(tr!("timeline-events-folder-unset"))
+                            }
+                        };
+                        if button.clicked() {
+                            if let Some(parent) = &self.events_folder {
+                                action = Some(TimelineAction::MoveToNew {
+                                    paths: paths(),
+                                    folder: parent.join(name),
+                                });
+                            }
+                        }
+                    }
+                    Row::Images { group, start } => {
+                        let indices = &self.groups[group].indices;
+                        let end = (start + columns).min(indices.len());
+                        for (column, &index) in indices[start..end].iter().enumerate() {
+                            let rect = egui::Rect::from_min_size(
+                                origin + egui::vec2(column as f32 * stride, *top),
+                                egui::Vec2::splat(CELL_SIZE),
+                            );
+                            let response = ui.allocate_rect(rect, egui::Sense::click());
+                            thumbnails.paint(ui, rect, &images[index], index == current);
+                            let file_name = images[index]
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy();
+                            if response.on_hover_text(file_name).clicked() {
+                                action = Some(TimelineAction::GoTo(index));
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn group_by_day_test() {
+        let images: Vec<PathBuf> = ["a.jpg", "b.jpg", "c.jpg", "d.jpg"]
+            .map(PathBuf::from)
+            .to_vec();
+        let dates = HashMap::from([
+            (PathBuf::from("a.jpg"), "2024-01-31 23:59:59".to_string()),
+            (PathBuf::from("b.jpg"), "2024-02-01 00:00:00".to_string()),
+            (PathBuf::from("d.jpg"), "2024-01-31 08:00:00".to_string()),
+        ]);
+        let days = group_by_day(&images, |path| dates.get(path).map(|date| date.as_str()));
+        let span = |first: &str, last: &str| Some((first.to_string(), last.to_string()));
+        assert_eq!(
+            days,
+            vec![
+                Group {
+                    span: span("2024-02-01 00:00:00", "2024-02-01 00:00:00"),
+                    indices: vec![1],
+                },
+                Group {
+                    span: span("2024-01-31 08:00:00", "2024-01-31 23:59:59"),
+                    indices: vec![0, 3],
+                },
+                Group {
+                    span: None,
+                    indices: vec![2],
+                },
+            ]
+        );
+
+        assert_eq!(
+            rows(&days, 1),
+            vec![
+                Row::Header(0),
+                Row::Images { group: 0, start: 0 },
+                Row::Header(1),
+                Row::Images { group: 1, start: 0 },
+                Row::Images { group: 1, start: 1 },
+                Row::Header(2),
+                Row::Images { group: 2, start: 0 },
+            ]
+        );
+        assert_eq!(rows(&days, 4).len(), 6);
+    }
+
+    #[test]
+    fn group_by_event_test() {
+        let dates = HashMap::from([
+            (PathBuf::from("a.jpg"), "2024-01-31 22:00:00"),
+            (PathBuf::from("b.jpg"), "2024-02-01 01:30:00"),
+            (PathBuf::from("c.jpg"), "2024-01-31 12:00:00"),
+            (PathBuf::from("d.jpg"), "2024-01-31 15:30:00"),
+            (PathBuf::from("e.jpg"), "unknown"),
+        ]);
+        let images: Vec<PathBuf> = ["a.jpg", "b.jpg", "c.jpg", "d.jpg", "e.jpg", "f.jpg"]
+            .map(PathBuf::from)
+            .to_vec();
+        let events = group_by_event(&images, |path| dates.get(path).copied(), 4 * 3600);
+        let span = |first: &str, last: &str| Some((first.to_string(), last.to_string()));
+        assert_eq!(
+            events,
+            vec![
+                // Past midnight, but only 3.5 hours later.
+                Group {
+                    span: span("2024-01-31 22:00:00", "2024-02-01 01:30:00"),
+                    indices: vec![0, 1],
+                },
+                Group {
+                    span: span("2024-01-31 12:00:00", "2024-01-31 15:30:00"),
+                    indices: vec![2, 3],
+                },
+                Group {
+                    span: None,
+                    indices: vec![4, 5],
+                },
+            ]
+        );
+        // Split with a shorter gap.
+        assert_eq!(
+            group_by_event(&images, |path| dates.get(path).copied(), 3600).len(),
+            5
+        );
+
+        let (late, day) = (events[0].span.as_ref(), events[1].span.as_ref());
+        assert_eq!(folder_name(day.unwrap()), "2024-01-31");
+        assert_eq!(folder_name(late.unwrap()), "2024-01-31 - 2024-02-01");
+        assert_eq!(describe_span(day.unwrap()), "2024-01-31 12:00 – 15:30");
+        assert_eq!(
+            describe_span(late.unwrap()),
+            "2024-01-31 22:00 – 2024-02-01 01:30"
+        );
+    }
+}
@@ -0,0 +1,211 @@
+// Most of this is only used by the icon, which is not built everywhere.
+#![cfg_attr(not(all(feature = "tray", target_os = "linux")), allow(dead_code))]
+
+use anyhow::Result;
+use eframe::egui;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+};
+
+use crate::i18n::tr;
+use crate::scanner;
+
+/// What was chosen in the menu of the tray icon.
+#[derive(Debug, PartialEq)]
+pub enum TrayEvent {
+    // Sort the watched folder.
+    OpenFolder(PathBuf),
+    Show,
+    Quit,
+}
+
+/// Whether this build can show a tray icon. It needs the "tray" feature and Linux, where the
+/// icon is a StatusNotifierItem over D-Bus.
+pub fn supported() -> bool {
+    cfg!(all(feature = "tray", target_os = "linux"))
+}
+
+/// The number of images directly in `folder`, which are the ones waiting to be sorted.
+pub fn count_unsorted(folder: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| scanner::is_image_file(&entry.path()))
+        .count()
+}
+
+/// The text next to the icon, e.g. "Screenshots: 12 to sort".
+pub fn summary(folder: &Path, count: usize) -> String {
+    let name = folder
+        .file_name()
+        .unwrap_or(folder.as_os_str())
+        .to_string_lossy()
+        .to_string();
+    tr!("tray-summary", folder = name, count = count)
+}
+
+/// A tray icon with the number of unsorted images in a watched folder. It is removed when
+/// dropped.
+pub struct TrayIcon {
+    events: mpsc::Receiver<TrayEvent>,
+    stop: Arc<AtomicBool>,
+    #[cfg(all(feature = "tray", target_os = "linux"))]
+    handle: Arc<ksni::blocking::Handle<linux::Tray>>,
+}
+
+impl TrayIcon {
+    /// Shows the icon for `folder` and keeps its count up to date on a background thread.
+    #[cfg(all(feature = "tray", target_os = "linux"))]
+    pub fn start(folder: &Path, ctx: egui::Context) -> Result<Self> {
+        use ksni::blocking::TrayMethods;
+
+        let (sender, events) = mpsc::channel();
+        let handle = linux::Tray {
+            folder: folder.to_path_buf(),
+            count: count_unsorted(folder),
+            sender,
+            ctx,
+        }
+        .spawn()?;
+        let handle = Arc::new(handle);
+        let stop = Arc::new(AtomicBool::new(false));
+        let updater = handle.clone();
+        let stopped = stop.clone();
+        let folder = folder.to_path_buf();
+        std::thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) && !updater.is_closed() {
+                std::thread::sleep(linux::REFRESH_INTERVAL);
+                let count = count_unsorted(&folder);
+                updater.update(|tray| tray.count = count);
+            }
+        });
+        Ok(Self {
+            events,
+            stop,
+            handle,
+        })
+    }
+
+    #[cfg(not(all(feature = "tray", target_os = "linux")))]
+    pub fn start(_folder: &Path, _ctx: egui::Context) -> Result<Self> {
+        anyhow::bail!("This build has no tray icon support")
+    }
+
+    /// What was chosen in the menu since the last call.
+    pub fn poll(&self) -> Vec<TrayEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        #[cfg(all(feature = "tray", target_os = "linux"))]
+        self.handle.shutdown();
+    }
+}
+
+#[cfg(all(feature = "tray", target_os = "linux"))]
+mod linux {
+    use super::*;
+
+    // How often the watched folder is counted again, e.g. after new screenshots were taken.
+    pub const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+    pub struct Tray {
+        pub folder: PathBuf,
+        pub count: usize,
+        pub sender: mpsc::Sender<TrayEvent>,
+        pub ctx: egui::Context,
+    }
+
+    impl Tray {
+        fn send(&self, event: TrayEvent) {
+            if self.sender.send(event).is_ok() {
+                self.ctx.request_repaint();
+            }
+        }
+    }
+
+    impl ksni::Tray for Tray {
+        fn id(&self) -> String {
+            env!("CARGO_PKG_NAME").into()
+        }
+
+        fn icon_name(&self) -> String {
+            "image-x-generic".into()
+        }
+
+        fn title(&self) -> String {
+            summary(&self.folder, self.count)
+        }
+
+        fn tool_tip(&self) -> ksni::ToolTip {
+            ksni::ToolTip {
+                title: summary(&self.folder, self.count),
+                description: self.folder.display().to_string(),
+                ..Default::default()
+            }
+        }
+
+        fn activate(&mut self, _x: i32, _y: i32) {
+            self.send(TrayEvent::OpenFolder(self.folder.clone()));
+        }
+
+        fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+            use ksni::menu::StandardItem;
+            vec![
+                StandardItem {
+                    label: tr!("tray-sort", count = self.count),
+                    activate: Box::new(|tray: &mut Self| {
+                        tray.send(TrayEvent::OpenFolder(tray.folder.clone()))
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+                StandardItem {
+                    label: tr!("tray-show"),
+                    activate: Box::new(|tray: &mut Self| tray.send(TrayEvent::Show)),
+                    ..Default::default()
+                }
+                .into(),
+                ksni::MenuItem::Separator,
+                StandardItem {
+                    label: tr!("tray-quit"),
+                    activate: Box::new(|tray: &mut Self| tray.send(TrayEvent::Quit)),
+                    ..Default::default()
+                }
+                .into(),
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_unsorted_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let folder = temp_dir.path().join("Screenshots");
+        fs::create_dir(&folder).unwrap();
+        assert_eq!(count_unsorted(&folder), 0);
+        fs::write(folder.join("a.png"), b"").unwrap();
+        fs::write(folder.join("b.jpg"), b"").unwrap();
+        fs::write(folder.join("notes.txt"), b"").unwrap();
+        // Already sorted into a subfolder.
+        fs::create_dir(folder.join("cats")).unwrap();
+        fs::write(folder.join("cats/c.png"), b"").unwrap();
+        assert_eq!(count_unsorted(&folder), 2);
+        assert_eq!(summary(&folder, 2), "Screenshots: 2 to sort");
+        assert_eq!(count_unsorted(&temp_dir.path().join("missing")), 0);
+    }
+}
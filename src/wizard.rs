@@ -0,0 +1,286 @@
+use eframe::egui;
+use rfd::FileDialog;
+use std::path::{Path, PathBuf};
+
+use crate::{demo, i18n::tr, palette};
+
+// Keys that go through the images, so no destination gets them.
+const RESERVED_LETTERS: [char; 2] = ['J', 'K'];
+
+/// The key suggested for `folder`: the first letter of its name, or else the first free one.
+pub fn suggest_letter(folder: &Path, taken: &[char]) -> Option<char> {
+    let free = |letter: &char| {
+        !RESERVED_LETTERS.contains(letter) && !taken.iter().any(|t| t.eq_ignore_ascii_case(letter))
+    };
+    let name = folder.file_name()?.to_string_lossy().to_uppercase();
+    name.chars()
+        .find(|c| c.is_ascii_alphanumeric())
+        .filter(free)
+        .or_else(|| ('A'..='Z').chain('0'..='9').find(free))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Step {
+    #[default]
+    Welcome,
+    Source,
+    Destinations,
+    Keys,
+}
+
+/// What the wizard was closed with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WizardAction {
+    Finish {
+        source: PathBuf,
+        destinations: Vec<(PathBuf, char, Option<[u8; 3]>)>,
+    },
+    // Try the keys on sample images first.
+    Practice,
+    Skip,
+}
+
+/// The steps shown on the first start: choosing the folder to sort, a few destinations, and
+/// the keys that sort.
+#[derive(Default)]
+pub struct Wizard {
+    step: Step,
+    source: Option<PathBuf>,
+    // The destinations with the key typed for each, and the color of the demo ones.
+    destinations: Vec<(PathBuf, String, Option<[u8; 3]>)>,
+    error: Option<String>,
+}
+
+impl Wizard {
+    fn taken_letters(&self) -> Vec<char> {
+        self.destinations
+            .iter()
+            .filter_map(|(_, letter, _)| letter.chars().next())
+            .collect()
+    }
+
+    fn add_destination(&mut self, folder: PathBuf, color: Option<[u8; 3]>) {
+        if self
+            .destinations
+            .iter()
+            .any(|(added, _, _)| *added == folder)
+        {
+            return;
+        }
+        let letter = suggest_letter(&folder, &self.taken_letters())
+            .map(String::from)
+            .unwrap_or_default();
+        self.destinations.push((folder, letter, color));
+    }
+
+    // Creates the demo folder and uses it, replacing what was chosen.
+    fn use_demo(&mut self) {
+        match demo::create(&std::env::temp_dir().join("image-organizer-demo")) {
+            Ok(demo) => {
+                self.source = Some(demo.inbox);
+                self.destinations.clear();
+                for (folder, letter, color) in demo.destinations {
+                    self.destinations
+                        .push((folder, letter.to_string(), Some(color)));
+                }
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    fn finish(&self) -> Option<WizardAction> {
+        Some(WizardAction::Finish {
+            source: self.source.clone()?,
+            destinations: self
+                .destinations
+                .iter()
+                .filter_map(|(folder, letter, color)| {
+                    let letter = letter.trim().chars().next()?.to_ascii_uppercase();
+                    Some((folder.clone(), letter, *color))
+                })
+                .collect(),
+        })
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<WizardAction> {
+        let mut action = None;
+        egui::Window::new(tr!("wizard"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.set_width(420.0);
+                match self.step {
+                    Step::Welcome => {
+                        ui.heading(tr!("wizard-welcome"));
+                        ui.label(tr!("wizard-welcome-text"));
+                        if ui.button(tr!("practice-menu")).clicked() {
+                            action = Some(WizardAction::Practice);
+                        }
+                    }
+                    Step::Source => self.show_source(ui),
+                    Step::Destinations => self.show_destinations(ui),
+                    Step::Keys => self.show_keys(ui),
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button(tr!("wizard-skip")).clicked() {
+                        action = Some(WizardAction::Skip);
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let (next, enabled) = match self.step {
+                            Step::Welcome => (Some(Step::Source), true),
+                            Step::Source => (Some(Step::Destinations), self.source.is_some()),
+                            Step::Destinations => (Some(Step::Keys), true),
+                            Step::Keys => (None, true),
+                        };
+                        let label = match next {
+                            Some(_) => tr!("wizard-next"),
+                            None => tr!("wizard-finish"),
+                        };
+                        if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+                            match next {
+                                Some(step) => self.step = step,
+                                None => action = self.finish(),
+                            }
+                        }
+                        let previous = match self.step {
+                            Step::Welcome => None,
+                            Step::Source => Some(Step::Welcome),
+                            Step::Destinations => Some(Step::Source),
+                            Step::Keys => Some(Step::Destinations),
+                        };
+                        if let Some(previous) = previous {
+                            if ui.button(tr!("wizard-back")).clicked() {
+                                self.step = previous;
+                            }
+                        }
+                    });
+                });
+            });
+        action
+    }
+
+    fn show_source(&mut self, ui: &mut egui::Ui) {
+        ui.heading(tr!("wizard-source"));
+        ui.label(tr!("wizard-source-text"));
+        ui.horizontal(|ui| {
+            if ui.button(tr!("choose-image-folder")).clicked() {
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    self.source = Some(folder);
+                }
+            }
+            if ui
+                .button(tr!("wizard-demo"))
+                .on_hover_text(tr!("wizard-demo-hint"))
+                .clicked()
+            {
+                self.use_demo();
+            }
+        });
+        match &self.source {
+            Some(folder) => ui.strong(folder.to_string_lossy()),
+            None => ui.weak(tr!("no-folder-selected")),
+        };
+    }
+
+    fn show_destinations(&mut self, ui: &mut egui::Ui) {
+        ui.heading(tr!("wizard-destinations"));
+        ui.label(tr!("wizard-destinations-text"));
+        let mut removed = None;
+        egui::Grid::new("wizard_destinations").show(ui, |ui| {
+            for (index, (folder, letter, color)) in self.destinations.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(letter).desired_width(24.0));
+                ui.horizontal(|ui| {
+                    if let Some(color) = color {
+                        palette::swatch(ui, *color);
+                    }
+                    ui.label(folder.to_string_lossy());
+                });
+                if ui.small_button("x").clicked() {
+                    removed = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = removed {
+            self.destinations.remove(index);
+        }
+        if ui.button(tr!("choose-destination-folder")).clicked() {
+            if let Some(folder) = FileDialog::new().pick_folder() {
+                self.add_destination(folder, None);
+            }
+        }
+    }
+
+    fn show_keys(&mut self, ui: &mut egui::Ui) {
+        ui.heading(tr!("wizard-keys"));
+        egui::Grid::new("wizard_keys").show(ui, |ui| {
+            for (key, text) in [
+                ("J / K", tr!("shortcut-navigate")),
+                ("Space", tr!("shortcut-skip")),
+                ("Ctrl + Z", tr!("shortcut-undo")),
+                ("?", tr!("shortcut-help")),
+            ] {
+                ui.monospace(key);
+                ui.label(text);
+                ui.end_row();
+            }
+            for (folder, letter, _) in &self.destinations {
+                let name = folder
+                    .file_name()
+                    .unwrap_or(folder.as_os_str())
+                    .to_string_lossy();
+                ui.monospace(letter.to_uppercase());
+                ui.label(tr!("shortcut-move", folder = name.to_string()));
+                ui.end_row();
+            }
+        });
+        ui.label(tr!("wizard-keys-text"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggest_letter_test() {
+        assert_eq!(suggest_letter(Path::new("/photos/cats"), &[]), Some('C'));
+        assert_eq!(suggest_letter(Path::new("/photos/cats"), &['c']), Some('A'));
+        // J and K go through the images.
+        assert_eq!(
+            suggest_letter(Path::new("/photos/kyoto"), &['A']),
+            Some('B')
+        );
+        assert_eq!(suggest_letter(Path::new("/photos/2024"), &[]), Some('2'));
+        assert_eq!(suggest_letter(Path::new("/photos/京都"), &[]), Some('A'));
+    }
+
+    #[test]
+    fn finish_test() {
+        let mut wizard = Wizard::default();
+        assert_eq!(wizard.finish(), None);
+        wizard.source = Some(PathBuf::from("/photos/inbox"));
+        wizard.add_destination(PathBuf::from("/photos/cats"), None);
+        wizard.add_destination(PathBuf::from("/photos/cars"), Some([1, 2, 3]));
+        wizard.add_destination(PathBuf::from("/photos/cats"), None);
+        wizard
+            .destinations
+            .push((PathBuf::from("/photos/none"), " ".to_string(), None));
+        assert_eq!(
+            wizard.finish(),
+            Some(WizardAction::Finish {
+                source: PathBuf::from("/photos/inbox"),
+                destinations: vec![
+                    (PathBuf::from("/photos/cats"), 'C', None),
+                    (PathBuf::from("/photos/cars"), 'A', Some([1, 2, 3])),
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::sidecar;
+
+pub const XMP_NAMESPACE: (&str, &str) = ("xmp", "http://ns.adobe.com/xap/1.0/");
+pub const DC_NAMESPACE: (&str, &str) = ("dc", "http://purl.org/dc/elements/1.1/");
+
+// A new sidecar. Properties are added as attributes of rdf:Description, which is what Lightroom
+// writes too.
+const EMPTY_PACKET: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""/>
+ </rdf:RDF>
+</x:xmpmeta>
+"#;
+
+const DESCRIPTION_TAG: &str = "<rdf:Description";
+
+/// The XMP sidecar of the image at `path`: the existing one, or "IMG_1234.xmp" like Lightroom
+/// names them.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    sidecar::sidecar_files(path)
+        .into_iter()
+        .find(|sidecar| {
+            sidecar
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("xmp"))
+        })
+        .unwrap_or_else(|| path.with_extension("xmp"))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Byte range of the value of the attribute `name`, e.g. xmp:Label="Red".
+fn find_attribute(xmp: &str, name: &str) -> Option<(usize, usize)> {
+    let pattern = format!("{}=\"", name);
+    let mut from = 0;
+    while let Some(found) = xmp[from..].find(&pattern) {
+        let start = from + found;
+        // Not a longer name that ends with this one.
+        let preceded_by_space = xmp[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_whitespace);
+        let value_start = start + pattern.len();
+        if preceded_by_space {
+            let value_end = value_start + xmp[value_start..].find('"')?;
+            return Some((value_start, value_end));
+        }
+        from = value_start;
+    }
+    None
+}
+
+// Byte range of the contents of the element `name`, e.g. <xmp:Label>Red</xmp:Label>.
+fn find_element(xmp: &str, name: &str) -> Option<(usize, usize)> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = xmp.find(&open)? + open.len();
+    let end = start + xmp[start..].find(&close)?;
+    Some((start, end))
+}
+
+fn no_description() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "The XMP has no rdf:Description.",
+    )
+}
+
+// Where the rdf:Description start tag is: the end of its name and the position of its '>'.
+fn description_tag(xmp: &str) -> io::Result<(usize, usize)> {
+    let name_end = xmp
+        .find(DESCRIPTION_TAG)
+        .map(|position| position + DESCRIPTION_TAG.len())
+        .ok_or_else(no_description)?;
+    let tag_end = name_end + xmp[name_end..].find('>').ok_or_else(no_description)?;
+    Ok((name_end, tag_end))
+}
+
+// Declares the (prefix, URI) `namespace` on rdf:Description unless it already is, there or on
+// an enclosing element.
+fn declare_namespace(xmp: &str, namespace: (&str, &str)) -> io::Result<String> {
+    let (name_end, tag_end) = description_tag(xmp)?;
+    let (prefix, uri) = namespace;
+    if xmp[..tag_end].contains(&format!("xmlns:{}=", prefix)) {
+        return Ok(xmp.to_string());
+    }
+    Ok(format!(
+        "{}\n    xmlns:{}=\"{}\"{}",
+        &xmp[..name_end],
+        prefix,
+        uri,
+        &xmp[name_end..]
+    ))
+}
+
+/// The value of a simple property, written either as an attribute or as an element.
+pub fn get_property(xmp: &str, name: &str) -> Option<String> {
+    let (start, end) = find_attribute(xmp, name).or_else(|| find_element(xmp, name))?;
+    Some(unescape(&xmp[start..end]))
+}
+
+/// Sets or, with None, removes a simple property. `namespace` is the (prefix, URI) of `name`,
+/// declared if it is not yet.
+pub fn set_property(
+    xmp: &str,
+    namespace: (&str, &str),
+    name: &str,
+    value: Option<&str>,
+) -> io::Result<String> {
+    let xmp = if xmp.trim().is_empty() {
+        EMPTY_PACKET
+    } else {
+        xmp
+    };
+    if let Some((start, end)) = find_attribute(xmp, name) {
+        return Ok(match value {
+            Some(value) => format!("{}{}{}", &xmp[..start], escape(value), &xmp[end..]),
+            None => {
+                // Also drop the space before the attribute and the closing quote.
+                let attribute_start = xmp[..start - name.len() - 2].trim_end().len();
+                format!("{}{}", &xmp[..attribute_start], &xmp[end + 1..])
+            }
+        });
+    }
+    if let Some((start, end)) = find_element(xmp, name) {
+        return Ok(match value {
+            Some(value) => format!("{}{}{}", &xmp[..start], escape(value), &xmp[end..]),
+            None => {
+                let element_start = start - name.len() - 2;
+                let element_end = end + name.len() + 3;
+                format!("{}{}", &xmp[..element_start], &xmp[element_end..])
+            }
+        });
+    }
+    let Some(value) = value else {
+        return Ok(xmp.to_string());
+    };
+    let xmp = declare_namespace(xmp, namespace)?;
+    let (_, tag_end) = description_tag(&xmp)?;
+    // Before the "/>" of an empty element.
+    let insert_at = if xmp[..tag_end].ends_with('/') {
+        tag_end - 1
+    } else {
+        tag_end
+    };
+    Ok(format!(
+        "{}\n    {}=\"{}\"{}",
+        &xmp[..insert_at],
+        name,
+        escape(value),
+        &xmp[insert_at..]
+    ))
+}
+
+/// The items of an unordered array property like dc:subject, the keywords.
+pub fn get_bag(xmp: &str, name: &str) -> Vec<String> {
+    let Some((start, end)) = find_element(xmp, name) else {
+        return Vec::new();
+    };
+    let mut items = Vec::new();
+    let mut rest = &xmp[start..end];
+    while let Some(open) = rest.find("<rdf:li") {
+        rest = &rest[open..];
+        let (Some(tag_end), Some(close)) = (rest.find('>'), rest.find("</rdf:li>")) else {
+            break;
+        };
+        if tag_end < close {
+            items.push(unescape(rest[tag_end + 1..close].trim()));
+        }
+        rest = &rest[close + "</rdf:li>".len()..];
+    }
+    items
+}
+
+/// Replaces the items of an unordered array property. An empty `items` removes it.
+pub fn set_bag(
+    xmp: &str,
+    namespace: (&str, &str),
+    name: &str,
+    items: &[String],
+) -> io::Result<String> {
+    let xmp = if xmp.trim().is_empty() {
+        EMPTY_PACKET
+    } else {
+        xmp
+    };
+    let element = if items.is_empty() {
+        String::new()
+    } else {
+        let list: String = items
+            .iter()
+            .map(|item| format!("     <rdf:li>{}</rdf:li>\n", escape(item)))
+            .collect();
+        format!(
+            "<{name}>\n    <rdf:Bag>\n{list}    </rdf:Bag>\n   </{name}>",
+            name = name,
+            list = list
+        )
+    };
+    if let Some((start, end)) = find_element(xmp, name) {
+        let mut element_start = start - name.len() - 2;
+        if items.is_empty() {
+            element_start = xmp[..element_start].trim_end().len();
+        }
+        let element_end = end + name.len() + 3;
+        return Ok(format!(
+            "{}{}{}",
+            &xmp[..element_start],
+            element,
+            &xmp[element_end..]
+        ));
+    }
+    if items.is_empty() {
+        return Ok(xmp.to_string());
+    }
+    let xmp = declare_namespace(xmp, namespace)?;
+    let (_, tag_end) = description_tag(&xmp)?;
+    if xmp[..tag_end].ends_with('/') {
+        // The element was empty. It needs a closing tag now.
+        Ok(format!(
+            "{}>\n   {}\n  </rdf:Description>{}",
+            &xmp[..tag_end - 1],
+            element,
+            &xmp[tag_end + 1..]
+        ))
+    } else {
+        Ok(format!(
+            "{}\n   {}{}",
+            &xmp[..=tag_end],
+            element,
+            &xmp[tag_end + 1..]
+        ))
+    }
+}
+
+/// The XMP sidecar of `image`, if there is one.
+pub fn read(image: &Path) -> Option<String> {
+    fs::read_to_string(sidecar_path(image)).ok()
+}
+
+/// Writes a property to the XMP sidecar of `image`, creating the sidecar if needed. Everything
+/// else in the sidecar is kept as it is.
+pub fn write_property(
+    image: &Path,
+    namespace: (&str, &str),
+    name: &str,
+    value: Option<&str>,
+) -> io::Result<()> {
+    update(image, value.is_some(), |xmp| {
+        set_property(xmp, namespace, name, value)
+    })
+}
+
+/// Writes an unordered array property to the XMP sidecar of `image` like `write_property`.
+pub fn write_bag(
+    image: &Path,
+    namespace: (&str, &str),
+    name: &str,
+    items: &[String],
+) -> io::Result<()> {
+    update(image, !items.is_empty(), |xmp| {
+        set_bag(xmp, namespace, name, items)
+    })
+}
+
+// Rewrites the sidecar of `image` with `edit`. A missing sidecar is only created if `create`.
+fn update(
+    image: &Path,
+    create: bool,
+    edit: impl FnOnce(&str) -> io::Result<String>,
+) -> io::Result<()> {
+    let path = sidecar_path(image);
+    let xmp = match fs::read_to_string(&path) {
+        Ok(xmp) => xmp,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if !create {
+                return Ok(());
+            }
+            String::new()
+        }
+        Err(e) => return Err(e),
+    };
+    fs::write(&path, edit(&xmp)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_property_test() {
+        let xmp = set_property("", XMP_NAMESPACE, "xmp:Label", Some("Red")).unwrap();
+        assert_eq!(get_property(&xmp, "xmp:Label").as_deref(), Some("Red"));
+        assert!(xmp.contains("xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\""));
+
+        let xmp = set_property(&xmp, XMP_NAMESPACE, "xmp:Label", Some("A & B")).unwrap();
+        assert_eq!(get_property(&xmp, "xmp:Label").as_deref(), Some("A & B"));
+        assert_eq!(xmp.matches("xmlns:xmp=").count(), 1);
+
+        let xmp = set_property(&xmp, XMP_NAMESPACE, "xmp:Label", None).unwrap();
+        assert_eq!(get_property(&xmp, "xmp:Label"), None);
+        assert_eq!(
+            xmp.replace("\n    xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"", ""),
+            EMPTY_PACKET
+        );
+    }
+
+    #[test]
+    fn element_property_test() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF><rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/" darktable:xmp_version="5">
+   <xmp:Label>Green</xmp:Label>
+  </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+        assert_eq!(get_property(xmp, "xmp:Label").as_deref(), Some("Green"));
+        assert_eq!(get_property(xmp, "version"), None);
+
+        let updated = set_property(xmp, XMP_NAMESPACE, "xmp:Label", Some("Blue")).unwrap();
+        assert_eq!(updated, xmp.replace("Green", "Blue"));
+        let removed = set_property(xmp, XMP_NAMESPACE, "xmp:Label", None).unwrap();
+        assert!(!removed.contains("Label"));
+        assert!(removed.contains("darktable:xmp_version=\"5\""));
+    }
+
+    #[test]
+    fn bag_test() {
+        let tags = vec!["cat".to_string(), "Tom & Jerry".to_string()];
+        let xmp = set_property("", XMP_NAMESPACE, "xmp:Rating", Some("3")).unwrap();
+        let xmp = set_bag(&xmp, DC_NAMESPACE, "dc:subject", &tags).unwrap();
+        assert_eq!(get_bag(&xmp, "dc:subject"), tags);
+        assert_eq!(get_property(&xmp, "xmp:Rating").as_deref(), Some("3"));
+        assert!(xmp.contains("</rdf:Description>"));
+
+        let xmp = set_bag(&xmp, DC_NAMESPACE, "dc:subject", &tags[..1]).unwrap();
+        assert_eq!(get_bag(&xmp, "dc:subject"), vec!["cat"]);
+        let xmp = set_bag(&xmp, DC_NAMESPACE, "dc:subject", &[]).unwrap();
+        assert!(get_bag(&xmp, "dc:subject").is_empty());
+        assert!(!xmp.contains("rdf:Bag"));
+
+        // As digiKam writes it.
+        let digikam = r#"<rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/">
+   <dc:subject>
+    <rdf:Bag>
+     <rdf:li>beach</rdf:li>
+     <rdf:li>sunset</rdf:li>
+    </rdf:Bag>
+   </dc:subject>
+  </rdf:Description>"#;
+        assert_eq!(get_bag(digikam, "dc:subject"), vec!["beach", "sunset"]);
+    }
+
+    #[test]
+    fn write_property_test() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let image = temp_dir.path().join("IMG_1.JPG");
+        fs::write(&image, b"").unwrap();
+        write_property(&image, XMP_NAMESPACE, "xmp:Label", None).unwrap();
+        assert!(!temp_dir.path().join("IMG_1.xmp").exists());
+
+        write_property(&image, XMP_NAMESPACE, "xmp:Label", Some("Red")).unwrap();
+        assert!(temp_dir.path().join("IMG_1.xmp").exists());
+        assert_eq!(
+            get_property(&read(&image).unwrap(), "xmp:Label").as_deref(),
+            Some("Red")
+        );
+    }
+}